@@ -0,0 +1,32 @@
+//! Android entry point, called by winit's native activity glue.
+//!
+//! Build with `cargo rustc --lib --crate-type cdylib --target aarch64-linux-android` and
+//! package the library as `libgame.so` in an APK using `NativeActivity`. Logs go to stderr,
+//! which Android discards, so run under a debugger to see them.
+
+use log::error;
+use winit::event_loop::EventLoop;
+use winit::platform::android::activity::AndroidApp;
+use winit::platform::android::EventLoopBuilderExtAndroid;
+
+use crate::game::world::app::{App, WORLD_SAVE_DIR};
+
+#[no_mangle]
+fn android_main(android_app: AndroidApp) {
+    // The working directory is not writable, so saves live in app-private storage
+    let world_dir = match android_app.internal_data_path() {
+        Some(data) => data.join(WORLD_SAVE_DIR),
+        None => WORLD_SAVE_DIR.into(),
+    };
+    let event_loop = match EventLoop::builder().with_android_app(android_app).build() {
+        Ok(event_loop) => event_loop,
+        Err(e) => {
+            error!("Failed to create event loop: {:?}", e);
+            return;
+        }
+    };
+    let mut app = App::with_world_dir(world_dir);
+    if let Err(e) = event_loop.run_app(&mut app) {
+        error!("Application error: {:?}", e);
+    }
+}
@@ -0,0 +1,152 @@
+//! Headless server entry point.
+//!
+//! Usage: server [--world <dir>] [--port <port>] [--rcon <addr>] [--restore <backup>]
+//!               [--metrics <file>] [--metrics-interval <seconds>]
+//!               [--world-type <type>] [--seed <number>] [--pregenerate <radius>]
+//! `--restore` replaces the world with a backup made by /backup before starting.
+//! `--pregenerate` generates and saves the chunks within `radius` chunks of spawn, on every
+//! core, then exits instead of starting the server.
+//! `--world-type` and `--seed` only apply when the world is created; the type is `default`,
+//! `superflat`, `superflat:<layers>` such as `superflat:stone*3,dirt*2,grass`, or `debug`.
+//! `--metrics` dumps world health figures to a file, as JSON or, for a `.prom` file, in
+//! the Prometheus text format.
+//! RCON is only enabled when PSU_RCON_PASSWORD is set.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+use log::{error, info, warn};
+
+use game::game::command::{CommandSender, PermissionLevel};
+use game::game::save::{BackupManager, WorldSave};
+use game::engine::net::DEFAULT_GAME_PORT;
+use game::game::server::pregenerate::{self, PregenProgress};
+use game::game::server::server::DEFAULT_SPAWN;
+use game::game::server::world_metrics::DEFAULT_DUMP_INTERVAL;
+use game::game::server::{MetricsDump, RconServer, Server, ServerNetwork, StdinConsole, TickClock};
+use game::game::world::chunk_manager::ChunkManager;
+use game::game::world::{WorldGen, WorldType};
+
+const DEFAULT_WORLD_DIR: &str = "saves/world";
+const DEFAULT_RCON_ADDR: &str = "127.0.0.1:47802";
+const SERVER_NAME: &str = "Dedicated server";
+/// Upper bound on how long console and RCON input can wait between ticks
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let mut world_dir = DEFAULT_WORLD_DIR.to_string();
+    let mut rcon_addr = DEFAULT_RCON_ADDR.to_string();
+    let mut port = DEFAULT_GAME_PORT;
+    let mut restore = None;
+    let mut metrics_path = None;
+    let mut metrics_interval = DEFAULT_DUMP_INTERVAL;
+    let mut generator = WorldGen::default();
+    let mut pregenerate_radius = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--world" => world_dir = args.next().ok_or("--world needs a directory")?,
+            "--port" => port = args.next().ok_or("--port needs a number")?.parse()?,
+            "--rcon" => rcon_addr = args.next().ok_or("--rcon needs an address")?,
+            "--restore" => restore = Some(args.next().ok_or("--restore needs a backup name")?),
+            "--metrics" => metrics_path = Some(args.next().ok_or("--metrics needs a file")?),
+            "--metrics-interval" => metrics_interval = args.next().ok_or("--metrics-interval needs a number")?.parse()?,
+            "--world-type" => generator.world_type = WorldType::parse(&args.next().ok_or("--world-type needs a type")?)?,
+            "--seed" => generator.seed = args.next().ok_or("--seed needs a number")?.parse()?,
+            "--pregenerate" => pregenerate_radius = Some(args.next().ok_or("--pregenerate needs a radius")?.parse()?),
+            other => warn!("Ignoring unknown argument '{}'", other),
+        }
+    }
+
+    if let Some(name) = restore {
+        BackupManager::new(&world_dir).restore(&name)?;
+    }
+
+    let mut server = Server::create(WorldSave::open(world_dir), generator);
+    if let Some(radius) = pregenerate_radius {
+        return pregenerate_spawn(&mut server, radius);
+    }
+    let mut network = ServerNetwork::new();
+    network.open_to_lan(SERVER_NAME, port)?;
+    let console = StdinConsole::spawn();
+    let rcon = match std::env::var("PSU_RCON_PASSWORD") {
+        Ok(password) if !password.is_empty() => {
+            let addr: SocketAddr = rcon_addr.parse()?;
+            Some(RconServer::bind(addr, password)?)
+        }
+        _ => None,
+    };
+    let mut metrics = metrics_path.map(|path| MetricsDump::new(path, Duration::from_secs(metrics_interval.max(1))));
+    info!("Server started, type /help for commands");
+
+    let console_sender = CommandSender::console();
+    let rcon_sender = CommandSender::new("Rcon", PermissionLevel::Admin);
+    let mut clock = TickClock::default();
+    while server.is_running() {
+        for line in console.poll() {
+            match server.execute(&console_sender, &line) {
+                Ok(output) => println!("{}", output),
+                Err(e) => println!("{}", e),
+            }
+        }
+        if let Some(rcon) = &rcon {
+            for request in rcon.poll() {
+                info!("RCON {}: {}", request.peer, request.command);
+                let output = match server.execute(&rcon_sender, &request.command) {
+                    Ok(output) => output,
+                    Err(e) => e.to_string(),
+                };
+                request.respond(&output);
+            }
+        }
+        if let Some(port) = server.take_publish_request() {
+            warn!("Dedicated server is already listening, ignoring publish on port {}", port);
+        }
+
+        network.poll(&mut server);
+        let (due, skipped) = clock.due();
+        if skipped > 0 {
+            warn!("Can't keep up, skipping {} ticks", skipped);
+            server.metrics.record_skipped(skipped);
+        }
+        for _ in 0..due {
+            server.tick();
+        }
+        if let Some(metrics) = &mut metrics {
+            metrics.poll(&server);
+        }
+        network.flush(&mut server);
+
+        std::thread::sleep(clock.until_next_tick().min(MAX_POLL_INTERVAL));
+    }
+
+    if let Err(e) = server.save_all() {
+        error!("Final save failed: {}", e);
+    }
+    network.flush(&mut server);
+    network.close_all();
+    Ok(())
+}
+
+/// Generates and saves the area around spawn, logging progress every tenth of the way
+fn pregenerate_spawn(server: &mut Server, radius: i32) -> Result<(), Box<dyn std::error::Error>> {
+    let spawn = ChunkManager::chunk_key_at(DEFAULT_SPAWN);
+    let chunks = pregenerate::area_chunks((spawn.0, spawn.2), radius);
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    info!("Pregenerating {} chunks within {} chunks of spawn on {} threads", chunks.len(), radius, threads);
+    let mut logged = 0;
+    let generator = server.chunks.generator().clone();
+    let report = pregenerate::pregenerate(&mut server.world_save, &generator, &chunks, threads, |progress: PregenProgress| {
+        let percent = progress.percent();
+        if percent >= logged + 10 {
+            logged = percent - percent % 10;
+            info!("Pregenerated {}/{} chunks ({}%)", progress.done, progress.total, percent);
+        }
+    })?;
+    info!(
+        "Pregenerated {} chunks, saving {}, in {:.1}s ({:.1}s generating)",
+        report.generated, report.saved, report.elapsed.as_secs_f32(), report.generate_time.as_secs_f32(),
+    );
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! Default assets compiled into the binary.
+//!
+//! These form the lowest resource pack, so the game looks right even when run without an
+//! assets directory, and any pack or the assets folder can still override them.
+
+pub const WORLD_SHADER_PATH: &str = "shaders/shader.wgsl";
+pub const WORLD_SHADER: &str = crate::engine::shaders::WORLD_SHADER;
+
+pub const EMBEDDED_ASSETS: &[(&str, &[u8])] = &[
+    ("assets/grass_block_top.png", include_bytes!("../../../assets/grass_block_top.png")),
+    ("assets/grass_block_side.png", include_bytes!("../../../assets/grass_block_side.png")),
+    ("assets/dirt.png", include_bytes!("../../../assets/dirt.png")),
+    ("assets/dirt_n.png", include_bytes!("../../../assets/dirt_n.png")),
+    ("assets/stone.png", include_bytes!("../../../assets/stone.png")),
+    ("assets/stone_n.png", include_bytes!("../../../assets/stone_n.png")),
+    ("assets/ladder.png", include_bytes!("../../../assets/ladder.png")),
+    ("assets/door_lower.png", include_bytes!("../../../assets/door_lower.png")),
+    ("assets/door_upper.png", include_bytes!("../../../assets/door_upper.png")),
+    ("assets/wheat_young.png", include_bytes!("../../../assets/wheat_young.png")),
+    ("assets/wheat_ripe.png", include_bytes!("../../../assets/wheat_ripe.png")),
+    ("assets/water.png", include_bytes!("../../../assets/water.png")),
+    ("assets/lava.png", include_bytes!("../../../assets/lava.png")),
+    ("assets/fire.png", include_bytes!("../../../assets/fire.png")),
+    ("assets/tnt.png", include_bytes!("../../../assets/tnt.png")),
+    ("assets/sign.png", include_bytes!("../../../assets/sign.png")),
+    ("assets/bed.png", include_bytes!("../../../assets/bed.png")),
+    ("assets/recipes.txt", include_bytes!("../../../assets/recipes.txt")),
+    ("assets/music.txt", include_bytes!("../../../assets/music.txt")),
+    (WORLD_SHADER_PATH, WORLD_SHADER.as_bytes()),
+];
+
+pub fn embedded(path: &str) -> Option<&'static [u8]> {
+    EMBEDDED_ASSETS.iter().find(|(p, _)| *p == path).map(|(_, data)| *data)
+}
@@ -0,0 +1,56 @@
+//! Asset loading that works both natively and in the browser.
+//!
+//! Native builds read from the working directory. The web build has no filesystem, so the
+//! same relative paths are fetched from the page's origin instead. Native builds can also
+//! layer resource packs over the base assets, see `pack`.
+
+pub mod embedded;
+pub mod pack;
+pub mod zip;
+
+pub use pack::{ResourcePack, ResourcePacks};
+pub use zip::ZipArchive;
+
+use std::error::Error;
+
+/// Reads an asset as raw bytes
+#[cfg(not(all(target_arch = "wasm32", feature = "web")))]
+pub async fn load_bytes(path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    Ok(std::fs::read(path)?)
+}
+
+/// Fetches an asset relative to the page URL
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+pub async fn load_bytes(path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+
+    let js_error = |e: wasm_bindgen::JsValue| -> Box<dyn Error> { format!("{:?}", e).into() };
+    let window = web_sys::window().ok_or("no browser window")?;
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_str(path)).await
+        .map_err(js_error)?
+        .dyn_into()
+        .map_err(js_error)?;
+    if !response.ok() {
+        return Err(format!("fetching {} failed with HTTP {}", path, response.status()).into());
+    }
+    let buffer = JsFuture::from(response.array_buffer().map_err(js_error)?).await.map_err(js_error)?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+/// Loads every path in order, using the built-in copy of any that cannot be loaded
+pub async fn load_all(paths: &[&str]) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    let mut loaded = Vec::with_capacity(paths.len());
+    for path in paths {
+        let data = match (load_bytes(path).await, embedded::embedded(path)) {
+            (Ok(data), _) => data,
+            (Err(e), Some(data)) => {
+                log::warn!("Using built-in {}: {}", path, e);
+                data.to_vec()
+            }
+            (Err(e), None) => return Err(e),
+        };
+        loaded.push(data);
+    }
+    Ok(loaded)
+}
@@ -0,0 +1,132 @@
+//! Layered resource packs.
+//!
+//! Assets are looked up by their path relative to a pack root, e.g. `assets/stone.png`. Packs
+//! are searched from the most recently added down to the base pack, so a texture pack only
+//! needs to contain the files it changes.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use log::{info, warn};
+
+use crate::engine::assets::embedded::embedded;
+use crate::engine::assets::zip::ZipArchive;
+
+/// Directory that holds installed packs, as folders or .zip files
+pub const RESOURCE_PACK_DIR: &str = "resourcepacks";
+/// Enabled pack names, one per line, lowest priority first
+pub const PACK_SELECTION_FILE: &str = "resourcepacks.txt";
+
+pub enum PackSource {
+    /// The defaults compiled into the binary
+    Embedded,
+    Directory(PathBuf),
+    Zip(ZipArchive),
+}
+
+pub struct ResourcePack {
+    pub name: String,
+    pub source: PackSource,
+}
+
+impl ResourcePack {
+    pub fn embedded() -> Self {
+        Self { name: "builtin".to_string(), source: PackSource::Embedded }
+    }
+
+    pub fn directory(name: impl Into<String>, root: impl Into<PathBuf>) -> Self {
+        Self { name: name.into(), source: PackSource::Directory(root.into()) }
+    }
+
+    pub fn zip(name: impl Into<String>, path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self { name: name.into(), source: PackSource::Zip(ZipArchive::open(path)?) })
+    }
+
+    /// Opens `<dir>/<name>` or `<dir>/<name>.zip`
+    pub fn find(dir: &Path, name: &str) -> io::Result<Self> {
+        let folder = dir.join(name);
+        if folder.is_dir() {
+            return Ok(Self::directory(name, folder));
+        }
+        let archive = if name.ends_with(".zip") { folder } else { dir.join(format!("{}.zip", name)) };
+        Self::zip(name, archive)
+    }
+
+    /// The file's contents, or None if this pack does not provide it
+    pub fn read(&self, path: &str) -> Option<io::Result<Vec<u8>>> {
+        match &self.source {
+            PackSource::Embedded => embedded(path).map(|data| Ok(data.to_vec())),
+            PackSource::Directory(root) => match fs::read(root.join(path)) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                result => Some(result),
+            },
+            PackSource::Zip(archive) => archive.read(path),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ResourcePacks {
+    /// Lowest priority first
+    packs: Vec<ResourcePack>,
+}
+
+impl ResourcePacks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in defaults, then the base assets in the working directory, overlaid by the
+    /// packs selected in PACK_SELECTION_FILE. Packs that fail to open are skipped.
+    pub fn from_settings() -> Self {
+        let mut packs = Self::new();
+        packs.push(ResourcePack::embedded());
+        packs.push(ResourcePack::directory("base", "."));
+        for name in read_selection(Path::new(PACK_SELECTION_FILE)) {
+            match ResourcePack::find(Path::new(RESOURCE_PACK_DIR), &name) {
+                Ok(pack) => {
+                    info!("Using resource pack {}", name);
+                    packs.push(pack);
+                }
+                Err(e) => warn!("Failed to open resource pack {}: {}", name, e),
+            }
+        }
+        packs
+    }
+
+    /// Adds a pack above all current ones
+    pub fn push(&mut self, pack: ResourcePack) {
+        self.packs.push(pack);
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.packs.iter().map(|p| p.name.as_str())
+    }
+
+    /// Reads a file from the highest-priority pack that has it. A pack whose copy is
+    /// unreadable is reported and the next one down is tried.
+    pub fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        for pack in self.packs.iter().rev() {
+            match pack.read(path) {
+                Some(Ok(data)) => return Ok(data),
+                Some(Err(e)) => warn!("Resource pack {} has an unreadable {}: {}", pack.name, path, e),
+                None => (),
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::NotFound, format!("no resource pack provides {}", path)))
+    }
+
+    pub fn read_all(&self, paths: &[&str]) -> io::Result<Vec<Vec<u8>>> {
+        paths.iter().map(|path| self.read(path)).collect()
+    }
+}
+
+/// Pack names from the selection file, skipping blank lines and `#` comments
+fn read_selection(path: &Path) -> Vec<String> {
+    let Ok(text) = fs::read_to_string(path) else { return Vec::new() };
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,135 @@
+//! Minimal ZIP archive reader for resource packs.
+//!
+//! Supports stored and deflated entries in single-disk archives without ZIP64, which covers
+//! what common tools produce for packs of textures and shaders.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::engine::codec::{crc32, ByteReader, DecodeError};
+
+const END_OF_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const DIRECTORY_ENTRY_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const END_OF_DIRECTORY_SIZE: usize = 22;
+/// The end record may be followed by a comment of up to this many bytes
+const MAX_COMMENT_SIZE: usize = u16::MAX as usize;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+#[derive(Debug, Clone, Copy)]
+struct ZipEntry {
+    method: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    header_offset: u32,
+}
+
+fn invalid(message: &str) -> io::Error {
+    DecodeError::Invalid(message.to_string()).into()
+}
+
+pub struct ZipArchive {
+    pub path: PathBuf,
+    entries: HashMap<String, ZipEntry>,
+}
+
+impl ZipArchive {
+    /// Reads the archive's directory. Entry data is read from the file on demand.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+        let len = file.seek(SeekFrom::End(0))?;
+        if len < END_OF_DIRECTORY_SIZE as u64 {
+            return Err(invalid("too short for a zip archive"));
+        }
+        let tail_len = len.min((END_OF_DIRECTORY_SIZE + MAX_COMMENT_SIZE) as u64) as usize;
+        let mut tail = vec![0; tail_len];
+        file.seek(SeekFrom::Start(len - tail_len as u64))?;
+        file.read_exact(&mut tail)?;
+
+        let end = (0..=tail_len - END_OF_DIRECTORY_SIZE).rev()
+            .find(|&i| tail[i..i + 4] == END_OF_DIRECTORY_SIGNATURE.to_le_bytes())
+            .ok_or_else(|| invalid("no zip end of directory record"))?;
+        let mut r = ByteReader::new(&tail[end + 4..]);
+        let _disk = r.read_u16()?;
+        let _directory_disk = r.read_u16()?;
+        let _disk_entries = r.read_u16()?;
+        let count = r.read_u16()?;
+        let directory_size = r.read_u32()?;
+        let directory_offset = r.read_u32()?;
+
+        let mut directory = vec![0; directory_size as usize];
+        file.seek(SeekFrom::Start(directory_offset as u64))?;
+        file.read_exact(&mut directory)?;
+        let mut r = ByteReader::new(&directory);
+        let mut entries = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            if r.read_u32()? != DIRECTORY_ENTRY_SIGNATURE {
+                return Err(invalid("bad zip directory entry"));
+            }
+            r.read_bytes(6)?; // versions and flags
+            let method = r.read_u16()?;
+            r.read_bytes(4)?; // modification time
+            let crc = r.read_u32()?;
+            let compressed_size = r.read_u32()?;
+            let size = r.read_u32()?;
+            let name_len = r.read_u16()? as usize;
+            let extra_len = r.read_u16()? as usize;
+            let comment_len = r.read_u16()? as usize;
+            r.read_bytes(8)?; // disk number and attributes
+            let header_offset = r.read_u32()?;
+            let name = String::from_utf8_lossy(r.read_bytes(name_len)?).replace('\\', "/");
+            r.read_bytes(extra_len + comment_len)?;
+            if !name.ends_with('/') {
+                entries.insert(name, ZipEntry { method, crc, compressed_size, size, header_offset });
+            }
+        }
+        Ok(Self { path, entries })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Reads and decompresses an entry, or None if the archive has no such file
+    pub fn read(&self, name: &str) -> Option<io::Result<Vec<u8>>> {
+        let entry = *self.entries.get(name)?;
+        Some(self.read_entry(entry))
+    }
+
+    fn read_entry(&self, entry: ZipEntry) -> io::Result<Vec<u8>> {
+        let mut file = File::open(&self.path)?;
+        let mut header = [0; 30];
+        file.seek(SeekFrom::Start(entry.header_offset as u64))?;
+        file.read_exact(&mut header)?;
+        let mut r = ByteReader::new(&header);
+        if r.read_u32()? != LOCAL_HEADER_SIGNATURE {
+            return Err(invalid("bad zip local header"));
+        }
+        // The local name and extra field lengths can differ from the directory's
+        let skip = u16::from_le_bytes([header[26], header[27]]) as i64 + u16::from_le_bytes([header[28], header[29]]) as i64;
+        file.seek(SeekFrom::Current(skip))?;
+        let mut compressed = vec![0; entry.compressed_size as usize];
+        file.read_exact(&mut compressed)?;
+
+        let data = match entry.method {
+            METHOD_STORED => compressed,
+            METHOD_DEFLATE => miniz_oxide::inflate::decompress_to_vec_with_limit(&compressed, entry.size as usize)
+                .map_err(|e| invalid(&format!("bad deflate data: {:?}", e.status)))?,
+            other => return Err(invalid(&format!("unsupported zip compression method {}", other))),
+        };
+        if data.len() != entry.size as usize || crc32(&data) != entry.crc {
+            return Err(invalid("zip entry checksum mismatch"));
+        }
+        Ok(data)
+    }
+}
@@ -0,0 +1,7 @@
+//! Sound effect and music playback.
+
+pub mod music;
+pub mod system;
+
+pub use music::{MusicConfig, MusicManager, Track};
+pub use system::{occlusion_gain, AudioSystem, Reverb, MAX_SOUND_DISTANCE, OCCLUSION_PER_BLOCK};
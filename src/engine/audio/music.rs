@@ -0,0 +1,274 @@
+//! Background music: a shuffled playlist with silence between tracks, crossfades, quieter
+//! music under menus and a tension track while hostile mobs are near.
+//!
+//! The tracks and timings come from a data file, MUSIC_PATH, which resource packs can
+//! replace. Like sound effects there is no output device yet; the manager keeps the gain of
+//! every track playing, which a backend would apply, and logs them starting and stopping.
+
+use log::{debug, warn};
+
+use crate::engine::assets::embedded::embedded;
+use crate::engine::math::Rng;
+
+/// Music settings, relative to a resource pack root
+pub const MUSIC_PATH: &str = "assets/music.txt";
+/// Seconds the tension track keeps playing after the last hostile mob went away
+pub const TENSION_LINGER: f32 = 10.0;
+/// Seconds to duck under a menu, and to come back up
+const DUCK_TIME: f32 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    /// Sound name, e.g. `music.meadow`
+    pub name: String,
+    /// Seconds
+    pub length: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MusicConfig {
+    /// Played in shuffled order
+    pub tracks: Vec<Track>,
+    /// Looped while hostile mobs are near
+    pub tension: Option<Track>,
+    /// Shortest and longest silence between tracks, in seconds
+    pub gap: (f32, f32),
+    /// Seconds to fade between tracks
+    pub fade: f32,
+    /// Music volume under menus, 0 to 1
+    pub duck: f32,
+}
+
+impl Default for MusicConfig {
+    fn default() -> Self {
+        Self { tracks: Vec::new(), tension: None, gap: (30.0, 120.0), fade: 3.0, duck: 0.35 }
+    }
+}
+
+fn parse_seconds(value: &str) -> Result<f32, String> {
+    value.parse::<f32>().ok().filter(|s| s.is_finite() && *s >= 0.0)
+        .ok_or_else(|| format!("expected seconds, got {}", value))
+}
+
+fn parse_track(args: &[&str]) -> Result<Track, String> {
+    let [name, length] = args else { return Err("expected <name> <seconds>".to_string()) };
+    let length = parse_seconds(length)?;
+    if length == 0.0 {
+        return Err("tracks must be longer than 0 seconds".to_string());
+    }
+    Ok(Track { name: name.to_string(), length })
+}
+
+impl MusicConfig {
+    /// Parses the settings, one per line, skipping blank lines and `#` comments
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let parsed = match (words[0], &words[1..]) {
+                ("track", args) => parse_track(args).map(|track| config.tracks.push(track)),
+                ("tension", args) => parse_track(args).map(|track| config.tension = Some(track)),
+                ("gap", [min, max]) => parse_seconds(min).and_then(|min| {
+                    let max = parse_seconds(max)?;
+                    if max < min {
+                        return Err("the longest gap is shorter than the shortest".to_string());
+                    }
+                    config.gap = (min, max);
+                    Ok(())
+                }),
+                ("fade", [seconds]) => parse_seconds(seconds).map(|fade| config.fade = fade),
+                ("duck", [volume]) => volume.parse::<f32>().ok().filter(|v| (0.0..=1.0).contains(v))
+                    .map(|duck| config.duck = duck)
+                    .ok_or_else(|| format!("duck must be 0 to 1, got {}", volume)),
+                (setting, _) => Err(format!("unknown setting or wrong arguments for {}", setting)),
+            };
+            parsed.map_err(|e| format!("line {}: {}", number + 1, e))?;
+        }
+        Ok(config)
+    }
+
+    /// The settings in the resource packs, falling back to the built-in ones if theirs do
+    /// not parse
+    pub fn load() -> Self {
+        let builtin = || {
+            let text = embedded(MUSIC_PATH).map(String::from_utf8_lossy).unwrap_or_default();
+            Self::parse(&text).unwrap_or_else(|e| {
+                warn!("Built-in music settings are broken, {}", e);
+                Self::default()
+            })
+        };
+        #[cfg(not(all(target_arch = "wasm32", feature = "web")))]
+        if let Ok(data) = crate::engine::assets::ResourcePacks::from_settings().read(MUSIC_PATH) {
+            match Self::parse(&String::from_utf8_lossy(&data)) {
+                Ok(config) => return config,
+                Err(e) => warn!("Failed to read {}, {}; using the built-in music", MUSIC_PATH, e),
+            }
+        }
+        builtin()
+    }
+}
+
+/// A track that is playing, or fading out
+#[derive(Debug, Clone)]
+struct Voice {
+    track: Track,
+    elapsed: f32,
+    gain: f32,
+    /// Gain it is fading towards
+    target: f32,
+    looping: bool,
+}
+
+pub struct MusicManager {
+    config: MusicConfig,
+    /// Music volume, 0 to 1, on top of ducking
+    pub volume: f32,
+    rng: Rng,
+    /// Indices of the tracks left in this round of the shuffle, drawn from the back
+    deck: Vec<usize>,
+    last: Option<usize>,
+    voices: Vec<Voice>,
+    /// Seconds of silence left before the next track
+    gap: f32,
+    ducked: bool,
+    duck: f32,
+    /// Seconds the tension track has left to play since hostile mobs were last near
+    tension_left: f32,
+    tense: bool,
+}
+
+impl MusicManager {
+    pub fn new(config: MusicConfig, seed: u64) -> Self {
+        let gap = config.gap.0;
+        Self {
+            config,
+            volume: 1.0,
+            rng: Rng::new(seed),
+            deck: Vec::new(),
+            last: None,
+            voices: Vec::new(),
+            gap,
+            ducked: false,
+            duck: 1.0,
+            tension_left: 0.0,
+            tense: false,
+        }
+    }
+
+    pub fn config(&self) -> &MusicConfig {
+        &self.config
+    }
+
+    /// Quieter music while a menu is open
+    pub fn set_ducked(&mut self, ducked: bool) {
+        self.ducked = ducked;
+    }
+
+    /// Whether hostile mobs are near the player now
+    pub fn set_hostiles_near(&mut self, near: bool) {
+        if near {
+            self.tension_left = TENSION_LINGER;
+        }
+    }
+
+    /// Whether the tension track is playing instead of the playlist
+    pub fn is_tense(&self) -> bool {
+        self.tense
+    }
+
+    /// The tracks playing and the gain of each, fading ones included
+    pub fn playing(&self) -> impl Iterator<Item = (&str, f32)> + '_ {
+        let gain = self.duck * self.volume;
+        self.voices.iter().map(move |voice| (voice.track.name.as_str(), voice.gain * gain))
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let duck_target = if self.ducked { self.config.duck } else { 1.0 };
+        self.duck = approach(self.duck, duck_target, (1.0 - self.config.duck) * dt / DUCK_TIME);
+
+        self.tension_left = (self.tension_left - dt).max(0.0);
+        let tense = self.tension_left > 0.0 && self.config.tension.is_some();
+        if tense != self.tense {
+            self.tense = tense;
+            for voice in &mut self.voices {
+                voice.target = 0.0;
+            }
+            match &self.config.tension {
+                Some(track) if tense => self.start(track.clone(), true),
+                // Calm again; the playlist picks up after a gap
+                _ => self.gap = self.random_gap(),
+            }
+        }
+
+        let fade = self.config.fade;
+        for voice in &mut self.voices {
+            voice.elapsed += dt;
+            if voice.looping {
+                voice.elapsed %= voice.track.length;
+            } else if voice.elapsed >= voice.track.length - fade {
+                voice.target = 0.0;
+            }
+            voice.gain = if fade > 0.0 { approach(voice.gain, voice.target, dt / fade) } else { voice.target };
+        }
+        self.voices.retain(|voice| {
+            let playing = (voice.looping || voice.elapsed < voice.track.length) && (voice.gain > 0.0 || voice.target > 0.0);
+            if !playing {
+                debug!("Music {} stopped", voice.track.name);
+            }
+            playing
+        });
+
+        // The gap runs once nothing is coming in or holding, so it starts as the last track
+        // begins fading out
+        if !self.tense && self.voices.iter().all(|voice| voice.target == 0.0) {
+            self.gap -= dt;
+            if self.gap <= 0.0 {
+                if let Some(track) = self.next_track() {
+                    self.start(track, false);
+                }
+                self.gap = self.random_gap();
+            }
+        }
+    }
+
+    fn start(&mut self, track: Track, looping: bool) {
+        debug!("Music {} starting", track.name);
+        let gain = if self.config.fade > 0.0 { 0.0 } else { 1.0 };
+        self.voices.push(Voice { track, elapsed: 0.0, gain, target: 1.0, looping });
+    }
+
+    fn random_gap(&mut self) -> f32 {
+        let (min, max) = self.config.gap;
+        min + (max - min) * self.rng.next_f32()
+    }
+
+    /// Draws from the shuffled deck, reshuffling once it runs out without playing the same
+    /// track twice in a row
+    fn next_track(&mut self) -> Option<Track> {
+        let count = self.config.tracks.len();
+        if count == 0 {
+            return None;
+        }
+        if self.deck.is_empty() {
+            self.deck = (0..count).collect();
+            for i in (1..count).rev() {
+                let j = self.rng.below(i as u32 + 1) as usize;
+                self.deck.swap(i, j);
+            }
+            if count > 1 && self.deck.last() == self.last.as_ref() {
+                self.deck.swap(0, count - 1);
+            }
+        }
+        let index = self.deck.pop()?;
+        self.last = Some(index);
+        Some(self.config.tracks[index].clone())
+    }
+}
+
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    if value < target { (value + step).min(target) } else { (value - step).max(target) }
+}
@@ -0,0 +1,114 @@
+//! Positional sound effects.
+//!
+//! There is no output device backend yet, so sounds are attenuated for the listener and
+//! logged. A backend only needs to take over `AudioSystem::emit`.
+//!
+//! The game tells the system how many solid blocks lie between a sound and the listener,
+//! and what kind of space the listener is in; working either out needs the world, which
+//! the engine doesn't know about.
+
+use glam::Vec3;
+use log::debug;
+
+/// Sounds further than this from the listener are not played
+pub const MAX_SOUND_DISTANCE: f32 = 24.0;
+/// Gain left after passing through each solid block
+pub const OCCLUSION_PER_BLOCK: f32 = 0.5;
+
+/// Echo applied to every sound, from the space around the listener
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Reverb {
+    /// Outdoors, or anywhere too small to echo
+    #[default]
+    None,
+    Room,
+    Cave,
+    LargeCave,
+}
+
+impl Reverb {
+    /// Seconds for the echo to die away
+    pub fn decay(&self) -> f32 {
+        match self {
+            Reverb::None => 0.0,
+            Reverb::Room => 0.4,
+            Reverb::Cave => 1.5,
+            Reverb::LargeCave => 3.0,
+        }
+    }
+
+    /// How much of what is heard is echo, 0 to 1
+    pub fn wet(&self) -> f32 {
+        match self {
+            Reverb::None => 0.0,
+            Reverb::Room => 0.15,
+            Reverb::Cave => 0.35,
+            Reverb::LargeCave => 0.5,
+        }
+    }
+}
+
+pub struct AudioSystem {
+    /// Master volume, 0 to 1
+    pub volume: f32,
+    listener: Vec3,
+    reverb: Reverb,
+}
+
+impl Default for AudioSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioSystem {
+    pub fn new() -> Self {
+        Self { volume: 1.0, listener: Vec3::ZERO, reverb: Reverb::None }
+    }
+
+    pub fn set_listener(&mut self, position: Vec3) {
+        self.listener = position;
+    }
+
+    pub fn set_reverb(&mut self, reverb: Reverb) {
+        self.reverb = reverb;
+    }
+
+    pub fn reverb(&self) -> Reverb {
+        self.reverb
+    }
+
+    /// Gain for a sound at `position`, falling off linearly to nothing at MAX_SOUND_DISTANCE
+    pub fn gain_at(&self, position: Vec3) -> f32 {
+        let distance = position.distance(self.listener);
+        (1.0 - distance / MAX_SOUND_DISTANCE).clamp(0.0, 1.0) * self.volume
+    }
+
+    /// Plays the named sound, e.g. `door.open`, from a point in the world
+    pub fn play_at(&mut self, name: &str, position: Vec3) {
+        self.play_at_volume(name, position, 1.0);
+    }
+
+    /// Plays a sound quieter than normal, e.g. a sneaking footstep
+    pub fn play_at_volume(&mut self, name: &str, position: Vec3, volume: f32) {
+        self.play_occluded(name, position, volume, 0);
+    }
+
+    /// Plays a sound that reaches the listener through `solid_blocks` blocks, each of which
+    /// muffles it further
+    pub fn play_occluded(&mut self, name: &str, position: Vec3, volume: f32, solid_blocks: u32) {
+        let gain = self.gain_at(position) * volume * occlusion_gain(solid_blocks);
+        if gain > 0.0 {
+            self.emit(name, gain);
+        }
+    }
+
+    fn emit(&mut self, name: &str, gain: f32) {
+        debug!("Sound {} at gain {:.2}, reverb {:?}", name, gain, self.reverb);
+    }
+}
+
+/// Gain left after passing through `solid_blocks` blocks
+pub fn occlusion_gain(solid_blocks: u32) -> f32 {
+    OCCLUSION_PER_BLOCK.powi(solid_blocks.min(i32::MAX as u32) as i32)
+}
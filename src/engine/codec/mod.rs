@@ -0,0 +1,7 @@
+//! Little-endian binary encoding used by save files.
+
+pub mod bytes;
+pub mod checksum;
+
+pub use bytes::{ByteReader, ByteWriter, DecodeError};
+pub use checksum::crc32;
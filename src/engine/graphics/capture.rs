@@ -0,0 +1,96 @@
+//! GPU frame captures for bug reports.
+//!
+//! `/capture` brackets the next frame with a capture boundary, which a GPU debugger the game
+//! runs under (RenderDoc, Xcode) picks up, and writes a report of the device and the frame
+//! to CAPTURE_DIR for attaching to the bug. Setting TRACE_ENV to a directory at startup also
+//! records a wgpu API trace there, on builds of wgpu with its `trace` feature.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::engine::graphics::renderer::Renderer;
+use crate::game::world::camera::Camera;
+
+pub const CAPTURE_DIR: &str = "captures";
+/// Names the directory to write a wgpu API trace to
+pub const TRACE_ENV: &str = "PSU_WGPU_TRACE";
+
+/// The API trace directory asked for through TRACE_ENV, created if needed
+pub fn trace_path() -> Option<PathBuf> {
+    let path = PathBuf::from(std::env::var_os(TRACE_ENV)?);
+    if let Err(e) = fs::create_dir_all(&path) {
+        log::warn!("Cannot create wgpu trace directory {}: {}", path.display(), e);
+        return None;
+    }
+    log::info!("Recording a wgpu API trace to {} (needs wgpu built with tracing)", path.display());
+    Some(path)
+}
+
+/// What was on screen when a frame was captured
+pub struct FrameInfo<'a> {
+    pub camera: &'a Camera,
+    pub chunks: usize,
+    pub face_instances: usize,
+    pub world_ticks: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct FrameCapture {
+    requested: bool,
+    capturing: bool,
+}
+
+impl FrameCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Captures the next frame drawn
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    /// Opens the capture boundary if one was requested; call before drawing a frame
+    pub fn begin(&mut self, device: &wgpu::Device) -> bool {
+        if std::mem::take(&mut self.requested) {
+            device.start_capture();
+            self.capturing = true;
+        }
+        self.capturing
+    }
+
+    /// Closes the boundary opened by `begin` and writes the report, returning where it went
+    pub fn end(&mut self, renderer: &Renderer, frame: &FrameInfo) -> io::Result<Option<PathBuf>> {
+        if !std::mem::take(&mut self.capturing) {
+            return Ok(None);
+        }
+        renderer.device.stop_capture();
+        let dir = PathBuf::from(CAPTURE_DIR);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("frame-{}.txt", crate::game::save::backup::timestamp(SystemTime::now())));
+        fs::write(&path, report(renderer, frame))?;
+        Ok(Some(path))
+    }
+}
+
+/// Plain-text description of the device, surface and frame
+pub fn report(renderer: &Renderer, frame: &FrameInfo) -> String {
+    let info = &renderer.adapter_info;
+    let config = &renderer.config;
+    let camera = frame.camera;
+    let mut out = String::new();
+    let _ = writeln!(out, "Legend of PSU frame capture, version {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out, "adapter: {} ({:?}, {:?})", info.name, info.device_type, info.backend);
+    let _ = writeln!(out, "vendor: {:#06x} device: {:#06x}", info.vendor, info.device);
+    let _ = writeln!(out, "driver: {} {}", info.driver, info.driver_info);
+    let _ = writeln!(out, "surface: {}x{} {:?}, {:?}", config.width, config.height, config.format, config.present_mode);
+    let _ = writeln!(out, "render format: {:?}{}", renderer.render_format(), if renderer.present.is_some() { ", encoded by the present pass" } else { "" });
+    let _ = writeln!(out, "camera: position {:?} yaw {:.3} pitch {:.3} fov {:.3}", camera.position, camera.yaw, camera.pitch, camera.fov);
+    let _ = writeln!(out, "chunks: {} face instances: {}", frame.chunks, frame.face_instances);
+    let _ = writeln!(out, "world ticks: {}", frame.world_ticks);
+    let _ = writeln!(out, "sky: {:?}", renderer.sky);
+    out
+}
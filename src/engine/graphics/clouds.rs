@@ -0,0 +1,157 @@
+//! The cloud layer.
+//!
+//! A flat plane at CLOUD_HEIGHT follows the viewer, its clouds shaped in the shader by value
+//! noise that the wind scrolls along. It is drawn after the world, tested against its depth,
+//! so it composites over the sky and behind anything nearer.
+
+use std::borrow::Cow;
+
+use crate::engine::shaders;
+
+/// Altitude of the cloud plane
+pub const CLOUD_HEIGHT: f32 = 64.0;
+/// How far the plane reaches from the viewer; from the ground its edge must stay nearer
+/// than the camera's far plane
+const CLOUD_RADIUS: f32 = 72.0;
+/// Noise cells the wind moves the clouds per second
+const WIND_SPEED: f64 = 0.05;
+/// Must match PERIOD in clouds.wgsl
+const NOISE_PERIOD: f64 = 256.0;
+const DEFAULT_DENSITY: f32 = 0.5;
+const UNIFORM_SIZE: u64 = 32;
+
+/// How the clouds look this frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clouds {
+    pub enabled: bool,
+    /// 0 for clear skies to 1 for overcast
+    pub density: f32,
+    /// Seconds of world time, which sets how far the wind has carried them
+    pub time: f64,
+    /// Tint, from the time of day
+    pub color: [f32; 3],
+}
+
+impl Default for Clouds {
+    fn default() -> Self {
+        Self { enabled: true, density: DEFAULT_DENSITY, time: 0.0, color: [1.0, 1.0, 1.0] }
+    }
+}
+
+impl Clouds {
+    /// Applies a console setting: `off`, `on`, or a density percentage
+    pub fn configure(&mut self, setting: &str) -> Result<(), String> {
+        match setting {
+            "off" => self.enabled = false,
+            "on" => self.enabled = true,
+            percent => {
+                let percent: f32 = percent.parse().map_err(|_| format!("expected off, on or a percentage, got {}", percent))?;
+                self.density = (percent / 100.0).clamp(0.0, 1.0);
+                self.enabled = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Cloud color for the time of day: white at noon, dim blue-grey at night
+    pub fn tint(sky_color: [f32; 3], daylight: f32) -> [f32; 3] {
+        [0, 1, 2].map(|i| sky_color[i] * 0.5 + 0.15 + 0.7 * daylight)
+    }
+
+    fn uniform(&self) -> [f32; 8] {
+        let offset = (self.time * WIND_SPEED) % NOISE_PERIOD;
+        [offset as f32, 0.0, CLOUD_HEIGHT, CLOUD_RADIUS, self.color[0], self.color[1], self.color[2], self.density]
+    }
+}
+
+pub struct CloudPass {
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl CloudPass {
+    /// Builds the pipeline for a pass drawing to `format` with a Depth32Float depth buffer,
+    /// using the world pass's camera bind group
+    pub fn new(device: &wgpu::Device, camera_bind_group_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cloud Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shaders::preprocess(shaders::CLOUD_SHADER, &[]).expect("cloud shader"))),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Cloud Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: std::num::NonZeroU64::new(UNIFORM_SIZE),
+                },
+                count: None,
+            }],
+        });
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cloud Buffer"),
+            size: UNIFORM_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Cloud Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Cloud Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Cloud Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        Self { pipeline, buffer, bind_group }
+    }
+
+    /// Uploads this frame's settings; call before the render pass
+    pub fn prepare(&self, queue: &wgpu::Queue, clouds: &Clouds) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&clouds.uniform()));
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, camera_bind_group: &'a wgpu::BindGroup, clouds: &Clouds) {
+        if !clouds.enabled || clouds.density <= 0.0 {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, camera_bind_group, &[]);
+        pass.set_bind_group(1, &self.bind_group, &[]);
+        pass.draw(0..6, 0..1);
+    }
+}
@@ -0,0 +1,138 @@
+//! Color management: where colors are sRGB encoded and where they are linear.
+//!
+//! Shaders light and blend in linear space. Color textures are stored in sRGB formats, so
+//! sampling decodes them, and frames are drawn into an sRGB target, so writing encodes them.
+//! Textures holding data rather than color, such as normal maps, stay linear. Some
+//! adapters, browsers mostly, only offer linear surfaces; frames are then drawn into an
+//! sRGB target of their own and the present pass encodes them by hand on the way out.
+
+use std::fmt;
+
+/// What a texture holds, which decides whether its format should be sRGB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorRole {
+    /// Colors as an artist painted them, sRGB encoded
+    Color,
+    /// Values sampled as stored, such as normals
+    Data,
+}
+
+/// sRGB encoded `c` in linear space, from 0 to 1
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Linear `c` encoded as sRGB, from 0 to 1; the present shader does the same
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Formats in floating point, which hold linear values beyond 1 and need no encoding
+fn is_float(format: wgpu::TextureFormat) -> bool {
+    matches!(format, wgpu::TextureFormat::Rgba16Float | wgpu::TextureFormat::Rgba32Float)
+}
+
+/// The surface format to ask for among those offered, sRGB where there is one
+pub fn choose_surface_format(formats: &[wgpu::TextureFormat]) -> Option<wgpu::TextureFormat> {
+    formats.iter().copied().find(|f| f.is_srgb()).or_else(|| formats.first().copied())
+}
+
+/// Whether frames for a `surface` format must be sRGB encoded by the present pass, since
+/// writing to it stores linear values as they are
+pub fn needs_manual_gamma(surface: wgpu::TextureFormat) -> bool {
+    !surface.is_srgb() && !is_float(surface)
+}
+
+/// The format the world is drawn in for a `surface` format: the surface's own where
+/// writing to it encodes correctly, otherwise an sRGB one to present through
+pub fn render_format(surface: wgpu::TextureFormat) -> wgpu::TextureFormat {
+    if !needs_manual_gamma(surface) {
+        return surface;
+    }
+    match surface.add_srgb_suffix() {
+        srgb if srgb.is_srgb() => srgb,
+        _ => wgpu::TextureFormat::Rgba8UnormSrgb,
+    }
+}
+
+/// How color is encoded from textures to the screen, and anything found wrong with it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorAudit {
+    pub surface: wgpu::TextureFormat,
+    pub render: wgpu::TextureFormat,
+    /// Whether frames are drawn offscreen and copied to the surface by the present pass
+    pub presented: bool,
+    /// Whether the present pass encodes sRGB by hand
+    pub manual_gamma: bool,
+    pub textures: Vec<(String, wgpu::TextureFormat, ColorRole)>,
+    pub problems: Vec<String>,
+}
+
+impl ColorAudit {
+    /// Checks that every step from `textures` to the `surface`, drawn in `render` and
+    /// `presented` or not, decodes and encodes sRGB exactly once
+    pub fn new(
+        surface: wgpu::TextureFormat,
+        render: wgpu::TextureFormat,
+        presented: bool,
+        textures: &[(&str, wgpu::TextureFormat, ColorRole)],
+    ) -> Self {
+        let manual_gamma = presented && needs_manual_gamma(surface);
+        let mut problems = Vec::new();
+        if !render.is_srgb() && !is_float(render) {
+            problems.push(format!("frames drawn to {:?} are stored without sRGB encoding", render));
+        }
+        if !presented && render != surface {
+            problems.push(format!("frames drawn in {:?} never reach the {:?} surface", render, surface));
+        }
+        if !presented && needs_manual_gamma(surface) {
+            problems.push(format!("{:?} is linear but nothing encodes frames for it", surface));
+        }
+        if is_float(surface) && !is_float(render) {
+            problems.push(format!("{:?} takes HDR frames but they are drawn in {:?}, which clips at white", surface, render));
+        }
+        for &(name, format, role) in textures {
+            match role {
+                ColorRole::Color if !format.is_srgb() => problems.push(format!("{} holds colors but {:?} is not decoded from sRGB", name, format)),
+                ColorRole::Data if format.is_srgb() => problems.push(format!("{} holds data but {:?} is decoded as sRGB", name, format)),
+                _ => (),
+            }
+        }
+        let textures = textures.iter().map(|&(name, format, role)| (name.to_string(), format, role)).collect();
+        Self { surface, render, presented, manual_gamma, textures, problems }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for ColorAudit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Surface {:?}, drawn in {:?}", self.surface, self.render)?;
+        if self.presented && is_float(self.render) {
+            write!(f, "{}", if is_float(self.surface) { ", scaled to the display" } else { ", tonemapped" })?;
+        }
+        if self.manual_gamma {
+            write!(f, ", sRGB encoded by the present pass")?;
+        }
+        for (name, format, role) in &self.textures {
+            write!(f, "\n{} {:?} ({:?})", name, format, role)?;
+        }
+        if self.problems.is_empty() {
+            write!(f, "\nNo problems")?;
+        }
+        for problem in &self.problems {
+            write!(f, "\nProblem: {}", problem)?;
+        }
+        Ok(())
+    }
+}
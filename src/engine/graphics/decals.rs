@@ -0,0 +1,141 @@
+//! Decal pass: textured quads laid over the world's surfaces.
+//!
+//! The game builds the quads, with texture coordinates into an atlas of its own, and
+//! uploads them each frame. They are drawn in one batch after the world, pulled toward the
+//! camera by a depth bias so they win against the faces they lie on without poking through
+//! anything in front.
+
+use std::borrow::Cow;
+use wgpu::util::DeviceExt;
+
+use crate::engine::graphics::texture::Texture;
+use crate::engine::shaders;
+
+/// Depth bias toward the camera, in the smallest steps the depth buffer resolves
+const DEPTH_BIAS: i32 = -2;
+/// Extra bias on faces seen at a glancing angle, whose depth changes fastest across a pixel
+const DEPTH_BIAS_SLOPE: f32 = -1.0;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DecalVertex {
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+    /// Multiplies the texture's alpha, for decals fading out
+    pub alpha: f32,
+}
+
+impl DecalVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: &[wgpu::VertexAttribute] = &wgpu::vertex_attr_array![
+            0 => Float32x3,
+            1 => Float32x2,
+            2 => Float32,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<DecalVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: ATTRIBUTES,
+        }
+    }
+}
+
+pub struct DecalPass {
+    pipeline: wgpu::RenderPipeline,
+    atlas: Texture,
+    /// Triangle list and its vertex count; None when there is nothing to draw
+    mesh: Option<(wgpu::Buffer, u32)>,
+}
+
+impl DecalPass {
+    /// Builds the pipeline for a pass drawing to `format` with a Depth32Float depth buffer,
+    /// using the world pass's camera bind group. Nothing shows until set_atlas is given the
+    /// decals' textures.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, camera_bind_group_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let atlas = Texture::create_atlas(device, queue, &image::RgbaImage::new(1, 1));
+        let pipeline = Self::pipeline(device, camera_bind_group_layout, &atlas.bind_group_layout, format);
+        Self { pipeline, atlas, mesh: None }
+    }
+
+    /// Switches to drawing to `format`, keeping the atlas and quads
+    pub fn set_format(&mut self, device: &wgpu::Device, camera_bind_group_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) {
+        self.pipeline = Self::pipeline(device, camera_bind_group_layout, &self.atlas.bind_group_layout, format);
+    }
+
+    fn pipeline(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        atlas_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Decal Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shaders::preprocess(shaders::DECAL_SHADER, &[]).expect("decal shader"))),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Decal Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, atlas_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Decal Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[DecalVertex::desc()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState { constant: DEPTH_BIAS, slope_scale: DEPTH_BIAS_SLOPE, clamp: 0.0 },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    pub fn atlas_format(&self) -> wgpu::TextureFormat {
+        self.atlas.texture.format()
+    }
+
+    /// Replaces the textures the quads' coordinates point into
+    pub fn set_atlas(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, atlas: &image::RgbaImage) {
+        self.atlas = Texture::create_atlas(device, queue, atlas);
+    }
+
+    /// Replaces the quads with `vertices`, a triangle list
+    pub fn upload(&mut self, device: &wgpu::Device, vertices: &[DecalVertex]) {
+        self.mesh = (!vertices.is_empty()).then(|| {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Decal Buffer"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+            (buffer, vertices.len() as u32)
+        });
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, camera_bind_group: &'a wgpu::BindGroup) {
+        let Some((buffer, count)) = &self.mesh else { return };
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, camera_bind_group, &[]);
+        pass.set_bind_group(1, &self.atlas.bind_group, &[]);
+        pass.set_vertex_buffer(0, buffer.slice(..));
+        pass.draw(0..*count, 0..1);
+    }
+}
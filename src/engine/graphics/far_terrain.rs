@@ -0,0 +1,115 @@
+//! Far terrain imposters.
+//!
+//! Past the loaded chunks the world is stood in for by a colored heightfield that the game
+//! builds from its generator, coarser the farther out it reaches. It is drawn after the
+//! world at the very back of the depth range without writing depth, so real chunks always
+//! hide it and it only shows where nothing is loaded. Its triangles come farthest first, so
+//! nearer hills paint over farther ones.
+
+use std::borrow::Cow;
+use wgpu::util::DeviceExt;
+
+use crate::engine::shaders;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FarVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+impl FarVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: &[wgpu::VertexAttribute] = &wgpu::vertex_attr_array![
+            0 => Float32x3,
+            1 => Float32x3,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<FarVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: ATTRIBUTES,
+        }
+    }
+}
+
+pub struct FarTerrainPass {
+    pipeline: wgpu::RenderPipeline,
+    /// Triangle list and its vertex count; None when there is nothing to draw
+    mesh: Option<(wgpu::Buffer, u32)>,
+}
+
+impl FarTerrainPass {
+    /// Builds the pipeline for a pass drawing to `format` with a Depth32Float depth buffer,
+    /// using the world pass's camera bind group
+    pub fn new(device: &wgpu::Device, camera_bind_group_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        Self { pipeline: Self::pipeline(device, camera_bind_group_layout, format), mesh: None }
+    }
+
+    /// Switches to drawing to `format`, keeping the mesh
+    pub fn set_format(&mut self, device: &wgpu::Device, camera_bind_group_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) {
+        self.pipeline = Self::pipeline(device, camera_bind_group_layout, format);
+    }
+
+    fn pipeline(device: &wgpu::Device, camera_bind_group_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Far Terrain Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shaders::preprocess(shaders::FAR_TERRAIN_SHADER, &[]).expect("far terrain shader"))),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Far Terrain Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Far Terrain Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[FarVertex::desc()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                // Only where the cleared depth is left, which the world never touched
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    /// Replaces the heightfield with `vertices`, a triangle list ordered farthest first
+    pub fn upload(&mut self, device: &wgpu::Device, vertices: &[FarVertex]) {
+        self.mesh = (!vertices.is_empty()).then(|| {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Far Terrain Buffer"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+            (buffer, vertices.len() as u32)
+        });
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, camera_bind_group: &'a wgpu::BindGroup) {
+        let Some((buffer, count)) = &self.mesh else { return };
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, camera_bind_group, &[]);
+        pass.set_vertex_buffer(0, buffer.slice(..));
+        pass.draw(0..*count, 0..1);
+    }
+}
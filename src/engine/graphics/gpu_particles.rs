@@ -0,0 +1,290 @@
+//! Particles simulated on the GPU.
+//!
+//! A compute pass steps a fixed pool of particles each frame and lists the living ones in
+//! an indirect draw, so the CPU only uploads the particles spawned since the last frame
+//! and tens of thousands cost it nothing to move. New particles take the pool's slots in
+//! turn, overwriting the oldest once it is full. Adapters without compute shaders or
+//! storage buffers in vertex shaders, such as WebGL, simulate on the CPU instead; see
+//! ParticleSystem::set_offloaded.
+
+use std::borrow::Cow;
+use bytemuck::{Pod, Zeroable};
+
+use crate::engine::graphics::particles::{Particle, PARTICLE_DRAG, PARTICLE_GRAVITY};
+use crate::engine::shaders;
+
+/// Particles the pool holds at once
+pub const GPU_PARTICLE_CAPACITY: u32 = 65536;
+const WORKGROUP_SIZE: u32 = 64;
+/// Vertices of the box each particle is drawn as
+const BOX_VERTICES: u32 = 36;
+const STEP_SIZE: u64 = 16;
+/// An indirect draw: vertex count, instance count, first vertex, first instance
+const DRAW_ARGS_SIZE: u64 = 16;
+
+/// A particle as the shaders see it; matches particle.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct GpuParticle {
+    pub position: [f32; 3],
+    pub life: f32,
+    pub velocity: [f32; 3],
+    pub size: f32,
+    pub color: [f32; 4],
+}
+
+impl From<&Particle> for GpuParticle {
+    fn from(particle: &Particle) -> Self {
+        Self {
+            position: particle.position.to_array(),
+            life: particle.life,
+            velocity: particle.velocity.to_array(),
+            size: particle.size,
+            color: particle.color,
+        }
+    }
+}
+
+/// Where `count` new particles go in a pool of `capacity` whose next free slot is `next`:
+/// the ranges of slots and of the particles written to them, at most two since the slots
+/// wrap around. Only the last `capacity` particles are kept.
+pub fn spawn_ranges(next: u32, count: usize, capacity: u32) -> Vec<(u32, std::ops::Range<usize>)> {
+    let kept = count.min(capacity as usize);
+    let first = count - kept;
+    let until_wrap = (capacity - next) as usize;
+    if kept <= until_wrap {
+        return vec![(next, first..count)];
+    }
+    vec![(next, first..first + until_wrap), (0, first + until_wrap..count)]
+}
+
+/// Whether `adapter` can run the particle simulation
+pub fn is_supported(adapter: &wgpu::Adapter) -> bool {
+    let needed = wgpu::DownlevelFlags::COMPUTE_SHADERS | wgpu::DownlevelFlags::VERTEX_STORAGE | wgpu::DownlevelFlags::INDIRECT_EXECUTION;
+    adapter.get_downlevel_capabilities().flags.contains(needed)
+}
+
+pub struct GpuParticlePass {
+    particles: wgpu::Buffer,
+    step: wgpu::Buffer,
+    draw_args: wgpu::Buffer,
+    update_pipeline: wgpu::ComputePipeline,
+    update_bind_group: wgpu::BindGroup,
+    draw_pipeline: wgpu::RenderPipeline,
+    draw_layout: wgpu::BindGroupLayout,
+    draw_bind_group: wgpu::BindGroup,
+    next_slot: u32,
+    /// Seconds until every particle in the pool has died, after which nothing is simulated
+    /// or drawn
+    remaining: f32,
+}
+
+fn storage_entry(binding: u32, visibility: wgpu::ShaderStages, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+impl GpuParticlePass {
+    /// Builds the simulation and a pipeline drawing to `format` with a Depth32Float depth
+    /// buffer, using the world pass's camera bind group
+    pub fn new(device: &wgpu::Device, camera_bind_group_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let particles = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Pool"),
+            size: GPU_PARTICLE_CAPACITY as u64 * std::mem::size_of::<GpuParticle>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            // Zeroed, so every slot starts out dead
+            mapped_at_creation: false,
+        });
+        let alive = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Alive Particles"),
+            size: GPU_PARTICLE_CAPACITY as u64 * 4,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let step = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Step"),
+            size: STEP_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let draw_args = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Draw Args"),
+            size: DRAW_ARGS_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let update_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Particle Update Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shaders::preprocess(shaders::PARTICLE_UPDATE_SHADER, &[]).expect("particle update shader"))),
+        });
+        let update_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Particle Update Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: std::num::NonZeroU64::new(STEP_SIZE),
+                    },
+                    count: None,
+                },
+                storage_entry(1, wgpu::ShaderStages::COMPUTE, false),
+                storage_entry(2, wgpu::ShaderStages::COMPUTE, false),
+                storage_entry(3, wgpu::ShaderStages::COMPUTE, false),
+            ],
+        });
+        let update_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Particle Update Bind Group"),
+            layout: &update_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: step.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: particles.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: alive.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: draw_args.as_entire_binding() },
+            ],
+        });
+        let update_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Particle Update Pipeline"),
+            layout: Some(&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Particle Update Pipeline Layout"),
+                bind_group_layouts: &[&update_layout],
+                push_constant_ranges: &[],
+            })),
+            module: &update_shader,
+            entry_point: "cs_update",
+            compilation_options: Default::default(),
+        });
+
+        let draw_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Particle Bind Group Layout"),
+            entries: &[
+                storage_entry(0, wgpu::ShaderStages::VERTEX, true),
+                storage_entry(1, wgpu::ShaderStages::VERTEX, true),
+            ],
+        });
+        let draw_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Particle Bind Group"),
+            layout: &draw_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: particles.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: alive.as_entire_binding() },
+            ],
+        });
+        let draw_pipeline = Self::draw_pipeline(device, camera_bind_group_layout, &draw_layout, format);
+
+        Self {
+            particles,
+            step,
+            draw_args,
+            update_pipeline,
+            update_bind_group,
+            draw_pipeline,
+            draw_layout,
+            draw_bind_group,
+            next_slot: 0,
+            remaining: 0.0,
+        }
+    }
+
+    /// Switches to drawing to `format`, keeping the particles
+    pub fn set_format(&mut self, device: &wgpu::Device, camera_bind_group_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) {
+        self.draw_pipeline = Self::draw_pipeline(device, camera_bind_group_layout, &self.draw_layout, format);
+    }
+
+    fn draw_pipeline(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        draw_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        let draw_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Particle Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shaders::preprocess(shaders::PARTICLE_SHADER, &[]).expect("particle shader"))),
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Particle Pipeline"),
+            layout: Some(&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Particle Pipeline Layout"),
+                bind_group_layouts: &[camera_bind_group_layout, draw_layout],
+                push_constant_ranges: &[],
+            })),
+            vertex: wgpu::VertexState {
+                module: &draw_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &draw_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    /// Adds `spawned` to the pool and steps every particle `delta` seconds; call once a
+    /// frame, before drawing
+    pub fn simulate(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, delta: f32, spawned: &[Particle]) {
+        let spawned: Vec<GpuParticle> = spawned.iter().map(GpuParticle::from).collect();
+        let stride = std::mem::size_of::<GpuParticle>() as u64;
+        for (slot, range) in spawn_ranges(self.next_slot, spawned.len(), GPU_PARTICLE_CAPACITY) {
+            queue.write_buffer(&self.particles, slot as u64 * stride, bytemuck::cast_slice(&spawned[range.clone()]));
+            self.next_slot = (slot + range.len() as u32) % GPU_PARTICLE_CAPACITY;
+        }
+        self.remaining = spawned.iter().map(|particle| particle.life).fold(self.remaining - delta, f32::max);
+        // The simulation counts the living back up from none
+        queue.write_buffer(&self.draw_args, 0, bytemuck::cast_slice(&[BOX_VERTICES, 0, 0, 0]));
+        if self.remaining <= 0.0 {
+            return;
+        }
+        let step = [delta.to_bits(), PARTICLE_GRAVITY.to_bits(), PARTICLE_DRAG.powf(delta).to_bits(), GPU_PARTICLE_CAPACITY];
+        queue.write_buffer(&self.step, 0, bytemuck::cast_slice(&step));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Particle Encoder") });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Particle Update Pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.update_pipeline);
+            pass.set_bind_group(0, &self.update_bind_group, &[]);
+            pass.dispatch_workgroups(GPU_PARTICLE_CAPACITY.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, camera_bind_group: &'a wgpu::BindGroup) {
+        if self.remaining <= 0.0 {
+            return;
+        }
+        pass.set_pipeline(&self.draw_pipeline);
+        pass.set_bind_group(0, camera_bind_group, &[]);
+        pass.set_bind_group(1, &self.draw_bind_group, &[]);
+        pass.draw_indirect(&self.draw_args, 0);
+    }
+}
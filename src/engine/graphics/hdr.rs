@@ -0,0 +1,151 @@
+//! HDR output.
+//!
+//! With HDR on, the world is drawn into a floating point frame, so highlights such as sun
+//! glints on wet stone can go past white. Where the display offers an extended range
+//! surface, the present pass scales the frame so white paper shows at the paper white
+//! brightness and rolls highlights off toward the peak. Elsewhere it tonemaps the frame
+//! down to an ordinary surface instead.
+
+use crate::engine::graphics::upscale::{UpscaleMode, UpscaleSettings, SHARPNESS};
+
+/// The frame drawn into while HDR is on
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+/// Brightness an extended range surface shows a value of 1 at, in nits
+pub const SCRGB_WHITE_NITS: f32 = 80.0;
+pub const DEFAULT_PAPER_WHITE: f32 = 200.0;
+pub const DEFAULT_PEAK: f32 = 1000.0;
+pub const MIN_PAPER_WHITE: f32 = 80.0;
+pub const MAX_PAPER_WHITE: f32 = 500.0;
+pub const MAX_PEAK: f32 = 10000.0;
+/// Values up to this share of the top of the range pass through tonemapping unchanged
+const SHOULDER_KNEE: f32 = 0.8;
+
+/// Where the display offers one, the format to present HDR frames through
+pub fn hdr_surface_format(formats: &[wgpu::TextureFormat]) -> Option<wgpu::TextureFormat> {
+    formats.iter().copied().find(|&f| f == HDR_FORMAT)
+}
+
+/// Rolls `c` off smoothly toward 1, leaving it as it is below SHOULDER_KNEE; the present
+/// shader does the same
+pub fn shoulder(c: f32) -> f32 {
+    let over = (c - SHOULDER_KNEE).max(0.0);
+    c.min(SHOULDER_KNEE) + (1.0 - SHOULDER_KNEE) * over / (over + 1.0 - SHOULDER_KNEE)
+}
+
+/// A half precision float, as HDR_FORMAT stores each channel, widened to f32
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HdrSettings {
+    pub enabled: bool,
+    /// How bright white surfaces and the HUD show, in nits
+    pub paper_white: f32,
+    /// The brightest the display should be asked for, in nits
+    pub peak: f32,
+}
+
+impl Default for HdrSettings {
+    fn default() -> Self {
+        Self { enabled: false, paper_white: DEFAULT_PAPER_WHITE, peak: DEFAULT_PEAK }
+    }
+}
+
+impl HdrSettings {
+    /// Applies a console setting: `off`, `on`, `paper <nits>` or `peak <nits>`. Setting
+    /// either brightness turns HDR on.
+    pub fn configure(&mut self, args: &[String]) -> Result<(), String> {
+        let nits = || -> Result<f32, String> {
+            let value = args.get(1).ok_or("expected a brightness in nits")?;
+            value.parse().map_err(|_| format!("expected a brightness in nits, got {}", value))
+        };
+        match args.first().map(String::as_str) {
+            Some("off") => self.enabled = false,
+            Some("on") => self.enabled = true,
+            Some("paper") => {
+                self.paper_white = nits()?.clamp(MIN_PAPER_WHITE, MAX_PAPER_WHITE);
+                self.enabled = true;
+            }
+            Some("peak") => {
+                self.peak = nits()?.clamp(MIN_PAPER_WHITE, MAX_PEAK);
+                self.enabled = true;
+            }
+            other => return Err(format!("expected off, on, paper or peak, got {}", other.unwrap_or(""))),
+        }
+        // Paper white past the peak would clip everything white
+        self.paper_white = self.paper_white.min(self.peak);
+        Ok(())
+    }
+}
+
+/// What the present pass does to the frame on its way to the surface
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PresentParams {
+    /// Multiplies the frame, taking white to paper white on HDR surfaces
+    pub scale: f32,
+    /// What the shoulder rolls highlights off toward, after `scale`
+    pub peak: f32,
+    /// 1 to tonemap through the shoulder, 0 to pass values through
+    pub tonemap: f32,
+    /// 1 to encode sRGB by hand, for surfaces storing linear values
+    pub encode_srgb: f32,
+    /// How a frame smaller than the surface is stretched: 0 copies texel for texel or
+    /// takes the nearest, 1 blends bilinearly, 2 blends and sharpens, see upscale.rs
+    pub upscale: f32,
+    /// How hard upscale 2 sharpens
+    pub sharpness: f32,
+    pub _padding: [f32; 2],
+}
+
+impl PresentParams {
+    /// For frames drawn in `render` going to `surface`. HDR frames are scaled to the
+    /// display's brightness on an HDR surface and tonemapped on any other; other frames are
+    /// only encoded, where the surface is linear.
+    pub fn new(settings: &HdrSettings, render: wgpu::TextureFormat, surface: wgpu::TextureFormat) -> Self {
+        let encode_srgb = if crate::engine::graphics::color::needs_manual_gamma(surface) { 1.0 } else { 0.0 };
+        let copy = Self { scale: 1.0, peak: 1.0, tonemap: 0.0, encode_srgb, upscale: 0.0, sharpness: 0.0, _padding: [0.0; 2] };
+        if render != HDR_FORMAT {
+            return copy;
+        }
+        if surface == HDR_FORMAT {
+            let scale = settings.paper_white / SCRGB_WHITE_NITS;
+            return Self { scale, peak: settings.peak / SCRGB_WHITE_NITS, tonemap: 1.0, encode_srgb: 0.0, ..copy };
+        }
+        Self { tonemap: 1.0, ..copy }
+    }
+
+    /// Stretches frames drawn below the surface's resolution as `upscale` asks
+    pub fn upscaled(self, upscale: &UpscaleSettings) -> Self {
+        if !upscale.is_scaled() {
+            return self;
+        }
+        let mode = match upscale.mode {
+            UpscaleMode::Nearest => 0.0,
+            UpscaleMode::Bilinear => 1.0,
+            UpscaleMode::Sharpen => 2.0,
+        };
+        Self { upscale: mode, sharpness: SHARPNESS, ..self }
+    }
+
+    /// What the present shader makes of a frame value `c`
+    pub fn apply(&self, c: f32) -> f32 {
+        let mut c = c.max(0.0) * self.scale;
+        if self.tonemap > 0.5 {
+            c = shoulder(c / self.peak) * self.peak;
+        }
+        if self.encode_srgb > 0.5 {
+            c = crate::engine::graphics::color::linear_to_srgb(c.clamp(0.0, 1.0));
+        }
+        c
+    }
+}
@@ -1,7 +1,43 @@
+pub mod capture;
+pub mod clouds;
+pub mod color;
+pub mod decals;
+pub mod far_terrain;
+pub mod font;
+pub mod gpu_particles;
+pub mod hdr;
+pub mod night_sky;
+pub mod normal_map;
+pub mod overlay;
+pub mod particles;
+pub mod picking;
+pub mod pipeline_cache;
+pub mod present;
+pub mod render_queue;
 pub mod renderer;
+pub mod screenshot;
+pub mod taa;
 pub mod texture;
+pub mod upscale;
 pub mod vertex;
 
-pub use renderer::Renderer;
+pub use capture::FrameCapture;
+pub use clouds::{CloudPass, Clouds};
+pub use color::{ColorAudit, ColorRole};
+pub use decals::{DecalPass, DecalVertex};
+pub use far_terrain::{FarTerrainPass, FarVertex};
+pub use gpu_particles::GpuParticlePass;
+pub use hdr::HdrSettings;
+pub use night_sky::NightSkyPass;
+pub use normal_map::NormalAtlas;
+pub use overlay::{Overlay, OverlayPass};
+pub use particles::ParticleSystem;
+pub use picking::{PickPass, PickTarget};
+pub use pipeline_cache::{PipelineCache, PipelineKey, RenderMaterial};
+pub use present::PresentPass;
+pub use render_queue::RenderQueue;
+pub use renderer::{Renderer, View, Viewport};
+pub use taa::{AntiAliasing, TaaPass};
 pub use texture::Texture;
+pub use upscale::{UpscaleMode, UpscaleSettings};
 pub use vertex::Vertex; 
\ No newline at end of file
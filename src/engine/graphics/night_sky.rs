@@ -0,0 +1,113 @@
+//! Stars and the moon, drawn behind the world at night.
+//!
+//! A full-screen pass right after the sky is cleared works out the view direction of each
+//! pixel and draws the star field and moon there, faded in as daylight goes. The world
+//! then draws over it.
+
+use std::borrow::Cow;
+
+use glam::{Mat4, Vec3};
+
+use crate::engine::graphics::renderer::Sky;
+use crate::engine::shaders;
+
+const UNIFORM_SIZE: u64 = 96;
+
+pub struct NightSkyPass {
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl NightSkyPass {
+    /// Builds the pipeline for a pass drawing to `format` with a Depth32Float depth buffer
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Night Sky Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shaders::preprocess(shaders::SKY_SHADER, &[]).expect("sky shader"))),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Night Sky Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: std::num::NonZeroU64::new(UNIFORM_SIZE),
+                },
+                count: None,
+            }],
+        });
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Night Sky Buffer"),
+            size: UNIFORM_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Night Sky Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Night Sky Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Night Sky Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // Behind everything: the world draws over it
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        Self { pipeline, buffer, bind_group }
+    }
+
+    /// Uploads this frame's view and sky; call before the render pass
+    pub fn prepare(&self, queue: &wgpu::Queue, view_proj: &Mat4, sky: &Sky) {
+        let sun = Vec3::from(sky.sun_direction).normalize_or_zero();
+        let night = 1.0 - sky.daylight;
+        // The sun circles the z axis, and the stars turn with it
+        let turned = sun.y.atan2(sun.x);
+        let mut uniform = [0.0f32; 24];
+        uniform[..16].copy_from_slice(&view_proj.inverse().to_cols_array());
+        uniform[16..20].copy_from_slice(&[-sun.x, -sun.y, -sun.z, night]);
+        uniform[20..24].copy_from_slice(&[sky.moon_phase, turned, 0.0, 0.0]);
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&uniform));
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, sky: &Sky) {
+        if sky.daylight >= 1.0 {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,114 @@
+//! Optional normal maps for block textures.
+//!
+//! A block texture `name.png` may come with a tangent-space normal map `name_n.png` of the
+//! same size, red pointing along the texture's +u and green toward the top of the image.
+//! They are packed into a second atlas with the block atlas's layout, and textures without
+//! one get a flat tile.
+
+use log::warn;
+
+/// A normal pointing straight out of the surface
+const FLAT_NORMAL: image::Rgba<u8> = image::Rgba([128, 128, 255, 255]);
+
+/// Where the normal map for the texture at `path` lives
+pub fn normal_map_path(path: &str) -> String {
+    match path.strip_suffix(".png") {
+        Some(stem) => format!("{}_n.png", stem),
+        None => format!("{}_n", path),
+    }
+}
+
+/// Packs the normal maps of the block textures, in atlas order, into an atlas of
+/// `tile_size` tiles. Missing or unreadable maps are flat.
+pub fn decode_normal_atlas(tiles: &[Option<Vec<u8>>], tile_size: (u32, u32)) -> image::RgbaImage {
+    let grid = (tiles.len() as f32).sqrt().ceil() as u32;
+    let mut atlas = image::RgbaImage::from_pixel(grid * tile_size.0, grid * tile_size.1, FLAT_NORMAL);
+    for (i, bytes) in tiles.iter().enumerate() {
+        let Some(bytes) = bytes else { continue };
+        let tile = match image::load_from_memory(bytes) {
+            Ok(tile) => tile.to_rgba8(),
+            Err(e) => {
+                warn!("Skipping normal map {}: {}", i, e);
+                continue;
+            }
+        };
+        if tile.dimensions() != tile_size {
+            warn!("Skipping normal map {}: {:?} does not match the texture's {:?}", i, tile.dimensions(), tile_size);
+            continue;
+        }
+        let (x, y) = ((i as u32 % grid) * tile_size.0, (i as u32 / grid) * tile_size.1);
+        image::imageops::replace(&mut atlas, &tile, x as i64, y as i64);
+    }
+    atlas
+}
+
+/// Size of each tile in a block atlas of `count` textures
+pub fn tile_size(atlas: &image::RgbaImage, count: usize) -> (u32, u32) {
+    let grid = (count as f32).sqrt().ceil().max(1.0) as u32;
+    (atlas.width() / grid, atlas.height() / grid)
+}
+
+pub struct NormalAtlas {
+    pub texture: wgpu::Texture,
+    pub bind_group: wgpu::BindGroup,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl NormalAtlas {
+    /// Uploads an atlas built by decode_normal_atlas. Normals are vectors, not colors, so
+    /// the texture is linear.
+    pub fn create(device: &wgpu::Device, queue: &wgpu::Queue, atlas: &image::RgbaImage) -> Self {
+        let (width, height) = atlas.dimensions();
+        let size = wgpu::Extent3d { width, height, depth_or_array_layers: 1 };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("Normal Map Atlas"),
+            view_formats: &[],
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            atlas,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Normal Map Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Normal Map Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) }],
+        });
+        Self { texture, bind_group, bind_group_layout }
+    }
+
+    /// A single flat normal, for when the block atlas itself failed to load
+    pub fn flat(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self::create(device, queue, &image::RgbaImage::from_pixel(1, 1, FLAT_NORMAL))
+    }
+}
@@ -0,0 +1,272 @@
+//! Overlays drawn on top of the world for tools and debugging.
+//!
+//! Callers fill an `Overlay` each frame with translucent boxes, lines and HUD text, and the
+//! renderer draws it after the world, depth tested against it except for the HUD.
+
+use std::borrow::Cow;
+use glam::Vec3;
+use wgpu::util::DeviceExt;
+
+use crate::engine::shaders;
+use crate::engine::graphics::font::{glyph, GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH, LINE_HEIGHT};
+
+pub type Color = [f32; 4];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ColorVertex {
+    pub position: [f32; 3],
+    pub color: Color,
+}
+
+impl ColorVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: &[wgpu::VertexAttribute] = &wgpu::vertex_attr_array![
+            0 => Float32x3,
+            1 => Float32x4,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ColorVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: ATTRIBUTES,
+        }
+    }
+}
+
+/// Corner indices of a box, with bit 0 selecting max x, bit 1 max y and bit 2 max z
+const BOX_FACES: [[usize; 4]; 6] = [
+    [0, 2, 3, 1], [4, 5, 7, 6], // -z, +z
+    [0, 4, 6, 2], [1, 3, 7, 5], // -x, +x
+    [0, 1, 5, 4], [2, 6, 7, 3], // -y, +y
+];
+const BOX_EDGES: [(usize, usize); 12] = [
+    (0, 1), (2, 3), (4, 5), (6, 7),
+    (0, 2), (1, 3), (4, 6), (5, 7),
+    (0, 4), (1, 5), (2, 6), (3, 7),
+];
+
+/// One frame's overlay geometry
+#[derive(Default)]
+pub struct Overlay {
+    triangles: Vec<ColorVertex>,
+    lines: Vec<ColorVertex>,
+    /// HUD triangles, in pixels from the top left of the window
+    hud: Vec<ColorVertex>,
+}
+
+impl Overlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty() && self.lines.is_empty() && self.hud.is_empty()
+    }
+
+    /// A filled box; use a low alpha to keep what is inside visible
+    pub fn add_box(&mut self, min: Vec3, max: Vec3, color: Color) {
+        let corners = box_corners(min, max);
+        for face in BOX_FACES {
+            for i in [0, 1, 2, 2, 3, 0] {
+                self.triangles.push(ColorVertex { position: corners[face[i]].to_array(), color });
+            }
+        }
+    }
+
+    pub fn add_box_outline(&mut self, min: Vec3, max: Vec3, color: Color) {
+        let corners = box_corners(min, max);
+        for (a, b) in BOX_EDGES {
+            self.add_line(corners[a], corners[b], color);
+        }
+    }
+
+    pub fn add_line(&mut self, from: Vec3, to: Vec3, color: Color) {
+        self.lines.push(ColorVertex { position: from.to_array(), color });
+        self.lines.push(ColorVertex { position: to.to_array(), color });
+    }
+
+    /// A screen-space rectangle, in pixels from the top left
+    pub fn add_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        let corners = [[x, y], [x + width, y], [x + width, y + height], [x, y + height]];
+        for i in [0, 1, 2, 2, 3, 0] {
+            self.hud.push(ColorVertex { position: [corners[i][0], corners[i][1], 0.0], color });
+        }
+    }
+
+    /// Draws text with its top left corner at (x, y) pixels. `scale` is the size of one font
+    /// pixel in screen pixels. Lines are separated by '\n'.
+    pub fn add_text(&mut self, x: f32, y: f32, scale: f32, color: Color, text: &str) {
+        for (row, line) in text.lines().enumerate() {
+            let top = y + (row as u32 * LINE_HEIGHT) as f32 * scale;
+            for (column, c) in line.chars().enumerate() {
+                let left = x + (column as u32 * GLYPH_ADVANCE) as f32 * scale;
+                for (gy, bits) in glyph(c).iter().enumerate() {
+                    for gx in 0..GLYPH_WIDTH {
+                        if bits & (1 << (GLYPH_WIDTH - 1 - gx)) != 0 {
+                            self.add_rect(left + gx as f32 * scale, top + gy as f32 * scale, scale, scale, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Text on a plane in the world, such as a sign. The top left corner of the first glyph
+    /// is at `origin`, glyphs run along `right` and lines along `down`, both unit vectors,
+    /// and each font pixel is `scale` world units across.
+    pub fn add_world_text(&mut self, origin: Vec3, right: Vec3, down: Vec3, scale: f32, color: Color, text: &str) {
+        for (row, line) in text.lines().enumerate() {
+            let top = origin + down * ((row as u32 * LINE_HEIGHT) as f32 * scale);
+            for (column, c) in line.chars().enumerate() {
+                let left = top + right * ((column as u32 * GLYPH_ADVANCE) as f32 * scale);
+                for (gy, bits) in glyph(c).iter().enumerate() {
+                    for gx in 0..GLYPH_WIDTH {
+                        if bits & (1 << (GLYPH_WIDTH - 1 - gx)) != 0 {
+                            let corner = left + (right * gx as f32 + down * gy as f32) * scale;
+                            let corners = [corner, corner + right * scale, corner + (right + down) * scale, corner + down * scale];
+                            for i in [0, 1, 2, 2, 3, 0] {
+                                self.triangles.push(ColorVertex { position: corners[i].to_array(), color });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Width and height of text drawn by `add_text`, in pixels
+    pub fn text_size(text: &str, scale: f32) -> (f32, f32) {
+        let columns = text.lines().map(|line| line.chars().count()).max().unwrap_or(0) as u32;
+        let rows = text.lines().count() as u32;
+        let width = (columns * GLYPH_ADVANCE).saturating_sub(1) as f32 * scale;
+        let height = (rows * LINE_HEIGHT).saturating_sub(LINE_HEIGHT - GLYPH_HEIGHT) as f32 * scale;
+        (width, height)
+    }
+
+    /// Text on a dark backing box, so it stays readable over bright terrain
+    pub fn add_label(&mut self, x: f32, y: f32, scale: f32, color: Color, text: &str) {
+        let (width, height) = Self::text_size(text, scale);
+        let pad = 2.0 * scale;
+        self.add_rect(x - pad, y - pad, width + 2.0 * pad, height + 2.0 * pad, [0.0, 0.0, 0.0, 0.5]);
+        self.add_text(x, y, scale, color, text);
+    }
+}
+
+fn box_corners(min: Vec3, max: Vec3) -> [Vec3; 8] {
+    std::array::from_fn(|i| Vec3::new(
+        if i & 1 != 0 { max.x } else { min.x },
+        if i & 2 != 0 { max.y } else { min.y },
+        if i & 4 != 0 { max.z } else { min.z },
+    ))
+}
+
+/// Vertex buffers for one frame's overlay
+pub struct OverlayBuffers {
+    triangles: Option<(wgpu::Buffer, u32)>,
+    lines: Option<(wgpu::Buffer, u32)>,
+    hud: Option<(wgpu::Buffer, u32)>,
+}
+
+pub struct OverlayPass {
+    triangle_pipeline: wgpu::RenderPipeline,
+    line_pipeline: wgpu::RenderPipeline,
+    hud_pipeline: wgpu::RenderPipeline,
+}
+
+impl OverlayPass {
+    /// Builds pipelines for a pass drawing to `format` with a Depth32Float depth buffer,
+    /// using the world pass's camera bind group
+    pub fn new(device: &wgpu::Device, camera_bind_group_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Overlay Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shaders::preprocess(shaders::OVERLAY_SHADER, &[]).expect("overlay shader"))),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |label: &str, entry_point: &str, topology, depth_compare| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point,
+                    buffers: &[ColorVertex::desc()],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState { topology, ..Default::default() },
+                // Overlays read the world's depth but never hide each other
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: false,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        Self {
+            triangle_pipeline: pipeline("Overlay Triangle Pipeline", "vs_world",
+                wgpu::PrimitiveTopology::TriangleList, wgpu::CompareFunction::LessEqual),
+            line_pipeline: pipeline("Overlay Line Pipeline", "vs_world",
+                wgpu::PrimitiveTopology::LineList, wgpu::CompareFunction::LessEqual),
+            hud_pipeline: pipeline("Overlay HUD Pipeline", "vs_screen",
+                wgpu::PrimitiveTopology::TriangleList, wgpu::CompareFunction::Always),
+        }
+    }
+
+    /// Uploads the overlay for a `screen` sized view
+    pub fn prepare(&self, device: &wgpu::Device, overlay: &Overlay, screen: (f32, f32)) -> OverlayBuffers {
+        let upload = |label: &str, vertices: &[ColorVertex]| {
+            (!vertices.is_empty()).then(|| (
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: bytemuck::cast_slice(vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }),
+                vertices.len() as u32,
+            ))
+        };
+        let hud: Vec<ColorVertex> = overlay.hud.iter()
+            .map(|v| ColorVertex {
+                position: [v.position[0] / screen.0 * 2.0 - 1.0, 1.0 - v.position[1] / screen.1 * 2.0, 0.0],
+                color: v.color,
+            })
+            .collect();
+        OverlayBuffers {
+            triangles: upload("Overlay Triangles", &overlay.triangles),
+            lines: upload("Overlay Lines", &overlay.lines),
+            hud: upload("Overlay HUD", &hud),
+        }
+    }
+
+    /// Draws into a pass that has the world's depth buffer attached
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, buffers: &'a OverlayBuffers, camera_bind_group: &'a wgpu::BindGroup) {
+        pass.set_bind_group(0, camera_bind_group, &[]);
+        for (pipeline, buffer) in [
+            (&self.triangle_pipeline, &buffers.triangles),
+            (&self.line_pipeline, &buffers.lines),
+            (&self.hud_pipeline, &buffers.hud),
+        ] {
+            if let Some((buffer, count)) = buffer {
+                pass.set_pipeline(pipeline);
+                pass.set_vertex_buffer(0, buffer.slice(..));
+                pass.draw(0..*count, 0..1);
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Short-lived particles for effects such as explosions.
+//!
+//! Particles are drawn as small boxes. Where the GPU can simulate them the system only
+//! collects new ones for GpuParticlePass; elsewhere it simulates them on the CPU and draws
+//! them through the overlay, which is plenty for the bursts the game spawns.
+
+use glam::Vec3;
+
+use crate::engine::graphics::overlay::{Color, Overlay};
+use crate::engine::math::{Aabb, Frustum, Rng};
+
+/// Particles kept at once; the oldest are dropped beyond this
+pub const MAX_PARTICLES: usize = 2048;
+pub(crate) const PARTICLE_GRAVITY: f32 = 6.0;
+/// Fraction of velocity kept per second
+pub(crate) const PARTICLE_DRAG: f32 = 0.2;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Particle {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    /// Seconds left to live
+    pub life: f32,
+    pub size: f32,
+    pub color: Color,
+}
+
+impl Particle {
+    pub fn bounds(&self) -> Aabb {
+        Aabb::from_center(self.position, Vec3::splat(self.size * 0.5))
+    }
+}
+
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    /// Spawned since the GPU last took them, while it simulates them
+    spawned: Vec<Particle>,
+    offloaded: bool,
+    rng: Rng,
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self {
+        Self { particles: Vec::new(), spawned: Vec::new(), offloaded: false, rng: Rng::new(0) }
+    }
+
+    /// Hands the simulation to the GPU: new particles wait for take_spawned instead of being
+    /// simulated and drawn here. Particles already here are dropped.
+    pub fn set_offloaded(&mut self, offloaded: bool) {
+        self.offloaded = offloaded;
+        self.particles.clear();
+        self.spawned.clear();
+    }
+
+    pub fn is_offloaded(&self) -> bool {
+        self.offloaded
+    }
+
+    /// The particles spawned since the last call, for the GPU to simulate
+    pub fn take_spawned(&mut self) -> Vec<Particle> {
+        std::mem::take(&mut self.spawned)
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn spawn(&mut self, particle: Particle) {
+        if self.offloaded {
+            self.spawned.push(particle);
+            return;
+        }
+        if self.particles.len() >= MAX_PARTICLES {
+            self.particles.remove(0);
+        }
+        self.particles.push(particle);
+    }
+
+    /// `count` particles thrown outwards from `center` at up to `speed` blocks per second
+    pub fn burst(&mut self, center: Vec3, count: usize, speed: f32, color: Color) {
+        for _ in 0..count {
+            let direction = Vec3::new(
+                self.rng.next_f32() * 2.0 - 1.0,
+                self.rng.next_f32() * 2.0 - 1.0,
+                self.rng.next_f32() * 2.0 - 1.0,
+            )
+            .normalize_or_zero();
+            let particle = Particle {
+                position: center,
+                velocity: direction * speed * (0.3 + 0.7 * self.rng.next_f32()),
+                life: 0.5 + self.rng.next_f32(),
+                size: 0.1 + 0.15 * self.rng.next_f32(),
+                color,
+            };
+            self.spawn(particle);
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        let drag = PARTICLE_DRAG.powf(delta_time);
+        for particle in &mut self.particles {
+            particle.velocity.y -= PARTICLE_GRAVITY * delta_time;
+            particle.velocity *= drag;
+            particle.position += particle.velocity * delta_time;
+            particle.life -= delta_time;
+        }
+        self.particles.retain(|p| p.life > 0.0);
+    }
+
+    /// Draws the particles `frustum` may see and returns how many were drawn
+    pub fn draw(&self, overlay: &mut Overlay, frustum: &Frustum) -> usize {
+        let culled = frustum.cull(&self.particles, |particle| particle.bounds());
+        for particle in &culled.visible {
+            let bounds = particle.bounds();
+            overlay.add_box(bounds.min, bounds.max, particle.color);
+        }
+        culled.visible.len()
+    }
+}
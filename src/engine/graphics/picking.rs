@@ -0,0 +1,319 @@
+//! GPU picking through an ID buffer.
+//!
+//! The scene is drawn again into a single R32Uint pixel, using a projection that blows the
+//! requested screen pixel up to fill it, so each pick costs one tiny pass and a 4 byte
+//! readback. Unlike a raycast this picks exactly what the depth test leaves visible.
+
+use std::borrow::Cow;
+use glam::Mat4;
+use wgpu::util::DeviceExt;
+
+use crate::engine::graphics::renderer::{CAMERA_ORIGIN_OFFSET, CAMERA_RELATIVE_VIEW_PROJ_OFFSET, CAMERA_UNIFORM_SIZE};
+use crate::engine::graphics::vertex::{BlockFaceInstance, Vertex, CUBE_INDICES, CUBE_VERTICES};
+use crate::engine::math::Aabb;
+use crate::engine::shaders;
+use crate::game::entity::EntityId;
+use crate::game::world::camera::Camera;
+
+/// Set on ids of boxes; the rest of the id is the box's index
+const BOX_ID_FLAG: u32 = 1 << 31;
+/// Copies to buffers must use rows of this many bytes
+const READBACK_ROW_BYTES: u32 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+const ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickTarget {
+    Nothing,
+    /// A block and the face (0-5, as in BlockFaceInstance) under the cursor
+    Block { block: (i32, i32, i32), face: u32 },
+    Entity(EntityId),
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct BoxInstance {
+    center: [f32; 3],
+    half_extents: [f32; 3],
+    id: u32,
+}
+
+impl BoxInstance {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: &[wgpu::VertexAttribute] = &wgpu::vertex_attr_array![
+            3 => Float32x3,
+            4 => Float32x3,
+            5 => Uint32,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<BoxInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: ATTRIBUTES,
+        }
+    }
+}
+
+pub struct PickPass {
+    face_pipeline: wgpu::RenderPipeline,
+    box_pipeline: wgpu::RenderPipeline,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    id_texture: wgpu::Texture,
+    depth_texture: wgpu::Texture,
+    readback: wgpu::Buffer,
+    quad_vertices: wgpu::Buffer,
+    quad_indices: wgpu::Buffer,
+    cube_vertices: wgpu::Buffer,
+    cube_indices: wgpu::Buffer,
+}
+
+fn one_pixel_texture(device: &wgpu::Device, label: &str, format: wgpu::TextureFormat, usage: wgpu::TextureUsages) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage,
+        view_formats: &[],
+    })
+}
+
+/// Maps the pixel at `pixel` of a `screen` sized view to the whole of clip space
+fn pixel_projection(pixel: (f32, f32), screen: (f32, f32)) -> Mat4 {
+    let center_x = 2.0 * (pixel.0 + 0.5) / screen.0 - 1.0;
+    let center_y = 1.0 - 2.0 * (pixel.1 + 0.5) / screen.1;
+    Mat4::from_cols(
+        glam::Vec4::new(screen.0, 0.0, 0.0, 0.0),
+        glam::Vec4::new(0.0, screen.1, 0.0, 0.0),
+        glam::Vec4::new(0.0, 0.0, 1.0, 0.0),
+        glam::Vec4::new(-center_x * screen.0, -center_y * screen.1, 0.0, 1.0),
+    )
+}
+
+impl PickPass {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Pick Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shaders::preprocess(shaders::PICK_SHADER, &[]).expect("pick shader"))),
+        });
+        let camera_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Pick Camera Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: std::num::NonZeroU64::new(CAMERA_UNIFORM_SIZE),
+                },
+                count: None,
+            }],
+        });
+        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Pick Camera Buffer"),
+            size: CAMERA_UNIFORM_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Pick Camera Bind Group"),
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: camera_buffer.as_entire_binding() }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Pick Pipeline Layout"),
+            bind_group_layouts: &[&camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |label: &str, entry_point: &str, instances: wgpu::VertexBufferLayout| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point,
+                    buffers: &[Vertex::desc(), instances],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: ID_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let face_pipeline = pipeline("Pick Face Pipeline", "vs_face", BlockFaceInstance::desc());
+        let box_pipeline = pipeline("Pick Box Pipeline", "vs_box", BoxInstance::desc());
+
+        // Same quad as the world pass
+        let quad = [
+            Vertex { position: [-0.5, -0.5, 0.0], tex_coords: [0.0, 0.0], texture_index: 0 },
+            Vertex { position: [ 0.5, -0.5, 0.0], tex_coords: [1.0, 0.0], texture_index: 0 },
+            Vertex { position: [ 0.5,  0.5, 0.0], tex_coords: [1.0, 1.0], texture_index: 0 },
+            Vertex { position: [-0.5,  0.5, 0.0], tex_coords: [0.0, 1.0], texture_index: 0 },
+        ];
+        let buffer = |label: &str, contents: &[u8], usage| device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage,
+        });
+        Self {
+            face_pipeline,
+            box_pipeline,
+            camera_buffer,
+            camera_bind_group,
+            id_texture: one_pixel_texture(device, "Pick ID Texture", ID_FORMAT,
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC),
+            depth_texture: one_pixel_texture(device, "Pick Depth Texture", wgpu::TextureFormat::Depth32Float,
+                wgpu::TextureUsages::RENDER_ATTACHMENT),
+            readback: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Pick Readback Buffer"),
+                size: READBACK_ROW_BYTES as u64,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            quad_vertices: buffer("Pick Quad Vertices", bytemuck::cast_slice(&quad), wgpu::BufferUsages::VERTEX),
+            quad_indices: buffer("Pick Quad Indices", bytemuck::cast_slice(&[0u16, 1, 2, 2, 3, 0]), wgpu::BufferUsages::INDEX),
+            cube_vertices: buffer("Pick Cube Vertices", bytemuck::cast_slice(CUBE_VERTICES), wgpu::BufferUsages::VERTEX),
+            cube_indices: buffer("Pick Cube Indices", bytemuck::cast_slice(CUBE_INDICES), wgpu::BufferUsages::INDEX),
+        }
+    }
+
+    /// Finds what is drawn at `pixel` (physical pixels from the top left) of a `screen` sized
+    /// view. Blocks come from `faces`; `boxes` are entity bounds. Waits for the GPU, so keep
+    /// this to tools and debugging rather than every frame.
+    #[allow(clippy::too_many_arguments)]
+    pub fn pick(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        pixel: (f32, f32),
+        screen: (f32, f32),
+        faces: &[BlockFaceInstance],
+        boxes: &[(EntityId, Aabb)],
+    ) -> PickTarget {
+        if screen.0 < 1.0 || screen.1 < 1.0 || faces.len() as u32 >= BOX_ID_FLAG {
+            return PickTarget::Nothing;
+        }
+        let (pixel_proj, aspect) = (pixel_projection(pixel, screen), screen.0 / screen.1);
+        let view_proj = pixel_proj * camera.view_proj_mat(aspect);
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&view_proj.to_cols_array()));
+        let relative_view_proj = pixel_proj * camera.relative_view_proj_mat(aspect);
+        queue.write_buffer(&self.camera_buffer, CAMERA_ORIGIN_OFFSET, bytemuck::cast_slice(&camera.render_origin().extend(0).to_array()));
+        queue.write_buffer(&self.camera_buffer, CAMERA_RELATIVE_VIEW_PROJ_OFFSET, bytemuck::cast_slice(&relative_view_proj.to_cols_array()));
+
+        let face_buffer = (!faces.is_empty()).then(|| device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Pick Face Instances"),
+            contents: bytemuck::cast_slice(faces),
+            usage: wgpu::BufferUsages::VERTEX,
+        }));
+        let box_instances: Vec<BoxInstance> = boxes.iter().enumerate().map(|(i, (_, aabb))| BoxInstance {
+            center: ((aabb.min + aabb.max) * 0.5).to_array(),
+            half_extents: ((aabb.max - aabb.min) * 0.5).to_array(),
+            id: BOX_ID_FLAG | i as u32,
+        }).collect();
+        let box_buffer = (!box_instances.is_empty()).then(|| device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Pick Box Instances"),
+            contents: bytemuck::cast_slice(&box_instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        }));
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Pick Encoder") });
+        {
+            let id_view = self.id_texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let depth_view = self.depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Pick Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &id_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT), store: wgpu::StoreOp::Store },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(1.0), store: wgpu::StoreOp::Discard }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            if let Some(face_buffer) = &face_buffer {
+                pass.set_pipeline(&self.face_pipeline);
+                pass.set_vertex_buffer(0, self.quad_vertices.slice(..));
+                pass.set_vertex_buffer(1, face_buffer.slice(..));
+                pass.set_index_buffer(self.quad_indices.slice(..), wgpu::IndexFormat::Uint16);
+                pass.draw_indexed(0..6, 0, 0..faces.len() as u32);
+            }
+            if let Some(box_buffer) = &box_buffer {
+                pass.set_pipeline(&self.box_pipeline);
+                pass.set_vertex_buffer(0, self.cube_vertices.slice(..));
+                pass.set_vertex_buffer(1, box_buffer.slice(..));
+                pass.set_index_buffer(self.cube_indices.slice(..), wgpu::IndexFormat::Uint16);
+                pass.draw_indexed(0..CUBE_INDICES.len() as u32, 0, 0..box_instances.len() as u32);
+            }
+        }
+        encoder.copy_texture_to_buffer(
+            self.id_texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback,
+                layout: wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(READBACK_ROW_BYTES), rows_per_image: None },
+            },
+            wgpu::Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let id = self.read_id(device);
+        match id {
+            None | Some(0) => PickTarget::Nothing,
+            Some(id) if id & BOX_ID_FLAG != 0 => boxes.get((id & !BOX_ID_FLAG) as usize)
+                .map_or(PickTarget::Nothing, |(entity, _)| PickTarget::Entity(*entity)),
+            Some(id) => faces.get(id as usize - 1).map_or(PickTarget::Nothing, |face| PickTarget::Block {
+                block: (face.position[0], face.position[1], face.position[2]),
+                face: face.face,
+            }),
+        }
+    }
+
+    fn read_id(&self, device: &wgpu::Device) -> Option<u32> {
+        let slice = self.readback.slice(..4);
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            tx.send(result).ok();
+        });
+        device.poll(wgpu::Maintain::Wait);
+        match rx.try_recv() {
+            Ok(Ok(())) => {
+                let id = {
+                    let data = slice.get_mapped_range();
+                    u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+                };
+                self.readback.unmap();
+                Some(id)
+            }
+            Ok(Err(_)) => None,
+            Err(_) => {
+                // Still pending, which happens where poll cannot block (the web); cancel it
+                self.readback.unmap();
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! World shader pipelines, built once per permutation.
+//!
+//! Each PipelineKey picks a variant of the world shader through its feature defines, plus
+//! the blend and depth state of its pass. Materials that share a key share the pipeline.
+//! Changing the surface format rebuilds every pipeline built so far.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::engine::graphics::vertex::{BlockFaceInstance, Vertex};
+use crate::engine::shaders;
+
+/// How a pipeline's output is combined with what is already drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pass {
+    /// Writes depth and replaces the color
+    Opaque,
+    /// Blends over what is behind, without writing depth
+    Translucent,
+}
+
+/// One permutation of the world shader and its pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub pass: Pass,
+    /// Discards texels below half alpha, for cut-out textures such as ladders
+    pub alpha_test: bool,
+    /// Lights surfaces through the normal map atlas rather than only their face's normal
+    pub normal_maps: bool,
+}
+
+impl PipelineKey {
+    /// Solid and cut-out blocks
+    pub const OPAQUE: Self = Self { pass: Pass::Opaque, alpha_test: true, normal_maps: true };
+    /// Water
+    pub const TRANSLUCENT: Self = Self { pass: Pass::Translucent, alpha_test: false, normal_maps: true };
+
+    /// Feature flags for the shader preprocessor
+    fn defines(&self) -> Vec<&'static str> {
+        let mut defines = Vec::new();
+        if self.alpha_test {
+            defines.push("ALPHA_TEST");
+        }
+        if self.normal_maps {
+            defines.push("NORMAL_MAPS");
+        }
+        if self.pass == Pass::Translucent {
+            defines.push("TRANSLUCENT");
+        }
+        defines
+    }
+}
+
+/// How a block's faces are drawn. Meshes group their faces by it, so each material's
+/// pipeline is bound once a frame and its ranges drawn across every chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RenderMaterial {
+    /// Fully opaque textures, which need no alpha test
+    Solid,
+    /// Textures with holes, such as ladders and crops
+    Cutout,
+    /// Lava, which is opaque
+    Fluid,
+    /// Water, blended over what is behind it once everything opaque is drawn
+    Translucent,
+}
+
+impl RenderMaterial {
+    /// Every material, in the order they are drawn
+    pub const ALL: [Self; 4] = [RenderMaterial::Solid, RenderMaterial::Cutout, RenderMaterial::Fluid, RenderMaterial::Translucent];
+
+    /// Whether its faces must be drawn back to front, see render_queue.rs
+    pub fn is_translucent(&self) -> bool {
+        matches!(self, RenderMaterial::Translucent)
+    }
+
+    /// The pipeline drawing this material
+    pub fn key(&self, normal_maps: bool) -> PipelineKey {
+        if self.is_translucent() {
+            return PipelineKey { normal_maps, ..PipelineKey::TRANSLUCENT };
+        }
+        let alpha_test = matches!(self, RenderMaterial::Cutout);
+        PipelineKey { alpha_test, normal_maps, ..PipelineKey::OPAQUE }
+    }
+}
+
+pub struct PipelineCache {
+    layout: wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+}
+
+impl PipelineCache {
+    /// A cache for pipelines drawing to `format`, with the camera, atlas and normal map
+    /// bind groups
+    pub fn new(device: &wgpu::Device, bind_group_layouts: &[&wgpu::BindGroupLayout], format: wgpu::TextureFormat) -> Self {
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+        Self { layout, format, pipelines: HashMap::new() }
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// The pipeline for `key`, if it has been built
+    pub fn get(&self, key: PipelineKey) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines.get(&key)
+    }
+
+    /// The pipeline for `key`, building it the first time it is asked for
+    pub fn get_or_create(&mut self, device: &wgpu::Device, key: PipelineKey) -> &wgpu::RenderPipeline {
+        if !self.pipelines.contains_key(&key) {
+            let pipeline = self.build(device, key);
+            self.pipelines.insert(key, pipeline);
+        }
+        &self.pipelines[&key]
+    }
+
+    /// Switches to drawing to `format`, rebuilding the pipelines built so far
+    pub fn set_format(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        if format == self.format {
+            return;
+        }
+        self.format = format;
+        let keys: Vec<PipelineKey> = self.pipelines.keys().copied().collect();
+        for key in keys {
+            let pipeline = self.build(device, key);
+            self.pipelines.insert(key, pipeline);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.is_empty()
+    }
+
+    fn build(&self, device: &wgpu::Device, key: PipelineKey) -> wgpu::RenderPipeline {
+        let source = shaders::preprocess(crate::engine::assets::embedded::WORLD_SHADER, &key.defines())
+            .unwrap_or_else(|e| panic!("world shader {:?}: {}", key, e));
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+        let (blend, depth_write_enabled) = match key.pass {
+            Pass::Opaque => (wgpu::BlendState::REPLACE, true),
+            Pass::Translucent => (wgpu::BlendState::ALPHA_BLENDING, false),
+        };
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("Render Pipeline {:?}", key)),
+            layout: Some(&self.layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc(), BlockFaceInstance::desc()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: self.format,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+}
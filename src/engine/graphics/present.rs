@@ -0,0 +1,181 @@
+//! Present pass: copies frames drawn offscreen to the surface, tonemapping HDR frames,
+//! encoding sRGB for surfaces that store linear values and scaling up frames drawn below
+//! the surface's resolution, see hdr.rs, color.rs and upscale.rs.
+
+use std::borrow::Cow;
+use wgpu::util::DeviceExt;
+
+use crate::engine::graphics::hdr::{HdrSettings, PresentParams};
+use crate::engine::shaders;
+
+pub struct PresentPass {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    /// What the world is drawn into instead of the surface
+    frame: wgpu::Texture,
+    frame_view: wgpu::TextureView,
+    params: wgpu::Buffer,
+    nearest: wgpu::Sampler,
+    linear: wgpu::Sampler,
+    bind_group: wgpu::BindGroup,
+}
+
+impl PresentPass {
+    /// Draws frames of `render_format` and `size` to a `surface_format` surface
+    pub fn new(device: &wgpu::Device, render_format: wgpu::TextureFormat, surface_format: wgpu::TextureFormat, size: (u32, u32)) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Present Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline = Self::pipeline(device, &bind_group_layout, surface_format);
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Present Params"),
+            contents: bytemuck::cast_slice(&[PresentParams::new(&HdrSettings::default(), render_format, surface_format)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let sampler = |label, filter| device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        });
+        let (nearest, linear) = (sampler("Present Nearest Sampler", wgpu::FilterMode::Nearest), sampler("Present Linear Sampler", wgpu::FilterMode::Linear));
+        let (frame, frame_view, bind_group) = Self::frame(device, &bind_group_layout, &params, [&nearest, &linear], render_format, size);
+        Self { pipeline, bind_group_layout, frame, frame_view, params, nearest, linear, bind_group }
+    }
+
+    fn pipeline(device: &wgpu::Device, bind_group_layout: &wgpu::BindGroupLayout, surface_format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Present Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shaders::preprocess(shaders::PRESENT_SHADER, &[]).expect("present shader"))),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Present Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Present Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn frame(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        params: &wgpu::Buffer,
+        samplers: [&wgpu::Sampler; 2],
+        format: wgpu::TextureFormat,
+        size: (u32, u32),
+    ) -> (wgpu::Texture, wgpu::TextureView, wgpu::BindGroup) {
+        let frame = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Present Frame"),
+            size: wgpu::Extent3d { width: size.0.max(1), height: size.1.max(1), depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = frame.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Present Bind Group"),
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+                wgpu::BindGroupEntry { binding: 1, resource: params.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(samplers[0]) },
+                wgpu::BindGroupEntry { binding: 3, resource: wgpu::BindingResource::Sampler(samplers[1]) },
+            ],
+        });
+        (frame, view, bind_group)
+    }
+
+    /// Where to draw the frame before presenting it
+    pub fn target(&self) -> &wgpu::TextureView {
+        &self.frame_view
+    }
+
+    /// Keeps the frame the size the world is drawn at
+    pub fn resize(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        let current = self.frame.size();
+        if (current.width, current.height) == size {
+            return;
+        }
+        let samplers = [&self.nearest, &self.linear];
+        (self.frame, self.frame_view, self.bind_group) = Self::frame(device, &self.bind_group_layout, &self.params, samplers, self.frame.format(), size);
+    }
+
+    /// Sets how the next frames are presented
+    pub fn prepare(&self, queue: &wgpu::Queue, params: &PresentParams) {
+        queue.write_buffer(&self.params, 0, bytemuck::cast_slice(&[*params]));
+    }
+
+    /// Copies the frame to `surface`, encoded and scaled up
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, surface: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Present Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: surface,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
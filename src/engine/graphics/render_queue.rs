@@ -0,0 +1,63 @@
+//! Back-to-front ordering for what is blended over the world.
+//!
+//! Translucent faces don't write depth, so they only blend right drawn farthest first.
+//! Render sections are queued by their distance from the eye, which orders them well
+//! enough where they don't overlap. Around the eye that breaks down, since the faces of its
+//! own chunk lie on every side of it, so those are sorted one by one instead.
+
+use glam::{IVec3, Vec3};
+
+use crate::engine::graphics::vertex::BlockFaceInstance;
+use crate::game::world::chunk::{FACE_AXES, FLAT_FACE_BASE};
+
+/// Draws collected over a frame, handed back farthest first
+pub struct RenderQueue<T> {
+    items: Vec<(f32, T)>,
+}
+
+impl<T> Default for RenderQueue<T> {
+    fn default() -> Self {
+        Self { items: Vec::new() }
+    }
+}
+
+impl<T> RenderQueue<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues `item`, `distance` from the eye. Any measure works as long as it grows with
+    /// distance, such as its square.
+    pub fn push(&mut self, distance: f32, item: T) {
+        self.items.push((distance, item));
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Everything queued, farthest first; equally far items keep the order they came in
+    pub fn back_to_front(mut self) -> impl Iterator<Item = T> {
+        self.items.sort_by(|a, b| b.0.total_cmp(&a.0));
+        self.items.into_iter().map(|(_, item)| item)
+    }
+}
+
+/// Middle of a face in the world. Cube faces sit half a block out from their block's
+/// center; flat models and plants are taken at the center.
+pub fn face_center(face: &BlockFaceInstance) -> Vec3 {
+    let center = IVec3::from(face.position).as_vec3();
+    match FACE_AXES.get(face.face as usize) {
+        Some([normal, _, _]) if face.face < FLAT_FACE_BASE => center + IVec3::new(normal.0, normal.1, normal.2).as_vec3() * 0.5,
+        _ => center,
+    }
+}
+
+/// Orders `faces` farthest from `eye` first
+pub fn sort_faces_back_to_front(faces: &mut [BlockFaceInstance], eye: Vec3) {
+    faces.sort_by(|a, b| face_center(b).distance_squared(eye).total_cmp(&face_center(a).distance_squared(eye)));
+}
@@ -1,16 +1,148 @@
-use std::borrow::Cow;
 use wgpu;
 use wgpu::util::DeviceExt;
 use crate::engine::graphics::{vertex::Vertex, texture::Texture};
 use crate::game::world::camera::Camera;
-use glam::{Vec3, Mat4, Vec4};
+use glam::{Mat4, Vec3};
 use crate::engine::graphics::vertex::BlockFaceInstance;
+use crate::engine::graphics::picking::{PickPass, PickTarget};
+use crate::engine::graphics::overlay::{Overlay, OverlayPass};
+use crate::engine::graphics::clouds::{CloudPass, Clouds};
+use crate::engine::graphics::far_terrain::{FarTerrainPass, FarVertex};
+use crate::engine::graphics::decals::{DecalPass, DecalVertex};
+use crate::engine::graphics::gpu_particles::{self, GpuParticlePass};
+use crate::engine::graphics::night_sky::NightSkyPass;
+use crate::engine::graphics::particles::Particle;
+use crate::engine::graphics::normal_map::NormalAtlas;
+use crate::engine::graphics::pipeline_cache::{PipelineCache, RenderMaterial};
+use crate::engine::graphics::render_queue::{self, RenderQueue};
+use crate::engine::graphics::color::{self, ColorAudit, ColorRole};
+use crate::engine::graphics::present::PresentPass;
+use crate::engine::graphics::hdr::{hdr_surface_format, HdrSettings, PresentParams, HDR_FORMAT};
+use crate::engine::graphics::taa::{AntiAliasing, TaaPass};
+use crate::engine::graphics::upscale::{self, UpscaleSettings};
+use crate::engine::time::FrameTime;
+
+/// The world shader's camera uniform: the view-projection matrix, then daylight, wetness
+/// and the frame's simulation and real time, the direction to the sun, the eye position and the fog color and distance,
+/// each padded to a vec4, then the render origin and the view-projection relative to it
+pub(crate) const CAMERA_UNIFORM_SIZE: u64 = 208;
+const CAMERA_SKY_OFFSET: u64 = 64;
+const CAMERA_SUN_OFFSET: u64 = 80;
+const CAMERA_EYE_OFFSET: u64 = 96;
+const CAMERA_FOG_OFFSET: u64 = 112;
+pub(crate) const CAMERA_ORIGIN_OFFSET: u64 = 128;
+pub(crate) const CAMERA_RELATIVE_VIEW_PROJ_OFFSET: u64 = 144;
+
+/// Color of the void below the world, which the sky fades into on the way down
+const VOID_COLOR: [f32; 3] = [0.02, 0.01, 0.04];
+/// Blocks below the world floor at which the sky is all void
+const VOID_FADE_DEPTH: f32 = 32.0;
+/// Share of the fog distance lost deep in the void
+const VOID_FOG_CLOSE: f32 = 0.5;
+
+/// How far an eye at `height` is into the void, from 0 at the world floor to 1 at
+/// VOID_FADE_DEPTH below it
+fn void_fade(height: f32) -> f32 {
+    ((crate::game::world::chunk_manager::WORLD_FLOOR - height) / VOID_FADE_DEPTH).clamp(0.0, 1.0)
+}
+
+/// How the sky looks and how brightly the world is lit this frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sky {
+    pub color: [f32; 3],
+    /// 0 at night to 1 during the day
+    pub daylight: f32,
+    /// Unit vector toward the sun
+    pub sun_direction: [f32; 3],
+    /// How wet the rain has left surfaces, 0 dry to 1 soaked
+    pub wetness: f32,
+    /// The moon's phase, 0 full to 0.5 new; it sits opposite the sun
+    pub moon_phase: f32,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self { color: [0.1, 0.2, 0.3], daylight: 1.0, sun_direction: [0.0, 0.94, 0.33], wetness: 0.0, moon_phase: 0.0 }
+    }
+}
+
+/// Where a frame is drawn: color and depth views of the same `size`
+pub(crate) struct FrameTarget<'a> {
+    pub color: &'a wgpu::TextureView,
+    pub depth: &'a wgpu::TextureView,
+    pub size: (u32, u32),
+}
+
+/// A rectangle of the target, in pixels from its top left corner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    pub fn full(size: (u32, u32)) -> Self {
+        Self { x: 0, y: 0, width: size.0, height: size.1 }
+    }
+
+    /// The `index`th of `count` split-screen regions of a `size` target. Two players split
+    /// along the longer side, so each keeps a sensible aspect; three or four get quarters.
+    pub fn split(size: (u32, u32), count: usize, index: usize) -> Self {
+        let (width, height) = size;
+        match count {
+            0 | 1 => Self::full(size),
+            2 if width >= height => {
+                let half = width / 2;
+                Self { x: half * index as u32, y: 0, width: if index == 0 { half } else { width - half }, height }
+            }
+            2 => {
+                let half = height / 2;
+                Self { x: 0, y: half * index as u32, width, height: if index == 0 { half } else { height - half } }
+            }
+            _ => {
+                let (half_width, half_height) = (width / 2, height / 2);
+                let (column, row) = (index as u32 % 2, index as u32 / 2);
+                Self {
+                    x: half_width * column,
+                    y: half_height * row,
+                    width: if column == 0 { half_width } else { width - half_width },
+                    height: if row == 0 { half_height } else { height - half_height },
+                }
+            }
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The same share of a `to` sized target as this is of a `from` sized one
+    pub fn scaled(&self, from: (u32, u32), to: (u32, u32)) -> Self {
+        let scale = |n: u32, from: u32, to: u32| (n as f32 * to as f32 / from.max(1) as f32).round() as u32;
+        let (x, y) = (scale(self.x, from.0, to.0), scale(self.y, from.1, to.1));
+        let (right, bottom) = (scale(self.x + self.width, from.0, to.0), scale(self.y + self.height, from.1, to.1));
+        Self { x, y, width: right - x, height: bottom - y }
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+}
+
+/// One camera's view of the world and the HUD drawn over it
+pub struct View<'a> {
+    pub camera: &'a Camera,
+    pub viewport: Viewport,
+    pub overlay: &'a Overlay,
+}
 
 pub struct Renderer {
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
-    pub render_pipeline: wgpu::RenderPipeline,
+    pub pipelines: PipelineCache,
     pub camera_buffer: wgpu::Buffer,
     pub camera_bind_group: wgpu::BindGroup,
     pub camera_bind_group_layout: wgpu::BindGroupLayout,
@@ -18,6 +150,40 @@ pub struct Renderer {
     // Occlusion culling support
     pub depth_pyramid: wgpu::Texture,
     pub depth_pyramid_mip_levels: u32,
+    pub pick_pass: PickPass,
+    pub overlay_pass: OverlayPass,
+    /// Set before each frame
+    pub sky: Sky,
+    /// Set before each frame, for shaders that animate
+    pub time: FrameTime,
+    /// Reported in frame captures
+    pub adapter_info: wgpu::AdapterInfo,
+    pub normal_atlas: NormalAtlas,
+    /// Whether blocks are lit through their normal maps or only by their face's direction
+    pub normal_maps: bool,
+    pub cloud_pass: CloudPass,
+    pub night_sky_pass: NightSkyPass,
+    /// Set before each frame
+    pub clouds: Clouds,
+    pub far_terrain_pass: FarTerrainPass,
+    pub decal_pass: DecalPass,
+    /// None where the adapter cannot simulate particles, which are then drawn through the
+    /// overlay
+    pub gpu_particles: Option<GpuParticlePass>,
+    /// Encodes frames for surfaces that only store linear values; None where the surface
+    /// is drawn to directly
+    pub present: Option<PresentPass>,
+    /// Change through set_hdr
+    pub hdr: HdrSettings,
+    /// What the surface can be configured with
+    surface_formats: Vec<wgpu::TextureFormat>,
+    /// Resolves jittered frames while TAA is on, see set_anti_aliasing
+    taa: Option<TaaPass>,
+    /// Change through set_upscale
+    pub upscale: UpscaleSettings,
+    /// Blocks this far away are lost in fog of the sky's color, hiding the edge of the
+    /// loaded world; 0 for no fog
+    pub fog_distance: f32,
 }
 
 impl Renderer {
@@ -28,12 +194,10 @@ impl Renderer {
         adapter: &wgpu::Adapter,
         size: winit::dpi::PhysicalSize<u32>,
         texture: &crate::engine::graphics::texture::Texture,
+        normal_atlas: NormalAtlas,
     ) -> Self {
         let surface_caps = surface.get_capabilities(adapter);
-        let surface_format = surface_caps.formats.iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
+        let surface_format = color::choose_surface_format(&surface_caps.formats).unwrap_or(surface_caps.formats[0]);
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -47,21 +211,16 @@ impl Renderer {
         };
         // Don't configure surface here - it's already configured in the app
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../shaders/shader.wgsl"))),
-        });
-
         // Camera setup
         let camera_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Camera Bind Group Layout"),
             entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
-                    min_binding_size: std::num::NonZeroU64::new(64),
+                    min_binding_size: std::num::NonZeroU64::new(CAMERA_UNIFORM_SIZE),
                 },
                 count: None,
             }],
@@ -69,11 +228,15 @@ impl Renderer {
 
         let camera = Camera::new();
         let camera_view_proj = camera.create_view_proj(size.width as f32 / size.height as f32);
-        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Camera Buffer"),
-            contents: bytemuck::cast_slice(&[camera_view_proj]),
+            size: CAMERA_UNIFORM_SIZE,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
         });
+        queue.write_buffer(&camera_buffer, 0, bytemuck::cast_slice(&[camera_view_proj]));
+        queue.write_buffer(&camera_buffer, CAMERA_SKY_OFFSET, bytemuck::cast_slice(&[1.0f32, 0.0, 0.0, 0.0]));
+        queue.write_buffer(&camera_buffer, CAMERA_SUN_OFFSET, bytemuck::cast_slice(&[0.0f32, 1.0, 0.0, 0.0]));
 
         let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Camera Bind Group"),
@@ -84,55 +247,19 @@ impl Renderer {
             }],
         });
 
-        // Pipeline layout with camera and texture
-        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[&camera_bind_group_layout, &texture.bind_group_layout],
-            push_constant_ranges: &[],
-        });
+        // Everything is drawn in render_format, which differs from the surface's only
+        // where the present pass has to tonemap or encode sRGB by hand
+        let hdr = HdrSettings::default();
+        let render_format = Self::render_format_for(&hdr, config.format);
+        let upscale = UpscaleSettings::default();
+        let present = Self::present_pass_for(&device, &hdr, &upscale, render_format, config.format, (size.width, size.height));
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc(), BlockFaceInstance::desc()],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
+        // World pipelines, with camera, texture and normal maps
+        let mut pipelines = PipelineCache::new(&device,
+            &[&camera_bind_group_layout, &texture.bind_group_layout, &normal_atlas.bind_group_layout], render_format);
+        for material in RenderMaterial::ALL {
+            pipelines.get_or_create(&device, material.key(true));
+        }
 
         // Create depth texture
         let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
@@ -145,7 +272,8 @@ impl Renderer {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            // Read back by the TAA resolve
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
             label: Some("Depth Texture"),
             view_formats: &[],
         });
@@ -167,17 +295,192 @@ impl Renderer {
             view_formats: &[],
         });
 
+        let pick_pass = PickPass::new(&device);
+        let overlay_pass = OverlayPass::new(&device, &camera_bind_group_layout, render_format);
+        let cloud_pass = CloudPass::new(&device, &camera_bind_group_layout, render_format);
+        let far_terrain_pass = FarTerrainPass::new(&device, &camera_bind_group_layout, render_format);
+        let night_sky_pass = NightSkyPass::new(&device, render_format);
+        let decal_pass = DecalPass::new(&device, &queue, &camera_bind_group_layout, render_format);
+        let gpu_particles = gpu_particles::is_supported(adapter)
+            .then(|| GpuParticlePass::new(&device, &camera_bind_group_layout, render_format));
+
         Self {
             device,
             queue,
             config,
-            render_pipeline,
+            pipelines,
             camera_buffer,
             camera_bind_group,
             camera_bind_group_layout,
             depth_texture,
             depth_pyramid,
             depth_pyramid_mip_levels,
+            pick_pass,
+            overlay_pass,
+            sky: Sky::default(),
+            time: FrameTime::default(),
+            adapter_info: adapter.get_info(),
+            normal_atlas,
+            normal_maps: true,
+            cloud_pass,
+            night_sky_pass,
+            clouds: Clouds::default(),
+            far_terrain_pass,
+            decal_pass,
+            gpu_particles,
+            present,
+            hdr,
+            surface_formats: surface_caps.formats,
+            taa: None,
+            upscale,
+            fog_distance: 0.0,
+        }
+    }
+
+    /// The HDR frame while HDR is on, otherwise whatever `surface` needs
+    fn render_format_for(hdr: &HdrSettings, surface: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        if hdr.enabled { HDR_FORMAT } else { color::render_format(surface) }
+    }
+
+    /// A present pass where frames are not drawn to the surface directly, drawn at `size`
+    fn present_pass_for(
+        device: &wgpu::Device,
+        hdr: &HdrSettings,
+        upscale: &UpscaleSettings,
+        render: wgpu::TextureFormat,
+        surface: wgpu::TextureFormat,
+        size: (u32, u32),
+    ) -> Option<PresentPass> {
+        (hdr.enabled || upscale.is_scaled() || color::needs_manual_gamma(surface)).then(|| PresentPass::new(device, render, surface, size))
+    }
+
+    /// The size the world is drawn at, which the present pass scales up to the surface's
+    pub fn render_size(&self) -> (u32, u32) {
+        upscale::scaled_size((self.config.width, self.config.height), self.upscale.scale)
+    }
+
+    /// Draws the world at `upscale`'s share of the window from the next frame on
+    pub fn set_upscale(&mut self, upscale: UpscaleSettings) {
+        let presented = self.present.is_some();
+        self.upscale = upscale;
+        if Self::present_pass_for(&self.device, &self.hdr, &upscale, self.render_format(), self.config.format, (1, 1)).is_some() != presented {
+            self.rebuild_for_format(self.config.format);
+        }
+        self.resize_targets();
+    }
+
+    /// Whether frames go out through an HDR surface
+    pub fn hdr_output(&self) -> bool {
+        self.render_format() == HDR_FORMAT && self.config.format == HDR_FORMAT
+    }
+
+    /// Applies `hdr`, switching to the display's HDR surface format where it offers one,
+    /// otherwise to an ordinary one tonemapped to
+    pub fn set_hdr(&mut self, hdr: HdrSettings, surface: &wgpu::Surface) {
+        let toggled = hdr.enabled != self.hdr.enabled;
+        self.hdr = hdr;
+        let sdr_formats: Vec<_> = self.surface_formats.iter().copied().filter(|&f| f != HDR_FORMAT).collect();
+        let format = hdr_surface_format(&self.surface_formats)
+            .filter(|_| hdr.enabled)
+            .or_else(|| color::choose_surface_format(&sdr_formats))
+            .unwrap_or(self.config.format);
+        if toggled && format == self.config.format {
+            self.rebuild_for_format(format);
+        } else {
+            self.set_surface_format(format, surface);
+        }
+    }
+
+    fn taa_pass(&self, format: wgpu::TextureFormat) -> TaaPass {
+        let depth = self.depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        TaaPass::new(&self.device, format, self.render_size(), &depth)
+    }
+
+    pub fn anti_aliasing(&self) -> AntiAliasing {
+        if self.taa.is_some() { AntiAliasing::Taa } else { AntiAliasing::Off }
+    }
+
+    /// Smooths edges with `mode` from the next frame on
+    pub fn set_anti_aliasing(&mut self, mode: AntiAliasing) {
+        if mode == self.anti_aliasing() {
+            return;
+        }
+        self.taa = (mode == AntiAliasing::Taa).then(|| self.taa_pass(self.render_format()));
+    }
+
+    /// The format everything is drawn in, see color.rs
+    pub fn render_format(&self) -> wgpu::TextureFormat {
+        self.pipelines.format()
+    }
+
+    /// How color is encoded on its way from `atlas` and the other textures to the surface
+    pub fn color_audit(&self, atlas: &Texture) -> ColorAudit {
+        ColorAudit::new(self.config.format, self.render_format(), self.present.is_some(), &[
+            ("Block atlas", atlas.texture.format(), ColorRole::Color),
+            ("Normal maps", self.normal_atlas.texture.format(), ColorRole::Data),
+            ("Decal atlas", self.decal_pass.atlas_format(), ColorRole::Color),
+        ])
+    }
+
+    /// Draws to `format` from now on, e.g. after the window moved to a display that wants
+    /// another format, rebuilding every pipeline that targets the surface
+    pub fn set_surface_format(&mut self, format: wgpu::TextureFormat, surface: &wgpu::Surface) {
+        if format == self.config.format {
+            return;
+        }
+        self.config.format = format;
+        surface.configure(&self.device, &self.config);
+        self.rebuild_for_format(format);
+    }
+
+    /// Rebuilds every pipeline that draws the frame, for a `surface_format` surface
+    fn rebuild_for_format(&mut self, surface_format: wgpu::TextureFormat) {
+        let format = Self::render_format_for(&self.hdr, surface_format);
+        self.present = Self::present_pass_for(&self.device, &self.hdr, &self.upscale, format, surface_format, self.render_size());
+        if format == self.pipelines.format() {
+            return;
+        }
+        if self.taa.is_some() {
+            self.taa = Some(self.taa_pass(format));
+        }
+        self.pipelines.set_format(&self.device, format);
+        self.overlay_pass = OverlayPass::new(&self.device, &self.camera_bind_group_layout, format);
+        self.cloud_pass = CloudPass::new(&self.device, &self.camera_bind_group_layout, format);
+        self.far_terrain_pass.set_format(&self.device, &self.camera_bind_group_layout, format);
+        self.night_sky_pass = NightSkyPass::new(&self.device, format);
+        self.decal_pass.set_format(&self.device, &self.camera_bind_group_layout, format);
+        if let Some(particles) = &mut self.gpu_particles {
+            particles.set_format(&self.device, &self.camera_bind_group_layout, format);
+        }
+    }
+
+    /// Replaces the textures decals are drawn with
+    pub fn set_decal_atlas(&mut self, atlas: &image::RgbaImage) {
+        self.decal_pass.set_atlas(&self.device, &self.queue, atlas);
+    }
+
+    /// Replaces the decals drawn over the world, see decals.rs
+    pub fn set_decals(&mut self, vertices: &[DecalVertex]) {
+        self.decal_pass.upload(&self.device, vertices);
+    }
+
+    /// Steps the GPU particles `delta` seconds after adding `spawned`; call once a frame
+    pub fn update_particles(&mut self, delta: f32, spawned: &[Particle]) {
+        if let Some(particles) = &mut self.gpu_particles {
+            particles.simulate(&self.device, &self.queue, delta, spawned);
+        }
+    }
+
+    /// Replaces the far terrain imposters, see far_terrain.rs
+    pub fn set_far_terrain(&mut self, vertices: &[FarVertex]) {
+        self.far_terrain_pass.upload(&self.device, vertices);
+    }
+
+    /// Turns normal mapping on or off, building its pipelines the first time they are needed
+    pub fn set_normal_maps(&mut self, on: bool) {
+        self.normal_maps = on;
+        for material in RenderMaterial::ALL {
+            self.pipelines.get_or_create(&self.device, material.key(on));
         }
     }
 
@@ -186,22 +489,7 @@ impl Renderer {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             surface.configure(&self.device, &self.config);
-            
-            // Recreate depth texture
-            self.depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-                size: wgpu::Extent3d {
-                    width: new_size.width,
-                    height: new_size.height,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Depth32Float,
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-                label: Some("Depth Texture"),
-                view_formats: &[],
-            });
+            self.resize_targets();
 
             // Recreate depth pyramid
             let new_mip_levels = (new_size.width.max(new_size.height) as f32).log2().ceil() as u32;
@@ -223,40 +511,32 @@ impl Renderer {
         }
     }
 
-    fn aabb_in_frustum(aabb_min: Vec3, aabb_max: Vec3, frustum_planes: &[Vec4; 6]) -> bool {
-        // Test AABB against all 6 frustum planes
-        for plane in frustum_planes.iter() {
-            // For each plane, compute the positive vertex (furthest in normal direction)
-            let p = Vec3::new(
-                if plane.x >= 0.0 { aabb_max.x } else { aabb_min.x },
-                if plane.y >= 0.0 { aabb_max.y } else { aabb_min.y },
-                if plane.z >= 0.0 { aabb_max.z } else { aabb_min.z },
-            );
-            // If the positive vertex is outside the plane, the box is outside
-            if plane.x * p.x + plane.y * p.y + plane.z * p.z + plane.w < 0.0 {
-                return false;
-            }
+    /// Keeps everything the world is drawn into at the render size
+    fn resize_targets(&mut self) {
+        let size = self.render_size();
+        if let Some(present) = &mut self.present {
+            present.resize(&self.device, size);
         }
-        true
-    }
 
-    fn extract_frustum_planes(mat: &Mat4) -> [Vec4; 6] {
-        // Extract frustum planes from a projection-view matrix (in world space)
-        let m = mat.to_cols_array_2d();
-        [
-            // Left
-            Vec4::new(m[0][3] + m[0][0], m[1][3] + m[1][0], m[2][3] + m[2][0], m[3][3] + m[3][0]),
-            // Right
-            Vec4::new(m[0][3] - m[0][0], m[1][3] - m[1][0], m[2][3] - m[2][0], m[3][3] - m[3][0]),
-            // Bottom
-            Vec4::new(m[0][3] + m[0][1], m[1][3] + m[1][1], m[2][3] + m[2][1], m[3][3] + m[3][1]),
-            // Top
-            Vec4::new(m[0][3] - m[0][1], m[1][3] - m[1][1], m[2][3] - m[2][1], m[3][3] - m[3][1]),
-            // Near
-            Vec4::new(m[0][3] + m[0][2], m[1][3] + m[1][2], m[2][3] + m[2][2], m[3][3] + m[3][2]),
-            // Far
-            Vec4::new(m[0][3] - m[0][2], m[1][3] - m[1][2], m[2][3] - m[2][2], m[3][3] - m[3][2]),
-        ]
+        // Recreate depth texture
+        self.depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            label: Some("Depth Texture"),
+            view_formats: &[],
+        });
+        if let Some(taa) = &mut self.taa {
+            let depth = self.depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+            taa.resize(&self.device, size, &depth);
+        }
     }
 
     fn calculate_chunk_distance(chunk_pos: Vec3, camera_pos: Vec3) -> f32 {
@@ -337,26 +617,165 @@ impl Renderer {
         true
     }
 
-    pub fn render(
+    /// What is drawn at a pixel of `viewport`, counted from its corner, blocks hidden by
+    /// others excluded. `boxes` are the bounds of entities that should be pickable.
+    pub fn pick(
         &self,
+        camera: &Camera,
+        viewport: Viewport,
+        pixel: (f32, f32),
+        chunks: &[&crate::game::world::chunk::Chunk],
+        boxes: &[(crate::game::entity::EntityId, crate::engine::math::Aabb)],
+    ) -> PickTarget {
+        let faces: Vec<BlockFaceInstance> = chunks.iter()
+            .flat_map(|chunk| chunk.block_face_instances.iter().copied())
+            .collect();
+        let screen = (viewport.width as f32, viewport.height as f32);
+        self.pick_pass.pick(&self.device, &self.queue, camera, pixel, screen, &faces, boxes)
+    }
+
+    /// Presents a single cleared frame, so the window shows something while the rest of
+    /// startup finishes
+    pub fn present_splash(device: &wgpu::Device, queue: &wgpu::Queue, surface: &wgpu::Surface) -> Result<(), wgpu::SurfaceError> {
+        let frame = surface.get_current_texture()?;
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Splash Encoder"),
+        });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Splash Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 }),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        queue.submit(std::iter::once(encoder.finish()));
+        frame.present();
+        Ok(())
+    }
+
+    pub fn render(
+        &mut self,
         surface: &wgpu::Surface,
         camera: &Camera,
         texture: &Texture,
         chunks: &[&crate::game::world::chunk::Chunk],
         chunk_manager: &crate::game::world::chunk_manager::ChunkManager,
+        overlay: &Overlay,
+    ) -> Result<(), wgpu::SurfaceError> {
+        let view = View { camera, viewport: Viewport::full((self.config.width, self.config.height)), overlay };
+        self.render_views(surface, &[view], texture, chunks, chunk_manager)
+    }
+
+    /// Draws each view into its own region of one frame, e.g. for split-screen
+    pub fn render_views(
+        &mut self,
+        surface: &wgpu::Surface,
+        views: &[View],
+        texture: &Texture,
+        chunks: &[&crate::game::world::chunk::Chunk],
+        chunk_manager: &crate::game::world::chunk_manager::ChunkManager,
     ) -> Result<(), wgpu::SurfaceError> {
         let frame = surface.get_current_texture()?;
-        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Render Encoder"),
-        });
+        let surface_view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let color = self.present.as_ref().map_or(&surface_view, |present| present.target());
+        let depth = self.depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let target = FrameTarget { color, depth: &depth, size: self.render_size() };
+        // The history covers the whole frame, so split views are drawn without it
+        let taa = self.taa.as_ref().filter(|_| views.len() == 1);
+        // A window a pixel wide splits into an empty half, which wgpu refuses as a viewport
+        for (index, view) in views.iter().filter(|view| view.viewport.width > 0 && view.viewport.height > 0).enumerate() {
+            // Views share the camera uniforms, so each is submitted before the next
+            // overwrites them
+            let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("View Encoder"),
+            });
+            self.encode_view(&mut encoder, &target, view, index == 0, taa, texture, chunks, chunk_manager);
+            self.queue.submit(std::iter::once(encoder.finish()));
+        }
+        if let Some(taa) = &mut self.taa {
+            match views {
+                [view] => taa.advance(view.camera.relative_view_proj_mat(view.viewport.aspect()), view.camera.render_origin()),
+                _ => taa.reset(),
+            }
+        }
+        if let Some(present) = &self.present {
+            present.prepare(&self.queue, &PresentParams::new(&self.hdr, self.render_format(), self.config.format).upscaled(&self.upscale));
+            let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Present Encoder"),
+            });
+            present.draw(&mut encoder, &surface_view);
+            self.queue.submit(std::iter::once(encoder.finish()));
+        }
+        frame.present();
+        Ok(())
+    }
 
+    /// Records a frame of the world into `target`, which must have the render format
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn encode_frame(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &FrameTarget,
+        camera: &Camera,
+        texture: &Texture,
+        chunks: &[&crate::game::world::chunk::Chunk],
+        chunk_manager: &crate::game::world::chunk_manager::ChunkManager,
+        overlay: &Overlay,
+    ) {
+        let view = View { camera, viewport: Viewport::full((self.config.width, self.config.height)), overlay };
+        self.encode_view(encoder, target, &view, true, None, texture, chunks, chunk_manager);
+    }
+
+    /// Records `view` into its viewport of `target`, scaled from the window's size to the
+    /// target's. The first view of a frame clears the whole target; later ones leave
+    /// everything outside their viewport as it was. With
+    /// `taa` the world is drawn jittered into its input and resolved into `target`, and the
+    /// overlay drawn on top afterwards.
+    #[allow(clippy::too_many_arguments)]
+    fn encode_view(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &FrameTarget,
+        view: &View,
+        clear: bool,
+        taa: Option<&TaaPass>,
+        texture: &Texture,
+        chunks: &[&crate::game::world::chunk::Chunk],
+        chunk_manager: &crate::game::world::chunk_manager::ChunkManager,
+    ) {
+        let (camera, viewport, overlay) = (view.camera, view.viewport, view.overlay);
         // Update camera buffer
-        let aspect = self.config.width as f32 / self.config.height as f32;
-        let view_proj = camera.create_view_proj(aspect);
+        let aspect = viewport.aspect();
+        let jitter = taa.map_or(Mat4::IDENTITY, |taa| taa.jitter(target.size));
+        let view_proj = (jitter * camera.view_proj_mat(aspect)).to_cols_array_2d();
         self.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[view_proj]));
+        // Chunk faces are drawn relative to the block the eye is in, so far from the origin
+        // they don't jitter
+        self.queue.write_buffer(&self.camera_buffer, CAMERA_ORIGIN_OFFSET, bytemuck::cast_slice(&camera.render_origin().extend(0).to_array()));
+        let relative_view_proj = camera.relative_view_proj_mat(aspect);
+        self.queue.write_buffer(&self.camera_buffer, CAMERA_RELATIVE_VIEW_PROJ_OFFSET, bytemuck::cast_slice(&(jitter * relative_view_proj).to_cols_array()));
+        if let Some(taa) = taa {
+            taa.prepare(&self.queue, relative_view_proj, camera.render_origin());
+        }
+        self.queue.write_buffer(&self.camera_buffer, CAMERA_SKY_OFFSET, bytemuck::cast_slice(&[self.sky.daylight, self.sky.wetness, self.time.simulation, self.time.real]));
+        let sun = Vec3::from(self.sky.sun_direction).normalize_or_zero().extend(0.0);
+        self.queue.write_buffer(&self.camera_buffer, CAMERA_SUN_OFFSET, bytemuck::cast_slice(&sun.to_array()));
+        self.queue.write_buffer(&self.camera_buffer, CAMERA_EYE_OFFSET, bytemuck::cast_slice(&camera.position.extend(1.0).to_array()));
+        // Below the world the sky darkens into the void and the fog closes in
+        let void = void_fade(camera.position.y);
+        let sky_color = Vec3::from(self.sky.color).lerp(Vec3::from(VOID_COLOR), void);
+        let fog = sky_color.extend(self.fog_distance * (1.0 - VOID_FOG_CLOSE * void));
+        self.queue.write_buffer(&self.camera_buffer, CAMERA_FOG_OFFSET, bytemuck::cast_slice(&fog.to_array()));
         let view_proj_mat = camera.view_proj_mat(aspect);
-        let frustum_planes = Renderer::extract_frustum_planes(&view_proj_mat);
+        let frustum = camera.frustum(aspect);
         
         // Calculate camera forward vector
         let (sy, cy) = camera.yaw.sin_cos();
@@ -369,12 +788,14 @@ impl Renderer {
             let max = min + Vec3::splat(crate::game::world::chunk::CHUNK_SIZE as f32);
             
             // Frustum culling
-            if !Renderer::aabb_in_frustum(min, max, &frustum_planes) {
+            if !frustum.intersects_box(min, max) {
                 return false;
             }
             
-            // Occlusion culling
-            if Renderer::is_chunk_occluded(chunk.position, crate::game::world::chunk::CHUNK_SIZE as f32, camera.position, camera_forward) {
+            // Occlusion culling; an orthographic view sees behind its position too, which the
+            // frustum planes already account for
+            if !camera.is_orthographic()
+                && Renderer::is_chunk_occluded(chunk.position, crate::game::world::chunk::CHUNK_SIZE as f32, camera.position, camera_forward) {
                 return false;
             }
             
@@ -423,27 +844,50 @@ impl Renderer {
             usage: wgpu::BufferUsages::INDEX,
         });
 
+        // The translucent faces of the chunk the eye is in, sorted on their own
+        let eye_chunk = crate::game::world::chunk_manager::ChunkManager::chunk_key_at(camera.position);
+        let in_eye_chunk = |chunk: &crate::game::world::chunk::Chunk| crate::game::world::chunk_manager::ChunkManager::chunk_key(chunk.origin()) == eye_chunk;
+        let mut eye_faces: Vec<BlockFaceInstance> = visible_chunks.iter()
+            .filter(|chunk| in_eye_chunk(chunk))
+            .flat_map(|chunk| chunk.sections.iter().flat_map(|section| section.materials.iter())
+                .filter(|(material, _)| material.is_translucent())
+                .flat_map(|(_, range)| &chunk.block_face_instances[range.start as usize..range.end as usize]))
+            .copied()
+            .collect();
+        render_queue::sort_faces_back_to_front(&mut eye_faces, camera.position);
+        let eye_faces_buffer = (!eye_faces.is_empty()).then(|| self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sorted Translucent Instance Buffer"),
+            contents: bytemuck::cast_slice(&eye_faces),
+            usage: wgpu::BufferUsages::VERTEX,
+        }));
+
+        self.cloud_pass.prepare(&self.queue, &self.clouds);
+        self.night_sky_pass.prepare(&self.queue, &view_proj_mat, &self.sky);
+        let overlay_buffers = self.overlay_pass.prepare(&self.device, overlay,
+            (viewport.width as f32, viewport.height as f32));
+        let sky_color = wgpu::Color {
+            r: sky_color.x as f64,
+            g: sky_color.y as f64,
+            b: sky_color.z as f64,
+            a: 1.0,
+        };
+
         {
-            let depth_view = self.depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: taa.map_or(target.color, TaaPass::input),
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
+                        load: if clear { wgpu::LoadOp::Clear(sky_color) } else { wgpu::LoadOp::Load },
                         store: wgpu::StoreOp::Store,
                     },
                 })],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &depth_view,
+                    view: target.depth,
                     depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
+                        // Viewports do not overlap, so one clear serves them all
+                        load: if clear { wgpu::LoadOp::Clear(1.0) } else { wgpu::LoadOp::Load },
                         store: wgpu::StoreOp::Store,
                     }),
                     stencil_ops: None,
@@ -452,21 +896,82 @@ impl Renderer {
                 occlusion_query_set: None,
             });
 
-            render_pass.set_pipeline(&self.render_pipeline);
+            let Viewport { x, y, width, height } = viewport.scaled((self.config.width, self.config.height), target.size);
+            render_pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+            render_pass.set_scissor_rect(x, y, width, height);
+            self.night_sky_pass.draw(&mut render_pass, &self.sky);
             render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
             render_pass.set_bind_group(1, &texture.bind_group, &[]);
-            for chunk in visible_chunks {
-                if let Some(instance_buffer) = &chunk.instance_buffer {
-                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                    render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
-                    render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                    render_pass.draw_indexed(0..6, 0, 0..chunk.block_face_instances.len() as u32);
+            render_pass.set_bind_group(2, &self.normal_atlas.bind_group, &[]);
+            // Sections are culled on their own, so tall chunks don't draw what is out of view
+            let visible_sections: Vec<_> = visible_chunks.iter()
+                .filter_map(|chunk| chunk.instance_buffer.as_ref().map(|buffer| (*chunk, buffer)))
+                .flat_map(|(chunk, buffer)| chunk.sections.iter().map(move |section| (chunk, buffer, section)))
+                .filter(|(_, _, section)| frustum.intersects_box(section.min, section.max))
+                .collect();
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            // Each opaque material's pipeline is bound once, then its ranges drawn front to back
+            for material in RenderMaterial::ALL.into_iter().filter(|material| !material.is_translucent()) {
+                let Some(pipeline) = self.pipelines.get(material.key(self.normal_maps)) else { continue };
+                render_pass.set_pipeline(pipeline);
+                for (_, buffer, section) in &visible_sections {
+                    for (_, range) in section.materials.iter().filter(|(m, _)| *m == material) {
+                        render_pass.set_vertex_buffer(1, buffer.slice(..));
+                        render_pass.draw_indexed(0..6, 0, range.clone());
+                    }
+                }
+            }
+            self.decal_pass.draw(&mut render_pass, &self.camera_bind_group);
+            self.far_terrain_pass.draw(&mut render_pass, &self.camera_bind_group);
+            if let Some(particles) = &self.gpu_particles {
+                particles.draw(&mut render_pass, &self.camera_bind_group);
+            }
+            // Translucent sections farthest first, then the eye's chunk nearest of all,
+            // switching pipelines only where the material changes
+            let mut translucent = RenderQueue::new();
+            for (_, buffer, section) in visible_sections.iter().filter(|(chunk, _, _)| !in_eye_chunk(chunk)) {
+                let center = (section.min + section.max) * 0.5;
+                for (material, range) in section.materials.iter().filter(|(m, _)| m.is_translucent()) {
+                    translucent.push(center.distance_squared(camera.position), (*material, *buffer, range.clone()));
+                }
+            }
+            if let Some(buffer) = &eye_faces_buffer {
+                translucent.push(0.0, (RenderMaterial::Translucent, buffer, 0..eye_faces.len() as u32));
+            }
+            let mut bound = None;
+            for (material, buffer, range) in translucent.back_to_front() {
+                if bound != Some(material) {
+                    let Some(pipeline) = self.pipelines.get(material.key(self.normal_maps)) else { continue };
+                    render_pass.set_pipeline(pipeline);
+                    bound = Some(material);
                 }
+                render_pass.set_vertex_buffer(1, buffer.slice(..));
+                render_pass.draw_indexed(0..6, 0, range);
+            }
+            self.cloud_pass.draw(&mut render_pass, &self.camera_bind_group, &self.clouds);
+            if taa.is_none() {
+                self.overlay_pass.draw(&mut render_pass, &overlay_buffers, &self.camera_bind_group);
             }
         }
-
-        self.queue.submit(std::iter::once(encoder.finish()));
-        frame.present();
-        Ok(())
+        let Some(taa) = taa else { return };
+        taa.draw(encoder, target.color);
+        // Drawn after the resolve, so text and outlines stay sharp
+        let mut overlay_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Overlay Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target.color,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: target.depth,
+                depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        self.overlay_pass.draw(&mut overlay_pass, &overlay_buffers, &self.camera_bind_group);
     }
 } 
\ No newline at end of file
@@ -0,0 +1,154 @@
+//! High resolution screenshots.
+//!
+//! The frame is drawn again into an offscreen target a whole multiple of the window's size,
+//! read back and written to SCREENSHOT_DIR as a PNG. The scale is lowered until the target
+//! fits the device's texture and buffer limits. HDR frames are tonemapped as they would be
+//! for an SDR display.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::engine::graphics::hdr::{f16_to_f32, PresentParams};
+use crate::engine::graphics::overlay::Overlay;
+use crate::engine::graphics::renderer::{FrameTarget, Renderer};
+use crate::engine::graphics::texture::Texture;
+use crate::game::world::camera::Camera;
+use crate::game::world::chunk::Chunk;
+use crate::game::world::chunk_manager::ChunkManager;
+
+pub const SCREENSHOT_DIR: &str = "screenshots";
+/// Largest multiple of the window size asked for
+pub const MAX_SCALE: u32 = 8;
+const BYTES_PER_PIXEL: u32 = 4;
+const HDR_BYTES_PER_PIXEL: u32 = 8;
+
+/// How the render format's pixels are laid out in the readback
+#[derive(Clone, Copy, PartialEq)]
+enum Layout {
+    Rgba8,
+    Bgra8,
+    Rgba16Float,
+}
+
+/// Draws `camera`'s view at `scale` times the window's resolution and saves it, returning
+/// where it went and its size
+pub fn take(
+    renderer: &Renderer,
+    camera: &Camera,
+    texture: &Texture,
+    chunks: &[&Chunk],
+    chunk_manager: &ChunkManager,
+    scale: u32,
+) -> io::Result<(PathBuf, (u32, u32))> {
+    let format = renderer.render_format();
+    // Pipelines are built for the render format, so the target must match it
+    let layout = match format {
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => Layout::Bgra8,
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => Layout::Rgba8,
+        wgpu::TextureFormat::Rgba16Float => Layout::Rgba16Float,
+        other => return Err(io::Error::other(format!("cannot save screenshots of a {:?} surface", other))),
+    };
+    let bytes_per_pixel = if layout == Layout::Rgba16Float { HDR_BYTES_PER_PIXEL } else { BYTES_PER_PIXEL };
+    let size = fit_size(renderer, scale, bytes_per_pixel);
+    let (width, height) = size;
+    let extent = wgpu::Extent3d { width, height, depth_or_array_layers: 1 };
+    let target_texture = |label: &str, format: wgpu::TextureFormat, usage: wgpu::TextureUsages| {
+        renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        })
+    };
+    let color = target_texture("Screenshot Color", format, wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC);
+    let depth = target_texture("Screenshot Depth", wgpu::TextureFormat::Depth32Float, wgpu::TextureUsages::RENDER_ATTACHMENT);
+    let color_view = color.create_view(&wgpu::TextureViewDescriptor::default());
+    let depth_view = depth.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let row_bytes = padded_row_bytes(width, bytes_per_pixel);
+    let readback = renderer.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Screenshot Readback"),
+        size: row_bytes as u64 * height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = renderer.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Screenshot Encoder"),
+    });
+    let target = FrameTarget { color: &color_view, depth: &depth_view, size };
+    renderer.encode_frame(&mut encoder, &target, camera, texture, chunks, chunk_manager, &Overlay::new());
+    encoder.copy_texture_to_buffer(
+        color.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &readback,
+            layout: wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(row_bytes), rows_per_image: Some(height) },
+        },
+        extent,
+    );
+    renderer.queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = readback.slice(..);
+    let (tx, rx) = crossbeam_channel::bounded(1);
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        tx.send(result).ok();
+    });
+    renderer.device.poll(wgpu::Maintain::Wait);
+    match rx.try_recv() {
+        Ok(Ok(())) => (),
+        Ok(Err(e)) => return Err(io::Error::other(format!("reading the screenshot back failed: {}", e))),
+        Err(_) => return Err(io::Error::other("the screenshot was not ready after waiting")),
+    }
+    let mut pixels = Vec::with_capacity((width * height * BYTES_PER_PIXEL) as usize);
+    // HDR frames are tonemapped and encoded as the present pass would for an SDR surface
+    let params = PresentParams::new(&renderer.hdr, format, wgpu::TextureFormat::Rgba8Unorm);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks_exact(row_bytes as usize) {
+            let row = &row[..(width * bytes_per_pixel) as usize];
+            if layout == Layout::Rgba16Float {
+                pixels.extend(row.chunks_exact(2).map(|half| {
+                    let c = f16_to_f32(u16::from_le_bytes([half[0], half[1]]));
+                    (params.apply(c) * 255.0).round() as u8
+                }));
+            } else {
+                pixels.extend_from_slice(row);
+            }
+        }
+    }
+    readback.unmap();
+    if layout == Layout::Bgra8 {
+        pixels.chunks_exact_mut(BYTES_PER_PIXEL as usize).for_each(|pixel| pixel.swap(0, 2));
+    }
+    // The sky is cleared opaque, so alpha carries nothing worth keeping
+    pixels.chunks_exact_mut(BYTES_PER_PIXEL as usize).for_each(|pixel| pixel[3] = 255);
+
+    let dir = PathBuf::from(SCREENSHOT_DIR);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("photo-{}.png", crate::game::save::backup::timestamp(SystemTime::now())));
+    image::save_buffer(&path, &pixels, width, height, image::ExtendedColorType::Rgba8).map_err(io::Error::other)?;
+    Ok((path, size))
+}
+
+/// Rows copied out of a texture must be padded to COPY_BYTES_PER_ROW_ALIGNMENT
+fn padded_row_bytes(width: u32, bytes_per_pixel: u32) -> u32 {
+    (width * bytes_per_pixel).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// The window's size times the largest scale, up to `scale`, that the device can draw and
+/// read back
+fn fit_size(renderer: &Renderer, scale: u32, bytes_per_pixel: u32) -> (u32, u32) {
+    let limits = renderer.device.limits();
+    let (width, height) = (renderer.config.width.max(1), renderer.config.height.max(1));
+    let fits = |scale: u32| {
+        let (w, h) = (width * scale, height * scale);
+        w.max(h) <= limits.max_texture_dimension_2d && padded_row_bytes(w, bytes_per_pixel) as u64 * h as u64 <= limits.max_buffer_size
+    };
+    let scale = (1..=scale.clamp(1, MAX_SCALE)).rev().find(|&s| fits(s)).unwrap_or(1);
+    (width * scale, height * scale)
+}
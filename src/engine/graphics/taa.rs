@@ -0,0 +1,297 @@
+//! Temporal anti-aliasing.
+//!
+//! Each frame the projection is nudged by a different fraction of a pixel, so over a few
+//! frames every pixel samples the thin block edges that alias at a distance at several
+//! points. The resolve pass blends the new frame into a history of the old ones, finding
+//! where each pixel was last frame from its depth and the camera's motion, and clamps the
+//! history to the colors around the pixel now so that what moved or came into view does
+//! not leave ghosts behind.
+
+use std::borrow::Cow;
+use glam::{IVec3, Mat4, Vec2, Vec3};
+use wgpu::util::DeviceExt;
+
+use crate::engine::shaders;
+
+/// How much of each new frame is blended into the history
+pub const TAA_BLEND: f32 = 0.1;
+/// Frames before the jitter pattern repeats
+pub const JITTER_PHASES: u32 = 8;
+
+/// How edges are smoothed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AntiAliasing {
+    Off,
+    Taa,
+}
+
+impl AntiAliasing {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Self::Off),
+            "taa" => Some(Self::Taa),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Taa => "taa",
+        }
+    }
+}
+
+/// The `index`th number of the Halton sequence in `base`, from 0 to 1
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let (mut result, mut fraction) = (0.0, 1.0);
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// How far frame `frame` is nudged, in pixels from -0.5 to 0.5 each way
+pub fn jitter(frame: u32) -> Vec2 {
+    // Starting at 1 skips the sequence's zero, which would repeat its last phase
+    let index = frame % JITTER_PHASES + 1;
+    Vec2::new(halton(index, 2), halton(index, 3)) - 0.5
+}
+
+/// Shifts everything a view-projection matrix draws by `pixels` in a target of `size`
+pub fn jitter_matrix(pixels: Vec2, size: (u32, u32)) -> Mat4 {
+    let ndc = pixels * 2.0 / Vec2::new(size.0.max(1) as f32, size.1.max(1) as f32);
+    // Clip space is scaled by w, so this moves every depth by the same share of the screen
+    Mat4::from_translation(ndc.extend(0.0))
+}
+
+/// Takes a point in this frame's clip space to where it was in the last frame's. Both
+/// view-projections are relative to their frames' origins, see Camera::render_origin.
+pub fn reprojection(previous: Mat4, previous_origin: IVec3, current: Mat4, current_origin: IVec3) -> Mat4 {
+    let shift = (current_origin - previous_origin).as_vec3();
+    previous * Mat4::from_translation(shift) * current.inverse()
+}
+
+/// Where a pixel at `uv` with `depth` was last frame, given a `reprojection`; the resolve
+/// shader does the same
+pub fn reproject_uv(reprojection: Mat4, uv: Vec2, depth: f32) -> Vec2 {
+    let ndc = Vec3::new(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth);
+    let previous = reprojection.project_point3(ndc);
+    Vec2::new(previous.x * 0.5 + 0.5, 0.5 - previous.y * 0.5)
+}
+
+// Matches TaaParams in taa.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct TaaParams {
+    reprojection: [[f32; 4]; 4],
+    blend: f32,
+    /// 1 when there is no history to blend with
+    reset: f32,
+    _padding: [f32; 2],
+}
+
+/// A frame drawn into by one pass and read by the next
+struct Target {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+pub struct TaaPass {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    params: wgpu::Buffer,
+    /// Where the world is drawn before it is resolved
+    input: Target,
+    histories: [Target; 2],
+    /// Reading `histories[i]` and writing the other
+    bind_groups: [wgpu::BindGroup; 2],
+    /// Which history holds the last resolved frame
+    current: usize,
+    frame: u32,
+    /// The last frame's unjittered relative view-projection and origin
+    previous: Option<(Mat4, IVec3)>,
+}
+
+impl TaaPass {
+    /// Resolves frames of `format` and `size`, read with `depth` as they were drawn
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, size: (u32, u32), depth: &wgpu::TextureView) -> Self {
+        let texture_entry = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture { sample_type, view_dimension: wgpu::TextureViewDimension::D2, multisampled: false },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("TAA Bind Group Layout"),
+            entries: &[
+                texture_entry(0, wgpu::TextureSampleType::Float { filterable: false }),
+                texture_entry(1, wgpu::TextureSampleType::Float { filterable: true }),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                texture_entry(3, wgpu::TextureSampleType::Depth),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("TAA History Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("TAA Params"),
+            contents: bytemuck::cast_slice(&[TaaParams { reprojection: Mat4::IDENTITY.to_cols_array_2d(), blend: 1.0, reset: 1.0, _padding: [0.0; 2] }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let pipeline = Self::pipeline(device, &bind_group_layout, format);
+        let (input, histories, bind_groups) = Self::targets(device, &bind_group_layout, &sampler, &params, format, size, depth);
+        Self { pipeline, bind_group_layout, sampler, params, input, histories, bind_groups, current: 0, frame: 0, previous: None }
+    }
+
+    fn pipeline(device: &wgpu::Device, bind_group_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("TAA Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shaders::preprocess(shaders::TAA_SHADER, &[]).expect("taa shader"))),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("TAA Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let target = Some(wgpu::ColorTargetState { format, blend: None, write_mask: wgpu::ColorWrites::ALL });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("TAA Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                // The resolved frame goes both on toward the screen and into the history
+                targets: &[target.clone(), target],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn targets(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        params: &wgpu::Buffer,
+        format: wgpu::TextureFormat,
+        size: (u32, u32),
+        depth: &wgpu::TextureView,
+    ) -> (Target, [Target; 2], [wgpu::BindGroup; 2]) {
+        let target = |label| {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d { width: size.0.max(1), height: size.1.max(1), depth_or_array_layers: 1 },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            Target { texture, view }
+        };
+        let input = target("TAA Input");
+        let histories = [target("TAA History A"), target("TAA History B")];
+        let bind_group = |history: &Target| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("TAA Bind Group"),
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&input.view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&history.view) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(sampler) },
+                wgpu::BindGroupEntry { binding: 3, resource: wgpu::BindingResource::TextureView(depth) },
+                wgpu::BindGroupEntry { binding: 4, resource: params.as_entire_binding() },
+            ],
+        });
+        let bind_groups = [bind_group(&histories[0]), bind_group(&histories[1])];
+        (input, histories, bind_groups)
+    }
+
+    /// Keeps the frames the target's size, read with the new `depth`. The history starts over.
+    pub fn resize(&mut self, device: &wgpu::Device, size: (u32, u32), depth: &wgpu::TextureView) {
+        let format = self.input.texture.format();
+        (self.input, self.histories, self.bind_groups) =
+            Self::targets(device, &self.bind_group_layout, &self.sampler, &self.params, format, size, depth);
+        self.previous = None;
+    }
+
+    /// Where to draw the world before it is resolved
+    pub fn input(&self) -> &wgpu::TextureView {
+        &self.input.view
+    }
+
+    /// What nudges this frame's view-projection, for a target of `size`
+    pub fn jitter(&self, size: (u32, u32)) -> Mat4 {
+        jitter_matrix(jitter(self.frame), size)
+    }
+
+    /// Sets up the resolve of a frame drawn with the unjittered relative view-projection
+    /// `view_proj` around `origin`
+    pub fn prepare(&self, queue: &wgpu::Queue, view_proj: Mat4, origin: IVec3) {
+        let (reprojection, reset) = match self.previous {
+            Some((previous, previous_origin)) => (reprojection(previous, previous_origin, view_proj, origin), 0.0),
+            None => (Mat4::IDENTITY, 1.0),
+        };
+        let params = TaaParams { reprojection: reprojection.to_cols_array_2d(), blend: TAA_BLEND, reset, _padding: [0.0; 2] };
+        queue.write_buffer(&self.params, 0, bytemuck::cast_slice(&[params]));
+    }
+
+    /// Blends the input into the history and writes the result to `output`
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        let attachment = |view| Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+        });
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("TAA Resolve Pass"),
+            color_attachments: &[attachment(output), attachment(&self.histories[1 - self.current].view)],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Moves on once a frame drawn with `view_proj` around `origin` has been resolved
+    pub fn advance(&mut self, view_proj: Mat4, origin: IVec3) {
+        self.previous = Some((view_proj, origin));
+        self.current = 1 - self.current;
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    /// Forgets the history, for frames that were not resolved through it
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+}
@@ -42,12 +42,21 @@ impl AtlasUVHelper {
             },
             crate::game::world::chunk::BlockType::Dirt => 2, // All faces - dirt
             crate::game::world::chunk::BlockType::Stone => 3, // All faces - stone
+            crate::game::world::chunk::BlockType::Ladder(_) => 4,
+            crate::game::world::chunk::BlockType::Door(door) => if door.upper { 6 } else { 5 },
+            crate::game::world::chunk::BlockType::Wheat(stage) => if stage >= crate::game::world::chunk::WHEAT_MAX_STAGE { 8 } else { 7 },
+            crate::game::world::chunk::BlockType::Water(_) => 9,
+            crate::game::world::chunk::BlockType::Lava(_) => 10,
+            crate::game::world::chunk::BlockType::Fire(_) => 11,
+            crate::game::world::chunk::BlockType::Tnt => 12,
+            crate::game::world::chunk::BlockType::Sign(_) => 13,
+            crate::game::world::chunk::BlockType::Bed => 14,
             crate::game::world::chunk::BlockType::Air => 0, // Should not happen
         };
 
         // Standard face UVs (will be transformed by get_uv_coords)
         let face_uvs = match face_idx {
-            0 | 1 | 2 | 3 | 4 | 5 => [0.0, 1.0], // All faces use the same UV mapping
+            0..=5 => [0.0, 1.0], // All faces use the same UV mapping
             _ => [0.0, 1.0],
         };
 
@@ -357,6 +366,13 @@ impl Texture {
     /// Creates a texture atlas from individual PNG files for maximum performance
     /// This is more performant than texture arrays as it uses a single texture binding
     pub fn create_atlas_from_files(device: &wgpu::Device, queue: &wgpu::Queue, paths: &[&str]) -> Result<Self, Box<dyn std::error::Error>> {
+        let atlas = Self::decode_atlas(paths).map_err(|e| e as Box<dyn std::error::Error>)?;
+        Ok(Self::create_atlas(device, queue, &atlas))
+    }
+
+    /// Decodes the tiles and packs them into an atlas image. Needs no GPU, so it can run on
+    /// another thread while the device is being set up.
+    pub fn decode_atlas<P: AsRef<std::path::Path>>(paths: &[P]) -> Result<image::RgbaImage, Box<dyn std::error::Error + Send + Sync>> {
         if paths.is_empty() {
             error!("No texture paths provided for atlas");
             return Err("No texture paths provided for atlas".into());
@@ -368,13 +384,29 @@ impl Texture {
             let img = image::open(path)?.to_rgba8();
             images.push(img);
         }
+        Self::pack_atlas(images)
+    }
+
+    /// Decodes tiles that were already read into memory, e.g. fetched by the web build
+    pub fn decode_atlas_bytes(tiles: &[Vec<u8>]) -> Result<image::RgbaImage, Box<dyn std::error::Error + Send + Sync>> {
+        if tiles.is_empty() {
+            error!("No texture data provided for atlas");
+            return Err("No texture data provided for atlas".into());
+        }
+        let mut images = Vec::new();
+        for bytes in tiles {
+            images.push(image::load_from_memory(bytes)?.to_rgba8());
+        }
+        Self::pack_atlas(images)
+    }
 
+    fn pack_atlas(images: Vec<image::RgbaImage>) -> Result<image::RgbaImage, Box<dyn std::error::Error + Send + Sync>> {
         // Verify all textures have the same dimensions
         let tile_size = images[0].dimensions();
         for (i, img) in images.iter().enumerate() {
             if img.dimensions() != tile_size {
                 error!("Texture {} has different dimensions: expected {:?}, got {:?}", 
-                    paths[i], tile_size, img.dimensions());
+                    i, tile_size, img.dimensions());
                 return Err("All textures must have the same dimensions".into());
             }
         }
@@ -406,6 +438,13 @@ impl Texture {
             }
         }
 
+        Ok(atlas)
+    }
+
+    /// Uploads an atlas built by decode_atlas
+    pub fn create_atlas(device: &wgpu::Device, queue: &wgpu::Queue, atlas: &image::RgbaImage) -> Self {
+        let (atlas_width, atlas_height) = atlas.dimensions();
+
         // Create wgpu texture from atlas
         let texture_size = wgpu::Extent3d {
             width: atlas_width,
@@ -432,7 +471,7 @@ impl Texture {
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            &atlas,
+            atlas,
             wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: Some(4 * atlas_width),
@@ -491,10 +530,10 @@ impl Texture {
             ],
         });
 
-        Ok(Self {
+        Self {
             texture,
             bind_group,
             bind_group_layout,
-        })
+        }
     }
 } 
\ No newline at end of file
@@ -0,0 +1,81 @@
+//! Render scale and upscaling.
+//!
+//! The world can be drawn at a fraction of the window's resolution, for slow GPUs, and the
+//! present pass stretches it back out. How it stretches is the upscaling mode: nearest
+//! keeps hard pixels, bilinear blends between them, and sharpen blends and then sharpens
+//! with contrast adaptive sharpening in the manner of FidelityFX CAS, putting back some of
+//! the detail blending smeared.
+
+pub const MIN_RENDER_SCALE: f32 = 0.25;
+pub const MAX_RENDER_SCALE: f32 = 1.0;
+/// How hard the sharpen mode sharpens, from 0 to 1
+pub const SHARPNESS: f32 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpscaleMode {
+    Nearest,
+    Bilinear,
+    Sharpen,
+}
+
+impl UpscaleMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nearest" => Some(Self::Nearest),
+            "bilinear" => Some(Self::Bilinear),
+            "sharpen" => Some(Self::Sharpen),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Nearest => "nearest",
+            Self::Bilinear => "bilinear",
+            Self::Sharpen => "sharpen",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UpscaleSettings {
+    /// The share of the window's resolution the world is drawn at
+    pub scale: f32,
+    pub mode: UpscaleMode,
+}
+
+impl Default for UpscaleSettings {
+    fn default() -> Self {
+        Self { scale: MAX_RENDER_SCALE, mode: UpscaleMode::Bilinear }
+    }
+}
+
+impl UpscaleSettings {
+    /// Applies a console setting: each of `args` is either a percentage of the window's
+    /// resolution or an upscaling mode
+    pub fn configure(&mut self, args: &[String]) -> Result<(), String> {
+        let mut settings = *self;
+        for arg in args {
+            if let Some(mode) = UpscaleMode::from_name(arg) {
+                settings.mode = mode;
+            } else {
+                let percent: f32 = arg.trim_end_matches('%').parse()
+                    .map_err(|_| format!("expected a percentage or nearest, bilinear or sharpen, got {}", arg))?;
+                settings.scale = (percent / 100.0).clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE);
+            }
+        }
+        *self = settings;
+        Ok(())
+    }
+
+    /// Whether the world is drawn below the window's resolution
+    pub fn is_scaled(&self) -> bool {
+        self.scale < MAX_RENDER_SCALE
+    }
+}
+
+/// The size the world is drawn at for a window of `size`
+pub fn scaled_size(size: (u32, u32), scale: f32) -> (u32, u32) {
+    let scale = |length: u32| ((length as f32 * scale).round() as u32).clamp(1, length.max(1));
+    (scale(size.0), scale(size.1))
+}
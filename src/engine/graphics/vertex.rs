@@ -17,12 +17,12 @@ impl Vertex {
                 format: wgpu::VertexFormat::Float32x3,
             },
             wgpu::VertexAttribute {
-                offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                offset: std::mem::size_of::<[i32; 3]>() as wgpu::BufferAddress,
                 shader_location: 1,
                 format: wgpu::VertexFormat::Float32x2,
             },
             wgpu::VertexAttribute {
-                offset: (std::mem::size_of::<[f32; 3]>() + std::mem::size_of::<[f32; 2]>()) as wgpu::BufferAddress,
+                offset: (std::mem::size_of::<[i32; 3]>() + std::mem::size_of::<[f32; 2]>()) as wgpu::BufferAddress,
                 shader_location: 2,
                 format: wgpu::VertexFormat::Uint32,
             },
@@ -39,9 +39,15 @@ impl Vertex {
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct BlockFaceInstance {
-    pub position: [f32; 3], // World position of the block
-    pub face: u32,          // Face index (0-5)
+    /// Block the face belongs to. Integer, so it stays exact however far from the origin;
+    /// the shader makes it relative to the camera before it becomes a float.
+    pub position: [i32; 3],
+    pub face: u32,          // Face index (0-5), FLAT_FACE_BASE + face for flat models, or CROSS_FACE_BASE + 0/1 for plants
     pub block_type: u32,    // Block type/texture index
+    /// Biome color at each corner, RGB565 packed two to a word, see biome::TintGrid
+    pub tint: [u32; 2],
+    /// Brightness at each corner, a byte each, see LightVolume::face_corners
+    pub light: u32,
 }
 
 impl BlockFaceInstance {
@@ -50,18 +56,28 @@ impl BlockFaceInstance {
             wgpu::VertexAttribute {
                 offset: 0,
                 shader_location: 3,
-                format: wgpu::VertexFormat::Float32x3,
+                format: wgpu::VertexFormat::Sint32x3,
             },
             wgpu::VertexAttribute {
-                offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                offset: std::mem::size_of::<[i32; 3]>() as wgpu::BufferAddress,
                 shader_location: 4,
                 format: wgpu::VertexFormat::Uint32,
             },
             wgpu::VertexAttribute {
-                offset: (std::mem::size_of::<[f32; 3]>() + std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+                offset: (std::mem::size_of::<[i32; 3]>() + std::mem::size_of::<u32>()) as wgpu::BufferAddress,
                 shader_location: 5,
                 format: wgpu::VertexFormat::Uint32,
             },
+            wgpu::VertexAttribute {
+                offset: (std::mem::size_of::<[i32; 3]>() + 2 * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+                shader_location: 6,
+                format: wgpu::VertexFormat::Uint32x2,
+            },
+            wgpu::VertexAttribute {
+                offset: (std::mem::size_of::<[i32; 3]>() + 4 * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+                shader_location: 7,
+                format: wgpu::VertexFormat::Uint32,
+            },
         ];
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<BlockFaceInstance>() as wgpu::BufferAddress,
@@ -0,0 +1,117 @@
+//! Keys bound to held actions, and whether each is held down or toggled.
+//!
+//! A Hold action is active while one of its keys is down. A Toggle action switches on and
+//! off with each press instead, for players who find holding a key uncomfortable.
+
+use std::collections::{HashMap, HashSet};
+use winit::keyboard::KeyCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Sprint,
+    Sneak,
+    Zoom,
+}
+
+impl Action {
+    pub const ALL: [Action; 3] = [Action::Sprint, Action::Sneak, Action::Zoom];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Sprint => "sprint",
+            Action::Sneak => "sneak",
+            Action::Zoom => "zoom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    fn default_keys(self) -> &'static [KeyCode] {
+        match self {
+            Action::Sprint => &[KeyCode::ControlLeft, KeyCode::ControlRight],
+            Action::Sneak => &[KeyCode::ShiftLeft, KeyCode::ShiftRight],
+            Action::Zoom => &[KeyCode::KeyC],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ActivationMode {
+    #[default]
+    Hold,
+    Toggle,
+}
+
+impl ActivationMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hold" => Some(ActivationMode::Hold),
+            "toggle" => Some(ActivationMode::Toggle),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub keys: Vec<KeyCode>,
+    pub mode: ActivationMode,
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    bindings: HashMap<Action, Binding>,
+    /// Toggle actions that are switched on
+    toggled: HashSet<Action>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let bindings = Action::ALL.into_iter()
+            .map(|action| (action, Binding { keys: action.default_keys().to_vec(), mode: ActivationMode::Hold }))
+            .collect();
+        Self { bindings, toggled: HashSet::new() }
+    }
+}
+
+impl KeyBindings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn binding(&self, action: Action) -> &Binding {
+        &self.bindings[&action]
+    }
+
+    /// Switching modes starts the action off
+    pub fn set_mode(&mut self, action: Action, mode: ActivationMode) {
+        if let Some(binding) = self.bindings.get_mut(&action) {
+            binding.mode = mode;
+        }
+        self.toggled.remove(&action);
+    }
+
+    /// Call when `key` goes down; repeats from holding it must not be passed on
+    pub fn key_pressed(&mut self, key: KeyCode) {
+        for (action, binding) in &self.bindings {
+            if binding.mode == ActivationMode::Toggle && binding.keys.contains(&key) && !self.toggled.remove(action) {
+                self.toggled.insert(*action);
+            }
+        }
+    }
+
+    /// Switches every toggled action off, e.g. when the window loses focus
+    pub fn clear_toggles(&mut self) {
+        self.toggled.clear();
+    }
+
+    pub fn is_active(&self, action: Action, pressed_keys: &HashSet<KeyCode>) -> bool {
+        let binding = self.binding(action);
+        match binding.mode {
+            ActivationMode::Hold => binding.keys.iter().any(|key| pressed_keys.contains(key)),
+            ActivationMode::Toggle => self.toggled.contains(&action),
+        }
+    }
+}
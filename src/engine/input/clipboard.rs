@@ -0,0 +1,185 @@
+//! Copying and pasting text.
+//!
+//! winit has no clipboard, so the system one is reached through the tools each platform
+//! ships: pbcopy and pbpaste on macOS, clip and PowerShell on Windows, and wl-clipboard,
+//! xclip or xsel elsewhere. Copied text is also kept in the game, so copy and paste work
+//! between the game's own fields when none of those are there, and in the browser.
+//!
+//! The tools can hang, e.g. xclip while the program owning the selection does not answer,
+//! so none of them is waited on for long: copying hands the text over on a thread of its
+//! own, and pasting gives up after PASTE_TIMEOUT.
+
+use std::time::Duration;
+use log::warn;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::engine::time::Instant;
+
+/// Longest a paste waits for the clipboard tool before giving up on it
+pub const PASTE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Where copied text goes and pasted text comes from
+pub trait ClipboardBackend {
+    fn get_text(&mut self) -> Option<String>;
+    /// Returns false if the text could not be copied
+    fn set_text(&mut self, text: &str) -> bool;
+}
+
+/// The system clipboard, through the platform's command line tools
+#[cfg(not(target_arch = "wasm32"))]
+pub struct SystemClipboard;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SystemClipboard {
+    /// Commands that copy from their input, best first
+    fn copy_commands() -> Vec<(&'static str, &'static [&'static str])> {
+        if cfg!(target_os = "macos") {
+            vec![("pbcopy", &[])]
+        } else if cfg!(target_os = "windows") {
+            vec![("clip", &[])]
+        } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            vec![("wl-copy", &[]), ("xclip", &["-selection", "clipboard"]), ("xsel", &["--clipboard", "--input"])]
+        } else {
+            vec![("xclip", &["-selection", "clipboard"]), ("xsel", &["--clipboard", "--input"])]
+        }
+    }
+
+    /// Commands that paste to their output, best first
+    fn paste_commands() -> Vec<(&'static str, &'static [&'static str])> {
+        if cfg!(target_os = "macos") {
+            vec![("pbpaste", &[])]
+        } else if cfg!(target_os = "windows") {
+            vec![("powershell", &["-NoProfile", "-Command", "Get-Clipboard"])]
+        } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            vec![("wl-paste", &["--no-newline"]), ("xclip", &["-selection", "clipboard", "-o"]), ("xsel", &["--clipboard", "--output"])]
+        } else {
+            vec![("xclip", &["-selection", "clipboard", "-o"]), ("xsel", &["--clipboard", "--output"])]
+        }
+    }
+}
+
+/// What running one paste command came to
+#[cfg(not(target_arch = "wasm32"))]
+enum Pasted {
+    Text(String),
+    /// The tool is missing or failed, so the next one may do
+    Failed,
+    /// The tool is there but did not answer in time, and the others would likely not either
+    TimedOut,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SystemClipboard {
+    /// Runs a paste command, reading what it prints on a thread so a full pipe cannot stall
+    /// it, and kills it if it has not finished by PASTE_TIMEOUT
+    fn paste_with(program: &str, args: &[&str]) -> Pasted {
+        use std::io::Read;
+        use std::process::{Command, Stdio};
+        let deadline = Instant::now() + PASTE_TIMEOUT;
+        let Ok(mut child) = Command::new(program).args(args)
+            .stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::null()).spawn() else { return Pasted::Failed };
+        let Some(mut stdout) = child.stdout.take() else { return Pasted::Failed };
+        let (done, output) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let mut text = Vec::new();
+            done.send(stdout.read_to_end(&mut text).map(|_| text)).ok();
+        });
+        let text = output.recv_timeout(deadline.saturating_duration_since(Instant::now()));
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    return match text {
+                        Ok(Ok(text)) if status.success() => Pasted::Text(String::from_utf8_lossy(&text).into_owned()),
+                        _ => Pasted::Failed,
+                    };
+                }
+                Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(5)),
+                _ => {
+                    child.kill().ok();
+                    child.wait().ok();
+                    return Pasted::TimedOut;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ClipboardBackend for SystemClipboard {
+    fn get_text(&mut self) -> Option<String> {
+        for (program, args) in Self::paste_commands() {
+            match Self::paste_with(program, args) {
+                Pasted::Text(text) => return Some(text),
+                Pasted::Failed => continue,
+                Pasted::TimedOut => {
+                    warn!("{} did not answer within {:?}, pasting what was copied in the game", program, PASTE_TIMEOUT);
+                    return None;
+                }
+            }
+        }
+        None
+    }
+
+    /// Hands the text to the first tool that starts, without waiting for it to take it
+    fn set_text(&mut self, text: &str) -> bool {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+        Self::copy_commands().into_iter().any(|(program, args)| {
+            let Ok(mut child) = Command::new(program).args(args)
+                .stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null()).spawn() else { return false };
+            let text = text.to_string();
+            let handed = std::thread::Builder::new().name("clipboard-copy".into()).spawn(move || {
+                // Closing its input tells it the text is complete
+                let written = child.stdin.take().is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+                if !child.wait().is_ok_and(|status| status.success()) || !written {
+                    warn!("Copying to the system clipboard failed");
+                }
+            });
+            handed.is_ok()
+        })
+    }
+}
+
+pub struct Clipboard {
+    backend: Option<Box<dyn ClipboardBackend>>,
+    /// The last text copied, for when the backend has nothing to give
+    local: String,
+    warned: bool,
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clipboard {
+    /// The system clipboard where there is one
+    pub fn new() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let backend: Option<Box<dyn ClipboardBackend>> = Some(Box::new(SystemClipboard));
+        #[cfg(target_arch = "wasm32")]
+        let backend = None;
+        Self { backend, local: String::new(), warned: false }
+    }
+
+    pub fn with_backend(backend: Box<dyn ClipboardBackend>) -> Self {
+        Self { backend: Some(backend), local: String::new(), warned: false }
+    }
+
+    pub fn copy(&mut self, text: &str) {
+        self.local = text.to_string();
+        let copied = self.backend.as_mut().is_some_and(|backend| backend.set_text(text));
+        if !copied && self.backend.is_some() && !self.warned {
+            self.warned = true;
+            warn!("Could not reach the system clipboard; copied text can only be pasted in the game");
+        }
+    }
+
+    /// The clipboard's text, or the last text copied in the game if it has none
+    pub fn paste(&mut self) -> String {
+        let text = self.backend.as_mut().and_then(|backend| backend.get_text()).filter(|text| !text.is_empty());
+        // PowerShell ends what it prints with a line break
+        text.map(|text| text.trim_end_matches(['\r', '\n']).to_string()).unwrap_or_else(|| self.local.clone())
+    }
+}
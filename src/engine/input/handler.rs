@@ -1,22 +1,52 @@
 use std::collections::HashSet;
 use winit::keyboard::KeyCode;
+use winit::dpi::PhysicalPosition;
 use winit::window::{Window, Fullscreen, CursorGrabMode};
-use log::debug;
+use log::{debug, warn};
 
+use crate::engine::input::bindings::{Action, KeyBindings};
+use crate::engine::input::mouse::MouseLook;
+use crate::engine::input::text::TextComposition;
+use crate::engine::input::touch::TouchControls;
+use crate::game::entity::Steering;
+use crate::game::player::physics::MoveInput;
 use crate::game::world::camera::Camera;
 
+/// Where mouse-look deltas come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseInputMode {
+    /// Unaccelerated DeviceEvent::MouseMotion deltas
+    Raw,
+    /// CursorMoved positions, with the cursor re-centered after each event. Follows the
+    /// OS pointer acceleration, and works where raw motion isn't delivered.
+    Window,
+}
+
 pub struct InputHandler {
-    pub mouse_sensitivity: f32,
+    pub mouse: MouseLook,
+    pub bindings: KeyBindings,
     pub movement_speed: f32,
+    pub mouse_mode: MouseInputMode,
+    pub touch: TouchControls,
+    /// Text being composed for the open text field
+    pub composition: TextComposition,
     pressed_keys: HashSet<KeyCode>,
+    cursor_grabbed: bool,
+    last_cursor: Option<PhysicalPosition<f64>>,
 }
 
 impl Default for InputHandler {
     fn default() -> Self {
         Self {
-            mouse_sensitivity: 0.002,
+            mouse: MouseLook::new(),
+            bindings: KeyBindings::new(),
             movement_speed: 0.1,
+            mouse_mode: MouseInputMode::Raw,
+            touch: TouchControls::new(),
+            composition: TextComposition::new(),
             pressed_keys: HashSet::new(),
+            cursor_grabbed: false,
+            last_cursor: None,
         }
     }
 }
@@ -32,7 +62,10 @@ impl InputHandler {
         pressed: bool,
     ) {
         if pressed {
-            self.pressed_keys.insert(keycode);
+            // Held keys repeat; only the first press flips a toggle
+            if self.pressed_keys.insert(keycode) {
+                self.bindings.key_pressed(keycode);
+            }
         } else {
             self.pressed_keys.remove(&keycode);
         }
@@ -65,34 +98,159 @@ impl InputHandler {
         }
 
         if direction != glam::Vec3::ZERO {
-            let norm = direction.normalize();
-            camera.position += norm * self.movement_speed;
+            direction = direction.normalize();
+        }
+        // The touch stick is analog, so it is added after normalizing the keys
+        let stick = self.touch.stick();
+        direction += right * stick.y - forward * stick.x;
+        let direction = direction.clamp_length_max(1.0);
+
+        if direction != glam::Vec3::ZERO {
+            camera.position += direction * self.movement_speed;
             debug!("Camera moved: {:?}", camera.position);
         }
     }
 
-    pub fn handle_mouse_motion(&self, delta: (f64, f64), camera: &mut Camera) {
-        let (delta_x, delta_y) = delta;
-        camera.rotate(
-            delta_x as f32 * self.mouse_sensitivity,
-            -delta_y as f32 * self.mouse_sensitivity,
-        );
+    /// Movement keys and the touch stick as a walking input
+    pub fn walk_input(&self, camera: &Camera) -> MoveInput {
+        use KeyCode::*;
+        let yaw = camera.yaw;
+        let forward = glam::Vec3::new(yaw.sin(), 0.0, -yaw.cos());
+        let right = glam::Vec3::new(yaw.cos(), 0.0, yaw.sin());
+        let mut wish = glam::Vec3::ZERO;
+        if self.pressed_keys.contains(&KeyW) {
+            wish += right;
+        }
+        if self.pressed_keys.contains(&KeyS) {
+            wish -= right;
+        }
+        if self.pressed_keys.contains(&KeyA) {
+            wish += forward;
+        }
+        if self.pressed_keys.contains(&KeyD) {
+            wish -= forward;
+        }
+        let stick = self.touch.stick();
+        let wish = (wish.normalize_or_zero() + right * stick.y - forward * stick.x).clamp_length_max(1.0);
+        MoveInput {
+            wish,
+            forward: self.pressed_keys.contains(&KeyW) || stick.y > 0.5,
+            jump: self.pressed_keys.contains(&Space),
+            sneak: self.is_active(Action::Sneak),
+            sprint: self.is_active(Action::Sprint),
+        }
+    }
+
+    pub fn is_key_pressed(&self, key: KeyCode) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    /// Whether an action is held down, or toggled on
+    pub fn is_active(&self, action: Action) -> bool {
+        self.bindings.is_active(action, &self.pressed_keys)
+    }
+
+    /// Movement keys and the touch stick as steering for a ridden boat: W and S paddle,
+    /// A and D turn
+    pub fn ride_input(&self) -> Steering {
+        use KeyCode::*;
+        let axis = |positive: KeyCode, negative: KeyCode| {
+            self.pressed_keys.contains(&positive) as i32 as f32 - self.pressed_keys.contains(&negative) as i32 as f32
+        };
+        let stick = self.touch.stick();
+        Steering::new(axis(KeyW, KeyS) + stick.y, axis(KeyD, KeyA) + stick.x)
+    }
+
+    /// Gathers a mouse delta, in counts, for the next `update_look`
+    pub fn handle_mouse_motion(&mut self, delta: (f64, f64)) {
+        self.mouse.add_delta(glam::Vec2::new(delta.0 as f32, delta.1 as f32));
+    }
+
+    /// Turns the camera by the mouse movement since the last frame, `dt` real seconds ago
+    pub fn update_look(&mut self, camera: &mut Camera, dt: f32) {
+        self.mouse.update(camera, dt);
     }
 
-    pub fn handle_window_focus(&self, focused: bool, window: Option<&Window>) {
+    pub fn handle_window_focus(&mut self, focused: bool, window: Option<&Window>) {
         if let Some(window) = window {
             if focused {
-                let _ = window.set_cursor_grab(CursorGrabMode::Locked);
-                window.set_cursor_visible(false);
-                debug!("Window focused, cursor locked and hidden");
+                self.grab_cursor(window);
             } else {
-                let _ = window.set_cursor_grab(CursorGrabMode::None);
-                window.set_cursor_visible(true);
-                debug!("Window unfocused, cursor unlocked and visible");
+                self.release_cursor(window);
+                // Sprinting on in a window we cannot see would be a surprise on return
+                self.bindings.clear_toggles();
             }
         }
     }
 
+    /// Lets the input method compose text while a text field is open, or stops it
+    pub fn set_text_input(&mut self, window: &Window, allowed: bool) {
+        if self.composition.set_allowed(allowed) {
+            window.set_ime_allowed(allowed);
+        }
+    }
+
+    /// Locks and hides the cursor for mouse look. Platforms without pointer locking
+    /// (Windows, X11) fall back to confining it to the window.
+    pub fn grab_cursor(&mut self, window: &Window) {
+        let grabbed = window.set_cursor_grab(CursorGrabMode::Locked)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+        if let Err(e) = grabbed {
+            warn!("Failed to grab cursor: {}", e);
+            return;
+        }
+        window.set_cursor_visible(false);
+        self.cursor_grabbed = true;
+        self.last_cursor = None;
+        debug!("Cursor grabbed and hidden");
+    }
+
+    pub fn release_cursor(&mut self, window: &Window) {
+        let _ = window.set_cursor_grab(CursorGrabMode::None);
+        window.set_cursor_visible(true);
+        self.cursor_grabbed = false;
+        self.last_cursor = None;
+        self.mouse.reset();
+        debug!("Cursor released and visible");
+    }
+
+    /// Mouse look only applies while the cursor is grabbed
+    pub fn is_cursor_grabbed(&self) -> bool {
+        self.cursor_grabbed
+    }
+
+    /// Whether raw device motion should drive the camera right now
+    pub fn wants_raw_motion(&self) -> bool {
+        self.cursor_grabbed && self.mouse_mode == MouseInputMode::Raw
+    }
+
+    /// Turns a CursorMoved position into a look delta when in Window mode, re-centering the
+    /// cursor so it never reaches the window edge
+    pub fn handle_cursor_moved(&mut self, position: PhysicalPosition<f64>, window: &Window) {
+        if !self.cursor_grabbed || self.mouse_mode != MouseInputMode::Window {
+            return;
+        }
+        if let Some(last) = self.last_cursor {
+            self.handle_mouse_motion((position.x - last.x, position.y - last.y));
+        }
+        let size = window.inner_size();
+        let center = PhysicalPosition::new(size.width as f64 / 2.0, size.height as f64 / 2.0);
+        // Locked cursors can't be moved on some platforms; then just track the last position
+        self.last_cursor = match window.set_cursor_position(center) {
+            Ok(()) => Some(center),
+            Err(_) => Some(position),
+        };
+    }
+
+    pub fn toggle_mouse_mode(&mut self) {
+        self.mouse_mode = match self.mouse_mode {
+            MouseInputMode::Raw => MouseInputMode::Window,
+            MouseInputMode::Window => MouseInputMode::Raw,
+        };
+        self.last_cursor = None;
+        debug!("Mouse input: {:?}", self.mouse_mode);
+    }
+
     pub fn handle_fullscreen_toggle(&mut self, fullscreen: &mut bool, window: Option<&Window>) {
         if let Some(window) = window {
             if *fullscreen {
@@ -106,8 +264,10 @@ impl InputHandler {
         }
     }
 
+    /// Scales mouse look on both axes, 1 being BASE_SENSITIVITY
     pub fn set_mouse_sensitivity(&mut self, sensitivity: f32) {
-        self.mouse_sensitivity = sensitivity;
+        self.mouse.settings.sensitivity_x = sensitivity;
+        self.mouse.settings.sensitivity_y = sensitivity;
     }
 
     pub fn set_movement_speed(&mut self, speed: f32) {
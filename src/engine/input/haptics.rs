@@ -0,0 +1,185 @@
+//! Controller rumble.
+//!
+//! Game events such as breaking a block, getting hurt or landing hard play a rumble
+//! pattern, and anything else can play a pattern of its own. Patterns that overlap are
+//! mixed by taking the strongest of each motor. The motor strengths go to a RumbleBackend;
+//! there is no gamepad backend yet, so by default they go nowhere, and a backend that
+//! drives real motors only needs to implement the trait.
+
+use log::debug;
+
+/// Patterns playing at once; more push out the oldest
+const MAX_PATTERNS: usize = 8;
+
+/// One step of a pattern: both motors held for a while
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RumblePulse {
+    /// The heavy, low frequency motor, 0 to 1
+    pub low: f32,
+    /// The light, high frequency motor, 0 to 1
+    pub high: f32,
+    /// Seconds
+    pub duration: f32,
+}
+
+/// Pulses played one after another
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RumblePattern {
+    pub pulses: Vec<RumblePulse>,
+}
+
+impl RumblePattern {
+    pub fn pulse(low: f32, high: f32, duration: f32) -> Self {
+        Self::default().then(low, high, duration)
+    }
+
+    /// Adds a pulse after the others; both motors at 0 makes a pause
+    pub fn then(mut self, low: f32, high: f32, duration: f32) -> Self {
+        self.pulses.push(RumblePulse { low: low.clamp(0.0, 1.0), high: high.clamp(0.0, 1.0), duration: duration.max(0.0) });
+        self
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.pulses.iter().map(|pulse| pulse.duration).sum()
+    }
+
+    /// Motor strengths `time` seconds in, None once it is over
+    pub fn at(&self, time: f32) -> Option<(f32, f32)> {
+        let mut start = 0.0;
+        for pulse in &self.pulses {
+            if time < start + pulse.duration {
+                return Some((pulse.low, pulse.high));
+            }
+            start += pulse.duration;
+        }
+        None
+    }
+}
+
+/// Things that happen to the player that can be felt
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HapticEvent {
+    BlockBreak,
+    /// Damage taken, as a fraction of the most health the player can have
+    Damage(f32),
+    /// A hard landing, 0 for the softest that rumbles to 1 for the hardest
+    Landing(f32),
+}
+
+impl HapticEvent {
+    pub fn pattern(&self) -> RumblePattern {
+        match *self {
+            HapticEvent::BlockBreak => RumblePattern::pulse(0.0, 0.4, 0.06),
+            HapticEvent::Damage(fraction) => {
+                let strength = (fraction * 4.0).clamp(0.3, 1.0);
+                RumblePattern::pulse(strength, strength * 0.6, 0.15).then(strength * 0.4, 0.0, 0.15)
+            }
+            HapticEvent::Landing(strength) => {
+                let strength = strength.clamp(0.0, 1.0);
+                RumblePattern::pulse(strength, 0.0, 0.1 + 0.15 * strength)
+            }
+        }
+    }
+}
+
+/// Where the motor strengths go
+pub trait RumbleBackend {
+    /// Whether there is a controller that can rumble
+    fn is_supported(&self) -> bool;
+    fn set_motors(&mut self, low: f32, high: f32);
+}
+
+/// For when there is no controller that can rumble
+pub struct NoRumble;
+
+impl RumbleBackend for NoRumble {
+    fn is_supported(&self) -> bool {
+        false
+    }
+
+    fn set_motors(&mut self, _low: f32, _high: f32) {}
+}
+
+pub struct Haptics {
+    /// Scales every pattern, 0 to 1
+    pub intensity: f32,
+    pub enabled: bool,
+    backend: Box<dyn RumbleBackend>,
+    /// Each pattern with the seconds it has played
+    playing: Vec<(RumblePattern, f32)>,
+    /// Last strengths sent to the backend
+    motors: (f32, f32),
+}
+
+impl Default for Haptics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Haptics {
+    pub fn new() -> Self {
+        Self::with_backend(Box::new(NoRumble))
+    }
+
+    pub fn with_backend(backend: Box<dyn RumbleBackend>) -> Self {
+        Self { intensity: 1.0, enabled: true, backend, playing: Vec::new(), motors: (0.0, 0.0) }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.backend.is_supported()
+    }
+
+    /// Applies a console setting: `off`, `on`, or an intensity percentage
+    pub fn configure(&mut self, setting: &str) -> Result<(), String> {
+        match setting {
+            "off" => self.enabled = false,
+            "on" => self.enabled = true,
+            percent => {
+                let percent: f32 = percent.parse().map_err(|_| format!("expected off, on or a percentage, got {}", percent))?;
+                self.intensity = (percent / 100.0).clamp(0.0, 1.0);
+                self.enabled = true;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn play(&mut self, event: HapticEvent) {
+        self.play_pattern(event.pattern());
+    }
+
+    /// Plays a pattern of the caller's own alongside any others
+    pub fn play_pattern(&mut self, pattern: RumblePattern) {
+        if !self.enabled || self.intensity <= 0.0 || pattern.duration() <= 0.0 {
+            return;
+        }
+        if self.playing.len() >= MAX_PATTERNS {
+            self.playing.remove(0);
+        }
+        self.playing.push((pattern, 0.0));
+    }
+
+    /// Strengths of the motors now, after the intensity
+    pub fn motors(&self) -> (f32, f32) {
+        self.motors
+    }
+
+    /// Tells the backend when the motors change, then moves the patterns on. A pattern is
+    /// felt for at least the frame it was played in, however short.
+    pub fn update(&mut self, delta_time: f32) {
+        let (low, high) = self.playing.iter()
+            .filter_map(|(pattern, time)| pattern.at(*time))
+            .fold((0.0f32, 0.0f32), |(low, high), (l, h)| (low.max(l), high.max(h)));
+        let scale = if self.enabled { self.intensity } else { 0.0 };
+        let motors = (low * scale, high * scale);
+        if motors != self.motors {
+            self.motors = motors;
+            debug!("Rumble {:.2} {:.2}", motors.0, motors.1);
+            self.backend.set_motors(motors.0, motors.1);
+        }
+        for (_, time) in &mut self.playing {
+            *time += delta_time;
+        }
+        self.playing.retain(|(pattern, time)| pattern.at(*time).is_some());
+    }
+}
@@ -1,6 +1,18 @@
 //! Input handling module
 //! This module contains input processing logic for keyboard, mouse, and window events.
 
+pub mod bindings;
+pub mod clipboard;
 pub mod handler;
+pub mod haptics;
+pub mod mouse;
+pub mod text;
+pub mod touch;
 
-pub use handler::InputHandler; 
\ No newline at end of file
+pub use bindings::{Action, ActivationMode, KeyBindings};
+pub use clipboard::{Clipboard, ClipboardBackend};
+pub use handler::{InputHandler, MouseInputMode};
+pub use haptics::{HapticEvent, Haptics, RumbleBackend, RumblePattern};
+pub use mouse::{MouseLook, MouseSettings, SensitivityCurve};
+pub use text::TextComposition;
+pub use touch::TouchControls; 
\ No newline at end of file
@@ -0,0 +1,176 @@
+//! Mouse look: sensitivity, acceleration and smoothing of mouse deltas.
+//!
+//! Deltas are gathered as events arrive and turned into camera rotation once per frame.
+//! Acceleration works from how fast the mouse moved, in counts per second, and smoothing
+//! eases the rotation in over a fixed time, so neither depends on the frame rate. Smoothing
+//! only delays rotation, it never loses any.
+
+use glam::Vec2;
+
+use crate::game::world::camera::Camera;
+
+/// Radians the camera turns per mouse count at a sensitivity of 1
+pub const BASE_SENSITIVITY: f32 = 0.002;
+/// Most an accelerated curve multiplies the sensitivity by
+pub const MAX_ACCELERATION_GAIN: f32 = 4.0;
+/// Longest smoothing time accepted, in seconds
+pub const MAX_SMOOTHING: f32 = 0.5;
+
+/// How the speed of the mouse changes its sensitivity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SensitivityCurve {
+    /// The same turn per count however fast the mouse moves
+    Linear,
+    /// Faster movement turns further per count: the gain grows by `acceleration` for each
+    /// count per millisecond, up to MAX_ACCELERATION_GAIN
+    Accelerated { acceleration: f32 },
+}
+
+impl SensitivityCurve {
+    /// Multiplier on the sensitivity when the mouse moves `speed` counts per second
+    pub fn gain(&self, speed: f32) -> f32 {
+        match *self {
+            SensitivityCurve::Linear => 1.0,
+            SensitivityCurve::Accelerated { acceleration } => (1.0 + acceleration * speed / 1000.0).min(MAX_ACCELERATION_GAIN),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MouseSettings {
+    /// Multipliers on BASE_SENSITIVITY for turning and for looking up and down
+    pub sensitivity_x: f32,
+    pub sensitivity_y: f32,
+    /// Moving the mouse forward looks down instead of up
+    pub invert_y: bool,
+    pub curve: SensitivityCurve,
+    /// Seconds over which rotation eases in, 0 for none
+    pub smoothing: f32,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self { sensitivity_x: 1.0, sensitivity_y: 1.0, invert_y: false, curve: SensitivityCurve::Linear, smoothing: 0.0 }
+    }
+}
+
+fn parse_positive(value: &str) -> Result<f32, String> {
+    value.parse::<f32>().ok().filter(|v| v.is_finite() && *v > 0.0)
+        .ok_or_else(|| format!("expected a positive number, got {}", value))
+}
+
+impl MouseSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Changes one setting from the console, returning a description of the result.
+    /// `sensitivity`, `x` and `y` take multipliers, `invert` on or off, `accel` off or
+    /// an amount, and `smoothing` milliseconds.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<String, String> {
+        match key {
+            "sensitivity" => {
+                let scale = parse_positive(value)?;
+                self.sensitivity_x = scale;
+                self.sensitivity_y = scale;
+                Ok(format!("Mouse sensitivity {}", scale))
+            }
+            "x" => {
+                self.sensitivity_x = parse_positive(value)?;
+                Ok(format!("Horizontal mouse sensitivity {}", self.sensitivity_x))
+            }
+            "y" => {
+                self.sensitivity_y = parse_positive(value)?;
+                Ok(format!("Vertical mouse sensitivity {}", self.sensitivity_y))
+            }
+            "invert" => {
+                self.invert_y = match value {
+                    "on" => true,
+                    "off" => false,
+                    other => return Err(format!("expected on or off, got {}", other)),
+                };
+                Ok(format!("Inverted mouse {}", value))
+            }
+            "accel" => {
+                if value == "off" {
+                    self.curve = SensitivityCurve::Linear;
+                    return Ok("Mouse acceleration off".to_string());
+                }
+                let acceleration = parse_positive(value)?;
+                self.curve = SensitivityCurve::Accelerated { acceleration };
+                Ok(format!("Mouse acceleration {}", acceleration))
+            }
+            "smoothing" => {
+                let ms = value.parse::<f32>().ok().filter(|v| v.is_finite() && *v >= 0.0)
+                    .ok_or_else(|| format!("expected milliseconds, got {}", value))?;
+                self.smoothing = (ms / 1000.0).min(MAX_SMOOTHING);
+                Ok(format!("Mouse smoothing {:.0} ms", self.smoothing * 1000.0))
+            }
+            other => Err(format!("unknown mouse setting {}", other)),
+        }
+    }
+}
+
+/// Turns gathered mouse deltas into camera rotation
+#[derive(Debug, Clone)]
+pub struct MouseLook {
+    pub settings: MouseSettings,
+    /// Extra multiplier on top of the settings, e.g. lowered while zoomed in
+    pub look_scale: f32,
+    /// Counts moved since the last frame
+    pending: Vec2,
+    /// Rotation, as (yaw, pitch) radians, that smoothing has yet to apply
+    backlog: Vec2,
+}
+
+impl Default for MouseLook {
+    fn default() -> Self {
+        Self { settings: MouseSettings::new(), look_scale: 1.0, pending: Vec2::ZERO, backlog: Vec2::ZERO }
+    }
+}
+
+impl MouseLook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gathers a delta, in counts, for the next `update`
+    pub fn add_delta(&mut self, delta: Vec2) {
+        self.pending += delta;
+    }
+
+    /// Drops gathered and smoothed movement, e.g. when the cursor is released
+    pub fn reset(&mut self) {
+        self.pending = Vec2::ZERO;
+        self.backlog = Vec2::ZERO;
+    }
+
+    /// The rotation, as (yaw, pitch) radians, to apply for `counts` moved over `dt` seconds
+    pub fn filter(&mut self, counts: Vec2, dt: f32) -> Vec2 {
+        let speed = if dt > 0.0 { counts.length() / dt } else { 0.0 };
+        let gain = self.settings.curve.gain(speed);
+        let pitch_sign = if self.settings.invert_y { 1.0 } else { -1.0 };
+        let turn = Vec2::new(counts.x * self.settings.sensitivity_x, pitch_sign * counts.y * self.settings.sensitivity_y)
+            * BASE_SENSITIVITY * gain * self.look_scale;
+        if self.settings.smoothing <= 0.0 {
+            return turn + std::mem::take(&mut self.backlog);
+        }
+        self.backlog += turn;
+        // Exponential easing: the same share is left after a given time at any frame rate
+        let share = 1.0 - (-dt / self.settings.smoothing).exp();
+        let step = self.backlog * share;
+        self.backlog -= step;
+        step
+    }
+
+    /// Applies the movement gathered since the last frame, `dt` real seconds ago as the
+    /// frame clock has it, which keeps a hitch from flushing the smoothing backlog at once;
+    /// call once per frame
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        let counts = std::mem::take(&mut self.pending);
+        let turn = self.filter(counts, dt);
+        if turn != Vec2::ZERO {
+            camera.rotate(turn.x, turn.y);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Composed text input from an input method (IME).
+//!
+//! Input methods for Chinese, Japanese, Korean and other scripts build each piece of text
+//! over several keystrokes. Meanwhile winit reports the unfinished text, the preedit, which
+//! a text field shows where it will go; the keystrokes that build it must not be typed
+//! themselves. Once the text is chosen it arrives all at once as a commit.
+
+use winit::event::Ime;
+
+#[derive(Debug, Default)]
+pub struct TextComposition {
+    /// Whether a text field is open and wants composed text
+    allowed: bool,
+    preedit: String,
+    /// Caret within the preedit, in characters
+    cursor: Option<usize>,
+}
+
+impl TextComposition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens or closes composition as a text field opens or closes, returning whether that
+    /// changed, so the caller knows to tell the window
+    pub fn set_allowed(&mut self, allowed: bool) -> bool {
+        if allowed == self.allowed {
+            return false;
+        }
+        self.allowed = allowed;
+        self.clear();
+        true
+    }
+
+    pub fn is_allowed(&self) -> bool {
+        self.allowed
+    }
+
+    /// Follows an input method event, returning the text it committed, if any
+    pub fn handle(&mut self, event: &Ime) -> Option<String> {
+        match event {
+            Ime::Enabled => None,
+            Ime::Preedit(text, cursor) => {
+                if self.allowed {
+                    self.preedit = text.clone();
+                    // winit gives byte offsets; the overlay lays text out by character
+                    self.cursor = cursor.and_then(|(start, _)| text.get(..start)).map(|before| before.chars().count());
+                }
+                None
+            }
+            Ime::Commit(text) => {
+                self.clear();
+                Some(text.clone()).filter(|text| self.allowed && !text.is_empty())
+            }
+            Ime::Disabled => {
+                self.clear();
+                None
+            }
+        }
+    }
+
+    /// Whether text is being composed; key presses belong to the input method until it is
+    /// committed
+    pub fn is_composing(&self) -> bool {
+        !self.preedit.is_empty()
+    }
+
+    /// The text being composed, empty when nothing is
+    pub fn preedit(&self) -> &str {
+        &self.preedit
+    }
+
+    /// Where the caret is within the preedit, in characters; None hides it
+    pub fn preedit_cursor(&self) -> Option<usize> {
+        self.cursor
+    }
+
+    fn clear(&mut self) {
+        self.preedit.clear();
+        self.cursor = None;
+    }
+}
@@ -0,0 +1,116 @@
+//! Touch gestures: drag to look, pinch to zoom, and an on-screen movement stick.
+//!
+//! A touch that starts in the lower-left of the screen becomes the movement stick while it
+//! lasts; any other touch drags the view. Two view touches at once pinch the field of view.
+
+use std::collections::HashMap;
+use glam::Vec2;
+use winit::event::{Touch, TouchPhase};
+
+use crate::game::world::camera::Camera;
+
+/// Fraction of the window width, from the left, where a touch grabs the stick
+pub const STICK_REGION_WIDTH: f32 = 0.4;
+/// Fraction of the window height, from the top, below which a touch grabs the stick
+pub const STICK_REGION_TOP: f32 = 0.5;
+/// Drag distance for full stick deflection, in UI units
+pub const STICK_RADIUS: f32 = 60.0;
+/// Radians of rotation per physical pixel dragged
+pub const TOUCH_LOOK_SENSITIVITY: f32 = 0.005;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TouchRole {
+    Stick,
+    Look,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ActiveTouch {
+    role: TouchRole,
+    start: Vec2,
+    last: Vec2,
+}
+
+#[derive(Default)]
+pub struct TouchControls {
+    touches: HashMap<u64, ActiveTouch>,
+    stick: Vec2,
+}
+
+impl TouchControls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stick deflection: x is strafe right, y is forward, each in -1..=1
+    pub fn stick(&self) -> Vec2 {
+        self.stick
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.touches.is_empty()
+    }
+
+    /// Forgets all touches, e.g. when the app is backgrounded and their end events are lost
+    pub fn reset(&mut self) {
+        self.touches.clear();
+        self.stick = Vec2::ZERO;
+    }
+
+    /// `window_size` is in physical pixels; `ui_scale` converts the stick radius to them
+    pub fn handle_touch(&mut self, touch: &Touch, window_size: Vec2, ui_scale: f32, camera: &mut Camera) {
+        let position = Vec2::new(touch.location.x as f32, touch.location.y as f32);
+        match touch.phase {
+            TouchPhase::Started => {
+                let in_stick_region = position.x < window_size.x * STICK_REGION_WIDTH
+                    && position.y > window_size.y * STICK_REGION_TOP;
+                let stick_taken = self.touches.values().any(|t| t.role == TouchRole::Stick);
+                let role = if in_stick_region && !stick_taken { TouchRole::Stick } else { TouchRole::Look };
+                self.touches.insert(touch.id, ActiveTouch { role, start: position, last: position });
+            }
+            TouchPhase::Moved => {
+                let Some(active) = self.touches.get(&touch.id).copied() else { return };
+                match active.role {
+                    TouchRole::Stick => {
+                        let radius = STICK_RADIUS * ui_scale.max(f32::EPSILON);
+                        let offset = (position - active.start).clamp_length_max(radius) / radius;
+                        // Screen y grows downwards, dragging up walks forward
+                        self.stick = Vec2::new(offset.x, -offset.y);
+                    }
+                    TouchRole::Look => self.look_or_pinch(touch.id, position, camera),
+                }
+                if let Some(active) = self.touches.get_mut(&touch.id) {
+                    active.last = position;
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if let Some(active) = self.touches.remove(&touch.id) {
+                    if active.role == TouchRole::Stick {
+                        self.stick = Vec2::ZERO;
+                    }
+                }
+            }
+        }
+    }
+
+    fn look_or_pinch(&mut self, id: u64, position: Vec2, camera: &mut Camera) {
+        let Some(active) = self.touches.get(&id) else { return };
+        let other = self.touches.iter()
+            .find(|(other_id, t)| **other_id != id && t.role == TouchRole::Look)
+            .map(|(_, t)| t.last);
+        match other {
+            Some(other) => {
+                let before = active.last.distance(other);
+                let after = position.distance(other);
+                if before > 1.0 && after > 1.0 {
+                    // Spreading the fingers zooms in, i.e. narrows the field of view
+                    camera.zoom(before / after);
+                }
+            }
+            None => {
+                let delta = position - active.last;
+                camera.rotate(delta.x * TOUCH_LOOK_SENSITIVITY, -delta.y * TOUCH_LOOK_SENSITIVITY);
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Axis-aligned bounding box implementation.
+
+use glam::Vec3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Builds a box from its center and half extents
+    pub fn from_center(center: Vec3, half_extents: Vec3) -> Self {
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Grows the box by `amount` on every side
+    pub fn expanded(&self, amount: f32) -> Self {
+        Self {
+            min: self.min - Vec3::splat(amount),
+            max: self.max + Vec3::splat(amount),
+        }
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && self.max.cmpge(other.min).all()
+    }
+
+    /// Slab test; returns the distance along `dir` to the first intersection, if any
+    pub fn ray_intersect(&self, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<f32> {
+        let (mut t_near, mut t_far) = (f32::NEG_INFINITY, f32::INFINITY);
+        for axis in 0..3 {
+            let (min, max, origin, dir) = (self.min[axis], self.max[axis], origin[axis], dir[axis]);
+            // A ray parallel to the slab never crosses it, and starting on one of its
+            // planes would give 0 * inf
+            if dir == 0.0 {
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let (t1, t2) = ((min - origin) / dir, (max - origin) / dir);
+            t_near = t_near.max(t1.min(t2));
+            t_far = t_far.min(t1.max(t2));
+        }
+        if t_far < 0.0 || t_near > t_far || t_near > max_distance {
+            return None;
+        }
+        Some(t_near.max(0.0))
+    }
+}
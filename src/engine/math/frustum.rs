@@ -0,0 +1,83 @@
+//! View frustum, for skipping whatever the camera cannot see before it is drawn.
+
+use glam::{Mat4, Vec3, Vec4};
+
+use crate::engine::math::Aabb;
+
+/// The six planes bounding what a view-projection matrix sees, each facing inward
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a view-projection matrix, in world space. `depth_from_zero`
+    /// for matrices that put the near plane at a depth of 0, as the orthographic projection
+    /// does, rather than at -1 like the GL perspective one.
+    pub fn from_view_proj(mat: &Mat4, depth_from_zero: bool) -> Self {
+        let m = mat.to_cols_array_2d();
+        let near = if depth_from_zero {
+            Vec4::new(m[0][2], m[1][2], m[2][2], m[3][2])
+        } else {
+            Vec4::new(m[0][3] + m[0][2], m[1][3] + m[1][2], m[2][3] + m[2][2], m[3][3] + m[3][2])
+        };
+        Self {
+            planes: [
+                // Left
+                Vec4::new(m[0][3] + m[0][0], m[1][3] + m[1][0], m[2][3] + m[2][0], m[3][3] + m[3][0]),
+                // Right
+                Vec4::new(m[0][3] - m[0][0], m[1][3] - m[1][0], m[2][3] - m[2][0], m[3][3] - m[3][0]),
+                // Bottom
+                Vec4::new(m[0][3] + m[0][1], m[1][3] + m[1][1], m[2][3] + m[2][1], m[3][3] + m[3][1]),
+                // Top
+                Vec4::new(m[0][3] - m[0][1], m[1][3] - m[1][1], m[2][3] - m[2][1], m[3][3] - m[3][1]),
+                near,
+                // Far
+                Vec4::new(m[0][3] - m[0][2], m[1][3] - m[1][2], m[2][3] - m[2][2], m[3][3] - m[3][2]),
+            ],
+        }
+    }
+
+    pub fn planes(&self) -> &[Vec4; 6] {
+        &self.planes
+    }
+
+    /// Whether any of the box from `min` to `max` may be in view. Boxes near a corner of the
+    /// frustum can pass without being seen, but none in view fail.
+    pub fn intersects_box(&self, min: Vec3, max: Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the plane's normal is the last to leave it
+            let p = Vec3::new(
+                if plane.x >= 0.0 { max.x } else { min.x },
+                if plane.y >= 0.0 { max.y } else { min.y },
+                if plane.z >= 0.0 { max.z } else { min.z },
+            );
+            plane.truncate().dot(p) + plane.w >= 0.0
+        })
+    }
+
+    pub fn intersects(&self, aabb: &Aabb) -> bool {
+        self.intersects_box(aabb.min, aabb.max)
+    }
+
+    /// The `items` whose bounds may be in view, in their order, so they can be drawn as one
+    /// batch, and a count of the rest
+    pub fn cull<T>(&self, items: impl IntoIterator<Item = T>, bounds: impl Fn(&T) -> Aabb) -> Culled<T> {
+        let mut culled = Culled { visible: Vec::new(), hidden: 0 };
+        for item in items {
+            if self.intersects(&bounds(&item)) {
+                culled.visible.push(item);
+            } else {
+                culled.hidden += 1;
+            }
+        }
+        culled
+    }
+}
+
+/// The result of Frustum::cull
+#[derive(Debug, Clone, PartialEq)]
+pub struct Culled<T> {
+    pub visible: Vec<T>,
+    pub hidden: usize,
+}
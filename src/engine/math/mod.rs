@@ -0,0 +1,9 @@
+//! Shared math helpers used by both engine and game code.
+
+pub mod aabb;
+pub mod frustum;
+pub mod rng;
+
+pub use aabb::Aabb;
+pub use frustum::{Culled, Frustum};
+pub use rng::{Rng, RngStreams};
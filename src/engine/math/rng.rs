@@ -0,0 +1,87 @@
+//! Small deterministic random number generator, and independent named streams of them.
+//!
+//! Systems that share one generator perturb each other: a new draw in one shifts every
+//! later draw in all the others, so nothing stays reproducible once anything changes.
+//! RngStreams gives each system its own generator, derived from one seed and the
+//! system's name, so each sequence depends only on the seed and on its own draws.
+
+use std::collections::HashMap;
+
+/// xorshift64*; fast and good enough for gameplay randomness, not for anything secure
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Zero is a fixed point of xorshift
+        Self { state: (seed ^ 0x9e37_79b9_7f4a_7c15) | 1 }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in 0..1
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform in 0..n; n must be non-zero
+    pub fn below(&mut self, n: u32) -> u32 {
+        (((self.next_u64() >> 32) * n as u64) >> 32) as u32
+    }
+
+    /// True with the given probability
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+}
+
+/// splitmix64, to spread a seed and a name hash over the whole state
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Independent generators drawn from one seed, by name
+#[derive(Debug, Clone)]
+pub struct RngStreams {
+    seed: u64,
+    streams: HashMap<String, Rng>,
+}
+
+impl RngStreams {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, streams: HashMap::new() }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The generator of the stream called `name`, started on first use
+    pub fn stream(&mut self, name: &str) -> &mut Rng {
+        if !self.streams.contains_key(name) {
+            self.streams.insert(name.to_string(), Self::derive(self.seed, name));
+        }
+        self.streams.get_mut(name).expect("stream was just added")
+    }
+
+    /// A stream as it starts, for a system that keeps its own generator
+    pub fn derive(seed: u64, name: &str) -> Rng {
+        // FNV-1a, which stays the same across builds and platforms
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in name.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        Rng::new(mix(seed ^ mix(hash)))
+    }
+}
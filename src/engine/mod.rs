@@ -1,7 +1,15 @@
 //! Engine module containing graphics, input, and window management.
 
+pub mod assets;
+pub mod audio;
+pub mod codec;
 pub mod graphics;
 pub mod input;
+pub mod math;
+pub mod net;
+pub mod profile;
+pub mod shaders;
+pub mod time;
 pub mod window;
 
 // Re-export commonly used types
@@ -0,0 +1,127 @@
+//! UDP broadcast LAN discovery.
+//!
+//! A hosting server periodically broadcasts a small announcement packet on DISCOVERY_PORT;
+//! clients listen on that port and keep a list of servers heard from recently.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+use crate::engine::time::Instant;
+use log::{debug, warn};
+
+use crate::engine::codec::{ByteReader, ByteWriter};
+
+pub const DISCOVERY_PORT: u16 = 47800;
+/// Port game servers listen on unless configured otherwise
+pub const DEFAULT_GAME_PORT: u16 = 47801;
+pub const ANNOUNCE_INTERVAL: Duration = Duration::from_millis(1500);
+/// Servers not heard from for this long are dropped from the list
+pub const SERVER_TIMEOUT: Duration = Duration::from_secs(5);
+const ANNOUNCE_MAGIC: &[u8; 7] = b"PSULAN1";
+
+fn encode_announcement(name: &str, game_port: u16) -> Vec<u8> {
+    let mut w = ByteWriter::new();
+    w.write_bytes(ANNOUNCE_MAGIC);
+    w.write_u16(game_port);
+    w.write_str(name);
+    w.into_inner()
+}
+
+fn decode_announcement(data: &[u8]) -> Option<(String, u16)> {
+    let mut r = ByteReader::new(data);
+    if r.read_bytes(ANNOUNCE_MAGIC.len()).ok()? != ANNOUNCE_MAGIC {
+        return None;
+    }
+    let port = r.read_u16().ok()?;
+    let name = r.read_str().ok()?;
+    Some((name, port))
+}
+
+/// Server side: broadcasts the server's presence on the local network
+pub struct LanAnnouncer {
+    socket: UdpSocket,
+    packet: Vec<u8>,
+    last_sent: Option<Instant>,
+}
+
+impl LanAnnouncer {
+    pub fn new(name: &str, game_port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        Ok(Self {
+            socket,
+            packet: encode_announcement(name, game_port),
+            last_sent: None,
+        })
+    }
+
+    /// Call regularly; sends an announcement once per ANNOUNCE_INTERVAL
+    pub fn tick(&mut self) {
+        let due = self.last_sent.is_none_or(|t| t.elapsed() >= ANNOUNCE_INTERVAL);
+        if !due {
+            return;
+        }
+        self.last_sent = Some(Instant::now());
+        if let Err(e) = self.socket.send_to(&self.packet, (Ipv4Addr::BROADCAST, DISCOVERY_PORT)) {
+            warn!("Failed to send LAN announcement: {}", e);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredServer {
+    pub name: String,
+    /// Address to connect to: the sender's IP with the advertised game port
+    pub address: SocketAddr,
+    pub last_seen: Instant,
+}
+
+/// Client side: listens for announcements and tracks live servers
+pub struct LanDiscovery {
+    socket: UdpSocket,
+    servers: HashMap<SocketAddr, DiscoveredServer>,
+}
+
+impl LanDiscovery {
+    pub fn new() -> io::Result<Self> {
+        Self::bind(DISCOVERY_PORT)
+    }
+
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            servers: HashMap::new(),
+        })
+    }
+
+    /// Drains pending announcements and expires stale servers
+    pub fn poll(&mut self) {
+        let mut buf = [0u8; 512];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, from)) => {
+                    if let Some((name, port)) = decode_announcement(&buf[..len]) {
+                        let address = SocketAddr::new(from.ip(), port);
+                        if !self.servers.contains_key(&address) {
+                            debug!("Discovered LAN server '{}' at {}", name, address);
+                        }
+                        self.servers.insert(address, DiscoveredServer { name, address, last_seen: Instant::now() });
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    warn!("LAN discovery receive failed: {}", e);
+                    break;
+                }
+            }
+        }
+        self.servers.retain(|_, server| server.last_seen.elapsed() < SERVER_TIMEOUT);
+    }
+
+    pub fn servers(&self) -> impl Iterator<Item = &DiscoveredServer> {
+        self.servers.values()
+    }
+}
@@ -0,0 +1,128 @@
+//! In-process loopback transport.
+//!
+//! Connections are a pair of channels; listeners register under a name in a shared hub that
+//! `connect` looks up, so the integrated server and its client never touch the OS network stack.
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use crossbeam_channel::{unbounded, Receiver, Sender, TryRecvError};
+
+use crate::engine::net::transport::{Connection, Listener, Transport};
+
+pub struct LoopbackConnection {
+    tx: Sender<Vec<u8>>,
+    rx: Receiver<Vec<u8>>,
+    peer: String,
+    open: bool,
+}
+
+impl LoopbackConnection {
+    /// Two connected endpoints
+    pub fn pair(name: &str) -> (LoopbackConnection, LoopbackConnection) {
+        let (a_tx, a_rx) = unbounded();
+        let (b_tx, b_rx) = unbounded();
+        (
+            LoopbackConnection { tx: a_tx, rx: b_rx, peer: format!("loopback:{}/server", name), open: true },
+            LoopbackConnection { tx: b_tx, rx: a_rx, peer: format!("loopback:{}/client", name), open: true },
+        )
+    }
+}
+
+impl Connection for LoopbackConnection {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        if !self.open {
+            return Err(io::ErrorKind::NotConnected.into());
+        }
+        self.tx.send(packet.to_vec()).map_err(|_| {
+            self.open = false;
+            io::Error::from(io::ErrorKind::BrokenPipe)
+        })
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match self.rx.try_recv() {
+            Ok(packet) => Ok(Some(packet)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => {
+                self.open = false;
+                Ok(None)
+            }
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.open
+    }
+
+    fn close(&mut self) {
+        // Dropping our sender lets the other side observe the disconnect
+        let (tx, _) = unbounded();
+        self.tx = tx;
+        self.open = false;
+    }
+
+    fn peer(&self) -> String {
+        self.peer.clone()
+    }
+}
+
+type ListenerHub = Arc<Mutex<HashMap<String, Sender<LoopbackConnection>>>>;
+
+pub struct LoopbackListener {
+    name: String,
+    incoming: Receiver<LoopbackConnection>,
+    hub: ListenerHub,
+}
+
+impl Drop for LoopbackListener {
+    fn drop(&mut self) {
+        if let Ok(mut listeners) = self.hub.lock() {
+            listeners.remove(&self.name);
+        }
+    }
+}
+
+impl Listener for LoopbackListener {
+    fn accept(&mut self) -> io::Result<Option<Box<dyn Connection>>> {
+        match self.incoming.try_recv() {
+            Ok(conn) => Ok(Some(Box::new(conn))),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn local_addr(&self) -> String {
+        format!("loopback:{}", self.name)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct LoopbackTransport {
+    listeners: ListenerHub,
+}
+
+impl LoopbackTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Transport for LoopbackTransport {
+    fn connect(&self, addr: &str) -> io::Result<Box<dyn Connection>> {
+        let listeners = self.listeners.lock().unwrap();
+        let incoming = listeners.get(addr).ok_or(io::ErrorKind::ConnectionRefused)?;
+        let (server_end, client_end) = LoopbackConnection::pair(addr);
+        incoming.send(server_end).map_err(|_| io::Error::from(io::ErrorKind::ConnectionRefused))?;
+        Ok(Box::new(client_end))
+    }
+
+    fn listen(&self, addr: &str) -> io::Result<Box<dyn Listener>> {
+        let mut listeners = self.listeners.lock().unwrap();
+        if listeners.contains_key(addr) {
+            return Err(io::ErrorKind::AddrInUse.into());
+        }
+        let (tx, rx) = unbounded();
+        listeners.insert(addr.to_string(), tx);
+        Ok(Box::new(LoopbackListener { name: addr.to_string(), incoming: rx, hub: self.listeners.clone() }))
+    }
+}
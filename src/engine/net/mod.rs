@@ -0,0 +1,11 @@
+//! Networking primitives shared by the client and server.
+
+pub mod discovery;
+pub mod loopback;
+pub mod tcp;
+pub mod transport;
+
+pub use discovery::{DiscoveredServer, LanAnnouncer, LanDiscovery, DEFAULT_GAME_PORT};
+pub use loopback::LoopbackTransport;
+pub use tcp::TcpTransport;
+pub use transport::{Connection, Listener, Transport};
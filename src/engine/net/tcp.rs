@@ -0,0 +1,162 @@
+//! TCP transport implementation.
+//!
+//! Packets are framed with a little-endian u32 length prefix over a non-blocking stream.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use crate::engine::net::transport::{Connection, Listener, Transport};
+
+/// Upper bound on a single packet, so a corrupt length can't make us allocate gigabytes
+pub const MAX_PACKET_SIZE: usize = 16 * 1024 * 1024;
+/// Most bytes queued for a peer before it is dropped, so one that stops reading can't make
+/// us buffer without end
+pub const MAX_WRITE_BUFFER: usize = 4 * MAX_PACKET_SIZE;
+
+pub struct TcpConnection {
+    stream: TcpStream,
+    peer: String,
+    read_buf: Vec<u8>,
+    write_buf: Vec<u8>,
+    open: bool,
+}
+
+impl TcpConnection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_else(|_| "unknown".into());
+        Ok(Self {
+            stream,
+            peer,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            open: true,
+        })
+    }
+
+    fn flush_writes(&mut self) -> io::Result<()> {
+        while !self.write_buf.is_empty() {
+            match self.stream.write(&self.write_buf) {
+                Ok(0) => {
+                    self.open = false;
+                    return Err(io::ErrorKind::WriteZero.into());
+                }
+                Ok(n) => {
+                    self.write_buf.drain(..n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    self.open = false;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_reads(&mut self) -> io::Result<()> {
+        let mut chunk = [0u8; 8192];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    self.open = false;
+                    break;
+                }
+                Ok(n) => self.read_buf.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    self.open = false;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Connection for TcpConnection {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        if !self.open {
+            return Err(io::ErrorKind::NotConnected.into());
+        }
+        if packet.len() > MAX_PACKET_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "packet too large"));
+        }
+        if self.write_buf.len() + 4 + packet.len() > MAX_WRITE_BUFFER {
+            self.close();
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "peer stopped reading"));
+        }
+        self.write_buf.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        self.write_buf.extend_from_slice(packet);
+        self.flush_writes()
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        self.flush_writes()?;
+        self.fill_reads()?;
+        if self.read_buf.len() < 4 {
+            return Ok(None);
+        }
+        let len = u32::from_le_bytes([self.read_buf[0], self.read_buf[1], self.read_buf[2], self.read_buf[3]]) as usize;
+        if len > MAX_PACKET_SIZE {
+            self.open = false;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "packet too large"));
+        }
+        if self.read_buf.len() < 4 + len {
+            return Ok(None);
+        }
+        let packet = self.read_buf[4..4 + len].to_vec();
+        self.read_buf.drain(..4 + len);
+        Ok(Some(packet))
+    }
+
+    fn is_open(&self) -> bool {
+        self.open
+    }
+
+    fn close(&mut self) {
+        let _ = self.flush_writes();
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+        self.open = false;
+    }
+
+    fn peer(&self) -> String {
+        self.peer.clone()
+    }
+}
+
+pub struct TcpConnectionListener {
+    listener: TcpListener,
+}
+
+impl Listener for TcpConnectionListener {
+    fn accept(&mut self) -> io::Result<Option<Box<dyn Connection>>> {
+        match self.listener.accept() {
+            Ok((stream, _)) => Ok(Some(Box::new(TcpConnection::new(stream)?))),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn local_addr(&self) -> String {
+        self.listener.local_addr().map(|a| a.to_string()).unwrap_or_default()
+    }
+}
+
+pub struct TcpTransport;
+
+impl Transport for TcpTransport {
+    fn connect(&self, addr: &str) -> io::Result<Box<dyn Connection>> {
+        let addr = addr.to_socket_addrs()?.next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address"))?;
+        let stream = TcpStream::connect(addr)?;
+        Ok(Box::new(TcpConnection::new(stream)?))
+    }
+
+    fn listen(&self, addr: &str) -> io::Result<Box<dyn Listener>> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Box::new(TcpConnectionListener { listener }))
+    }
+}
@@ -0,0 +1,29 @@
+//! Transport abstraction.
+//!
+//! Game code only sees framed, reliable, ordered packets through `Connection`; whether they
+//! travel over TCP or an in-process channel is decided by the `Transport` it was opened with.
+//! Singleplayer uses the loopback transport so the client/server split is always exercised.
+
+use std::io;
+
+pub trait Connection: Send {
+    /// Queues a packet for delivery. Never blocks.
+    fn send(&mut self, packet: &[u8]) -> io::Result<()>;
+    /// Returns the next complete packet, or None if nothing has arrived. Never blocks.
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>>;
+    fn is_open(&self) -> bool;
+    fn close(&mut self);
+    /// Human-readable remote address for logs
+    fn peer(&self) -> String;
+}
+
+pub trait Listener: Send {
+    /// Returns a newly connected client, or None if none is waiting. Never blocks.
+    fn accept(&mut self) -> io::Result<Option<Box<dyn Connection>>>;
+    fn local_addr(&self) -> String;
+}
+
+pub trait Transport {
+    fn connect(&self, addr: &str) -> io::Result<Box<dyn Connection>>;
+    fn listen(&self, addr: &str) -> io::Result<Box<dyn Listener>>;
+}
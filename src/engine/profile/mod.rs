@@ -0,0 +1,5 @@
+//! Lightweight timing helpers for profiling engine stages.
+
+pub mod stage_timer;
+
+pub use stage_timer::StageTimer;
@@ -0,0 +1,49 @@
+//! Logs how long each stage of a multi-step process takes, e.g. startup.
+
+use std::time::Duration;
+use crate::engine::time::Instant;
+use log::info;
+
+pub struct StageTimer {
+    name: &'static str,
+    started: Instant,
+    last: Instant,
+    stages: Vec<(&'static str, Duration)>,
+}
+
+impl StageTimer {
+    pub fn new(name: &'static str) -> Self {
+        let now = Instant::now();
+        Self { name, started: now, last: now, stages: Vec::new() }
+    }
+
+    /// Ends the current stage, logging its duration and the running total
+    pub fn stage(&mut self, stage: &'static str) -> Duration {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        self.last = now;
+        self.stages.push((stage, elapsed));
+        info!("[{}] {} took {:.1} ms ({:.1} ms total)", self.name, stage, ms(elapsed), ms(now - self.started));
+        elapsed
+    }
+
+    pub fn total(&self) -> Duration {
+        self.last - self.started
+    }
+
+    pub fn stages(&self) -> &[(&'static str, Duration)] {
+        &self.stages
+    }
+
+    /// Logs every stage recorded so far, slowest first
+    pub fn log_summary(&self) {
+        let mut stages = self.stages.clone();
+        stages.sort_by_key(|s| std::cmp::Reverse(s.1));
+        let breakdown: Vec<String> = stages.iter().map(|(stage, d)| format!("{} {:.1}", stage, ms(*d))).collect();
+        info!("[{}] finished in {:.1} ms: {}", self.name, ms(self.total()), breakdown.join(", "));
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,73 @@
+// Cloud layer: a flat plane around the viewer, shaped by scrolling value noise.
+
+#include "camera.wgsl"
+
+struct Clouds {
+    // xy: how far the wind has carried the clouds, in noise cells. z: altitude. w: radius
+    placement: vec4<f32>,
+    // rgb: color. a: density, 0 for clear skies to 1 for overcast
+    color: vec4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> clouds: Clouds;
+
+// Blocks per noise cell
+const CELL_SIZE: f32 = 24.0;
+// Noise repeats after this many cells, so the wind offset can wrap without a seam
+const PERIOD: f32 = 256.0;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // Two triangles covering -1..1, centered under the eye
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, 1.0), vec2<f32>(-1.0, -1.0),
+    );
+    let corner = corners[index] * clouds.placement.w;
+    let world = vec3<f32>(camera.eye.x + corner.x, clouds.placement.z, camera.eye.z + corner.y);
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(world, 1.0);
+    out.world_position = world;
+    return out;
+}
+
+fn hash(cell: vec2<f32>) -> f32 {
+    let wrapped = cell - PERIOD * floor(cell / PERIOD);
+    return fract(sin(dot(wrapped, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+fn value_noise(p: vec2<f32>) -> f32 {
+    let cell = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    let a = hash(cell);
+    let b = hash(cell + vec2<f32>(1.0, 0.0));
+    let c = hash(cell + vec2<f32>(0.0, 1.0));
+    let d = hash(cell + vec2<f32>(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+// Three octaves, each a whole number of times finer so the result still repeats
+fn cloud_noise(p: vec2<f32>) -> f32 {
+    return value_noise(p) * 0.57 + value_noise(p * 2.0) * 0.29 + value_noise(p * 4.0) * 0.14;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let p = in.world_position.xz / CELL_SIZE + clouds.placement.xy;
+    let density = clouds.color.a;
+    let coverage = smoothstep(1.0 - density, 1.0 - density + 0.25, cloud_noise(p));
+    // Thin out toward the edge of the plane so it never ends in a hard line
+    let distance = length(in.world_position.xz - camera.eye.xz) / clouds.placement.w;
+    let alpha = coverage * (1.0 - smoothstep(0.5, 1.0, distance)) * 0.85;
+    if (alpha < 0.01) {
+        discard;
+    }
+    return vec4<f32>(clouds.color.rgb, alpha);
+}
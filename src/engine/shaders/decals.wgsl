@@ -0,0 +1,36 @@
+// Decals: small textures laid over block faces, such as cracks and scorch marks. They are
+// drawn just in front of the faces they cover through a depth bias.
+
+#include "camera.wgsl"
+
+@group(1) @binding(0)
+var t_decals: texture_2d<f32>;
+@group(1) @binding(1)
+var s_decals: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) alpha: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) alpha: f32,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(in.position, 1.0);
+    out.uv = in.uv;
+    out.alpha = in.alpha;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_decals, s_decals, in.uv);
+    return vec4<f32>(color.rgb, color.a * in.alpha);
+}
@@ -0,0 +1,41 @@
+// Far terrain: a colored heightfield standing in for the world beyond the loaded chunks.
+
+#include "camera.wgsl"
+
+// How bright the far terrain is at midnight, relative to noon; matches the world shader
+const NIGHT_BRIGHTNESS: f32 = 0.25;
+// Share of the fog distance that is still clear
+const FOG_START: f32 = 0.7;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+    @location(1) world_position: vec3<f32>,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    let clip = camera.view_proj * vec4<f32>(in.position, 1.0);
+    // Pinned to the far end of the depth range, past the camera's far plane, so it is never
+    // clipped there and anything the world draws hides it
+    out.clip_position = vec4<f32>(clip.xy, clip.w, clip.w);
+    out.color = in.color;
+    out.world_position = in.position;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var lit = in.color * mix(NIGHT_BRIGHTNESS, 1.0, camera.sky.x);
+    if (camera.fog.w > 0.0) {
+        let distance = length(in.world_position - camera.eye.xyz);
+        lit = mix(lit, camera.fog.rgb, smoothstep(camera.fog.w * FOG_START, camera.fog.w, distance));
+    }
+    return vec4<f32>(lit, 1.0);
+}
@@ -0,0 +1,33 @@
+// The world camera uniform, bound at group 0. Matches CAMERA_UNIFORM_SIZE in renderer.rs.
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // x: daylight, 0 at night to 1 during the day. y: wetness, 0 dry to 1 soaked.
+    // z: seconds of simulation time, which stops while paused. w: seconds of real time.
+    // Both wrap around every hour.
+    sky: vec4<f32>,
+    // xyz: unit vector toward the sun
+    sun: vec4<f32>,
+    // xyz: the eye position
+    eye: vec4<f32>,
+    // xyz: fog color. w: distance at which fog hides everything, or 0 for no fog
+    fog: vec4<f32>,
+    // xyz: the block the chunk meshes are drawn relative to, near the eye
+    origin: vec4<i32>,
+    // view_proj with the eye at its offset from origin, for positions relative to origin
+    relative_view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// Where a block at `block` sits relative to camera.origin. The subtraction is done on
+// integers so it stays exact however far the world reaches.
+fn relative_block(block: vec3<i32>) -> vec3<f32> {
+    return vec3<f32>(block - camera.origin.xyz);
+}
+
+// The eye relative to camera.origin
+fn relative_eye() -> vec3<f32> {
+    return camera.eye.xyz - vec3<f32>(camera.origin.xyz);
+}
@@ -0,0 +1,17 @@
+// sRGB encoding, for targets whose format does not do it on write, and the tonemapping
+// shoulder. Match color.rs and hdr.rs.
+
+// Values up to this share of the top of the range pass through the shoulder unchanged
+const SHOULDER_KNEE: f32 = 0.8;
+
+fn linear_to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let low = linear * 12.92;
+    let high = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, linear <= vec3<f32>(0.0031308));
+}
+
+// Rolls `c` off smoothly toward 1, leaving it as it is below SHOULDER_KNEE
+fn shoulder(c: vec3<f32>) -> vec3<f32> {
+    let over = max(c - SHOULDER_KNEE, vec3<f32>(0.0));
+    return min(c, vec3<f32>(SHOULDER_KNEE)) + (1.0 - SHOULDER_KNEE) * over / (over + 1.0 - SHOULDER_KNEE);
+}
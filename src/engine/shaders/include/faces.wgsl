@@ -0,0 +1,52 @@
+// Placing a unit quad on a block face, shared by the world and pick shaders.
+
+// Flat models (ladders) use faces 6-9: a side face pulled 1/16 of a block into the cell
+// and plants faces 10-11: the two diagonals of the cell
+fn face_transform(face: u32, pos: vec3<f32>) -> vec3<f32> {
+    if (face >= 10u) {
+        if (face == 10u) {
+            return vec3<f32>(pos.x, pos.y, pos.x);
+        }
+        return vec3<f32>(pos.x, pos.y, -pos.x);
+    }
+    if (face >= 6u) {
+        var p = face_transform_cube(face - 6u, pos);
+        if (face < 8u) {
+            p.z *= 0.875;
+        } else {
+            p.x *= 0.875;
+        }
+        return p;
+    }
+    return face_transform_cube(face, pos);
+}
+
+// Face orientations (6 directions)
+fn face_transform_cube(face: u32, pos: vec3<f32>) -> vec3<f32> {
+    if (face == 0u) { // front (z+)
+        return vec3<f32>(pos.x, pos.y, 0.5);
+    } else if (face == 1u) { // back (z-)
+        return vec3<f32>(-pos.x, pos.y, -0.5);
+    } else if (face == 2u) { // left (x-)
+        return vec3<f32>(-0.5, pos.y, -pos.x);
+    } else if (face == 3u) { // right (x+)
+        return vec3<f32>(0.5, pos.y, pos.x);
+    } else if (face == 4u) { // top (y+)
+        return vec3<f32>(pos.x, 0.5, -pos.y);
+    } else { // bottom (y-)
+        return vec3<f32>(pos.x, -0.5, pos.y);
+    }
+}
+
+// Outward normal of a face. Plants have no one side, so they count as facing up.
+fn face_normal(face: u32) -> vec3<f32> {
+    if (face >= 10u) {
+        return vec3<f32>(0.0, 1.0, 0.0);
+    }
+    return normalize(face_transform(face, vec3<f32>(0.0)));
+}
+
+// The direction in the world that a quad's local `axis` runs along on a face
+fn face_axis(face: u32, axis: vec3<f32>) -> vec3<f32> {
+    return normalize(face_transform(face, axis) - face_transform(face, vec3<f32>(0.0)));
+}
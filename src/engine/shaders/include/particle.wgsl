@@ -0,0 +1,19 @@
+// A particle in the GPU pool, shared by the simulation and the particle shader. Matches
+// GpuParticle in gpu_particles.rs.
+
+struct Particle {
+    position: vec3<f32>,
+    // Seconds left to live; 0 or less for a free slot
+    life: f32,
+    velocity: vec3<f32>,
+    size: f32,
+    color: vec4<f32>,
+};
+
+// The indirect draw of the living particles, counted up by the simulation
+struct DrawArgs {
+    vertex_count: u32,
+    instance_count: atomic<u32>,
+    first_vertex: u32,
+    first_instance: u32,
+};
@@ -0,0 +1,17 @@
+//! WGSL sources and the preprocessor that assembles them.
+
+pub mod preprocess;
+
+pub use preprocess::{preprocess, ShaderError, INCLUDES};
+
+pub const WORLD_SHADER: &str = include_str!("shader.wgsl");
+pub const PICK_SHADER: &str = include_str!("pick.wgsl");
+pub const OVERLAY_SHADER: &str = include_str!("overlay.wgsl");
+pub const SKY_SHADER: &str = include_str!("sky.wgsl");
+pub const CLOUD_SHADER: &str = include_str!("clouds.wgsl");
+pub const DECAL_SHADER: &str = include_str!("decals.wgsl");
+pub const FAR_TERRAIN_SHADER: &str = include_str!("far_terrain.wgsl");
+pub const PARTICLE_SHADER: &str = include_str!("particles.wgsl");
+pub const PARTICLE_UPDATE_SHADER: &str = include_str!("particle_update.wgsl");
+pub const PRESENT_SHADER: &str = include_str!("present.wgsl");
+pub const TAA_SHADER: &str = include_str!("taa.wgsl");
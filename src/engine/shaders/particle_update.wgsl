@@ -0,0 +1,41 @@
+// Particle simulation: moves every particle in the pool one step, the way
+// ParticleSystem::update does on the CPU, and lists the ones still alive for drawing.
+
+#include "particle.wgsl"
+
+struct Step {
+    delta: f32,
+    gravity: f32,
+    // Fraction of velocity kept over this step
+    drag: f32,
+    capacity: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> sim: Step;
+@group(0) @binding(1)
+var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2)
+var<storage, read_write> alive: array<u32>;
+@group(0) @binding(3)
+var<storage, read_write> draw_args: DrawArgs;
+
+@compute @workgroup_size(64)
+fn cs_update(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if (index >= sim.capacity) {
+        return;
+    }
+    var particle = particles[index];
+    if (particle.life <= 0.0) {
+        return;
+    }
+    particle.velocity.y -= sim.gravity * sim.delta;
+    particle.velocity *= sim.drag;
+    particle.position += particle.velocity * sim.delta;
+    particle.life -= sim.delta;
+    particles[index] = particle;
+    if (particle.life > 0.0) {
+        alive[atomicAdd(&draw_args.instance_count, 1u)] = index;
+    }
+}
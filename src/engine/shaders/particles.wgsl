@@ -0,0 +1,41 @@
+// GPU particles, drawn as small flat-colored boxes like the overlay draws CPU ones. Each
+// instance is one entry of the alive list the simulation wrote.
+
+#include "camera.wgsl"
+#include "particle.wgsl"
+
+@group(1) @binding(0)
+var<storage, read> particles: array<Particle>;
+@group(1) @binding(1)
+var<storage, read> alive: array<u32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex: u32, @builtin(instance_index) instance: u32) -> VertexOutput {
+    // Corners of the 12 triangles of a box, numbered with x in bit 0, y in bit 1 and z in
+    // bit 2
+    var corners = array<u32, 36>(
+        0u, 2u, 3u, 3u, 1u, 0u,
+        4u, 5u, 7u, 7u, 6u, 4u,
+        0u, 4u, 6u, 6u, 2u, 0u,
+        1u, 3u, 7u, 7u, 5u, 1u,
+        0u, 1u, 5u, 5u, 4u, 0u,
+        2u, 6u, 7u, 7u, 3u, 2u,
+    );
+    let particle = particles[alive[instance]];
+    let corner = corners[vertex];
+    let offset = vec3<f32>(f32(corner & 1u), f32((corner >> 1u) & 1u), f32((corner >> 2u) & 1u)) - 0.5;
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(particle.position + offset * particle.size, 1.0);
+    out.color = particle.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,53 @@
+// ID buffer pass: writes the id of whatever covers each pixel instead of its color.
+// 0 means nothing was drawn there.
+
+#include "camera.wgsl"
+#include "faces.wgsl"
+
+struct FaceInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) texture_index: u32,
+    @location(3) instance_pos: vec3<i32>,
+    @location(4) face: u32,
+    @location(5) block_type: u32,
+}
+
+struct BoxInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) texture_index: u32,
+    @location(3) center: vec3<f32>,
+    @location(4) half_extents: vec3<f32>,
+    @location(5) id: u32,
+}
+
+struct PickOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) @interpolate(flat) id: u32,
+}
+
+@vertex
+fn vs_face(model: FaceInput, @builtin(instance_index) instance: u32) -> PickOutput {
+    var out: PickOutput;
+    // Fluids are picked as full cubes
+    let world = face_transform(model.face, model.position) + relative_block(model.instance_pos);
+    out.clip_position = camera.relative_view_proj * vec4<f32>(world, 1.0);
+    out.id = instance + 1u;
+    return out;
+}
+
+@vertex
+fn vs_box(model: BoxInput) -> PickOutput {
+    var out: PickOutput;
+    // The unit cube spans -0.5..0.5
+    let world = model.center + model.position * 2.0 * model.half_extents;
+    out.clip_position = camera.view_proj * vec4<f32>(world, 1.0);
+    out.id = model.id;
+    return out;
+}
+
+@fragment
+fn fs_main(in: PickOutput) -> @location(0) u32 {
+    return in.id;
+}
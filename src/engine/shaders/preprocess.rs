@@ -0,0 +1,117 @@
+//! A small WGSL preprocessor.
+//!
+//! Lines starting with a directive are replaced by what it produces:
+//!
+//! - `#include "name.wgsl"` pastes in a snippet from INCLUDES, once per shader however
+//!   often it is included.
+//! - `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif` keep or drop the lines between
+//!   them, depending on whether NAME is among the feature defines. They nest.
+//!
+//! Everything else passes through untouched.
+
+use std::collections::HashSet;
+use std::fmt;
+
+/// Snippets shaders can `#include`
+pub const INCLUDES: &[(&str, &str)] = &[
+    ("camera.wgsl", include_str!("include/camera.wgsl")),
+    ("color.wgsl", include_str!("include/color.wgsl")),
+    ("faces.wgsl", include_str!("include/faces.wgsl")),
+    ("particle.wgsl", include_str!("include/particle.wgsl")),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShaderError {
+    UnknownInclude(String),
+    /// An `#include` reached a snippet that is still being included
+    IncludeCycle(String),
+    /// `#else` or `#endif` without an `#ifdef`
+    UnmatchedDirective(String),
+    /// An `#ifdef` without its `#endif`
+    UnterminatedConditional,
+    Malformed(String),
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::UnknownInclude(name) => write!(f, "no shader include named {}", name),
+            ShaderError::IncludeCycle(name) => write!(f, "{} includes itself", name),
+            ShaderError::UnmatchedDirective(line) => write!(f, "{} without #ifdef", line),
+            ShaderError::UnterminatedConditional => write!(f, "#ifdef without #endif"),
+            ShaderError::Malformed(line) => write!(f, "malformed directive: {}", line),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// Expands the directives in `source` with the feature flags in `defines` set
+pub fn preprocess(source: &str, defines: &[&str]) -> Result<String, ShaderError> {
+    let mut out = String::with_capacity(source.len());
+    let mut included = HashSet::new();
+    let mut including = Vec::new();
+    expand(source, defines, &mut included, &mut including, &mut out)?;
+    Ok(out)
+}
+
+fn expand<'a>(
+    source: &str,
+    defines: &[&str],
+    included: &mut HashSet<&'a str>,
+    including: &mut Vec<&'a str>,
+    out: &mut String,
+) -> Result<(), ShaderError> {
+    // One entry per open conditional: whether its current branch is kept, and whether the
+    // conditional as a whole sits in kept code
+    let mut conditionals: Vec<(bool, bool)> = Vec::new();
+    for line in source.lines() {
+        let trimmed = line.trim();
+        let active = conditionals.last().is_none_or(|&(keep, _)| keep);
+        let Some(directive) = trimmed.strip_prefix('#') else {
+            if active {
+                out.push_str(line);
+                out.push('\n');
+            }
+            continue;
+        };
+        let (name, arg) = directive.split_once(char::is_whitespace).map_or((directive, ""), |(n, a)| (n, a.trim()));
+        match name {
+            "ifdef" | "ifndef" => {
+                if arg.is_empty() {
+                    return Err(ShaderError::Malformed(trimmed.to_string()));
+                }
+                let set = defines.contains(&arg);
+                conditionals.push((active && set == (name == "ifdef"), active));
+            }
+            "else" => {
+                let Some((keep, outer)) = conditionals.last_mut() else {
+                    return Err(ShaderError::UnmatchedDirective(trimmed.to_string()));
+                };
+                *keep = *outer && !*keep;
+            }
+            "endif" => {
+                if conditionals.pop().is_none() {
+                    return Err(ShaderError::UnmatchedDirective(trimmed.to_string()));
+                }
+            }
+            "include" if active => {
+                let file = arg.strip_prefix('"').and_then(|a| a.strip_suffix('"'))
+                    .ok_or_else(|| ShaderError::Malformed(trimmed.to_string()))?;
+                let &(file, snippet) = INCLUDES.iter().find(|(n, _)| *n == file)
+                    .ok_or_else(|| ShaderError::UnknownInclude(file.to_string()))?;
+                if including.contains(&file) {
+                    return Err(ShaderError::IncludeCycle(file.to_string()));
+                }
+                if included.insert(file) {
+                    including.push(file);
+                    expand(snippet, defines, included, including, out)?;
+                    including.pop();
+                }
+            }
+            "include" => (),
+            _ => return Err(ShaderError::Malformed(trimmed.to_string())),
+        }
+    }
+    if conditionals.is_empty() { Ok(()) } else { Err(ShaderError::UnterminatedConditional) }
+}
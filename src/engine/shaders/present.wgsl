@@ -0,0 +1,77 @@
+// Copies the finished frame to the surface: stretched up where it was drawn smaller,
+// tonemapped or scaled to the display's brightness for HDR frames, and sRGB encoded for
+// surfaces that store linear values.
+
+#include "color.wgsl"
+
+// Matches PresentParams in hdr.rs
+struct Present {
+    scale: f32,
+    peak: f32,
+    tonemap: f32,
+    encode_srgb: f32,
+    upscale: f32,
+    sharpness: f32,
+    padding: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var t_frame: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> present: Present;
+@group(0) @binding(2)
+var s_nearest: sampler;
+@group(0) @binding(3)
+var s_linear: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // Where on the frame, which may be smaller than the surface
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // One triangle covering the whole screen
+    let ndc = vec2<f32>(f32(index / 2u) * 4.0 - 1.0, f32(index % 2u) * 4.0 - 1.0);
+    return VertexOutput(vec4<f32>(ndc, 0.0, 1.0), vec2<f32>(ndc.x, -ndc.y) * 0.5 + 0.5);
+}
+
+// Contrast adaptive sharpening, after FidelityFX CAS: the frame at `uv` less a share of
+// its neighbours, that share smaller where the contrast is already high so edges do not
+// ring
+fn sharpen(uv: vec2<f32>, texel: vec2<f32>) -> vec3<f32> {
+    let center = textureSampleLevel(t_frame, s_linear, uv, 0.0).rgb;
+    let up = textureSampleLevel(t_frame, s_linear, uv - vec2<f32>(0.0, texel.y), 0.0).rgb;
+    let down = textureSampleLevel(t_frame, s_linear, uv + vec2<f32>(0.0, texel.y), 0.0).rgb;
+    let left = textureSampleLevel(t_frame, s_linear, uv - vec2<f32>(texel.x, 0.0), 0.0).rgb;
+    let right = textureSampleLevel(t_frame, s_linear, uv + vec2<f32>(texel.x, 0.0), 0.0).rgb;
+    let low = min(center, min(min(up, down), min(left, right)));
+    let high = max(center, max(max(up, down), max(left, right)));
+    // Room left below black and above white, over the brightest; HDR values past white
+    // leave none and go unsharpened
+    let room = clamp(min(low, vec3<f32>(1.0) - high) / max(high, vec3<f32>(0.0001)), vec3<f32>(0.0), vec3<f32>(1.0));
+    let weight = sqrt(room) * (-1.0 / mix(8.0, 5.0, present.sharpness));
+    return (center + (up + down + left + right) * weight) / (vec3<f32>(1.0) + 4.0 * weight);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = in.uv;
+    var frame: vec4<f32>;
+    if (present.upscale > 1.5) {
+        frame = vec4<f32>(sharpen(uv, 1.0 / vec2<f32>(textureDimensions(t_frame))), 1.0);
+    } else if (present.upscale > 0.5) {
+        frame = textureSampleLevel(t_frame, s_linear, uv, 0.0);
+    } else {
+        frame = textureSampleLevel(t_frame, s_nearest, uv, 0.0);
+    }
+    var color = max(frame.rgb, vec3<f32>(0.0)) * present.scale;
+    if (present.tonemap > 0.5) {
+        color = shoulder(color / present.peak) * present.peak;
+    }
+    if (present.encode_srgb > 0.5) {
+        color = linear_to_srgb(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)));
+    }
+    return vec4<f32>(color, frame.a);
+}
@@ -1,51 +1,67 @@
-struct Camera {
-    view_proj: mat4x4<f32>,
-};
+#include "camera.wgsl"
+#include "faces.wgsl"
 
-@group(0) @binding(0)
-var<uniform> camera: Camera;
+// How bright unlit blocks are at midnight, relative to noon
+const NIGHT_BRIGHTNESS: f32 = 0.25;
+// How much darker faces turned away from the sun are during the day
+const SUN_SHADING: f32 = 0.35;
+// Sun glints on soaked surfaces: their brightness, and how tight they are
+const WET_SPECULAR: f32 = 0.5;
+const WET_SHININESS: f32 = 48.0;
+// How much darker soaked surfaces are
+const WET_DARKENING: f32 = 0.25;
+// Share of the fog distance that is still clear
+const FOG_START: f32 = 0.7;
+// How much of what is behind water its surface hides
+const WATER_OPACITY: f32 = 0.7;
 
 @group(1) @binding(0)
 var t_atlas: texture_2d<f32>;
 @group(1) @binding(1)
 var s_atlas: sampler;
 
+// Tangent-space normals laid out like the atlas, see normal_map.rs
+@group(2) @binding(0)
+var t_normals: texture_2d<f32>;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
     @location(2) texture_index: u32, // unused
     // Instance attributes
-    @location(3) instance_pos: vec3<f32>,
+    @location(3) instance_pos: vec3<i32>,
     @location(4) face: u32,
     @location(5) block_type: u32,
+    // Biome color at each corner as RGB565, two to a word: -x-z and +x-z, then -x+z and +x+z
+    @location(6) tint: vec2<u32>,
+    // Brightness at each corner, a byte each: corner c is toward the quad's +x if c & 1 and
+    // its +y if c & 2
+    @location(7) light: u32,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
-}
-
-// Face orientations (6 directions)
-fn face_transform(face: u32, pos: vec3<f32>) -> vec3<f32> {
-    if (face == 0u) { // front (z+)
-        return vec3<f32>(pos.x, pos.y, 0.5);
-    } else if (face == 1u) { // back (z-)
-        return vec3<f32>(-pos.x, pos.y, -0.5);
-    } else if (face == 2u) { // left (x-)
-        return vec3<f32>(-0.5, pos.y, -pos.x);
-    } else if (face == 3u) { // right (x+)
-        return vec3<f32>(0.5, pos.y, pos.x);
-    } else if (face == 4u) { // top (y+)
-        return vec3<f32>(pos.x, 0.5, -pos.y);
-    } else { // bottom (y-)
-        return vec3<f32>(pos.x, -0.5, pos.y);
-    }
+    @location(1) brightness: f32,
+    // How much facing away from the sun darkens this face
+    @location(2) sun_shading: f32,
+    @location(3) normal: vec3<f32>,
+    // The face's directions toward the texture's +u and the top of the image
+    @location(4) tangent: vec3<f32>,
+    @location(5) bitangent: vec3<f32>,
+    // Relative to camera.origin
+    @location(6) world_position: vec3<f32>,
+    // Wetness, or 0 for surfaces that glow
+    @location(7) wetness: f32,
+    // What the texture is multiplied by, white when the face isn't tinted
+    @location(8) tint: vec3<f32>,
 }
 
 // Atlas UV calculation
 fn get_atlas_uvs(block_type: u32, face: u32, base_uv: vec2<f32>) -> vec2<f32> {
-    // Atlas layout: 2x2 grid (4 textures)
-    let atlas_size = 2.0;
+    // Atlas layout: 4x4 grid, see BLOCK_TEXTURE_PATHS
+    let atlas_size = 4.0;
+    let atlas_columns = 4u;
     let tile_size = 1.0 / atlas_size;
     
     // Determine texture index based on block type and face
@@ -64,13 +80,44 @@ fn get_atlas_uvs(block_type: u32, face: u32, base_uv: vec2<f32>) -> vec2<f32> {
         texture_index = 2u;
     } else if (block_type == 2u) { // Stone
         texture_index = 3u;
+    } else if (block_type == 3u) { // Ladder
+        texture_index = 4u;
+        uv.y = 1.0 - uv.y;
+    } else if (block_type == 4u) { // Door, lower half
+        texture_index = 5u;
+        uv.y = 1.0 - uv.y;
+    } else if (block_type == 5u) { // Door, upper half
+        texture_index = 6u;
+        uv.y = 1.0 - uv.y;
+    } else if (block_type >= 6u && block_type < 13u) { // Wheat, growing
+        texture_index = 7u;
+        uv.y = 1.0 - uv.y;
+    } else if (block_type == 13u) { // Wheat, ripe
+        texture_index = 8u;
+        uv.y = 1.0 - uv.y;
+    } else if (block_type >= 14u && block_type <= 22u) { // Water
+        texture_index = 9u;
+    } else if (block_type >= 23u && block_type <= 31u) { // Lava
+        texture_index = 10u;
+    } else if (block_type == 32u) { // Fire
+        texture_index = 11u;
+        uv.y = 1.0 - uv.y;
+    } else if (block_type == 33u) { // TNT
+        texture_index = 12u;
+        uv.y = 1.0 - uv.y;
+    } else if (block_type == 34u) { // Sign
+        texture_index = 13u;
+        uv.y = 1.0 - uv.y;
+    } else if (block_type == 35u) { // Bed
+        texture_index = 14u;
+        uv.y = 1.0 - uv.y;
     } else {
         texture_index = 0u;
     }
     
     // Calculate atlas position
-    let tile_x = f32(texture_index % 2u) * tile_size;
-    let tile_y = f32(texture_index / 2u) * tile_size;
+    let tile_x = f32(texture_index % atlas_columns) * tile_size;
+    let tile_y = f32(texture_index / atlas_columns) * tile_size;
     
     // Transform base UV to atlas position
     return vec2<f32>(
@@ -79,19 +126,98 @@ fn get_atlas_uvs(block_type: u32, face: u32, base_uv: vec2<f32>) -> vec2<f32> {
     );
 }
 
+// Height of a fluid's top surface above the bottom of its cell. block_type is
+// WATER_TEXTURE_BASE or LAVA_TEXTURE_BASE plus the level: 0 is a source, 1-7 weaker flow
+// and 8 falling.
+fn fluid_height(block_type: u32) -> f32 {
+    let level = (block_type - 14u) % 9u;
+    if (level >= 8u) {
+        return 1.0;
+    }
+    return f32(8u - level) / 8.0 * 0.875;
+}
+
+// The tint at the corner of the face nearest `local`
+fn corner_tint(tint: vec2<u32>, local: vec3<f32>) -> vec3<f32> {
+    let corner = u32(local.x > 0.0) + 2u * u32(local.z > 0.0);
+    let packed = (select(tint.x, tint.y, corner >= 2u) >> (16u * (corner % 2u))) & 0xffffu;
+    return vec3<f32>(
+        f32(packed >> 11u) / 31.0,
+        f32((packed >> 5u) & 63u) / 63.0,
+        f32(packed & 31u) / 31.0,
+    );
+}
+
+// The smooth light at the corner of the quad at `quad`, 0 to 1
+fn corner_light(light: u32, quad: vec3<f32>) -> f32 {
+    let corner = u32(quad.x > 0.0) + 2u * u32(quad.y > 0.0);
+    return f32((light >> (8u * corner)) & 0xffu) / 255.0;
+}
+
 @vertex
 fn vs_main(model: VertexInput) -> VertexOutput {
     var out: VertexOutput;
-    // Transform quad to face orientation and world position
-    let local = face_transform(model.face, model.position);
-    let world = local + model.instance_pos;
-    out.clip_position = camera.view_proj * vec4<f32>(world, 1.0);
+    // Transform quad to face orientation and its position relative to the camera origin
+    var local = face_transform(model.face, model.position);
+    if (model.block_type >= 14u && model.block_type <= 31u) {
+        local.y = mix(-0.5, fluid_height(model.block_type) - 0.5, local.y + 0.5);
+    }
+    let world = local + relative_block(model.instance_pos);
+    out.clip_position = camera.relative_view_proj * vec4<f32>(world, 1.0);
     // Calculate atlas UVs from block_type and base UVs
     out.tex_coords = get_atlas_uvs(model.block_type, model.face, model.tex_coords);
+    // Lava and fire glow at night
+    if (model.block_type >= 23u && model.block_type <= 32u) {
+        out.brightness = 1.0;
+        out.sun_shading = 0.0;
+        out.wetness = 0.0;
+    } else {
+        out.brightness = mix(NIGHT_BRIGHTNESS, 1.0, camera.sky.x) * corner_light(model.light, model.position);
+        out.sun_shading = SUN_SHADING * camera.sky.x;
+        out.wetness = camera.sky.y;
+    }
+    out.world_position = world;
+    out.tint = corner_tint(model.tint, local);
+    out.normal = face_normal(model.face);
+    out.tangent = face_axis(model.face, vec3<f32>(1.0, 0.0, 0.0));
+    // Textures flipped vertically on this face run toward the quad's +y
+    let dv = get_atlas_uvs(model.block_type, model.face, vec2<f32>(0.0, 1.0)).y
+        - get_atlas_uvs(model.block_type, model.face, vec2<f32>(0.0, 0.0)).y;
+    out.bitangent = face_axis(model.face, vec3<f32>(0.0, 1.0, 0.0)) * -sign(dv);
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_atlas, s_atlas, in.tex_coords);
+    let color = textureSample(t_atlas, s_atlas, in.tex_coords);
+    var normal = in.normal;
+#ifdef NORMAL_MAPS
+    let mapped = textureSample(t_normals, s_atlas, in.tex_coords).xyz * 2.0 - 1.0;
+    normal = in.tangent * mapped.x + in.bitangent * mapped.y + in.normal * mapped.z;
+#endif
+#ifdef ALPHA_TEST
+    // Cut-out textures such as ladders
+    if (color.a < 0.5) {
+        discard;
+    }
+#endif
+    normal = normalize(normal);
+    let facing_sun = max(dot(normal, camera.sun.xyz), 0.0);
+    let light = in.brightness * (1.0 - in.sun_shading * (1.0 - facing_sun));
+    // Blinn-Phong highlight of the sun on wet surfaces, faded out with the daylight
+    let to_eye = normalize(relative_eye() - in.world_position);
+    let halfway = normalize(camera.sun.xyz + to_eye);
+    let glint = pow(max(dot(normal, halfway), 0.0), WET_SHININESS) * step(0.0, dot(normal, camera.sun.xyz));
+    let specular = WET_SPECULAR * in.wetness * camera.sky.x * glint;
+    let albedo = color.rgb * in.tint * (1.0 - WET_DARKENING * in.wetness);
+    var lit = albedo * light + vec3<f32>(specular);
+    if (camera.fog.w > 0.0) {
+        let distance = length(in.world_position - relative_eye());
+        lit = mix(lit, camera.fog.rgb, smoothstep(camera.fog.w * FOG_START, camera.fog.w, distance));
+    }
+#ifdef TRANSLUCENT
+    return vec4<f32>(lit, color.a * WATER_OPACITY);
+#else
+    return vec4<f32>(lit, color.a);
+#endif
 } 
\ No newline at end of file
@@ -0,0 +1,100 @@
+// Night sky behind the world: a star field and the moon, faded in as daylight goes.
+
+struct NightSky {
+    // Takes clip space back to the world, to find the view direction of each pixel
+    inv_view_proj: mat4x4<f32>,
+    // xyz: unit vector toward the moon. w: how dark it is, 0 by day to 1 at night
+    moon: vec4<f32>,
+    // x: moon phase, 0 full to 0.5 new. y: how far the sky has turned, in radians
+    cycle: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> sky: NightSky;
+
+// Angular radius of the moon, in radians
+const MOON_RADIUS: f32 = 0.05;
+// Share of the moon's dark side still faintly visible
+const EARTHSHINE: f32 = 0.06;
+// Star cells across the sky; a few of them hold a star
+const STAR_GRID: f32 = 120.0;
+const STAR_CHANCE: f32 = 0.02;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // One triangle covering the whole screen
+    let ndc = vec2<f32>(f32(index / 2u) * 4.0 - 1.0, f32(index % 2u) * 4.0 - 1.0);
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+fn hash3(p: vec3<f32>) -> f32 {
+    return fract(sin(dot(p, vec3<f32>(127.1, 311.7, 74.7))) * 43758.5453);
+}
+
+fn stars(direction: vec3<f32>) -> f32 {
+    // The stars turn with the sky, about the axis the sun circles
+    let angle = -sky.cycle.y;
+    let c = cos(angle);
+    let s = sin(angle);
+    let d = vec3<f32>(c * direction.x - s * direction.y, s * direction.x + c * direction.y, direction.z);
+    let p = d * STAR_GRID;
+    let cell = floor(p);
+    if (hash3(cell) > STAR_CHANCE) {
+        return 0.0;
+    }
+    let center = cell + 0.5 + (vec3<f32>(hash3(cell + 1.0), hash3(cell + 2.0), hash3(cell + 3.0)) - 0.5) * 0.6;
+    let brightness = 0.4 + 0.6 * hash3(cell + 4.0);
+    return brightness * (1.0 - smoothstep(0.05, 0.2, length(p - center)));
+}
+
+// Brightness of the moon in `direction`, or -1 if the moon is not there
+fn moon(direction: vec3<f32>) -> f32 {
+    let toward = sky.moon.xyz;
+    let along = dot(direction, toward);
+    if (along <= 0.0) {
+        return -1.0;
+    }
+    // Across the disc along the path the sun and moon circle, which is about the z axis,
+    // so the lit side faces where the sun is
+    let right = normalize(cross(vec3<f32>(0.0, 0.0, 1.0), toward));
+    let up = cross(right, toward);
+    let disc = vec2<f32>(dot(direction, right), dot(direction, up)) / MOON_RADIUS;
+    let r2 = dot(disc, disc);
+    if (r2 > 1.0) {
+        return -1.0;
+    }
+    // Light the disc as a sphere, the light swinging around it as the phase goes by
+    let normal = vec3<f32>(disc, sqrt(1.0 - r2));
+    let angle = sky.cycle.x * 6.2831853;
+    let light = vec3<f32>(sin(angle), 0.0, cos(angle));
+    let lit = smoothstep(-0.05, 0.05, dot(normal, light));
+    return mix(EARTHSHINE, 1.0, lit) * (1.0 - smoothstep(0.9, 1.0, r2) * 0.5);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let night = sky.moon.w;
+    if (night <= 0.0) {
+        discard;
+    }
+    let near = sky.inv_view_proj * vec4<f32>(in.ndc, 0.5, 1.0);
+    let far = sky.inv_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let direction = normalize(far.xyz / far.w - near.xyz / near.w);
+    let moon_light = moon(direction);
+    if (moon_light >= 0.0) {
+        return vec4<f32>(vec3<f32>(0.95, 0.95, 0.85) * moon_light, night);
+    }
+    let star = stars(direction);
+    if (star <= 0.0) {
+        discard;
+    }
+    return vec4<f32>(vec3<f32>(0.9, 0.92, 1.0), star * night);
+}
@@ -0,0 +1,66 @@
+// Resolves a jittered frame against the history of those before it, see taa.rs.
+
+// Matches TaaParams in taa.rs
+struct TaaParams {
+    reprojection: mat4x4<f32>,
+    blend: f32,
+    reset: f32,
+    padding: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var t_input: texture_2d<f32>;
+@group(0) @binding(1)
+var t_history: texture_2d<f32>;
+@group(0) @binding(2)
+var s_history: sampler;
+@group(0) @binding(3)
+var t_depth: texture_depth_2d;
+@group(0) @binding(4)
+var<uniform> taa: TaaParams;
+
+struct Resolved {
+    @location(0) color: vec4<f32>,
+    @location(1) history: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // One triangle covering the whole screen
+    let ndc = vec2<f32>(f32(index / 2u) * 4.0 - 1.0, f32(index % 2u) * 4.0 - 1.0);
+    return vec4<f32>(ndc, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> Resolved {
+    let size = vec2<i32>(textureDimensions(t_input));
+    let pixel = vec2<i32>(position.xy);
+    let color = textureLoad(t_input, pixel, 0);
+    if (taa.reset > 0.5) {
+        return Resolved(color, color);
+    }
+
+    // The history may only hold colors found around the pixel now
+    var low = color.rgb;
+    var high = color.rgb;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbour = textureLoad(t_input, clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), size - 1), 0).rgb;
+            low = min(low, neighbour);
+            high = max(high, neighbour);
+        }
+    }
+
+    // Where this pixel was last frame, from its depth and the camera's motion
+    let uv = position.xy / vec2<f32>(size);
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, textureLoad(t_depth, pixel, 0), 1.0);
+    let previous = taa.reprojection * ndc;
+    let previous_uv = vec2<f32>(previous.x, -previous.y) / previous.w * 0.5 + 0.5;
+    if (any(previous_uv < vec2<f32>(0.0)) || any(previous_uv > vec2<f32>(1.0))) {
+        // Just came into view
+        return Resolved(color, color);
+    }
+    let history = clamp(textureSampleLevel(t_history, s_history, previous_uv, 0.0).rgb, low, high);
+    let resolved = vec4<f32>(mix(history, color.rgb, taa.blend), color.a);
+    return Resolved(resolved, resolved);
+}
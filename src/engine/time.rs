@@ -0,0 +1,162 @@
+//! Monotonic clock that also works in the browser, where `std::time::Instant` panics, and
+//! the frame clock everything that animates or simulates reads its time from.
+//!
+//! `Clock` keeps two times: real time, which always runs, and simulation time, which can be
+//! paused or scaled to play the world in slow motion while debugging. Menus, carets and
+//! anything else the player works with run on real time; the world runs on simulation time.
+//! Frames are timed by the clock itself, so the world keeps pace whatever the frame rate,
+//! and whatever runs once per frame takes its time from the clock rather than its own
+//! `Instant::now()`.
+
+use std::time::Duration;
+
+#[cfg(not(all(target_arch = "wasm32", feature = "web")))]
+pub use std::time::Instant;
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+pub use web_time::Instant;
+
+/// Slowest and fastest the simulation can be made to run
+pub const MIN_TIME_SCALE: f32 = 1.0 / 16.0;
+pub const MAX_TIME_SCALE: f32 = 4.0;
+/// Longest real time one frame stands for. A hitch, a stop in the debugger or a window
+/// hidden for a while moves the world on this much rather than all at once.
+pub const MAX_FRAME_DELTA: f32 = 0.1;
+/// Times handed to shaders wrap around after this many seconds, so an f32 keeps its
+/// precision however long the game has run
+pub const SHADER_TIME_PERIOD: f64 = 3600.0;
+
+/// The times shaders animate with this frame, in seconds
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FrameTime {
+    pub simulation: f32,
+    pub real: f32,
+}
+
+pub struct Clock {
+    started: Instant,
+    /// When the current frame began
+    now: Instant,
+    real_delta: f32,
+    delta: f32,
+    simulation: f64,
+    scale: f32,
+    paused: bool,
+    frames: u64,
+    ticks: u64,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            now,
+            real_delta: 0.0,
+            delta: 0.0,
+            simulation: 0.0,
+            scale: 1.0,
+            paused: false,
+            frames: 0,
+            ticks: 0,
+        }
+    }
+
+    /// Starts a frame now and returns how far the simulation moves in it, which is 0 while
+    /// paused. The frame stands for the real time since the last, up to MAX_FRAME_DELTA.
+    pub fn begin_frame(&mut self) -> f32 {
+        let now = Instant::now();
+        let real_delta = now.saturating_duration_since(self.now).as_secs_f32().min(MAX_FRAME_DELTA);
+        self.step(now, real_delta)
+    }
+
+    /// Starts a frame standing for `real_delta` seconds, however long it has really been,
+    /// and returns how far the simulation moves in it
+    pub fn advance(&mut self, real_delta: f32) -> f32 {
+        self.step(Instant::now(), real_delta)
+    }
+
+    fn step(&mut self, now: Instant, real_delta: f32) -> f32 {
+        self.now = now;
+        self.real_delta = real_delta.max(0.0);
+        self.delta = if self.paused { 0.0 } else { self.real_delta * self.scale };
+        self.simulation += self.delta as f64;
+        self.frames += 1;
+        if !self.paused {
+            self.ticks += 1;
+        }
+        self.delta
+    }
+
+    /// When the current frame began; everything in a frame sees the same moment
+    pub fn now(&self) -> Instant {
+        self.now
+    }
+
+    /// How long before the current frame `earlier` was
+    pub fn since(&self, earlier: Instant) -> Duration {
+        self.now.saturating_duration_since(earlier)
+    }
+
+    /// Seconds of real time from the clock's start to the current frame
+    pub fn real_seconds(&self) -> f64 {
+        self.since(self.started).as_secs_f64()
+    }
+
+    /// Seconds the simulation has run, leaving out time paused and scaled with the speed
+    pub fn simulation_seconds(&self) -> f64 {
+        self.simulation
+    }
+
+    /// Seconds of real time the current frame stands for
+    pub fn real_delta(&self) -> f32 {
+        self.real_delta
+    }
+
+    /// Seconds the simulation moves in the current frame
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    /// Frames begun, paused or not
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Frames the simulation has stepped through
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn frame_time(&self) -> FrameTime {
+        FrameTime {
+            simulation: (self.simulation % SHADER_TIME_PERIOD) as f32,
+            real: (self.real_seconds() % SHADER_TIME_PERIOD) as f32,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// How many times faster than real time the simulation runs
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Sets the simulation speed, clamped to MIN_TIME_SCALE..=MAX_TIME_SCALE, and returns
+    /// the speed set
+    pub fn set_scale(&mut self, scale: f32) -> f32 {
+        self.scale = if scale.is_finite() { scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE) } else { 1.0 };
+        self.scale
+    }
+}
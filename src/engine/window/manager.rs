@@ -1,13 +1,27 @@
 //! Window management implementation.
 
+use std::sync::Arc;
 use winit::window::{Window, WindowId};
 use winit::event::WindowEvent;
 use winit::event_loop::ActiveEventLoop;
 use log::error;
 
 pub struct WindowManager {
-    pub window: Option<Window>,
+    /// Shared so surfaces can own a handle to it
+    pub window: Option<Arc<Window>>,
     pub size: Option<winit::dpi::PhysicalSize<u32>>,
+    /// Set while the compositor reports the window as fully hidden
+    pub occluded: bool,
+    /// Set between the platform's suspend and resume, when the window has no surface
+    pub suspended: bool,
+    /// Physical pixels per logical pixel on the monitor the window is on
+    pub scale_factor: f64,
+}
+
+impl Default for WindowManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl WindowManager {
@@ -15,6 +29,9 @@ impl WindowManager {
         Self {
             window: None,
             size: None,
+            occluded: false,
+            suspended: false,
+            scale_factor: 1.0,
         }
     }
 
@@ -27,20 +44,23 @@ impl WindowManager {
         
         let size = window.inner_size();
         self.size = Some(size);
-        self.window = Some(window);
+        self.scale_factor = window.scale_factor();
+        self.window = Some(Arc::new(window));
         Ok(())
     }
 
     pub fn set_window(&mut self, window: Window) {
         let size = window.inner_size();
         self.size = Some(size);
-        self.window = Some(window);
+        self.scale_factor = window.scale_factor();
+        self.window = Some(Arc::new(window));
     }
 
     pub fn set_window_size(&mut self, size: winit::dpi::PhysicalSize<u32>) {
         self.size = Some(size);
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn handle_window_event(
         &mut self,
         _event_loop: &ActiveEventLoop,
@@ -82,7 +102,27 @@ impl WindowManager {
     }
 
     pub fn get_window(&self) -> Option<&Window> {
-        self.window.as_ref()
+        self.window.as_deref()
+    }
+
+    /// Called on ScaleFactorChanged, e.g. when the window moves to another monitor
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    pub fn set_occluded(&mut self, occluded: bool) {
+        self.occluded = occluded;
+    }
+
+    pub fn set_suspended(&mut self, suspended: bool) {
+        self.suspended = suspended;
+    }
+
+    /// False while suspended, minimized, occluded or zero-sized, when there is nothing to present to
+    pub fn is_renderable(&self) -> bool {
+        let Some(window) = &self.window else { return false };
+        let has_area = self.size.is_some_and(|s| s.width > 0 && s.height > 0);
+        has_area && !self.occluded && !self.suspended && window.is_minimized() != Some(true)
     }
 
     pub fn get_size(&self) -> Option<winit::dpi::PhysicalSize<u32>> {
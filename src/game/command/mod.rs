@@ -0,0 +1,5 @@
+//! Command parsing and permissions shared by the server console, RCON, and the in-game console.
+
+pub mod registry;
+
+pub use registry::{CommandError, CommandRegistry, CommandSender, CommandSpec, ParsedCommand, PermissionLevel};
@@ -0,0 +1,117 @@
+//! Command registry implementation.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Who may run a command. Levels are ordered, so a sender may run anything at or below
+/// its own level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PermissionLevel {
+    Player,
+    Moderator,
+    Admin,
+    /// The server's own stdin console
+    Console,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandSender {
+    pub name: String,
+    pub permission: PermissionLevel,
+}
+
+impl CommandSender {
+    pub fn new(name: impl Into<String>, permission: PermissionLevel) -> Self {
+        Self { name: name.into(), permission }
+    }
+
+    pub fn console() -> Self {
+        Self::new("Server", PermissionLevel::Console)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub permission: PermissionLevel,
+    pub min_args: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedCommand {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl ParsedCommand {
+    /// Arguments from `index` onwards joined back into one string, for free-text arguments
+    pub fn rest(&self, index: usize) -> String {
+        self.args.get(index..).map(|a| a.join(" ")).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    Empty,
+    Unknown(String),
+    PermissionDenied(String),
+    Usage(String),
+    Failed(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Empty => write!(f, "No command given"),
+            CommandError::Unknown(name) => write!(f, "Unknown command '{}'", name),
+            CommandError::PermissionDenied(name) => write!(f, "You do not have permission to use '{}'", name),
+            CommandError::Usage(usage) => write!(f, "Usage: {}", usage),
+            CommandError::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<&'static str, CommandSpec>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, spec: CommandSpec) {
+        self.commands.insert(spec.name, spec);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CommandSpec> {
+        self.commands.get(name)
+    }
+
+    /// Commands the sender is allowed to run, in name order
+    pub fn available<'a>(&'a self, sender: &'a CommandSender) -> impl Iterator<Item = &'a CommandSpec> {
+        self.commands.values().filter(move |spec| sender.permission >= spec.permission)
+    }
+
+    /// Splits a line such as "/kick Steve griefing" and checks it against the registry
+    pub fn parse(&self, sender: &CommandSender, line: &str) -> Result<ParsedCommand, CommandError> {
+        let line = line.trim();
+        let line = line.strip_prefix('/').unwrap_or(line);
+        let mut parts = line.split_whitespace();
+        let name = parts.next().ok_or(CommandError::Empty)?.to_lowercase();
+        let spec = self.commands.get(name.as_str()).ok_or_else(|| CommandError::Unknown(name.clone()))?;
+        if sender.permission < spec.permission {
+            return Err(CommandError::PermissionDenied(name));
+        }
+        let args: Vec<String> = parts.map(str::to_string).collect();
+        if args.len() < spec.min_args {
+            return Err(CommandError::Usage(spec.usage.to_string()));
+        }
+        Ok(ParsedCommand { name, args })
+    }
+}
@@ -0,0 +1,137 @@
+//! Brush shapes and the block changes they make.
+
+use crate::game::editor::history::BlockEdit;
+use crate::game::world::chunk::BlockType;
+use crate::game::world::chunk_manager::ChunkManager;
+
+pub const MIN_BRUSH_RADIUS: i32 = 0;
+pub const MAX_BRUSH_RADIUS: i32 = 16;
+/// Blocks the brush can paint with, in the order the number keys select them
+pub const BRUSH_MATERIALS: [BlockType; 3] = [BlockType::Grass, BlockType::Dirt, BlockType::Stone];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrushShape {
+    Sphere,
+    Cube,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrushMode {
+    /// Sets every block in the shape to the material
+    Fill,
+    /// Clears every block in the shape
+    Erase,
+    /// Repaints solid blocks with the material, leaving air alone
+    Replace,
+    /// Fills hollows and shaves off bumps by majority vote of each block's neighbours
+    Smooth,
+}
+
+impl BrushMode {
+    pub fn next(self) -> Self {
+        match self {
+            BrushMode::Fill => BrushMode::Erase,
+            BrushMode::Erase => BrushMode::Replace,
+            BrushMode::Replace => BrushMode::Smooth,
+            BrushMode::Smooth => BrushMode::Fill,
+        }
+    }
+
+    /// Whether the brush is centred on the empty block in front of the targeted face rather
+    /// than on the targeted block itself
+    pub fn builds_outward(self) -> bool {
+        self == BrushMode::Fill
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Brush {
+    pub shape: BrushShape,
+    pub mode: BrushMode,
+    /// 0 edits a single block
+    pub radius: i32,
+    pub material: BlockType,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Brush {
+    pub fn new() -> Self {
+        Self { shape: BrushShape::Sphere, mode: BrushMode::Fill, radius: 2, material: BlockType::Stone }
+    }
+
+    pub fn adjust_radius(&mut self, steps: i32) {
+        self.radius = (self.radius + steps).clamp(MIN_BRUSH_RADIUS, MAX_BRUSH_RADIUS);
+    }
+
+    /// Blocks covered by the brush when centred on `center`
+    pub fn cells(&self, center: (i32, i32, i32)) -> Vec<(i32, i32, i32)> {
+        let r = self.radius;
+        // The half-block margin rounds small spheres out instead of leaving a plus sign
+        let limit = (r as f32 + 0.5).powi(2);
+        let mut cells = Vec::new();
+        for dx in -r..=r {
+            for dy in -r..=r {
+                for dz in -r..=r {
+                    if self.shape == BrushShape::Sphere && (dx * dx + dy * dy + dz * dz) as f32 > limit {
+                        continue;
+                    }
+                    cells.push((center.0 + dx, center.1 + dy, center.2 + dz));
+                }
+            }
+        }
+        cells
+    }
+
+    /// The changes one stroke at `center` would make. Blocks in unloaded chunks are skipped.
+    /// Every block is decided from the world as it was before the stroke.
+    pub fn plan(&self, chunks: &ChunkManager, center: (i32, i32, i32)) -> Vec<BlockEdit> {
+        self.cells(center)
+            .into_iter()
+            .filter_map(|pos| {
+                let before = chunks.get_block(pos.0, pos.1, pos.2)?;
+                let after = match self.mode {
+                    BrushMode::Fill => self.material,
+                    BrushMode::Erase => BlockType::Air,
+                    BrushMode::Replace if before.is_solid() => self.material,
+                    BrushMode::Replace => before,
+                    BrushMode::Smooth => smoothed(chunks, pos, before),
+                };
+                (after != before).then_some(BlockEdit { pos, before, after })
+            })
+            .collect()
+    }
+}
+
+/// What a block becomes when smoothed: solid if most of its 26 neighbours are, taking the
+/// most common neighbouring material
+fn smoothed(chunks: &ChunkManager, pos: (i32, i32, i32), current: BlockType) -> BlockType {
+    let mut counts = [0u32; 256];
+    let mut solid = 0;
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            for dz in -1..=1 {
+                if (dx, dy, dz) == (0, 0, 0) {
+                    continue;
+                }
+                if let Some(block) = chunks.get_block(pos.0 + dx, pos.1 + dy, pos.2 + dz).filter(|b| b.is_solid()) {
+                    counts[block.id() as usize] += 1;
+                    solid += 1;
+                }
+            }
+        }
+    }
+    match solid {
+        0..=12 => BlockType::Air,
+        13 => current,
+        _ if current.is_solid() => current,
+        _ => (0..counts.len())
+            .max_by_key(|&id| counts[id])
+            .and_then(|id| BlockType::from_id(id as u8))
+            .unwrap_or(current),
+    }
+}
@@ -0,0 +1,87 @@
+//! Undo and redo of editor strokes.
+
+use crate::game::world::chunk::BlockType;
+use crate::game::world::chunk_manager::ChunkManager;
+
+/// Strokes kept for undo before the oldest are forgotten
+pub const DEFAULT_UNDO_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockEdit {
+    pub pos: (i32, i32, i32),
+    pub before: BlockType,
+    pub after: BlockType,
+}
+
+pub struct EditHistory {
+    /// Oldest first; each entry is one stroke
+    undo: Vec<Vec<BlockEdit>>,
+    redo: Vec<Vec<BlockEdit>>,
+    pub limit: usize,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditHistory {
+    pub fn new() -> Self {
+        Self { undo: Vec::new(), redo: Vec::new(), limit: DEFAULT_UNDO_LIMIT }
+    }
+
+    /// Applies a stroke and records it for undo. A new stroke discards anything undone.
+    pub fn apply(&mut self, chunks: &mut ChunkManager, edits: Vec<BlockEdit>) -> usize {
+        let applied: Vec<BlockEdit> = edits.into_iter()
+            .filter_map(|edit| {
+                // Record what was really there, in case the world changed since planning
+                let before = chunks.set_block(edit.pos, edit.after)?;
+                (before != edit.after).then_some(BlockEdit { before, ..edit })
+            })
+            .collect();
+        let count = applied.len();
+        if count > 0 {
+            self.redo.clear();
+            self.undo.push(applied);
+            let excess = self.undo.len().saturating_sub(self.limit.max(1));
+            self.undo.drain(..excess);
+        }
+        count
+    }
+
+    /// Reverts the latest stroke, returning how many blocks changed
+    pub fn undo(&mut self, chunks: &mut ChunkManager) -> Option<usize> {
+        let stroke = self.undo.pop()?;
+        for edit in stroke.iter().rev() {
+            chunks.set_block(edit.pos, edit.before);
+        }
+        let count = stroke.len();
+        self.redo.push(stroke);
+        Some(count)
+    }
+
+    /// Reapplies the latest undone stroke
+    pub fn redo(&mut self, chunks: &mut ChunkManager) -> Option<usize> {
+        let stroke = self.redo.pop()?;
+        for edit in &stroke {
+            chunks.set_block(edit.pos, edit.after);
+        }
+        let count = stroke.len();
+        self.undo.push(stroke);
+        Some(count)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
@@ -0,0 +1,189 @@
+//! Terrain editor mode: sculpt the world with brushes, undo mistakes, and select and
+//! measure regions.
+//!
+//! Edits are made to the client's copy of the world only.
+
+pub mod brush;
+pub mod history;
+pub mod selection;
+
+pub use brush::{Brush, BrushMode, BrushShape, BRUSH_MATERIALS};
+pub use history::{BlockEdit, EditHistory};
+pub use selection::{Measurement, Selection, SelectionStats};
+
+use glam::Vec3;
+use log::info;
+use winit::keyboard::{KeyCode, ModifiersState};
+
+use crate::engine::graphics::Overlay;
+use crate::game::world::camera::Camera;
+use crate::game::world::chunk::BlockType;
+use crate::game::world::chunk_manager::ChunkManager;
+use crate::game::world::raycast::{raycast_targets, BlockHit};
+
+/// How far away the editor can target blocks
+pub const EDITOR_REACH: f32 = 64.0;
+
+const SELECTION_FILL: [f32; 4] = [0.3, 0.6, 1.0, 0.2];
+const SELECTION_EDGE: [f32; 4] = [0.5, 0.8, 1.0, 1.0];
+const MEASURE_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+const HUD_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+#[derive(Default)]
+pub struct Editor {
+    pub brush: Brush,
+    pub history: EditHistory,
+    pub selection: Selection,
+    pub measurement: Measurement,
+    /// Block counts of the selection, refreshed by `update` after anything changes it
+    stats: Option<SelectionStats>,
+    stats_stale: bool,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The block under the crosshair
+    pub fn target(chunks: &ChunkManager, camera: &Camera) -> Option<BlockHit> {
+        raycast_targets(chunks, camera.position, camera.forward(), EDITOR_REACH)
+    }
+
+    /// Per-frame upkeep
+    pub fn update(&mut self, chunks: &ChunkManager) {
+        if self.stats_stale {
+            self.stats = self.selection.stats(chunks);
+            self.stats_stale = false;
+        }
+    }
+
+    /// Applies the brush where the crosshair meets the terrain. Returns the number of blocks
+    /// changed.
+    pub fn stroke(&mut self, chunks: &mut ChunkManager, camera: &Camera) -> usize {
+        let Some(hit) = Self::target(chunks, camera) else { return 0 };
+        let center = if self.brush.mode.builds_outward() {
+            (hit.block.0 + hit.normal.0, hit.block.1 + hit.normal.1, hit.block.2 + hit.normal.2)
+        } else {
+            hit.block
+        };
+        let edits = self.brush.plan(chunks, center);
+        self.stats_stale = true;
+        self.history.apply(chunks, edits)
+    }
+
+    /// Uses the targeted block as the next selection corner
+    pub fn select(&mut self, chunks: &ChunkManager, camera: &Camera) {
+        if let Some(hit) = Self::target(chunks, camera) {
+            self.selection.pick(hit.block);
+            self.stats_stale = true;
+            if let Some((x, y, z)) = self.selection.size() {
+                info!("Selection: {}x{}x{} ({} blocks)", x, y, z, self.selection.volume());
+            }
+        }
+    }
+
+    /// Uses the point under the crosshair as the next measurement end
+    pub fn measure(&mut self, chunks: &ChunkManager, camera: &Camera) {
+        if let Some(hit) = Self::target(chunks, camera) {
+            self.measurement.pick(camera.position + camera.forward().normalize_or_zero() * hit.distance);
+            if let Some(distance) = self.measurement.distance() {
+                info!("Distance: {:.2} blocks", distance);
+            }
+        }
+    }
+
+    /// Handles editor shortcuts, returning true if the key was one
+    pub fn handle_key(&mut self, keycode: KeyCode, modifiers: ModifiersState, chunks: &mut ChunkManager, camera: &Camera) -> bool {
+        if modifiers.control_key() {
+            let redo = match keycode {
+                KeyCode::KeyZ => modifiers.shift_key(),
+                KeyCode::KeyY => true,
+                _ => return false,
+            };
+            let (verb, result) = if redo {
+                ("redo", self.history.redo(chunks))
+            } else {
+                ("undo", self.history.undo(chunks))
+            };
+            self.stats_stale = true;
+            match result {
+                Some(count) => info!("Editor: {} of {} blocks", verb, count),
+                None => info!("Editor: nothing to {}", verb),
+            }
+            return true;
+        }
+        match keycode {
+            KeyCode::BracketLeft => self.brush.adjust_radius(-1),
+            KeyCode::BracketRight => self.brush.adjust_radius(1),
+            KeyCode::KeyB => {
+                self.brush.shape = match self.brush.shape {
+                    BrushShape::Sphere => BrushShape::Cube,
+                    BrushShape::Cube => BrushShape::Sphere,
+                };
+            }
+            KeyCode::Tab => self.brush.mode = self.brush.mode.next(),
+            KeyCode::Digit1 => self.brush.material = BRUSH_MATERIALS[0],
+            KeyCode::Digit2 => self.brush.material = BRUSH_MATERIALS[1],
+            KeyCode::Digit3 => self.brush.material = BRUSH_MATERIALS[2],
+            KeyCode::KeyG => {
+                self.select(chunks, camera);
+                return true;
+            }
+            KeyCode::KeyM => {
+                self.measure(chunks, camera);
+                return true;
+            }
+            KeyCode::KeyC => {
+                self.selection.clear();
+                self.measurement.clear();
+                self.stats = None;
+                return true;
+            }
+            _ => return false,
+        }
+        info!("Brush: {:?} {:?}, radius {}, {:?}", self.brush.mode, self.brush.shape, self.brush.radius, self.brush.material);
+        true
+    }
+
+    /// Adds the selection, measurement and editor HUD to this frame's overlay
+    pub fn draw_overlay(&self, overlay: &mut Overlay, text_scale: f32) {
+        if let Some((min, max)) = self.selection.world_bounds() {
+            // Grown slightly so the faces don't fight with the terrain's
+            let margin = Vec3::splat(0.01);
+            overlay.add_box(min - margin, max + margin, SELECTION_FILL);
+            overlay.add_box_outline(min - margin, max + margin, SELECTION_EDGE);
+        }
+        for point in [self.measurement.from, self.measurement.to].into_iter().flatten() {
+            overlay.add_box(point - Vec3::splat(0.08), point + Vec3::splat(0.08), MEASURE_COLOR);
+        }
+        if let (Some(from), Some(to)) = (self.measurement.from, self.measurement.to) {
+            overlay.add_line(from, to, MEASURE_COLOR);
+        }
+        overlay.add_label(8.0 * text_scale, 8.0 * text_scale, text_scale, HUD_COLOR, &self.hud_text());
+    }
+
+    fn hud_text(&self) -> String {
+        let brush = &self.brush;
+        let mut text = format!("EDITOR  {:?} {:?} R{} {:?}", brush.mode, brush.shape, brush.radius, brush.material);
+        if let Some((x, y, z)) = self.selection.size() {
+            text += &format!("\nSELECTION {}X{}X{} = {} BLOCKS", x, y, z, self.selection.volume());
+            match &self.stats {
+                Some(stats) => {
+                    text += &format!("\n  SOLID {}", stats.solid());
+                    for block in [BlockType::Grass, BlockType::Dirt, BlockType::Stone] {
+                        text += &format!("  {:?} {}", block, stats.count(block));
+                    }
+                    if stats.unloaded > 0 {
+                        text += &format!("  UNLOADED {}", stats.unloaded);
+                    }
+                }
+                None => text += "\n  TOO LARGE TO COUNT",
+            }
+        }
+        if let Some(distance) = self.measurement.distance() {
+            text += &format!("\nDISTANCE {:.2}", distance);
+        }
+        text
+    }
+}
@@ -0,0 +1,133 @@
+//! Box selections and point-to-point measurements for the editor.
+
+use glam::Vec3;
+
+use crate::game::world::chunk::BlockType;
+use crate::game::world::chunk_manager::ChunkManager;
+
+/// Selections larger than this are not counted, to keep the editor responsive
+pub const MAX_COUNTED_BLOCKS: u64 = 1 << 22;
+
+type BlockPos = (i32, i32, i32);
+
+/// A box of blocks between two corners, both inclusive
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    pub first: Option<BlockPos>,
+    pub second: Option<BlockPos>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SelectionStats {
+    /// Indexed by block id
+    pub counts: Vec<u64>,
+    /// Blocks in chunks that are not loaded, so could not be counted
+    pub unloaded: u64,
+}
+
+impl SelectionStats {
+    pub fn count(&self, block_type: BlockType) -> u64 {
+        self.counts.get(block_type.id() as usize).copied().unwrap_or(0)
+    }
+
+    pub fn solid(&self) -> u64 {
+        self.counts.iter().skip(BlockType::Air.id() as usize + 1).sum()
+    }
+}
+
+impl Selection {
+    /// Sets the first corner, then the second; a third pick starts a new selection
+    pub fn pick(&mut self, block: BlockPos) {
+        match (self.first, self.second) {
+            (Some(_), None) => self.second = Some(block),
+            _ => {
+                self.first = Some(block);
+                self.second = None;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.first = None;
+        self.second = None;
+    }
+
+    /// Inclusive block bounds; a single picked corner selects just that block
+    pub fn bounds(&self) -> Option<(BlockPos, BlockPos)> {
+        let a = self.first?;
+        let b = self.second.unwrap_or(a);
+        Some(((a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)), (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2))))
+    }
+
+    /// Size in blocks along each axis
+    pub fn size(&self) -> Option<(u32, u32, u32)> {
+        let (min, max) = self.bounds()?;
+        Some(((max.0 - min.0 + 1) as u32, (max.1 - min.1 + 1) as u32, (max.2 - min.2 + 1) as u32))
+    }
+
+    pub fn volume(&self) -> u64 {
+        self.size().map_or(0, |(x, y, z)| x as u64 * y as u64 * z as u64)
+    }
+
+    /// World-space box around the selected blocks
+    pub fn world_bounds(&self) -> Option<(Vec3, Vec3)> {
+        let (min, max) = self.bounds()?;
+        let corner = |b: BlockPos| Vec3::new(b.0 as f32, b.1 as f32, b.2 as f32);
+        Some((corner(min) - Vec3::splat(0.5), corner(max) + Vec3::splat(0.5)))
+    }
+
+    /// Counts the selected blocks by type, or None if the selection is too big to count
+    pub fn stats(&self, chunks: &ChunkManager) -> Option<SelectionStats> {
+        let (min, max) = self.bounds()?;
+        if self.volume() > MAX_COUNTED_BLOCKS {
+            return None;
+        }
+        let mut stats = SelectionStats::default();
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 {
+                    match chunks.get_block(x, y, z) {
+                        Some(block) => {
+                            let id = block.id() as usize;
+                            if stats.counts.len() <= id {
+                                stats.counts.resize(id + 1, 0);
+                            }
+                            stats.counts[id] += 1;
+                        }
+                        None => stats.unloaded += 1,
+                    }
+                }
+            }
+        }
+        Some(stats)
+    }
+}
+
+/// Distance between two picked points
+#[derive(Debug, Clone, Default)]
+pub struct Measurement {
+    pub from: Option<Vec3>,
+    pub to: Option<Vec3>,
+}
+
+impl Measurement {
+    /// Sets the start point, then the end point; a third pick starts a new measurement
+    pub fn pick(&mut self, point: Vec3) {
+        match (self.from, self.to) {
+            (Some(_), None) => self.to = Some(point),
+            _ => {
+                self.from = Some(point);
+                self.to = None;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.from = None;
+        self.to = None;
+    }
+
+    pub fn distance(&self) -> Option<f32> {
+        Some(self.from?.distance(self.to?))
+    }
+}
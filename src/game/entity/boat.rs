@@ -0,0 +1,91 @@
+//! Boat steering and floating.
+//!
+//! A boat turns and paddles along its yaw from its rider's steering, and floats on water by
+//! springing towards the surface instead of falling. On land it barely moves.
+
+use glam::Vec3;
+
+use crate::game::entity::entity::Entity;
+use crate::game::world::chunk::BlockType;
+use crate::game::world::chunk_manager::ChunkManager;
+use crate::game::world::fluid;
+
+/// Top speed on water, in blocks per second
+pub const BOAT_SPEED: f32 = 6.0;
+/// Radians per second at full turn
+pub const BOAT_TURN_RATE: f32 = 2.0;
+/// Height of the rider's eye above the boat's center
+pub const RIDER_EYE_HEIGHT: f32 = 1.0;
+const BOAT_ACCELERATION: f32 = 8.0;
+/// Fraction of thrust a boat keeps on land
+const LAND_GRIP: f32 = 0.1;
+/// Depth of the hull below the water surface once settled
+const DRAFT: f32 = 0.15;
+const BUOYANCY: f32 = 30.0;
+/// Damps bobbing on the water
+const BOB_DAMPING: f32 = 8.0;
+/// Fraction of velocity kept per second on water
+const WATER_DRAG: f32 = 0.3;
+
+/// What the rider asks of the boat, each in -1..=1
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Steering {
+    /// Positive paddles forward
+    pub forward: f32,
+    /// Positive turns right
+    pub turn: f32,
+}
+
+impl Steering {
+    pub fn new(forward: f32, turn: f32) -> Self {
+        Self { forward: forward.clamp(-1.0, 1.0), turn: turn.clamp(-1.0, 1.0) }
+    }
+}
+
+/// Direction a boat with this yaw travels in
+pub fn heading(yaw: f32) -> Vec3 {
+    Vec3::new(yaw.cos(), 0.0, yaw.sin())
+}
+
+/// Where a rider's eye sits on the boat
+pub fn rider_eye(boat: &Entity) -> Vec3 {
+    boat.position + Vec3::Y * RIDER_EYE_HEIGHT
+}
+
+/// Height of the highest water surface in the column the boat's hull spans
+fn water_surface(chunks: &ChunkManager, boat: &Entity) -> Option<f32> {
+    let half = boat.kind.half_extents();
+    let top = ChunkManager::block_coords(boat.position + Vec3::Y * half.y);
+    let bottom = ChunkManager::block_coords(boat.position - Vec3::Y * (half.y + 0.5));
+    (bottom.1..=top.1).rev().find_map(|y| match chunks.get_block(top.0, y, top.2) {
+        Some(BlockType::Water(level)) => Some(y as f32 - 0.5 + fluid::surface_height(level)),
+        _ => None,
+    })
+}
+
+/// Turns and paddles the boat, and floats it if it is on water. Returns whether it is
+/// afloat, in which case the caller must not apply gravity or ground friction.
+pub fn step(boat: &mut Entity, chunks: &ChunkManager, delta_time: f32) -> bool {
+    let steering = boat.steering;
+    boat.yaw += steering.turn * BOAT_TURN_RATE * delta_time;
+    let surface = water_surface(chunks, boat);
+    let grip = if surface.is_some() { 1.0 } else { LAND_GRIP };
+    let heading = heading(boat.yaw);
+    // Only the speed along the heading is kept, so the boat does not drift sideways in turns
+    let along = Vec3::new(boat.velocity.x, 0.0, boat.velocity.z).dot(heading);
+    let target = steering.forward * BOAT_SPEED * grip;
+    let along = along + (target - along).clamp(-BOAT_ACCELERATION * delta_time, BOAT_ACCELERATION * delta_time);
+    boat.velocity.x = heading.x * along;
+    boat.velocity.z = heading.z * along;
+
+    let Some(surface) = surface else { return false };
+    let rest = surface + boat.kind.half_extents().y - DRAFT;
+    boat.velocity.y += ((rest - boat.position.y) * BUOYANCY - boat.velocity.y * BOB_DAMPING) * delta_time;
+    if steering.forward == 0.0 {
+        let drag = WATER_DRAG.powf(delta_time);
+        boat.velocity.x *= drag;
+        boat.velocity.z *= drag;
+    }
+    boat.on_ground = false;
+    true
+}
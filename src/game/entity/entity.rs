@@ -0,0 +1,219 @@
+//! Entity implementation.
+
+use glam::Vec3;
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::engine::math::Aabb;
+use crate::game::entity::boat::Steering;
+use crate::game::entity::projectile::Projectile;
+use crate::game::item::ItemStack;
+
+/// Seconds an entity flashes red after taking damage
+pub const HURT_FLASH_DURATION: f32 = 0.3;
+/// Seconds between lighting TNT and its explosion
+pub const TNT_FUSE: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntityKind {
+    Mob,
+    Arrow,
+    ThrownItem,
+    /// An item lying in the world, e.g. a harvested crop
+    ItemDrop,
+    /// Lit TNT counting down to its explosion
+    PrimedTnt,
+    /// Floats on water and carries a player
+    Boat,
+}
+
+impl EntityKind {
+    pub fn half_extents(&self) -> Vec3 {
+        match self {
+            EntityKind::Mob => Vec3::new(0.3, 0.9, 0.3),
+            EntityKind::Arrow => Vec3::splat(0.1),
+            EntityKind::ThrownItem => Vec3::splat(0.15),
+            EntityKind::ItemDrop => Vec3::splat(0.125),
+            EntityKind::PrimedTnt => Vec3::splat(0.49),
+            EntityKind::Boat => Vec3::new(0.7, 0.3, 0.7),
+        }
+    }
+
+    pub fn max_health(&self) -> f32 {
+        match self {
+            EntityKind::Mob => 20.0,
+            EntityKind::Boat => 8.0,
+            EntityKind::Arrow | EntityKind::ThrownItem | EntityKind::ItemDrop | EntityKind::PrimedTnt => 1.0,
+        }
+    }
+
+    /// Whether damage is ignored; primed TNT only goes off when its fuse runs out
+    pub fn is_invulnerable(&self) -> bool {
+        matches!(self, EntityKind::PrimedTnt)
+    }
+
+    /// Whether it attacks players, which brings on the tension music
+    pub fn is_hostile(&self) -> bool {
+        matches!(self, EntityKind::Mob)
+    }
+
+    /// Whether a player can mount it by using it
+    pub fn is_rideable(&self) -> bool {
+        matches!(self, EntityKind::Boat)
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            EntityKind::Mob => 0,
+            EntityKind::Arrow => 1,
+            EntityKind::ThrownItem => 2,
+            EntityKind::ItemDrop => 3,
+            EntityKind::PrimedTnt => 4,
+            EntityKind::Boat => 5,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(EntityKind::Mob),
+            1 => Some(EntityKind::Arrow),
+            2 => Some(EntityKind::ThrownItem),
+            3 => Some(EntityKind::ItemDrop),
+            4 => Some(EntityKind::PrimedTnt),
+            5 => Some(EntityKind::Boat),
+            _ => None,
+        }
+    }
+
+    pub fn is_projectile(&self) -> bool {
+        matches!(self, EntityKind::Arrow | EntityKind::ThrownItem)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entity {
+    pub id: EntityId,
+    pub kind: EntityKind,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    /// Facing angle around +Y, same convention as Camera::yaw
+    pub yaw: f32,
+    pub health: f32,
+    pub hurt_timer: f32,
+    pub on_ground: bool,
+    pub projectile: Option<Projectile>,
+    /// What an ItemDrop holds
+    pub item: Option<ItemStack>,
+    /// Seconds since the entity was spawned or loaded
+    pub age: f32,
+    /// Seconds until primed TNT explodes
+    pub fuse: Option<f32>,
+    /// How the rider is steering a boat; not saved, since riders are not either
+    pub steering: Steering,
+}
+
+impl Entity {
+    pub fn new(id: EntityId, kind: EntityKind, position: Vec3) -> Self {
+        Self {
+            id,
+            kind,
+            position,
+            velocity: Vec3::ZERO,
+            yaw: 0.0,
+            health: kind.max_health(),
+            hurt_timer: 0.0,
+            on_ground: false,
+            projectile: None,
+            item: None,
+            age: 0.0,
+            fuse: (kind == EntityKind::PrimedTnt).then_some(TNT_FUSE),
+            steering: Steering::default(),
+        }
+    }
+
+    /// Bounding box centered on the entity position
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_center(self.position, self.kind.half_extents())
+    }
+
+    /// Applies damage and knockback. Hits during the hurt flash are ignored.
+    pub fn damage(&mut self, amount: f32, knockback: Vec3) -> bool {
+        if self.is_hurt() || self.kind.is_invulnerable() {
+            return false;
+        }
+        self.health -= amount;
+        self.hurt_timer = HURT_FLASH_DURATION;
+        self.velocity += knockback;
+        self.on_ground = false;
+        true
+    }
+
+    /// Turns to face the horizontal direction of travel, if moving
+    pub fn face_velocity(&mut self) {
+        if self.velocity.x.abs() > 0.01 || self.velocity.z.abs() > 0.01 {
+            self.yaw = self.velocity.z.atan2(self.velocity.x);
+        }
+    }
+
+    pub fn is_hurt(&self) -> bool {
+        self.hurt_timer > 0.0
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health <= 0.0
+    }
+
+    /// Writes the persistent state. Ids are session-local and are reassigned on load,
+    /// so projectile owners are not kept.
+    pub fn encode(&self, w: &mut ByteWriter) {
+        w.write_u8(self.kind.id());
+        w.write_vec3(self.position);
+        w.write_vec3(self.velocity);
+        w.write_f32(self.health);
+        match &self.projectile {
+            Some(projectile) => {
+                w.write_u8(1);
+                w.write_f32(projectile.damage);
+                w.write_f32(projectile.age);
+                w.write_f32(projectile.lifetime);
+            }
+            None => w.write_u8(0),
+        }
+        // Only item drops carry a stack, so saves from before items existed still load
+        if self.kind == EntityKind::ItemDrop {
+            match &self.item {
+                Some(stack) => {
+                    w.write_u8(1);
+                    stack.encode(w);
+                }
+                None => w.write_u8(0),
+            }
+        }
+        if self.kind == EntityKind::PrimedTnt {
+            w.write_f32(self.fuse.unwrap_or(0.0));
+        }
+    }
+
+    pub fn decode(r: &mut ByteReader, id: EntityId) -> Result<Self, DecodeError> {
+        let kind_id = r.read_u8()?;
+        let kind = EntityKind::from_id(kind_id)
+            .ok_or_else(|| DecodeError::Invalid(format!("unknown entity kind {}", kind_id)))?;
+        let mut entity = Entity::new(id, kind, r.read_vec3()?);
+        entity.velocity = r.read_vec3()?;
+        entity.health = r.read_f32()?;
+        if r.read_u8()? != 0 {
+            let mut projectile = Projectile::new(None, r.read_f32()?);
+            projectile.age = r.read_f32()?;
+            projectile.lifetime = r.read_f32()?;
+            entity.projectile = Some(projectile);
+        }
+        if kind == EntityKind::ItemDrop && r.read_u8()? != 0 {
+            entity.item = Some(ItemStack::decode(r)?);
+        }
+        if kind == EntityKind::PrimedTnt {
+            entity.fuse = Some(r.read_f32()?);
+        }
+        Ok(entity)
+    }
+}
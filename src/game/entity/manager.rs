@@ -0,0 +1,337 @@
+//! Entity manager implementation.
+
+use std::collections::HashMap;
+use glam::Vec3;
+use log::debug;
+
+use crate::engine::math::Aabb;
+use crate::game::entity::boat;
+use crate::game::entity::entity::{Entity, EntityId, EntityKind};
+use crate::game::entity::projectile::{ImpactTarget, Projectile, ProjectileImpact, PROJECTILE_GRAVITY};
+use crate::game::entity::spatial::SpatialHash;
+use crate::game::player::physics::{self, DEFAULT_MAX_SUBSTEP};
+use crate::game::world::behavior;
+use crate::game::world::chunk_manager::{ChunkManager, VOID_DEPTH, WORLD_FLOOR};
+use crate::game::world::raycast;
+
+pub const GRAVITY: f32 = 20.0;
+pub const GROUND_FRICTION: f32 = 10.0;
+pub const PROJECTILE_KNOCKBACK: f32 = 3.0;
+/// Largest half extent of any entity kind; used to pad spatial hash queries so entities whose
+/// center sits in a neighbouring chunk are still found
+pub const MAX_ENTITY_HALF_EXTENT: f32 = 1.0;
+/// Seconds an item drop lies in the world before it disappears
+pub const ITEM_DESPAWN_TIME: f32 = 300.0;
+
+pub struct EntityManager {
+    /// Longest distance an entity moves per substep, in blocks, so fast falls cannot pass
+    /// through a floor between ticks
+    pub max_substep: f32,
+    entities: HashMap<EntityId, Entity>,
+    spatial: SpatialHash,
+    next_id: u64,
+    impacts: Vec<ProjectileImpact>,
+    /// Where primed TNT went off since the last drain
+    detonations: Vec<Vec3>,
+}
+
+impl Default for EntityManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EntityManager {
+    pub fn new() -> Self {
+        Self {
+            max_substep: DEFAULT_MAX_SUBSTEP,
+            entities: HashMap::new(),
+            spatial: SpatialHash::new(),
+            next_id: 1,
+            impacts: Vec::new(),
+            detonations: Vec::new(),
+        }
+    }
+
+    pub fn spawn(&mut self, kind: EntityKind, position: Vec3) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        self.entities.insert(id, Entity::new(id, kind, position));
+        self.spatial.update(id, position);
+        debug!("Spawned {:?} {:?} at {:?}", kind, id, position);
+        id
+    }
+
+    pub fn spawn_projectile(
+        &mut self,
+        kind: EntityKind,
+        position: Vec3,
+        velocity: Vec3,
+        owner: Option<EntityId>,
+        damage: f32,
+    ) -> EntityId {
+        let id = self.spawn(kind, position);
+        if let Some(entity) = self.entities.get_mut(&id) {
+            entity.velocity = velocity;
+            entity.projectile = Some(Projectile::new(owner, damage));
+        }
+        id
+    }
+
+    /// Takes the projectile impacts recorded since the last call
+    pub fn drain_impacts(&mut self) -> Vec<ProjectileImpact> {
+        std::mem::take(&mut self.impacts)
+    }
+
+    /// Takes the positions of TNT whose fuse ran out since the last call
+    pub fn drain_detonations(&mut self) -> Vec<Vec3> {
+        std::mem::take(&mut self.detonations)
+    }
+
+    /// Inserts a previously saved entity under a freshly allocated id
+    pub fn insert(&mut self, mut entity: Entity) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        entity.id = id;
+        self.spatial.update(id, entity.position);
+        self.entities.insert(id, entity);
+        id
+    }
+
+    /// Inserts an entity keeping its id, for mirroring another manager (e.g. the server's)
+    pub fn insert_with_id(&mut self, entity: Entity) {
+        self.next_id = self.next_id.max(entity.id.0 + 1);
+        self.spatial.update(entity.id, entity.position);
+        self.entities.insert(entity.id, entity);
+    }
+
+    /// Moves an entity, keeping the spatial index up to date
+    pub fn set_motion(&mut self, id: EntityId, position: Vec3, velocity: Vec3) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            entity.position = position;
+            entity.velocity = velocity;
+            self.spatial.update(id, position);
+        }
+    }
+
+    /// Removes and returns every entity whose position lies in the given chunk
+    pub fn take_in_chunk(&mut self, chunk_key: (i32, i32, i32)) -> Vec<Entity> {
+        let ids: Vec<EntityId> = self.spatial.in_chunk(chunk_key).collect();
+        ids.into_iter().filter_map(|id| self.despawn(id)).collect()
+    }
+
+    pub fn despawn(&mut self, id: EntityId) -> Option<Entity> {
+        self.spatial.remove(id);
+        self.entities.remove(&id)
+    }
+
+    pub fn entities_in_chunk(&self, chunk_key: (i32, i32, i32)) -> impl Iterator<Item = &Entity> {
+        self.spatial.in_chunk(chunk_key).filter_map(|id| self.entities.get(&id))
+    }
+
+    /// Entities whose bounding box overlaps `bounds`
+    pub fn query_aabb(&self, bounds: &Aabb) -> Vec<EntityId> {
+        self.spatial.query_aabb(&bounds.expanded(MAX_ENTITY_HALF_EXTENT))
+            .into_iter()
+            .filter(|id| self.entities.get(id).is_some_and(|e| e.aabb().intersects(bounds)))
+            .collect()
+    }
+
+    /// Entities whose position is within `radius` of `center`
+    pub fn query_radius(&self, center: Vec3, radius: f32) -> Vec<EntityId> {
+        let bounds = Aabb::from_center(center, Vec3::splat(radius));
+        self.spatial.query_aabb(&bounds)
+            .into_iter()
+            .filter(|id| self.entities.get(id).is_some_and(|e| e.position.distance_squared(center) <= radius * radius))
+            .collect()
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.get_mut(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Integrates entity motion against the loaded terrain and removes dead entities
+    pub fn update(&mut self, delta_time: f32, chunk_manager: &ChunkManager) {
+        let max_substep = self.max_substep;
+        for entity in self.entities.values_mut() {
+            if entity.projectile.is_some() {
+                continue;
+            }
+            entity.hurt_timer = (entity.hurt_timer - delta_time).max(0.0);
+            entity.age += delta_time;
+            if entity.kind == EntityKind::ItemDrop && entity.age > ITEM_DESPAWN_TIME {
+                entity.health = 0.0;
+            }
+            // Nothing comes back out of the void
+            if entity.position.y < WORLD_FLOOR - VOID_DEPTH {
+                entity.health = 0.0;
+            }
+            if let Some(fuse) = &mut entity.fuse {
+                *fuse -= delta_time;
+                if *fuse <= 0.0 {
+                    self.detonations.push(entity.position);
+                    entity.health = 0.0;
+                }
+            }
+
+            let half = entity.kind.half_extents();
+            let feet = entity.position - Vec3::new(0.0, half.y, 0.0);
+            let below = ChunkManager::block_coords(feet - Vec3::new(0.0, 0.01, 0.0));
+            let support = chunk_manager.get_block_or_void(below.0, below.1, below.2);
+            // Entities over unloaded chunks are held in place until the terrain arrives
+            let falling = support.is_some_and(|b| !b.is_solid());
+
+            let afloat = entity.kind == EntityKind::Boat && boat::step(entity, chunk_manager, delta_time);
+            if afloat {
+                // Buoyancy stands in for gravity and the ground
+            } else if falling {
+                entity.velocity.y -= GRAVITY * delta_time;
+                entity.on_ground = false;
+            } else if entity.velocity.y <= 0.0 {
+                entity.velocity.y = 0.0;
+                entity.on_ground = true;
+                let friction = (1.0 - GROUND_FRICTION * delta_time).max(0.0);
+                entity.velocity.x *= friction;
+                entity.velocity.z *= friction;
+            }
+
+            let steps = physics::substeps(entity.velocity * delta_time, max_substep);
+            let mut motion = entity.velocity * (delta_time / steps as f32);
+            for _ in 0..steps {
+                entity.position += motion;
+                // Snap onto the top face of the block we landed in
+                let feet = entity.position - Vec3::new(0.0, half.y, 0.0);
+                let landed = ChunkManager::block_coords(feet);
+                if entity.velocity.y < 0.0 && chunk_manager.get_block(landed.0, landed.1, landed.2).is_some_and(|b| b.is_solid()) {
+                    entity.position.y = landed.1 as f32 + 0.5 + half.y;
+                    entity.velocity.y = 0.0;
+                    entity.on_ground = true;
+                    motion.y = 0.0;
+                }
+            }
+            // Boats point where they are steered, and may paddle backwards
+            if entity.kind != EntityKind::Boat {
+                entity.face_velocity();
+            }
+
+            let burn = behavior::contact_damage(chunk_manager, &entity.aabb());
+            if burn > 0.0 {
+                entity.damage(burn, Vec3::ZERO);
+            }
+        }
+
+        self.update_projectiles(delta_time, chunk_manager);
+
+        let spatial = &mut self.spatial;
+        self.entities.retain(|id, entity| {
+            if entity.is_dead() {
+                debug!("Entity {:?} died", id);
+                spatial.remove(*id);
+                return false;
+            }
+            spatial.update(*id, entity.position);
+            true
+        });
+    }
+
+    /// Moves projectiles with a swept test against voxels and entities so fast shots can't pass
+    /// through thin walls or targets between frames
+    fn update_projectiles(&mut self, delta_time: f32, chunk_manager: &ChunkManager) {
+        let projectile_ids: Vec<EntityId> = self.entities.values()
+            .filter(|e| e.projectile.is_some())
+            .map(|e| e.id)
+            .collect();
+        let mut finished = Vec::new();
+
+        for id in projectile_ids {
+            let Some(entity) = self.entities.get_mut(&id) else { continue };
+            let Some(projectile) = entity.projectile.as_mut() else { continue };
+            projectile.age += delta_time;
+            if projectile.is_expired() {
+                finished.push(id);
+                continue;
+            }
+            entity.velocity.y -= PROJECTILE_GRAVITY * delta_time;
+
+            let projectile = *projectile;
+            entity.face_velocity();
+            let start = entity.position;
+            let velocity = entity.velocity;
+            let half = entity.kind.half_extents();
+            let motion = velocity * delta_time;
+            let distance = motion.length();
+            if distance <= f32::EPSILON {
+                continue;
+            }
+            let dir = motion / distance;
+
+            let block_hit = raycast::raycast_blocks(chunk_manager, start, dir, distance);
+            let swept = Aabb::new(start.min(start + motion), start.max(start + motion))
+                .expanded(MAX_ENTITY_HALF_EXTENT + half.max_element());
+            let entity_hit = self.spatial.query_aabb(&swept)
+                .into_iter()
+                .filter_map(|other| self.entities.get(&other))
+                .filter(|other| other.id != id && Some(other.id) != projectile.owner && other.projectile.is_none())
+                .filter_map(|other| {
+                    // Inflate the target by the projectile size so the ray test matches a box sweep
+                    let bounds = other.aabb();
+                    Aabb::new(bounds.min - half, bounds.max + half)
+                        .ray_intersect(start, dir, distance)
+                        .map(|t| (other.id, t))
+                })
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+            let impact = match (block_hit, entity_hit) {
+                (Some(block), Some((target, t))) if t < block.distance => Some((ImpactTarget::Entity(target), t)),
+                (Some(block), _) => Some((ImpactTarget::Block { block: block.block, normal: block.normal }, block.distance)),
+                (None, Some((target, t))) => Some((ImpactTarget::Entity(target), t)),
+                (None, None) => None,
+            };
+
+            match impact {
+                Some((target, t)) => {
+                    let position = start + dir * t;
+                    if let ImpactTarget::Entity(target_id) = target {
+                        if let Some(target) = self.entities.get_mut(&target_id) {
+                            target.damage(projectile.damage, dir * PROJECTILE_KNOCKBACK);
+                        }
+                    }
+                    self.impacts.push(ProjectileImpact {
+                        projectile: id,
+                        owner: projectile.owner,
+                        position,
+                        velocity,
+                        damage: projectile.damage,
+                        target,
+                    });
+                    finished.push(id);
+                }
+                None => {
+                    if let Some(entity) = self.entities.get_mut(&id) {
+                        entity.position += motion;
+                    }
+                }
+            }
+        }
+
+        for id in finished {
+            self.despawn(id);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+//! Entity definitions and management.
+
+pub mod boat;
+#[allow(clippy::module_inception)]
+pub mod entity;
+pub mod manager;
+pub mod projectile;
+pub mod spatial;
+
+pub use boat::Steering;
+pub use entity::{Entity, EntityId, EntityKind};
+pub use manager::EntityManager;
+pub use projectile::{ImpactTarget, Projectile, ProjectileImpact};
+pub use spatial::SpatialHash;
@@ -0,0 +1,50 @@
+//! Projectile state and impact events.
+
+use glam::Vec3;
+
+use crate::game::entity::entity::EntityId;
+
+/// Seconds before an unimpacted projectile despawns
+pub const PROJECTILE_LIFETIME: f32 = 10.0;
+pub const PROJECTILE_GRAVITY: f32 = 12.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Projectile {
+    /// Entity that fired the projectile; it is never hit by its own shot
+    pub owner: Option<EntityId>,
+    pub damage: f32,
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+impl Projectile {
+    pub fn new(owner: Option<EntityId>, damage: f32) -> Self {
+        Self {
+            owner,
+            damage,
+            age: 0.0,
+            lifetime: PROJECTILE_LIFETIME,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.age >= self.lifetime
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImpactTarget {
+    Block { block: (i32, i32, i32), normal: (i32, i32, i32) },
+    Entity(EntityId),
+}
+
+/// Emitted when a projectile hits something; consumers spawn particles, play sounds, etc.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProjectileImpact {
+    pub projectile: EntityId,
+    pub owner: Option<EntityId>,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub damage: f32,
+    pub target: ImpactTarget,
+}
@@ -0,0 +1,72 @@
+//! Chunk-keyed spatial hash for entity lookups.
+
+use std::collections::{HashMap, HashSet};
+use glam::Vec3;
+
+use crate::engine::math::Aabb;
+use crate::game::entity::entity::EntityId;
+use crate::game::world::chunk_manager::ChunkManager;
+
+#[derive(Debug, Default)]
+pub struct SpatialHash {
+    cells: HashMap<(i32, i32, i32), HashSet<EntityId>>,
+    locations: HashMap<EntityId, (i32, i32, i32)>,
+}
+
+impl SpatialHash {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Places or moves an entity into the cell containing `position`
+    pub fn update(&mut self, id: EntityId, position: Vec3) {
+        let key = ChunkManager::chunk_key_at(position);
+        match self.locations.get(&id) {
+            Some(current) if *current == key => return,
+            Some(_) => self.remove(id),
+            None => (),
+        }
+        self.cells.entry(key).or_default().insert(id);
+        self.locations.insert(id, key);
+    }
+
+    pub fn remove(&mut self, id: EntityId) {
+        if let Some(key) = self.locations.remove(&id) {
+            if let Some(cell) = self.cells.get_mut(&key) {
+                cell.remove(&id);
+                if cell.is_empty() {
+                    self.cells.remove(&key);
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.locations.clear();
+    }
+
+    pub fn in_chunk(&self, chunk_key: (i32, i32, i32)) -> impl Iterator<Item = EntityId> + '_ {
+        self.cells.get(&chunk_key).into_iter().flatten().copied()
+    }
+
+    /// Entities whose position lies in any chunk overlapped by `bounds`. Callers that need
+    /// exact overlap should still test each entity's own box.
+    pub fn query_aabb(&self, bounds: &Aabb) -> Vec<EntityId> {
+        let min = ChunkManager::chunk_key_at(bounds.min);
+        let max = ChunkManager::chunk_key_at(bounds.max);
+        let mut out = Vec::new();
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 {
+                    out.extend(self.in_chunk((x, y, z)));
+                }
+            }
+        }
+        out
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+}
@@ -0,0 +1,176 @@
+//! Crafting: turning items laid out in a small grid into something new.
+//!
+//! Recipes come from a data file, RECIPES_PATH, which resource packs can replace. A shaped
+//! recipe needs its items in a pattern, which may sit anywhere in the grid; a shapeless one
+//! only needs the right items in any cells. Crafting uses up one item from every filled cell.
+
+use std::collections::HashMap;
+use log::warn;
+
+use crate::engine::assets::embedded::embedded;
+use crate::game::item::inventory::MAX_STACK;
+use crate::game::item::{ItemStack, ItemType};
+
+/// Recipe list, relative to a resource pack root
+pub const RECIPES_PATH: &str = "assets/recipes.txt";
+/// Width and height of the crafting grid
+pub const CRAFTING_GRID_SIZE: usize = 2;
+pub const CRAFTING_SLOTS: usize = CRAFTING_GRID_SIZE * CRAFTING_GRID_SIZE;
+
+/// Cells of the crafting grid, row by row from the top left
+pub type CraftingGrid = [Option<ItemType>; CRAFTING_SLOTS];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecipeShape {
+    /// Rows of `width` cells, trimmed to the smallest box around the items
+    Shaped { width: usize, cells: Vec<Option<ItemType>> },
+    Shapeless(Vec<ItemType>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recipe {
+    pub shape: RecipeShape,
+    pub result: ItemStack,
+}
+
+impl Recipe {
+    pub fn matches(&self, grid: &CraftingGrid) -> bool {
+        match &self.shape {
+            RecipeShape::Shaped { width, cells } => {
+                let Some((trimmed_width, trimmed)) = trim(grid) else { return false };
+                trimmed_width == *width && trimmed == *cells
+            }
+            RecipeShape::Shapeless(items) => count_items(grid.iter().flatten().copied()) == count_items(items.iter().copied()),
+        }
+    }
+}
+
+fn count_items(items: impl Iterator<Item = ItemType>) -> HashMap<ItemType, usize> {
+    let mut counts = HashMap::new();
+    for item in items {
+        *counts.entry(item).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// The grid cut down to the box around its filled cells, as (width, cells), or None if empty
+fn trim(grid: &CraftingGrid) -> Option<(usize, Vec<Option<ItemType>>)> {
+    let filled = |i: usize| grid[i].is_some();
+    let columns: Vec<usize> = (0..CRAFTING_SLOTS).filter(|&i| filled(i)).map(|i| i % CRAFTING_GRID_SIZE).collect();
+    let rows: Vec<usize> = (0..CRAFTING_SLOTS).filter(|&i| filled(i)).map(|i| i / CRAFTING_GRID_SIZE).collect();
+    let (left, right) = (*columns.iter().min()?, *columns.iter().max()?);
+    let (top, bottom) = (*rows.iter().min()?, *rows.iter().max()?);
+    let cells = (top..=bottom)
+        .flat_map(|row| (left..=right).map(move |column| grid[row * CRAFTING_GRID_SIZE + column]))
+        .collect();
+    Some((right - left + 1, cells))
+}
+
+fn parse_item(name: &str) -> Result<ItemType, String> {
+    ItemType::from_name(name).ok_or_else(|| format!("unknown item {}", name))
+}
+
+/// Parses one `<kind> <result> <count> = <ingredients>` line
+fn parse_recipe(line: &str) -> Result<Recipe, String> {
+    let (head, ingredients) = line.split_once('=').ok_or("expected = between the result and the ingredients")?;
+    let head: Vec<&str> = head.split_whitespace().collect();
+    let [kind, result, count] = head[..] else {
+        return Err("expected <shaped | shapeless> <result> <count> before =".to_string());
+    };
+    let count = count.parse::<u8>().ok().filter(|c| (1..=MAX_STACK).contains(c))
+        .ok_or_else(|| format!("count must be 1 to {}, got {}", MAX_STACK, count))?;
+    let result = ItemStack::new(parse_item(result)?, count);
+    let shape = match kind {
+        "shaped" => {
+            let rows: Vec<Vec<&str>> = ingredients.split('/').map(|row| row.split_whitespace().collect()).collect();
+            let width = rows[0].len();
+            if width == 0 || rows.iter().any(|row| row.len() != width) {
+                return Err("shaped rows must all have the same number of cells".to_string());
+            }
+            if width > CRAFTING_GRID_SIZE || rows.len() > CRAFTING_GRID_SIZE {
+                return Err(format!("shaped recipes must fit a {0}x{0} grid", CRAFTING_GRID_SIZE));
+            }
+            let cells = rows.iter().flatten()
+                .map(|&cell| if cell == "_" { Ok(None) } else { parse_item(cell).map(Some) })
+                .collect::<Result<Vec<_>, _>>()?;
+            // Store it trimmed so it lines up with a trimmed grid
+            let mut grid = [None; CRAFTING_SLOTS];
+            for (i, cell) in cells.iter().enumerate() {
+                grid[(i / width) * CRAFTING_GRID_SIZE + i % width] = *cell;
+            }
+            let (width, cells) = trim(&grid).ok_or("shaped recipes need at least one item")?;
+            RecipeShape::Shaped { width, cells }
+        }
+        "shapeless" => {
+            let items = ingredients.split_whitespace().map(parse_item).collect::<Result<Vec<_>, _>>()?;
+            if items.is_empty() || items.len() > CRAFTING_SLOTS {
+                return Err(format!("shapeless recipes need 1 to {} items", CRAFTING_SLOTS));
+            }
+            RecipeShape::Shapeless(items)
+        }
+        other => return Err(format!("expected shaped or shapeless, got {}", other)),
+    };
+    Ok(Recipe { shape, result })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RecipeBook {
+    recipes: Vec<Recipe>,
+}
+
+impl RecipeBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a recipe list, skipping blank lines and `#` comments
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut book = Self::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let recipe = parse_recipe(line).map_err(|e| format!("line {}: {}", number + 1, e))?;
+            book.add(recipe);
+        }
+        Ok(book)
+    }
+
+    /// The recipes in the resource packs, falling back to the built-in list if theirs does
+    /// not parse
+    pub fn load() -> Self {
+        let builtin = || {
+            let text = embedded(RECIPES_PATH).map(String::from_utf8_lossy).unwrap_or_default();
+            Self::parse(&text).unwrap_or_else(|e| {
+                warn!("Built-in recipes are broken, {}", e);
+                Self::new()
+            })
+        };
+        #[cfg(not(all(target_arch = "wasm32", feature = "web")))]
+        if let Ok(data) = crate::engine::assets::ResourcePacks::from_settings().read(RECIPES_PATH) {
+            match Self::parse(&String::from_utf8_lossy(&data)) {
+                Ok(book) => return book,
+                Err(e) => warn!("Failed to read {}, {}; using the built-in recipes", RECIPES_PATH, e),
+            }
+        }
+        builtin()
+    }
+
+    pub fn add(&mut self, recipe: Recipe) {
+        self.recipes.push(recipe);
+    }
+
+    pub fn len(&self) -> usize {
+        self.recipes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recipes.is_empty()
+    }
+
+    /// The first recipe the grid fits
+    pub fn find(&self, grid: &CraftingGrid) -> Option<&Recipe> {
+        self.recipes.iter().find(|recipe| recipe.matches(grid))
+    }
+}
@@ -0,0 +1,93 @@
+//! A player's inventory: a fixed number of slots, each holding one stack.
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::game::item::{ItemStack, ItemType, ToolKind};
+use crate::game::world::chunk::BlockType;
+
+pub const INVENTORY_SLOTS: usize = 27;
+/// Most items of one type a slot holds
+pub const MAX_STACK: u8 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Inventory {
+    pub slots: [Option<ItemStack>; INVENTORY_SLOTS],
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    /// Adds a stack, topping up slots of the same item before taking empty ones. Returns
+    /// what did not fit.
+    pub fn add(&mut self, mut stack: ItemStack) -> Option<ItemStack> {
+        for slot in self.slots.iter_mut().flatten().filter(|s| s.item == stack.item) {
+            let moved = stack.count.min(MAX_STACK - slot.count.min(MAX_STACK));
+            slot.count += moved;
+            stack.count -= moved;
+        }
+        for slot in self.slots.iter_mut().filter(|s| s.is_none()) {
+            if stack.count == 0 {
+                break;
+            }
+            let moved = stack.count.min(MAX_STACK);
+            *slot = Some(ItemStack::new(stack.item, moved));
+            stack.count -= moved;
+        }
+        Some(stack).filter(|s| s.count > 0)
+    }
+
+    /// How many of `item` the inventory holds across all its slots
+    pub fn count(&self, item: ItemType) -> u32 {
+        self.slots.iter().flatten().filter(|s| s.item == item).map(|s| s.count as u32).sum()
+    }
+
+    /// The tool carried for breaking `block_type`, if there is one that suits it
+    pub fn tool_for(&self, block_type: BlockType) -> Option<ToolKind> {
+        let wanted = block_type.preferred_tool()?;
+        self.slots.iter().flatten().filter_map(|s| s.item.tool()).find(|&tool| tool == wanted)
+    }
+
+    /// Takes one item from the first stack that is `wanted`
+    pub fn take_one(&mut self, wanted: impl Fn(ItemType) -> bool) -> Option<ItemType> {
+        let slot = self.slots.iter_mut().find(|s| s.is_some_and(|s| wanted(s.item)))?;
+        let stack = slot.as_mut()?;
+        let item = stack.item;
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        Some(item)
+    }
+
+    /// Empties the inventory, returning everything that was in it
+    pub fn take_all(&mut self) -> Vec<ItemStack> {
+        self.slots.iter_mut().filter_map(Option::take).collect()
+    }
+
+    pub fn encode(&self, w: &mut ByteWriter) {
+        for slot in &self.slots {
+            match slot {
+                Some(stack) => {
+                    w.write_u8(1);
+                    stack.encode(w);
+                }
+                None => w.write_u8(0),
+            }
+        }
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        let mut inventory = Self::new();
+        for slot in inventory.slots.iter_mut() {
+            if r.read_u8()? != 0 {
+                *slot = Some(ItemStack::decode(r)?);
+            }
+        }
+        Ok(inventory)
+    }
+}
@@ -0,0 +1,138 @@
+//! Item implementation.
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::game::item::tool::ToolKind;
+use crate::game::world::chunk::BlockType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemType {
+    Wheat,
+    WheatSeeds,
+    Bread,
+    Dirt,
+    Stone,
+    /// Chipped off stone broken without a pickaxe
+    Flint,
+    Pickaxe,
+    Shovel,
+    Axe,
+}
+
+impl ItemType {
+    pub const ALL: [ItemType; 9] = [
+        ItemType::Wheat,
+        ItemType::WheatSeeds,
+        ItemType::Bread,
+        ItemType::Dirt,
+        ItemType::Stone,
+        ItemType::Flint,
+        ItemType::Pickaxe,
+        ItemType::Shovel,
+        ItemType::Axe,
+    ];
+
+    /// Stable numeric id used by save files
+    pub fn id(&self) -> u8 {
+        match self {
+            ItemType::Wheat => 0,
+            ItemType::WheatSeeds => 1,
+            ItemType::Bread => 2,
+            ItemType::Dirt => 3,
+            ItemType::Stone => 4,
+            ItemType::Flint => 5,
+            ItemType::Pickaxe => 6,
+            ItemType::Shovel => 7,
+            ItemType::Axe => 8,
+        }
+    }
+
+    /// Name used in data files such as the recipe list
+    pub fn name(&self) -> &'static str {
+        match self {
+            ItemType::Wheat => "wheat",
+            ItemType::WheatSeeds => "wheat_seeds",
+            ItemType::Bread => "bread",
+            ItemType::Dirt => "dirt",
+            ItemType::Stone => "stone",
+            ItemType::Flint => "flint",
+            ItemType::Pickaxe => "pickaxe",
+            ItemType::Shovel => "shovel",
+            ItemType::Axe => "axe",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|item| item.name() == name)
+    }
+
+    /// Food points eating one restores, for items that can be eaten
+    pub fn food(&self) -> Option<f32> {
+        match self {
+            ItemType::Wheat => Some(2.0),
+            ItemType::Bread => Some(5.0),
+            _ => None,
+        }
+    }
+
+    /// The block placing one puts down, for items that are blocks
+    pub fn placed_block(&self) -> Option<BlockType> {
+        match self {
+            ItemType::Dirt => Some(BlockType::Dirt),
+            ItemType::Stone => Some(BlockType::Stone),
+            ItemType::WheatSeeds => Some(BlockType::Wheat(0)),
+            _ => None,
+        }
+    }
+
+    /// The item used up placing `block`, if survival players can place it at all
+    pub fn for_block(block: BlockType) -> Option<Self> {
+        Self::ALL.into_iter().find(|item| item.placed_block() == Some(block))
+    }
+
+    pub fn tool(&self) -> Option<ToolKind> {
+        match self {
+            ItemType::Pickaxe => Some(ToolKind::Pickaxe),
+            ItemType::Shovel => Some(ToolKind::Shovel),
+            ItemType::Axe => Some(ToolKind::Axe),
+            _ => None,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(ItemType::Wheat),
+            1 => Some(ItemType::WheatSeeds),
+            2 => Some(ItemType::Bread),
+            3 => Some(ItemType::Dirt),
+            4 => Some(ItemType::Stone),
+            5 => Some(ItemType::Flint),
+            6 => Some(ItemType::Pickaxe),
+            7 => Some(ItemType::Shovel),
+            8 => Some(ItemType::Axe),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemStack {
+    pub item: ItemType,
+    pub count: u8,
+}
+
+impl ItemStack {
+    pub fn new(item: ItemType, count: u8) -> Self {
+        Self { item, count }
+    }
+
+    pub fn encode(&self, w: &mut ByteWriter) {
+        w.write_u8(self.item.id());
+        w.write_u8(self.count);
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        let id = r.read_u8()?;
+        let item = ItemType::from_id(id).ok_or_else(|| DecodeError::Invalid(format!("unknown item {}", id)))?;
+        Ok(Self { item, count: r.read_u8()? })
+    }
+}
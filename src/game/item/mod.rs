@@ -0,0 +1,12 @@
+//! Item types, stacks and inventories.
+
+pub mod crafting;
+pub mod inventory;
+#[allow(clippy::module_inception)]
+pub mod item;
+pub mod tool;
+
+pub use crafting::{CraftingGrid, RecipeBook};
+pub use inventory::Inventory;
+pub use item::{ItemStack, ItemType};
+pub use tool::ToolKind;
@@ -0,0 +1,33 @@
+//! Tools, and how long blocks take to break with and without them.
+//!
+//! Each block has a hardness, the seconds it takes to break by hand, and may name the tool
+//! that suits it. The right tool breaks it TOOL_SPEED times faster, and some blocks, such
+//! as stone, only drop themselves when broken with it.
+
+use crate::game::world::chunk::BlockType;
+
+/// How much faster the right tool breaks a block than a bare hand
+pub const TOOL_SPEED: f32 = 4.0;
+/// Breaking time each attack on a block counts for; the client repeats attacks this often
+/// while the button is held
+pub const HIT_SECONDS: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToolKind {
+    /// Stone
+    Pickaxe,
+    /// Grass and dirt
+    Shovel,
+    /// Wooden blocks
+    Axe,
+}
+
+/// Seconds it takes to break `block_type` using `tool`, or with bare hands for None
+pub fn break_time(block_type: BlockType, tool: Option<ToolKind>) -> f32 {
+    let hardness = block_type.hardness();
+    if tool.is_some() && tool == block_type.preferred_tool() {
+        hardness / TOOL_SPEED
+    } else {
+        hardness
+    }
+}
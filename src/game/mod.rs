@@ -1,6 +1,13 @@
 //! Game-specific logic and features.
 
+pub mod command;
+pub mod editor;
+pub mod entity;
+pub mod item;
+pub mod net;
 pub mod player;
+pub mod save;
+pub mod server;
 pub mod state;
 pub mod world;
 
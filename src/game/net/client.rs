@@ -0,0 +1,195 @@
+//! Client side of the connection to a (possibly integrated) server.
+
+use glam::Vec3;
+use log::{info, warn};
+
+use crate::engine::net::Connection;
+use crate::game::entity::{Entity, EntityId, EntityManager};
+use crate::game::item::Inventory;
+use crate::game::net::edit_cache::ChunkEditCache;
+use crate::game::net::protocol::{ChunkEdit, ClientId, ClientMessage, ServerMessage};
+use crate::game::net::snapshot::SnapshotReceiver;
+use crate::game::world::chunk::{BlockType, CHUNK_SIZE};
+use crate::game::world::worldgen::WorldGen;
+
+/// Things the rest of the client needs to react to
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientEvent {
+    Welcome { position: Vec3, yaw: f32, pitch: f32 },
+    Chat(String),
+    BlockUpdate { block: (i32, i32, i32), block_type: BlockType },
+    /// The server rejected our movement and moved us back
+    PositionCorrected(Vec3),
+    Sound { name: String, position: Vec3 },
+    Health(f32),
+    Explosion { position: Vec3, power: f32 },
+    Mounted(Option<EntityId>),
+    SignText { block: (i32, i32, i32), text: String },
+    EditSign((i32, i32, i32)),
+    /// The server's time of day, in ticks
+    Time(u64),
+    /// Whether it is raining
+    Weather(bool),
+    /// Our player died here, and dropped their items unless the server let them keep them
+    Died { position: Vec3, kept_inventory: bool },
+    /// Our food level, or None when we cannot get hungry
+    Hunger(Option<f32>),
+    /// Everything we carry
+    Inventory(Inventory),
+    /// How far we are through breaking a block, 0 once we stopped
+    BreakProgress { block: (i32, i32, i32), progress: f32 },
+    /// How the server generates its world; our chunks must be generated the same way
+    WorldGen(WorldGen),
+    Disconnected(String),
+}
+
+pub struct ClientSession {
+    connection: Box<dyn Connection>,
+    pub client_id: Option<ClientId>,
+    /// Mirror of the entities the server has told us about
+    pub entities: EntityManager,
+    snapshots: SnapshotReceiver,
+    /// Edits from earlier sessions on this server. Without it every edited chunk is asked for.
+    edit_cache: Option<ChunkEditCache>,
+}
+
+impl ClientSession {
+    pub fn connect(mut connection: Box<dyn Connection>, name: &str) -> Self {
+        if let Err(e) = connection.send(&ClientMessage::Hello { name: name.to_string() }.to_bytes()) {
+            warn!("Failed to send Hello: {}", e);
+        }
+        Self {
+            connection,
+            client_id: None,
+            entities: EntityManager::new(),
+            snapshots: SnapshotReceiver::new(),
+            edit_cache: None,
+        }
+    }
+
+    /// Keeps the chunk edits the server sends in `cache`, and takes them from there when
+    /// they haven't changed since
+    pub fn set_edit_cache(&mut self, cache: ChunkEditCache) {
+        self.edit_cache = Some(cache);
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_open()
+    }
+
+    /// Hangs up; the server notices and saves the player
+    pub fn close(&mut self) {
+        self.connection.close();
+        self.save_edit_cache();
+    }
+
+    fn save_edit_cache(&mut self) {
+        if let Some(Err(e)) = self.edit_cache.as_mut().map(ChunkEditCache::save) {
+            warn!("Failed to save chunk edit cache: {}", e);
+        }
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) {
+        if let Err(e) = self.connection.send(&message.to_bytes()) {
+            warn!("Failed to send to server: {}", e);
+        }
+    }
+
+    /// Applies pending server messages, returning the events the caller must handle
+    pub fn poll(&mut self) -> Vec<ClientEvent> {
+        let mut events = Vec::new();
+        let acked = self.snapshots.latest();
+        loop {
+            let packet = match self.connection.recv() {
+                Ok(Some(packet)) => packet,
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("Connection error: {}", e);
+                    break;
+                }
+            };
+            let message = match ServerMessage::from_bytes(&packet) {
+                Ok(message) => message,
+                Err(e) => {
+                    warn!("Bad packet from server: {}", e);
+                    continue;
+                }
+            };
+            match message {
+                ServerMessage::Welcome { client, position, yaw, pitch } => {
+                    info!("Joined server as {:?}", client);
+                    self.client_id = Some(client);
+                    events.push(ClientEvent::Welcome { position, yaw, pitch });
+                }
+                ServerMessage::EntityEnter { id, kind, position, velocity } => {
+                    let mut entity = Entity::new(id, kind, position);
+                    entity.velocity = velocity;
+                    self.entities.insert_with_id(entity);
+                }
+                ServerMessage::EntityLeave { id } => {
+                    self.entities.despawn(id);
+                }
+                ServerMessage::Snapshot(snapshot) => {
+                    let entities = &mut self.entities;
+                    let Some(states) = self.snapshots.receive(&snapshot, |id| entities.get(id).is_some()) else {
+                        continue;
+                    };
+                    for (id, state) in states {
+                        entities.set_motion(*id, state.position(), state.velocity());
+                        if let Some(entity) = entities.get_mut(*id) {
+                            entity.yaw = state.yaw();
+                        }
+                    }
+                }
+                ServerMessage::BlockUpdate { block, block_type } => {
+                    events.push(ClientEvent::BlockUpdate { block, block_type });
+                }
+                ServerMessage::ChunkHash { chunk, hash } => {
+                    match self.edit_cache.as_ref().and_then(|cache| cache.get(chunk, hash)) {
+                        Some(edits) => push_chunk_edits(&mut events, chunk, edits),
+                        None => self.send(&ClientMessage::RequestChunkEdits { chunk }),
+                    }
+                }
+                ServerMessage::ChunkEdits { chunk, edits } => {
+                    push_chunk_edits(&mut events, chunk, &edits);
+                    if let Some(cache) = &mut self.edit_cache {
+                        cache.store(chunk, edits);
+                    }
+                }
+                ServerMessage::Chat { text } => events.push(ClientEvent::Chat(text)),
+                ServerMessage::CorrectPosition { position } => events.push(ClientEvent::PositionCorrected(position)),
+                ServerMessage::Sound { name, position } => events.push(ClientEvent::Sound { name, position }),
+                ServerMessage::Health { health } => events.push(ClientEvent::Health(health)),
+                ServerMessage::Explosion { position, power } => events.push(ClientEvent::Explosion { position, power }),
+                ServerMessage::Mounted { entity } => events.push(ClientEvent::Mounted(entity)),
+                ServerMessage::SignText { block, text } => events.push(ClientEvent::SignText { block, text }),
+                ServerMessage::EditSign { block } => events.push(ClientEvent::EditSign(block)),
+                ServerMessage::Time { ticks } => events.push(ClientEvent::Time(ticks)),
+                ServerMessage::Weather { raining } => events.push(ClientEvent::Weather(raining)),
+                ServerMessage::Died { position, kept_inventory } => events.push(ClientEvent::Died { position, kept_inventory }),
+                ServerMessage::Hunger { food } => events.push(ClientEvent::Hunger(food)),
+                ServerMessage::Inventory { inventory } => events.push(ClientEvent::Inventory(inventory)),
+                ServerMessage::BreakProgress { block, progress } => events.push(ClientEvent::BreakProgress { block, progress }),
+                ServerMessage::WorldGen { generator } => events.push(ClientEvent::WorldGen(generator)),
+                ServerMessage::Disconnect { reason } => {
+                    self.close();
+                    events.push(ClientEvent::Disconnected(reason));
+                }
+            }
+        }
+        if self.snapshots.latest() != acked {
+            self.send(&ClientMessage::SnapshotAck { sequence: self.snapshots.latest() });
+        }
+        events
+    }
+}
+
+/// One BlockUpdate per edit, at its position in the world
+fn push_chunk_edits(events: &mut Vec<ClientEvent>, chunk: (i32, i32, i32), edits: &[ChunkEdit]) {
+    let size = CHUNK_SIZE as i32;
+    let origin = (chunk.0 * size, chunk.1 * size, chunk.2 * size);
+    for &((x, y, z), block_type) in edits {
+        let block = (origin.0 + x as i32, origin.1 + y as i32, origin.2 + z as i32);
+        events.push(ClientEvent::BlockUpdate { block, block_type });
+    }
+}
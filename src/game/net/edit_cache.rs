@@ -0,0 +1,104 @@
+//! On-disk cache of the chunk edits a server sent, for rejoining without asking again.
+//!
+//! When an edited chunk comes within range the server sends only the hash of its edits.
+//! The edits are asked for when the cache holds nothing under that hash, and stored once
+//! they arrive. Only the latest edits of each chunk are kept. The file belongs to one
+//! server; edits cached from another would never match its hashes, only waste space.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use log::warn;
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::game::net::protocol::{chunk_edits_hash, read_chunk_edits, write_chunk_edits, ChunkEdit};
+use crate::game::save::atomic::write_atomic;
+
+const EDIT_CACHE_MAGIC: &[u8; 4] = b"PSUE";
+const EDIT_CACHE_VERSION: u32 = 1;
+
+type ChunkKey = (i32, i32, i32);
+
+pub struct ChunkEditCache {
+    path: PathBuf,
+    chunks: HashMap<ChunkKey, (u64, Vec<ChunkEdit>)>,
+    /// Stored since it was last written
+    dirty: bool,
+}
+
+impl ChunkEditCache {
+    /// Reads the cache kept at `path`, starting empty if it is missing or unreadable
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let chunks = Self::read(&path).unwrap_or_else(|e| {
+            warn!("Ignoring chunk edit cache {}: {}", path.display(), e);
+            HashMap::new()
+        });
+        Self { path, chunks, dirty: false }
+    }
+
+    fn read(path: &Path) -> io::Result<HashMap<ChunkKey, (u64, Vec<ChunkEdit>)>> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e),
+        };
+        let mut r = ByteReader::new(&data);
+        if r.read_bytes(4)? != EDIT_CACHE_MAGIC {
+            return Err(DecodeError::Invalid("bad chunk edit cache magic".into()).into());
+        }
+        if r.read_u32()? != EDIT_CACHE_VERSION {
+            return Ok(HashMap::new());
+        }
+        let count = r.read_u32()?;
+        let mut chunks = HashMap::new();
+        for _ in 0..count {
+            let chunk = (r.read_i32()?, r.read_i32()?, r.read_i32()?);
+            let edits = read_chunk_edits(&mut r)?;
+            chunks.insert(chunk, (chunk_edits_hash(&edits), edits));
+        }
+        Ok(chunks)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// The cached edits of a chunk, if they hash to `hash`
+    pub fn get(&self, chunk: ChunkKey, hash: u64) -> Option<&[ChunkEdit]> {
+        self.chunks.get(&chunk)
+            .filter(|(cached, _)| *cached == hash)
+            .map(|(_, edits)| edits.as_slice())
+    }
+
+    /// Keeps the edits the server sent for a chunk, replacing older ones
+    pub fn store(&mut self, chunk: ChunkKey, edits: Vec<ChunkEdit>) {
+        self.chunks.insert(chunk, (chunk_edits_hash(&edits), edits));
+        self.dirty = true;
+    }
+
+    /// Writes the cache if anything was stored since it was opened or last saved
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let mut w = ByteWriter::new();
+        w.write_bytes(EDIT_CACHE_MAGIC);
+        w.write_u32(EDIT_CACHE_VERSION);
+        w.write_u32(self.chunks.len() as u32);
+        for (chunk, (_, edits)) in &self.chunks {
+            w.write_i32(chunk.0);
+            w.write_i32(chunk.1);
+            w.write_i32(chunk.2);
+            write_chunk_edits(&mut w, edits);
+        }
+        write_atomic(&self.path, &w.into_inner())?;
+        self.dirty = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,11 @@
+//! Client/server protocol definitions.
+
+pub mod client;
+pub mod edit_cache;
+pub mod protocol;
+pub mod snapshot;
+
+pub use client::{ClientEvent, ClientSession};
+pub use edit_cache::ChunkEditCache;
+pub use protocol::{ClientId, ClientMessage, ServerMessage};
+pub use snapshot::{EntityDelta, EntityState, Snapshot, SnapshotReceiver, SnapshotSender};
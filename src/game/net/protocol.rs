@@ -0,0 +1,483 @@
+//! Wire protocol messages.
+
+use glam::Vec3;
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::game::entity::{EntityId, EntityKind};
+use crate::game::item::crafting::{CraftingGrid, CRAFTING_SLOTS};
+use crate::game::item::{Inventory, ItemType};
+use crate::game::net::snapshot::Snapshot;
+use crate::game::world::chunk::{BlockType, CHUNK_SIZE};
+use crate::game::world::worldgen::WorldGen;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClientId(pub u32);
+
+/// Messages sent from a client to the server
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientMessage {
+    /// First packet on every connection
+    Hello { name: String },
+    Move { position: Vec3, yaw: f32, pitch: f32 },
+    /// Attack along a ray; the server resolves what was hit, damaging an entity or
+    /// breaking a block
+    Attack { origin: Vec3, dir: Vec3 },
+    /// Place a block against the face the ray hits
+    Place { origin: Vec3, dir: Vec3, block_type: BlockType },
+    /// Chat line, or a command if it starts with '/'
+    Chat { text: String },
+    /// Newest entity snapshot the client has applied
+    SnapshotAck { sequence: u32 },
+    /// How the player steers what they are riding
+    Steer { forward: f32, turn: f32 },
+    /// Get off what the player is riding
+    Dismount,
+    /// New text for a sign the server asked the player to edit
+    SetSignText { block: (i32, i32, i32), text: String },
+    /// Come back to life after dying
+    Respawn,
+    /// Eat something from the inventory
+    Eat,
+    /// Craft with these items from the inventory laid out in the crafting grid
+    Craft { grid: CraftingGrid },
+    /// Send the edits of a chunk whose ChunkHash we had nothing cached for
+    RequestChunkEdits { chunk: (i32, i32, i32) },
+}
+
+/// A block changed since its chunk was generated, by position within the chunk
+pub type ChunkEdit = ((u8, u8, u8), BlockType);
+
+/// FNV-1a hash of a chunk's edits in the order they are sent, which both sides compute the
+/// same way; the server sends them sorted
+pub fn chunk_edits_hash(edits: &[ChunkEdit]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut feed = |byte: u8| {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    };
+    for &((x, y, z), block_type) in edits {
+        for byte in [x, y, z, block_type.id(), block_type.meta()] {
+            feed(byte);
+        }
+    }
+    hash
+}
+
+/// Most edits one chunk can have: one for every block
+const MAX_CHUNK_EDITS: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+
+pub(crate) fn write_chunk_edits(w: &mut ByteWriter, edits: &[ChunkEdit]) {
+    w.write_u16(edits.len() as u16);
+    for &((x, y, z), block_type) in edits {
+        w.write_u8(x);
+        w.write_u8(y);
+        w.write_u8(z);
+        write_block_type(w, block_type);
+    }
+}
+
+pub(crate) fn read_chunk_edits(r: &mut ByteReader) -> Result<Vec<ChunkEdit>, DecodeError> {
+    let count = r.read_u16()? as usize;
+    if count > MAX_CHUNK_EDITS {
+        return Err(DecodeError::Invalid(format!("{} edits in one chunk", count)));
+    }
+    let mut edits = Vec::with_capacity(count);
+    for _ in 0..count {
+        let local = (r.read_u8()?, r.read_u8()?, r.read_u8()?);
+        if [local.0, local.1, local.2].iter().any(|&c| c as usize >= CHUNK_SIZE) {
+            return Err(DecodeError::Invalid(format!("edit at {:?} is outside the chunk", local)));
+        }
+        edits.push((local, read_block_type(r)?));
+    }
+    Ok(edits)
+}
+
+fn read_block_type(r: &mut ByteReader) -> Result<BlockType, DecodeError> {
+    let (id, meta) = (r.read_u8()?, r.read_u8()?);
+    BlockType::from_parts(id, meta).ok_or_else(|| DecodeError::Invalid(format!("unknown block type {}:{}", id, meta)))
+}
+
+fn write_block_type(w: &mut ByteWriter, block_type: BlockType) {
+    w.write_u8(block_type.id());
+    w.write_u8(block_type.meta());
+}
+
+/// Items are sent as their id plus one, with zero for an empty cell
+fn read_grid(r: &mut ByteReader) -> Result<CraftingGrid, DecodeError> {
+    let mut grid = [None; CRAFTING_SLOTS];
+    for cell in grid.iter_mut() {
+        let id = r.read_u8()?;
+        if id != 0 {
+            *cell = Some(ItemType::from_id(id - 1).ok_or_else(|| DecodeError::Invalid(format!("unknown item {}", id - 1)))?);
+        }
+    }
+    Ok(grid)
+}
+
+fn write_grid(w: &mut ByteWriter, grid: &CraftingGrid) {
+    for cell in grid {
+        w.write_u8(cell.map_or(0, |item| item.id() + 1));
+    }
+}
+
+fn read_block_pos(r: &mut ByteReader) -> Result<(i32, i32, i32), DecodeError> {
+    Ok((r.read_i32()?, r.read_i32()?, r.read_i32()?))
+}
+
+fn write_block_pos(w: &mut ByteWriter, pos: (i32, i32, i32)) {
+    w.write_i32(pos.0);
+    w.write_i32(pos.1);
+    w.write_i32(pos.2);
+}
+
+const MSG_HELLO: u8 = 1;
+const MSG_MOVE: u8 = 2;
+const MSG_ATTACK: u8 = 3;
+const MSG_CLIENT_CHAT: u8 = 4;
+const MSG_SNAPSHOT_ACK: u8 = 5;
+const MSG_PLACE: u8 = 6;
+const MSG_STEER: u8 = 7;
+const MSG_DISMOUNT: u8 = 8;
+const MSG_SET_SIGN_TEXT: u8 = 9;
+const MSG_RESPAWN: u8 = 10;
+const MSG_EAT: u8 = 11;
+const MSG_CRAFT: u8 = 12;
+const MSG_REQUEST_CHUNK_EDITS: u8 = 13;
+
+impl ClientMessage {
+    pub fn encode(&self, w: &mut ByteWriter) {
+        match self {
+            ClientMessage::Hello { name } => {
+                w.write_u8(MSG_HELLO);
+                w.write_str(name);
+            }
+            ClientMessage::Move { position, yaw, pitch } => {
+                w.write_u8(MSG_MOVE);
+                w.write_vec3(*position);
+                w.write_f32(*yaw);
+                w.write_f32(*pitch);
+            }
+            ClientMessage::Attack { origin, dir } => {
+                w.write_u8(MSG_ATTACK);
+                w.write_vec3(*origin);
+                w.write_vec3(*dir);
+            }
+            ClientMessage::Place { origin, dir, block_type } => {
+                w.write_u8(MSG_PLACE);
+                w.write_vec3(*origin);
+                w.write_vec3(*dir);
+                write_block_type(w, *block_type);
+            }
+            ClientMessage::Chat { text } => {
+                w.write_u8(MSG_CLIENT_CHAT);
+                w.write_str(text);
+            }
+            ClientMessage::SnapshotAck { sequence } => {
+                w.write_u8(MSG_SNAPSHOT_ACK);
+                w.write_u32(*sequence);
+            }
+            ClientMessage::Steer { forward, turn } => {
+                w.write_u8(MSG_STEER);
+                w.write_f32(*forward);
+                w.write_f32(*turn);
+            }
+            ClientMessage::Dismount => w.write_u8(MSG_DISMOUNT),
+            ClientMessage::SetSignText { block, text } => {
+                w.write_u8(MSG_SET_SIGN_TEXT);
+                write_block_pos(w, *block);
+                w.write_str(text);
+            }
+            ClientMessage::Respawn => w.write_u8(MSG_RESPAWN),
+            ClientMessage::Eat => w.write_u8(MSG_EAT),
+            ClientMessage::Craft { grid } => {
+                w.write_u8(MSG_CRAFT);
+                write_grid(w, grid);
+            }
+            ClientMessage::RequestChunkEdits { chunk } => {
+                w.write_u8(MSG_REQUEST_CHUNK_EDITS);
+                write_block_pos(w, *chunk);
+            }
+        }
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        let tag = r.read_u8()?;
+        match tag {
+            MSG_HELLO => Ok(ClientMessage::Hello { name: r.read_str()? }),
+            MSG_MOVE => Ok(ClientMessage::Move { position: r.read_vec3()?, yaw: r.read_f32()?, pitch: r.read_f32()? }),
+            MSG_ATTACK => Ok(ClientMessage::Attack { origin: r.read_vec3()?, dir: r.read_vec3()? }),
+            MSG_PLACE => Ok(ClientMessage::Place { origin: r.read_vec3()?, dir: r.read_vec3()?, block_type: read_block_type(r)? }),
+            MSG_CLIENT_CHAT => Ok(ClientMessage::Chat { text: r.read_str()? }),
+            MSG_SNAPSHOT_ACK => Ok(ClientMessage::SnapshotAck { sequence: r.read_u32()? }),
+            MSG_STEER => Ok(ClientMessage::Steer { forward: r.read_f32()?, turn: r.read_f32()? }),
+            MSG_DISMOUNT => Ok(ClientMessage::Dismount),
+            MSG_SET_SIGN_TEXT => Ok(ClientMessage::SetSignText { block: read_block_pos(r)?, text: r.read_str()? }),
+            MSG_RESPAWN => Ok(ClientMessage::Respawn),
+            MSG_EAT => Ok(ClientMessage::Eat),
+            MSG_CRAFT => Ok(ClientMessage::Craft { grid: read_grid(r)? }),
+            MSG_REQUEST_CHUNK_EDITS => Ok(ClientMessage::RequestChunkEdits { chunk: read_block_pos(r)? }),
+            _ => Err(DecodeError::Invalid(format!("unknown client message {}", tag))),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = ByteWriter::new();
+        self.encode(&mut w);
+        w.into_inner()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode(&mut ByteReader::new(data))
+    }
+}
+
+/// Messages sent from the server to a client
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerMessage {
+    /// Reply to Hello: the client's id and where its player is
+    Welcome { client: ClientId, position: Vec3, yaw: f32, pitch: f32 },
+    /// An entity came within the client's interest range
+    EntityEnter { id: EntityId, kind: EntityKind, position: Vec3, velocity: Vec3 },
+    /// An entity left the client's interest range or was removed
+    EntityLeave { id: EntityId },
+    /// Changes to visible entities since the last acknowledged snapshot
+    Snapshot(Snapshot),
+    BlockUpdate { block: (i32, i32, i32), block_type: BlockType },
+    /// Blocks of a chunk changed since it was generated, sorted by position within the
+    /// chunk, sent when the client asks for them
+    ChunkEdits { chunk: (i32, i32, i32), edits: Vec<ChunkEdit> },
+    /// A chunk with edits came within the client's range; the hash is chunk_edits_hash of
+    /// its edits, so a client that cached them from an earlier session needn't ask again
+    ChunkHash { chunk: (i32, i32, i32), hash: u64 },
+    Chat { text: String },
+    /// The server is closing the connection, e.g. after a kick
+    Disconnect { reason: String },
+    /// A move was rejected; the client must snap back to this position
+    CorrectPosition { position: Vec3 },
+    /// A sound effect at a point in the world, e.g. a door opening
+    Sound { name: String, position: Vec3 },
+    /// The client's player health changed
+    Health { health: f32 },
+    /// Something exploded, for effects; the block changes are sent separately
+    Explosion { position: Vec3, power: f32 },
+    /// The entity the client's player now rides, or None once they got off
+    Mounted { entity: Option<EntityId> },
+    /// The text of a sign, sent when it changes and when it comes within range
+    SignText { block: (i32, i32, i32), text: String },
+    /// Open the sign editor for a sign the player placed or used
+    EditSign { block: (i32, i32, i32) },
+    /// The time of day in ticks, sent on joining, now and then to correct drift, and when
+    /// it jumps
+    Time { ticks: u64 },
+    /// Whether it is raining, sent on joining and when it starts or stops
+    Weather { raining: bool },
+    /// The client's player died at `position`; they stay dead until they ask to respawn
+    Died { position: Vec3, kept_inventory: bool },
+    /// The client's player food level, or None outside survival where there is no hunger
+    Hunger { food: Option<f32> },
+    /// Everything the client's player carries, sent on joining and whenever it changes
+    Inventory { inventory: Inventory },
+    /// How far the client's player is through breaking a block, from 0 to 1; 0 once they
+    /// stop or it breaks
+    BreakProgress { block: (i32, i32, i32), progress: f32 },
+    /// How the world's chunks are generated, sent on joining so the client builds the same
+    /// terrain
+    WorldGen { generator: WorldGen },
+}
+
+const MSG_WELCOME: u8 = 0;
+const MSG_ENTITY_ENTER: u8 = 1;
+const MSG_ENTITY_LEAVE: u8 = 2;
+const MSG_SNAPSHOT: u8 = 3;
+const MSG_BLOCK_UPDATE: u8 = 4;
+const MSG_CHAT: u8 = 5;
+const MSG_DISCONNECT: u8 = 6;
+const MSG_CORRECT_POSITION: u8 = 7;
+const MSG_SOUND: u8 = 8;
+const MSG_HEALTH: u8 = 9;
+const MSG_EXPLOSION: u8 = 10;
+const MSG_MOUNTED: u8 = 11;
+const MSG_SIGN_TEXT: u8 = 12;
+const MSG_EDIT_SIGN: u8 = 13;
+const MSG_TIME: u8 = 14;
+const MSG_WEATHER: u8 = 15;
+const MSG_DIED: u8 = 16;
+const MSG_HUNGER: u8 = 17;
+const MSG_INVENTORY: u8 = 18;
+const MSG_BREAK_PROGRESS: u8 = 19;
+const MSG_WORLD_GEN: u8 = 20;
+const MSG_CHUNK_EDITS: u8 = 21;
+const MSG_CHUNK_HASH: u8 = 22;
+
+impl ServerMessage {
+    pub fn encode(&self, w: &mut ByteWriter) {
+        match self {
+            ServerMessage::Welcome { client, position, yaw, pitch } => {
+                w.write_u8(MSG_WELCOME);
+                w.write_u32(client.0);
+                w.write_vec3(*position);
+                w.write_f32(*yaw);
+                w.write_f32(*pitch);
+            }
+            ServerMessage::EntityEnter { id, kind, position, velocity } => {
+                w.write_u8(MSG_ENTITY_ENTER);
+                w.write_u64(id.0);
+                w.write_u8(kind.id());
+                w.write_vec3(*position);
+                w.write_vec3(*velocity);
+            }
+            ServerMessage::EntityLeave { id } => {
+                w.write_u8(MSG_ENTITY_LEAVE);
+                w.write_u64(id.0);
+            }
+            ServerMessage::Snapshot(snapshot) => {
+                w.write_u8(MSG_SNAPSHOT);
+                snapshot.encode(w);
+            }
+            ServerMessage::BlockUpdate { block, block_type } => {
+                w.write_u8(MSG_BLOCK_UPDATE);
+                write_block_pos(w, *block);
+                write_block_type(w, *block_type);
+            }
+            ServerMessage::ChunkEdits { chunk, edits } => {
+                w.write_u8(MSG_CHUNK_EDITS);
+                write_block_pos(w, *chunk);
+                write_chunk_edits(w, edits);
+            }
+            ServerMessage::ChunkHash { chunk, hash } => {
+                w.write_u8(MSG_CHUNK_HASH);
+                write_block_pos(w, *chunk);
+                w.write_u64(*hash);
+            }
+            ServerMessage::Chat { text } => {
+                w.write_u8(MSG_CHAT);
+                w.write_str(text);
+            }
+            ServerMessage::Disconnect { reason } => {
+                w.write_u8(MSG_DISCONNECT);
+                w.write_str(reason);
+            }
+            ServerMessage::CorrectPosition { position } => {
+                w.write_u8(MSG_CORRECT_POSITION);
+                w.write_vec3(*position);
+            }
+            ServerMessage::Sound { name, position } => {
+                w.write_u8(MSG_SOUND);
+                w.write_str(name);
+                w.write_vec3(*position);
+            }
+            ServerMessage::Health { health } => {
+                w.write_u8(MSG_HEALTH);
+                w.write_f32(*health);
+            }
+            ServerMessage::Explosion { position, power } => {
+                w.write_u8(MSG_EXPLOSION);
+                w.write_vec3(*position);
+                w.write_f32(*power);
+            }
+            ServerMessage::Mounted { entity } => {
+                w.write_u8(MSG_MOUNTED);
+                // Entity ids start at 1
+                w.write_u64(entity.map_or(0, |id| id.0));
+            }
+            ServerMessage::SignText { block, text } => {
+                w.write_u8(MSG_SIGN_TEXT);
+                write_block_pos(w, *block);
+                w.write_str(text);
+            }
+            ServerMessage::EditSign { block } => {
+                w.write_u8(MSG_EDIT_SIGN);
+                write_block_pos(w, *block);
+            }
+            ServerMessage::Time { ticks } => {
+                w.write_u8(MSG_TIME);
+                w.write_u64(*ticks);
+            }
+            ServerMessage::Weather { raining } => {
+                w.write_u8(MSG_WEATHER);
+                w.write_u8(*raining as u8);
+            }
+            ServerMessage::Died { position, kept_inventory } => {
+                w.write_u8(MSG_DIED);
+                w.write_vec3(*position);
+                w.write_u8(*kept_inventory as u8);
+            }
+            ServerMessage::Hunger { food } => {
+                w.write_u8(MSG_HUNGER);
+                w.write_u8(food.is_some() as u8);
+                w.write_f32(food.unwrap_or(0.0));
+            }
+            ServerMessage::Inventory { inventory } => {
+                w.write_u8(MSG_INVENTORY);
+                inventory.encode(w);
+            }
+            ServerMessage::BreakProgress { block, progress } => {
+                w.write_u8(MSG_BREAK_PROGRESS);
+                write_block_pos(w, *block);
+                w.write_f32(*progress);
+            }
+            ServerMessage::WorldGen { generator } => {
+                w.write_u8(MSG_WORLD_GEN);
+                generator.encode(w);
+            }
+        }
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        let tag = r.read_u8()?;
+        match tag {
+            MSG_WELCOME => Ok(ServerMessage::Welcome {
+                client: ClientId(r.read_u32()?),
+                position: r.read_vec3()?,
+                yaw: r.read_f32()?,
+                pitch: r.read_f32()?,
+            }),
+            MSG_ENTITY_ENTER => {
+                let id = EntityId(r.read_u64()?);
+                let kind_id = r.read_u8()?;
+                let kind = EntityKind::from_id(kind_id)
+                    .ok_or_else(|| DecodeError::Invalid(format!("unknown entity kind {}", kind_id)))?;
+                Ok(ServerMessage::EntityEnter { id, kind, position: r.read_vec3()?, velocity: r.read_vec3()? })
+            }
+            MSG_ENTITY_LEAVE => Ok(ServerMessage::EntityLeave { id: EntityId(r.read_u64()?) }),
+            MSG_SNAPSHOT => Ok(ServerMessage::Snapshot(Snapshot::decode(r)?)),
+            MSG_BLOCK_UPDATE => {
+                let block = read_block_pos(r)?;
+                Ok(ServerMessage::BlockUpdate { block, block_type: read_block_type(r)? })
+            }
+            MSG_CHUNK_EDITS => Ok(ServerMessage::ChunkEdits { chunk: read_block_pos(r)?, edits: read_chunk_edits(r)? }),
+            MSG_CHUNK_HASH => Ok(ServerMessage::ChunkHash { chunk: read_block_pos(r)?, hash: r.read_u64()? }),
+            MSG_CHAT => Ok(ServerMessage::Chat { text: r.read_str()? }),
+            MSG_DISCONNECT => Ok(ServerMessage::Disconnect { reason: r.read_str()? }),
+            MSG_CORRECT_POSITION => Ok(ServerMessage::CorrectPosition { position: r.read_vec3()? }),
+            MSG_SOUND => Ok(ServerMessage::Sound { name: r.read_str()?, position: r.read_vec3()? }),
+            MSG_HEALTH => Ok(ServerMessage::Health { health: r.read_f32()? }),
+            MSG_EXPLOSION => Ok(ServerMessage::Explosion { position: r.read_vec3()?, power: r.read_f32()? }),
+            MSG_SIGN_TEXT => Ok(ServerMessage::SignText { block: read_block_pos(r)?, text: r.read_str()? }),
+            MSG_EDIT_SIGN => Ok(ServerMessage::EditSign { block: read_block_pos(r)? }),
+            MSG_TIME => Ok(ServerMessage::Time { ticks: r.read_u64()? }),
+            MSG_WEATHER => Ok(ServerMessage::Weather { raining: r.read_u8()? != 0 }),
+            MSG_DIED => Ok(ServerMessage::Died { position: r.read_vec3()?, kept_inventory: r.read_u8()? != 0 }),
+            MSG_HUNGER => {
+                let survival = r.read_u8()? != 0;
+                let food = r.read_f32()?;
+                Ok(ServerMessage::Hunger { food: survival.then_some(food) })
+            }
+            MSG_INVENTORY => Ok(ServerMessage::Inventory { inventory: Inventory::decode(r)? }),
+            MSG_BREAK_PROGRESS => Ok(ServerMessage::BreakProgress { block: read_block_pos(r)?, progress: r.read_f32()? }),
+            MSG_WORLD_GEN => Ok(ServerMessage::WorldGen { generator: WorldGen::decode(r)? }),
+            MSG_MOUNTED => Ok(ServerMessage::Mounted { entity: Some(r.read_u64()?).filter(|&id| id != 0).map(EntityId) }),
+            _ => Err(DecodeError::Invalid(format!("unknown server message {}", tag))),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = ByteWriter::new();
+        self.encode(&mut w);
+        w.into_inner()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode(&mut ByteReader::new(data))
+    }
+}
@@ -0,0 +1,284 @@
+//! Delta-compressed entity snapshots.
+//!
+//! Entity state is quantized before it goes on the wire, and each snapshot only carries
+//! the fields that differ from the last snapshot the client acknowledged. Both ends keep
+//! a short history keyed by sequence number so the baseline can be rebuilt on either
+//! side; if the acknowledged snapshot has fallen out of the history the server sends
+//! full state instead.
+
+use std::collections::{HashMap, VecDeque};
+use std::f32::consts::TAU;
+use glam::Vec3;
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::game::entity::{Entity, EntityId};
+
+/// Positions are sent in 1/64ths of a block
+pub const POSITION_SCALE: f32 = 64.0;
+/// Velocities are sent in 1/256ths of a block per second
+pub const VELOCITY_SCALE: f32 = 256.0;
+/// How many snapshots each side remembers for use as a baseline
+pub const SNAPSHOT_HISTORY: usize = 32;
+/// Baseline value meaning "no baseline, every field is present"
+pub const NO_BASELINE: u32 = 0;
+
+const FIELD_POSITION: u8 = 1;
+const FIELD_VELOCITY: u8 = 2;
+const FIELD_YAW: u8 = 4;
+
+/// Quantized entity state as both ends see it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EntityState {
+    pub position: [i32; 3],
+    pub velocity: [i32; 3],
+    /// Facing angle in 1/256ths of a turn
+    pub yaw: u8,
+}
+
+fn quantize_vec(v: Vec3, scale: f32) -> [i32; 3] {
+    [(v.x * scale).round() as i32, (v.y * scale).round() as i32, (v.z * scale).round() as i32]
+}
+
+fn dequantize_vec(v: [i32; 3], scale: f32) -> Vec3 {
+    Vec3::new(v[0] as f32, v[1] as f32, v[2] as f32) / scale
+}
+
+impl EntityState {
+    pub fn quantize(entity: &Entity) -> Self {
+        Self {
+            position: quantize_vec(entity.position, POSITION_SCALE),
+            velocity: quantize_vec(entity.velocity, VELOCITY_SCALE),
+            yaw: ((entity.yaw.rem_euclid(TAU) / TAU * 256.0).round() as u32 % 256) as u8,
+        }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        dequantize_vec(self.position, POSITION_SCALE)
+    }
+
+    pub fn velocity(&self) -> Vec3 {
+        dequantize_vec(self.velocity, VELOCITY_SCALE)
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw as f32 / 256.0 * TAU
+    }
+}
+
+/// Changed fields of one entity. The position is an offset from the baseline position
+/// (or from the origin without a baseline), which keeps the varints short.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityDelta {
+    pub id: EntityId,
+    pub position_offset: Option<[i32; 3]>,
+    pub velocity: Option<[i32; 3]>,
+    pub yaw: Option<u8>,
+}
+
+impl EntityDelta {
+    /// The fields of `current` that differ from `base`, or None if nothing changed
+    pub fn between(id: EntityId, base: Option<&EntityState>, current: &EntityState) -> Option<Self> {
+        let reference = base.copied().unwrap_or_default();
+        let full = base.is_none();
+        let delta = Self {
+            id,
+            position_offset: (full || current.position != reference.position).then(|| [
+                current.position[0].wrapping_sub(reference.position[0]),
+                current.position[1].wrapping_sub(reference.position[1]),
+                current.position[2].wrapping_sub(reference.position[2]),
+            ]),
+            velocity: (full || current.velocity != reference.velocity).then_some(current.velocity),
+            yaw: (full || current.yaw != reference.yaw).then_some(current.yaw),
+        };
+        let changed = delta.position_offset.is_some() || delta.velocity.is_some() || delta.yaw.is_some();
+        changed.then_some(delta)
+    }
+
+    pub fn apply(&self, base: Option<&EntityState>) -> EntityState {
+        let mut state = base.copied().unwrap_or_default();
+        if let Some(offset) = self.position_offset {
+            for (axis, offset) in state.position.iter_mut().zip(offset) {
+                *axis = axis.wrapping_add(offset);
+            }
+        }
+        if let Some(velocity) = self.velocity {
+            state.velocity = velocity;
+        }
+        if let Some(yaw) = self.yaw {
+            state.yaw = yaw;
+        }
+        state
+    }
+
+    fn encode(&self, w: &mut ByteWriter) {
+        let mut fields = 0;
+        if self.position_offset.is_some() { fields |= FIELD_POSITION; }
+        if self.velocity.is_some() { fields |= FIELD_VELOCITY; }
+        if self.yaw.is_some() { fields |= FIELD_YAW; }
+        w.write_var_u64(self.id.0);
+        w.write_u8(fields);
+        for v in self.position_offset.iter().chain(self.velocity.iter()) {
+            for axis in v {
+                w.write_var_i32(*axis);
+            }
+        }
+        if let Some(yaw) = self.yaw {
+            w.write_u8(yaw);
+        }
+    }
+
+    fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        fn read_triple(r: &mut ByteReader) -> Result<[i32; 3], DecodeError> {
+            Ok([r.read_var_i32()?, r.read_var_i32()?, r.read_var_i32()?])
+        }
+        let id = EntityId(r.read_var_u64()?);
+        let fields = r.read_u8()?;
+        Ok(Self {
+            id,
+            position_offset: if fields & FIELD_POSITION != 0 { Some(read_triple(r)?) } else { None },
+            velocity: if fields & FIELD_VELOCITY != 0 { Some(read_triple(r)?) } else { None },
+            yaw: if fields & FIELD_YAW != 0 { Some(r.read_u8()?) } else { None },
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub sequence: u32,
+    /// Sequence this snapshot is relative to, or NO_BASELINE
+    pub baseline: u32,
+    pub entities: Vec<EntityDelta>,
+}
+
+impl Snapshot {
+    pub fn encode(&self, w: &mut ByteWriter) {
+        w.write_u32(self.sequence);
+        w.write_u32(self.baseline);
+        w.write_var_u64(self.entities.len() as u64);
+        for delta in &self.entities {
+            delta.encode(w);
+        }
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        let sequence = r.read_u32()?;
+        let baseline = r.read_u32()?;
+        let count = r.read_var_u64()? as usize;
+        // Each delta is at least two bytes, so a bogus count can't make us allocate much
+        let mut entities = Vec::with_capacity(count.min(r.remaining() / 2));
+        for _ in 0..count {
+            entities.push(EntityDelta::decode(r)?);
+        }
+        Ok(Self { sequence, baseline, entities })
+    }
+}
+
+type StateMap = HashMap<EntityId, EntityState>;
+
+fn prune(history: &mut VecDeque<(u32, StateMap)>) {
+    while history.len() > SNAPSHOT_HISTORY {
+        history.pop_front();
+    }
+}
+
+/// Server side: builds snapshots for one client against its last acknowledged one
+pub struct SnapshotSender {
+    next_sequence: u32,
+    acked: u32,
+    history: VecDeque<(u32, StateMap)>,
+}
+
+impl Default for SnapshotSender {
+    fn default() -> Self {
+        Self {
+            next_sequence: NO_BASELINE + 1,
+            acked: NO_BASELINE,
+            history: VecDeque::new(),
+        }
+    }
+}
+
+impl SnapshotSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ack(&mut self, sequence: u32) {
+        if sequence > self.acked && sequence < self.next_sequence {
+            self.acked = sequence;
+        }
+    }
+
+    /// Builds the next snapshot of `states`, or None if the client is already up to date
+    pub fn build(&mut self, states: StateMap) -> Option<Snapshot> {
+        let (baseline, base) = match self.history.iter().find(|(seq, _)| *seq == self.acked) {
+            Some((seq, base)) => (*seq, Some(base)),
+            None => (NO_BASELINE, None),
+        };
+        let mut entities: Vec<EntityDelta> = states.iter()
+            .filter_map(|(id, state)| EntityDelta::between(*id, base.and_then(|b| b.get(id)), state))
+            .collect();
+        entities.sort_by_key(|d| d.id);
+
+        // Entities missing from the delta fall back to the baseline on the client, so an
+        // empty delta is only safe to skip when the last thing we sent agrees with it
+        let up_to_date = match self.history.back() {
+            Some((_, last)) => *last == states,
+            None => states.is_empty(),
+        };
+        if entities.is_empty() && up_to_date {
+            return None;
+        }
+
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.history.push_back((sequence, states));
+        prune(&mut self.history);
+        Some(Snapshot { sequence, baseline, entities })
+    }
+}
+
+/// Client side: rebuilds full state from incoming deltas
+#[derive(Default)]
+pub struct SnapshotReceiver {
+    latest: u32,
+    history: VecDeque<(u32, StateMap)>,
+}
+
+impl SnapshotReceiver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sequence of the newest snapshot applied, to be acknowledged to the server
+    pub fn latest(&self) -> u32 {
+        self.latest
+    }
+
+    /// Reconstructs the full state described by `snapshot`. `known` filters baseline
+    /// entries down to entities the client still tracks. Returns None for stale
+    /// snapshots or ones whose baseline we no longer have.
+    pub fn receive(&mut self, snapshot: &Snapshot, known: impl Fn(EntityId) -> bool) -> Option<&StateMap> {
+        if snapshot.sequence <= self.latest {
+            return None;
+        }
+        let base = if snapshot.baseline == NO_BASELINE {
+            None
+        } else {
+            Some(&self.history.iter().find(|(seq, _)| *seq == snapshot.baseline)?.1)
+        };
+
+        let mut states: StateMap = base
+            .map(|b| b.iter().filter(|(id, _)| known(**id)).map(|(id, s)| (*id, *s)).collect())
+            .unwrap_or_default();
+        for delta in &snapshot.entities {
+            let state = delta.apply(base.and_then(|b| b.get(&delta.id)));
+            states.insert(delta.id, state);
+        }
+
+        self.latest = snapshot.sequence;
+        self.history.push_back((snapshot.sequence, states));
+        prune(&mut self.history);
+        self.history.back().map(|(_, states)| states)
+    }
+}
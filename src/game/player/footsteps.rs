@@ -0,0 +1,86 @@
+//! Footstep sounds from walking movement.
+//!
+//! A step sounds each time the body covers a stride on the ground or a ladder, using the
+//! material of the block it stands on or climbs. Sneaking takes short, quiet steps and
+//! sprinting long, loud ones.
+
+use glam::Vec3;
+
+use crate::game::player::physics::{Gait, PlayerBody, EYE_HEIGHT};
+use crate::game::world::chunk::BlockType;
+use crate::game::world::chunk_manager::ChunkManager;
+
+/// A footstep to play at the player's feet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Footstep {
+    pub sound: &'static str,
+    /// Relative to a normal walking step
+    pub volume: f32,
+}
+
+impl Gait {
+    /// Distance covered between footsteps, in blocks
+    pub fn stride(&self) -> f32 {
+        match self {
+            Gait::Sneak => 1.0,
+            Gait::Walk => 1.6,
+            Gait::Sprint => 2.0,
+        }
+    }
+
+    pub fn footstep_volume(&self) -> f32 {
+        match self {
+            Gait::Sneak => 0.2,
+            Gait::Walk => 0.6,
+            Gait::Sprint => 0.8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Footsteps {
+    /// Distance covered since the last step
+    travelled: f32,
+    /// Counts steps, to alternate between a material's variants
+    steps: usize,
+}
+
+impl Footsteps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The block the body is walking on, or the ladder it is climbing; None in mid-air
+    pub fn surface(body: &PlayerBody, eye: Vec3, chunks: &ChunkManager) -> Option<BlockType> {
+        let at = |pos: Vec3| {
+            let block = ChunkManager::block_coords(pos);
+            chunks.get_block(block.0, block.1, block.2)
+        };
+        let feet = eye - Vec3::Y * EYE_HEIGHT;
+        if body.climbing {
+            at(feet + Vec3::Y * 0.5).filter(|b| b.is_climbable()).or_else(|| at(eye).filter(|b| b.is_climbable()))
+        } else if body.on_ground {
+            at(feet - Vec3::Y * 0.01)
+        } else {
+            None
+        }
+    }
+
+    /// Advances by a move of `travelled` blocks over `surface`, returning the footstep to
+    /// play if a stride was completed. Leaving the ground starts the stride over.
+    pub fn update(&mut self, travelled: f32, gait: Gait, surface: Option<BlockType>) -> Option<Footstep> {
+        let Some(material) = surface.and_then(|b| b.material()) else {
+            self.travelled = 0.0;
+            return None;
+        };
+        self.travelled += travelled;
+        if self.travelled < gait.stride() {
+            return None;
+        }
+        self.travelled = 0.0;
+        let sounds = material.footstep_sounds();
+        let sound = sounds[self.steps % sounds.len()];
+        self.steps += 1;
+        Some(Footstep { sound, volume: gait.footstep_volume() })
+    }
+}
@@ -0,0 +1,64 @@
+//! Field of view effects layered over the camera's own FOV: zooming in while the zoom key
+//! is held, and widening slightly while sprinting.
+//!
+//! Each effect is a multiplier that eases toward its target, so they compose and release
+//! smoothly whichever order they start and stop in. The camera keeps its base FOV; only the
+//! drawn camera gets the effects, so pinch zoom and the base setting are never lost.
+
+use crate::game::world::camera::Camera;
+
+/// FOV multiplier while zoomed, unless changed
+pub const DEFAULT_ZOOM: f32 = 0.25;
+/// FOV multiplier while sprinting
+const SPRINT_WIDEN: f32 = 1.1;
+/// Seconds for an effect to get most of the way to its target
+const EASE_TIME: f32 = 0.08;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FovEffects {
+    /// Multiplier the zoom key narrows the FOV to, 0 to 1
+    pub zoom: f32,
+    zoom_scale: f32,
+    sprint_scale: f32,
+}
+
+impl Default for FovEffects {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn ease(current: f32, target: f32, delta_time: f32) -> f32 {
+    // Exponential easing, so the speed does not depend on the frame rate
+    let share = 1.0 - (-delta_time / EASE_TIME).exp();
+    current + (target - current) * share
+}
+
+impl FovEffects {
+    pub fn new() -> Self {
+        Self { zoom: DEFAULT_ZOOM, zoom_scale: 1.0, sprint_scale: 1.0 }
+    }
+
+    /// Eases the effects toward what the player is doing. With `reduced_motion` sprinting
+    /// leaves the FOV alone.
+    pub fn update(&mut self, delta_time: f32, zooming: bool, sprinting: bool, reduced_motion: bool) {
+        let zoom_target = if zooming { self.zoom } else { 1.0 };
+        let sprint_target = if sprinting && !reduced_motion { SPRINT_WIDEN } else { 1.0 };
+        self.zoom_scale = ease(self.zoom_scale, zoom_target, delta_time);
+        self.sprint_scale = ease(self.sprint_scale, sprint_target, delta_time);
+    }
+
+    /// How much the zoom narrows the FOV right now; mouse look is scaled by this so aiming
+    /// feels the same zoomed in
+    pub fn zoom_scale(&self) -> f32 {
+        self.zoom_scale
+    }
+
+    /// The camera to draw: `camera` with its FOV, or the height of an orthographic view,
+    /// scaled by the current effects
+    pub fn apply(&self, camera: &Camera) -> Camera {
+        let mut view = camera.clone();
+        view.zoom(self.zoom_scale * self.sprint_scale);
+        view
+    }
+}
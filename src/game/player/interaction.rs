@@ -0,0 +1,180 @@
+//! Block breaking and placing rates.
+//!
+//! Mouse buttons only report press and release, so a held button is turned into a stream of
+//! actions here: the first one fires on press, the next after `repeat_delay`, then one every
+//! cooldown until release. Clicking faster than the cooldown does not beat it either.
+
+use std::time::Duration;
+
+use crate::engine::time::Instant;
+use crate::game::world::chunk::{BlockType, DoorState, Facing, Hinge};
+
+/// Block types that can be selected for placing, in number key order; the mouse wheel
+/// reaches the ones past the tenth. The server orients blocks such as ladders to the face
+/// they are placed against.
+pub const PLACEABLE_BLOCKS: [BlockType; 12] = [
+    BlockType::Grass,
+    BlockType::Dirt,
+    BlockType::Stone,
+    BlockType::Ladder(Facing::North),
+    BlockType::Door(DoorState { facing: Facing::North, hinge: Hinge::Left, open: false, upper: false }),
+    BlockType::Wheat(0),
+    BlockType::Water(0),
+    BlockType::Lava(0),
+    BlockType::Fire(0),
+    BlockType::Tnt,
+    BlockType::Sign(Facing::North),
+    BlockType::Bed,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InteractionConfig {
+    /// Minimum time between two breaks
+    pub break_cooldown: Duration,
+    /// Minimum time between two placements
+    pub place_cooldown: Duration,
+    /// How long a button must be held before it starts repeating
+    pub repeat_delay: Duration,
+}
+
+impl Default for InteractionConfig {
+    fn default() -> Self {
+        Self {
+            break_cooldown: Duration::from_millis(250),
+            place_cooldown: Duration::from_millis(200),
+            repeat_delay: Duration::from_millis(300),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InteractionAction {
+    Break,
+    Place,
+}
+
+/// Press and repeat timing for one button
+#[derive(Debug, Clone, Copy, Default)]
+struct Repeater {
+    held: bool,
+    /// Whether the current press has fired yet, so the next firing waits for the repeat delay
+    fired: bool,
+    /// Earliest time the action may run again
+    ready_at: Option<Instant>,
+    /// When the held button next fires
+    next_at: Option<Instant>,
+}
+
+impl Repeater {
+    fn press(&mut self, now: Instant) {
+        if self.held {
+            return;
+        }
+        self.held = true;
+        self.fired = false;
+        self.next_at = Some(self.ready_at.map_or(now, |ready| ready.max(now)));
+    }
+
+    fn release(&mut self) {
+        self.held = false;
+        self.next_at = None;
+    }
+
+    fn poll(&mut self, now: Instant, cooldown: Duration, repeat_delay: Duration) -> bool {
+        match self.next_at {
+            Some(next) if self.held && now >= next => {
+                let delay = if self.fired { cooldown } else { repeat_delay.max(cooldown) };
+                self.fired = true;
+                self.ready_at = Some(now + cooldown);
+                self.next_at = Some(now + delay);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Interaction {
+    pub config: InteractionConfig,
+    /// Block placed by the place action
+    pub selected: BlockType,
+    breaking: Repeater,
+    placing: Repeater,
+}
+
+impl Default for Interaction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interaction {
+    pub fn new() -> Self {
+        Self::with_config(InteractionConfig::default())
+    }
+
+    pub fn with_config(config: InteractionConfig) -> Self {
+        Self { config, selected: PLACEABLE_BLOCKS[0], breaking: Repeater::default(), placing: Repeater::default() }
+    }
+
+    pub fn press(&mut self, action: InteractionAction, now: Instant) {
+        self.repeater(action).press(now);
+    }
+
+    pub fn release(&mut self, action: InteractionAction) {
+        self.repeater(action).release();
+    }
+
+    /// Stops any held action, e.g. when the player starts doing something else or the
+    /// window stops receiving button releases. Cooldowns already running are kept.
+    pub fn interrupt(&mut self) {
+        self.breaking.release();
+        self.placing.release();
+    }
+
+    pub fn is_held(&self, action: InteractionAction) -> bool {
+        match action {
+            InteractionAction::Break => self.breaking.held,
+            InteractionAction::Place => self.placing.held,
+        }
+    }
+
+    /// Selects the Nth placeable block, returning false if there is none
+    pub fn select(&mut self, index: usize) -> bool {
+        match PLACEABLE_BLOCKS.get(index) {
+            Some(&block_type) => {
+                self.selected = block_type;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Selects the placeable block `step` places after the current one, wrapping around
+    pub fn cycle(&mut self, step: i32) {
+        let current = PLACEABLE_BLOCKS.iter().position(|&b| b == self.selected).unwrap_or(0) as i32;
+        let index = (current + step).rem_euclid(PLACEABLE_BLOCKS.len() as i32);
+        self.selected = PLACEABLE_BLOCKS[index as usize];
+    }
+
+    /// Actions due by `now`; call once per frame
+    pub fn poll(&mut self, now: Instant) -> Vec<InteractionAction> {
+        let mut actions = Vec::new();
+        let config = self.config;
+        if self.breaking.poll(now, config.break_cooldown, config.repeat_delay) {
+            actions.push(InteractionAction::Break);
+        }
+        if self.placing.poll(now, config.place_cooldown, config.repeat_delay) {
+            actions.push(InteractionAction::Place);
+        }
+        actions
+    }
+
+    fn repeater(&mut self, action: InteractionAction) -> &mut Repeater {
+        match action {
+            InteractionAction::Break => &mut self.breaking,
+            InteractionAction::Place => &mut self.placing,
+        }
+    }
+}
@@ -1,3 +1,14 @@
+pub mod footsteps;
+pub mod fov;
+pub mod interaction;
+pub mod physics;
+#[allow(clippy::module_inception)]
 pub mod player;
+pub mod survival;
 
-pub use player::Player;
\ No newline at end of file
+pub use footsteps::{Footstep, Footsteps};
+pub use fov::FovEffects;
+pub use interaction::{Interaction, InteractionAction, InteractionConfig};
+pub use physics::{Gait, MoveInput, MovementMode, PlayerBody};
+pub use player::{Player, PLAYER_MAX_HEALTH};
+pub use survival::{Gamemode, Hunger};
@@ -0,0 +1,249 @@
+//! Walking movement: gravity, collision with solid blocks and ladder climbing.
+//!
+//! The body is an upright box under the camera. Motion is resolved one axis at a time
+//! against the colliding blocks the box would move into; blocks it already overlaps are ignored
+//! so a player spawned inside terrain can still walk out. Each axis move sweeps the box over
+//! its whole path, and long steps are split into substeps of at most `max_substep` blocks so
+//! fast diagonal motion cannot slip past block corners either.
+//!
+//! A body on the ground that walks into a ledge up to STEP_HEIGHT tall steps up onto it,
+//! as long as there is headroom above. Blocks collide with a box of their collision height,
+//! so lower blocks make lower steps.
+
+use glam::Vec3;
+
+use crate::engine::math::Aabb;
+use crate::game::entity::manager::GRAVITY;
+use crate::game::world::chunk_manager::ChunkManager;
+
+pub const PLAYER_HALF_WIDTH: f32 = 0.3;
+pub const PLAYER_HEIGHT: f32 = 1.8;
+/// Height of the camera above the feet
+pub const EYE_HEIGHT: f32 = 1.6;
+/// Walking speed in blocks per second
+pub const WALK_SPEED: f32 = 4.3;
+pub const SPRINT_SPEED: f32 = 5.6;
+pub const SNEAK_SPEED: f32 = 1.3;
+pub const JUMP_SPEED: f32 = 7.5;
+/// Kept below the server's MAX_MOVE_SPEED so long falls are not rejected as cheating
+pub const MAX_FALL_SPEED: f32 = 10.0;
+pub const CLIMB_SPEED: f32 = 2.5;
+/// Tallest ledge a walking body steps onto without jumping: one full block
+pub const STEP_HEIGHT: f32 = 1.0 + 2.0 * CONTACT_EPSILON;
+/// Fastest a player slides down a ladder when not climbing
+pub const CLIMB_FALL_SPEED: f32 = 1.5;
+/// Gap left between the body and a block it collides with
+const CONTACT_EPSILON: f32 = 1e-3;
+/// Longest distance, in blocks, a body moves in one collision substep unless configured
+pub const DEFAULT_MAX_SUBSTEP: f32 = 0.25;
+/// Most substeps one step is split into, so a huge time step cannot stall a frame
+pub const MAX_SUBSTEPS: u32 = 64;
+
+/// Number of equal substeps to split `motion` into so that none moves further than
+/// `max_substep` along any axis
+pub fn substeps(motion: Vec3, max_substep: f32) -> u32 {
+    if max_substep <= 0.0 || !max_substep.is_finite() {
+        return 1;
+    }
+    ((motion.abs().max_element() / max_substep).ceil() as u32).clamp(1, MAX_SUBSTEPS)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementMode {
+    /// Free camera that ignores terrain
+    Fly,
+    Walk,
+}
+
+/// What the player is asking for this step
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MoveInput {
+    /// Horizontal direction, at most unit length
+    pub wish: Vec3,
+    pub forward: bool,
+    pub jump: bool,
+    pub sneak: bool,
+    pub sprint: bool,
+}
+
+/// How a walking body moves, which sets its speed and how loud its footsteps are
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Gait {
+    Sneak,
+    #[default]
+    Walk,
+    Sprint,
+}
+
+impl Gait {
+    pub fn speed(&self) -> f32 {
+        match self {
+            Gait::Sneak => SNEAK_SPEED,
+            Gait::Walk => WALK_SPEED,
+            Gait::Sprint => SPRINT_SPEED,
+        }
+    }
+}
+
+impl MoveInput {
+    /// Sneaking wins over sprinting, and only forward motion can be a sprint
+    pub fn gait(&self) -> Gait {
+        if self.sneak {
+            Gait::Sneak
+        } else if self.sprint && self.forward {
+            Gait::Sprint
+        } else {
+            Gait::Walk
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerBody {
+    pub velocity: Vec3,
+    pub on_ground: bool,
+    /// Overlapping a climbable block such as a ladder
+    pub climbing: bool,
+    /// Gait of the last step
+    pub gait: Gait,
+    /// Speed the body hit the ground at during the last step, 0 if it did not land
+    pub landing_speed: f32,
+}
+
+impl PlayerBody {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The body's box for a camera at `eye`
+    pub fn aabb(eye: Vec3) -> Aabb {
+        let feet = eye - Vec3::Y * EYE_HEIGHT;
+        Aabb::new(
+            feet - Vec3::new(PLAYER_HALF_WIDTH, 0.0, PLAYER_HALF_WIDTH),
+            feet + Vec3::new(PLAYER_HALF_WIDTH, PLAYER_HEIGHT, PLAYER_HALF_WIDTH),
+        )
+    }
+
+    /// Advances the body by `delta_time`, moving `eye` with it in substeps of at most
+    /// `max_substep` blocks
+    pub fn step(&mut self, eye: &mut Vec3, input: MoveInput, chunks: &ChunkManager, delta_time: f32, max_substep: f32) {
+        self.climbing = blocks_in(&Self::aabb(*eye)).any(|b| chunks.get_block(b.0, b.1, b.2).is_some_and(|t| t.is_climbable()));
+
+        self.gait = input.gait();
+        let horizontal = input.wish.clamp_length_max(1.0) * self.gait.speed();
+        self.velocity.x = horizontal.x;
+        self.velocity.z = horizontal.z;
+        if self.climbing {
+            self.velocity.y = if input.forward || input.jump {
+                CLIMB_SPEED
+            } else if input.sneak {
+                // Holding sneak keeps the player in place on the ladder
+                0.0
+            } else {
+                (self.velocity.y - GRAVITY * delta_time).max(-CLIMB_FALL_SPEED)
+            };
+        } else {
+            if self.on_ground && input.jump {
+                self.velocity.y = JUMP_SPEED;
+            }
+            self.velocity.y = (self.velocity.y - GRAVITY * delta_time).max(-MAX_FALL_SPEED);
+        }
+
+        let steps = substeps(self.velocity * delta_time, max_substep);
+        let motion = self.velocity * (delta_time / steps as f32);
+        let was_on_ground = self.on_ground;
+        let falling_speed = -self.velocity.y;
+        self.on_ground = false;
+        self.landing_speed = 0.0;
+        let mut blocked = [false; 3];
+        for _ in 0..steps {
+            for axis in [1, 0, 2] {
+                if blocked[axis] {
+                    continue;
+                }
+                let mut moved = move_axis(eye, axis, motion[axis], chunks);
+                if moved != motion[axis] && axis != 1 && self.on_ground {
+                    moved += step_up(eye, axis, motion[axis] - moved, chunks);
+                }
+                if moved != motion[axis] {
+                    if axis == 1 && motion.y < 0.0 {
+                        self.on_ground = true;
+                        if !was_on_ground {
+                            self.landing_speed = falling_speed;
+                        }
+                    }
+                    blocked[axis] = true;
+                    self.velocity[axis] = 0.0;
+                }
+            }
+        }
+    }
+}
+
+/// Cells whose blocks overlap the box; block n spans n - 0.5 to n + 0.5
+fn blocks_in(bounds: &Aabb) -> impl Iterator<Item = (i32, i32, i32)> {
+    let min = ChunkManager::block_coords(bounds.min);
+    let max = ChunkManager::block_coords(bounds.max);
+    (min.0..=max.0).flat_map(move |x| (min.1..=max.1).flat_map(move |y| (min.2..=max.2).map(move |z| (x, y, z))))
+}
+
+/// Collision box of the block in a cell, if it has one. Unloaded terrain counts as solid,
+/// but the void below the world does not.
+fn collision_box(chunks: &ChunkManager, block: (i32, i32, i32)) -> Option<Aabb> {
+    let height = chunks.get_block_or_void(block.0, block.1, block.2).map_or(1.0, |b| b.collision_height());
+    if height <= 0.0 {
+        return None;
+    }
+    let min = Vec3::new(block.0 as f32, block.1 as f32, block.2 as f32) - Vec3::splat(0.5);
+    Some(Aabb::new(min, min + Vec3::new(1.0, height, 1.0)))
+}
+
+/// Whether two boxes overlap by more than touching
+fn overlaps(a: &Aabb, b: &Aabb) -> bool {
+    a.min.cmplt(b.max).all() && a.max.cmpgt(b.min).all()
+}
+
+/// Moves the eye by up to `delta` along one axis, stopping at the first collision box the body
+/// runs into. Returns the distance actually moved.
+fn move_axis(eye: &mut Vec3, axis: usize, delta: f32, chunks: &ChunkManager) -> f32 {
+    if delta == 0.0 {
+        return 0.0;
+    }
+    let start = PlayerBody::aabb(*eye);
+    let mut offset = Vec3::ZERO;
+    offset[axis] = delta;
+    let end = Aabb::new(start.min + offset, start.max + offset);
+    let swept = Aabb::new(start.min.min(end.min), start.max.max(end.max));
+    let allowed = blocks_in(&swept)
+        .filter_map(|b| collision_box(chunks, b))
+        .filter(|bounds| overlaps(bounds, &swept) && !overlaps(bounds, &start))
+        .map(|bounds| {
+            if delta > 0.0 {
+                (bounds.min[axis] - start.max[axis] - CONTACT_EPSILON).max(0.0)
+            } else {
+                (bounds.max[axis] - start.min[axis] + CONTACT_EPSILON).min(0.0)
+            }
+        })
+        .fold(delta, |allowed, limit| if delta > 0.0 { allowed.min(limit) } else { allowed.max(limit) });
+    eye[axis] += allowed;
+    allowed
+}
+
+/// Tries to finish a horizontal move that ran into a ledge by lifting the body up to
+/// STEP_HEIGHT, moving the rest of the way and settling back down onto the ledge. Returns the
+/// extra distance moved, or 0 with `eye` untouched if the ledge is too tall or leaves no
+/// headroom.
+fn step_up(eye: &mut Vec3, axis: usize, remaining: f32, chunks: &ChunkManager) -> f32 {
+    let mut raised = *eye;
+    let lift = move_axis(&mut raised, 1, STEP_HEIGHT, chunks);
+    if lift <= 0.0 {
+        return 0.0;
+    }
+    let moved = move_axis(&mut raised, axis, remaining, chunks);
+    if moved == 0.0 {
+        return 0.0;
+    }
+    move_axis(&mut raised, 1, -lift, chunks);
+    *eye = raised;
+    moved
+}
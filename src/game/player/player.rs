@@ -1,15 +1,41 @@
 //! Player implementation.
 
+use crate::game::entity::{boat, Entity, EntityId};
 use crate::game::world::camera::Camera;
-use crate::engine::input::InputHandler;
+use crate::game::world::chunk_manager::ChunkManager;
+use crate::game::player::footsteps::{Footstep, Footsteps};
+use crate::game::player::fov::FovEffects;
+use crate::game::player::physics::{Gait, MovementMode, PlayerBody, DEFAULT_MAX_SUBSTEP};
+use crate::engine::input::{Action, InputHandler};
 use winit::event::DeviceEvent;
 use winit::window::Window;
 
+pub const PLAYER_MAX_HEALTH: f32 = 20.0;
+
 pub struct Player {
     pub camera: Camera,
     pub input_handler: InputHandler,
     pub movement_speed: f32,
     pub mouse_sensitivity: f32,
+    pub mode: MovementMode,
+    pub body: PlayerBody,
+    /// Longest distance the body moves per collision substep, in blocks
+    pub max_substep: f32,
+    pub footsteps: Footsteps,
+    /// Zoom and sprint changes to the drawn field of view
+    pub fov: FovEffects,
+    /// Last health the server reported
+    pub health: f32,
+    /// Last food level the server reported, None when we cannot get hungry
+    pub food: Option<f32>,
+    /// Entity the server has us mounted on; it moves us instead of our own input
+    pub riding: Option<EntityId>,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Player {
@@ -19,17 +45,84 @@ impl Player {
             input_handler: InputHandler::new(),
             movement_speed: 5.0,
             mouse_sensitivity: 0.002,
+            mode: MovementMode::Fly,
+            body: PlayerBody::new(),
+            max_substep: DEFAULT_MAX_SUBSTEP,
+            footsteps: Footsteps::new(),
+            fov: FovEffects::new(),
+            health: PLAYER_MAX_HEALTH,
+            food: None,
+            riding: None,
+        }
+    }
+
+    /// Moves the player by `delta_time`, returning the footstep to play if they took one
+    pub fn update(&mut self, delta_time: f32, chunks: &ChunkManager) -> Option<Footstep> {
+        if self.riding.is_some() {
+            return None;
+        }
+        match self.mode {
+            // Apply movement based on currently pressed keys
+            MovementMode::Fly => {
+                self.input_handler.apply_movement(&mut self.camera);
+                None
+            }
+            MovementMode::Walk => {
+                let mut input = self.input_handler.walk_input(&self.camera);
+                // Too hungry to sprint
+                input.sprint &= self.food.is_none_or(|food| food > 0.0);
+                let before = self.camera.position;
+                self.body.step(&mut self.camera.position, input, chunks, delta_time, self.max_substep);
+                let surface = Footsteps::surface(&self.body, self.camera.position, chunks);
+                self.footsteps.update(before.distance(self.camera.position), self.body.gait, surface)
+            }
         }
     }
 
-    pub fn update(&mut self, _delta_time: f32) {
-        // Apply movement based on currently pressed keys
-        self.input_handler.apply_movement(&mut self.camera);
+    /// Gets on or off an entity, starting from rest either way
+    pub fn mount(&mut self, entity: Option<EntityId>) {
+        self.riding = entity;
+        self.body = PlayerBody::new();
+    }
+
+    /// Puts the camera in the seat of the entity we ride
+    pub fn follow(&mut self, vehicle: &Entity) {
+        self.camera.position = boat::rider_eye(vehicle);
+    }
+
+    /// Switches between flying and walking, starting from rest
+    pub fn toggle_movement_mode(&mut self) -> MovementMode {
+        self.mode = match self.mode {
+            MovementMode::Fly => MovementMode::Walk,
+            MovementMode::Walk => MovementMode::Fly,
+        };
+        self.body = PlayerBody::new();
+        self.mode
     }
 
     pub fn handle_mouse_motion(&mut self, delta: winit::dpi::PhysicalPosition<f64>) {
         let delta_tuple = (delta.x, delta.y);
-        self.input_handler.handle_mouse_motion(delta_tuple, &mut self.camera);
+        self.input_handler.handle_mouse_motion(delta_tuple);
+    }
+
+    /// Turns the camera by the mouse movement since the last frame, `dt` real seconds ago;
+    /// call once per frame
+    pub fn update_look(&mut self, dt: f32) {
+        self.input_handler.mouse.look_scale = self.fov.zoom_scale();
+        self.input_handler.update_look(&mut self.camera, dt);
+    }
+
+    /// Eases the zoom and sprint FOV toward the held keys and last step; call once per
+    /// frame after `update`
+    pub fn update_fov(&mut self, delta_time: f32, reduced_motion: bool) {
+        let zooming = self.input_handler.is_active(Action::Zoom);
+        let sprinting = self.mode == MovementMode::Walk && self.riding.is_none() && self.body.gait == Gait::Sprint;
+        self.fov.update(delta_time, zooming, sprinting, reduced_motion);
+    }
+
+    /// The camera to draw, with the FOV effects applied
+    pub fn view_camera(&self) -> Camera {
+        self.fov.apply(&self.camera)
     }
 
     pub fn handle_keyboard_input(&mut self, keycode: winit::keyboard::KeyCode, pressed: bool) {
@@ -40,7 +133,18 @@ impl Player {
         self.input_handler.handle_window_focus(focused, window);
     }
 
+    pub fn handle_touch(&mut self, touch: &winit::event::Touch, window_size: glam::Vec2, ui_scale: f32) {
+        self.input_handler.touch.handle_touch(touch, window_size, ui_scale, &mut self.camera);
+    }
+
+    pub fn handle_cursor_moved(&mut self, position: winit::dpi::PhysicalPosition<f64>, window: &Window) {
+        self.input_handler.handle_cursor_moved(position, window);
+    }
+
     pub fn handle_device_event(&mut self, event: DeviceEvent) {
+        if !self.input_handler.wants_raw_motion() {
+            return;
+        }
         if let DeviceEvent::MouseMotion { delta } = event {
             let delta_pos = winit::dpi::PhysicalPosition::new(delta.0, delta.1);
             self.handle_mouse_motion(delta_pos);
@@ -0,0 +1,163 @@
+//! Survival stats, and the gamemode that turns them on.
+//!
+//! In creative, the default, players never get hungry. In survival, hunger drains slowly
+//! over time and faster with each block travelled or hit; a full stomach slowly heals, an
+//! empty one stops sprinting and slowly starves. Eating food fills it back up. The server
+//! keeps the stats and tells the client, which only draws them and stops sprinting.
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::engine::graphics::Overlay;
+
+/// A full stomach
+pub const MAX_FOOD: f32 = 20.0;
+/// Exhaustion that costs one point of food
+const EXHAUSTION_PER_FOOD: f32 = 4.0;
+/// Exhaustion per second just from being alive
+const IDLE_EXHAUSTION: f32 = 0.01;
+/// Exhaustion per block travelled on foot, so sprinting, which covers more ground, costs more
+pub const TRAVEL_EXHAUSTION: f32 = 0.03;
+/// Exhaustion for each attack or broken block
+pub const ACTION_EXHAUSTION: f32 = 0.05;
+/// Food needed to heal
+const HEAL_MIN_FOOD: f32 = 18.0;
+/// Seconds between healing one point, or starving one point away
+const HEAL_INTERVAL: f32 = 4.0;
+const STARVE_INTERVAL: f32 = 4.0;
+/// Exhaustion each point of healing costs
+const HEAL_EXHAUSTION: f32 = 3.0;
+/// Starving stops here rather than killing
+pub const STARVE_MIN_HEALTH: f32 = 1.0;
+/// Food points shown by each HUD icon
+const FOOD_PER_ICON: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Gamemode {
+    /// No hunger
+    #[default]
+    Creative,
+    Survival,
+}
+
+impl Gamemode {
+    pub fn id(&self) -> u8 {
+        match self {
+            Gamemode::Creative => 0,
+            Gamemode::Survival => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Gamemode::Creative),
+            1 => Some(Gamemode::Survival),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "creative" => Some(Gamemode::Creative),
+            "survival" => Some(Gamemode::Survival),
+            _ => None,
+        }
+    }
+
+    pub fn has_hunger(&self) -> bool {
+        *self == Gamemode::Survival
+    }
+}
+
+/// What hunger did to health over a tick
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HungerEffect {
+    pub heal: f32,
+    pub starve: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hunger {
+    pub food: f32,
+    exhaustion: f32,
+    /// Seconds toward the next heal or starve
+    timer: f32,
+}
+
+impl Default for Hunger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hunger {
+    pub fn new() -> Self {
+        Self { food: MAX_FOOD, exhaustion: 0.0, timer: 0.0 }
+    }
+
+    /// Tires the player by `amount` exhaustion, costing food once enough has built up
+    pub fn exert(&mut self, amount: f32) {
+        self.exhaustion += amount.max(0.0);
+        while self.exhaustion >= EXHAUSTION_PER_FOOD {
+            self.exhaustion -= EXHAUSTION_PER_FOOD;
+            self.food = (self.food - 1.0).max(0.0);
+        }
+    }
+
+    pub fn eat(&mut self, food: f32) {
+        self.food = (self.food + food).min(MAX_FOOD);
+    }
+
+    pub fn can_sprint(&self) -> bool {
+        self.food > 0.0
+    }
+
+    /// Advances by `delta_time` for a player with `health` out of `max_health`
+    pub fn tick(&mut self, delta_time: f32, health: f32, max_health: f32) -> HungerEffect {
+        self.exert(IDLE_EXHAUSTION * delta_time);
+        let mut effect = HungerEffect::default();
+        let healing = self.food >= HEAL_MIN_FOOD && health < max_health;
+        let starving = self.food <= 0.0 && health > STARVE_MIN_HEALTH;
+        if !healing && !starving {
+            self.timer = 0.0;
+            return effect;
+        }
+        self.timer += delta_time;
+        if healing && self.timer >= HEAL_INTERVAL {
+            self.timer = 0.0;
+            effect.heal = 1.0;
+            self.exert(HEAL_EXHAUSTION);
+        } else if starving && self.timer >= STARVE_INTERVAL {
+            self.timer = 0.0;
+            effect.starve = 1.0;
+        }
+        effect
+    }
+
+    pub fn encode(&self, w: &mut ByteWriter) {
+        w.write_f32(self.food);
+        w.write_f32(self.exhaustion);
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        let food = r.read_f32()?;
+        let exhaustion = r.read_f32()?;
+        Ok(Self { food: food.clamp(0.0, MAX_FOOD), exhaustion: exhaustion.max(0.0), timer: 0.0 })
+    }
+}
+
+/// Draws `food` as a row of icons in the bottom right corner, each full, half or empty
+pub fn draw_food(overlay: &mut Overlay, food: f32, screen: (f32, f32), text_scale: f32) {
+    let icons = (MAX_FOOD / FOOD_PER_ICON) as usize;
+    let size = 4.0 * text_scale;
+    let gap = 1.0 * text_scale;
+    let y = screen.1 - 8.0 * text_scale - size;
+    for i in 0..icons {
+        // Icons empty from the left, like the stomach draining toward the screen edge
+        let x = screen.0 - 8.0 * text_scale - (icons - i) as f32 * (size + gap);
+        let fill = ((food - (icons - 1 - i) as f32 * FOOD_PER_ICON) / FOOD_PER_ICON).clamp(0.0, 1.0);
+        overlay.add_rect(x, y, size, size, [0.2, 0.12, 0.05, 0.8]);
+        if fill > 0.0 {
+            let width = if fill >= 1.0 { size } else { size / 2.0 };
+            overlay.add_rect(x + size - width, y, width, size, [0.85, 0.55, 0.2, 1.0]);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Crash-safe file replacement.
+//!
+//! A file is never overwritten in place. The new contents go to `<name>.tmp` and are synced
+//! to disk. The old file is then renamed to `<name>.bak` and the temporary file is renamed
+//! into place. At every point either the new file, the old file or its backup is complete
+//! on disk.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Where the previous contents of `path` are kept
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".tmp")
+}
+
+pub fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp = temp_path(path);
+    {
+        let mut file = File::create(&temp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+    }
+    match fs::rename(path, backup_path(path)) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => return Err(e),
+    }
+    fs::rename(&temp, path)?;
+    sync_parent(path);
+    Ok(())
+}
+
+/// Makes the renames durable. Not every platform can open a directory, so this is best effort.
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            dir.sync_all().ok();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+/// Deletes a temporary file left behind by a write that was interrupted. The previous
+/// contents are still intact in `path` or its backup. Returns whether one was found.
+pub fn discard_interrupted_write(path: &Path) -> bool {
+    fs::remove_file(temp_path(path)).is_ok()
+}
@@ -0,0 +1,172 @@
+//! Timestamped world backups.
+//!
+//! A backup is a directory of hard links to the world's files. Save files are always
+//! replaced by renaming a new file into place (see `atomic`) and never edited in place, so
+//! a linked snapshot keeps its contents while the world moves on. Where linking is not
+//! possible, e.g. across filesystems, files are copied instead.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use log::{info, warn};
+
+/// Backups kept per world before the oldest are deleted
+pub const DEFAULT_BACKUP_RETENTION: usize = 10;
+const BACKUP_DIR: &str = "backups";
+/// Derived data that is rebuilt on demand, so not worth keeping
+const SKIPPED_DIRS: [&str; 1] = ["meshes"];
+
+#[derive(Debug, Clone)]
+pub struct BackupInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub files: usize,
+}
+
+pub struct BackupManager {
+    pub world_root: PathBuf,
+    /// Backups of this world live here, next to the world directory
+    pub backup_root: PathBuf,
+    pub retention: usize,
+}
+
+impl BackupManager {
+    pub fn new(world_root: impl Into<PathBuf>) -> Self {
+        let world_root = world_root.into();
+        let world_name = world_root.file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "world".to_string());
+        let backup_root = world_root.parent().unwrap_or(Path::new(".")).join(BACKUP_DIR).join(world_name);
+        Self { world_root, backup_root, retention: DEFAULT_BACKUP_RETENTION }
+    }
+
+    /// Snapshots the world as it is on disk; save it first. `label` is appended to the
+    /// timestamp to make a backup easier to find later.
+    pub fn create(&self, label: Option<&str>) -> io::Result<BackupInfo> {
+        let mut name = timestamp(SystemTime::now());
+        if let Some(label) = label {
+            let label: String = label.chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+                .collect();
+            name = format!("{}-{}", name, label);
+        }
+        let path = self.backup_root.join(&name);
+        if path.exists() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("backup {} already exists", name)));
+        }
+        // A new world may have nothing saved yet
+        fs::create_dir_all(&self.world_root)?;
+        let files = link_tree(&self.world_root, &path)?;
+        info!("Backed up {} files to {}", files, path.display());
+        self.prune()?;
+        Ok(BackupInfo { name, path, files })
+    }
+
+    /// Backup names, oldest first
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.backup_root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        // Names start with a fixed-width timestamp, so they sort chronologically
+        names.sort();
+        Ok(names)
+    }
+
+    /// Deletes the oldest backups beyond the retention limit
+    fn prune(&self) -> io::Result<()> {
+        let names = self.list()?;
+        let excess = names.len().saturating_sub(self.retention.max(1));
+        for name in &names[..excess] {
+            info!("Removing old backup {}", name);
+            fs::remove_dir_all(self.backup_root.join(name))?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the world with a backup. The server must not be running. The current world is
+    /// kept beside it as `<world>.before-restore` so a mistaken restore can be undone.
+    pub fn restore(&self, name: &str) -> io::Result<()> {
+        let source = self.backup_root.join(name);
+        if name.contains(['/', '\\']) || name.starts_with('.') || !source.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no backup named {}", name)));
+        }
+        if self.world_root.exists() {
+            let mut aside = self.world_root.clone().into_os_string();
+            aside.push(".before-restore");
+            let aside = PathBuf::from(aside);
+            if aside.exists() {
+                fs::remove_dir_all(&aside)?;
+            }
+            fs::rename(&self.world_root, &aside)?;
+            info!("Moved current world to {}", aside.display());
+        }
+        // Copy rather than link back, so the backup stays untouched by later saves
+        let files = copy_tree(&source, &self.world_root)?;
+        info!("Restored {} files from backup {}", files, name);
+        Ok(())
+    }
+}
+
+fn link_tree(from: &Path, to: &Path) -> io::Result<usize> {
+    walk_tree(from, to, &|src, dst| fs::hard_link(src, dst).or_else(|e| {
+        warn!("Copying {} instead of linking: {}", src.display(), e);
+        fs::copy(src, dst).map(|_| ())
+    }))
+}
+
+fn copy_tree(from: &Path, to: &Path) -> io::Result<usize> {
+    walk_tree(from, to, &|src, dst| fs::copy(src, dst).map(|_| ()))
+}
+
+fn walk_tree(from: &Path, to: &Path, place: &dyn Fn(&Path, &Path) -> io::Result<()>) -> io::Result<usize> {
+    fs::create_dir_all(to)?;
+    let mut files = 0;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let src = entry.path();
+        let dst = to.join(&name);
+        if entry.file_type()?.is_dir() {
+            if !SKIPPED_DIRS.iter().any(|skip| name == *skip) {
+                files += walk_tree(&src, &dst, place)?;
+            }
+        } else if src.extension().is_none_or(|ext| ext != "tmp") {
+            // Temporary files are unfinished writes
+            place(&src, &dst)?;
+            files += 1;
+        }
+    }
+    Ok(files)
+}
+
+/// UTC time as `YYYYMMDD-HHMMSS`
+pub(crate) fn timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", year, month, day, rem / 3600, rem / 60 % 60, rem % 60)
+}
+
+/// Gregorian date for a count of days since 1970-01-01 (Howard Hinnant's algorithm)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,175 @@
+//! On-disk cache of built chunk meshes.
+//!
+//! Meshes are stored per region next to the world's region files, under `meshes/`. Each
+//! entry carries the content hash of the chunk and its boundary neighbours at the time it
+//! was meshed, so an entry is only reused when the inputs to meshing are unchanged.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use log::warn;
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::engine::graphics::vertex::BlockFaceInstance;
+use crate::game::save::region::region_key;
+
+const MESH_MAGIC: &[u8; 4] = b"PSUM";
+const MESH_VERSION: u32 = 3;
+/// Bytes per face: position, face and block type, then the two tint words and the light
+const FACE_BYTES: usize = 17;
+
+#[derive(Debug, Clone, Copy)]
+struct CachedFace {
+    /// Position relative to the chunk origin
+    position: [u8; 3],
+    face: u8,
+    block_type: u8,
+    tint: [u32; 2],
+    light: u32,
+}
+
+#[derive(Debug, Clone)]
+struct CachedMesh {
+    hash: u64,
+    faces: Vec<CachedFace>,
+}
+
+#[derive(Debug, Default)]
+struct MeshRegion {
+    meshes: HashMap<(i32, i32, i32), CachedMesh>,
+}
+
+impl MeshRegion {
+    fn load(path: &Path) -> io::Result<Self> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        let mut r = ByteReader::new(&data);
+        if r.read_bytes(4)? != MESH_MAGIC {
+            return Err(DecodeError::Invalid("bad mesh cache magic".into()).into());
+        }
+        let version = r.read_u32()?;
+        if version != MESH_VERSION {
+            // Stale caches are simply rebuilt
+            return Ok(Self::default());
+        }
+        let count = r.read_u32()?;
+        let mut meshes = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let key = (r.read_i32()?, r.read_i32()?, r.read_i32()?);
+            let hash = r.read_u64()?;
+            let face_count = r.read_u32()? as usize;
+            let bytes = r.read_bytes(face_count * FACE_BYTES)?;
+            let faces = bytes.chunks_exact(FACE_BYTES).map(|f| {
+                let word = |at: usize| u32::from_le_bytes([f[at], f[at + 1], f[at + 2], f[at + 3]]);
+                CachedFace { position: [f[0], f[1], f[2]], face: f[3], block_type: f[4], tint: [word(5), word(9)], light: word(13) }
+            }).collect();
+            meshes.insert(key, CachedMesh { hash, faces });
+        }
+        Ok(Self { meshes })
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let mut w = ByteWriter::new();
+        w.write_bytes(MESH_MAGIC);
+        w.write_u32(MESH_VERSION);
+        w.write_u32(self.meshes.len() as u32);
+        for (key, mesh) in &self.meshes {
+            w.write_i32(key.0);
+            w.write_i32(key.1);
+            w.write_i32(key.2);
+            w.write_u64(mesh.hash);
+            w.write_u32(mesh.faces.len() as u32);
+            for f in &mesh.faces {
+                w.write_bytes(&f.position);
+                w.write_bytes(&[f.face, f.block_type]);
+                w.write_u32(f.tint[0]);
+                w.write_u32(f.tint[1]);
+                w.write_u32(f.light);
+            }
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, w.into_inner())
+    }
+}
+
+pub struct MeshCache {
+    pub root: PathBuf,
+    regions: HashMap<(i32, i32, i32), MeshRegion>,
+    dirty_regions: HashSet<(i32, i32, i32)>,
+}
+
+impl MeshCache {
+    /// Opens the cache stored in `world_root`/meshes
+    pub fn open(world_root: impl AsRef<Path>) -> Self {
+        Self {
+            root: world_root.as_ref().join("meshes"),
+            regions: HashMap::new(),
+            dirty_regions: HashSet::new(),
+        }
+    }
+
+    fn region_path(&self, key: (i32, i32, i32)) -> PathBuf {
+        self.root.join(format!("r.{}.{}.{}.msh", key.0, key.1, key.2))
+    }
+
+    fn region_mut(&mut self, key: (i32, i32, i32)) -> &mut MeshRegion {
+        if !self.regions.contains_key(&key) {
+            let path = self.region_path(key);
+            let region = MeshRegion::load(&path).unwrap_or_else(|e| {
+                warn!("Failed to read mesh cache {}: {}", path.display(), e);
+                MeshRegion::default()
+            });
+            self.regions.insert(key, region);
+        }
+        self.regions.get_mut(&key).unwrap()
+    }
+
+    /// Cached faces for a chunk, if they were built from content with the same hash
+    pub fn get(&mut self, chunk_key: (i32, i32, i32), origin: (i32, i32, i32), hash: u64) -> Option<Vec<BlockFaceInstance>> {
+        let mesh = self.region_mut(region_key(chunk_key)).meshes.get(&chunk_key)?;
+        if mesh.hash != hash {
+            return None;
+        }
+        Some(mesh.faces.iter().map(|f| BlockFaceInstance {
+            position: [origin.0 + f.position[0] as i32, origin.1 + f.position[1] as i32, origin.2 + f.position[2] as i32],
+            face: f.face as u32,
+            block_type: f.block_type as u32,
+            tint: f.tint,
+            light: f.light,
+        }).collect())
+    }
+
+    pub fn store(&mut self, chunk_key: (i32, i32, i32), origin: (i32, i32, i32), hash: u64, faces: &[BlockFaceInstance]) {
+        let faces = faces.iter().map(|f| CachedFace {
+            position: [
+                (f.position[0] - origin.0) as u8,
+                (f.position[1] - origin.1) as u8,
+                (f.position[2] - origin.2) as u8,
+            ],
+            face: f.face as u8,
+            block_type: f.block_type as u8,
+            tint: f.tint,
+            light: f.light,
+        }).collect();
+        let region = region_key(chunk_key);
+        self.region_mut(region).meshes.insert(chunk_key, CachedMesh { hash, faces });
+        self.dirty_regions.insert(region);
+    }
+
+    /// Writes changed regions and drops everything from memory
+    pub fn flush(&mut self) -> io::Result<()> {
+        for key in std::mem::take(&mut self.dirty_regions) {
+            if let Some(region) = self.regions.get(&key) {
+                region.save(&self.region_path(key))?;
+            }
+        }
+        self.regions.clear();
+        Ok(())
+    }
+}
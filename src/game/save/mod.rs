@@ -0,0 +1,18 @@
+//! World persistence: region files and global save data.
+
+pub mod atomic;
+pub mod backup;
+pub mod mesh_cache;
+pub mod region;
+pub mod saved_chunks;
+pub mod settings;
+pub mod world_save;
+pub mod writer;
+
+pub use backup::{BackupInfo, BackupManager};
+pub use mesh_cache::MeshCache;
+pub use region::{ChunkRecord, RegionFile};
+pub use saved_chunks::SavedChunks;
+pub use settings::SaveSettings;
+pub use world_save::{PlayerData, PlayerId, WorldData, WorldSave};
+pub use writer::SaveWriter;
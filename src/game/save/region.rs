@@ -0,0 +1,344 @@
+//! Region file format.
+//!
+//! A region stores the records of a REGION_SIZE^3 cube of chunks in a single file. Each chunk
+//! record is a list of tagged sections so new kinds of per-chunk data can be added without
+//! breaking older saves; unknown sections are skipped on load.
+//!
+//! Every record carries a CRC-32 and may be deflated, and files are replaced atomically,
+//! keeping the previous version as a backup. Loading checks each record and restores damaged
+//! ones from the backup, so a crash or a torn write loses at most the latest save of a chunk.
+//! Records the backup has and the file does not were removed on purpose and stay removed,
+//! unless the file lost records it can no longer name, when the backup fills every gap.
+
+use std::collections::hash_map::{Entry, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use log::warn;
+
+use crate::engine::codec::{crc32, ByteReader, ByteWriter, DecodeError};
+use crate::game::entity::{Entity, EntityId};
+use crate::game::net::protocol::ChunkEdit;
+use crate::game::save::atomic;
+use crate::game::world::chunk::{BlockType, ChunkBlocks, CHUNK_SIZE};
+use crate::game::world::sign::SignData;
+
+/// Chunks per region along each axis
+pub const REGION_SIZE: i32 = 8;
+const REGION_MAGIC: &[u8; 4] = b"PSUR";
+/// Version 2 added per-record checksums, version 3 compression
+const REGION_VERSION: u32 = 3;
+/// Largest record a region will inflate, so a damaged length cannot exhaust memory
+const MAX_RECORD_SIZE: usize = 64 * 1024 * 1024;
+
+const METHOD_STORED: u8 = 0;
+const METHOD_DEFLATE: u8 = 1;
+
+const SECTION_ENTITIES: u8 = 1;
+const SECTION_SIGNS: u8 = 2;
+const SECTION_BLOCKS: u8 = 3;
+const SECTION_EDITS: u8 = 4;
+
+/// The file holding region `key` of the world saved in `root`
+pub fn region_path(root: &Path, key: (i32, i32, i32)) -> PathBuf {
+    root.join("region").join(format!("r.{}.{}.{}.bin", key.0, key.1, key.2))
+}
+
+pub fn region_key(chunk_key: (i32, i32, i32)) -> (i32, i32, i32) {
+    (
+        chunk_key.0.div_euclid(REGION_SIZE),
+        chunk_key.1.div_euclid(REGION_SIZE),
+        chunk_key.2.div_euclid(REGION_SIZE),
+    )
+}
+
+/// Persisted data for a single chunk
+#[derive(Debug, Clone, Default)]
+pub struct ChunkRecord {
+    pub entities: Vec<Entity>,
+    pub signs: Vec<SignData>,
+    /// The whole chunk, for chunks saved ahead of time, e.g. by pregeneration. Without it
+    /// the chunk comes from the generator.
+    pub blocks: Option<Box<ChunkBlocks>>,
+    /// The blocks edited in the chunk by position within it, applied over `blocks` or the
+    /// generated terrain when it loads, as the block journal had them at the last save
+    pub edits: Vec<ChunkEdit>,
+}
+
+impl ChunkRecord {
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty() && self.signs.is_empty() && self.blocks.is_none() && self.edits.is_empty()
+    }
+
+    pub fn encode(&self, w: &mut ByteWriter) {
+        let mut sections = Vec::new();
+        if !self.entities.is_empty() {
+            let mut s = ByteWriter::new();
+            s.write_u32(self.entities.len() as u32);
+            for entity in &self.entities {
+                entity.encode(&mut s);
+            }
+            sections.push((SECTION_ENTITIES, s.into_inner()));
+        }
+        if !self.signs.is_empty() {
+            let mut s = ByteWriter::new();
+            s.write_u32(self.signs.len() as u32);
+            for sign in &self.signs {
+                sign.encode(&mut s);
+            }
+            sections.push((SECTION_SIGNS, s.into_inner()));
+        }
+        if let Some(blocks) = &self.blocks {
+            let mut s = ByteWriter::new();
+            for block in blocks.iter().flatten().flatten() {
+                s.write_u8(block.id());
+                s.write_u8(block.meta());
+            }
+            sections.push((SECTION_BLOCKS, s.into_inner()));
+        }
+        if !self.edits.is_empty() {
+            let mut s = ByteWriter::new();
+            s.write_u32(self.edits.len() as u32);
+            for &((x, y, z), block) in &self.edits {
+                s.write_u8(x);
+                s.write_u8(y);
+                s.write_u8(z);
+                s.write_u8(block.id());
+                s.write_u8(block.meta());
+            }
+            sections.push((SECTION_EDITS, s.into_inner()));
+        }
+        w.write_u8(sections.len() as u8);
+        for (tag, data) in sections {
+            w.write_u8(tag);
+            w.write_blob(&data);
+        }
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        let mut record = ChunkRecord::default();
+        let section_count = r.read_u8()?;
+        for _ in 0..section_count {
+            let tag = r.read_u8()?;
+            let data = r.read_blob()?;
+            let mut s = ByteReader::new(data);
+            if tag == SECTION_ENTITIES {
+                let count = s.read_u32()?;
+                for _ in 0..count {
+                    // Real ids are assigned when the entities are inserted into the world
+                    record.entities.push(Entity::decode(&mut s, EntityId(0))?);
+                }
+            } else if tag == SECTION_SIGNS {
+                let count = s.read_u32()?;
+                for _ in 0..count {
+                    record.signs.push(SignData::decode(&mut s)?);
+                }
+            } else if tag == SECTION_BLOCKS {
+                let mut blocks = Box::new([[[BlockType::Air; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]);
+                for block in blocks.iter_mut().flatten().flatten() {
+                    let (id, meta) = (s.read_u8()?, s.read_u8()?);
+                    *block = BlockType::from_parts(id, meta)
+                        .ok_or_else(|| DecodeError::Invalid(format!("unknown block {}:{}", id, meta)))?;
+                }
+                record.blocks = Some(blocks);
+            } else if tag == SECTION_EDITS {
+                let count = s.read_u32()?;
+                let size = CHUNK_SIZE as u8;
+                for _ in 0..count {
+                    let local = (s.read_u8()?, s.read_u8()?, s.read_u8()?);
+                    if local.0 >= size || local.1 >= size || local.2 >= size {
+                        return Err(DecodeError::Invalid(format!("edit outside the chunk at {:?}", local)));
+                    }
+                    let (id, meta) = (s.read_u8()?, s.read_u8()?);
+                    let block = BlockType::from_parts(id, meta)
+                        .ok_or_else(|| DecodeError::Invalid(format!("unknown block {}:{}", id, meta)))?;
+                    record.edits.push((local, block));
+                }
+            }
+        }
+        Ok(record)
+    }
+}
+
+type ChunkKey = (i32, i32, i32);
+
+/// Records read from one file, and how many could not be read
+struct ReadResult {
+    chunks: HashMap<ChunkKey, ChunkRecord>,
+    damaged: usize,
+    /// Chunks whose records were there but failed to read
+    damaged_keys: Vec<ChunkKey>,
+    /// Whether records were lost without their keys, e.g. past a cut
+    lost_keys: bool,
+}
+
+/// Reads a region file. The header must be intact; damaged records are counted and skipped.
+/// A truncated file keeps the records before the cut.
+fn read_region(data: &[u8]) -> Result<ReadResult, DecodeError> {
+    let mut r = ByteReader::new(data);
+    if r.read_bytes(4)? != REGION_MAGIC {
+        return Err(DecodeError::Invalid("bad region magic".into()));
+    }
+    let version = r.read_u32()?;
+    if version == 0 || version > REGION_VERSION {
+        return Err(DecodeError::Invalid(format!("unsupported region version {}", version)));
+    }
+    let count = r.read_u32()?;
+    let mut result = ReadResult { chunks: HashMap::new(), damaged: 0, damaged_keys: Vec::new(), lost_keys: false };
+    for read in 0..count {
+        let record = (|| {
+            let key = (r.read_i32()?, r.read_i32()?, r.read_i32()?);
+            if version == 1 {
+                return Ok((key, ChunkRecord::decode(&mut r)));
+            }
+            let checksum = r.read_u32()?;
+            let method = if version >= 3 { r.read_u8()? } else { METHOD_STORED };
+            let blob = r.read_blob()?;
+            let data = match method {
+                METHOD_STORED => blob.to_vec(),
+                METHOD_DEFLATE => match miniz_oxide::inflate::decompress_to_vec_with_limit(blob, MAX_RECORD_SIZE) {
+                    Ok(data) => data,
+                    Err(e) => return Ok((key, Err(DecodeError::Invalid(format!("bad compressed data: {:?}", e.status))))),
+                },
+                other => return Ok((key, Err(DecodeError::Invalid(format!("unknown compression method {}", other))))),
+            };
+            // The checksum covers the uncompressed record, so it also catches bad inflating
+            if crc32(&data) != checksum {
+                return Ok((key, Err(DecodeError::Invalid("checksum mismatch".into()))));
+            }
+            Ok((key, ChunkRecord::decode(&mut ByteReader::new(&data))))
+        })();
+        match record {
+            Ok((key, Ok(record))) => {
+                result.chunks.insert(key, record);
+            }
+            Ok((key, Err(e))) => {
+                warn!("Damaged record for chunk {:?}: {}", key, e);
+                result.damaged += 1;
+                result.damaged_keys.push(key);
+            }
+            Err(DecodeError::UnexpectedEof) => {
+                // Everything after a cut is lost, so count it all
+                result.damaged += (count - read) as usize;
+                result.lost_keys = true;
+                break;
+            }
+            Err(e) => return Err(e),
+        }
+        if version == 1 && result.damaged > 0 {
+            // Without lengths there is no way to find the next record
+            result.damaged += (count - read - 1) as usize;
+            result.lost_keys |= read + 1 < count;
+            break;
+        }
+    }
+    Ok(result)
+}
+
+fn read_region_file(path: &Path) -> io::Result<Option<ReadResult>> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(read_region(&data)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RegionFile {
+    pub chunks: HashMap<(i32, i32, i32), ChunkRecord>,
+    /// Set when loading had to repair the file, so it should be rewritten
+    pub repaired: bool,
+}
+
+impl RegionFile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a region file, returning an empty region if it does not exist yet. Damage is
+    /// repaired from the backup the previous save left behind, where possible.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let interrupted = atomic::discard_interrupted_write(path);
+        if interrupted {
+            warn!("Discarded interrupted write of {}", path.display());
+        }
+        Self::read(path)
+    }
+
+    /// `load` without touching the files, for reading while the save thread may be writing
+    /// the same region. A region caught between the renames of a write is read from its
+    /// backup.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let primary = match read_region_file(path) {
+            Ok(Some(result)) if result.damaged == 0 => return Ok(Self { chunks: result.chunks, repaired: false }),
+            other => other,
+        };
+
+        // The file is missing, unreadable or has damaged records: salvage what we can and
+        // fill the gaps from the backup
+        let backup_path = atomic::backup_path(path);
+        let backup = read_region_file(&backup_path).unwrap_or_else(|e| {
+            warn!("Failed to read backup {}: {}", backup_path.display(), e);
+            None
+        });
+        // Which chunks to take from the backup: only the damaged ones when the file names
+        // them all, since chunks missing from it otherwise were removed since the backup
+        let (mut chunks, damaged, only) = match primary {
+            Ok(Some(result)) if result.lost_keys => (result.chunks, result.damaged, None),
+            Ok(Some(result)) => (result.chunks, result.damaged, Some(result.damaged_keys)),
+            Ok(None) if backup.is_none() => return Ok(Self::new()),
+            Ok(None) => (HashMap::new(), 0, None),
+            Err(e) if backup.is_none() => return Err(e),
+            Err(e) => {
+                warn!("Region {} is unreadable: {}", path.display(), e);
+                (HashMap::new(), 0, None)
+            }
+        };
+        let mut restored = 0;
+        if let Some(backup) = backup {
+            for (key, record) in backup.chunks {
+                if only.as_ref().is_some_and(|keys| !keys.contains(&key)) {
+                    continue;
+                }
+                if let Entry::Vacant(entry) = chunks.entry(key) {
+                    entry.insert(record);
+                    restored += 1;
+                }
+            }
+        }
+        warn!("Repaired region {}: {} damaged records, {} restored from backup",
+            path.display(), damaged, restored);
+        Ok(Self { chunks, repaired: true })
+    }
+
+    /// The region file contents, with records deflated at `compression_level`, 0 storing
+    /// them as they are
+    pub fn encode(&self, compression_level: u8) -> Vec<u8> {
+        let mut w = ByteWriter::new();
+        w.write_bytes(REGION_MAGIC);
+        w.write_u32(REGION_VERSION);
+        let records: Vec<_> = self.chunks.iter().filter(|(_, record)| !record.is_empty()).collect();
+        w.write_u32(records.len() as u32);
+        for (key, record) in records {
+            w.write_i32(key.0);
+            w.write_i32(key.1);
+            w.write_i32(key.2);
+            let mut data = ByteWriter::new();
+            record.encode(&mut data);
+            let data = data.into_inner();
+            w.write_u32(crc32(&data));
+            if compression_level == 0 {
+                w.write_u8(METHOD_STORED);
+                w.write_blob(&data);
+            } else {
+                w.write_u8(METHOD_DEFLATE);
+                w.write_blob(&miniz_oxide::deflate::compress_to_vec(&data, compression_level));
+            }
+        }
+        w.into_inner()
+    }
+
+    pub fn save(&self, path: &Path, compression_level: u8) -> io::Result<()> {
+        atomic::write_atomic(path, &self.encode(compression_level))
+    }
+}
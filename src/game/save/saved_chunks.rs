@@ -0,0 +1,73 @@
+//! Reading chunks saved whole, such as pregenerated ones, from the chunk loading threads.
+//!
+//! The threads that would otherwise generate a chunk look it up here first. Region files
+//! are read on those threads, never the server's, and only the chunk blocks are kept. Each
+//! region is read once and kept for the next chunk in it until the cache is full. Chunks
+//! saved whole while the world is running are not seen until it is opened again, which
+//! is fine for pregeneration, the only thing that saves them.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use log::warn;
+
+use crate::game::save::region::{self, region_key, RegionFile};
+use crate::game::world::chunk::ChunkBlocks;
+
+type ChunkKey = (i32, i32, i32);
+type RegionBlocks = HashMap<ChunkKey, Box<ChunkBlocks>>;
+
+/// Regions whose blocks are kept in memory at once
+const CACHED_REGIONS: usize = 8;
+
+#[derive(Default)]
+struct Cache {
+    regions: HashMap<ChunkKey, Arc<RegionBlocks>>,
+    /// Keys of `regions`, the first read first
+    order: VecDeque<ChunkKey>,
+}
+
+/// Shared between the loading threads of a ChunkManager
+pub struct SavedChunks {
+    root: PathBuf,
+    cache: Mutex<Cache>,
+}
+
+impl SavedChunks {
+    /// Reads the chunks of the world saved in `root`
+    pub fn open(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), cache: Mutex::new(Cache::default()) }
+    }
+
+    /// The blocks saved for a chunk, if it was saved whole. May read its region from disk.
+    pub fn load(&self, chunk_key: ChunkKey) -> Option<Box<ChunkBlocks>> {
+        let key = region_key(chunk_key);
+        let cached = self.cache.lock().ok()?.regions.get(&key).cloned();
+        // Read without holding the lock, so other threads keep loading. Two threads may
+        // race to read the same region; both get the same blocks.
+        let blocks = cached.unwrap_or_else(|| self.read_region(key));
+        blocks.get(&chunk_key).cloned()
+    }
+
+    fn read_region(&self, key: ChunkKey) -> Arc<RegionBlocks> {
+        let path = region::region_path(&self.root, key);
+        let region = RegionFile::read(&path).unwrap_or_else(|e| {
+            warn!("Failed to read region {}: {}", path.display(), e);
+            RegionFile::new()
+        });
+        let blocks: RegionBlocks = region.chunks.into_iter()
+            .filter_map(|(chunk, record)| record.blocks.map(|blocks| (chunk, blocks)))
+            .collect();
+        let blocks = Arc::new(blocks);
+        if let Ok(mut cache) = self.cache.lock() {
+            if cache.regions.insert(key, Arc::clone(&blocks)).is_none() {
+                cache.order.push_back(key);
+            }
+            while cache.order.len() > CACHED_REGIONS {
+                let Some(oldest) = cache.order.pop_front() else { break };
+                cache.regions.remove(&oldest);
+            }
+        }
+        blocks
+    }
+}
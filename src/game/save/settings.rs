@@ -0,0 +1,98 @@
+//! Save settings, read from a text file in the world directory.
+//!
+//! The file holds `key = value` lines, with `#` comments. A world without one gets the
+//! defaults written out, so there is a file to edit.
+
+use std::fs;
+use std::path::Path;
+use log::warn;
+
+use crate::game::save::atomic;
+
+/// File in the world directory holding the save settings
+pub const SAVE_SETTINGS_FILE: &str = "save_settings.txt";
+/// Highest deflate level; 0 stores region records uncompressed
+pub const MAX_COMPRESSION_LEVEL: u8 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveSettings {
+    /// Seconds between autosaves, 0 to only save on request and on shutdown
+    pub autosave_interval: u32,
+    /// Most region files handed to the IO thread per server tick. Each region holds the
+    /// saved records of many chunks, so this bounds the encoding work a tick does.
+    pub max_region_writes_per_tick: usize,
+    /// Deflate level of region records, 0 to MAX_COMPRESSION_LEVEL
+    pub compression_level: u8,
+}
+
+impl Default for SaveSettings {
+    fn default() -> Self {
+        Self { autosave_interval: 300, max_region_writes_per_tick: 4, compression_level: 6 }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{} must be a whole number, got {}", key, value))
+}
+
+impl SaveSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the settings file, keeping the default of anything it leaves out
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut settings = Self::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| format!("line {}: expected key = value", number + 1))?;
+            settings.set(key.trim(), value.trim()).map_err(|e| format!("line {}: {}", number + 1, e))?;
+        }
+        Ok(settings)
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "autosave_interval" => self.autosave_interval = parse_number(key, value)?,
+            // At least one, or dirty regions would only ever be written by a full save
+            "max_region_writes_per_tick" => self.max_region_writes_per_tick = parse_number::<usize>(key, value)?.max(1),
+            "compression_level" => self.compression_level = parse_number::<u8>(key, value)?.min(MAX_COMPRESSION_LEVEL),
+            _ => return Err(format!("unknown setting {}", key)),
+        }
+        Ok(())
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "# Seconds between autosaves, 0 to only save on /save-all and on shutdown\n\
+             autosave_interval = {}\n\
+             # Most region files queued for writing per server tick\n\
+             max_region_writes_per_tick = {}\n\
+             # Region compression, 0 (none) to {}\n\
+             compression_level = {}\n",
+            self.autosave_interval, self.max_region_writes_per_tick, MAX_COMPRESSION_LEVEL, self.compression_level,
+        )
+    }
+
+    /// Reads the settings of the world in `world_dir`, writing the defaults there if it has
+    /// none yet. Unreadable settings are reported and the defaults used.
+    pub fn load(world_dir: &Path) -> Self {
+        let path = world_dir.join(SAVE_SETTINGS_FILE);
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text).unwrap_or_else(|e| {
+                warn!("Failed to read {}, {}; using the defaults", path.display(), e);
+                Self::new()
+            }),
+            Err(_) => {
+                let settings = Self::new();
+                if let Err(e) = atomic::write_atomic(&path, settings.to_text().as_bytes()) {
+                    warn!("Failed to write default save settings {}: {}", path.display(), e);
+                }
+                settings
+            }
+        }
+    }
+}
@@ -0,0 +1,405 @@
+//! World save implementation.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use glam::Vec3;
+use log::{info, warn};
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::game::entity::{Entity, EntityManager};
+use crate::game::item::Inventory;
+use crate::game::net::protocol::ChunkEdit;
+use crate::game::player::{Gamemode, Hunger};
+use crate::game::world::chunk::ChunkBlocks;
+use crate::game::world::chunk_manager::ChunkManager;
+use crate::game::world::rules::GameRules;
+use crate::game::world::sign::SignData;
+use crate::game::world::worldgen::WorldGen;
+use crate::game::save::atomic;
+use crate::game::save::region::{self, region_key, RegionFile};
+use crate::game::save::settings::SaveSettings;
+use crate::game::save::writer::SaveWriter;
+
+const PLAYER_DIR: &str = "players";
+/// Global world state such as the time of day and the game rules
+const WORLD_FILE: &str = "world.dat";
+
+/// Identifies a player from one session to the next. Players are known only by the name
+/// they join with, so it comes from that, ignoring case as bans and lookups do. Anyone
+/// joining with a name gets the player saved under it; see AccessLists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PlayerId(pub u64);
+
+impl PlayerId {
+    pub fn from_name(name: &str) -> Self {
+        // FNV-1a, which stays the same across builds and platforms
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in name.to_lowercase().bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        Self(hash)
+    }
+}
+
+impl std::fmt::Display for PlayerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Global player state, stored outside the region files since it isn't tied to a chunk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerData {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Bed the player last slept in, where they respawn
+    pub spawn: Option<(i32, i32, i32)>,
+    pub inventory: Inventory,
+    pub gamemode: Gamemode,
+    pub hunger: Hunger,
+}
+
+impl PlayerData {
+    pub fn new(position: Vec3) -> Self {
+        Self { position, yaw: 0.0, pitch: 0.0, spawn: None, inventory: Inventory::new(), gamemode: Gamemode::default(), hunger: Hunger::new() }
+    }
+
+    pub fn encode(&self, w: &mut ByteWriter) {
+        w.write_vec3(self.position);
+        w.write_f32(self.yaw);
+        w.write_f32(self.pitch);
+        w.write_u8(self.spawn.is_some() as u8);
+        if let Some(spawn) = self.spawn {
+            w.write_i32(spawn.0);
+            w.write_i32(spawn.1);
+            w.write_i32(spawn.2);
+        }
+        self.inventory.encode(w);
+        w.write_u8(self.gamemode.id());
+        self.hunger.encode(w);
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        let position = r.read_vec3()?;
+        let yaw = r.read_f32()?;
+        let pitch = r.read_f32()?;
+        // Saves from before beds end here
+        let spawn = if !r.is_empty() && r.read_u8()? != 0 {
+            Some((r.read_i32()?, r.read_i32()?, r.read_i32()?))
+        } else {
+            None
+        };
+        // And saves from before inventories here
+        let inventory = if r.is_empty() { Inventory::new() } else { Inventory::decode(r)? };
+        // And saves from before gamemodes here
+        let (gamemode, hunger) = if r.is_empty() {
+            (Gamemode::default(), Hunger::new())
+        } else {
+            let id = r.read_u8()?;
+            let gamemode = Gamemode::from_id(id).ok_or_else(|| DecodeError::Invalid(format!("unknown gamemode {}", id)))?;
+            (gamemode, Hunger::decode(r)?)
+        };
+        Ok(Self { position, yaw, pitch, spawn, inventory, gamemode, hunger })
+    }
+}
+
+/// World-wide state kept in the world file
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WorldData {
+    /// Time of day in ticks
+    pub ticks: u64,
+    pub rules: GameRules,
+    /// Chosen when the world was created, and never changed after
+    pub generator: WorldGen,
+    /// Chunks with edits saved in their region, so clients can be caught up on them
+    /// without reading every region when the world opens
+    pub edited_chunks: Vec<(i32, i32, i32)>,
+}
+
+impl WorldData {
+    pub fn encode(&self, w: &mut ByteWriter) {
+        w.write_u64(self.ticks);
+        self.rules.encode(w);
+        self.generator.encode(w);
+        w.write_u32(self.edited_chunks.len() as u32);
+        for key in &self.edited_chunks {
+            w.write_i32(key.0);
+            w.write_i32(key.1);
+            w.write_i32(key.2);
+        }
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        let ticks = r.read_u64()?;
+        let rules = GameRules::decode(r)?;
+        // Saves from before world types end here, and were all default worlds
+        let generator = if r.is_empty() { WorldGen::default() } else { WorldGen::decode(r)? };
+        // And saves from before edited terrain was saved here
+        let mut edited_chunks = Vec::new();
+        if !r.is_empty() {
+            for _ in 0..r.read_u32()? {
+                edited_chunks.push((r.read_i32()?, r.read_i32()?, r.read_i32()?));
+            }
+        }
+        Ok(Self { ticks, rules, generator, edited_chunks })
+    }
+}
+
+/// Files are encoded on the calling thread and written by a SaveWriter, so saving only
+/// waits on the disk in `flush` and `wait`
+pub struct WorldSave {
+    pub root: PathBuf,
+    pub settings: SaveSettings,
+    regions: HashMap<(i32, i32, i32), RegionFile>,
+    dirty_regions: HashSet<(i32, i32, i32)>,
+    writer: SaveWriter,
+}
+
+impl WorldSave {
+    pub fn open(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        info!("Using world save at {}", root.display());
+        Self {
+            settings: SaveSettings::load(&root),
+            root,
+            regions: HashMap::new(),
+            dirty_regions: HashSet::new(),
+            writer: SaveWriter::new(),
+        }
+    }
+
+    fn region_path(&self, key: (i32, i32, i32)) -> PathBuf {
+        region::region_path(&self.root, key)
+    }
+
+    fn region_mut(&mut self, key: (i32, i32, i32)) -> &mut RegionFile {
+        if !self.regions.contains_key(&key) {
+            let path = self.region_path(key);
+            let region = RegionFile::load(&path).unwrap_or_else(|e| {
+                warn!("Failed to read region {}: {}", path.display(), e);
+                RegionFile::new()
+            });
+            if region.repaired {
+                // Write the salvaged records back so the damage is gone on the next load
+                self.dirty_regions.insert(key);
+            }
+            self.regions.insert(key, region);
+        }
+        self.regions.get_mut(&key).unwrap()
+    }
+
+    /// Removes the saved entities of a chunk so they can be placed into the live world
+    pub fn take_chunk_entities(&mut self, chunk_key: (i32, i32, i32)) -> Vec<Entity> {
+        let key = region_key(chunk_key);
+        let entities = self.region_mut(key).chunks.get_mut(&chunk_key)
+            .map(|record| std::mem::take(&mut record.entities))
+            .unwrap_or_default();
+        // The region on disk still holds them, and would bring them back twice after a crash
+        if !entities.is_empty() {
+            self.dirty_regions.insert(key);
+        }
+        entities
+    }
+
+    /// Replaces the saved entities of a chunk
+    pub fn store_chunk_entities(&mut self, chunk_key: (i32, i32, i32), entities: Vec<Entity>) {
+        let key = region_key(chunk_key);
+        let region = self.region_mut(key);
+        if entities.is_empty() && !region.chunks.contains_key(&chunk_key) {
+            return;
+        }
+        region.chunks.entry(chunk_key).or_default().entities = entities;
+        self.dirty_regions.insert(key);
+    }
+
+    /// The signs saved in a chunk, to put back when it loads
+    pub fn chunk_signs(&mut self, chunk_key: (i32, i32, i32)) -> Vec<SignData> {
+        let region = self.region_mut(region_key(chunk_key));
+        region.chunks.get(&chunk_key).map(|record| record.signs.clone()).unwrap_or_default()
+    }
+
+    /// Saves a sign, replacing what was saved for its block
+    pub fn store_sign(&mut self, sign: SignData) {
+        let chunk_key = ChunkManager::chunk_key(sign.block);
+        let key = region_key(chunk_key);
+        let signs = &mut self.region_mut(key).chunks.entry(chunk_key).or_default().signs;
+        match signs.iter_mut().find(|s| s.block == sign.block) {
+            Some(saved) if *saved == sign => return,
+            Some(saved) => *saved = sign,
+            None => signs.push(sign),
+        }
+        self.dirty_regions.insert(key);
+    }
+
+    /// Forgets the sign saved at `block`, if any
+    pub fn remove_sign(&mut self, block: (i32, i32, i32)) {
+        let chunk_key = ChunkManager::chunk_key(block);
+        let key = region_key(chunk_key);
+        let Some(record) = self.region_mut(key).chunks.get_mut(&chunk_key) else { return };
+        let count = record.signs.len();
+        record.signs.retain(|s| s.block != block);
+        if record.signs.len() != count {
+            self.dirty_regions.insert(key);
+        }
+    }
+
+    /// Saves every block of a chunk, so it can be loaded instead of generated
+    pub fn store_chunk_blocks(&mut self, chunk_key: (i32, i32, i32), blocks: &ChunkBlocks) {
+        let key = region_key(chunk_key);
+        self.region_mut(key).chunks.entry(chunk_key).or_default().blocks = Some(Box::new(*blocks));
+        self.dirty_regions.insert(key);
+    }
+
+    /// The blocks saved for a chunk, if it was saved whole
+    pub fn chunk_blocks(&mut self, chunk_key: (i32, i32, i32)) -> Option<Box<ChunkBlocks>> {
+        let region = self.region_mut(region_key(chunk_key));
+        region.chunks.get(&chunk_key).and_then(|record| record.blocks.clone())
+    }
+
+    /// The edits saved for a chunk, by position within it
+    pub fn chunk_edits(&mut self, chunk_key: (i32, i32, i32)) -> Vec<ChunkEdit> {
+        let region = self.region_mut(region_key(chunk_key));
+        region.chunks.get(&chunk_key).map(|record| record.edits.clone()).unwrap_or_default()
+    }
+
+    /// Replaces the saved edits of a chunk
+    pub fn store_chunk_edits(&mut self, chunk_key: (i32, i32, i32), edits: Vec<ChunkEdit>) {
+        let key = region_key(chunk_key);
+        let region = self.region_mut(key);
+        match region.chunks.get(&chunk_key) {
+            Some(record) if record.edits == edits => return,
+            None if edits.is_empty() => return,
+            _ => {}
+        }
+        region.chunks.entry(chunk_key).or_default().edits = edits;
+        self.dirty_regions.insert(key);
+    }
+
+    /// Stores every live entity under its current chunk, e.g. before shutting down.
+    /// Call `flush` afterwards to write them out.
+    pub fn store_all_entities(&mut self, entities: &EntityManager) {
+        let mut by_chunk: HashMap<(i32, i32, i32), Vec<Entity>> = HashMap::new();
+        for entity in entities.iter() {
+            by_chunk.entry(ChunkManager::chunk_key_at(entity.position)).or_default().push(entity.clone());
+        }
+        for (key, entities) in by_chunk {
+            self.store_chunk_entities(key, entities);
+        }
+    }
+
+    /// Regions changed since they were last queued for writing
+    pub fn dirty_region_count(&self) -> usize {
+        self.dirty_regions.len()
+    }
+
+    /// Files handed to the IO thread and not yet written
+    pub fn pending_writes(&self) -> usize {
+        self.writer.pending()
+    }
+
+    fn queue_region(&mut self, key: (i32, i32, i32)) {
+        self.dirty_regions.remove(&key);
+        if let Some(region) = self.regions.get(&key) {
+            self.writer.write(self.region_path(key), region.encode(self.settings.compression_level));
+        }
+    }
+
+    /// Queues up to `limit` modified regions for writing, fewer if the IO thread is behind.
+    /// The rest stay modified for a later call.
+    pub fn flush_some(&mut self, limit: usize) {
+        let mut queued = 0;
+        while queued < limit && self.writer.has_room() {
+            let Some(&key) = self.dirty_regions.iter().next() else { break };
+            self.queue_region(key);
+            queued += 1;
+        }
+    }
+
+    /// Writes every modified region, and everything queued before, to disk
+    pub fn flush(&mut self) -> io::Result<()> {
+        let keys: Vec<_> = self.dirty_regions.iter().copied().collect();
+        for key in keys {
+            self.queue_region(key);
+        }
+        self.wait()
+    }
+
+    /// Waits for every queued write, returning the first that failed since the last wait
+    pub fn wait(&self) -> io::Result<()> {
+        self.writer.wait()
+    }
+
+    /// The saved time of day and game rules, or the defaults for a new world. Falls back to
+    /// the previous save if the latest one is unreadable.
+    pub fn load_world(&self) -> WorldData {
+        let path = self.root.join(WORLD_FILE);
+        self.finish_writes();
+        atomic::discard_interrupted_write(&path);
+        [path.clone(), atomic::backup_path(&path)].iter().find_map(|path| {
+            let data = fs::read(path).ok()?;
+            WorldData::decode(&mut ByteReader::new(&data))
+                .map_err(|e| warn!("Failed to read world data {}: {}", path.display(), e))
+                .ok()
+        }).unwrap_or_default()
+    }
+
+    /// Whether the world has been saved before; a new one has no world file yet
+    pub fn has_world(&self) -> bool {
+        let path = self.root.join(WORLD_FILE);
+        path.exists() || atomic::backup_path(&path).exists()
+    }
+
+    /// Queues the world data for writing; `wait` reports whether it made it to disk
+    pub fn save_world(&self, world: &WorldData) {
+        let mut w = ByteWriter::new();
+        world.encode(&mut w);
+        self.writer.write(self.root.join(WORLD_FILE), w.into_inner());
+    }
+
+    /// Lets queued writes land before reading a file back, so a save still on its way is
+    /// not read stale or taken for an interrupted write
+    fn finish_writes(&self) {
+        if let Err(e) = self.writer.wait() {
+            warn!("Failed to write world save: {}", e);
+        }
+    }
+
+    fn player_path(&self, id: PlayerId) -> PathBuf {
+        self.root.join(PLAYER_DIR).join(format!("{}.dat", id))
+    }
+
+    /// Where players were saved by name before they had ids
+    fn legacy_player_path(&self, name: &str) -> PathBuf {
+        // Keep names from escaping the players directory
+        let file: String = name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+            .collect();
+        self.root.join(PLAYER_DIR).join(format!("{}.dat", file))
+    }
+
+    /// Falls back to the previous save if the latest one is unreadable, and to the save
+    /// under the player's name from before player ids. The next save moves it to the id.
+    pub fn load_player(&self, id: PlayerId, name: &str) -> Option<PlayerData> {
+        let path = self.player_path(id);
+        self.finish_writes();
+        atomic::discard_interrupted_write(&path);
+        let legacy = self.legacy_player_path(name);
+        [path.clone(), atomic::backup_path(&path), legacy].iter().find_map(|path| {
+            let data = fs::read(path).ok()?;
+            PlayerData::decode(&mut ByteReader::new(&data))
+                .map_err(|e| warn!("Failed to read player data {}: {}", path.display(), e))
+                .ok()
+        })
+    }
+
+    /// Queues the player's data for writing; `wait` reports whether it made it to disk
+    pub fn save_player(&self, id: PlayerId, player: &PlayerData) {
+        let mut w = ByteWriter::new();
+        player.encode(&mut w);
+        self.writer.write(self.player_path(id), w.into_inner());
+    }
+}
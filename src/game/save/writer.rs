@@ -0,0 +1,133 @@
+//! Save files written on a dedicated IO thread.
+//!
+//! The server encodes what it saves and queues the bytes here, so a slow disk never stalls
+//! a tick. The queue is bounded: callers check `has_room` before encoding more, and leave
+//! the rest for a later tick, which keeps memory bounded however much is being edited.
+//! Platforms without threads write straight away instead.
+
+use std::io;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use log::error;
+
+use crate::game::save::atomic;
+
+/// Writes that can wait in the queue before callers have to hold back
+pub const WRITE_QUEUE_DEPTH: usize = 16;
+
+enum Job {
+    Write { path: PathBuf, data: Vec<u8> },
+    /// Answered once every job queued before it is done
+    Sync(Sender<()>),
+}
+
+pub struct SaveWriter {
+    /// None where no thread could be started
+    jobs: Option<Sender<Job>>,
+    errors: Receiver<io::Error>,
+    error_sender: Sender<io::Error>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Default for SaveWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SaveWriter {
+    pub fn new() -> Self {
+        let (jobs, job_rx) = bounded::<Job>(WRITE_QUEUE_DEPTH);
+        let (error_sender, errors) = unbounded();
+        let thread_errors = error_sender.clone();
+        let spawned = std::thread::Builder::new()
+            .name("save-writer".into())
+            .spawn(move || {
+                for job in job_rx {
+                    match job {
+                        Job::Write { path, data } => {
+                            if let Err(e) = atomic::write_atomic(&path, &data) {
+                                error!("Failed to write {}: {}", path.display(), e);
+                                thread_errors.send(e).ok();
+                            }
+                        }
+                        Job::Sync(done) => {
+                            done.send(()).ok();
+                        }
+                    }
+                }
+            });
+        let (jobs, handle) = match spawned {
+            Ok(handle) => (Some(jobs), Some(handle)),
+            Err(e) => {
+                error!("Failed to start the save thread, saving on the server thread instead: {}", e);
+                (None, None)
+            }
+        };
+        Self { jobs, errors, error_sender, handle }
+    }
+
+    /// Whether a write can be queued without waiting
+    pub fn has_room(&self) -> bool {
+        self.jobs.as_ref().is_none_or(|jobs| jobs.len() < WRITE_QUEUE_DEPTH)
+    }
+
+    /// Writes queued and not yet done
+    pub fn pending(&self) -> usize {
+        self.jobs.as_ref().map_or(0, Sender::len)
+    }
+
+    /// Queues `data` to replace the file at `path`, waiting for room if the queue is full.
+    /// Failures are reported by the next `wait`.
+    pub fn write(&self, path: PathBuf, data: Vec<u8>) {
+        match &self.jobs {
+            Some(jobs) => {
+                if let Err(e) = jobs.send(Job::Write { path, data }) {
+                    error!("Save thread is gone");
+                    let Job::Write { path, data } = e.into_inner() else { return };
+                    self.write_now(path, data);
+                }
+            }
+            None => self.write_now(path, data),
+        }
+    }
+
+    fn write_now(&self, path: PathBuf, data: Vec<u8>) {
+        if let Err(e) = atomic::write_atomic(&path, &data) {
+            error!("Failed to write {}: {}", path.display(), e);
+            self.error_sender.send(e).ok();
+        }
+    }
+
+    /// Waits until every queued write is on disk, returning the first failure since the
+    /// last wait
+    pub fn wait(&self) -> io::Result<()> {
+        if let Some(jobs) = &self.jobs {
+            let (done, finished) = bounded(1);
+            if jobs.send(Job::Sync(done)).is_ok() {
+                finished.recv().ok();
+            }
+        }
+        match self.errors.try_iter().next() {
+            Some(e) => {
+                // Later failures were logged by the thread already
+                self.errors.try_iter().for_each(drop);
+                Err(e)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for SaveWriter {
+    fn drop(&mut self) {
+        // Closing the queue lets the thread finish what is left and exit
+        self.jobs = None;
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                error!("Save thread panicked");
+            }
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Who may join the server and who gets more than Player permission, kept in text files in
+//! the world directory so they can be edited by hand and read again with `/reload`.
+//!
+//! `whitelist.txt` names the players let in while the whitelist is on, one per line, with
+//! an `enabled = true` line turning it on. `ops.txt` holds `<name> <moderator | admin>`
+//! lines. Both skip blank lines and `#` comments, and names are matched ignoring case.
+//!
+//! Players are known only by the name they join with, and nothing checks that a player
+//! owns it: whoever joins as an op gets the op's permission, and whoever joins as a
+//! whitelisted player gets in. The lists keep out players who don't know a name, not ones
+//! who do, so on a server others can reach keep the whitelist short and give out names
+//! with moderator or admin only to players on a network you trust.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::game::command::PermissionLevel;
+use crate::game::save::atomic;
+
+pub const WHITELIST_FILE: &str = "whitelist.txt";
+pub const OPS_FILE: &str = "ops.txt";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccessLists {
+    pub whitelist_enabled: bool,
+    /// Lowercased names to the names as written
+    whitelist: BTreeMap<String, String>,
+    ops: BTreeMap<String, (String, PermissionLevel)>,
+}
+
+fn parse_permission(value: &str) -> Option<PermissionLevel> {
+    match value {
+        "player" => Some(PermissionLevel::Player),
+        "moderator" => Some(PermissionLevel::Moderator),
+        "admin" => Some(PermissionLevel::Admin),
+        _ => None,
+    }
+}
+
+fn permission_name(permission: PermissionLevel) -> &'static str {
+    match permission {
+        PermissionLevel::Player => "player",
+        PermissionLevel::Moderator => "moderator",
+        PermissionLevel::Admin | PermissionLevel::Console => "admin",
+    }
+}
+
+/// The lines of a list file that are not blank or comments, with their line numbers
+fn entries(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines().enumerate()
+        .map(|(number, line)| (number + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+impl AccessLists {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(whitelist: &str, ops: &str) -> Result<Self, String> {
+        let mut lists = Self::new();
+        for (number, line) in entries(whitelist) {
+            match line.split_once('=') {
+                Some((key, value)) if key.trim() == "enabled" => {
+                    lists.whitelist_enabled = value.trim().parse()
+                        .map_err(|_| format!("{} line {}: enabled must be true or false", WHITELIST_FILE, number))?;
+                }
+                Some(_) => return Err(format!("{} line {}: expected a player name", WHITELIST_FILE, number)),
+                None => lists.allow(line),
+            }
+        }
+        for (number, line) in entries(ops) {
+            let (name, permission) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [name] => (name, PermissionLevel::Admin),
+                [name, level] => (name, parse_permission(level)
+                    .ok_or_else(|| format!("{} line {}: unknown permission {}", OPS_FILE, number, level))?),
+                _ => return Err(format!("{} line {}: expected <name> [moderator | admin]", OPS_FILE, number)),
+            };
+            lists.op(name, permission);
+        }
+        Ok(lists)
+    }
+
+    /// Reads the lists in `world_dir`; missing files are empty lists
+    pub fn load(world_dir: &Path) -> Result<Self, String> {
+        let read = |file: &str| match fs::read_to_string(world_dir.join(file)) {
+            Ok(text) => Ok(text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(format!("failed to read {}: {}", file, e)),
+        };
+        Self::parse(&read(WHITELIST_FILE)?, &read(OPS_FILE)?)
+    }
+
+    pub fn save(&self, world_dir: &Path) -> io::Result<()> {
+        atomic::write_atomic(&world_dir.join(WHITELIST_FILE), self.whitelist_text().as_bytes())?;
+        atomic::write_atomic(&world_dir.join(OPS_FILE), self.ops_text().as_bytes())
+    }
+
+    pub fn whitelist_text(&self) -> String {
+        let mut text = format!(
+            "# Players allowed to join while the whitelist is enabled, one name per line\nenabled = {}\n",
+            self.whitelist_enabled,
+        );
+        for name in self.whitelist.values() {
+            text.push_str(name);
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn ops_text(&self) -> String {
+        let mut text = "# Players given more permission when they join: <name> <moderator | admin>\n".to_string();
+        for (name, permission) in self.ops.values() {
+            text.push_str(&format!("{} {}\n", name, permission_name(*permission)));
+        }
+        text
+    }
+
+    /// Whether a player may join; everyone may while the whitelist is off
+    pub fn is_allowed(&self, name: &str) -> bool {
+        !self.whitelist_enabled || self.whitelist.contains_key(&name.to_lowercase())
+    }
+
+    pub fn allow(&mut self, name: &str) {
+        self.whitelist.insert(name.to_lowercase(), name.to_string());
+    }
+
+    /// Returns false if the player was not on the whitelist
+    pub fn disallow(&mut self, name: &str) -> bool {
+        self.whitelist.remove(&name.to_lowercase()).is_some()
+    }
+
+    pub fn whitelisted(&self) -> impl Iterator<Item = &str> {
+        self.whitelist.values().map(String::as_str)
+    }
+
+    /// Permission the ops list gives a player, Player if it does not name them
+    pub fn permission(&self, name: &str) -> PermissionLevel {
+        self.ops.get(&name.to_lowercase()).map_or(PermissionLevel::Player, |(_, permission)| *permission)
+    }
+
+    pub fn op(&mut self, name: &str, permission: PermissionLevel) {
+        if permission == PermissionLevel::Player {
+            self.deop(name);
+        } else {
+            self.ops.insert(name.to_lowercase(), (name.to_string(), permission.min(PermissionLevel::Admin)));
+        }
+    }
+
+    /// Returns false if the player was not an op
+    pub fn deop(&mut self, name: &str) -> bool {
+        self.ops.remove(&name.to_lowercase()).is_some()
+    }
+}
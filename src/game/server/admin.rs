@@ -0,0 +1,252 @@
+//! Built-in administration commands.
+
+use crate::engine::net::DEFAULT_GAME_PORT;
+use crate::game::command::{CommandError, CommandRegistry, CommandSender, CommandSpec, ParsedCommand, PermissionLevel};
+use crate::game::entity::{boat, EntityKind};
+use crate::game::net::protocol::ServerMessage;
+use crate::game::player::Gamemode;
+use crate::game::save::BackupManager;
+use crate::game::server::random;
+use crate::game::server::server::Server;
+use crate::game::server::world_metrics::WorldMetrics;
+use crate::game::world::memory::MemoryUsage;
+
+pub fn register_commands(registry: &mut CommandRegistry) {
+    let specs = [
+        CommandSpec { name: "help", usage: "/help", help: "List the commands you can use", permission: PermissionLevel::Player, min_args: 0 },
+        CommandSpec { name: "list", usage: "/list", help: "List connected players", permission: PermissionLevel::Player, min_args: 0 },
+        CommandSpec { name: "say", usage: "/say <message>", help: "Broadcast a message to everyone", permission: PermissionLevel::Moderator, min_args: 1 },
+        CommandSpec { name: "kick", usage: "/kick <player> [reason]", help: "Disconnect a player", permission: PermissionLevel::Moderator, min_args: 1 },
+        CommandSpec { name: "ban", usage: "/ban <player> [reason]", help: "Disconnect a player and refuse future joins", permission: PermissionLevel::Admin, min_args: 1 },
+        CommandSpec { name: "pardon", usage: "/pardon <player>", help: "Lift a ban", permission: PermissionLevel::Admin, min_args: 1 },
+        CommandSpec { name: "whitelist", usage: "/whitelist <on | off | add | remove | list> [player]", help: "Turn the whitelist on or off, or change who is on it", permission: PermissionLevel::Admin, min_args: 1 },
+        CommandSpec { name: "op", usage: "/op <player> [moderator | admin]", help: "Give a player more permission, admin if not given", permission: PermissionLevel::Admin, min_args: 1 },
+        CommandSpec { name: "deop", usage: "/deop <player>", help: "Take a player's extra permission away", permission: PermissionLevel::Admin, min_args: 1 },
+        CommandSpec { name: "reload", usage: "/reload", help: "Read the whitelist and ops list again", permission: PermissionLevel::Admin, min_args: 0 },
+        CommandSpec { name: "summon", usage: "/summon <mob | boat | tnt>", help: "Spawn an entity in front of you", permission: PermissionLevel::Moderator, min_args: 1 },
+        CommandSpec { name: "weather", usage: "/weather <clear | rain>", help: "Stop or start the rain", permission: PermissionLevel::Moderator, min_args: 1 },
+        CommandSpec { name: "gamerule", usage: "/gamerule <rule> [value]", help: "Show or change a game rule, such as keepInventory", permission: PermissionLevel::Admin, min_args: 1 },
+        CommandSpec { name: "gamemode", usage: "/gamemode <creative | survival> [player]", help: "Change your or another player's gamemode", permission: PermissionLevel::Admin, min_args: 1 },
+        CommandSpec { name: "tps", usage: "/tps", help: "Show server tick timing", permission: PermissionLevel::Player, min_args: 0 },
+        CommandSpec { name: "metrics", usage: "/metrics [json | prometheus]", help: "Show chunk, save and tick figures", permission: PermissionLevel::Admin, min_args: 0 },
+        CommandSpec { name: "memory", usage: "/memory", help: "Show memory used by the server's world data", permission: PermissionLevel::Admin, min_args: 0 },
+        CommandSpec { name: "save-all", usage: "/save-all", help: "Write the world to disk", permission: PermissionLevel::Admin, min_args: 0 },
+        CommandSpec { name: "backup", usage: "/backup [list | <label>]", help: "Save and snapshot the world, or list snapshots", permission: PermissionLevel::Admin, min_args: 0 },
+        CommandSpec { name: "publish", usage: "/publish [port]", help: "Open the world to LAN players", permission: PermissionLevel::Admin, min_args: 0 },
+        CommandSpec { name: "quicksave", usage: "/quicksave [slot]", help: "Snapshot the players, time and edited chunks to a slot, 1 if not given", permission: PermissionLevel::Admin, min_args: 0 },
+        CommandSpec { name: "quickload", usage: "/quickload [slot]", help: "Go back to a quicksave, 1 if no slot is given", permission: PermissionLevel::Admin, min_args: 0 },
+        CommandSpec { name: "stop", usage: "/stop", help: "Save and shut the server down", permission: PermissionLevel::Admin, min_args: 0 },
+    ];
+    for spec in specs {
+        registry.register(spec);
+    }
+}
+
+pub fn execute(server: &mut Server, sender: &CommandSender, command: &ParsedCommand) -> Result<String, CommandError> {
+    match command.name.as_str() {
+        "help" => {
+            let lines: Vec<String> = server.commands.available(sender)
+                .map(|spec| format!("{} - {}", spec.usage, spec.help))
+                .collect();
+            Ok(lines.join("\n"))
+        }
+        "list" => {
+            let mut names: Vec<&str> = server.sessions().map(|(_, s)| s.name.as_str()).collect();
+            names.sort_unstable();
+            Ok(format!("{} player(s) online: {}", names.len(), names.join(", ")))
+        }
+        "say" => {
+            let text = format!("[{}] {}", sender.name, command.rest(0));
+            server.broadcast(ServerMessage::Chat { text: text.clone() });
+            Ok(text)
+        }
+        "kick" | "ban" => {
+            let name = &command.args[0];
+            let reason = match command.rest(1) {
+                r if r.is_empty() => if command.name == "ban" { "Banned".to_string() } else { "Kicked".to_string() },
+                r => r,
+            };
+            if command.name == "ban" {
+                server.ban(name);
+            }
+            match server.find_player(name) {
+                Some(client) => server.disconnect(client, &reason),
+                None if command.name == "kick" => return Err(CommandError::Failed(format!("No player named '{}'", name))),
+                None => (),
+            }
+            Ok(format!("{} {}: {}", if command.name == "ban" { "Banned" } else { "Kicked" }, name, reason))
+        }
+        "pardon" => {
+            let name = &command.args[0];
+            if server.pardon(name) {
+                Ok(format!("Unbanned {}", name))
+            } else {
+                Err(CommandError::Failed(format!("{} is not banned", name)))
+            }
+        }
+        "whitelist" => {
+            const USAGE: &str = "/whitelist <on | off | add | remove | list> [player]";
+            let name = command.args.get(1);
+            let reply = match (command.args[0].as_str(), name) {
+                ("list", _) => {
+                    let names: Vec<&str> = server.access.whitelisted().collect();
+                    let state = if server.access.whitelist_enabled { "on" } else { "off" };
+                    return Ok(format!("The whitelist is {}; {} player(s) on it: {}", state, names.len(), names.join(", ")));
+                }
+                ("on", None) => {
+                    server.access.whitelist_enabled = true;
+                    "Turned the whitelist on".to_string()
+                }
+                ("off", None) => {
+                    server.access.whitelist_enabled = false;
+                    "Turned the whitelist off".to_string()
+                }
+                ("add", Some(name)) => {
+                    server.access.allow(name);
+                    format!("Added {} to the whitelist", name)
+                }
+                ("remove", Some(name)) => {
+                    if !server.access.disallow(name) {
+                        return Err(CommandError::Failed(format!("{} is not on the whitelist", name)));
+                    }
+                    format!("Removed {} from the whitelist", name)
+                }
+                _ => return Err(CommandError::Usage(USAGE.to_string())),
+            };
+            save_access(server)?;
+            Ok(with_refused(reply, server.apply_access()))
+        }
+        "op" => {
+            let name = &command.args[0];
+            let permission = match command.args.get(1).map(String::as_str) {
+                None | Some("admin") => PermissionLevel::Admin,
+                Some("moderator") => PermissionLevel::Moderator,
+                Some(_) => return Err(CommandError::Usage("/op <player> [moderator | admin]".to_string())),
+            };
+            server.access.op(name, permission);
+            save_access(server)?;
+            server.apply_access();
+            Ok(format!("Made {} {:?}", name, permission))
+        }
+        "deop" => {
+            let name = &command.args[0];
+            if !server.access.deop(name) {
+                return Err(CommandError::Failed(format!("{} is not an op", name)));
+            }
+            save_access(server)?;
+            server.apply_access();
+            Ok(format!("{} is no longer an op", name))
+        }
+        "reload" => {
+            let refused = server.reload_access().map_err(|e| CommandError::Failed(format!("Reload failed, {}", e)))?;
+            Ok(with_refused("Reloaded the whitelist and ops list".to_string(), refused))
+        }
+        "publish" => {
+            let port = match command.args.first() {
+                Some(arg) => arg.parse::<u16>().map_err(|_| CommandError::Usage("/publish [port]".to_string()))?,
+                None => DEFAULT_GAME_PORT,
+            };
+            server.request_publish(port);
+            Ok(format!("Opening world to LAN on port {}", port))
+        }
+        "summon" => {
+            let kind = match command.args[0].as_str() {
+                "mob" => EntityKind::Mob,
+                "boat" => EntityKind::Boat,
+                "tnt" => EntityKind::PrimedTnt,
+                _ => return Err(CommandError::Usage("/summon <mob | boat | tnt>".to_string())),
+            };
+            let session = server.find_player(&sender.name).and_then(|client| server.session(client))
+                .ok_or_else(|| CommandError::Failed("Only players can summon".to_string()))?;
+            let position = session.position + boat::heading(session.yaw) * 2.0;
+            let id = server.entities.spawn(kind, position);
+            Ok(format!("Summoned {:?} as {:?}", kind, id))
+        }
+        "weather" => {
+            let raining = match command.args[0].as_str() {
+                "clear" => false,
+                "rain" => true,
+                _ => return Err(CommandError::Usage("/weather <clear | rain>".to_string())),
+            };
+            server.weather.set(raining, server.random.stream(random::WEATHER));
+            server.broadcast(ServerMessage::Weather { raining });
+            Ok(if raining { "It starts to rain".to_string() } else { "The rain stops".to_string() })
+        }
+        "gamerule" => {
+            let name = &command.args[0];
+            match command.args.get(1) {
+                Some(value) => {
+                    server.rules.set(name, value).map_err(CommandError::Failed)?;
+                    Ok(format!("Game rule {} is now {}", name, value))
+                }
+                None => server.rules.get(name)
+                    .map(|value| format!("Game rule {} is {}", name, value))
+                    .ok_or_else(|| CommandError::Failed(format!("No game rule called {}", name))),
+            }
+        }
+        "gamemode" => {
+            let gamemode = Gamemode::from_name(&command.args[0])
+                .ok_or_else(|| CommandError::Usage("/gamemode <creative | survival> [player]".to_string()))?;
+            let name = command.args.get(1).unwrap_or(&sender.name);
+            let client = server.find_player(name)
+                .ok_or_else(|| CommandError::Failed(format!("No player named '{}'", name)))?;
+            server.set_gamemode(client, gamemode);
+            Ok(format!("Set {}'s gamemode to {}", name, command.args[0]))
+        }
+        "tps" => Ok(server.metrics.summary()),
+        "metrics" => {
+            // Generation throughput needs two samples apart in time; --metrics dumps have it
+            let metrics = WorldMetrics::sample(server);
+            match command.args.first().map(String::as_str) {
+                None | Some("json") => Ok(metrics.to_json().trim_end().to_string()),
+                Some("prometheus") => Ok(metrics.to_prometheus().trim_end().to_string()),
+                _ => Err(CommandError::Usage("/metrics [json | prometheus]".to_string())),
+            }
+        }
+        "memory" => Ok(MemoryUsage::measure(&server.chunks).to_string()),
+        "save-all" => {
+            server.save_all().map_err(|e| CommandError::Failed(format!("Save failed: {}", e)))?;
+            Ok("World saved".to_string())
+        }
+        "backup" => {
+            let backups = BackupManager::new(&server.world_save.root);
+            if command.args.first().is_some_and(|a| a == "list") {
+                let names = backups.list().map_err(|e| CommandError::Failed(format!("Listing backups failed: {}", e)))?;
+                return Ok(format!("{} backup(s): {}", names.len(), names.join(", ")));
+            }
+            server.save_all().map_err(|e| CommandError::Failed(format!("Save failed: {}", e)))?;
+            let label = Some(command.rest(0)).filter(|l| !l.is_empty());
+            let backup = backups.create(label.as_deref())
+                .map_err(|e| CommandError::Failed(format!("Backup failed: {}", e)))?;
+            Ok(format!("Backed up {} files as {}", backup.files, backup.name))
+        }
+        "quicksave" | "quickload" => {
+            let slot = match command.args.first() {
+                Some(arg) => arg.parse::<u8>().map_err(|_| CommandError::Usage(format!("/{} [slot]", command.name)))?,
+                None => 1,
+            };
+            if command.name == "quicksave" {
+                server.quicksave(slot).map_err(CommandError::Failed)?;
+                Ok(format!("Quicksaved to slot {}", slot))
+            } else {
+                server.quickload(slot).map_err(CommandError::Failed)?;
+                Ok(format!("Loaded quicksave slot {}", slot))
+            }
+        }
+        "stop" => {
+            server.save_all().map_err(|e| CommandError::Failed(format!("Save failed: {}", e)))?;
+            server.stop();
+            Ok("Stopping server".to_string())
+        }
+        other => Err(CommandError::Unknown(other.to_string())),
+    }
+}
+
+fn save_access(server: &Server) -> Result<(), CommandError> {
+    server.save_access().map_err(|e| CommandError::Failed(format!("Failed to write the whitelist and ops list: {}", e)))
+}
+
+/// Mentions the players a whitelist change disconnected
+fn with_refused(reply: String, refused: usize) -> String {
+    if refused == 0 { reply } else { format!("{}; disconnected {} player(s) not on it", reply, refused) }
+}
@@ -0,0 +1,39 @@
+//! Stdin console for the headless server, also used by the client's terminal.
+
+use std::io::BufRead;
+use crossbeam_channel::{unbounded, Receiver};
+use log::warn;
+
+/// Reads stdin lines on a background thread so the server loop never blocks on input
+pub struct StdinConsole {
+    rx: Receiver<String>,
+}
+
+impl StdinConsole {
+    pub fn spawn() -> Self {
+        let (tx, rx) = unbounded();
+        std::thread::Builder::new()
+            .name("stdin-console".into())
+            .spawn(move || {
+                for line in std::io::stdin().lock().lines() {
+                    match line {
+                        Ok(line) => {
+                            if tx.send(line).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            warn!("Console read failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+            })
+            .expect("Failed to spawn console thread");
+        Self { rx }
+    }
+
+    pub fn poll(&self) -> Vec<String> {
+        self.rx.try_iter().filter(|line| !line.trim().is_empty()).collect()
+    }
+}
@@ -0,0 +1,146 @@
+//! Integrated server for singleplayer.
+//!
+//! Singleplayer runs the same authoritative Server as a dedicated host, on its own thread,
+//! and the local client talks to it over the loopback transport.
+
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use log::{error, info};
+
+use crate::engine::net::{Connection, LoopbackTransport, Transport};
+use crate::game::command::PermissionLevel;
+use crate::game::save::WorldSave;
+use crate::game::server::network::ServerNetwork;
+use crate::game::server::scheduler::TickClock;
+use crate::game::server::server::Server;
+use crate::game::world::worldgen::WorldGen;
+
+const LOOPBACK_ADDR: &str = "integrated";
+
+enum Control {
+    OpenToLan(u16),
+    Pause(bool),
+    TimeScale(f32),
+    Save,
+    Stop,
+}
+
+pub struct IntegratedServer {
+    control: Sender<Control>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl IntegratedServer {
+    /// Starts the server thread and returns it with the host's client connection. A new
+    /// world is created with `generator`.
+    pub fn start(world_dir: impl Into<PathBuf>, generator: WorldGen) -> std::io::Result<(Self, Box<dyn Connection>)> {
+        let transport = LoopbackTransport::new();
+        let listener = transport.listen(LOOPBACK_ADDR)?;
+        let connection = transport.connect(LOOPBACK_ADDR)?;
+        let world_dir = world_dir.into();
+        let (control, control_rx) = unbounded();
+
+        let handle = std::thread::Builder::new()
+            .name("integrated-server".into())
+            .spawn(move || {
+                let mut server = Server::create(WorldSave::open(world_dir), generator);
+                let mut network = ServerNetwork::new();
+                // The host owns the world, so it gets full permissions
+                network.add_listener(listener, PermissionLevel::Admin);
+                let ran = std::panic::catch_unwind(AssertUnwindSafe(|| run(&mut server, &mut network, &control_rx)));
+                if ran.is_err() {
+                    // The world may be mid-update, but that beats losing everything since
+                    // the last save
+                    error!("Integrated server crashed, saving what it can");
+                    if let Err(e) = server.save_all() {
+                        error!("Emergency save failed: {}", e);
+                    }
+                    network.close_all();
+                }
+            })?;
+        info!("Integrated server started");
+        Ok((Self { control, handle: Some(handle) }, connection))
+    }
+
+    /// Lets other machines on the LAN join this world
+    pub fn open_to_lan(&self, port: u16) {
+        self.control.send(Control::OpenToLan(port)).ok();
+    }
+
+    /// Stops the world ticking while `paused`, e.g. for photo mode. It keeps going while
+    /// anyone else has joined over the LAN.
+    pub fn set_paused(&self, paused: bool) {
+        self.control.send(Control::Pause(paused)).ok();
+    }
+
+    /// Runs the world `scale` times as fast as normal, to match the client's clock
+    pub fn set_time_scale(&self, scale: f32) {
+        self.control.send(Control::TimeScale(scale)).ok();
+    }
+
+    /// Saves the world and players without stopping
+    pub fn save(&self) {
+        self.control.send(Control::Save).ok();
+    }
+
+    /// Saves and stops the server, waiting for its thread to finish
+    pub fn shutdown(&mut self) {
+        self.control.send(Control::Stop).ok();
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                error!("Integrated server thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for IntegratedServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn run(server: &mut Server, network: &mut ServerNetwork, control: &Receiver<Control>) {
+    let mut clock = TickClock::default();
+    let mut paused = false;
+    while server.is_running() {
+        for message in control.try_iter() {
+            match message {
+                Control::OpenToLan(port) => server.request_publish(port),
+                Control::Pause(pause) => paused = pause,
+                Control::TimeScale(scale) => clock.set_scale(scale),
+                Control::Save => match server.save_all() {
+                    Ok(()) => info!("World saved"),
+                    Err(e) => error!("Failed to save world: {}", e),
+                },
+                Control::Stop => server.stop(),
+            }
+        }
+        if let Some(port) = server.take_publish_request() {
+            match network.open_to_lan("Singleplayer world", port) {
+                Ok(()) => info!("World opened to LAN on port {}", port),
+                Err(e) => error!("Failed to open world to LAN: {}", e),
+            }
+        }
+
+        network.poll(server);
+        let (due, skipped) = clock.due();
+        // Ticks that come due while paused are dropped, so there is nothing to catch up on
+        if !paused || server.sessions().count() > 1 {
+            server.metrics.record_skipped(skipped);
+            for _ in 0..due {
+                server.tick();
+            }
+        }
+        network.flush(server);
+
+        std::thread::sleep(clock.until_next_tick());
+    }
+    if let Err(e) = server.save_all() {
+        error!("Failed to save world on shutdown: {}", e);
+    }
+    network.flush(server);
+    network.close_all();
+}
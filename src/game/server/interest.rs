@@ -0,0 +1,180 @@
+//! Interest management: decides what each client gets told about.
+//!
+//! A client only receives entities and block updates within its view distance of chunks,
+//! using the same cube-shaped range as ChunkManager. Entities crossing the boundary produce
+//! EntityEnter/EntityLeave messages so the client can create or drop its copies; the state
+//! of everything in range then goes out as delta-compressed snapshots.
+
+use std::collections::{HashMap, HashSet};
+use glam::Vec3;
+
+use crate::engine::math::Aabb;
+use crate::game::entity::{EntityId, EntityManager};
+use crate::game::net::protocol::{ClientId, ServerMessage};
+use crate::game::net::snapshot::{EntityState, SnapshotSender};
+use crate::game::world::chunk::{BlockType, CHUNK_SIZE_F};
+use crate::game::world::chunk_manager::ChunkManager;
+
+pub struct ClientInterest {
+    pub center: (i32, i32, i32),
+    pub view_distance: i32,
+    visible: HashSet<EntityId>,
+    snapshots: SnapshotSender,
+    /// Center and view distance the client was last caught up on block edits for, or None
+    /// before the first time
+    edits_synced: Option<((i32, i32, i32), i32)>,
+}
+
+/// Whether `chunk_key` is within `view_distance` chunks of `center` on every axis
+fn within(chunk_key: (i32, i32, i32), center: (i32, i32, i32), view_distance: i32) -> bool {
+    (chunk_key.0 - center.0).abs() <= view_distance &&
+    (chunk_key.1 - center.1).abs() <= view_distance &&
+    (chunk_key.2 - center.2).abs() <= view_distance
+}
+
+impl ClientInterest {
+    pub fn in_range(&self, chunk_key: (i32, i32, i32)) -> bool {
+        within(chunk_key, self.center, self.view_distance)
+    }
+
+    pub fn visible_entities(&self) -> impl Iterator<Item = &EntityId> {
+        self.visible.iter()
+    }
+
+    fn bounds(&self) -> Aabb {
+        let min = Vec3::new(
+            (self.center.0 - self.view_distance) as f32,
+            (self.center.1 - self.view_distance) as f32,
+            (self.center.2 - self.view_distance) as f32,
+        ) * CHUNK_SIZE_F;
+        let max = Vec3::new(
+            (self.center.0 + self.view_distance + 1) as f32,
+            (self.center.1 + self.view_distance + 1) as f32,
+            (self.center.2 + self.view_distance + 1) as f32,
+        ) * CHUNK_SIZE_F;
+        // Block n spans [n - 0.5, n + 0.5), so chunk borders sit half a block back
+        Aabb::new(min - Vec3::splat(0.5), max - Vec3::splat(0.5))
+    }
+}
+
+#[derive(Default)]
+pub struct InterestManager {
+    clients: HashMap<ClientId, ClientInterest>,
+}
+
+impl InterestManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_client(&mut self, client: ClientId, position: Vec3, view_distance: i32) {
+        self.clients.insert(client, ClientInterest {
+            center: ChunkManager::chunk_key_at(position),
+            view_distance,
+            visible: HashSet::new(),
+            snapshots: SnapshotSender::new(),
+            edits_synced: None,
+        });
+    }
+
+    pub fn remove_client(&mut self, client: ClientId) {
+        self.clients.remove(&client);
+    }
+
+    pub fn client(&self, client: ClientId) -> Option<&ClientInterest> {
+        self.clients.get(&client)
+    }
+
+    pub fn set_position(&mut self, client: ClientId, position: Vec3) {
+        if let Some(interest) = self.clients.get_mut(&client) {
+            interest.center = ChunkManager::chunk_key_at(position);
+        }
+    }
+
+    pub fn set_view_distance(&mut self, client: ClientId, view_distance: i32) {
+        if let Some(interest) = self.clients.get_mut(&client) {
+            interest.view_distance = view_distance;
+        }
+    }
+
+    /// The client has applied every snapshot up to `sequence`
+    pub fn ack_snapshot(&mut self, client: ClientId, sequence: u32) {
+        if let Some(interest) = self.clients.get_mut(&client) {
+            interest.snapshots.ack(sequence);
+        }
+    }
+
+    /// Builds the entity messages for one client: enter/leave for boundary crossings and
+    /// a snapshot of whatever changed among the entities in range
+    pub fn entity_messages(&mut self, client: ClientId, entities: &EntityManager) -> Vec<ServerMessage> {
+        let Some(interest) = self.clients.get_mut(&client) else {
+            return Vec::new();
+        };
+        let now_visible: HashSet<EntityId> = entities.query_aabb(&interest.bounds())
+            .into_iter()
+            .filter(|id| entities.get(*id).is_some_and(|e| interest.in_range(ChunkManager::chunk_key_at(e.position))))
+            .collect();
+
+        let mut messages = Vec::new();
+        for id in interest.visible.difference(&now_visible) {
+            messages.push(ServerMessage::EntityLeave { id: *id });
+        }
+        let mut states = HashMap::new();
+        for id in &now_visible {
+            let Some(entity) = entities.get(*id) else { continue };
+            if !interest.visible.contains(id) {
+                messages.push(ServerMessage::EntityEnter {
+                    id: *id,
+                    kind: entity.kind,
+                    position: entity.position,
+                    velocity: entity.velocity,
+                });
+            }
+            states.insert(*id, EntityState::quantize(entity));
+        }
+        interest.visible = now_visible;
+        if let Some(snapshot) = interest.snapshots.build(states) {
+            messages.push(ServerMessage::Snapshot(snapshot));
+        }
+        messages
+    }
+
+    /// Those of `chunks` that came within the client's range since the last call, or all of
+    /// them in range on the first call
+    pub fn entered_chunks<'a>(&mut self, client: ClientId, chunks: impl Iterator<Item = &'a (i32, i32, i32)>) -> Vec<(i32, i32, i32)> {
+        let Some(interest) = self.clients.get_mut(&client) else {
+            return Vec::new();
+        };
+        let now = (interest.center, interest.view_distance);
+        let before = interest.edits_synced.replace(now);
+        if before == Some(now) {
+            return Vec::new();
+        }
+        chunks
+            .filter(|&&key| interest.in_range(key) && before.is_none_or(|(center, distance)| !within(key, center, distance)))
+            .copied()
+            .collect()
+    }
+
+    /// Whether any client has the chunk in range
+    pub fn is_watched(&self, chunk_key: (i32, i32, i32)) -> bool {
+        self.clients.values().any(|interest| interest.in_range(chunk_key))
+    }
+
+    /// Clients that should be told about a block change
+    pub fn clients_for_block(&self, block: (i32, i32, i32)) -> Vec<ClientId> {
+        let chunk_key = ChunkManager::chunk_key(block);
+        self.clients.iter()
+            .filter(|(_, interest)| interest.in_range(chunk_key))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Fans a block change out to the interested clients
+    pub fn block_update(&self, block: (i32, i32, i32), block_type: BlockType) -> Vec<(ClientId, ServerMessage)> {
+        self.clients_for_block(block)
+            .into_iter()
+            .map(|client| (client, ServerMessage::BlockUpdate { block, block_type }))
+            .collect()
+    }
+}
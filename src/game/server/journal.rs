@@ -0,0 +1,134 @@
+//! The blocks changed in each chunk, for catching up clients that weren't watching.
+//!
+//! Clients generate terrain themselves from the world generator, and only hear of block
+//! changes made while the chunk is within their range. Whoever joins late, reconnects, or
+//! walks into a chunk edited while they were away would see it as it was generated. The
+//! journal keeps the latest block at every edited position, per chunk, and when a chunk
+//! comes within a client's range the client is sent a hash of its edits, then the edits
+//! themselves in one small message if it doesn't have them cached.
+//!
+//! Each save writes the edits of every chunk into its region record, and the chunks then
+//! out of everyone's reach are let go of, keeping only their keys. Their edits are read
+//! back from the save when the server loads the chunk or a client comes within range, and
+//! put back into chunks the server loads, generated or not, so edited terrain survives
+//! both unloading and restarts.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+use crate::game::net::protocol::{chunk_edits_hash, ChunkEdit};
+use crate::game::world::chunk::{BlockType, CHUNK_SIZE};
+use crate::game::world::chunk_manager::ChunkManager;
+
+type ChunkKey = (i32, i32, i32);
+type BlockPos = (i32, i32, i32);
+
+#[derive(Debug, Default)]
+pub struct BlockJournal {
+    chunks: HashMap<ChunkKey, HashMap<BlockPos, BlockType>>,
+    /// Edited chunks whose edits are only in the save, see `release`
+    saved: HashSet<ChunkKey>,
+    edits: usize,
+}
+
+impl BlockJournal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A journal of a world whose `chunks` have edits in the save, to be `restore`d
+    pub fn with_saved(chunks: impl IntoIterator<Item = ChunkKey>) -> Self {
+        Self { saved: chunks.into_iter().collect(), ..Self::default() }
+    }
+
+    /// Notes that `block` is now `block_type`, replacing any earlier edit there. Restore
+    /// its chunk first if it was released, or the saved edits would be overwritten.
+    pub fn record(&mut self, block: BlockPos, block_type: BlockType) {
+        let key = ChunkManager::chunk_key(block);
+        self.saved.remove(&key);
+        if self.chunks.entry(key).or_default().insert(block, block_type).is_none() {
+            self.edits += 1;
+        }
+    }
+
+    /// Whether the edits of a chunk are here rather than only in the save
+    pub fn holds(&self, chunk: ChunkKey) -> bool {
+        self.chunks.contains_key(&chunk)
+    }
+
+    /// Takes back the edits of a chunk as read from the save. Edits recorded since win.
+    pub fn restore(&mut self, chunk: ChunkKey, edits: &[ChunkEdit]) {
+        self.saved.remove(&chunk);
+        if edits.is_empty() {
+            return;
+        }
+        let size = CHUNK_SIZE as i32;
+        let blocks = self.chunks.entry(chunk).or_default();
+        for &((x, y, z), block_type) in edits {
+            let block = (chunk.0 * size + x as i32, chunk.1 * size + y as i32, chunk.2 * size + z as i32);
+            if let Entry::Vacant(entry) = blocks.entry(block) {
+                entry.insert(block_type);
+                self.edits += 1;
+            }
+        }
+    }
+
+    /// Lets go of the edits of the chunks `keep` turns down, once they have been saved.
+    /// They still count among `chunks`, and must be restored before they are used.
+    pub fn release(&mut self, keep: impl Fn(&ChunkKey) -> bool) {
+        let released: Vec<ChunkKey> = self.chunks.keys().filter(|key| !keep(key)).copied().collect();
+        for key in released {
+            if let Some(blocks) = self.chunks.remove(&key) {
+                self.edits -= blocks.len();
+                self.saved.insert(key);
+            }
+        }
+    }
+
+    /// Chunks with edits in the save that have not been restored
+    pub fn released(&self) -> impl Iterator<Item = &ChunkKey> {
+        self.saved.iter()
+    }
+
+    /// The latest block at every edited position of a chunk, in no particular order
+    pub fn edits(&self, chunk: ChunkKey) -> impl Iterator<Item = (BlockPos, BlockType)> + '_ {
+        self.chunks.get(&chunk).into_iter().flatten().map(|(&block, &block_type)| (block, block_type))
+    }
+
+    /// The edits of a chunk by position within it, sorted, as sent to clients
+    pub fn local_edits(&self, chunk: ChunkKey) -> Vec<ChunkEdit> {
+        let size = CHUNK_SIZE as i32;
+        let mut edits: Vec<ChunkEdit> = self.edits(chunk)
+            .map(|(block, block_type)| {
+                let local = (block.0.rem_euclid(size) as u8, block.1.rem_euclid(size) as u8, block.2.rem_euclid(size) as u8);
+                (local, block_type)
+            })
+            .collect();
+        edits.sort_by_key(|&(local, _)| local);
+        edits
+    }
+
+    /// chunk_edits_hash of a chunk's local_edits
+    pub fn hash(&self, chunk: ChunkKey) -> u64 {
+        chunk_edits_hash(&self.local_edits(chunk))
+    }
+
+    /// Chunks with at least one edit, held or released
+    pub fn chunks(&self) -> impl Iterator<Item = &ChunkKey> {
+        self.chunks.keys().chain(&self.saved)
+    }
+
+    /// Chunks whose edits are held
+    pub fn held(&self) -> impl Iterator<Item = &ChunkKey> {
+        self.chunks.keys()
+    }
+
+    /// Edited positions across the chunks held
+    pub fn len(&self) -> usize {
+        self.edits
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits == 0
+    }
+}
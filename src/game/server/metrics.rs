@@ -0,0 +1,89 @@
+//! Tick timing metrics.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::game::server::scheduler::TICK_RATE;
+
+/// Number of recent ticks averaged for the reported figures
+pub const METRICS_WINDOW: usize = 100;
+
+/// Time spent in each phase of one tick
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PhaseTimes {
+    pub chunks: Duration,
+    pub block_ticks: Duration,
+    pub entities: Duration,
+    pub replication: Duration,
+}
+
+impl PhaseTimes {
+    pub fn total(&self) -> Duration {
+        self.chunks + self.block_ticks + self.entities + self.replication
+    }
+}
+
+#[derive(Default)]
+pub struct TickMetrics {
+    pub ticks: u64,
+    pub skipped_ticks: u64,
+    pub last: PhaseTimes,
+    recent: VecDeque<PhaseTimes>,
+}
+
+fn ms(d: Duration) -> f32 {
+    d.as_secs_f32() * 1000.0
+}
+
+impl TickMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, phases: PhaseTimes) {
+        self.ticks += 1;
+        self.last = phases;
+        self.recent.push_back(phases);
+        if self.recent.len() > METRICS_WINDOW {
+            self.recent.pop_front();
+        }
+    }
+
+    pub fn record_skipped(&mut self, count: u32) {
+        self.skipped_ticks += count as u64;
+    }
+
+    fn average(&self, phase: impl Fn(&PhaseTimes) -> Duration) -> f32 {
+        if self.recent.is_empty() {
+            return 0.0;
+        }
+        self.recent.iter().map(|p| ms(phase(p))).sum::<f32>() / self.recent.len() as f32
+    }
+
+    /// Average milliseconds per tick over the recent window
+    pub fn mspt(&self) -> f32 {
+        self.average(PhaseTimes::total)
+    }
+
+    /// Slowest tick in the recent window, in milliseconds
+    pub fn max_mspt(&self) -> f32 {
+        self.recent.iter().map(|p| ms(p.total())).fold(0.0, f32::max)
+    }
+
+    /// Ticks per second the server could sustain at the recent cost, capped at the target
+    pub fn tps(&self) -> f32 {
+        let budget = 1000.0 / TICK_RATE as f32;
+        let mspt = self.mspt();
+        if mspt <= budget { TICK_RATE as f32 } else { 1000.0 / mspt }
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "TPS {:.1}/{} | {:.2} ms/tick avg, {:.2} max | chunks {:.2}, blocks {:.2}, entities {:.2}, replication {:.2} | {} ticks, {} skipped",
+            self.tps(), TICK_RATE, self.mspt(), self.max_mspt(),
+            self.average(|p| p.chunks), self.average(|p| p.block_ticks),
+            self.average(|p| p.entities), self.average(|p| p.replication),
+            self.ticks, self.skipped_ticks,
+        )
+    }
+}
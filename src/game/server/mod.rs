@@ -0,0 +1,32 @@
+//! Authoritative server-side systems.
+
+pub mod access;
+pub mod admin;
+pub mod console;
+pub mod integrated;
+pub mod interest;
+pub mod journal;
+pub mod metrics;
+pub mod movement;
+pub mod network;
+pub mod pregenerate;
+pub mod quicksave;
+pub mod random;
+pub mod rcon;
+pub mod scheduler;
+#[allow(clippy::module_inception)]
+pub mod server;
+pub mod world_metrics;
+
+pub use access::AccessLists;
+pub use console::StdinConsole;
+pub use integrated::IntegratedServer;
+pub use interest::InterestManager;
+pub use journal::BlockJournal;
+pub use metrics::TickMetrics;
+pub use movement::{MovementValidator, MoveVerdict};
+pub use network::ServerNetwork;
+pub use rcon::RconServer;
+pub use scheduler::{BlockTickScheduler, TickClock, TICK_DELTA, TICK_RATE};
+pub use server::Server;
+pub use world_metrics::{MetricsDump, WorldMetrics};
@@ -0,0 +1,79 @@
+//! Server-side validation of client movement.
+//!
+//! Clients report their own positions, so every Move is checked against a speed budget
+//! and against the terrain before the server accepts it. Rejected moves are answered
+//! with the last accepted position, which rubber-bands the client back.
+
+use glam::Vec3;
+
+use crate::game::world::chunk_manager::ChunkManager;
+use crate::game::world::raycast;
+
+/// Fastest legitimate movement in blocks per second. The free camera moves at about half
+/// of this, leaving room for frame-rate variation.
+pub const MAX_MOVE_SPEED: f32 = 12.0;
+/// Extra distance a client may bank, to absorb packets that arrive bunched together
+pub const MOVE_BURST_ALLOWANCE: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveVerdict {
+    Accept,
+    /// Non-finite coordinates
+    Invalid,
+    TooFast { distance: f32, allowed: f32 },
+    /// The path from the last accepted position passes through a solid block
+    Blocked { block: (i32, i32, i32) },
+}
+
+#[derive(Debug, Clone)]
+pub struct MovementValidator {
+    allowance: f32,
+}
+
+impl Default for MovementValidator {
+    fn default() -> Self {
+        Self { allowance: MOVE_BURST_ALLOWANCE }
+    }
+}
+
+impl MovementValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grants the movement budget for `delta_time` seconds of server time
+    pub fn tick(&mut self, delta_time: f32) {
+        self.allowance = (self.allowance + MAX_MOVE_SPEED * delta_time).min(MOVE_BURST_ALLOWANCE + MAX_MOVE_SPEED);
+    }
+
+    /// Checks a move and spends budget if it is accepted
+    pub fn check(&mut self, chunks: &ChunkManager, from: Vec3, to: Vec3) -> MoveVerdict {
+        if !to.is_finite() {
+            return MoveVerdict::Invalid;
+        }
+        let distance = from.distance(to);
+        if distance > self.allowance {
+            return MoveVerdict::TooFast { distance, allowed: self.allowance };
+        }
+        if let Some(block) = Self::blocking_block(chunks, from, to) {
+            return MoveVerdict::Blocked { block };
+        }
+        self.allowance -= distance;
+        MoveVerdict::Accept
+    }
+
+    /// First solid block on the segment. A player already inside terrain (e.g. after it
+    /// loaded around them) may move freely so they can get out. Unloaded chunks count as
+    /// open, since the server only keeps terrain near players loaded.
+    fn blocking_block(chunks: &ChunkManager, from: Vec3, to: Vec3) -> Option<(i32, i32, i32)> {
+        let start = ChunkManager::block_coords(from);
+        if chunks.get_block(start.0, start.1, start.2).is_some_and(|b| b.is_solid()) {
+            return None;
+        }
+        let distance = from.distance(to);
+        if distance <= f32::EPSILON {
+            return None;
+        }
+        raycast::raycast_blocks(chunks, from, to - from, distance).map(|hit| hit.block)
+    }
+}
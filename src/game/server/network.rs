@@ -0,0 +1,172 @@
+//! Server connection handling over any transport.
+
+use std::collections::HashMap;
+use std::io;
+use std::time::Duration;
+use log::{info, warn};
+
+use crate::engine::net::{Connection, LanAnnouncer, Listener, TcpTransport, Transport};
+use crate::engine::time::Instant;
+use crate::game::command::PermissionLevel;
+use crate::game::net::protocol::{ClientId, ClientMessage, ServerMessage};
+use crate::game::server::server::Server;
+
+/// How long a new connection has to send Hello before it is dropped
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest name a player may join with
+pub const MAX_NAME_LENGTH: usize = 16;
+
+/// Why a player can't join with `name`, if they can't. Names are ASCII letters, digits,
+/// `_` and `-`, so two can't pass for one another once case is ignored, as bans, the
+/// access lists and player ids do, and none can hide control characters in logs or chat.
+/// Nothing proves a player owns the name they give; see AccessLists for what that means.
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("A name is needed to join".to_string());
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!("Names can be at most {} characters long", MAX_NAME_LENGTH));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err("Names can only have letters, digits, _ and -".to_string());
+    }
+    Ok(())
+}
+
+struct ListenerEntry {
+    listener: Box<dyn Listener>,
+    /// Permission given to players who join through this listener
+    permission: PermissionLevel,
+}
+
+struct PendingConnection {
+    connection: Box<dyn Connection>,
+    permission: PermissionLevel,
+    accepted: Instant,
+}
+
+#[derive(Default)]
+pub struct ServerNetwork {
+    listeners: Vec<ListenerEntry>,
+    pending: Vec<PendingConnection>,
+    clients: HashMap<ClientId, Box<dyn Connection>>,
+    announcer: Option<LanAnnouncer>,
+}
+
+impl ServerNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_listener(&mut self, listener: Box<dyn Listener>, permission: PermissionLevel) {
+        info!("Listening on {}", listener.local_addr());
+        self.listeners.push(ListenerEntry { listener, permission });
+    }
+
+    /// Starts accepting TCP players on `port` and announces the server on the LAN
+    pub fn open_to_lan(&mut self, name: &str, port: u16) -> io::Result<()> {
+        let listener = TcpTransport.listen(&format!("0.0.0.0:{}", port))?;
+        self.add_listener(listener, PermissionLevel::Player);
+        self.announcer = Some(LanAnnouncer::new(name, port)?);
+        Ok(())
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Accepts connections, completes handshakes, and feeds client messages to the server
+    pub fn poll(&mut self, server: &mut Server) {
+        if let Some(announcer) = &mut self.announcer {
+            announcer.tick();
+        }
+        for entry in &mut self.listeners {
+            loop {
+                match entry.listener.accept() {
+                    Ok(Some(connection)) => {
+                        self.pending.push(PendingConnection { connection, permission: entry.permission, accepted: Instant::now() });
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!("Accept failed on {}: {}", entry.listener.local_addr(), e);
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut still_pending = Vec::new();
+        for mut pending in self.pending.drain(..) {
+            match pending.connection.recv() {
+                Ok(Some(packet)) => match ClientMessage::from_bytes(&packet) {
+                    Ok(ClientMessage::Hello { name }) => match check_name(&name).and_then(|()| server.connect(&name, pending.permission)) {
+                        Ok(client) => {
+                            self.clients.insert(client, pending.connection);
+                        }
+                        Err(reason) => {
+                            let _ = pending.connection.send(&ServerMessage::Disconnect { reason }.to_bytes());
+                            pending.connection.close();
+                        }
+                    },
+                    _ => {
+                        warn!("{} did not start with Hello, dropping", pending.connection.peer());
+                        pending.connection.close();
+                    }
+                },
+                Ok(None) if pending.accepted.elapsed() > HANDSHAKE_TIMEOUT => {
+                    warn!("{} sent no Hello in {:?}, dropping", pending.connection.peer(), HANDSHAKE_TIMEOUT);
+                    pending.connection.close();
+                }
+                Ok(None) if pending.connection.is_open() => still_pending.push(pending),
+                Ok(None) => (),
+                Err(e) => warn!("Handshake with {} failed: {}", pending.connection.peer(), e),
+            }
+        }
+        self.pending = still_pending;
+
+        let mut closed = Vec::new();
+        for (client, connection) in &mut self.clients {
+            loop {
+                match connection.recv() {
+                    Ok(Some(packet)) => match ClientMessage::from_bytes(&packet) {
+                        Ok(message) => server.handle_message(*client, message),
+                        Err(e) => warn!("Bad packet from {}: {}", connection.peer(), e),
+                    },
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!("Connection to {} failed: {}", connection.peer(), e);
+                        break;
+                    }
+                }
+            }
+            if !connection.is_open() {
+                closed.push(*client);
+            }
+        }
+        for client in closed {
+            self.clients.remove(&client);
+            server.disconnect(client, "Connection closed");
+        }
+    }
+
+    /// Sends everything the server queued and closes connections it disconnected
+    pub fn flush(&mut self, server: &mut Server) {
+        for (client, message) in server.drain_outbox() {
+            let Some(connection) = self.clients.get_mut(&client) else { continue };
+            if let Err(e) = connection.send(&message.to_bytes()) {
+                warn!("Send to {} failed: {}", connection.peer(), e);
+            }
+            if matches!(message, ServerMessage::Disconnect { .. }) {
+                connection.close();
+                self.clients.remove(&client);
+            }
+        }
+    }
+
+    pub fn close_all(&mut self) {
+        for connection in self.clients.values_mut() {
+            connection.close();
+        }
+        self.clients.clear();
+    }
+}
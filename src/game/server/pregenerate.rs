@@ -0,0 +1,116 @@
+//! Generating and saving the chunks around a point ahead of time.
+//!
+//! A server can bake its spawn area before anyone joins, and benchmarks can time generation
+//! on its own, apart from streaming chunks to players. Chunks are generated on a pool of
+//! threads and saved whole into the region files as they come in. Chunks that come out all
+//! air are not saved, since generating them again costs next to nothing.
+
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use crossbeam_channel::unbounded;
+use glam::Vec3;
+
+use crate::engine::time::Instant;
+use crate::game::save::WorldSave;
+use crate::game::world::chunk::CHUNK_SIZE_F;
+use crate::game::world::chunk_manager::MIN_CHUNK_Y;
+use crate::game::world::worldgen::WorldGen;
+
+type ChunkKey = (i32, i32, i32);
+
+/// Layers of chunks pregenerated above the lowest one, which covers all the terrain the
+/// generators make
+pub const PREGENERATE_LAYERS: i32 = 4;
+/// Largest radius, in chunks, that can be pregenerated in one go
+pub const MAX_PREGENERATE_RADIUS: i32 = 256;
+/// Chunks generated between queueing finished regions for writing
+const FLUSH_INTERVAL: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PregenProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl PregenProgress {
+    pub fn percent(&self) -> u32 {
+        (self.done * 100).checked_div(self.total).map_or(100, |percent| percent as u32)
+    }
+}
+
+/// What a pregeneration did and how long it took
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PregenReport {
+    pub generated: usize,
+    /// Chunks with something in them, which were saved
+    pub saved: usize,
+    /// Time spent generating, summed over the threads
+    pub generate_time: Duration,
+    /// Time from start to everything being on disk
+    pub elapsed: Duration,
+}
+
+/// Chunks of the columns within `radius` chunks of the column `center`, nearest first,
+/// from the lowest layer up
+pub fn area_chunks(center: (i32, i32), radius: i32) -> Vec<ChunkKey> {
+    let radius = radius.clamp(0, MAX_PREGENERATE_RADIUS);
+    let mut columns = Vec::new();
+    for dx in -radius..=radius {
+        for dz in -radius..=radius {
+            if dx * dx + dz * dz <= radius * radius {
+                columns.push((dx, dz));
+            }
+        }
+    }
+    columns.sort_by_key(|&(dx, dz)| (dx * dx + dz * dz, dx, dz));
+    columns.into_iter()
+        .flat_map(|(dx, dz)| (MIN_CHUNK_Y..MIN_CHUNK_Y + PREGENERATE_LAYERS).map(move |y| (center.0 + dx, y, center.1 + dz)))
+        .collect()
+}
+
+/// Generates `chunks` on `threads` threads, at least one, and saves them into `save`,
+/// waiting until they are written. `progress` is called on this thread after each chunk.
+pub fn pregenerate(
+    save: &mut WorldSave,
+    generator: &WorldGen,
+    chunks: &[ChunkKey],
+    threads: usize,
+    mut progress: impl FnMut(PregenProgress),
+) -> io::Result<PregenReport> {
+    let start = Instant::now();
+    let mut report = PregenReport::default();
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = unbounded();
+    std::thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let (tx, next) = (tx.clone(), &next);
+            scope.spawn(move || {
+                while let Some(&key) = chunks.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let started = Instant::now();
+                    let position = Vec3::new(key.0 as f32, key.1 as f32, key.2 as f32) * CHUNK_SIZE_F;
+                    let chunk = generator.generate(position);
+                    if tx.send((key, chunk, started.elapsed())).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+        for (key, chunk, took) in rx {
+            report.generated += 1;
+            report.generate_time += took;
+            if !chunk.is_all_air() {
+                save.store_chunk_blocks(key, &chunk.blocks);
+                report.saved += 1;
+            }
+            if report.generated.is_multiple_of(FLUSH_INTERVAL) {
+                save.flush_some(usize::MAX);
+            }
+            progress(PregenProgress { done: report.generated, total: chunks.len() });
+        }
+    });
+    save.flush()?;
+    report.elapsed = start.elapsed();
+    Ok(report)
+}
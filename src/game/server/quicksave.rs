@@ -0,0 +1,109 @@
+//! Developer save states, for getting back to the moment before a bug as often as needed.
+//!
+//! `/quicksave [slot]` snapshots the players online, the time of day, the weather and
+//! every edited chunk, and `/quickload [slot]` puts them all back; the client binds them to
+//! F5 and F9. Edited terrain is the world generator plus the block journal, so the journal
+//! is what is kept of chunks. Slots are files in the world's `quicksaves` directory, so
+//! they outlive the server, and a world written since is not touched by loading one.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::game::net::protocol::{read_chunk_edits, write_chunk_edits, ChunkEdit};
+use crate::game::save::atomic::write_atomic;
+use crate::game::save::PlayerData;
+
+pub const QUICKSAVE_DIR: &str = "quicksaves";
+/// Slots are numbered from 1 to this
+pub const QUICKSAVE_SLOTS: u8 = 9;
+const QUICKSAVE_MAGIC: &[u8; 4] = b"PSUQ";
+const QUICKSAVE_VERSION: u32 = 1;
+
+type ChunkKey = (i32, i32, i32);
+
+/// A player as they were at the quicksave
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuickPlayer {
+    pub name: String,
+    pub data: PlayerData,
+    pub health: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct QuickSave {
+    pub ticks: u64,
+    pub raining: bool,
+    pub players: Vec<QuickPlayer>,
+    /// The edits of every edited chunk, by position within it
+    pub chunks: Vec<(ChunkKey, Vec<ChunkEdit>)>,
+}
+
+/// File holding a slot, or an error naming the slots there are
+pub fn slot_path(world_dir: &Path, slot: u8) -> Result<PathBuf, String> {
+    if !(1..=QUICKSAVE_SLOTS).contains(&slot) {
+        return Err(format!("there are quicksave slots 1 to {}, not {}", QUICKSAVE_SLOTS, slot));
+    }
+    Ok(world_dir.join(QUICKSAVE_DIR).join(format!("slot{}.dat", slot)))
+}
+
+impl QuickSave {
+    pub fn encode(&self, w: &mut ByteWriter) {
+        w.write_bytes(QUICKSAVE_MAGIC);
+        w.write_u32(QUICKSAVE_VERSION);
+        w.write_u64(self.ticks);
+        w.write_u8(self.raining as u8);
+        w.write_u32(self.players.len() as u32);
+        for player in &self.players {
+            w.write_str(&player.name);
+            w.write_f32(player.health);
+            player.data.encode(w);
+        }
+        w.write_u32(self.chunks.len() as u32);
+        for (key, edits) in &self.chunks {
+            w.write_i32(key.0);
+            w.write_i32(key.1);
+            w.write_i32(key.2);
+            write_chunk_edits(w, edits);
+        }
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        if r.read_bytes(4)? != QUICKSAVE_MAGIC {
+            return Err(DecodeError::Invalid("not a quicksave".into()));
+        }
+        let version = r.read_u32()?;
+        if version != QUICKSAVE_VERSION {
+            return Err(DecodeError::Invalid(format!("quicksave version {} is not supported", version)));
+        }
+        let ticks = r.read_u64()?;
+        let raining = r.read_u8()? != 0;
+        let mut players = Vec::new();
+        for _ in 0..r.read_u32()? {
+            let name = r.read_str()?;
+            let health = r.read_f32()?;
+            players.push(QuickPlayer { name, health, data: PlayerData::decode(r)? });
+        }
+        let mut chunks = Vec::new();
+        for _ in 0..r.read_u32()? {
+            let key = (r.read_i32()?, r.read_i32()?, r.read_i32()?);
+            chunks.push((key, read_chunk_edits(r)?));
+        }
+        Ok(Self { ticks, raining, players, chunks })
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut w = ByteWriter::new();
+        self.encode(&mut w);
+        write_atomic(path, &w.into_inner())
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        Ok(Self::decode(&mut ByteReader::new(&data))?)
+    }
+}
@@ -0,0 +1,16 @@
+//! Names of the server's random streams, one per system, all derived from the world seed.
+//!
+//! A system draws only from its own stream, so a change to how often one system rolls
+//! leaves the others playing out the same, which replays and lockstep tests rely on.
+
+/// Features placed by the world generator
+pub const WORLDGEN: &str = "worldgen";
+/// Random block ticks and the blocks they change, such as fire and growing grass
+pub const TICKS: &str = "ticks";
+pub const WEATHER: &str = "weather";
+/// What mobs and other entities decide to do
+pub const AI: &str = "ai";
+/// Where dropped items fly
+pub const LOOT: &str = "loot";
+/// Fuses of TNT set off by other explosions
+pub const EXPLOSIONS: &str = "explosions";
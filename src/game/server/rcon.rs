@@ -0,0 +1,139 @@
+//! Line-based remote console.
+//!
+//! A client connects over TCP and sends the password as its first line. Every following line
+//! is run as a command with Admin permission; each reply is the command output followed by an
+//! empty line.
+//!
+//! A wrong password closes the connection after FAILED_LOGIN_DELAY, and only MAX_CONNECTIONS
+//! are served at once, which keeps guessing slow.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use log::{info, warn};
+
+/// Connections served at once; more are turned away
+pub const MAX_CONNECTIONS: usize = 4;
+/// Longest line read, in bytes, so a peer can't make the server buffer without end
+pub const MAX_LINE: usize = 4096;
+/// How long a new connection has to send the password
+pub const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long an authenticated connection may sit without sending a command
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+/// Wait before a wrong password is answered and the connection closed
+pub const FAILED_LOGIN_DELAY: Duration = Duration::from_secs(2);
+
+pub struct RconRequest {
+    pub peer: SocketAddr,
+    pub command: String,
+    reply: Sender<String>,
+}
+
+impl RconRequest {
+    pub fn respond(self, output: &str) {
+        self.reply.send(output.to_string()).ok();
+    }
+}
+
+pub struct RconServer {
+    rx: Receiver<RconRequest>,
+    pub local_addr: SocketAddr,
+}
+
+impl RconServer {
+    pub fn bind(addr: SocketAddr, password: String) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let (tx, rx) = unbounded();
+        std::thread::Builder::new()
+            .name("rcon-listener".into())
+            .spawn(move || {
+                let connections = Arc::new(AtomicUsize::new(0));
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { continue };
+                    if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                        connections.fetch_sub(1, Ordering::SeqCst);
+                        warn!("Turning away RCON connection from {:?}, {} already open", stream.peer_addr(), MAX_CONNECTIONS);
+                        writeln!(stream, "Too many connections").ok();
+                        continue;
+                    }
+                    let tx = tx.clone();
+                    let password = password.clone();
+                    let open = connections.clone();
+                    let spawned = std::thread::Builder::new()
+                        .name("rcon-connection".into())
+                        .spawn(move || {
+                            if let Err(e) = handle_connection(stream, &password, tx) {
+                                warn!("RCON connection error: {}", e);
+                            }
+                            open.fetch_sub(1, Ordering::SeqCst);
+                        });
+                    if let Err(e) = spawned {
+                        warn!("Failed to start RCON connection thread: {}", e);
+                        connections.fetch_sub(1, Ordering::SeqCst);
+                    }
+                }
+            })?;
+        info!("RCON listening on {}", local_addr);
+        Ok(Self { rx, local_addr })
+    }
+
+    pub fn poll(&self) -> Vec<RconRequest> {
+        self.rx.try_iter().collect()
+    }
+}
+
+/// Reads a line of at most MAX_LINE bytes, without its line ending. None once the peer is
+/// done.
+fn read_line(reader: &mut BufReader<TcpStream>) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.by_ref().take(MAX_LINE as u64 + 1).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && line.len() > MAX_LINE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+    }
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+/// Compares in time that depends only on the lengths, so timing doesn't give away how much
+/// of a guess was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let differences = (0..a.len().max(b.len()))
+        .fold(0, |acc, i| acc | (a.get(i).copied().unwrap_or(0) ^ b.get(i).copied().unwrap_or(0)));
+    differences == 0 && a.len() == b.len()
+}
+
+fn handle_connection(stream: TcpStream, password: &str, tx: Sender<RconRequest>) -> io::Result<()> {
+    let peer = stream.peer_addr()?;
+    let mut writer = stream.try_clone()?;
+    stream.set_read_timeout(Some(AUTH_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+
+    let Some(attempt) = read_line(&mut reader)? else { return Ok(()) };
+    if !constant_time_eq(attempt.trim().as_bytes(), password.as_bytes()) {
+        warn!("RCON authentication failed from {}", peer);
+        std::thread::sleep(FAILED_LOGIN_DELAY);
+        writeln!(writer, "Authentication failed")?;
+        return Ok(());
+    }
+    info!("RCON client authenticated from {}", peer);
+    writeln!(writer, "Authenticated")?;
+    reader.get_ref().set_read_timeout(Some(IDLE_TIMEOUT))?;
+
+    while let Some(command) = read_line(&mut reader)? {
+        if command.trim().is_empty() {
+            continue;
+        }
+        let (reply_tx, reply_rx) = bounded(1);
+        if tx.send(RconRequest { peer, command, reply: reply_tx }).is_err() {
+            break;
+        }
+        let output = reply_rx.recv().unwrap_or_else(|_| "Server stopped".to_string());
+        writeln!(writer, "{}\n", output)?;
+    }
+    Ok(())
+}
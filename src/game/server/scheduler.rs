@@ -0,0 +1,116 @@
+//! Fixed-rate tick clock and scheduled block ticks.
+//!
+//! The server simulates in fixed steps of TICK_DELTA no matter how often the loop around it
+//! wakes up, so gameplay speed does not depend on any client's frame rate.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::time::Duration;
+use crate::engine::time::{Instant, MAX_TIME_SCALE, MIN_TIME_SCALE};
+
+pub const TICK_RATE: u32 = 20;
+pub const TICK_DELTA: f32 = 1.0 / TICK_RATE as f32;
+/// Ticks run back to back after a stall before the clock gives up and skips ahead
+pub const MAX_CATCH_UP_TICKS: u32 = 10;
+
+pub struct TickClock {
+    /// Time between ticks at normal speed
+    base_interval: Duration,
+    interval: Duration,
+    next_tick: Instant,
+}
+
+impl Default for TickClock {
+    fn default() -> Self {
+        Self::new(TICK_RATE)
+    }
+}
+
+impl TickClock {
+    pub fn new(rate: u32) -> Self {
+        let interval = Duration::from_secs(1) / rate.max(1);
+        Self { base_interval: interval, interval, next_tick: Instant::now() }
+    }
+
+    /// Runs ticks `scale` times as fast as normal, for slow motion while debugging. Each
+    /// tick still covers TICK_DELTA of game time.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.interval = self.base_interval.div_f64(scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE) as f64);
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns how many ticks to run now and how many were skipped because the server fell
+    /// too far behind
+    pub fn due(&mut self) -> (u32, u32) {
+        let now = Instant::now();
+        if now < self.next_tick {
+            return (0, 0);
+        }
+        let behind = (now - self.next_tick).as_nanos() / self.interval.as_nanos();
+        let due = u32::try_from(behind + 1).unwrap_or(u32::MAX);
+        let run = due.min(MAX_CATCH_UP_TICKS);
+        let skipped = due - run;
+        if skipped > 0 {
+            self.next_tick = now + self.interval;
+        } else {
+            self.next_tick += self.interval * run;
+        }
+        (run, skipped)
+    }
+
+    /// How long the loop may sleep before the next tick is due
+    pub fn until_next_tick(&self) -> Duration {
+        self.next_tick.saturating_duration_since(Instant::now())
+    }
+}
+
+type BlockPos = (i32, i32, i32);
+
+/// Block positions waiting for a tick, ordered by the tick they are due on. A block has at
+/// most one pending tick; scheduling it again before that runs is ignored.
+#[derive(Default)]
+pub struct BlockTickScheduler {
+    queue: BinaryHeap<Reverse<(u64, BlockPos)>>,
+    pending: HashSet<BlockPos>,
+}
+
+impl BlockTickScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn schedule(&mut self, block: BlockPos, due_tick: u64) {
+        if self.pending.insert(block) {
+            self.queue.push(Reverse((due_tick, block)));
+        }
+    }
+
+    pub fn is_scheduled(&self, block: BlockPos) -> bool {
+        self.pending.contains(&block)
+    }
+
+    /// Removes and returns every block due on or before `tick`
+    pub fn drain_due(&mut self, tick: u64) -> Vec<BlockPos> {
+        let mut due = Vec::new();
+        while let Some(Reverse((due_tick, block))) = self.queue.peek().copied() {
+            if due_tick > tick {
+                break;
+            }
+            self.queue.pop();
+            self.pending.remove(&block);
+            due.push(block);
+        }
+        due
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
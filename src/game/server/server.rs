@@ -0,0 +1,1265 @@
+//! Server state implementation.
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use crate::engine::time::Instant;
+use glam::Vec3;
+use log::{debug, info, warn};
+
+use crate::game::command::{CommandError, CommandRegistry, CommandSender, PermissionLevel};
+use crate::game::entity::{boat, EntityId, EntityManager, Steering};
+use crate::game::net::protocol::{ClientId, ClientMessage, ServerMessage};
+use crate::game::item::tool::{self, HIT_SECONDS};
+use crate::game::item::{CraftingGrid, Inventory, ItemStack, ItemType, RecipeBook};
+use crate::game::save::{PlayerData, PlayerId, WorldData, WorldSave};
+use crate::game::server::access::AccessLists;
+use crate::game::server::admin;
+use crate::game::server::interest::InterestManager;
+use crate::game::server::journal::BlockJournal;
+use crate::game::server::quicksave::{self, QuickPlayer, QuickSave};
+use crate::game::server::random;
+use crate::game::server::metrics::{PhaseTimes, TickMetrics};
+use crate::game::server::movement::{MovementValidator, MoveVerdict};
+use crate::game::server::scheduler::{BlockTickScheduler, TICK_DELTA, TICK_RATE};
+use crate::engine::math::{Aabb, RngStreams};
+use crate::game::entity::EntityKind;
+use crate::game::player::physics::EYE_HEIGHT;
+use crate::game::player::survival::{ACTION_EXHAUSTION, MAX_FOOD, STARVE_MIN_HEALTH, TRAVEL_EXHAUSTION};
+use crate::game::player::{Gamemode, Hunger, PlayerBody, PLAYER_MAX_HEALTH};
+use crate::game::world::behavior::{self, BlockChange};
+use crate::game::world::chunk::{BlockType, CHUNK_SIZE};
+use crate::game::world::chunk_manager::{ChunkManager, VOID_DEPTH, WORLD_FLOOR};
+use crate::game::world::day_cycle::{TimeOfDay, DAY_LENGTH, TIME_SYNC_INTERVAL};
+use crate::game::world::explosion::{Explosion, TNT_POWER};
+use crate::game::world::fluid::{self, Fluid};
+use crate::game::world::raycast::{self, RaycastHit};
+use crate::game::world::rules::GameRules;
+use crate::game::world::sign::{self, SignData};
+use crate::game::world::weather::WeatherCycle;
+use crate::game::world::worldgen::WorldGen;
+
+pub const DEFAULT_VIEW_DISTANCE: i32 = 10;
+/// Chunks kept loaded around each player for server-side simulation. Smaller than the
+/// client's view distance since the server only needs terrain near simulated entities.
+pub const SIMULATION_DISTANCE: i32 = 4;
+pub const DEFAULT_SPAWN: Vec3 = Vec3::new(8.0, 4.0, 8.0);
+/// How far a player can reach when attacking or interacting
+pub const PLAYER_REACH: f32 = 5.0;
+/// How far the start of an attack or placement ray can be from the player's eye, which
+/// covers the eye moving between the client's last move and its action
+pub const MAX_ORIGIN_DRIFT: f32 = 1.0;
+pub const ATTACK_DAMAGE: f32 = 4.0;
+pub const ATTACK_KNOCKBACK: f32 = 6.0;
+/// Ticks between recomputing which chunks the players need loaded
+pub const CHUNK_UPDATE_INTERVAL: u64 = 10;
+/// Random blocks ticked in each loaded chunk every server tick, which drives crop growth
+pub const RANDOM_TICKS_PER_CHUNK: u32 = 3;
+/// Seconds after a player takes damage before they can take more
+pub const PLAYER_HURT_COOLDOWN: f32 = 0.5;
+/// Damage each hurt cooldown to survival players in the void, which kills in a few seconds
+pub const VOID_DAMAGE: f32 = 4.0;
+/// Shortest fuse of TNT set off by another explosion; the longest is twice this
+pub const CHAINED_FUSE_MIN: f32 = 0.5;
+/// How far a sleeping player can move from their bed before they count as awake
+pub const BED_LEAVE_DISTANCE: f32 = 3.0;
+/// How close a player has to be to an item drop to pick it up
+pub const PICKUP_RADIUS: f32 = 1.5;
+/// Seconds an item drop lies before it can be picked up, so it is seen to drop
+pub const PICKUP_DELAY: f32 = 0.5;
+/// Ticks without a hit after which a half broken block heals
+pub const MINING_TIMEOUT_TICKS: u64 = 10;
+/// Fastest an item dropped on death is thrown out sideways, in blocks per second
+const DEATH_SCATTER_SPEED: f32 = 2.0;
+
+/// A block a survival player is partway through breaking
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mining {
+    pub block: (i32, i32, i32),
+    /// Seconds of breaking done so far
+    pub progress: f32,
+    /// Tick of the latest hit
+    pub last_hit: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlayerSession {
+    pub id: PlayerId,
+    pub name: String,
+    pub permission: PermissionLevel,
+    /// Permission the connection came with, before the ops list adds to it
+    pub granted: PermissionLevel,
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub movement: MovementValidator,
+    /// Moves rejected by validation since the player joined
+    pub violations: u32,
+    pub health: f32,
+    /// Seconds left before the player can be hurt again
+    pub hurt_timer: f32,
+    /// Entity the player is mounted on; it carries them and their moves are ignored
+    pub riding: Option<EntityId>,
+    /// Bed the player respawns at, if they have slept in one
+    pub spawn: Option<(i32, i32, i32)>,
+    /// Bed the player is sleeping in, waiting for everyone else to sleep too
+    pub sleeping: Option<(i32, i32, i32)>,
+    pub inventory: Inventory,
+    /// Died and has not asked to respawn yet; dead players cannot move, act or be hurt
+    pub dead: bool,
+    pub gamemode: Gamemode,
+    /// Only drains in survival
+    pub hunger: Hunger,
+    /// Block being broken; in creative blocks break at once
+    pub mining: Option<Mining>,
+}
+
+impl PlayerSession {
+    pub fn sender(&self) -> CommandSender {
+        CommandSender::new(self.name.clone(), self.permission)
+    }
+
+    /// The food level the client is told about, None when the player cannot get hungry
+    fn food(&self) -> Option<f32> {
+        self.gamemode.has_hunger().then_some(self.hunger.food)
+    }
+
+    fn data(&self) -> PlayerData {
+        PlayerData { position: self.position, yaw: self.yaw, pitch: self.pitch, spawn: self.spawn, inventory: self.inventory, gamemode: self.gamemode, hunger: self.hunger }
+    }
+}
+
+pub struct Server {
+    pub chunks: ChunkManager,
+    pub entities: EntityManager,
+    pub interest: InterestManager,
+    pub world_save: WorldSave,
+    pub commands: CommandRegistry,
+    pub block_ticks: BlockTickScheduler,
+    /// Every block edited in the world, for clients that weren't there to see it
+    pub journal: BlockJournal,
+    pub metrics: TickMetrics,
+    /// Randomness for gameplay, a stream per system; see `random`
+    pub random: RngStreams,
+    pub time: TimeOfDay,
+    pub weather: WeatherCycle,
+    pub rules: GameRules,
+    pub recipes: RecipeBook,
+    /// The whitelist and ops list, as last read from the world directory
+    pub access: AccessLists,
+    tick_count: u64,
+    sessions: HashMap<ClientId, PlayerSession>,
+    banned: HashSet<String>,
+    outbox: Vec<(ClientId, ServerMessage)>,
+    publish_request: Option<u16>,
+    next_client_id: u32,
+    running: bool,
+}
+
+impl Server {
+    pub fn new(world_save: WorldSave) -> Self {
+        Self::create(world_save, WorldGen::default())
+    }
+
+    /// Opens the world, creating it with `generator` if it was never saved. An existing
+    /// world keeps the generator it was created with.
+    pub fn create(world_save: WorldSave, generator: WorldGen) -> Self {
+        let mut commands = CommandRegistry::new();
+        admin::register_commands(&mut commands);
+        let world = if world_save.has_world() {
+            world_save.load_world()
+        } else {
+            info!("Creating a {} world with seed {}", generator.world_type.name(), generator.seed);
+            let world = WorldData { generator, ..WorldData::default() };
+            world_save.save_world(&world);
+            world
+        };
+        let seed = world.generator.seed;
+        let mut chunks = ChunkManager::new(SIMULATION_DISTANCE);
+        chunks.set_generator(world.generator);
+        #[cfg(not(target_arch = "wasm32"))]
+        chunks.set_saved_chunks(crate::game::save::SavedChunks::open(&world_save.root));
+        #[cfg(not(target_arch = "wasm32"))]
+        let access = AccessLists::load(&world_save.root).unwrap_or_else(|e| {
+            warn!("Not using the whitelist and ops list, {}", e);
+            AccessLists::new()
+        });
+        #[cfg(target_arch = "wasm32")]
+        let access = AccessLists::new();
+        Self {
+            chunks,
+            entities: EntityManager::new(),
+            interest: InterestManager::new(),
+            world_save,
+            commands,
+            block_ticks: BlockTickScheduler::new(),
+            journal: BlockJournal::with_saved(world.edited_chunks),
+            metrics: TickMetrics::new(),
+            random: RngStreams::new(seed as u64),
+            time: TimeOfDay::new(world.ticks),
+            weather: WeatherCycle::new(),
+            rules: world.rules,
+            recipes: RecipeBook::load(),
+            access,
+            tick_count: 0,
+            sessions: HashMap::new(),
+            banned: HashSet::new(),
+            outbox: Vec::new(),
+            publish_request: None,
+            next_client_id: 1,
+            running: true,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn stop(&mut self) {
+        if self.running {
+            info!("Stopping server");
+        }
+        self.running = false;
+    }
+
+    /// Admits a player, refusing banned names and, while the whitelist is on, names not on
+    /// it, and queues the Welcome reply. `permission` is what the connection grants; the
+    /// ops list can raise it.
+    pub fn connect(&mut self, name: &str, permission: PermissionLevel) -> Result<ClientId, String> {
+        if self.is_banned(name) {
+            return Err(format!("{} is banned from this server", name));
+        }
+        // The host of the world is always let in
+        if permission < PermissionLevel::Admin && !self.access.is_allowed(name) {
+            return Err(format!("{} is not whitelisted on this server", name));
+        }
+        // Sessions sharing an id would overwrite each other's save
+        let player = PlayerId::from_name(name);
+        if self.find_player(name).is_some() || self.sessions.values().any(|session| session.id == player) {
+            return Err(format!("{} is already connected", name));
+        }
+        let data = self.world_save.load_player(player, name)
+            .unwrap_or(PlayerData::new(DEFAULT_SPAWN));
+        let id = ClientId(self.next_client_id);
+        self.next_client_id += 1;
+        self.sessions.insert(id, PlayerSession {
+            id: player,
+            name: name.to_string(),
+            // Taken on the name's word, see AccessLists
+            permission: permission.max(self.access.permission(name)),
+            granted: permission,
+            position: data.position,
+            yaw: data.yaw,
+            pitch: data.pitch,
+            movement: MovementValidator::new(),
+            violations: 0,
+            health: PLAYER_MAX_HEALTH,
+            hurt_timer: 0.0,
+            riding: None,
+            spawn: data.spawn,
+            sleeping: None,
+            inventory: data.inventory,
+            dead: false,
+            gamemode: data.gamemode,
+            hunger: data.hunger,
+            mining: None,
+        });
+        self.interest.add_client(id, data.position, DEFAULT_VIEW_DISTANCE);
+        self.outbox.push((id, ServerMessage::Welcome { client: id, position: data.position, yaw: data.yaw, pitch: data.pitch }));
+        self.outbox.push((id, ServerMessage::WorldGen { generator: self.chunks.generator().clone() }));
+        self.outbox.push((id, ServerMessage::Time { ticks: self.time.ticks }));
+        self.outbox.push((id, ServerMessage::Weather { raining: self.weather.raining }));
+        self.outbox.push((id, ServerMessage::Hunger { food: data.gamemode.has_hunger().then_some(data.hunger.food) }));
+        self.outbox.push((id, ServerMessage::Inventory { inventory: data.inventory }));
+        info!("{} joined as {:?}", name, id);
+        Ok(id)
+    }
+
+    pub fn disconnect(&mut self, client: ClientId, reason: &str) {
+        if let Some(session) = self.sessions.remove(&client) {
+            if let Some(entity) = session.riding.and_then(|id| self.entities.get_mut(id)) {
+                entity.steering = Steering::default();
+            }
+            info!("{} left: {}", session.name, reason);
+            self.world_save.save_player(session.id, &session.data());
+            self.outbox.push((client, ServerMessage::Disconnect { reason: reason.to_string() }));
+            self.interest.remove_client(client);
+        }
+    }
+
+    pub fn session(&self, client: ClientId) -> Option<&PlayerSession> {
+        self.sessions.get(&client)
+    }
+
+    pub fn sessions(&self) -> impl Iterator<Item = (&ClientId, &PlayerSession)> {
+        self.sessions.iter()
+    }
+
+    pub fn find_player(&self, name: &str) -> Option<ClientId> {
+        self.sessions.iter()
+            .find(|(_, s)| s.name.eq_ignore_ascii_case(name))
+            .map(|(id, _)| *id)
+    }
+
+    pub fn ban(&mut self, name: &str) {
+        self.banned.insert(name.to_lowercase());
+    }
+
+    pub fn pardon(&mut self, name: &str) -> bool {
+        self.banned.remove(&name.to_lowercase())
+    }
+
+    pub fn is_banned(&self, name: &str) -> bool {
+        self.banned.contains(&name.to_lowercase())
+    }
+
+    /// Reads the whitelist and ops list again after they were edited by hand and applies
+    /// them, returning how many players that disconnected
+    pub fn reload_access(&mut self) -> Result<usize, String> {
+        self.access = AccessLists::load(&self.world_save.root)?;
+        Ok(self.apply_access())
+    }
+
+    /// Writes the whitelist and ops list out after a command changed them
+    pub fn save_access(&self) -> io::Result<()> {
+        self.access.save(&self.world_save.root)
+    }
+
+    /// Gives players online the permission the ops list now gives them, and disconnects
+    /// the ones the whitelist no longer lets in, returning how many that was
+    pub fn apply_access(&mut self) -> usize {
+        let mut refused = Vec::new();
+        for (client, session) in &mut self.sessions {
+            let permission = session.granted.max(self.access.permission(&session.name));
+            if permission != session.permission {
+                info!("{} now has {:?} permission", session.name, permission);
+                session.permission = permission;
+            }
+            if session.granted < PermissionLevel::Admin && !self.access.is_allowed(&session.name) {
+                refused.push(*client);
+            }
+        }
+        for client in &refused {
+            self.disconnect(*client, "You are not whitelisted on this server");
+        }
+        refused.len()
+    }
+
+    pub fn send(&mut self, client: ClientId, message: ServerMessage) {
+        self.outbox.push((client, message));
+    }
+
+    pub fn broadcast(&mut self, message: ServerMessage) {
+        for client in self.sessions.keys() {
+            self.outbox.push((*client, message.clone()));
+        }
+    }
+
+    /// Messages queued for delivery since the last call
+    pub fn drain_outbox(&mut self) -> Vec<(ClientId, ServerMessage)> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    /// Asks the network layer to start accepting LAN connections on `port`
+    pub fn request_publish(&mut self, port: u16) {
+        self.publish_request = Some(port);
+    }
+
+    pub fn take_publish_request(&mut self) -> Option<u16> {
+        self.publish_request.take()
+    }
+
+    /// Queues the world, its players, every live entity and the edited terrain for saving.
+    /// Regions are written a few per tick from then on, as the save settings allow.
+    fn queue_save(&mut self) {
+        self.world_save.store_all_entities(&self.entities);
+        let held: Vec<_> = self.journal.held().copied().collect();
+        for key in held {
+            self.world_save.store_chunk_edits(key, self.journal.local_edits(key));
+        }
+        // What no one is near is in the save now, and is read back when someone comes close
+        let (chunks, interest) = (&self.chunks, &self.interest);
+        self.journal.release(|&key| chunks.is_loaded(key) || interest.is_watched(key));
+        self.world_save.save_world(&WorldData {
+            ticks: self.time.ticks,
+            rules: self.rules,
+            generator: self.chunks.generator().clone(),
+            edited_chunks: self.journal.chunks().copied().collect(),
+        });
+        for session in self.sessions.values() {
+            self.world_save.save_player(session.id, &session.data());
+        }
+    }
+
+    /// Reads the saved edits of a chunk back into the journal, unless it holds them already
+    fn restore_edits(&mut self, key: (i32, i32, i32)) {
+        if !self.journal.holds(key) {
+            let edits = self.world_save.chunk_edits(key);
+            self.journal.restore(key, &edits);
+        }
+    }
+
+    /// Saves everything and waits until it is on disk
+    pub fn save_all(&mut self) -> io::Result<()> {
+        self.queue_save();
+        self.world_save.flush()
+    }
+
+    /// Snapshots the players online, the time, the weather and the edited chunks to a
+    /// quicksave slot
+    pub fn quicksave(&mut self, slot: u8) -> Result<(), String> {
+        let path = quicksave::slot_path(&self.world_save.root, slot)?;
+        let released: Vec<_> = self.journal.released().copied().collect();
+        let mut chunks: Vec<_> = self.journal.held().map(|&key| (key, self.journal.local_edits(key))).collect();
+        chunks.extend(released.into_iter().map(|key| (key, self.world_save.chunk_edits(key))));
+        let save = QuickSave {
+            ticks: self.time.ticks,
+            raining: self.weather.raining,
+            players: self.sessions.values()
+                .map(|session| QuickPlayer { name: session.name.clone(), data: session.data(), health: session.health })
+                .collect(),
+            chunks,
+        };
+        save.write(&path).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+    }
+
+    /// Puts back what a quicksave slot holds. Blocks edited since it was taken go back to
+    /// how they were, and the players in it who are online go back to where they were.
+    pub fn quickload(&mut self, slot: u8) -> Result<(), String> {
+        let path = quicksave::slot_path(&self.world_save.root, slot)?;
+        let save = QuickSave::read(&path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let size = CHUNK_SIZE as i32;
+        let mut blocks: HashMap<(i32, i32, i32), BlockType> = HashMap::new();
+        for (key, edits) in &save.chunks {
+            for &((x, y, z), block_type) in edits {
+                blocks.insert((key.0 * size + x as i32, key.1 * size + y as i32, key.2 * size + z as i32), block_type);
+            }
+        }
+        // Blocks edited only since go back to the generated terrain
+        let chunks: Vec<_> = self.journal.chunks().copied().collect();
+        for &key in &chunks {
+            self.restore_edits(key);
+        }
+        for key in chunks {
+            let since: Vec<_> = self.journal.edits(key).map(|(block, _)| block).filter(|block| !blocks.contains_key(block)).collect();
+            if since.is_empty() {
+                continue;
+            }
+            let generated = self.chunks.generator().generate(Vec3::new(key.0 as f32, key.1 as f32, key.2 as f32) * CHUNK_SIZE as f32);
+            for block in since {
+                let local = (block.0.rem_euclid(size) as usize, block.1.rem_euclid(size) as usize, block.2.rem_euclid(size) as usize);
+                blocks.insert(block, generated.blocks[local.0][local.1][local.2]);
+            }
+        }
+        for (block, block_type) in blocks {
+            if self.chunks.get_block(block.0, block.1, block.2).is_some() {
+                self.set_block(block, block_type);
+            } else {
+                // Put in when the chunk loads
+                self.journal.record(block, block_type);
+            }
+        }
+
+        self.time = TimeOfDay::new(save.ticks);
+        self.broadcast(ServerMessage::Time { ticks: save.ticks });
+        self.weather.set(save.raining, self.random.stream(random::WEATHER));
+        self.broadcast(ServerMessage::Weather { raining: save.raining });
+        for player in save.players {
+            let Some(client) = self.find_player(&player.name) else { continue };
+            let Some(session) = self.sessions.get_mut(&client) else { continue };
+            if let Some(entity) = session.riding.take().and_then(|id| self.entities.get_mut(id)) {
+                entity.steering = Steering::default();
+                self.outbox.push((client, ServerMessage::Mounted { entity: None }));
+            }
+            let data = player.data;
+            session.position = data.position;
+            session.yaw = data.yaw;
+            session.pitch = data.pitch;
+            session.spawn = data.spawn;
+            session.inventory = data.inventory;
+            session.gamemode = data.gamemode;
+            session.hunger = data.hunger;
+            session.health = player.health;
+            session.hurt_timer = 0.0;
+            session.dead = false;
+            session.sleeping = None;
+            session.mining = None;
+            let food = session.food();
+            self.interest.set_position(client, data.position);
+            self.outbox.push((client, ServerMessage::CorrectPosition { position: data.position }));
+            self.outbox.push((client, ServerMessage::Health { health: player.health }));
+            self.outbox.push((client, ServerMessage::Hunger { food }));
+            self.outbox.push((client, ServerMessage::Inventory { inventory: data.inventory }));
+        }
+        Ok(())
+    }
+
+    fn autosave_if_due(&mut self) {
+        let interval = self.world_save.settings.autosave_interval as u64 * TICK_RATE as u64;
+        if interval > 0 && self.tick_count.is_multiple_of(interval) {
+            debug!("Autosaving");
+            self.queue_save();
+        }
+    }
+
+    /// Parses and runs a command line, returning the text to show the sender
+    pub fn execute(&mut self, sender: &CommandSender, line: &str) -> Result<String, CommandError> {
+        let command = self.commands.parse(sender, line)?;
+        admin::execute(self, sender, &command)
+    }
+
+    pub fn handle_message(&mut self, client: ClientId, message: ClientMessage) {
+        let Some(session) = self.sessions.get_mut(&client) else { return };
+        if session.dead && !matches!(message, ClientMessage::Respawn | ClientMessage::Chat { .. } | ClientMessage::SnapshotAck { .. }) {
+            return;
+        }
+        match message {
+            ClientMessage::Hello { .. } => debug!("Ignoring repeated Hello from {:?}", client),
+            ClientMessage::Move { position, yaw, pitch } => {
+                session.yaw = yaw;
+                session.pitch = pitch;
+                if session.riding.is_some() {
+                    return;
+                }
+                match session.movement.check(&self.chunks, session.position, position) {
+                    MoveVerdict::Accept => {
+                        if session.gamemode.has_hunger() {
+                            let travelled = (position - session.position).with_y(0.0).length();
+                            session.hunger.exert(travelled * TRAVEL_EXHAUSTION);
+                        }
+                        session.position = position;
+                        let bed = session.sleeping.map(|b| Vec3::new(b.0 as f32, b.1 as f32, b.2 as f32));
+                        if bed.is_some_and(|bed| bed.distance(position) > BED_LEAVE_DISTANCE) {
+                            session.sleeping = None;
+                        }
+                        self.interest.set_position(client, position);
+                    }
+                    verdict => {
+                        session.violations += 1;
+                        warn!("Rejected move by {} ({:?}), {} so far", session.name, verdict, session.violations);
+                        let position = session.position;
+                        self.send(client, ServerMessage::CorrectPosition { position });
+                    }
+                }
+            }
+            // A NaN ray would be cast from block (0, 0, 0), wherever the player is
+            ClientMessage::Attack { origin, dir } | ClientMessage::Place { origin, dir, .. }
+                if !origin.is_finite() || !dir.is_finite() || session.position.distance(origin) > MAX_ORIGIN_DRIFT =>
+            {
+                debug!("Ignoring action from {:?} cast from {} toward {}", client, origin, dir);
+            }
+            ClientMessage::Attack { origin, dir } => {
+                if session.gamemode.has_hunger() {
+                    session.hunger.exert(ACTION_EXHAUSTION);
+                }
+                self.attack(client, origin, dir);
+            }
+            ClientMessage::Place { origin, dir, block_type } => self.place(client, origin, dir, block_type),
+            ClientMessage::SnapshotAck { sequence } => self.interest.ack_snapshot(client, sequence),
+            ClientMessage::Steer { forward, turn } => {
+                if let Some(entity) = session.riding.and_then(|id| self.entities.get_mut(id)) {
+                    entity.steering = Steering::new(forward, turn);
+                }
+            }
+            ClientMessage::Dismount => self.dismount(client),
+            ClientMessage::Respawn => self.respawn(client),
+            ClientMessage::Eat => self.eat(client),
+            ClientMessage::Craft { grid } => self.craft(client, &grid),
+            ClientMessage::RequestChunkEdits { chunk } => {
+                // Only chunks the client can see, or anyone could read the whole journal
+                if self.interest.client(client).is_some_and(|interest| interest.in_range(chunk)) {
+                    self.restore_edits(chunk);
+                    let edits = self.journal.local_edits(chunk);
+                    self.outbox.push((client, ServerMessage::ChunkEdits { chunk, edits }));
+                }
+            }
+            ClientMessage::SetSignText { block, text } => {
+                let within_reach = session.position.distance(Vec3::new(block.0 as f32, block.1 as f32, block.2 as f32)) <= PLAYER_REACH + 1.0;
+                if !within_reach || !self.set_sign_text(block, sign::sanitize(&text)) {
+                    debug!("Ignoring sign text from {:?} for {:?}", client, block);
+                }
+            }
+            ClientMessage::Chat { text } => {
+                if text.starts_with('/') {
+                    let sender = session.sender();
+                    let reply = match self.execute(&sender, &text) {
+                        Ok(output) => output,
+                        Err(e) => e.to_string(),
+                    };
+                    self.send(client, ServerMessage::Chat { text: reply });
+                } else {
+                    let line = format!("<{}> {}", session.name, text);
+                    info!("{}", line);
+                    self.broadcast(ServerMessage::Chat { text: line });
+                }
+            }
+        }
+    }
+
+    /// Damages the first entity along the ray, or hits the first block if it is closer
+    fn attack(&mut self, client: ClientId, origin: Vec3, dir: Vec3) {
+        match raycast::raycast(&self.chunks, &self.entities, origin, dir, PLAYER_REACH) {
+            Some(RaycastHit::Entity { id, distance }) => {
+                if let Some(entity) = self.entities.get_mut(id) {
+                    let push = Vec3::new(dir.x, 0.0, dir.z).normalize_or_zero() * ATTACK_KNOCKBACK + Vec3::Y * 4.0;
+                    if entity.damage(ATTACK_DAMAGE, push) {
+                        debug!("Hit {:?} at distance {:.2}, health {}", id, distance, entity.health);
+                    }
+                }
+            }
+            Some(RaycastHit::Block(hit)) => self.hit_block(client, hit.block, hit.block_type),
+            None => (),
+        }
+    }
+
+    /// Breaks a block at once in creative. In survival each hit adds HIT_SECONDS of breaking
+    /// until the block's break time is reached, sped up by a suitable tool from the
+    /// inventory, and the player is told how far along it is.
+    fn hit_block(&mut self, client: ClientId, block: (i32, i32, i32), block_type: BlockType) {
+        let Some(session) = self.sessions.get_mut(&client) else { return };
+        let tool = session.inventory.tool_for(block_type);
+        if session.gamemode.has_hunger() {
+            let needed = tool::break_time(block_type, tool);
+            let tick = self.tick_count;
+            let progress = match session.mining {
+                Some(m) if m.block == block && tick - m.last_hit <= MINING_TIMEOUT_TICKS => m.progress + HIT_SECONDS,
+                _ => HIT_SECONDS,
+            };
+            if progress < needed {
+                session.mining = Some(Mining { block, progress, last_hit: tick });
+                self.outbox.push((client, ServerMessage::BreakProgress { block, progress: progress / needed }));
+                return;
+            }
+            if session.mining.take().is_some() {
+                self.outbox.push((client, ServerMessage::BreakProgress { block, progress: 0.0 }));
+            }
+        }
+        let change = behavior::break_block(&self.chunks, block, tool);
+        self.apply_change(block, change);
+    }
+
+    /// Heals blocks survival players stopped hitting before breaking them
+    fn expire_mining(&mut self) {
+        for (&client, session) in self.sessions.iter_mut() {
+            let Some(mining) = session.mining else { continue };
+            if self.tick_count - mining.last_hit > MINING_TIMEOUT_TICKS {
+                session.mining = None;
+                self.outbox.push((client, ServerMessage::BreakProgress { block: mining.block, progress: 0.0 }));
+            }
+        }
+    }
+
+    /// Mounts a rideable entity the ray hits, or uses the block it hits if it is interactable,
+    /// such as a door. Otherwise places a block against the face it hits, unless a player or
+    /// entity is in the way. Using or placing a sign opens the editor for it, and using a bed
+    /// sleeps in it.
+    fn place(&mut self, client: ClientId, origin: Vec3, dir: Vec3, block_type: BlockType) {
+        if let Some(RaycastHit::Entity { id, .. }) = raycast::raycast(&self.chunks, &self.entities, origin, dir, PLAYER_REACH) {
+            if self.entities.get(id).is_some_and(|e| e.kind.is_rideable()) {
+                self.mount(client, id);
+                return;
+            }
+        }
+        let Some(hit) = raycast::raycast_targets(&self.chunks, origin, dir, PLAYER_REACH) else { return };
+        if let BlockType::Sign(_) = hit.block_type {
+            self.outbox.push((client, ServerMessage::EditSign { block: hit.block }));
+            return;
+        }
+        if hit.block_type == BlockType::Bed {
+            self.sleep(client, hit.block);
+            return;
+        }
+        if let Some(change) = behavior::use_block(&self.chunks, hit.block) {
+            self.apply_change(hit.block, change);
+            return;
+        }
+        // Building against a non-solid block, e.g. a ladder, is not allowed
+        if !hit.block_type.is_solid() {
+            return;
+        }
+        // Survival players build from their inventory, one item per block
+        let Some(session) = self.sessions.get(&client) else { return };
+        let item = match session.gamemode {
+            Gamemode::Creative => None,
+            Gamemode::Survival => match ItemType::for_block(block_type) {
+                Some(item) if session.inventory.count(item) > 0 => Some(item),
+                _ => {
+                    debug!("{} tried to place {:?} without the item", session.name, block_type);
+                    return;
+                }
+            },
+        };
+        let Some(block_type) = block_type.placed_against(hit.normal, dir).filter(|&b| b != BlockType::Air) else { return };
+        let block = (hit.block.0 + hit.normal.0, hit.block.1 + hit.normal.1, hit.block.2 + hit.normal.2);
+        if !self.chunks.get_block(block.0, block.1, block.2).is_some_and(|b| b.is_replaceable()) {
+            return;
+        }
+        let Some(change) = behavior::place(&self.chunks, block, block_type) else { return };
+        // Blocks that don't collide, such as ladders, can be placed where someone stands
+        let occupied = change.edits.iter().filter(|(_, b)| b.collides()).any(|&(pos, _)| {
+            let bounds = Aabb::from_center(Vec3::new(pos.0 as f32, pos.1 as f32, pos.2 as f32), Vec3::splat(0.5));
+            self.sessions.values().any(|s| PlayerBody::aabb(s.position).intersects(&bounds))
+                || !self.entities.query_aabb(&bounds).is_empty()
+        });
+        if occupied {
+            debug!("{:?} tried to place a block inside something at {:?}", client, block);
+            return;
+        }
+        self.apply_change(block, change);
+        if let Some(session) = item.and_then(|_| self.sessions.get_mut(&client)) {
+            session.inventory.take_one(|i| Some(i) == item);
+            let inventory = session.inventory;
+            self.outbox.push((client, ServerMessage::Inventory { inventory }));
+        }
+        if let BlockType::Sign(_) = block_type {
+            self.outbox.push((client, ServerMessage::EditSign { block }));
+        }
+    }
+
+    /// Sleeps in the bed at `bed` if it is night, which makes it the player's respawn point.
+    /// The night is skipped once every player is asleep.
+    fn sleep(&mut self, client: ClientId, bed: (i32, i32, i32)) {
+        if !self.time.is_night() {
+            self.send(client, ServerMessage::Chat { text: "You can only sleep at night".to_string() });
+            return;
+        }
+        let Some(session) = self.sessions.get_mut(&client) else { return };
+        session.spawn = Some(bed);
+        session.sleeping = Some(bed);
+        let name = session.name.clone();
+        self.send(client, ServerMessage::Chat { text: "Respawn point set".to_string() });
+        let asleep = self.sessions.values().filter(|s| s.sleeping.is_some()).count();
+        let text = format!("{} is sleeping ({}/{})", name, asleep, self.sessions.len());
+        info!("{}", text);
+        self.broadcast(ServerMessage::Chat { text });
+        self.skip_night_if_all_asleep();
+    }
+
+    /// Jumps to morning once everyone online is asleep, and wakes sleepers up once it is
+    /// day either way
+    fn skip_night_if_all_asleep(&mut self) {
+        if self.time.is_night() {
+            if self.sessions.is_empty() || self.sessions.values().any(|s| s.sleeping.is_none()) {
+                return;
+            }
+            self.time.skip_to_morning();
+            info!("Everyone slept, skipping to day {}", self.time.ticks / DAY_LENGTH);
+            self.broadcast(ServerMessage::Time { ticks: self.time.ticks });
+            self.broadcast(ServerMessage::Chat { text: "Good morning".to_string() });
+        }
+        for session in self.sessions.values_mut() {
+            session.sleeping = None;
+        }
+    }
+
+    /// Puts the player on `entity` unless someone else is riding it
+    fn mount(&mut self, client: ClientId, entity: EntityId) {
+        if self.sessions.values().any(|s| s.riding == Some(entity)) {
+            return;
+        }
+        let Some(session) = self.sessions.get_mut(&client) else { return };
+        debug!("{} mounted {:?}", session.name, entity);
+        session.riding = Some(entity);
+        self.outbox.push((client, ServerMessage::Mounted { entity: Some(entity) }));
+    }
+
+    /// Takes the player off what they ride and stands them on top of it, or where they sat
+    /// if it is gone
+    fn dismount(&mut self, client: ClientId) {
+        let Some(session) = self.sessions.get_mut(&client) else { return };
+        let Some(id) = session.riding.take() else { return };
+        if let Some(entity) = self.entities.get_mut(id) {
+            entity.steering = Steering::default();
+            session.position = entity.position + Vec3::Y * (entity.kind.half_extents().y + EYE_HEIGHT);
+        }
+        let position = session.position;
+        self.interest.set_position(client, position);
+        self.outbox.push((client, ServerMessage::CorrectPosition { position }));
+        self.outbox.push((client, ServerMessage::Mounted { entity: None }));
+    }
+
+    /// Moves riding players along with what they ride, and gets them off if it is gone
+    fn carry_riders(&mut self) {
+        let mut stranded = Vec::new();
+        for (&client, session) in self.sessions.iter_mut() {
+            let Some(id) = session.riding else { continue };
+            match self.entities.get(id) {
+                Some(entity) => {
+                    session.position = boat::rider_eye(entity);
+                    self.interest.set_position(client, session.position);
+                }
+                None => stranded.push(client),
+            }
+        }
+        for client in stranded {
+            self.dismount(client);
+        }
+    }
+
+    /// Applies a block change, and plays its sound and spawns its drops at `at` along with
+    /// any entities it creates
+    fn apply_change(&mut self, at: (i32, i32, i32), change: BlockChange) {
+        for (block, block_type) in change.edits {
+            self.set_block(block, block_type);
+        }
+        for (kind, position) in change.spawns {
+            self.entities.spawn(kind, position);
+        }
+        for stack in change.drops {
+            self.drop_item(Vec3::new(at.0 as f32, at.1 as f32, at.2 as f32), stack);
+        }
+        if let Some(name) = change.sound {
+            let position = Vec3::new(at.0 as f32, at.1 as f32, at.2 as f32);
+            for client in self.interest.clients_for_block(at) {
+                self.outbox.push((client, ServerMessage::Sound { name: name.to_string(), position }));
+            }
+        }
+    }
+
+    /// Changes a loaded block and tells the clients that can see it. Signs are saved as
+    /// soon as they are placed or removed.
+    pub fn set_block(&mut self, block: (i32, i32, i32), block_type: BlockType) {
+        match self.chunks.set_block(block, block_type) {
+            Some(previous) if previous != block_type => {
+                self.restore_edits(ChunkManager::chunk_key(block));
+                self.journal.record(block, block_type);
+                let updates = self.interest.block_update(block, block_type);
+                self.outbox.extend(updates);
+                self.schedule_block_reactions(block);
+                if let BlockType::Sign(facing) = block_type {
+                    self.world_save.store_sign(SignData { block, facing, text: String::new() });
+                } else if let BlockType::Sign(_) = previous {
+                    self.world_save.remove_sign(block);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Sets the text of the sign at `block`, saves it and shows it to the clients that can
+    /// see it. Returns false if there is no sign there.
+    pub fn set_sign_text(&mut self, block: (i32, i32, i32), text: String) -> bool {
+        let Some(BlockType::Sign(facing)) = self.chunks.get_block(block.0, block.1, block.2) else { return false };
+        self.chunks.set_sign_text(block, text.clone());
+        for client in self.interest.clients_for_block(block) {
+            self.outbox.push((client, ServerMessage::SignText { block, text: text.clone() }));
+        }
+        self.world_save.store_sign(SignData { block, facing, text });
+        true
+    }
+
+    /// Wakes up fluids in and around a changed block so they can flow into or out of it,
+    /// and keeps fire burning
+    fn schedule_block_reactions(&mut self, block: (i32, i32, i32)) {
+        for (dx, dy, dz) in [(0, 0, 0), (1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)] {
+            let pos = (block.0 + dx, block.1 + dy, block.2 + dz);
+            if let Some((fluid, _)) = self.chunks.get_block(pos.0, pos.1, pos.2).and_then(Fluid::of) {
+                self.schedule_block_tick(pos, fluid.tick_delay());
+            }
+        }
+        if let Some(BlockType::Fire(_)) = self.chunks.get_block(block.0, block.1, block.2) {
+            self.schedule_block_tick(block, behavior::FIRE_TICK_DELAY);
+        }
+    }
+
+    /// Number of ticks simulated so far
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    /// Queues a tick for `block` after `delay` server ticks
+    pub fn schedule_block_tick(&mut self, block: (i32, i32, i32), delay: u64) {
+        self.block_ticks.schedule(block, self.tick_count + delay.max(1));
+    }
+
+    /// Advances the simulation by one fixed step of TICK_DELTA and queues replication messages
+    pub fn tick(&mut self) {
+        self.tick_count += 1;
+        self.time.tick();
+        // Someone who stayed awake may have left or dozed off meanwhile
+        self.skip_night_if_all_asleep();
+        if self.tick_count.is_multiple_of(TIME_SYNC_INTERVAL) {
+            self.broadcast(ServerMessage::Time { ticks: self.time.ticks });
+        }
+        if self.weather.tick(self.random.stream(random::WEATHER)) {
+            self.broadcast(ServerMessage::Weather { raining: self.weather.raining });
+        }
+        let mut phases = PhaseTimes::default();
+
+        let start = Instant::now();
+        if self.tick_count % CHUNK_UPDATE_INTERVAL == 1 {
+            let centers: Vec<Vec3> = self.sessions.values().map(|s| s.position).collect();
+            self.chunks.update_chunks_around(&centers);
+        }
+        self.chunks.poll_generated();
+        self.sync_chunk_entities();
+        self.autosave_if_due();
+        phases.chunks = start.elapsed();
+
+        let start = Instant::now();
+        for block in self.block_ticks.drain_due(self.tick_count) {
+            self.run_block_tick(block);
+        }
+        self.run_random_ticks();
+        phases.block_ticks = start.elapsed();
+
+        let start = Instant::now();
+        for session in self.sessions.values_mut() {
+            session.movement.tick(TICK_DELTA);
+        }
+        self.hurt_players();
+        self.tick_hunger();
+        self.expire_mining();
+        self.entities.update(TICK_DELTA, &self.chunks);
+        self.collect_items();
+        self.carry_riders();
+        for impact in self.entities.drain_impacts() {
+            debug!("Projectile {:?} hit {:?} at {:?}", impact.projectile, impact.target, impact.position);
+        }
+        for position in self.entities.drain_detonations() {
+            self.explode(position, TNT_POWER);
+        }
+        phases.entities = start.elapsed();
+
+        let start = Instant::now();
+        let clients: Vec<ClientId> = self.sessions.keys().copied().collect();
+        for client in clients {
+            self.catch_up_edits(client);
+            for message in self.interest.entity_messages(client, &self.entities) {
+                self.outbox.push((client, message));
+            }
+        }
+        phases.replication = start.elapsed();
+
+        self.metrics.record(phases);
+    }
+
+    /// Tells the client the hash of the journaled edits of every chunk that came within its
+    /// range since the last tick. It asks for the edits it hasn't cached.
+    fn catch_up_edits(&mut self, client: ClientId) {
+        for chunk in self.interest.entered_chunks(client, self.journal.chunks()) {
+            self.restore_edits(chunk);
+            let hash = self.journal.hash(chunk);
+            self.outbox.push((client, ServerMessage::ChunkHash { chunk, hash }));
+        }
+    }
+
+    /// Runs a scheduled tick for one block, for fluids and fire
+    fn run_block_tick(&mut self, block: (i32, i32, i32)) {
+        let change = match self.chunks.get_block(block.0, block.1, block.2) {
+            Some(BlockType::Fire(_)) => behavior::fire_tick(&self.chunks, block, self.random.stream(random::TICKS)),
+            _ => fluid::tick(&self.chunks, block),
+        };
+        if let Some(change) = change {
+            self.apply_change(block, change);
+        }
+    }
+
+    /// Damages players touching blocks such as lava or fire, and survival players who fell
+    /// into the void. Creative players in the void are put back at their spawn instead.
+    fn hurt_players(&mut self) {
+        let mut burning = Vec::new();
+        let mut lost = Vec::new();
+        for (&client, session) in self.sessions.iter_mut().filter(|(_, s)| !s.dead) {
+            session.hurt_timer = (session.hurt_timer - TICK_DELTA).max(0.0);
+            let mut damage = behavior::contact_damage(&self.chunks, &PlayerBody::aabb(session.position));
+            if session.position.y < WORLD_FLOOR - VOID_DEPTH {
+                match session.gamemode {
+                    Gamemode::Survival => damage = damage.max(VOID_DAMAGE),
+                    Gamemode::Creative => lost.push(client),
+                }
+            }
+            if damage > 0.0 {
+                burning.push((client, damage));
+            }
+        }
+        for (client, damage) in burning {
+            self.damage_player(client, damage);
+        }
+        for client in lost {
+            self.return_to_spawn(client);
+        }
+    }
+
+    /// Where a player comes back: on their bed, or at DEFAULT_SPAWN if they have none
+    fn spawn_position(session: &PlayerSession) -> Vec3 {
+        session.spawn.map_or(DEFAULT_SPAWN, |b| Vec3::new(b.0 as f32, b.1 as f32 + 0.5 + EYE_HEIGHT, b.2 as f32))
+    }
+
+    /// Moves a living player back to their spawn, e.g. out of the void
+    fn return_to_spawn(&mut self, client: ClientId) {
+        let Some(session) = self.sessions.get_mut(&client) else { return };
+        if let Some(id) = session.riding.take() {
+            if let Some(entity) = self.entities.get_mut(id) {
+                entity.steering = Steering::default();
+            }
+            self.outbox.push((client, ServerMessage::Mounted { entity: None }));
+        }
+        let position = Self::spawn_position(session);
+        info!("{} fell out of the world", session.name);
+        session.position = position;
+        self.interest.set_position(client, position);
+        self.outbox.push((client, ServerMessage::CorrectPosition { position }));
+    }
+
+    /// Hurts a player unless they were hurt too recently or are already dead. Players who
+    /// run out of health die.
+    pub fn damage_player(&mut self, client: ClientId, damage: f32) {
+        let Some(session) = self.sessions.get_mut(&client) else { return };
+        if session.hurt_timer > 0.0 || session.dead {
+            return;
+        }
+        session.hurt_timer = PLAYER_HURT_COOLDOWN;
+        session.health = (session.health - damage).max(0.0);
+        let health = session.health;
+        self.outbox.push((client, ServerMessage::Health { health }));
+        if health <= 0.0 {
+            self.kill_player(client);
+        }
+    }
+
+    /// Leaves a player dead where they stand until they respawn, dropping everything they
+    /// carry unless the keepInventory rule is on
+    fn kill_player(&mut self, client: ClientId) {
+        let Some(session) = self.sessions.get_mut(&client) else { return };
+        info!("{} died", session.name);
+        session.dead = true;
+        session.sleeping = None;
+        let position = session.position;
+        let kept_inventory = self.rules.keep_inventory;
+        let drops = if kept_inventory { Vec::new() } else { session.inventory.take_all() };
+        let riding = session.riding.take();
+        if let Some(id) = riding {
+            if let Some(entity) = self.entities.get_mut(id) {
+                entity.steering = Steering::default();
+            }
+            self.outbox.push((client, ServerMessage::Mounted { entity: None }));
+        }
+        let feet = position - Vec3::Y * EYE_HEIGHT;
+        if !drops.is_empty() {
+            self.outbox.push((client, ServerMessage::Inventory { inventory: Inventory::new() }));
+        }
+        for stack in drops {
+            let id = self.drop_item(feet + Vec3::Y * 0.5, stack);
+            let rng = self.random.stream(random::LOOT);
+            let scatter = Vec3::new(rng.next_f32() * 2.0 - 1.0, 0.0, rng.next_f32() * 2.0 - 1.0) * DEATH_SCATTER_SPEED;
+            if let Some(entity) = self.entities.get_mut(id) {
+                entity.velocity = scatter + Vec3::Y * 3.0;
+            }
+        }
+        self.outbox.push((client, ServerMessage::Died { position: feet, kept_inventory }));
+    }
+
+    /// Brings a dead player back at their bed, or at spawn if they have none, with full
+    /// health
+    fn respawn(&mut self, client: ClientId) {
+        let Some(session) = self.sessions.get_mut(&client) else { return };
+        if !session.dead {
+            return;
+        }
+        session.dead = false;
+        session.health = PLAYER_MAX_HEALTH;
+        session.hurt_timer = 0.0;
+        session.hunger = Hunger::new();
+        let food = session.food();
+        // A bed in an unloaded chunk is assumed to still be there
+        if let Some(bed) = session.spawn.filter(|b| self.chunks.get_block(b.0, b.1, b.2).is_some_and(|b| b != BlockType::Bed)) {
+            debug!("{}'s bed at {:?} is gone", session.name, bed);
+            session.spawn = None;
+            self.outbox.push((client, ServerMessage::Chat { text: "Your bed was missing".to_string() }));
+        }
+        let position = Self::spawn_position(session);
+        session.position = position;
+        self.interest.set_position(client, position);
+        self.outbox.push((client, ServerMessage::CorrectPosition { position }));
+        self.outbox.push((client, ServerMessage::Health { health: PLAYER_MAX_HEALTH }));
+        self.outbox.push((client, ServerMessage::Hunger { food }));
+    }
+
+    /// Eats one item of food from the inventory, unless the player is full or not in survival
+    fn eat(&mut self, client: ClientId) {
+        let Some(session) = self.sessions.get_mut(&client) else { return };
+        if !session.gamemode.has_hunger() || session.hunger.food >= MAX_FOOD {
+            return;
+        }
+        let Some(item) = session.inventory.take_one(|item| item.food().is_some()) else {
+            self.outbox.push((client, ServerMessage::Chat { text: "You have nothing to eat".to_string() }));
+            return;
+        };
+        session.hunger.eat(item.food().unwrap_or(0.0));
+        let food = session.food();
+        let inventory = session.inventory;
+        self.outbox.push((client, ServerMessage::Hunger { food }));
+        self.outbox.push((client, ServerMessage::Inventory { inventory }));
+    }
+
+    /// Crafts whatever the grid's recipe makes, if the player has the items for it. The
+    /// product goes into the inventory, or is dropped at their feet if it does not fit.
+    fn craft(&mut self, client: ClientId, grid: &CraftingGrid) {
+        let Some(result) = self.recipes.find(grid).map(|recipe| recipe.result) else {
+            debug!("{:?} tried to craft with a grid no recipe fits", client);
+            return;
+        };
+        let Some(session) = self.sessions.get_mut(&client) else { return };
+        let mut needed = HashMap::new();
+        for item in grid.iter().flatten() {
+            *needed.entry(*item).or_insert(0) += 1;
+        }
+        if needed.iter().any(|(&item, &count)| session.inventory.count(item) < count) {
+            debug!("{} tried to craft {:?} without the items", session.name, result.item);
+            return;
+        }
+        for item in grid.iter().flatten() {
+            session.inventory.take_one(|i| i == *item);
+        }
+        let left = session.inventory.add(result);
+        let (feet, inventory) = (session.position - Vec3::Y * EYE_HEIGHT, session.inventory);
+        self.outbox.push((client, ServerMessage::Inventory { inventory }));
+        if let Some(stack) = left {
+            self.drop_item(feet, stack);
+        }
+    }
+
+    /// Spawns an item drop at `position`
+    fn drop_item(&mut self, position: Vec3, stack: ItemStack) -> EntityId {
+        let id = self.entities.spawn(EntityKind::ItemDrop, position);
+        if let Some(entity) = self.entities.get_mut(id) {
+            entity.item = Some(stack);
+        }
+        id
+    }
+
+    /// Changes a player's gamemode, telling them whether they can now get hungry
+    pub fn set_gamemode(&mut self, client: ClientId, gamemode: Gamemode) {
+        let Some(session) = self.sessions.get_mut(&client) else { return };
+        session.gamemode = gamemode;
+        let food = session.food();
+        self.outbox.push((client, ServerMessage::Hunger { food }));
+    }
+
+    /// Drains survival players' hunger, healing those well fed and starving those empty
+    fn tick_hunger(&mut self) {
+        for (&client, session) in self.sessions.iter_mut().filter(|(_, s)| !s.dead && s.gamemode.has_hunger()) {
+            let before = session.hunger.food;
+            let effect = session.hunger.tick(TICK_DELTA, session.health, PLAYER_MAX_HEALTH);
+            if effect.heal > 0.0 || effect.starve > 0.0 {
+                let health = (session.health + effect.heal).min(PLAYER_MAX_HEALTH);
+                session.health = (health - effect.starve).max(STARVE_MIN_HEALTH.min(health));
+                self.outbox.push((client, ServerMessage::Health { health: session.health }));
+            }
+            if session.hunger.food != before {
+                self.outbox.push((client, ServerMessage::Hunger { food: Some(session.hunger.food) }));
+            }
+        }
+    }
+
+    /// Moves item drops that living players stand next to into their inventories
+    fn collect_items(&mut self) {
+        for (&client, session) in self.sessions.iter_mut().filter(|(_, s)| !s.dead) {
+            let before = session.inventory;
+            let body = PlayerBody::aabb(session.position).center();
+            for id in self.entities.query_radius(body, PICKUP_RADIUS) {
+                let Some(entity) = self.entities.get_mut(id) else { continue };
+                if entity.kind != EntityKind::ItemDrop || entity.age < PICKUP_DELAY {
+                    continue;
+                }
+                let Some(stack) = entity.item else { continue };
+                match session.inventory.add(stack) {
+                    Some(left) => entity.item = Some(left),
+                    None => {
+                        self.entities.despawn(id);
+                    }
+                }
+            }
+            if session.inventory != before {
+                self.outbox.push((client, ServerMessage::Inventory { inventory: session.inventory }));
+            }
+        }
+    }
+
+    /// Blows up the area around `center`, hurting and pushing back anything nearby. TNT in
+    /// the blast is primed with a short random fuse, so explosions chain.
+    pub fn explode(&mut self, center: Vec3, power: f32) {
+        let explosion = Explosion::new(center, power);
+        let reach = Aabb::from_center(center, Vec3::splat(2.0 * power));
+        for id in self.entities.query_aabb(&reach) {
+            if let Some(entity) = self.entities.get_mut(id) {
+                let position = entity.position;
+                entity.damage(explosion.damage_at(position), explosion.knockback_at(position));
+            }
+        }
+        let hurt: Vec<(ClientId, f32)> = self.sessions.iter()
+            .map(|(&client, s)| (client, explosion.damage_at(PlayerBody::aabb(s.position).center())))
+            .filter(|&(_, damage)| damage > 0.0)
+            .collect();
+        for (client, damage) in hurt {
+            self.damage_player(client, damage);
+        }
+
+        let blast = explosion.affect_blocks(&self.chunks);
+        let at = ChunkManager::block_coords(center);
+        self.apply_change(at, blast.change);
+        for block in blast.tnt {
+            let id = self.entities.spawn(EntityKind::PrimedTnt, Vec3::new(block.0 as f32, block.1 as f32, block.2 as f32));
+            if let Some(entity) = self.entities.get_mut(id) {
+                entity.fuse = Some(CHAINED_FUSE_MIN + self.random.stream(random::EXPLOSIONS).next_f32() * CHAINED_FUSE_MIN);
+            }
+        }
+        for client in self.interest.clients_for_block(at) {
+            self.outbox.push((client, ServerMessage::Explosion { position: center, power }));
+        }
+    }
+
+    /// Ticks RANDOM_TICKS_PER_CHUNK random blocks in every loaded chunk
+    fn run_random_ticks(&mut self) {
+        let cs = CHUNK_SIZE as u32;
+        // In a fixed order, so the same seed ticks the same blocks
+        let mut keys: Vec<(i32, i32, i32)> = self.chunks.loaded.keys().copied().collect();
+        keys.sort_unstable();
+        for key in keys {
+            for _ in 0..RANDOM_TICKS_PER_CHUNK {
+                let rng = self.random.stream(random::TICKS);
+                let block = (
+                    key.0 * cs as i32 + rng.below(cs) as i32,
+                    key.1 * cs as i32 + rng.below(cs) as i32,
+                    key.2 * cs as i32 + rng.below(cs) as i32,
+                );
+                if !self.chunks.get_block(block.0, block.1, block.2).is_some_and(|b| b.ticks_randomly()) {
+                    continue;
+                }
+                if let Some(change) = behavior::random_tick(&self.chunks, block, self.random.stream(random::TICKS)) {
+                    self.apply_change(block, change);
+                }
+            }
+        }
+    }
+
+    /// Moves entities between the save and the live world as their chunks load and unload,
+    /// and puts saved signs back into chunks that load
+    fn sync_chunk_entities(&mut self) {
+        for key in self.chunks.drain_loaded() {
+            // Chunks generated again, after unloading or a restart, get back their edits
+            self.restore_edits(key);
+            let edits: Vec<_> = self.journal.edits(key).collect();
+            for (block, block_type) in edits {
+                self.chunks.set_block(block, block_type);
+            }
+            for entity in self.world_save.take_chunk_entities(key) {
+                self.entities.insert(entity);
+            }
+            for saved in self.world_save.chunk_signs(key) {
+                self.set_block(saved.block, BlockType::Sign(saved.facing));
+                if !saved.text.is_empty() {
+                    self.set_sign_text(saved.block, saved.text);
+                }
+            }
+        }
+        for key in self.chunks.drain_unloaded() {
+            let entities = self.entities.take_in_chunk(key);
+            self.world_save.store_chunk_entities(key, entities);
+        }
+        // The IO thread writes them, so a burst of edits is spread over the next ticks
+        // instead of stalling this one
+        let limit = self.world_save.settings.max_region_writes_per_tick;
+        self.world_save.flush_some(limit);
+    }
+}
@@ -0,0 +1,165 @@
+//! World health figures for operators and benchmarks.
+//!
+//! A WorldMetrics snapshot gathers chunk, save and tick counts from a running server. The
+//! dedicated server can dump one to a file at a fixed interval, as JSON or, for a path
+//! ending in `.prom`, in the Prometheus text format read by node_exporter's textfile
+//! collector.
+
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use log::warn;
+
+use crate::engine::time::Instant;
+use crate::game::server::Server;
+
+/// Seconds between dumps unless the operator picks another interval
+pub const DEFAULT_DUMP_INTERVAL: u64 = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WorldMetrics {
+    pub loaded_chunks: usize,
+    /// Chunks being generated
+    pub pending_chunks: usize,
+    /// Generated chunks waiting to be taken in by the next tick
+    pub queued_chunks: usize,
+    /// Chunks with edited blocks waiting for a new mesh
+    pub dirty_chunks: usize,
+    /// Regions changed since they were last handed to the save thread
+    pub dirty_regions: usize,
+    /// Files waiting in the save thread's queue
+    pub pending_writes: usize,
+    pub chunks_generated: u64,
+    /// Average milliseconds spent generating one chunk
+    pub generate_ms: f32,
+    /// Chunks generated per second since the previous snapshot
+    pub generation_rate: f32,
+    /// Chunks read from the save instead of generated
+    pub chunks_from_disk: u64,
+    /// Average milliseconds spent reading one chunk from the save
+    pub disk_ms: f32,
+    /// Chunks brought back from memory soon after they were unloaded
+    pub chunks_reused: u64,
+    pub entities: usize,
+    pub players: usize,
+    pub ticks: u64,
+    pub tps: f32,
+    pub mspt: f32,
+}
+
+impl WorldMetrics {
+    /// Figures as they are now; `generation_rate` is left for the caller to work out
+    pub fn sample(server: &Server) -> Self {
+        let chunks = &server.chunks;
+        let average_ms = |time: Duration, count: u64| if count == 0 { 0.0 } else { time.as_secs_f32() * 1000.0 / count as f32 };
+        Self {
+            loaded_chunks: chunks.loaded.len(),
+            pending_chunks: chunks.pending.len(),
+            queued_chunks: chunks.queued_len(),
+            dirty_chunks: chunks.dirty_len(),
+            dirty_regions: server.world_save.dirty_region_count(),
+            pending_writes: server.world_save.pending_writes(),
+            chunks_generated: chunks.chunks_generated,
+            generate_ms: average_ms(chunks.generate_time, chunks.chunks_generated),
+            generation_rate: 0.0,
+            chunks_from_disk: chunks.chunks_from_disk,
+            disk_ms: average_ms(chunks.disk_time, chunks.chunks_from_disk),
+            chunks_reused: chunks.chunks_reused,
+            entities: server.entities.len(),
+            players: server.sessions().count(),
+            ticks: server.tick_count(),
+            tps: server.metrics.tps(),
+            mspt: server.metrics.mspt(),
+        }
+    }
+
+    /// (name, help, value, whether it only ever grows) of every figure
+    fn fields(&self) -> [(&'static str, &'static str, f64, bool); 17] {
+        [
+            ("loaded_chunks", "Chunks in memory", self.loaded_chunks as f64, false),
+            ("pending_chunks", "Chunks being generated", self.pending_chunks as f64, false),
+            ("queued_chunks", "Generated chunks waiting for the next tick", self.queued_chunks as f64, false),
+            ("dirty_chunks", "Edited chunks waiting for a new mesh", self.dirty_chunks as f64, false),
+            ("dirty_regions", "Changed regions not yet queued for saving", self.dirty_regions as f64, false),
+            ("pending_writes", "Files waiting in the save queue", self.pending_writes as f64, false),
+            ("chunks_generated", "Chunks generated since startup", self.chunks_generated as f64, true),
+            ("generate_ms", "Average milliseconds to generate a chunk", self.generate_ms as f64, false),
+            ("generation_rate", "Chunks generated per second", self.generation_rate as f64, false),
+            ("chunks_from_disk", "Chunks read from the save since startup", self.chunks_from_disk as f64, true),
+            ("disk_ms", "Average milliseconds to read a chunk from the save", self.disk_ms as f64, false),
+            ("chunks_reused", "Chunks reloaded from memory since startup", self.chunks_reused as f64, true),
+            ("entities", "Live entities", self.entities as f64, false),
+            ("players", "Connected players", self.players as f64, false),
+            ("ticks", "Ticks simulated since startup", self.ticks as f64, true),
+            ("tps", "Ticks per second", self.tps as f64, false),
+            ("mspt", "Average milliseconds per tick", self.mspt as f64, false),
+        ]
+    }
+
+    /// One JSON object holding every figure
+    pub fn to_json(&self) -> String {
+        let fields: Vec<String> = self.fields().iter().map(|(name, _, value, _)| format!("\"{}\": {}", name, value)).collect();
+        format!("{{{}}}\n", fields.join(", "))
+    }
+
+    /// The Prometheus text exposition format, each figure prefixed with `psu_`
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        for (name, help, value, counter) in self.fields() {
+            let kind = if counter { "counter" } else { "gauge" };
+            // Writing to a String cannot fail
+            let _ = write!(text, "# HELP psu_{0} {1}\n# TYPE psu_{0} {2}\npsu_{0} {3}\n", name, help, kind, value);
+        }
+        text
+    }
+}
+
+/// Writes a WorldMetrics snapshot to a file every `interval`
+pub struct MetricsDump {
+    path: PathBuf,
+    interval: Duration,
+    /// When the last snapshot was taken and how many chunks had been generated by then
+    last: Option<(Instant, u64)>,
+}
+
+impl MetricsDump {
+    pub fn new(path: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self { path: path.into(), interval, last: None }
+    }
+
+    fn prometheus(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == "prom")
+    }
+
+    /// Writes a snapshot if the interval has passed since the last one. Call it every tick.
+    pub fn poll(&mut self, server: &Server) {
+        let now = Instant::now();
+        if self.last.is_some_and(|(at, _)| now.duration_since(at) < self.interval) {
+            return;
+        }
+        let mut metrics = WorldMetrics::sample(server);
+        if let Some((at, generated)) = self.last {
+            let seconds = now.duration_since(at).as_secs_f32();
+            metrics.generation_rate = (metrics.chunks_generated - generated) as f32 / seconds;
+        }
+        self.last = Some((now, metrics.chunks_generated));
+        if let Err(e) = self.write(&metrics) {
+            warn!("Failed to write metrics to {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Replaces the file by renaming, so readers never see half a snapshot. Unlike saves
+    /// there is no backup to keep and nothing lost by a crash, so it skips the syncing.
+    fn write(&self, metrics: &WorldMetrics) -> io::Result<()> {
+        let text = if Self::prometheus(&self.path) { metrics.to_prometheus() } else { metrics.to_json() };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut temp = self.path.as_os_str().to_owned();
+        temp.push(".tmp");
+        fs::write(&temp, text)?;
+        fs::rename(&temp, &self.path)
+    }
+}
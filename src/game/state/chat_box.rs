@@ -0,0 +1,109 @@
+//! The line chat is typed into, opened with T, or with / to start a command.
+//!
+//! Enter sends the line to the server, which runs it as a command if it starts with /, and
+//! Escape closes the box without sending. Lines too long for the box scroll, showing their
+//! end. Text being composed with an input method is shown underlined at the caret, as in
+//! the sign editor.
+
+use crate::engine::graphics::font::{GLYPH_ADVANCE, GLYPH_HEIGHT, LINE_HEIGHT};
+use crate::engine::graphics::Overlay;
+use crate::engine::input::TextComposition;
+
+/// Longest line that can be typed
+pub const MAX_CHAT_CHARS: usize = 256;
+
+pub struct ChatBox {
+    text: String,
+}
+
+impl ChatBox {
+    /// Opens the box with `text` already typed, e.g. "/" for a command
+    pub fn open(text: &str) -> Self {
+        let mut chat = Self { text: String::new() };
+        chat.type_text(text);
+        chat
+    }
+
+    /// Adds typed characters to the end of the line, dropping control characters and what
+    /// does not fit
+    pub fn type_text(&mut self, text: &str) {
+        let room = MAX_CHAT_CHARS.saturating_sub(self.text.chars().count());
+        self.text.extend(text.chars().filter(|c| !c.is_control()).take(room));
+    }
+
+    /// Types pasted text, its line breaks as spaces since chat is one line
+    pub fn paste(&mut self, text: &str) {
+        self.type_text(&text.lines().collect::<Vec<_>>().join(" "));
+    }
+
+    pub fn backspace(&mut self) {
+        self.text.pop();
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The line to send, None if there is nothing but spaces to send
+    pub fn message(&self) -> Option<String> {
+        let text = self.text.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    /// Top left of the text in the box along the bottom of the screen, and the box's size
+    /// without padding
+    fn layout(screen: (f32, f32), text_scale: f32) -> (f32, f32, f32, f32) {
+        let margin = 8.0 * text_scale;
+        let (_, height) = Overlay::text_size("M", text_scale);
+        let width = (screen.0 - 2.0 * margin).max(0.0);
+        (margin, screen.1 - margin - height, width, height)
+    }
+
+    /// The end of the line that fits in the box with the composed text after it
+    fn visible(&self, screen: (f32, f32), text_scale: f32, composition: &TextComposition) -> &str {
+        let (_, _, width, _) = Self::layout(screen, text_scale);
+        let columns = (width / (GLYPH_ADVANCE as f32 * text_scale)) as usize;
+        // One column is kept for the caret
+        let room = columns.saturating_sub(composition.preedit().chars().count() + 1);
+        let skip = self.text.chars().count().saturating_sub(room);
+        let start = self.text.char_indices().nth(skip).map_or(self.text.len(), |(i, _)| i);
+        &self.text[start..]
+    }
+
+    /// Screen position of the character cell the caret is in, after any composed text
+    /// before it
+    fn caret_cell(&self, screen: (f32, f32), text_scale: f32, composition: &TextComposition) -> (f32, f32) {
+        let (x, y, _, _) = Self::layout(screen, text_scale);
+        let composed = composition.preedit_cursor().unwrap_or(composition.preedit().chars().count());
+        let column = (self.visible(screen, text_scale, composition).chars().count() + composed) as u32;
+        (x + (column * GLYPH_ADVANCE) as f32 * text_scale, y)
+    }
+
+    /// Where the input method should put its candidate window: the caret, as x, y, width
+    /// and height
+    pub fn caret_area(&self, screen: (f32, f32), text_scale: f32, composition: &TextComposition) -> (f32, f32, f32, f32) {
+        let (x, y) = self.caret_cell(screen, text_scale, composition);
+        (x, y, GLYPH_ADVANCE as f32 * text_scale, LINE_HEIGHT as f32 * text_scale)
+    }
+
+    /// The box along the bottom of the screen showing the line and anything being composed,
+    /// with a caret when `caret` is set
+    pub fn draw(&self, overlay: &mut Overlay, screen: (f32, f32), text_scale: f32, caret: bool, composition: &TextComposition) {
+        let (x, y, width, height) = Self::layout(screen, text_scale);
+        let pad = 4.0 * text_scale;
+        overlay.add_rect(x - pad, y - pad, width + 2.0 * pad, height + 2.0 * pad, [0.0, 0.0, 0.0, 0.6]);
+        let visible = self.visible(screen, text_scale, composition);
+        overlay.add_text(x, y, text_scale, [1.0, 1.0, 1.0, 1.0], visible);
+        let preedit = composition.preedit();
+        if !preedit.is_empty() {
+            let left = x + (visible.chars().count() as u32 * GLYPH_ADVANCE) as f32 * text_scale;
+            let (preedit_width, _) = Overlay::text_size(preedit, text_scale);
+            overlay.add_text(left, y, text_scale, [1.0, 0.95, 0.6, 1.0], preedit);
+            overlay.add_rect(left, y + (GLYPH_HEIGHT + 1) as f32 * text_scale, preedit_width, text_scale, [1.0, 0.95, 0.6, 1.0]);
+        }
+        if caret && (preedit.is_empty() || composition.preedit_cursor().is_some()) {
+            let (caret_x, caret_y) = self.caret_cell(screen, text_scale, composition);
+            overlay.add_text(caret_x, caret_y, text_scale, [1.0, 1.0, 1.0, 1.0], "_");
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Client console: lines typed into the game's terminal.
+//!
+//! Commands the client understands itself, such as `/debug`, are handled locally. Anything
+//! else is sent to the server as chat, so server commands work from here too.
+
+use crate::game::command::{CommandError, CommandRegistry, CommandSender, CommandSpec, ParsedCommand, PermissionLevel};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::game::server::StdinConsole;
+
+pub const CLIENT_COMMANDS: [CommandSpec; 20] = [
+    CommandSpec {
+        name: "debug",
+        usage: "/debug <light|chunks|memory|color>",
+        help: "Toggles a debug overlay",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+    CommandSpec {
+        name: "memory",
+        usage: "/memory",
+        help: "Reports memory used by this client's world data",
+        permission: PermissionLevel::Player,
+        min_args: 0,
+    },
+    CommandSpec {
+        name: "shake",
+        usage: "/shake <off|on|percent>",
+        help: "Turns camera shake off or on, or sets how strong it is",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+    CommandSpec {
+        name: "rumble",
+        usage: "/rumble <off|on|percent>",
+        help: "Turns controller rumble off or on, or sets how strong it is",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+    CommandSpec {
+        name: "capture",
+        usage: "/capture",
+        help: "Captures the next frame for a GPU debugger and writes a report for bug reports",
+        permission: PermissionLevel::Player,
+        min_args: 0,
+    },
+    CommandSpec {
+        name: "normalmaps",
+        usage: "/normalmaps <on|off>",
+        help: "Turns normal mapping of block textures on or off",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+    CommandSpec {
+        name: "clouds",
+        usage: "/clouds <off|on|density percent>",
+        help: "Turns the clouds off or on, or sets how much of the sky they cover",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+    CommandSpec {
+        name: "hdr",
+        usage: "/hdr <off|on|paper|peak> [nits]",
+        help: "Turns HDR output off or on, or sets how bright white paper and the brightest highlights show",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+    CommandSpec {
+        name: "antialias",
+        usage: "/antialias <off|taa>",
+        help: "Smooths the jagged edges of distant blocks by blending each frame with the last ones, or stops",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+    CommandSpec {
+        name: "renderscale",
+        usage: "/renderscale <percent|nearest|bilinear|sharpen>...",
+        help: "Draws the world at a percentage of the window's resolution and sets how it is scaled back up",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+    CommandSpec {
+        name: "farterrain",
+        usage: "/farterrain <on|off>",
+        help: "Draws cheap stand-in terrain out to the horizon past the loaded chunks, or stops",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+    CommandSpec {
+        name: "keymode",
+        usage: "/keymode <sprint|sneak|zoom> <hold|toggle>",
+        help: "Makes an action last while its key is held, or switch on and off with each press",
+        permission: PermissionLevel::Player,
+        min_args: 2,
+    },
+    CommandSpec {
+        name: "reducedmotion",
+        usage: "/reducedmotion <on|off>",
+        help: "Turns off camera motion you do not cause yourself, such as shaking",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+    CommandSpec {
+        name: "mouse",
+        usage: "/mouse [sensitivity|x|y|invert|accel|smoothing] [value]",
+        help: "Shows the mouse settings or changes one: sensitivity multipliers, invert on/off, accel off/amount, smoothing ms",
+        permission: PermissionLevel::Player,
+        min_args: 0,
+    },
+    CommandSpec {
+        name: "photo",
+        usage: "/photo [scale]",
+        help: "Enters or leaves photo mode; F2 there saves screenshots at scale times the window size",
+        permission: PermissionLevel::Player,
+        min_args: 0,
+    },
+    CommandSpec {
+        name: "timescale",
+        usage: "/timescale [speed|pause|resume]",
+        help: "Shows or changes how fast a singleplayer world runs, for watching it in slow motion",
+        permission: PermissionLevel::Player,
+        min_args: 0,
+    },
+    CommandSpec {
+        name: "projection",
+        usage: "/projection <perspective|ortho|iso> [height]",
+        help: "Draws the world in perspective, orthographically, or isometrically around you, height blocks tall",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+    CommandSpec {
+        name: "splitscreen",
+        usage: "/splitscreen <on|off>",
+        help: "Adds or removes a second local player, drawn in its own half of the window",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+    CommandSpec {
+        name: "viewdistance",
+        usage: "/viewdistance [chunks]",
+        help: "Shows or changes how many chunks away the world is drawn",
+        permission: PermissionLevel::Player,
+        min_args: 0,
+    },
+    CommandSpec {
+        name: "waypoint",
+        usage: "/waypoint <add|remove|list> [name]",
+        help: "Marks where you stand with a named waypoint, removes one, or lists them",
+        permission: PermissionLevel::Player,
+        min_args: 1,
+    },
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsoleInput {
+    Command(ParsedCommand),
+    /// Chat or a server command
+    Chat(String),
+    /// A client command used wrongly
+    Error(CommandError),
+}
+
+pub struct ClientConsole {
+    registry: CommandRegistry,
+    sender: CommandSender,
+    #[cfg(not(target_arch = "wasm32"))]
+    stdin: StdinConsole,
+}
+
+impl Default for ClientConsole {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientConsole {
+    /// Starts reading the terminal. The browser has no terminal, so there it only parses.
+    pub fn new() -> Self {
+        let mut registry = CommandRegistry::new();
+        for spec in CLIENT_COMMANDS {
+            registry.register(spec);
+        }
+        Self {
+            registry,
+            sender: CommandSender::new("Player", PermissionLevel::Player),
+            #[cfg(not(target_arch = "wasm32"))]
+            stdin: StdinConsole::spawn(),
+        }
+    }
+
+    /// Lines typed since the last call
+    pub fn poll(&self) -> Vec<ConsoleInput> {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.stdin.poll().iter().map(|line| self.parse(line)).collect();
+        #[cfg(target_arch = "wasm32")]
+        Vec::new()
+    }
+
+    pub fn parse(&self, line: &str) -> ConsoleInput {
+        let line = line.trim();
+        if !line.starts_with('/') {
+            return ConsoleInput::Chat(line.to_string());
+        }
+        match self.registry.parse(&self.sender, line) {
+            Ok(command) => ConsoleInput::Command(command),
+            Err(CommandError::Unknown(_)) => ConsoleInput::Chat(line.to_string()),
+            Err(e) => ConsoleInput::Error(e),
+        }
+    }
+}
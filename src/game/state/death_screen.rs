@@ -0,0 +1,58 @@
+//! The screen shown while the player is dead, with a button to respawn.
+//!
+//! Clicking the button or pressing Enter asks the server to respawn; the player stays dead
+//! until it does.
+
+use glam::{Vec2, Vec3};
+
+use crate::engine::graphics::Overlay;
+
+const TITLE: &str = "You died";
+const BUTTON: &str = "Respawn";
+/// Button size in text scale units
+const BUTTON_SIZE: (f32, f32) = (80.0, 16.0);
+
+pub struct DeathScreen {
+    /// Where the player died, at their feet
+    pub position: Vec3,
+    pub kept_inventory: bool,
+}
+
+impl DeathScreen {
+    pub fn new(position: Vec3, kept_inventory: bool) -> Self {
+        Self { position, kept_inventory }
+    }
+
+    /// The respawn button as (x, y, width, height) in pixels
+    fn button(screen: (f32, f32), text_scale: f32) -> (f32, f32, f32, f32) {
+        let (width, height) = (BUTTON_SIZE.0 * text_scale, BUTTON_SIZE.1 * text_scale);
+        ((screen.0 - width) / 2.0, screen.1 / 2.0 + 8.0 * text_scale, width, height)
+    }
+
+    /// Whether `cursor` is over the respawn button
+    pub fn over_button(cursor: Vec2, screen: (f32, f32), text_scale: f32) -> bool {
+        let (x, y, width, height) = Self::button(screen, text_scale);
+        cursor.x >= x && cursor.x <= x + width && cursor.y >= y && cursor.y <= y + height
+    }
+
+    pub fn draw(&self, overlay: &mut Overlay, screen: (f32, f32), text_scale: f32, cursor: Option<Vec2>) {
+        overlay.add_rect(0.0, 0.0, screen.0, screen.1, [0.5, 0.0, 0.0, 0.5]);
+        let title_scale = text_scale * 2.0;
+        let (width, height) = Overlay::text_size(TITLE, title_scale);
+        overlay.add_label((screen.0 - width) / 2.0, screen.1 / 2.0 - height - 16.0 * text_scale, title_scale, [1.0, 1.0, 1.0, 1.0], TITLE);
+        let note = if self.kept_inventory {
+            "You kept your items".to_string()
+        } else {
+            format!("Your items lie at {:.0} {:.0} {:.0}", self.position.x, self.position.y, self.position.z)
+        };
+        let note_scale = text_scale * 0.5;
+        let (width, _) = Overlay::text_size(&note, note_scale);
+        overlay.add_label((screen.0 - width) / 2.0, screen.1 / 2.0 - 8.0 * text_scale, note_scale, [0.9, 0.9, 0.9, 1.0], &note);
+        let (x, y, w, h) = Self::button(screen, text_scale);
+        let hovered = cursor.is_some_and(|cursor| Self::over_button(cursor, screen, text_scale));
+        let color = if hovered { [0.45, 0.45, 0.5, 0.95] } else { [0.3, 0.3, 0.35, 0.95] };
+        overlay.add_rect(x, y, w, h, color);
+        let (label_width, label_height) = Overlay::text_size(BUTTON, text_scale);
+        overlay.add_text(x + (w - label_width) / 2.0, y + (h - label_height) / 2.0, text_scale, [1.0, 1.0, 1.0, 1.0], BUTTON);
+    }
+}
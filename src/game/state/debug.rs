@@ -0,0 +1,187 @@
+//! Debug visualizations drawn over the world, switched on from the client console with
+//! `/debug <name>`.
+
+use std::time::Duration;
+use glam::Vec3;
+
+use crate::engine::graphics::color::srgb_to_linear;
+use crate::engine::graphics::{ColorAudit, Overlay};
+use crate::engine::time::Instant;
+use crate::game::world::chunk::CHUNK_SIZE_F;
+use crate::game::world::chunk_manager::{ChunkManager, ChunkTimings};
+use crate::game::world::light::{LightVolume, MAX_LIGHT};
+use crate::game::world::memory::MemoryUsage;
+
+/// Blocks around the player covered by the light overlay, horizontally and vertically
+pub const LIGHT_OVERLAY_RADIUS: i32 = 24;
+pub const LIGHT_OVERLAY_HEIGHT: i32 = 16;
+/// Light is recomputed at least this often, to pick up block changes
+const LIGHT_REFRESH_SECS: f32 = 0.5;
+
+/// Chunk layers above and below the player shown by the timing heatmap
+pub const HEATMAP_LAYERS: i32 = 1;
+
+/// Names accepted by `/debug`
+pub const DEBUG_OVERLAY_NAMES: [&str; 4] = ["light", "chunks", "memory", "color"];
+
+/// Where the player is, as shown with the FPS and copied with Ctrl+C: the exact position,
+/// the block it is in and that block's chunk
+pub fn coordinates(position: Vec3) -> String {
+    let block = ChunkManager::block_coords(position);
+    let chunk = ChunkManager::chunk_key(block);
+    format!(
+        "XYZ {:.2} {:.2} {:.2}\nBlock {} {} {}\nChunk {} {} {}",
+        position.x, position.y, position.z, block.0, block.1, block.2, chunk.0, chunk.1, chunk.2,
+    )
+}
+
+#[derive(Default)]
+pub struct DebugOverlays {
+    /// Colors the top face of every block by the light level above it
+    pub light: bool,
+    /// Tints chunk bounds by how long they took to generate and mesh
+    pub chunk_timings: bool,
+    /// Shows world memory use in the bottom right corner
+    pub memory: bool,
+    /// Shows gamma test patches and how colors are encoded, see draw_color_audit
+    pub color: bool,
+    light_cache: Option<(Instant, (i32, i32, i32), LightVolume)>,
+}
+
+impl DebugOverlays {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Toggles an overlay by name, returning its new state
+    pub fn toggle(&mut self, name: &str) -> Result<bool, String> {
+        let flag = match name {
+            "light" => &mut self.light,
+            "chunks" => &mut self.chunk_timings,
+            "memory" => &mut self.memory,
+            "color" => &mut self.color,
+            _ => return Err(format!("Unknown overlay '{}', expected one of: {}", name, DEBUG_OVERLAY_NAMES.join(", "))),
+        };
+        *flag = !*flag;
+        Ok(*flag)
+    }
+
+    /// `screen` is the window size and `text_scale` the HUD font pixel size
+    /// Draws the overlays turned on for the frame that began at `now`
+    pub fn draw(&mut self, overlay: &mut Overlay, chunks: &ChunkManager, player_pos: Vec3, screen: (f32, f32), text_scale: f32, now: Instant) {
+        if self.light {
+            self.draw_light(overlay, chunks, player_pos, now);
+        } else {
+            self.light_cache = None;
+        }
+        if self.chunk_timings {
+            draw_chunk_heatmap(overlay, chunks, player_pos, screen, text_scale);
+        }
+        if self.memory {
+            let text = MemoryUsage::measure(chunks).to_string().to_uppercase();
+            let (width, height) = Overlay::text_size(&text, text_scale);
+            overlay.add_label(screen.0 - width - 8.0 * text_scale, screen.1 - height - 8.0 * text_scale,
+                text_scale, [1.0, 1.0, 1.0, 1.0], &text);
+        }
+    }
+
+    fn draw_light(&mut self, overlay: &mut Overlay, chunks: &ChunkManager, player_pos: Vec3, now: Instant) {
+        let center = ChunkManager::block_coords(player_pos);
+        let stale = self.light_cache.as_ref()
+            .is_none_or(|(at, c, _)| *c != center || now.saturating_duration_since(*at).as_secs_f32() > LIGHT_REFRESH_SECS);
+        if stale {
+            let (r, h) = (LIGHT_OVERLAY_RADIUS, LIGHT_OVERLAY_HEIGHT);
+            let volume = LightVolume::compute(chunks,
+                (center.0 - r, center.1 - h, center.2 - r),
+                (center.0 + r, center.1 + h, center.2 + r));
+            self.light_cache = Some((now, center, volume));
+        }
+        let Some((_, _, volume)) = &self.light_cache else { return };
+        for x in volume.min.0..=volume.max.0 {
+            for z in volume.min.2..=volume.max.2 {
+                for y in volume.min.1..volume.max.1 {
+                    let solid = chunks.get_block(x, y, z).is_some_and(|b| b.is_solid());
+                    let Some(level) = volume.get((x, y + 1, z)) else { continue };
+                    if !solid || chunks.get_block(x, y + 1, z).is_some_and(|b| b.is_solid()) {
+                        continue;
+                    }
+                    let top = Vec3::new(x as f32, y as f32 + 0.5, z as f32);
+                    overlay.add_box(top - Vec3::new(0.5, -0.01, 0.5), top + Vec3::new(0.5, 0.03, 0.5), light_color(level));
+                }
+            }
+        }
+    }
+}
+
+/// Size of the gamma test patches, in HUD pixels
+const GAMMA_PATCH: f32 = 48.0;
+/// Steps of the gray ramp
+const RAMP_STEPS: u32 = 16;
+
+/// Gamma test patches in the top right corner, with the audit below them. Of the three
+/// patches, the middle one should look like the striped one from a distance: it is linear
+/// half gray, as bright as stripes half black and half white. The last, which mistakes
+/// sRGB half gray for linear, should look darker. Below, the ramp's steps should look even.
+pub fn draw_color_audit(overlay: &mut Overlay, audit: &ColorAudit, screen: (f32, f32), text_scale: f32) {
+    let margin = 8.0 * text_scale;
+    let (x, y) = (screen.0 - 3.0 * GAMMA_PATCH - margin, margin);
+    for row in 0..GAMMA_PATCH as u32 {
+        let white = if row % 2 == 0 { 1.0 } else { 0.0 };
+        overlay.add_rect(x, y + row as f32, GAMMA_PATCH, 1.0, [white, white, white, 1.0]);
+    }
+    overlay.add_rect(x + GAMMA_PATCH, y, GAMMA_PATCH, GAMMA_PATCH, [0.5, 0.5, 0.5, 1.0]);
+    let wrong = srgb_to_linear(0.5);
+    overlay.add_rect(x + 2.0 * GAMMA_PATCH, y, GAMMA_PATCH, GAMMA_PATCH, [wrong, wrong, wrong, 1.0]);
+    let step = 3.0 * GAMMA_PATCH / RAMP_STEPS as f32;
+    for i in 0..RAMP_STEPS {
+        // Evenly spaced in sRGB, so they look evenly spaced
+        let gray = srgb_to_linear(i as f32 / (RAMP_STEPS - 1) as f32);
+        overlay.add_rect(x + i as f32 * step, y + GAMMA_PATCH, step, GAMMA_PATCH / 2.0, [gray, gray, gray, 1.0]);
+    }
+    let text = audit.to_string().to_uppercase();
+    let (width, _) = Overlay::text_size(&text, text_scale);
+    let color = if audit.is_ok() { [1.0, 1.0, 1.0, 1.0] } else { [1.0, 0.4, 0.4, 1.0] };
+    overlay.add_label(screen.0 - width - margin, y + 1.5 * GAMMA_PATCH + margin, text_scale, color, &text);
+}
+
+/// Tints the chunks around the player's layer from green (cheapest) to red (the slowest
+/// loaded chunk), with a summary in the bottom left corner
+fn draw_chunk_heatmap(overlay: &mut Overlay, chunks: &ChunkManager, player_pos: Vec3, screen: (f32, f32), text_scale: f32) {
+    let layer = ChunkManager::chunk_key_at(player_pos).1;
+    let Some((&slowest_key, slowest)) = chunks.timings.iter().max_by_key(|(_, t)| t.total()) else { return };
+    let max = slowest.total().as_secs_f32().max(f32::EPSILON);
+    for (key, timings) in &chunks.timings {
+        if (key.1 - layer).abs() > HEATMAP_LAYERS {
+            continue;
+        }
+        let t = timings.total().as_secs_f32() / max;
+        let [r, g, b, _] = heat_color(t);
+        let min = Vec3::new(key.0 as f32, key.1 as f32, key.2 as f32) * CHUNK_SIZE_F - Vec3::splat(0.5);
+        let max = min + Vec3::splat(CHUNK_SIZE_F);
+        // Expensive chunks stand out more
+        overlay.add_box(min, max, [r, g, b, 0.05 + 0.25 * t]);
+        overlay.add_box_outline(min, max, [r, g, b, 1.0]);
+    }
+
+    let count = chunks.timings.len() as f32;
+    let average = |f: fn(&ChunkTimings) -> Duration| {
+        chunks.timings.values().map(|t| f(t).as_secs_f32()).sum::<f32>() / count * 1000.0
+    };
+    let text = format!("CHUNKS {}  AVG GEN {:.2}MS  MESH {:.2}MS\nSLOWEST {:?} GEN {:.2}MS  MESH {:.2}MS",
+        chunks.timings.len(), average(|t| t.generate), average(|t| t.mesh),
+        slowest_key, slowest.generate.as_secs_f32() * 1000.0, slowest.mesh.as_secs_f32() * 1000.0);
+    let (_, height) = Overlay::text_size(&text, text_scale);
+    overlay.add_label(8.0 * text_scale, screen.1 - height - 8.0 * text_scale, text_scale, [1.0, 1.0, 1.0, 1.0], &text);
+}
+
+/// Green through yellow to red as `t` goes from 0 to 1
+fn heat_color(t: f32) -> [f32; 4] {
+    let t = t.clamp(0.0, 1.0);
+    [(2.0 * t).min(1.0), (2.0 - 2.0 * t).min(1.0), 0.1, 1.0]
+}
+
+/// Dark blue for no light through to yellow at full light
+fn light_color(level: u8) -> [f32; 4] {
+    let t = level as f32 / MAX_LIGHT as f32;
+    [0.1 + 0.9 * t, 0.1 + 0.8 * t, 0.6 - 0.4 * t, 0.6]
+}
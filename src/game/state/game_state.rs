@@ -1,23 +1,46 @@
 //! Game state management implementation.
 
-use std::time::Instant;
+use crate::engine::time::Instant;
+
+pub const MIN_UI_SCALE: f32 = 0.5;
+pub const MAX_UI_SCALE: f32 = 3.0;
+pub const UI_SCALE_STEP: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameMode {
+    Play,
+    /// Terrain editing; clicks apply the brush instead of attacking
+    Editor,
+}
 
 pub struct GameState {
+    pub mode: GameMode,
     pub show_fps: bool,
-    pub last_fps_print: Instant,
+    /// When the frame rate was last printed, None until the first frame with it shown
+    pub last_fps_print: Option<Instant>,
     pub frame_count: u32,
     pub last_fps: u32,
     pub fullscreen: bool,
+    /// User preference applied on top of the monitor's scale factor
+    pub ui_scale: f32,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GameState {
     pub fn new() -> Self {
         Self {
+            mode: GameMode::Play,
             show_fps: false,
-            last_fps_print: Instant::now(),
+            last_fps_print: None,
             frame_count: 0,
             last_fps: 0,
             fullscreen: false,
+            ui_scale: 1.0,
         }
     }
 
@@ -25,18 +48,23 @@ impl GameState {
         self.frame_count += 1;
     }
 
-    pub fn update_fps_display(&mut self) -> Option<u32> {
+    /// The frames counted in the second up to `now`, the start of this frame, once a second
+    pub fn update_fps_display(&mut self, now: Instant) -> Option<u32> {
         if !self.show_fps {
             return None;
         }
 
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.last_fps_print);
+        let Some(last) = self.last_fps_print else {
+            self.frame_count = 0;
+            self.last_fps_print = Some(now);
+            return None;
+        };
+        let elapsed = now.saturating_duration_since(last);
         
         if elapsed.as_secs_f32() >= 1.0 {
             self.last_fps = self.frame_count;
             self.frame_count = 0;
-            self.last_fps_print = now;
+            self.last_fps_print = Some(now);
             Some(self.last_fps)
         } else {
             None
@@ -48,10 +76,31 @@ impl GameState {
         println!("Show FPS: {}", self.show_fps);
     }
 
+    pub fn toggle_editor(&mut self) {
+        self.mode = match self.mode {
+            GameMode::Play => GameMode::Editor,
+            GameMode::Editor => GameMode::Play,
+        };
+    }
+
     pub fn toggle_fullscreen(&mut self) {
         self.fullscreen = !self.fullscreen;
     }
 
+    pub fn set_ui_scale(&mut self, scale: f32) {
+        self.ui_scale = scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+    }
+
+    pub fn adjust_ui_scale(&mut self, steps: i32) {
+        self.set_ui_scale(self.ui_scale + steps as f32 * UI_SCALE_STEP);
+    }
+
+    /// Physical pixels per UI unit. HUD and text should size themselves in UI units and
+    /// multiply by this, so they keep the same physical size on any monitor.
+    pub fn effective_ui_scale(&self, scale_factor: f64) -> f32 {
+        scale_factor as f32 * self.ui_scale
+    }
+
     pub fn get_fps(&self) -> u32 {
         self.last_fps
     }
@@ -0,0 +1,174 @@
+//! The inventory screen, with the 2x2 crafting grid above the player's slots.
+//!
+//! Clicking a slot lays one of its items into the next free grid cell and clicking a grid
+//! cell takes it back out. Items in the grid stay in the inventory until the result is
+//! clicked, when the server is asked to craft; the pattern stays for crafting again while
+//! the items last.
+
+use glam::Vec2;
+
+use crate::engine::graphics::overlay::{Color, Overlay};
+use crate::game::item::crafting::{CRAFTING_GRID_SIZE, CRAFTING_SLOTS};
+use crate::game::item::inventory::INVENTORY_SLOTS;
+use crate::game::item::{CraftingGrid, Inventory, ItemStack, ItemType, RecipeBook};
+
+const TITLE: &str = "Crafting";
+const HINT: &str = "Click items into the grid, then click the result. E: close";
+const COLUMNS: usize = 9;
+/// Slot size and the gap between slots, in text scale units
+const SLOT: f32 = 12.0;
+const GAP: f32 = 1.0;
+const PADDING: f32 = 6.0;
+
+/// Something on the screen that can be clicked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Slot(usize),
+    Grid(usize),
+    Result,
+}
+
+fn item_color(item: ItemType) -> Color {
+    match item {
+        ItemType::Wheat => [0.85, 0.75, 0.3, 1.0],
+        ItemType::WheatSeeds => [0.4, 0.6, 0.2, 1.0],
+        ItemType::Bread => [0.7, 0.45, 0.2, 1.0],
+        ItemType::Dirt => [0.45, 0.3, 0.2, 1.0],
+        ItemType::Stone => [0.5, 0.5, 0.5, 1.0],
+        ItemType::Flint => [0.2, 0.2, 0.25, 1.0],
+        ItemType::Pickaxe => [0.6, 0.6, 0.7, 1.0],
+        ItemType::Shovel => [0.7, 0.6, 0.5, 1.0],
+        ItemType::Axe => [0.7, 0.5, 0.5, 1.0],
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct InventoryScreen {
+    pub grid: CraftingGrid,
+}
+
+impl InventoryScreen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// What each inventory slot still holds once the items laid in the grid are set aside,
+    /// taking them from the first slots of their kind
+    fn available(&self, inventory: &Inventory) -> [Option<ItemStack>; INVENTORY_SLOTS] {
+        let mut slots = inventory.slots;
+        for item in self.grid.iter().flatten() {
+            if let Some(slot) = slots.iter_mut().find(|s| s.is_some_and(|s| s.item == *item)) {
+                let stack = slot.as_mut().expect("found a filled slot");
+                stack.count -= 1;
+                if stack.count == 0 {
+                    *slot = None;
+                }
+            }
+        }
+        slots
+    }
+
+    /// Takes items back out of the grid, last first, until the inventory has them all
+    pub fn sync(&mut self, inventory: &Inventory) {
+        for cell in (0..CRAFTING_SLOTS).rev() {
+            let Some(item) = self.grid[cell] else { continue };
+            let laid = self.grid.iter().flatten().filter(|&&i| i == item).count() as u32;
+            if laid > inventory.count(item) {
+                self.grid[cell] = None;
+            }
+        }
+    }
+
+    /// Pixel rectangle (x, y, size) of each target
+    fn layout(screen: (f32, f32), text_scale: f32) -> Vec<(Target, f32, f32, f32)> {
+        let step = (SLOT + GAP) * text_scale;
+        let width = COLUMNS as f32 * step - GAP * text_scale;
+        let rows = INVENTORY_SLOTS.div_ceil(COLUMNS);
+        let height = (CRAFTING_GRID_SIZE + rows) as f32 * step + 2.0 * PADDING * text_scale;
+        let left = (screen.0 - width) / 2.0;
+        let top = (screen.1 - height) / 2.0;
+        let mut targets = Vec::new();
+        for cell in 0..CRAFTING_SLOTS {
+            let (column, row) = (cell % CRAFTING_GRID_SIZE, cell / CRAFTING_GRID_SIZE);
+            targets.push((Target::Grid(cell), left + (column + 2) as f32 * step, top + row as f32 * step, SLOT * text_scale));
+        }
+        let result_y = top + (CRAFTING_GRID_SIZE as f32 - 1.0) * step / 2.0;
+        targets.push((Target::Result, left + (CRAFTING_GRID_SIZE + 4) as f32 * step, result_y, SLOT * text_scale));
+        let slots_top = top + CRAFTING_GRID_SIZE as f32 * step + 2.0 * PADDING * text_scale;
+        for slot in 0..INVENTORY_SLOTS {
+            let (column, row) = (slot % COLUMNS, slot / COLUMNS);
+            targets.push((Target::Slot(slot), left + column as f32 * step, slots_top + row as f32 * step, SLOT * text_scale));
+        }
+        targets
+    }
+
+    fn target_at(cursor: Vec2, screen: (f32, f32), text_scale: f32) -> Option<Target> {
+        Self::layout(screen, text_scale).into_iter()
+            .find(|&(_, x, y, size)| cursor.x >= x && cursor.x <= x + size && cursor.y >= y && cursor.y <= y + size)
+            .map(|(target, ..)| target)
+    }
+
+    /// Handles a left click at `cursor`, returning the grid to craft with if the result was
+    /// clicked
+    pub fn click(&mut self, cursor: Vec2, inventory: &Inventory, recipes: &RecipeBook, screen: (f32, f32), text_scale: f32) -> Option<CraftingGrid> {
+        match Self::target_at(cursor, screen, text_scale)? {
+            Target::Slot(slot) => {
+                let stack = self.available(inventory)[slot]?;
+                let cell = self.grid.iter_mut().find(|cell| cell.is_none())?;
+                *cell = Some(stack.item);
+                None
+            }
+            Target::Grid(cell) => {
+                self.grid[cell] = None;
+                None
+            }
+            Target::Result => recipes.find(&self.grid).map(|_| self.grid),
+        }
+    }
+
+    pub fn draw(&self, overlay: &mut Overlay, inventory: &Inventory, recipes: &RecipeBook, screen: (f32, f32), text_scale: f32, cursor: Option<Vec2>) {
+        overlay.add_rect(0.0, 0.0, screen.0, screen.1, [0.0, 0.0, 0.0, 0.5]);
+        let layout = Self::layout(screen, text_scale);
+        let (left, top) = layout.iter().fold((f32::MAX, f32::MAX), |(l, t), &(_, x, y, _)| (l.min(x), t.min(y)));
+        let (right, bottom) = layout.iter().fold((0.0f32, 0.0f32), |(r, b), &(_, x, y, size)| (r.max(x + size), b.max(y + size)));
+        let pad = PADDING * text_scale;
+        overlay.add_rect(left - pad, top - pad, right - left + 2.0 * pad, bottom - top + 2.0 * pad, [0.2, 0.2, 0.22, 0.95]);
+        let (title_width, title_height) = Overlay::text_size(TITLE, text_scale);
+        overlay.add_label((screen.0 - title_width) / 2.0, top - 2.0 * pad - title_height, text_scale, [1.0, 1.0, 1.0, 1.0], TITLE);
+        let available = self.available(inventory);
+        let result = recipes.find(&self.grid).map(|recipe| recipe.result);
+        let hovered = cursor.and_then(|cursor| Self::target_at(cursor, screen, text_scale));
+        let mut tooltip = None;
+        for &(target, x, y, size) in &layout {
+            let stack = match target {
+                Target::Slot(slot) => available[slot],
+                Target::Grid(cell) => self.grid[cell].map(|item| ItemStack::new(item, 1)),
+                Target::Result => result,
+            };
+            let background = if hovered == Some(target) { [0.45, 0.45, 0.5, 1.0] } else { [0.32, 0.32, 0.36, 1.0] };
+            overlay.add_rect(x, y, size, size, background);
+            let Some(stack) = stack else { continue };
+            let inset = 2.0 * text_scale;
+            overlay.add_rect(x + inset, y + inset, size - 2.0 * inset, size - 2.0 * inset, item_color(stack.item));
+            if stack.count > 1 {
+                let count = stack.count.to_string();
+                let (width, height) = Overlay::text_size(&count, text_scale * 0.5);
+                overlay.add_label(x + size - width, y + size - height, text_scale * 0.5, [1.0, 1.0, 1.0, 1.0], &count);
+            }
+            if hovered == Some(target) {
+                tooltip = Some((x, y + size, stack.item.name()));
+            }
+        }
+        // The arrow between the grid and the result
+        let (_, result_x, result_y, size) = layout.iter().find(|(t, ..)| *t == Target::Result).copied().expect("layout has a result slot");
+        let arrow = "->";
+        let (width, height) = Overlay::text_size(arrow, text_scale);
+        overlay.add_text(result_x - (SLOT + GAP) * text_scale + (size - width) / 2.0, result_y + (size - height) / 2.0, text_scale, [1.0, 1.0, 1.0, 1.0], arrow);
+        if let Some((x, y, name)) = tooltip {
+            overlay.add_label(x, y, text_scale * 0.5, [1.0, 1.0, 0.8, 1.0], name);
+        }
+        let hint_scale = text_scale * 0.5;
+        let (hint_width, _) = Overlay::text_size(HINT, hint_scale);
+        overlay.add_label((screen.0 - hint_width) / 2.0, bottom + 2.0 * pad, hint_scale, [0.9, 0.9, 0.9, 1.0], HINT);
+    }
+}
@@ -0,0 +1,171 @@
+//! The fullscreen world map, drawn from the explored columns.
+//!
+//! North (-z) is up and east (+x) to the right. The arrow keys or WASD and dragging with
+//! the mouse pan the view, and the wheel or +/- zoom it. Markers show the player and
+//! anything else worth finding again.
+
+use glam::Vec2;
+use winit::keyboard::KeyCode;
+
+use crate::engine::graphics::overlay::{Color, Overlay};
+use crate::game::world::world_map::WorldMap;
+
+/// Screen pixels per block when the map opens
+const DEFAULT_ZOOM: f32 = 4.0;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 32.0;
+/// Zoom factor of one wheel notch or key press
+const ZOOM_STEP: f32 = 1.25;
+/// Screen pixels the view moves per pan key press
+const PAN_STEP: f32 = 48.0;
+/// Zoomed out, neighbouring blocks are merged until a drawn cell is at least this wide,
+/// so the number of rectangles stays bounded by the screen size
+const MIN_CELL_PIXELS: f32 = 6.0;
+/// Largest square of blocks merged into one cell, the size of a chunk
+const MAX_CELL_BLOCKS: i32 = 16;
+const BACKGROUND: Color = [0.05, 0.05, 0.07, 0.95];
+const HINT: &str = "M: close  Drag/arrows: pan  Wheel/+-: zoom  Right click: waypoint";
+
+/// Something to mark on the map, at block coordinates (x, z)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapMarker {
+    pub position: Vec2,
+    pub label: String,
+    pub color: Color,
+    /// Direction the marker points in, as (x, z), for the player's heading
+    pub heading: Option<Vec2>,
+}
+
+pub struct MapScreen {
+    /// Block coordinates (x, z) shown in the middle of the screen
+    pub center: Vec2,
+    /// Screen pixels per block
+    pub zoom: f32,
+    /// Cursor position the current drag last moved from
+    drag: Option<Vec2>,
+}
+
+impl MapScreen {
+    /// Opens the map centered on `center`
+    pub fn open(center: Vec2) -> Self {
+        Self { center, zoom: DEFAULT_ZOOM, drag: None }
+    }
+
+    /// Zooms in by `steps` notches, or out if negative
+    pub fn zoom_by(&mut self, steps: f32) {
+        self.zoom = (self.zoom * ZOOM_STEP.powf(steps)).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    /// Moves the view so the map follows a drag of `pixels` across the screen
+    pub fn pan(&mut self, pixels: Vec2) {
+        self.center -= pixels / self.zoom;
+    }
+
+    /// Handles a pan or zoom key, returning whether it was one
+    pub fn handle_key(&mut self, keycode: KeyCode) -> bool {
+        let pan = match keycode {
+            KeyCode::ArrowLeft | KeyCode::KeyA => Vec2::X,
+            KeyCode::ArrowRight | KeyCode::KeyD => -Vec2::X,
+            KeyCode::ArrowUp | KeyCode::KeyW => Vec2::Y,
+            KeyCode::ArrowDown | KeyCode::KeyS => -Vec2::Y,
+            KeyCode::Equal | KeyCode::NumpadAdd => {
+                self.zoom_by(1.0);
+                return true;
+            }
+            KeyCode::Minus | KeyCode::NumpadSubtract => {
+                self.zoom_by(-1.0);
+                return true;
+            }
+            _ => return false,
+        };
+        self.pan(pan * PAN_STEP);
+        true
+    }
+
+    pub fn start_drag(&mut self, cursor: Vec2) {
+        self.drag = Some(cursor);
+    }
+
+    pub fn drag_to(&mut self, cursor: Vec2) {
+        if let Some(from) = self.drag.replace(cursor) {
+            self.pan(cursor - from);
+        }
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag = None;
+    }
+
+    /// Block coordinates (x, z) under a screen position
+    pub fn block_at(&self, pixel: Vec2, screen: (f32, f32)) -> Vec2 {
+        (pixel - Vec2::new(screen.0, screen.1) / 2.0) / self.zoom + self.center
+    }
+
+    /// Screen position of block coordinates (x, z)
+    fn to_screen(&self, block: Vec2, screen: (f32, f32)) -> Vec2 {
+        (block - self.center) * self.zoom + Vec2::new(screen.0, screen.1) / 2.0
+    }
+
+    /// Covers the screen with the explored map and the markers on it
+    pub fn draw(&self, overlay: &mut Overlay, map: &WorldMap, markers: &[MapMarker], screen: (f32, f32), text_scale: f32) {
+        overlay.add_rect(0.0, 0.0, screen.0, screen.1, BACKGROUND);
+        // Whole blocks per drawn cell, a power of two so cells line up with chunks
+        let mut step = 1;
+        while (step as f32) * self.zoom < MIN_CELL_PIXELS && step < MAX_CELL_BLOCKS {
+            step *= 2;
+        }
+        let half = Vec2::new(screen.0, screen.1) / (2.0 * self.zoom);
+        let (min, max) = (self.center - half, self.center + half);
+        let first = |v: f32| (v.floor() as i32).div_euclid(step) * step;
+        let size = step as f32 * self.zoom;
+        for z in (first(min.y)..=max.y.ceil() as i32).step_by(step as usize) {
+            // Runs of equal color along a row are drawn as one rectangle
+            let mut run: Option<(i32, [f32; 3])> = None;
+            let flush = |run: Option<(i32, [f32; 3])>, end: i32, overlay: &mut Overlay| {
+                if let Some((start, color)) = run {
+                    // Block n covers n - 0.5 to n + 0.5
+                    let corner = self.to_screen(Vec2::new(start as f32 - 0.5, z as f32 - 0.5), screen);
+                    overlay.add_rect(corner.x, corner.y, (end - start) as f32 * self.zoom, size, [color[0], color[1], color[2], 1.0]);
+                }
+            };
+            let mut x = first(min.x);
+            while x <= max.x.ceil() as i32 {
+                let color = map.cell(x, z).map(|cell| cell.color(map.cell(x, z - step)));
+                match (run, color) {
+                    (Some((_, current)), Some(color)) if current == color => (),
+                    (_, color) => {
+                        flush(run, x, overlay);
+                        run = color.map(|color| (x, color));
+                    }
+                }
+                x += step;
+            }
+            flush(run, x, overlay);
+        }
+        for marker in markers {
+            self.draw_marker(overlay, marker, screen, text_scale);
+        }
+        let title = format!("X {:.0}  Z {:.0}", self.center.x, self.center.y);
+        overlay.add_label(8.0 * text_scale, 8.0 * text_scale, text_scale, [1.0, 1.0, 1.0, 1.0], &title);
+        let hint_scale = text_scale * 0.5;
+        let (hint_width, hint_height) = Overlay::text_size(HINT, hint_scale);
+        overlay.add_label((screen.0 - hint_width) / 2.0, screen.1 - hint_height - 8.0 * text_scale, hint_scale, [0.9, 0.9, 0.9, 1.0], HINT);
+    }
+
+    fn draw_marker(&self, overlay: &mut Overlay, marker: &MapMarker, screen: (f32, f32), text_scale: f32) {
+        let at = self.to_screen(marker.position, screen);
+        let dot = 3.0 * text_scale;
+        overlay.add_rect(at.x - dot, at.y - dot, 2.0 * dot, 2.0 * dot, marker.color);
+        if let Some(heading) = marker.heading.and_then(Vec2::try_normalize) {
+            // A short trail of dots points the way the player faces
+            for i in 1..=3 {
+                let tip = at + heading * (dot * 1.5 * i as f32);
+                let size = dot * 0.5;
+                overlay.add_rect(tip.x - size, tip.y - size, 2.0 * size, 2.0 * size, marker.color);
+            }
+        }
+        let label_scale = text_scale * 0.5;
+        let (width, _) = Overlay::text_size(&marker.label, label_scale);
+        overlay.add_label(at.x - width / 2.0, at.y + 2.0 * dot, label_scale, [1.0, 1.0, 1.0, 1.0], &marker.label);
+    }
+}
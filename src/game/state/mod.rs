@@ -1,5 +1,23 @@
 //! Game state management.
 
+pub mod chat_box;
+pub mod console;
+pub mod death_screen;
+pub mod debug;
 pub mod game_state;
+pub mod inventory_screen;
+pub mod map_screen;
+pub mod photo_mode;
+pub mod server_list;
+pub mod sign_editor;
 
-pub use game_state::GameState;
\ No newline at end of file
+pub use chat_box::ChatBox;
+pub use console::{ClientConsole, ConsoleInput};
+pub use death_screen::DeathScreen;
+pub use debug::DebugOverlays;
+pub use game_state::{GameMode, GameState};
+pub use inventory_screen::InventoryScreen;
+pub use map_screen::{MapMarker, MapScreen};
+pub use photo_mode::PhotoMode;
+pub use server_list::ServerList;
+pub use sign_editor::SignEditor;
\ No newline at end of file
@@ -0,0 +1,71 @@
+//! Photo mode, for taking pictures of the world.
+//!
+//! The world stops where it is and the HUD goes away, and a camera detached from the player
+//! flies around freely. Q and E roll it, the wheel changes its field of view, R puts both
+//! back, and F2 saves a screenshot at `scale` times the window's resolution.
+
+use winit::keyboard::KeyCode;
+
+use crate::engine::input::InputHandler;
+use crate::game::world::camera::Camera;
+use crate::game::world::day_cycle::TimeOfDay;
+
+/// Screenshot scale unless another is asked for
+pub const DEFAULT_SCALE: u32 = 2;
+/// Radians the camera rolls per second while Q or E is held
+const ROLL_SPEED: f32 = 0.8;
+/// FOV factor of one wheel notch
+const FOV_STEP: f32 = 1.1;
+
+pub struct PhotoMode {
+    pub camera: Camera,
+    /// Multiple of the window resolution screenshots are saved at
+    pub scale: u32,
+    /// Time of day when the mode was entered; the sky stays there
+    pub time: TimeOfDay,
+    /// Field of view to go back to on R
+    base_fov: f32,
+    screenshot_requested: bool,
+}
+
+impl PhotoMode {
+    /// Starts with the camera where `camera` is, frozen at `time`
+    pub fn enter(camera: &Camera, time: TimeOfDay, scale: u32) -> Self {
+        let mut camera = camera.clone();
+        camera.roll = 0.0;
+        Self { base_fov: camera.fov, camera, scale, time, screenshot_requested: false }
+    }
+
+    /// Flies and turns the camera from the player's input; call once per frame
+    pub fn update(&mut self, input: &mut InputHandler, delta_time: f32) {
+        // Zoomed in, the mouse turns the camera slower, as it does for the zoom key
+        input.mouse.look_scale = self.camera.fov / self.base_fov;
+        input.update_look(&mut self.camera, delta_time);
+        input.apply_movement(&mut self.camera);
+        let roll = input.is_key_pressed(KeyCode::KeyE) as i32 - input.is_key_pressed(KeyCode::KeyQ) as i32;
+        self.camera.roll += roll as f32 * ROLL_SPEED * delta_time;
+    }
+
+    /// Narrows the view by `steps` wheel notches, or widens it if negative
+    pub fn zoom_by(&mut self, steps: f32) {
+        self.camera.zoom(FOV_STEP.powf(-steps));
+    }
+
+    /// Handles a key press, returning whether it was used
+    pub fn handle_key(&mut self, keycode: KeyCode) -> bool {
+        match keycode {
+            KeyCode::KeyR => {
+                self.camera.roll = 0.0;
+                self.camera.fov = self.base_fov;
+            }
+            KeyCode::F2 => self.screenshot_requested = true,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Whether a screenshot was asked for since the last call
+    pub fn take_screenshot_request(&mut self) -> bool {
+        std::mem::take(&mut self.screenshot_requested)
+    }
+}
@@ -0,0 +1,82 @@
+//! Multiplayer server list state.
+
+use std::net::{SocketAddr, ToSocketAddrs};
+use log::warn;
+
+use crate::engine::net::{LanDiscovery, DEFAULT_GAME_PORT};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerEntry {
+    pub name: String,
+    pub address: SocketAddr,
+}
+
+/// Backing state for the multiplayer menu: LAN servers found automatically plus the
+/// manually typed "direct connect" address
+pub struct ServerList {
+    discovery: Option<LanDiscovery>,
+    pub direct_connect: String,
+}
+
+impl Default for ServerList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServerList {
+    pub fn new() -> Self {
+        // Another client on this machine may already own the discovery port
+        let discovery = LanDiscovery::new()
+            .map_err(|e| warn!("LAN discovery unavailable: {}", e))
+            .ok();
+        Self {
+            discovery,
+            direct_connect: String::new(),
+        }
+    }
+
+    pub fn poll(&mut self) {
+        if let Some(discovery) = &mut self.discovery {
+            discovery.poll();
+        }
+    }
+
+    /// LAN servers currently visible, sorted by name
+    pub fn lan_servers(&self) -> Vec<ServerEntry> {
+        let mut entries: Vec<ServerEntry> = self.discovery.iter()
+            .flat_map(|d| d.servers())
+            .map(|s| ServerEntry { name: s.name.clone(), address: s.address })
+            .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name).then(a.address.cmp(&b.address)));
+        entries
+    }
+
+    pub fn push_direct_connect_char(&mut self, c: char) {
+        if !c.is_control() {
+            self.direct_connect.push(c);
+        }
+    }
+
+    pub fn pop_direct_connect_char(&mut self) {
+        self.direct_connect.pop();
+    }
+
+    /// Resolves the direct connect field as `host` or `host:port`. This may block on DNS, so
+    /// only call it when the player actually connects.
+    pub fn direct_connect_address(&self) -> Result<SocketAddr, String> {
+        let text = self.direct_connect.trim();
+        if text.is_empty() {
+            return Err("No address entered".into());
+        }
+        let with_port = if text.parse::<SocketAddr>().is_ok() || text.rsplit_once(':').is_some_and(|(_, p)| p.parse::<u16>().is_ok()) {
+            text.to_string()
+        } else {
+            format!("{}:{}", text, DEFAULT_GAME_PORT)
+        };
+        with_port.to_socket_addrs()
+            .map_err(|e| format!("Invalid address '{}': {}", text, e))?
+            .next()
+            .ok_or_else(|| format!("Could not resolve '{}'", text))
+    }
+}
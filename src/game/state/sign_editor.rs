@@ -0,0 +1,122 @@
+//! The small text editor shown after placing or using a sign.
+//!
+//! Typing always goes to the end of the last line. Enter starts a new line, or finishes on
+//! the last one, and Escape finishes early; the caller then sends the text to the server.
+//! Text being composed with an input method is shown underlined where it will go.
+
+use crate::engine::graphics::font::{GLYPH_ADVANCE, GLYPH_HEIGHT, LINE_HEIGHT};
+use crate::engine::graphics::Overlay;
+use crate::engine::input::TextComposition;
+use crate::game::world::sign::{self, MAX_SIGN_LINES, MAX_SIGN_LINE_CHARS};
+
+const HINT: &str = "Enter: next line  Esc: done";
+
+pub struct SignEditor {
+    pub block: (i32, i32, i32),
+    /// Never empty; the last line is the one being typed
+    lines: Vec<String>,
+}
+
+impl SignEditor {
+    /// Starts editing with the sign's current text
+    pub fn open(block: (i32, i32, i32), text: &str) -> Self {
+        let mut lines: Vec<String> = sign::sanitize(text).lines().map(str::to_string).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        Self { block, lines }
+    }
+
+    /// Adds typed characters to the current line, dropping what does not fit
+    pub fn type_text(&mut self, text: &str) {
+        let line = self.lines.last_mut().expect("editor always has a line");
+        for c in text.chars().filter(|c| sign::is_sign_char(*c)) {
+            if line.chars().count() < MAX_SIGN_LINE_CHARS {
+                line.push(c);
+            }
+        }
+    }
+
+    /// Types pasted text, line breaks and all, dropping what does not fit
+    pub fn paste(&mut self, text: &str) {
+        for (i, line) in text.lines().enumerate() {
+            if i > 0 && !self.new_line() {
+                break;
+            }
+            self.type_text(line);
+        }
+    }
+
+    /// Deletes the last character, going back to the previous line once this one is empty
+    pub fn backspace(&mut self) {
+        let line = self.lines.last_mut().expect("editor always has a line");
+        if line.pop().is_none() && self.lines.len() > 1 {
+            self.lines.pop();
+        }
+    }
+
+    /// Moves on to a new line, returning false if the sign is already full
+    pub fn new_line(&mut self) -> bool {
+        if self.lines.len() >= MAX_SIGN_LINES {
+            return false;
+        }
+        self.lines.push(String::new());
+        true
+    }
+
+    /// The text as it will appear on the sign, without trailing blank lines
+    pub fn text(&self) -> String {
+        self.lines.join("\n").trim_end_matches('\n').to_string()
+    }
+
+    /// Top left of the text in the panel, and the panel's size without padding
+    fn layout(screen: (f32, f32), text_scale: f32) -> (f32, f32, f32, f32) {
+        let widest = "M".repeat(MAX_SIGN_LINE_CHARS);
+        let (width, _) = Overlay::text_size(&widest, text_scale);
+        let (_, height) = Overlay::text_size(&"M\n".repeat(MAX_SIGN_LINES), text_scale);
+        ((screen.0 - width) / 2.0, (screen.1 - height) / 2.0, width, height)
+    }
+
+    /// Screen position of the character cell the caret is in, after any composed text
+    /// before it
+    fn caret_cell(&self, screen: (f32, f32), text_scale: f32, composition: &TextComposition) -> (f32, f32) {
+        let (x, y, _, _) = Self::layout(screen, text_scale);
+        let line = self.lines.last().expect("editor always has a line");
+        let composed = composition.preedit_cursor().unwrap_or(composition.preedit().chars().count());
+        let column = (line.chars().count() + composed) as u32;
+        let row = (self.lines.len() - 1) as u32;
+        (x + (column * GLYPH_ADVANCE) as f32 * text_scale, y + (row * LINE_HEIGHT) as f32 * text_scale)
+    }
+
+    /// Where the input method should put its candidate window: the caret, as x, y, width
+    /// and height
+    pub fn caret_area(&self, screen: (f32, f32), text_scale: f32, composition: &TextComposition) -> (f32, f32, f32, f32) {
+        let (x, y) = self.caret_cell(screen, text_scale, composition);
+        (x, y, GLYPH_ADVANCE as f32 * text_scale, LINE_HEIGHT as f32 * text_scale)
+    }
+
+    /// A panel in the middle of the screen showing the text and anything being composed,
+    /// with a caret when `caret` is set
+    pub fn draw(&self, overlay: &mut Overlay, screen: (f32, f32), text_scale: f32, caret: bool, composition: &TextComposition) {
+        let (x, y, width, height) = Self::layout(screen, text_scale);
+        let pad = 4.0 * text_scale;
+        overlay.add_rect(x - pad, y - pad, width + 2.0 * pad, height + 2.0 * pad, [0.45, 0.3, 0.15, 0.9]);
+        overlay.add_text(x, y, text_scale, [1.0, 1.0, 1.0, 1.0], &self.lines.join("\n"));
+        let preedit = composition.preedit();
+        if !preedit.is_empty() {
+            let line = self.lines.last().expect("editor always has a line");
+            let row = (self.lines.len() - 1) as u32;
+            let left = x + (line.chars().count() as u32 * GLYPH_ADVANCE) as f32 * text_scale;
+            let top = y + (row * LINE_HEIGHT) as f32 * text_scale;
+            let (preedit_width, _) = Overlay::text_size(preedit, text_scale);
+            overlay.add_text(left, top, text_scale, [1.0, 0.95, 0.6, 1.0], preedit);
+            overlay.add_rect(left, top + (GLYPH_HEIGHT + 1) as f32 * text_scale, preedit_width, text_scale, [1.0, 0.95, 0.6, 1.0]);
+        }
+        if caret && (preedit.is_empty() || composition.preedit_cursor().is_some()) {
+            let (caret_x, caret_y) = self.caret_cell(screen, text_scale, composition);
+            overlay.add_text(caret_x, caret_y, text_scale, [1.0, 1.0, 1.0, 1.0], "_");
+        }
+        let (hint_width, _) = Overlay::text_size(HINT, text_scale * 0.5);
+        overlay.add_label((screen.0 - hint_width) / 2.0, y + height + 2.0 * pad, text_scale * 0.5, [0.9, 0.9, 0.9, 1.0], HINT);
+    }
+}
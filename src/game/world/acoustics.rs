@@ -0,0 +1,77 @@
+//! What the world around the listener does to sounds: the blocks in the way of each one,
+//! and the echo of the space they stand in.
+//!
+//! The space is judged from the air the listener could walk through. It is flood filled
+//! from their block until it either climbs up to open sky or runs out of air. Air that
+//! never reaches the sky is enclosed, and the more of it there is the longer the echo.
+
+use std::collections::{HashSet, VecDeque};
+use glam::Vec3;
+
+use crate::engine::audio::Reverb;
+use crate::game::world::chunk_manager::ChunkManager;
+use crate::game::world::raycast::VoxelWalk;
+
+type BlockPos = (i32, i32, i32);
+
+/// Blocks above the listener the air has to climb to count as reaching the sky
+pub const SKY_HEIGHT: i32 = 12;
+/// Air blocks filled before stopping; a space this big counts as the largest cave
+pub const MAX_AIR_VOLUME: usize = 6000;
+/// Enclosed air below this doesn't echo, such as a hole dug into a hillside
+const MIN_ROOM_VOLUME: usize = 24;
+/// Enclosed air below this echoes like a room
+const MIN_CAVE_VOLUME: usize = 400;
+/// Enclosed air below this echoes like a cave; more, or more than can be filled, like a
+/// large one
+const MIN_LARGE_CAVE_VOLUME: usize = 2500;
+
+/// Solid blocks between a sound and the listener, ignoring the blocks they are in
+pub fn occluding_blocks(chunk_manager: &ChunkManager, source: Vec3, listener: Vec3) -> u32 {
+    let Some(walk) = VoxelWalk::new(source, listener - source) else { return 0 };
+    let (from, to) = (ChunkManager::block_coords(source), ChunkManager::block_coords(listener));
+    let distance = source.distance(listener);
+    walk.take_while(|step| step.distance <= distance && step.block != to)
+        .filter(|step| step.block != from)
+        .filter(|step| chunk_manager.get_block(step.block.0, step.block.1, step.block.2).is_some_and(|block| block.collides()))
+        .count() as u32
+}
+
+/// The echo around a listener standing at `listener`
+pub fn reverb_at(chunk_manager: &ChunkManager, listener: Vec3) -> Reverb {
+    match enclosed_air(chunk_manager, ChunkManager::block_coords(listener)) {
+        None => Reverb::None,
+        Some(volume) if volume < MIN_ROOM_VOLUME => Reverb::None,
+        Some(volume) if volume < MIN_CAVE_VOLUME => Reverb::Room,
+        Some(volume) if volume < MIN_LARGE_CAVE_VOLUME => Reverb::Cave,
+        Some(_) => Reverb::LargeCave,
+    }
+}
+
+/// How many air blocks are connected to `start`, up to MAX_AIR_VOLUME, or None if they
+/// reach SKY_HEIGHT above it. Blocks that don't collide count as air; unloaded ones as
+/// walls.
+pub fn enclosed_air(chunk_manager: &ChunkManager, start: BlockPos) -> Option<usize> {
+    let is_air = |block: BlockPos| chunk_manager.get_block(block.0, block.1, block.2).is_some_and(|b| !b.collides());
+    if !is_air(start) {
+        return Some(0);
+    }
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(block) = queue.pop_front() {
+        if block.1 >= start.1 + SKY_HEIGHT {
+            return None;
+        }
+        if seen.len() >= MAX_AIR_VOLUME {
+            break;
+        }
+        let (x, y, z) = block;
+        for next in [(x + 1, y, z), (x - 1, y, z), (x, y + 1, z), (x, y - 1, z), (x, y, z + 1), (x, y, z - 1)] {
+            if !seen.contains(&next) && is_air(next) {
+                seen.insert(next);
+                queue.push_back(next);
+            }
+        }
+    }
+    Some(seen.len())
+}
@@ -1,45 +1,317 @@
 use winit::application::ApplicationHandler;
 use winit::event::WindowEvent;
-use winit::event_loop::ActiveEventLoop;
+use winit::event_loop::{ActiveEventLoop, ControlFlow};
 use winit::window::{Window, WindowId};
 use winit::event::DeviceEvent;
-use log::{error, warn};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::engine::time::{Clock, Instant};
+use log::{error, info, warn};
 
+use crate::engine::audio::{AudioSystem, MusicConfig, MusicManager};
 use crate::engine::window::WindowManager;
-use crate::engine::graphics::{renderer::Renderer, texture::Texture};
+use crate::engine::math::Frustum;
+use crate::engine::input::{Action, ActivationMode, Clipboard, HapticEvent, Haptics};
+#[cfg(not(all(target_arch = "wasm32", feature = "web")))]
+use crate::engine::assets::ResourcePacks;
+use crate::engine::graphics::{capture::{self, FrameInfo}, renderer::{Renderer, Sky, View, Viewport}, screenshot, texture::Texture, FrameCapture, Overlay, ParticleSystem, PickTarget};
+use crate::engine::graphics::clouds::Clouds;
+use crate::engine::graphics::color;
+use crate::engine::graphics::taa::AntiAliasing;
+use crate::engine::graphics::normal_map::{self, decode_normal_atlas, normal_map_path, NormalAtlas};
+use crate::game::entity::EntityKind;
+use crate::game::world::acoustics;
+use crate::game::world::camera::{Camera, Projection, DEFAULT_FAR, DEFAULT_ORTHO_HEIGHT, MAX_ORTHO_HEIGHT, MIN_ORTHO_HEIGHT};
+use crate::game::world::chunk::{BlockType, CHUNK_SIZE_F};
+use crate::game::world::camera_shake::{CameraShake, LANDING_SHAKE_SPEED};
+use crate::game::world::raycast::{self, RaycastHit};
+use crate::game::world::weather::Wetness;
 use crate::game::world::chunk_manager::ChunkManager;
-use crate::game::state::GameState;
-use crate::game::player::Player;
+use crate::game::world::day_cycle::TimeOfDay;
+use crate::game::world::decals::{self, Decals};
+use crate::game::world::memory::MemoryUsage;
+use crate::game::world::sign;
+use crate::game::world::waypoint::Waypoints;
+use crate::game::world::world_map::WorldMap;
+use crate::game::world::far_terrain::{self, FarTerrain};
+use crate::game::world::worldgen::WorldGen;
+use crate::game::state::{ChatBox, ClientConsole, ConsoleInput, DeathScreen, DebugOverlays, GameMode, GameState, InventoryScreen, MapMarker, MapScreen, PhotoMode, SignEditor};
+use crate::game::state::{debug, photo_mode};
+use crate::game::item::{Inventory, RecipeBook};
+use crate::game::editor::Editor;
+use crate::game::player::{Interaction, InteractionAction, Player, PLAYER_MAX_HEALTH};
+use crate::game::player::physics::EYE_HEIGHT;
+use crate::game::player::survival;
+use crate::engine::profile::StageTimer;
+use crate::game::net::{ChunkEditCache, ClientEvent, ClientMessage, ClientSession};
+use crate::game::state::ServerList;
+use crate::engine::net::{TcpTransport, Transport};
+use crate::game::save::MeshCache;
+use crate::game::server::IntegratedServer;
+use crate::game::server::server::PLAYER_REACH;
+use crate::game::server::scheduler::TICK_RATE;
+
+pub const WORLD_SAVE_DIR: &str = "saves/world";
+/// Chunks generated around the player in each direction
+#[cfg(not(target_arch = "wasm32"))]
+pub const VIEW_DISTANCE: i32 = 10;
+/// The browser generates chunks on the main thread, so it keeps a much smaller radius
+#[cfg(target_arch = "wasm32")]
+pub const VIEW_DISTANCE: i32 = 4;
+pub const SINGLEPLAYER_NAME: &str = "Player";
+/// Where the chunk edits sent by each server joined are kept, see edit_cache.rs
+pub const SERVER_CACHE_DIR: &str = "saves/servers";
+/// How long `lan` waits for a server on the local network to announce itself
+pub const LAN_SEARCH_TIME: Duration = Duration::from_secs(4);
+/// Waypoint moved to wherever the player last died
+pub const DEATH_WAYPOINT: &str = "Death";
+/// Minimum time between movement updates sent to the server
+pub const MOVE_SEND_INTERVAL: Duration = Duration::from_millis(50);
+/// How often a paused (minimized or hidden) client wakes up to service the network
+pub const PAUSED_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Longest the app waits on chunk generation threads when quitting
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+/// Hostile mobs within this many blocks bring on the tension music
+pub const HOSTILE_MUSIC_RADIUS: f32 = 16.0;
+/// Tiles of the block texture atlas, in atlas order
+pub const BLOCK_TEXTURE_PATHS: [&str; 15] = [
+    "assets/grass_block_top.png",   // 0
+    "assets/grass_block_side.png", // 1
+    "assets/dirt.png",             // 2
+    "assets/stone.png",            // 3
+    "assets/ladder.png",           // 4
+    "assets/door_lower.png",       // 5
+    "assets/door_upper.png",       // 6
+    "assets/wheat_young.png",      // 7
+    "assets/wheat_ripe.png",       // 8
+    "assets/water.png",            // 9
+    "assets/lava.png",             // 10
+    "assets/fire.png",             // 11
+    "assets/tnt.png",              // 12
+    "assets/sign.png",             // 13
+    "assets/bed.png",              // 14
+];
+
+/// GPU objects created once the window exists
+struct GpuContext {
+    instance: wgpu::Instance,
+    surface: wgpu::Surface<'static>,
+    renderer: Renderer,
+    texture: Texture,
+    atlas_helper: crate::engine::graphics::texture::AtlasUVHelper,
+    startup: StageTimer,
+}
+
+/// Filled in by the GPU setup future, which the browser cannot block on
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+type PendingGpu = std::rc::Rc<std::cell::RefCell<Option<GpuContext>>>;
 
 pub struct App {
     window_manager: WindowManager,
     instance: Option<wgpu::Instance>,
+    /// Dropped while the app is suspended, since mobile platforms destroy the native window
+    surface: Option<wgpu::Surface<'static>>,
     renderer: Option<Renderer>,
     player: Player,
     texture: Option<Texture>,
     chunk_manager: ChunkManager,
+    server: Option<IntegratedServer>,
+    client: Option<ClientSession>,
+    /// None until the first movement update goes out
+    last_move_sent: Option<Instant>,
     atlas_helper: Option<crate::engine::graphics::texture::AtlasUVHelper>,
     game_state: GameState,
+    editor: Editor,
+    interaction: Interaction,
+    audio: AudioSystem,
+    music: MusicManager,
+    /// Block whose surroundings the reverb was judged from, None to judge again
+    reverb_block: Option<(i32, i32, i32)>,
+    particles: ParticleSystem,
+    /// Scorch marks on the world's faces, and the cracks of the block being broken
+    decals: Decals,
+    camera_shake: CameraShake,
+    /// Turns off motion the player does not cause themselves, such as camera shake
+    reduced_motion: bool,
+    /// Height in blocks of the isometric view drawn around the player, None for their own
+    /// view
+    isometric: Option<f32>,
+    frame_capture: FrameCapture,
+    /// Real time and the world's simulation time, advanced once a frame
+    clock: Clock,
+    console: ClientConsole,
+    debug_overlays: DebugOverlays,
+    /// Open while the player types the text of a sign
+    sign_editor: Option<SignEditor>,
+    /// Open while the player types a chat line or command
+    chat_box: Option<ChatBox>,
+    /// Where the explored map and waypoints are saved; the browser has no filesystem to
+    /// keep them in
+    map_dir: Option<PathBuf>,
+    world_map: WorldMap,
+    /// Imposters drawn past the loaded chunks
+    far_terrain: FarTerrain,
+    waypoints: Waypoints,
+    /// Open while the fullscreen map is shown
+    map_screen: Option<MapScreen>,
+    /// What the server says we carry, and the recipes to preview crafting with
+    inventory: Inventory,
+    recipes: RecipeBook,
+    /// Block we are partway through breaking, and how far along, from 0 to 1
+    breaking: Option<((i32, i32, i32), f32)>,
+    /// Block our last attack hit, which rumbles when the server says it broke
+    attacked: Option<(i32, i32, i32)>,
+    haptics: Haptics,
+    clipboard: Clipboard,
+    /// Open while the inventory and crafting grid are shown
+    inventory_screen: Option<InventoryScreen>,
+    /// Shown from dying until the server respawns the player
+    death_screen: Option<DeathScreen>,
+    /// Open while taking photos, with the world frozen and the HUD hidden
+    photo_mode: Option<PhotoMode>,
+    /// A second player on this machine, drawn in its own half of the window. Nothing
+    /// steers it until a second input device is mapped to its input handler.
+    second_player: Option<Player>,
+    /// Time of day as last sent by the server, and the simulation time it arrived at
+    world_time: TimeOfDay,
+    time_synced: f64,
+    /// Whether the server says it is raining
+    raining: bool,
+    wetness: Wetness,
+    /// Present until the first world frame has been drawn
+    startup: Option<StageTimer>,
+    /// Rendering is suspended while the window is minimized, hidden or zero-sized
+    paused: bool,
+    /// Set once `shutdown` has run, so the paths that reach it after the first do nothing
+    shut_down: bool,
+    modifiers: winit::keyboard::ModifiersState,
+    /// Last reported cursor position, in physical pixels
+    cursor_position: Option<winit::dpi::PhysicalPosition<f64>>,
+    #[cfg(all(target_arch = "wasm32", feature = "web"))]
+    pending_gpu: PendingGpu,
 }
 
 impl Default for App {
     fn default() -> Self {
+        Self::with_world_dir(WORLD_SAVE_DIR)
+    }
+}
+
+impl App {
+    /// Plays the singleplayer world saved in `world_dir`
+    pub fn with_world_dir(world_dir: impl Into<PathBuf>) -> Self {
+        Self::with_world(world_dir, WorldGen::default())
+    }
+
+    /// Plays the singleplayer world saved in `world_dir`, creating it with `generator` if
+    /// there is none there yet
+    pub fn with_world(world_dir: impl Into<PathBuf>, generator: WorldGen) -> Self {
+        let world_dir = world_dir.into();
+        let mut startup = StageTimer::new("startup");
+        let (server, client) = Self::start_singleplayer(&world_dir, generator);
+        startup.stage("integrated server");
+        let mut chunk_manager = ChunkManager::new(VIEW_DISTANCE);
+        #[cfg(not(target_arch = "wasm32"))]
+        chunk_manager.set_mesh_cache(MeshCache::open(&world_dir));
+        #[cfg(not(target_arch = "wasm32"))]
+        chunk_manager.set_saved_chunks(crate::game::save::SavedChunks::open(&world_dir));
+        let map_dir = (!cfg!(target_arch = "wasm32")).then(|| world_dir.clone());
+        Self::with_session(server, client, chunk_manager, map_dir, startup)
+    }
+
+    /// Joins the server at `address`, given as `host` or `host:port`, or `lan` for the
+    /// first server found on the local network, playing as `name`
+    pub fn join(address: &str, name: &str) -> Self {
+        let mut startup = StageTimer::new("startup");
+        let client = Self::connect_remote(address, name);
+        startup.stage("connect");
+        // The server's world is not ours to cache meshes or chunks of, nor to map
+        Self::with_session(None, client, ChunkManager::new(VIEW_DISTANCE), None, startup)
+    }
+
+    fn with_session(
+        server: Option<IntegratedServer>,
+        client: Option<ClientSession>,
+        chunk_manager: ChunkManager,
+        map_dir: Option<PathBuf>,
+        startup: StageTimer,
+    ) -> Self {
+        let world_map = map_dir.as_deref().map(WorldMap::load).unwrap_or_default();
+        let waypoints = map_dir.as_deref().map(Waypoints::load).unwrap_or_default();
         Self {
             window_manager: WindowManager::new(),
             instance: None,
+            surface: None,
             renderer: None,
             player: Player::new(),
             texture: None,
-            chunk_manager: ChunkManager::new(10), // view_distance = 10 for now
+            chunk_manager,
+            server,
+            client,
+            last_move_sent: None,
             atlas_helper: None,
             game_state: GameState::new(),
+            editor: Editor::new(),
+            interaction: Interaction::new(),
+            audio: AudioSystem::new(),
+            reverb_block: None,
+            music: MusicManager::new(MusicConfig::load(), music_seed()),
+            particles: ParticleSystem::new(),
+            decals: Decals::new(),
+            camera_shake: CameraShake::new(),
+            reduced_motion: false,
+            isometric: None,
+            frame_capture: FrameCapture::new(),
+            clock: Clock::new(),
+            console: ClientConsole::new(),
+            debug_overlays: DebugOverlays::new(),
+            sign_editor: None,
+            chat_box: None,
+            map_dir,
+            world_map,
+            far_terrain: FarTerrain::default(),
+            waypoints,
+            map_screen: None,
+            inventory: Inventory::new(),
+            recipes: RecipeBook::load(),
+            breaking: None,
+            attacked: None,
+            haptics: Haptics::new(),
+            clipboard: Clipboard::new(),
+            inventory_screen: None,
+            death_screen: None,
+            photo_mode: None,
+            second_player: None,
+            world_time: TimeOfDay::default(),
+            time_synced: 0.0,
+            raining: false,
+            wetness: Wetness::default(),
+            startup: Some(startup),
+            paused: false,
+            shut_down: false,
+            modifiers: winit::keyboard::ModifiersState::empty(),
+            cursor_position: None,
+            #[cfg(all(target_arch = "wasm32", feature = "web"))]
+            pending_gpu: PendingGpu::default(),
         }
     }
 }
 
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        let window = event_loop.create_window(Window::default_attributes())
+        if self.window_manager.get_window().is_some() {
+            // Back from the background: the window survived but its surface did not
+            self.window_manager.set_suspended(false);
+            self.recreate_surface();
+            self.window_manager.request_redraw();
+            return;
+        }
+        let attributes = Window::default_attributes();
+        // Let winit insert its canvas into the page body
+        #[cfg(all(target_arch = "wasm32", feature = "web"))]
+        let attributes = winit::platform::web::WindowAttributesExtWebSys::with_append(attributes, true);
+        let window = event_loop.create_window(attributes)
             .map_err(|e| {
                 error!("Failed to create window: {:?}", e);
                 e
@@ -47,46 +319,149 @@ impl ApplicationHandler for App {
                 error!("Failed to create window, exiting");
                 std::process::exit(1);
             });
-        let _size = window.inner_size();
         self.window_manager.set_window(window);
+        let mut startup = self.startup.take().unwrap_or_else(|| StageTimer::new("startup"));
+        startup.stage("window");
+        let window = self.window_manager.window.clone().unwrap();
         // Initialize wgpu
-        pollster::block_on(self.init_wgpu());
+        #[cfg(not(all(target_arch = "wasm32", feature = "web")))]
+        self.install_gpu(pollster::block_on(init_wgpu(window, startup)));
+        // The browser event loop must not block, so finish setup in the background and
+        // pick the result up in about_to_wait
+        #[cfg(all(target_arch = "wasm32", feature = "web"))]
+        {
+            let pending = self.pending_gpu.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                *pending.borrow_mut() = Some(init_wgpu(window, startup).await);
+            });
+        }
     }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
         match event {
             WindowEvent::CloseRequested => {
+                self.shutdown();
                 event_loop.exit();
             },
+            WindowEvent::RedrawRequested if !self.window_manager.is_renderable() => {
+                // Paused; about_to_wait keeps the connection serviced until we are visible again
+            }
             WindowEvent::RedrawRequested => {
-                // Update player movement
-                self.player.update(0.016); // Assuming 60 FPS for now
-                self.chunk_manager.update_chunks(self.player.get_position());
+                self.clock.begin_frame();
+                if let Some(photo) = &mut self.photo_mode {
+                    // Everything but the photo camera stands still
+                    photo.update(&mut self.player.input_handler, self.clock.real_delta());
+                } else if !self.clock.is_paused() {
+                    self.update_play();
+                }
+                self.poll_console();
+                self.update_network();
+                self.update_music();
+                self.haptics.update(self.clock.real_delta());
+                self.update_text_input();
+                self.follow_vehicle();
+                let center = self.photo_mode.as_ref().map_or(self.player.get_position(), |photo| photo.camera.position);
+                self.chunk_manager.update_chunks(center);
+                let current_time = self.current_time();
                 
-                if let Some(renderer) = &self.renderer {
+                if let Some(renderer) = &mut self.renderer {
                     self.chunk_manager.poll_new_chunks(&renderer.device);
+                    self.chunk_manager.remesh_dirty(&renderer.device);
+                    for key in self.chunk_manager.drain_loaded() {
+                        if let Some(chunk) = self.chunk_manager.loaded.get(&key) {
+                            self.world_map.record(key, chunk);
+                        }
+                    }
+                    for key in self.chunk_manager.drain_unloaded() {
+                        self.decals.remove_chunk(key);
+                    }
+                    let time = self.photo_mode.as_ref().map_or(current_time, |photo| photo.time);
+                    renderer.sky = Sky {
+                        color: time.sky_color(),
+                        daylight: time.daylight(),
+                        sun_direction: time.sun_direction(),
+                        wetness: self.wetness.0,
+                        moon_phase: time.moon_phase(),
+                    };
+                    renderer.time = self.clock.frame_time();
+                    // Photo mode freezes the world, particles included
+                    let particle_delta = if self.photo_mode.is_some() { 0.0 } else { self.clock.delta() };
+                    renderer.update_particles(particle_delta, &self.particles.take_spawned());
+                    renderer.set_decals(&self.decals.vertices(self.breaking));
+                    renderer.clouds.time = time.ticks as f64 / TICK_RATE as f64;
+                    renderer.clouds.color = Clouds::tint(time.sky_color(), time.daylight());
+                    let view_distance = self.chunk_manager.view_distance;
+                    if let Some(vertices) = self.far_terrain.update(self.chunk_manager.generator(), center, view_distance) {
+                        renderer.set_far_terrain(&vertices);
+                    }
                 }
-                if let (Some(renderer), Some(texture)) = (&self.renderer, &self.texture) {
-                    if let Some(window) = self.window_manager.get_window() {
-                        let instance = self.instance.as_ref().unwrap_or_else(|| {
-                            error!("No wgpu instance available");
-                            panic!("No wgpu instance available");
-                        });
-                        let surface = instance.create_surface(window).unwrap_or_else(|e| {
-                            error!("Failed to create surface: {:?}", e);
-                            panic!("Failed to create surface: {:?}", e);
-                        });
-                        surface.configure(&renderer.device, &renderer.config);
-                        let chunks: Vec<&crate::game::world::chunk::Chunk> = self.chunk_manager.all_chunks().collect();
-                        if let Err(e) = renderer.render(&surface, self.player.get_camera(), texture, &chunks, &self.chunk_manager) {
-                            error!("Render error: {:?}", e);
+                let size = self.window_manager.get_size().unwrap_or_default();
+                let size = (size.width, size.height);
+                // Photo mode shows only its own camera
+                let split = if self.photo_mode.is_some() { 1 } else { 1 + self.second_player.is_some() as usize };
+                let main_viewport = Viewport::split(size, split, 0);
+                let camera = if let Some(photo) = &self.photo_mode {
+                    photo.camera.clone()
+                } else if self.reduced_motion {
+                    self.view_camera()
+                } else {
+                    self.camera_shake.apply(&self.view_camera())
+                };
+                let overlay = if self.photo_mode.is_some() {
+                    Overlay::new()
+                } else {
+                    let screen = (main_viewport.width as f32, main_viewport.height as f32);
+                    self.build_overlay(screen, &camera.frustum(main_viewport.aspect()))
+                };
+                let second_view = self.second_player.as_ref().filter(|_| split > 1).map(|second| {
+                    let camera = second.view_camera();
+                    let mut overlay = Overlay::new();
+                    self.draw_world(&mut overlay, second.get_position(), &camera.frustum(Viewport::split(size, split, 1).aspect()));
+                    (camera, overlay)
+                });
+                if let (Some(renderer), Some(texture), Some(surface)) = (&mut self.renderer, &self.texture, &self.surface) {
+                    let chunks: Vec<&crate::game::world::chunk::Chunk> = self.chunk_manager.all_chunks().collect();
+                    let capturing = self.frame_capture.begin(&renderer.device);
+                    let mut views = vec![View { camera: &camera, viewport: main_viewport, overlay: &overlay }];
+                    if let Some((camera, overlay)) = &second_view {
+                        views.push(View { camera, viewport: Viewport::split(size, split, 1), overlay });
+                    }
+                    match renderer.render_views(surface, &views, texture, &chunks, &self.chunk_manager) {
+                        Ok(()) => (),
+                        Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                            surface.configure(&renderer.device, &renderer.config);
                         }
+                        Err(e) => error!("Render error: {:?}", e),
+                    }
+                    if capturing {
+                        let frame = FrameInfo {
+                            camera: &camera,
+                            chunks: chunks.len(),
+                            face_instances: chunks.iter().map(|c| c.block_face_instances.len()).sum(),
+                            world_ticks: self.world_time.ticks,
+                        };
+                        match self.frame_capture.end(renderer, &frame) {
+                            Ok(Some(path)) => info!("Captured frame, report written to {}", path.display()),
+                            Ok(None) => (),
+                            Err(e) => error!("Failed to write frame capture report: {}", e),
+                        }
+                    }
+                    if self.photo_mode.as_mut().is_some_and(PhotoMode::take_screenshot_request) {
+                        let scale = self.photo_mode.as_ref().map_or(photo_mode::DEFAULT_SCALE, |photo| photo.scale);
+                        match screenshot::take(renderer, &camera, texture, &chunks, &self.chunk_manager, scale) {
+                            Ok((path, (width, height))) => info!("Saved a {}x{} screenshot to {}", width, height, path.display()),
+                            Err(e) => error!("Failed to save screenshot: {}", e),
+                        }
+                    }
+                    if let Some(mut startup) = self.startup.take() {
+                        startup.stage("first frame");
+                        startup.log_summary();
                     }
                 }
                 
                 // Update game state (FPS tracking)
                 self.game_state.update_frame_count();
-                if let Some(fps) = self.game_state.update_fps_display() {
+                if let Some(fps) = self.game_state.update_fps_display(self.clock.now()) {
                     println!("FPS: {}", fps);
                 }
                 
@@ -95,120 +470,1419 @@ impl ApplicationHandler for App {
             WindowEvent::Resized(physical_size) => {
                 self.resize(physical_size);
             }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // winit follows this with a Resized carrying the new physical size
+                self.window_manager.set_scale_factor(scale_factor);
+                info!("Scale factor changed to {}, UI scale now {:.2}", scale_factor,
+                    self.game_state.effective_ui_scale(scale_factor));
+            }
+            WindowEvent::Occluded(occluded) => {
+                self.window_manager.set_occluded(occluded);
+            }
+            WindowEvent::Ime(ime) => {
+                if let Some(text) = self.player.input_handler.composition.handle(&ime) {
+                    if let Some(editor) = &mut self.sign_editor {
+                        editor.type_text(&text);
+                    } else if let Some(chat) = &mut self.chat_box {
+                        chat.type_text(&text);
+                    }
+                }
+            }
             WindowEvent::KeyboardInput { event, .. } => {
                 if let winit::keyboard::PhysicalKey::Code(keycode) = event.physical_key {
                     let pressed = event.state == winit::event::ElementState::Pressed;
+                    if self.sign_editor.is_some() {
+                        // Releases still reach the player so keys held when the editor opened
+                        // do not stay down
+                        // While composing, keys belong to the input method
+                        if pressed && !self.player.input_handler.composition.is_composing() {
+                            self.edit_sign(keycode, event.text.as_deref());
+                        } else if !pressed {
+                            self.player.handle_keyboard_input(keycode, false);
+                        }
+                        return;
+                    }
+                    if self.chat_box.is_some() {
+                        if pressed && !self.player.input_handler.composition.is_composing() {
+                            self.edit_chat(keycode, event.text.as_deref());
+                        } else if !pressed {
+                            self.player.handle_keyboard_input(keycode, false);
+                        }
+                        return;
+                    }
+                    if self.death_screen.is_some() {
+                        if pressed && matches!(keycode, winit::keyboard::KeyCode::Enter | winit::keyboard::KeyCode::NumpadEnter) {
+                            self.respawn();
+                        } else if !pressed {
+                            self.player.handle_keyboard_input(keycode, false);
+                        }
+                        return;
+                    }
+                    if self.inventory_screen.is_some() {
+                        if pressed && matches!(keycode, winit::keyboard::KeyCode::KeyE | winit::keyboard::KeyCode::Escape) {
+                            self.close_inventory();
+                        } else if !pressed {
+                            self.player.handle_keyboard_input(keycode, false);
+                        }
+                        return;
+                    }
+                    if let Some(map) = &mut self.map_screen {
+                        if pressed && matches!(keycode, winit::keyboard::KeyCode::KeyM | winit::keyboard::KeyCode::Escape) {
+                            self.close_map();
+                        } else if !pressed || !map.handle_key(keycode) {
+                            self.player.handle_keyboard_input(keycode, pressed);
+                        }
+                        return;
+                    }
+                    if let Some(photo) = &mut self.photo_mode {
+                        if pressed && matches!(keycode, winit::keyboard::KeyCode::F8 | winit::keyboard::KeyCode::Escape) {
+                            self.leave_photo_mode();
+                        } else if !pressed || !photo.handle_key(keycode) {
+                            self.player.handle_keyboard_input(keycode, pressed);
+                        }
+                        return;
+                    }
+                    if pressed && keycode == winit::keyboard::KeyCode::F8 {
+                        self.enter_photo_mode(photo_mode::DEFAULT_SCALE);
+                        return;
+                    }
                     if pressed && keycode == winit::keyboard::KeyCode::F3 {
                         self.game_state.toggle_fps_display();
                     }
+                    if pressed && keycode == winit::keyboard::KeyCode::F4 {
+                        self.game_state.toggle_editor();
+                        self.interaction.interrupt();
+                    }
+                    if pressed && self.game_state.mode == GameMode::Editor
+                        && self.editor.handle_key(keycode, self.modifiers, &mut self.chunk_manager, self.player.get_camera()) {
+                        return;
+                    }
+                    // Developer save states, run on the server like any other command
+                    if pressed && matches!(keycode, winit::keyboard::KeyCode::F5 | winit::keyboard::KeyCode::F9) {
+                        let text = if keycode == winit::keyboard::KeyCode::F5 { "/quicksave" } else { "/quickload" };
+                        if let Some(client) = &mut self.client {
+                            client.send(&ClientMessage::Chat { text: text.to_string() });
+                        }
+                    }
+                    if pressed && keycode == winit::keyboard::KeyCode::F6 {
+                        info!("Under cursor: {:?}", self.pick_under_cursor());
+                    }
+                    if pressed && keycode == winit::keyboard::KeyCode::F7 {
+                        self.player.input_handler.toggle_mouse_mode();
+                    }
+                    if pressed && keycode == winit::keyboard::KeyCode::Escape {
+                        if let Some(window) = self.window_manager.get_window() {
+                            self.player.input_handler.release_cursor(window);
+                        }
+                        self.interaction.interrupt();
+                    }
+                    if pressed && self.modifiers.control_key() {
+                        match keycode {
+                            winit::keyboard::KeyCode::Equal => self.game_state.adjust_ui_scale(1),
+                            winit::keyboard::KeyCode::Minus => self.game_state.adjust_ui_scale(-1),
+                            winit::keyboard::KeyCode::Digit0 => self.game_state.set_ui_scale(1.0),
+                            winit::keyboard::KeyCode::KeyC if self.game_state.show_fps => {
+                                self.clipboard.copy(&debug::coordinates(self.player.get_position()));
+                                info!("Copied coordinates");
+                            }
+                            _ => (),
+                        }
+                    } else if pressed && self.game_state.mode == GameMode::Play {
+                        if keycode == winit::keyboard::KeyCode::KeyF {
+                            info!("Movement: {:?}", self.player.toggle_movement_mode());
+                        }
+                        if keycode == winit::keyboard::KeyCode::KeyM {
+                            self.open_map();
+                        }
+                        if keycode == winit::keyboard::KeyCode::KeyE {
+                            self.open_inventory();
+                        }
+                        if matches!(keycode, winit::keyboard::KeyCode::KeyT | winit::keyboard::KeyCode::Slash) && self.client.is_some() {
+                            self.interaction.interrupt();
+                            self.chat_box = Some(ChatBox::open(if keycode == winit::keyboard::KeyCode::Slash { "/" } else { "" }));
+                            return;
+                        }
+                        if keycode == winit::keyboard::KeyCode::KeyR && self.player.food.is_some() {
+                            if let Some(client) = &mut self.client {
+                                client.send(&ClientMessage::Eat);
+                            }
+                        }
+                        let sneak = self.player.input_handler.bindings.binding(Action::Sneak).keys.contains(&keycode);
+                        if sneak && self.player.riding.is_some() {
+                            if let Some(client) = &mut self.client {
+                                client.send(&ClientMessage::Dismount);
+                            }
+                        }
+                        let slot = match keycode {
+                            winit::keyboard::KeyCode::Digit1 => Some(0),
+                            winit::keyboard::KeyCode::Digit2 => Some(1),
+                            winit::keyboard::KeyCode::Digit3 => Some(2),
+                            winit::keyboard::KeyCode::Digit4 => Some(3),
+                            winit::keyboard::KeyCode::Digit5 => Some(4),
+                            winit::keyboard::KeyCode::Digit6 => Some(5),
+                            winit::keyboard::KeyCode::Digit7 => Some(6),
+                            winit::keyboard::KeyCode::Digit8 => Some(7),
+                            winit::keyboard::KeyCode::Digit9 => Some(8),
+                            winit::keyboard::KeyCode::Digit0 => Some(9),
+                            _ => None,
+                        };
+                        if slot.is_some_and(|slot| self.interaction.select(slot)) {
+                            info!("Placing {:?}", self.interaction.selected);
+                        }
+                    }
+                    let sprinting = self.player.input_handler.is_active(Action::Sprint);
                     self.player.handle_keyboard_input(keycode, pressed);
+                    // Held or toggled on, sprinting stops any break or place in progress
+                    if !sprinting && self.player.input_handler.is_active(Action::Sprint) {
+                        self.interaction.interrupt();
+                    }
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::MouseInput { state, button, .. } if self.death_screen.is_some() => {
+                let clicked = state == winit::event::ElementState::Pressed && button == winit::event::MouseButton::Left;
+                let size = self.window_manager.get_size().unwrap_or_default();
+                let text_scale = 2.0 * self.game_state.effective_ui_scale(self.window_manager.scale_factor);
+                let over = self.cursor_position.is_some_and(|cursor| {
+                    DeathScreen::over_button(glam::Vec2::new(cursor.x as f32, cursor.y as f32), (size.width as f32, size.height as f32), text_scale)
+                });
+                if clicked && over {
+                    self.respawn();
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } if self.inventory_screen.is_some() => {
+                let clicked = state == winit::event::ElementState::Pressed && button == winit::event::MouseButton::Left;
+                let (Some(screen), Some(cursor)) = (&mut self.inventory_screen, self.cursor_position) else { return };
+                if !clicked {
+                    return;
+                }
+                let size = self.window_manager.get_size().unwrap_or_default();
+                let text_scale = 2.0 * self.game_state.effective_ui_scale(self.window_manager.scale_factor);
+                let cursor = glam::Vec2::new(cursor.x as f32, cursor.y as f32);
+                let craft = screen.click(cursor, &self.inventory, &self.recipes, (size.width as f32, size.height as f32), text_scale);
+                if let (Some(grid), Some(client)) = (craft, &mut self.client) {
+                    client.send(&ClientMessage::Craft { grid });
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } if self.map_screen.is_some() => {
+                let Some(map) = &mut self.map_screen else { return };
+                let pressed = state == winit::event::ElementState::Pressed;
+                let cursor = self.cursor_position.map(|cursor| glam::Vec2::new(cursor.x as f32, cursor.y as f32));
+                match (button, cursor) {
+                    (winit::event::MouseButton::Left, Some(cursor)) if pressed => map.start_drag(cursor),
+                    (winit::event::MouseButton::Left, _) => map.end_drag(),
+                    (winit::event::MouseButton::Right, Some(cursor)) if pressed => {
+                        let size = self.window_manager.get_size().unwrap_or_default();
+                        let block = map.block_at(cursor, (size.width as f32, size.height as f32));
+                        self.add_map_waypoint(block);
+                    }
+                    _ => (),
+                }
+            }
+            WindowEvent::MouseInput { state, .. } if self.photo_mode.is_some() => {
+                // Nothing to click on, but the cursor may need grabbing again
+                let regrab = state == winit::event::ElementState::Pressed && !self.player.input_handler.is_cursor_grabbed();
+                if let (true, Some(window)) = (regrab, self.window_manager.get_window()) {
+                    self.player.input_handler.grab_cursor(window);
+                }
+            }
+            WindowEvent::MouseInput { state: winit::event::ElementState::Released, button, .. } => {
+                match button {
+                    winit::event::MouseButton::Left => self.interaction.release(InteractionAction::Break),
+                    winit::event::MouseButton::Right => self.interaction.release(InteractionAction::Place),
+                    _ => (),
+                }
+            }
+            WindowEvent::MouseInput { state: winit::event::ElementState::Pressed, button, .. } => {
+                // Clicking back into the window re-grabs the cursor; focus events alone are
+                // unreliable after alt-tab on some platforms
+                if !self.player.input_handler.is_cursor_grabbed() {
+                    if let Some(window) = self.window_manager.get_window() {
+                        self.player.input_handler.grab_cursor(window);
+                    }
+                } else {
+                    match (self.game_state.mode, button) {
+                        (GameMode::Play, winit::event::MouseButton::Left) => {
+                            self.interaction.press(InteractionAction::Break, self.clock.now());
+                        }
+                        (GameMode::Play, winit::event::MouseButton::Right) => {
+                            self.interaction.press(InteractionAction::Place, self.clock.now());
+                        }
+                        (GameMode::Editor, winit::event::MouseButton::Left) => {
+                            self.editor.stroke(&mut self.chunk_manager, self.player.get_camera());
+                        }
+                        (GameMode::Editor, winit::event::MouseButton::Right) => {
+                            self.editor.select(&self.chunk_manager, self.player.get_camera());
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } if self.photo_mode.is_some() => {
+                let step = match delta {
+                    winit::event::MouseScrollDelta::LineDelta(_, y) => y,
+                    winit::event::MouseScrollDelta::PixelDelta(position) => position.y as f32,
+                };
+                if let Some(photo) = &mut self.photo_mode {
+                    // Scrolling up zooms in
+                    photo.zoom_by(step.signum());
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } if self.map_screen.is_some() => {
+                let step = match delta {
+                    winit::event::MouseScrollDelta::LineDelta(_, y) => y,
+                    winit::event::MouseScrollDelta::PixelDelta(position) => position.y as f32,
+                };
+                if let Some(map) = &mut self.map_screen {
+                    // Scrolling up zooms in
+                    map.zoom_by(step.signum());
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } if self.game_state.mode == GameMode::Play => {
+                let step = match delta {
+                    winit::event::MouseScrollDelta::LineDelta(_, y) => y,
+                    winit::event::MouseScrollDelta::PixelDelta(position) => position.y as f32,
+                };
+                // Scrolling down moves to the next block, like the number keys counting up
+                if step != 0.0 {
+                    self.interaction.cycle(if step < 0.0 { 1 } else { -1 });
+                    info!("Placing {:?}", self.interaction.selected);
+                }
+            }
+            WindowEvent::Touch(touch) => {
+                let size = self.window_manager.get_size().unwrap_or_default();
+                let ui_scale = self.game_state.effective_ui_scale(self.window_manager.scale_factor);
+                self.player.handle_touch(&touch, glam::Vec2::new(size.width as f32, size.height as f32), ui_scale);
+            }
+            WindowEvent::PinchGesture { delta, .. } => {
+                // Trackpad pinch: positive delta means the fingers spread
+                let camera = match &mut self.photo_mode {
+                    Some(photo) => &mut photo.camera,
+                    None => self.player.get_camera_mut(),
+                };
+                camera.zoom(1.0 - delta as f32);
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some(position);
+                if let Some(map) = &mut self.map_screen {
+                    map.drag_to(glam::Vec2::new(position.x as f32, position.y as f32));
+                }
+                if let Some(window) = self.window_manager.get_window() {
+                    self.player.handle_cursor_moved(position, window);
                 }
             }
             WindowEvent::Focused(focused) => {
+                if !focused {
+                    // The button release may go to another window
+                    self.interaction.interrupt();
+                }
                 self.player.handle_window_focus(focused, self.window_manager.get_window());
             }
             _ => (),
         }
     }
 
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        #[cfg(all(target_arch = "wasm32", feature = "web"))]
+        if let Some(gpu) = self.pending_gpu.borrow_mut().take() {
+            self.install_gpu(gpu);
+            self.window_manager.request_redraw();
+        }
+        let renderable = self.window_manager.is_renderable();
+        if renderable && self.paused {
+            // Skipped RedrawRequested events break the redraw chain, so restart it
+            self.window_manager.request_redraw();
+        }
+        self.paused = !renderable;
+        if renderable {
+            // Browsers drive redraws from requestAnimationFrame, so only wait for them there
+            #[cfg(not(all(target_arch = "wasm32", feature = "web")))]
+            event_loop.set_control_flow(ControlFlow::Poll);
+            #[cfg(all(target_arch = "wasm32", feature = "web"))]
+            event_loop.set_control_flow(ControlFlow::Wait);
+        } else {
+            // No frames are being drawn, so keep the server connection alive from here
+            self.poll_console();
+            self.update_network();
+            event_loop.set_control_flow(ControlFlow::WaitUntil(Instant::now() + PAUSED_POLL_INTERVAL));
+        }
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        // A backgrounded mobile app can be killed without further notice, so save now
+        self.persist();
+        self.surface = None;
+        self.window_manager.set_suspended(true);
+        self.player.input_handler.touch.reset();
+        info!("Suspended");
+    }
+
     fn device_event(&mut self, _event_loop: &ActiveEventLoop, _device_id: winit::event::DeviceId, event: DeviceEvent) {
         self.player.handle_device_event(event);
     }
+
+    /// Covers the ways out of the event loop that skip CloseRequested
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.shutdown();
+    }
+}
+
+impl Drop for App {
+    /// Runs while unwinding from a panic, so even a crash saves the world
+    fn drop(&mut self) {
+        self.shutdown();
+    }
 }
 
 impl App {
-    async fn init_wgpu(&mut self) {
-        let window = self.window_manager.window.as_ref().unwrap();
-        let size = window.inner_size();
+    /// Starts the local server and connects to it. The browser has no threads or
+    /// filesystem for it, so the web build plays without one.
+    fn start_singleplayer(world_dir: &Path, generator: WorldGen) -> (Option<IntegratedServer>, Option<ClientSession>) {
+        if cfg!(target_arch = "wasm32") {
+            info!("Singleplayer server is unavailable in the browser");
+            return (None, None);
+        }
+        match IntegratedServer::start(world_dir, generator) {
+            Ok((server, connection)) => (Some(server), Some(ClientSession::connect(connection, SINGLEPLAYER_NAME))),
+            Err(e) => {
+                error!("Failed to start integrated server: {}", e);
+                (None, None)
+            }
+        }
+    }
 
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
+    /// Dials a multiplayer server through the server list, which resolves typed addresses
+    /// and finds servers announcing themselves on the LAN
+    fn connect_remote(address: &str, name: &str) -> Option<ClientSession> {
+        if cfg!(target_arch = "wasm32") {
+            info!("Joining servers is unavailable in the browser");
+            return None;
+        }
+        let mut servers = ServerList::new();
+        let address = if address == "lan" {
+            let searching = Instant::now();
+            loop {
+                servers.poll();
+                if let Some(found) = servers.lan_servers().into_iter().next() {
+                    info!("Found {} on the LAN at {}", found.name, found.address);
+                    break found.address;
+                }
+                if searching.elapsed() >= LAN_SEARCH_TIME {
+                    error!("No server announced itself on the LAN");
+                    return None;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        } else {
+            servers.direct_connect = address.to_string();
+            match servers.direct_connect_address() {
+                Ok(address) => address,
+                Err(e) => {
+                    error!("{}", e);
+                    return None;
+                }
+            }
+        };
+        match TcpTransport.connect(&address.to_string()) {
+            Ok(connection) => {
+                info!("Connected to {}", address);
+                let mut client = ClientSession::connect(connection, name);
+                let cache = format!("{}.bin", address.to_string().replace([':', '[', ']'], "_"));
+                client.set_edit_cache(ChunkEditCache::open(Path::new(SERVER_CACHE_DIR).join(cache)));
+                Some(client)
+            }
+            Err(e) => {
+                error!("Failed to connect to {}: {}", address, e);
+                None
+            }
+        }
+    }
 
-        let surface = instance.create_surface(window).unwrap_or_else(|e| {
-            error!("Failed to create surface: {:?}", e);
-            std::process::exit(1);
-        });
-        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::default(),
-            compatible_surface: Some(&surface),
-            force_fallback_adapter: false,
-        }).await.unwrap_or_else(|| {
-            error!("Failed to request adapter");
-            std::process::exit(1);
-        });
+    fn install_gpu(&mut self, gpu: GpuContext) {
+        self.instance = Some(gpu.instance);
+        self.surface = Some(gpu.surface);
+        self.particles.set_offloaded(gpu.renderer.gpu_particles.is_some());
+        let mut renderer = gpu.renderer;
+        renderer.set_decal_atlas(&decals::atlas());
+        self.renderer = Some(renderer);
+        self.texture = Some(gpu.texture);
+        self.atlas_helper = Some(gpu.atlas_helper);
+        self.startup = Some(gpu.startup);
+        self.fit_view();
+    }
 
-        let (device, queue) = adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                label: None,
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
-            },
-            None,
-        ).await.unwrap_or_else(|e| {
-            error!("Failed to request device: {:?}", e);
-            std::process::exit(1);
+    /// Moves the player and everything that plays out around them for one frame
+    fn update_play(&mut self) {
+        let dt = self.clock.delta();
+        self.player.update_look(self.clock.real_delta());
+        // The dead stay where they fell
+        let step = if self.death_screen.is_none() { self.player.update(dt, &self.chunk_manager) } else { None };
+        if let Some(step) = step {
+            let feet = self.player.get_position() - glam::Vec3::Y * EYE_HEIGHT;
+            self.audio.play_at_volume(step.sound, feet, step.volume);
+        }
+        if self.player.body.landing_speed > 0.0 {
+            self.camera_shake.on_landing(self.player.body.landing_speed);
+            if self.player.body.landing_speed > LANDING_SHAKE_SPEED {
+                self.haptics.play(HapticEvent::Landing((self.player.body.landing_speed - LANDING_SHAKE_SPEED) / LANDING_SHAKE_SPEED));
+            }
+        }
+        self.player.update_fov(dt, self.reduced_motion);
+        if let Some(second) = &mut self.second_player {
+            second.update_look(self.clock.real_delta());
+            second.update(dt, &self.chunk_manager);
+            second.update_fov(dt, self.reduced_motion);
+        }
+        self.particles.update(dt);
+        self.decals.update(dt);
+        self.camera_shake.update(dt);
+        self.wetness.update(self.raining, dt);
+        self.run_interactions();
+    }
+
+    /// The player's view as drawn, before camera shake
+    fn view_camera(&self) -> Camera {
+        let camera = self.player.view_camera();
+        match self.isometric {
+            Some(height) => camera.isometric(height),
+            None => camera,
+        }
+    }
+
+    /// The time of day, moved on from the server's last report
+    fn current_time(&self) -> TimeOfDay {
+        self.world_time.after((self.clock.simulation_seconds() - self.time_synced) as f32)
+    }
+
+    /// Whether a screen covers the world and takes the input
+    fn menu_open(&self) -> bool {
+        self.sign_editor.is_some() || self.death_screen.is_some() || self.inventory_screen.is_some() || self.map_screen.is_some()
+    }
+
+    /// Ducks the music under menus and brings on the tension track when hostile mobs come
+    /// close
+    fn update_music(&mut self) {
+        let listener = self.player.get_position();
+        let hostiles_near = self.client.as_ref().is_some_and(|client| {
+            client.entities.iter().any(|e| e.kind.is_hostile() && e.position.distance(listener) <= HOSTILE_MUSIC_RADIUS)
         });
+        self.music.set_hostiles_near(hostiles_near);
+        self.music.set_ducked(self.menu_open());
+        self.music.update(self.clock.real_delta());
+    }
+
+    /// Lets the input method compose while the sign editor or the chat box is open, with
+    /// its candidates shown by the caret
+    fn update_text_input(&mut self) {
+        let Some(window) = self.window_manager.get_window() else { return };
+        let input = &mut self.player.input_handler;
+        input.set_text_input(window, self.sign_editor.is_some() || self.chat_box.is_some());
+        if self.sign_editor.is_some() || self.chat_box.is_some() {
+            let size = self.window_manager.get_size().unwrap_or_default();
+            let viewport = Viewport::split((size.width, size.height), 1 + self.second_player.is_some() as usize, 0);
+            let text_scale = 2.0 * self.game_state.effective_ui_scale(self.window_manager.scale_factor);
+            let screen = (viewport.width as f32, viewport.height as f32);
+            let (x, y, width, height) = match (&self.sign_editor, &self.chat_box) {
+                (Some(editor), _) => editor.caret_area(screen, text_scale, &input.composition),
+                (None, Some(chat)) => chat.caret_area(screen, text_scale, &input.composition),
+                (None, None) => return,
+            };
+            window.set_ime_cursor_area(
+                winit::dpi::PhysicalPosition::new(viewport.x as f32 + x, viewport.y as f32 + y),
+                winit::dpi::PhysicalSize::new(width, height),
+            );
+        }
+    }
+
+    /// Freezes the world and hands the view to a free camera, starting where the player
+    /// looks from
+    fn enter_photo_mode(&mut self, scale: u32) {
+        if self.menu_open() {
+            warn!("Close the open screen before entering photo mode");
+            return;
+        }
+        self.photo_mode = Some(PhotoMode::enter(&self.view_camera(), self.current_time(), scale));
+        self.interaction.interrupt();
+        self.sync_server_clock();
+        info!("Photo mode: WASD/Space/Shift fly, Q/E roll, wheel zoom, R reset, F2 screenshot, F8 leave");
+    }
+
+    fn leave_photo_mode(&mut self) {
+        let Some(photo) = self.photo_mode.take() else { return };
+        // The integrated server stopped with us, so the sky carries on from where it froze
+        self.world_time = photo.time;
+        self.time_synced = self.clock.simulation_seconds();
+        self.sync_server_clock();
+        info!("Left photo mode");
+    }
+
+    /// Has the integrated server tick as fast as the clock runs, stopping while it or photo
+    /// mode is paused
+    fn sync_server_clock(&self) {
+        if let Some(server) = &self.server {
+            server.set_paused(self.photo_mode.is_some() || self.clock.is_paused());
+            server.set_time_scale(self.clock.scale());
+        }
+    }
+
+    /// Changes how far the world loads and is drawn, returning the distance used
+    fn set_view_distance(&mut self, chunks: i32) -> i32 {
+        let chunks = self.chunk_manager.set_view_distance(chunks);
+        self.fit_view();
+        chunks
+    }
 
-        // Configure surface
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps.formats.iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
-
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-            desired_maximum_frame_latency: 2,
+    /// Fits the fog and far plane to the view distance, so the fog closes in where the
+    /// loaded world ends, or where the far terrain does when it is drawn
+    fn fit_view(&mut self) {
+        let distance = self.chunk_manager.view_distance as f32 * CHUNK_SIZE_F;
+        self.player.get_camera_mut().far = distance.max(DEFAULT_FAR);
+        let fog = if self.far_terrain.enabled { distance.max(far_terrain::reach()) } else { distance };
+        if let Some(renderer) = &mut self.renderer {
+            renderer.fog_distance = fog;
+        }
+    }
+
+    /// Handles server messages and reports our movement
+    fn update_network(&mut self) {
+        let Some(client) = &mut self.client else { return };
+        let listener = self.player.get_position();
+        self.audio.set_listener(listener);
+        let listener_block = ChunkManager::block_coords(listener);
+        if self.reverb_block != Some(listener_block) {
+            self.reverb_block = Some(listener_block);
+            self.audio.set_reverb(acoustics::reverb_at(&self.chunk_manager, listener));
+        }
+        for event in client.poll() {
+            match event {
+                ClientEvent::Welcome { position, yaw, pitch } => {
+                    self.player.set_position(position);
+                    let camera = self.player.get_camera_mut();
+                    camera.yaw = yaw;
+                    camera.pitch = pitch;
+                }
+                ClientEvent::Chat(text) => info!("[chat] {}", text),
+                ClientEvent::BlockUpdate { block, block_type } => {
+                    self.chunk_manager.apply_edit(block, block_type);
+                    self.decals.remove_block(block);
+                    if block_type == BlockType::Air && self.attacked == Some(block) {
+                        self.attacked = None;
+                        self.haptics.play(HapticEvent::BlockBreak);
+                    }
+                    // Digging out or walling in the space changes how it echoes
+                    self.reverb_block = None;
+                    let key = ChunkManager::chunk_key(block);
+                    if let Some(chunk) = self.chunk_manager.loaded.get(&key) {
+                        self.world_map.record(key, chunk);
+                    }
+                }
+                ClientEvent::PositionCorrected(position) => self.player.set_position(position),
+                ClientEvent::Sound { name, position } => {
+                    let blocked = acoustics::occluding_blocks(&self.chunk_manager, position, listener);
+                    self.audio.play_occluded(&name, position, 1.0, blocked);
+                }
+                ClientEvent::Health(health) => {
+                    if health < self.player.health {
+                        self.camera_shake.on_damage(self.player.health - health, PLAYER_MAX_HEALTH);
+                        self.haptics.play(HapticEvent::Damage((self.player.health - health) / PLAYER_MAX_HEALTH));
+                    }
+                    self.player.health = health;
+                }
+                ClientEvent::SignText { block, text } => {
+                    self.chunk_manager.set_sign_text(block, text);
+                }
+                ClientEvent::EditSign(block) => {
+                    self.interaction.interrupt();
+                    self.sign_editor = Some(SignEditor::open(block, self.chunk_manager.sign_text(block).unwrap_or("")));
+                }
+                ClientEvent::Time(ticks) => {
+                    self.world_time = TimeOfDay::new(ticks);
+                    self.time_synced = self.clock.simulation_seconds();
+                }
+                ClientEvent::Weather(raining) => {
+                    info!("{}", if raining { "It starts to rain" } else { "The rain stops" });
+                    self.raining = raining;
+                }
+                ClientEvent::Mounted(entity) => {
+                    info!("Riding {:?}", entity);
+                    self.player.mount(entity);
+                }
+                ClientEvent::Explosion { position, power } => {
+                    self.camera_shake.on_explosion(power, position.distance(self.player.get_position()));
+                    let count = (power * 40.0) as usize;
+                    self.particles.burst(position, count, power * 3.0, [1.0, 0.6, 0.2, 1.0]);
+                    self.particles.burst(position, count / 2, power * 1.5, [0.3, 0.3, 0.3, 1.0]);
+                    // The blast's block updates came first, so the crater is open already
+                    self.decals.scorch(&self.chunk_manager, position, power);
+                }
+                ClientEvent::Hunger(food) => self.player.food = food,
+                ClientEvent::BreakProgress { block, progress } => {
+                    self.breaking = (progress > 0.0).then_some((block, progress));
+                }
+                ClientEvent::WorldGen(generator) => {
+                    if *self.chunk_manager.generator() != generator {
+                        info!("World is {} with seed {}", generator.world_type.name(), generator.seed);
+                        self.chunk_manager.set_generator(generator);
+                    }
+                }
+                ClientEvent::Inventory(inventory) => {
+                    self.inventory = inventory;
+                    if let Some(screen) = &mut self.inventory_screen {
+                        screen.sync(&inventory);
+                    }
+                }
+                ClientEvent::Died { position, kept_inventory } => {
+                    info!("You died at {:.0} {:.0} {:.0}", position.x, position.y, position.z);
+                    self.interaction.interrupt();
+                    self.sign_editor = None;
+                    self.map_screen = None;
+                    self.inventory_screen = None;
+                    if let Some(window) = self.window_manager.get_window() {
+                        self.player.input_handler.release_cursor(window);
+                    }
+                    if let Err(e) = self.waypoints.set(DEATH_WAYPOINT, position) {
+                        warn!("{}", e);
+                    }
+                    self.death_screen = Some(DeathScreen::new(position, kept_inventory));
+                }
+                ClientEvent::Disconnected(reason) => warn!("Disconnected from server: {}", reason),
+            }
+        }
+        if self.last_move_sent.is_none_or(|sent| self.clock.since(sent) >= MOVE_SEND_INTERVAL) {
+            self.send_position();
+        }
+    }
+
+    /// Runs client commands typed into the terminal and forwards everything else as chat
+    fn poll_console(&mut self) {
+        for input in self.console.poll() {
+            match input {
+                ConsoleInput::Command(command) => match command.name.as_str() {
+                    "debug" => match self.debug_overlays.toggle(&command.args[0]) {
+                        Ok(on) => {
+                            info!("Debug overlay {}: {}", command.args[0], if on { "on" } else { "off" });
+                            if let (true, "color", Some(renderer), Some(texture)) = (on, command.args[0].as_str(), &self.renderer, &self.texture) {
+                                info!("{}", renderer.color_audit(texture));
+                            }
+                        }
+                        Err(e) => warn!("{}", e),
+                    },
+                    "memory" => info!("{}", MemoryUsage::measure(&self.chunk_manager)),
+                    "shake" => match self.camera_shake.configure(&command.args[0]) {
+                        Ok(()) if self.camera_shake.enabled => info!("Camera shake at {:.0}%", self.camera_shake.intensity * 100.0),
+                        Ok(()) => info!("Camera shake off"),
+                        Err(e) => warn!("{}", e),
+                    },
+                    "rumble" => match self.haptics.configure(&command.args[0]) {
+                        Ok(()) if !self.haptics.is_supported() => info!("No controller that can rumble; rumble set for when there is"),
+                        Ok(()) if self.haptics.enabled => info!("Rumble at {:.0}%", self.haptics.intensity * 100.0),
+                        Ok(()) => info!("Rumble off"),
+                        Err(e) => warn!("{}", e),
+                    },
+                    "normalmaps" => match (command.args[0].as_str(), &mut self.renderer) {
+                        ("on" | "off", Some(renderer)) => {
+                            renderer.set_normal_maps(command.args[0] == "on");
+                            info!("Normal maps {}", command.args[0]);
+                        }
+                        ("on" | "off", None) => warn!("No renderer yet"),
+                        (other, _) => warn!("expected on or off, got {}", other),
+                    },
+                    "clouds" => match &mut self.renderer {
+                        Some(renderer) => match renderer.clouds.configure(&command.args[0]) {
+                            Ok(()) if renderer.clouds.enabled => info!("Clouds at {:.0}% density", renderer.clouds.density * 100.0),
+                            Ok(()) => info!("Clouds off"),
+                            Err(e) => warn!("{}", e),
+                        },
+                        None => warn!("No renderer yet"),
+                    },
+                    "hdr" => match (&mut self.renderer, &self.surface) {
+                        (Some(renderer), Some(surface)) => {
+                            let mut hdr = renderer.hdr;
+                            match hdr.configure(&command.args) {
+                                Ok(()) => {
+                                    renderer.set_hdr(hdr, surface);
+                                    if renderer.hdr_output() {
+                                        info!("HDR output, white paper at {:.0} nits, highlights up to {:.0}", hdr.paper_white, hdr.peak);
+                                    } else if hdr.enabled {
+                                        info!("HDR on, but the display offers no HDR format; tonemapping to SDR");
+                                    } else {
+                                        info!("HDR off");
+                                    }
+                                }
+                                Err(e) => warn!("{}", e),
+                            }
+                        }
+                        _ => warn!("No renderer yet"),
+                    },
+                    "antialias" => match (AntiAliasing::from_name(&command.args[0]), &mut self.renderer) {
+                        (Some(mode), Some(renderer)) => {
+                            renderer.set_anti_aliasing(mode);
+                            info!("Anti-aliasing {}", mode.name());
+                        }
+                        (Some(_), None) => warn!("No renderer yet"),
+                        (None, _) => warn!("expected off or taa, got {}", command.args[0]),
+                    },
+                    "renderscale" => match &mut self.renderer {
+                        Some(renderer) => {
+                            let mut upscale = renderer.upscale;
+                            match upscale.configure(&command.args) {
+                                Ok(()) => {
+                                    renderer.set_upscale(upscale);
+                                    info!("Drawing at {:.0}% of the window, scaled up {}", upscale.scale * 100.0, upscale.mode.name());
+                                }
+                                Err(e) => warn!("{}", e),
+                            }
+                        }
+                        None => warn!("No renderer yet"),
+                    },
+                    "farterrain" => match command.args[0].as_str() {
+                        "on" | "off" => {
+                            self.far_terrain.set_enabled(command.args[0] == "on");
+                            self.fit_view();
+                            info!("Far terrain {}", command.args[0]);
+                        }
+                        other => warn!("expected on or off, got {}", other),
+                    },
+                    "keymode" => match (Action::from_name(&command.args[0]), command.args.get(1).and_then(|m| ActivationMode::from_name(m))) {
+                        (Some(action), Some(mode)) => {
+                            self.player.input_handler.bindings.set_mode(action, mode);
+                            info!("{} now {}", action.name(), command.args[1]);
+                        }
+                        (None, _) => warn!("expected sprint, sneak or zoom, got {}", command.args[0]),
+                        (_, None) => warn!("expected hold or toggle"),
+                    },
+                    "reducedmotion" => match command.args[0].as_str() {
+                        "on" | "off" => {
+                            self.reduced_motion = command.args[0] == "on";
+                            info!("Reduced motion {}", command.args[0]);
+                        }
+                        other => warn!("expected on or off, got {}", other),
+                    },
+                    "mouse" => {
+                        let mouse = &mut self.player.input_handler.mouse.settings;
+                        match &command.args[..] {
+                            [] => info!("{:?}", mouse),
+                            [key, value] => match mouse.set(key, value) {
+                                Ok(result) => info!("{}", result),
+                                Err(e) => warn!("{}", e),
+                            },
+                            _ => warn!("expected a setting and a value"),
+                        }
+                    }
+                    "photo" => match command.args.first() {
+                        None if self.photo_mode.is_some() => self.leave_photo_mode(),
+                        None => self.enter_photo_mode(photo_mode::DEFAULT_SCALE),
+                        Some(arg) => match arg.parse::<u32>() {
+                            Ok(scale) if (1..=screenshot::MAX_SCALE).contains(&scale) => {
+                                match &mut self.photo_mode {
+                                    Some(photo) => photo.scale = scale,
+                                    None => self.enter_photo_mode(scale),
+                                }
+                                info!("Screenshots at {}x the window size", scale);
+                            }
+                            _ => warn!("expected a scale from 1 to {}, got {}", screenshot::MAX_SCALE, arg),
+                        },
+                    },
+                    "timescale" => match command.args.first().map(String::as_str) {
+                        // A remote server keeps its own time, so only a local world can be slowed
+                        _ if self.server.is_none() => warn!("Only a singleplayer world can be paused or slowed down"),
+                        None => info!(
+                            "Time runs at {}x{}",
+                            self.clock.scale(),
+                            if self.clock.is_paused() { ", paused" } else { "" },
+                        ),
+                        Some("pause" | "resume") => {
+                            self.clock.set_paused(command.args[0] == "pause");
+                            self.sync_server_clock();
+                            info!("Time {}", if self.clock.is_paused() { "paused" } else { "resumed" });
+                        }
+                        Some(arg) => match arg.parse::<f32>() {
+                            Ok(scale) if scale.is_finite() && scale > 0.0 => {
+                                let scale = self.clock.set_scale(scale);
+                                self.sync_server_clock();
+                                info!("Time runs at {}x", scale);
+                            }
+                            _ => warn!("expected a speed, pause or resume, got {}", arg),
+                        },
+                    },
+                    "projection" => {
+                        let height = match command.args.get(1).map(|arg| arg.parse::<f32>()) {
+                            None => Ok(DEFAULT_ORTHO_HEIGHT),
+                            Some(Ok(height)) if height.is_finite() => Ok(height.clamp(MIN_ORTHO_HEIGHT, MAX_ORTHO_HEIGHT)),
+                            Some(_) => Err(command.args[1].clone()),
+                        };
+                        match (command.args[0].as_str(), height) {
+                            ("perspective", _) => {
+                                self.player.get_camera_mut().projection = Projection::Perspective;
+                                self.isometric = None;
+                                info!("Perspective view");
+                            }
+                            ("ortho", Ok(height)) => {
+                                self.player.get_camera_mut().projection = Projection::Orthographic { height };
+                                self.isometric = None;
+                                info!("Orthographic view {} blocks tall", height);
+                            }
+                            ("iso", Ok(height)) => {
+                                self.isometric = Some(height);
+                                info!("Isometric view {} blocks tall", height);
+                            }
+                            ("ortho" | "iso", Err(arg)) => warn!("expected a height in blocks, got {}", arg),
+                            (other, _) => warn!("expected perspective, ortho or iso, got {}", other),
+                        }
+                    }
+                    "splitscreen" => match command.args[0].as_str() {
+                        "on" if self.second_player.is_none() => {
+                            // Joins where the first player stands, facing the same way
+                            let mut second = Player::new();
+                            second.camera = self.player.get_camera().clone();
+                            second.mode = self.player.mode;
+                            self.second_player = Some(second);
+                            info!("Split-screen on");
+                        }
+                        "on" => info!("Split-screen is already on"),
+                        "off" => {
+                            self.second_player = None;
+                            info!("Split-screen off");
+                        }
+                        other => warn!("expected on or off, got {}", other),
+                    },
+                    "viewdistance" => match command.args.first() {
+                        None => info!("View distance is {} chunks", self.chunk_manager.view_distance),
+                        Some(arg) => match arg.parse::<i32>() {
+                            Ok(chunks) => {
+                                let chunks = self.set_view_distance(chunks);
+                                info!("View distance set to {} chunks", chunks);
+                            }
+                            Err(_) => warn!("expected a number of chunks, got {}", arg),
+                        },
+                    },
+                    "waypoint" => {
+                        let name = command.args[1..].join(" ");
+                        match command.args[0].as_str() {
+                            "add" => {
+                                let name = if name.is_empty() { self.waypoints.next_name() } else { name };
+                                let position = self.player.get_position() - glam::Vec3::Y * EYE_HEIGHT;
+                                match self.waypoints.set(&name, position) {
+                                    Ok(name) => info!("Waypoint {} set at {:.0} {:.0} {:.0}", name, position.x, position.y, position.z),
+                                    Err(e) => warn!("{}", e),
+                                }
+                            }
+                            "remove" if self.waypoints.remove(&name) => info!("Removed waypoint {}", name),
+                            "remove" => warn!("No waypoint called {}", name),
+                            "list" if self.waypoints.is_empty() => info!("No waypoints"),
+                            "list" => {
+                                for w in self.waypoints.iter() {
+                                    info!("{}: {:.0} {:.0} {:.0}", w.name, w.position.x, w.position.y, w.position.z);
+                                }
+                            }
+                            other => warn!("expected add, remove or list, got {}", other),
+                        }
+                    }
+                    "capture" => {
+                        self.frame_capture.request();
+                        info!("Capturing the next frame");
+                    }
+                    other => warn!("Unhandled console command {}", other),
+                },
+                ConsoleInput::Chat(text) => {
+                    if let Some(client) = &mut self.client {
+                        client.send(&ClientMessage::Chat { text });
+                    }
+                }
+                ConsoleInput::Error(e) => warn!("{}", e),
+            }
+        }
+    }
+
+    fn send_position(&mut self) {
+        let Some(client) = &mut self.client else { return };
+        // Moves sent before the Welcome would be checked against the saved position
+        if client.client_id.is_some() && client.is_connected() {
+            let camera = self.player.get_camera();
+            client.send(&ClientMessage::Move { position: camera.position, yaw: camera.yaw, pitch: camera.pitch });
+            if self.player.riding.is_some() {
+                let steering = self.player.input_handler.ride_input();
+                client.send(&ClientMessage::Steer { forward: steering.forward, turn: steering.turn });
+            }
+            self.last_move_sent = Some(self.clock.now());
+        }
+    }
+
+    /// Types into the open sign editor, sending the text to the server once it is done
+    fn edit_sign(&mut self, keycode: winit::keyboard::KeyCode, text: Option<&str>) {
+        use winit::keyboard::KeyCode;
+        let shortcut = self.modifiers.control_key() || self.modifiers.super_key();
+        let Some(editor) = &mut self.sign_editor else { return };
+        let done = match keycode {
+            KeyCode::KeyV if shortcut => {
+                editor.paste(&self.clipboard.paste());
+                false
+            }
+            KeyCode::KeyC if shortcut => {
+                self.clipboard.copy(&editor.text());
+                false
+            }
+            KeyCode::Escape => true,
+            KeyCode::Enter | KeyCode::NumpadEnter => !editor.new_line(),
+            KeyCode::Backspace => {
+                editor.backspace();
+                false
+            }
+            _ => {
+                editor.type_text(text.unwrap_or(""));
+                false
+            }
         };
-        surface.configure(&device, &config);
+        if !done {
+            return;
+        }
+        if let (Some(editor), Some(client)) = (self.sign_editor.take(), &mut self.client) {
+            client.send(&ClientMessage::SetSignText { block: editor.block, text: editor.text() });
+        }
+    }
+
+    /// Types into the open chat box, sending the line to the server on Enter
+    fn edit_chat(&mut self, keycode: winit::keyboard::KeyCode, text: Option<&str>) {
+        use winit::keyboard::KeyCode;
+        let shortcut = self.modifiers.control_key() || self.modifiers.super_key();
+        let Some(chat) = &mut self.chat_box else { return };
+        match keycode {
+            KeyCode::KeyV if shortcut => chat.paste(&self.clipboard.paste()),
+            KeyCode::KeyC if shortcut => self.clipboard.copy(chat.text()),
+            KeyCode::Escape => self.chat_box = None,
+            KeyCode::Enter | KeyCode::NumpadEnter => {
+                let line = self.chat_box.take().and_then(|chat| chat.message());
+                if let (Some(text), Some(client)) = (line, &mut self.client) {
+                    client.send(&ClientMessage::Chat { text });
+                }
+            }
+            KeyCode::Backspace => chat.backspace(),
+            _ => chat.type_text(text.unwrap_or("")),
+        }
+    }
+
+    /// Keeps the camera in the seat of whatever the player rides, as last replicated
+    fn follow_vehicle(&mut self) {
+        let (Some(id), Some(client)) = (self.player.riding, &self.client) else { return };
+        if let Some(vehicle) = client.entities.get(id) {
+            self.player.follow(vehicle);
+        }
+    }
+
+    /// Saves everything that would be lost if the process were killed now
+    fn persist(&mut self) {
+        self.send_position();
+        if let Some(server) = &self.server {
+            server.save();
+        }
+        self.chunk_manager.flush_mesh_cache();
+        self.save_map_data();
+    }
+
+    /// Stops everything in order: the server saves with our latest position and its thread
+    /// is joined, chunk generation threads are given a moment to finish, client-side data is
+    /// written, and GPU resources are released before the surface, the instance and last
+    /// the window they belong to. Safe to call more than once.
+    fn shutdown(&mut self) {
+        if self.shut_down {
+            return;
+        }
+        self.shut_down = true;
+        info!("Shutting down");
+        // Keep a sign that was being written
+        if let (Some(editor), Some(client)) = (self.sign_editor.take(), &mut self.client) {
+            client.send(&ClientMessage::SetSignText { block: editor.block, text: editor.text() });
+        }
+        self.send_position();
+        if let Some(mut server) = self.server.take() {
+            server.shutdown();
+        }
+        if let Some(mut client) = self.client.take() {
+            client.close();
+        }
+        self.chunk_manager.finish_pending(SHUTDOWN_TIMEOUT);
+        self.chunk_manager.flush_mesh_cache();
+        self.save_map_data();
+        self.release_gpu();
+        self.window_manager.window = None;
+        info!("Shutdown complete");
+    }
 
-        // Load texture atlas for blocks
-        let texture_paths = [
-            "assets/grass_block_top.png",   // 0
-            "assets/grass_block_side.png", // 1
-            "assets/dirt.png",             // 2
-            "assets/stone.png",            // 3
-        ];
-        let texture = Texture::create_atlas_from_files(&device, &queue, &texture_paths)
-            .unwrap_or_else(|e| {
-                warn!("Failed to load texture atlas: {:?}, using default", e);
-                Texture::create_default(&device, &queue)
+    /// Waits for the GPU to finish with our buffers, then drops them before the renderer
+    /// and the surface
+    fn release_gpu(&mut self) {
+        if let Some(renderer) = &self.renderer {
+            renderer.device.poll(wgpu::Maintain::Wait);
+        }
+        // Chunk meshes live in GPU buffers
+        self.chunk_manager.loaded.clear();
+        self.atlas_helper = None;
+        self.texture = None;
+        self.renderer = None;
+        self.surface = None;
+        self.instance = None;
+    }
+
+    fn save_map_data(&mut self) {
+        let Some(dir) = &self.map_dir else { return };
+        if let Err(e) = self.world_map.save(dir) {
+            error!("Failed to save world map: {}", e);
+        }
+        if let Err(e) = self.waypoints.save(dir) {
+            error!("Failed to save waypoints: {}", e);
+        }
+    }
+
+    /// Marks the column at block coordinates (x, z) with a new waypoint, standing on top
+    /// of it if it was explored
+    fn add_map_waypoint(&mut self, block: glam::Vec2) {
+        let (x, z) = (block.x.round() as i32, block.y.round() as i32);
+        let y = match self.world_map.cell(x, z) {
+            Some(cell) => cell.height as f32 + 1.0,
+            None => self.player.get_position().y,
+        };
+        let name = self.waypoints.next_name();
+        match self.waypoints.set(&name, glam::Vec3::new(x as f32, y, z as f32)) {
+            Ok(name) => info!("Added waypoint {} at {} {:.0} {}", name, x, y, z),
+            Err(e) => warn!("{}", e),
+        }
+    }
+
+    /// Leaves the death screen and asks the server to bring the player back
+    fn respawn(&mut self) {
+        self.death_screen = None;
+        if let Some(client) = &mut self.client {
+            client.send(&ClientMessage::Respawn);
+        }
+        if let Some(window) = self.window_manager.get_window() {
+            self.player.input_handler.grab_cursor(window);
+        }
+    }
+
+    /// Shows the fullscreen map around the player, freeing the cursor to drag it
+    fn open_map(&mut self) {
+        let position = self.player.get_position();
+        self.map_screen = Some(MapScreen::open(glam::Vec2::new(position.x, position.z)));
+        if let Some(window) = self.window_manager.get_window() {
+            self.player.input_handler.release_cursor(window);
+        }
+        self.interaction.interrupt();
+    }
+
+    /// Shows the inventory and crafting grid, freeing the cursor to click them
+    fn open_inventory(&mut self) {
+        self.inventory_screen = Some(InventoryScreen::new());
+        if let Some(window) = self.window_manager.get_window() {
+            self.player.input_handler.release_cursor(window);
+        }
+        self.interaction.interrupt();
+    }
+
+    fn close_inventory(&mut self) {
+        self.inventory_screen = None;
+        if let Some(window) = self.window_manager.get_window() {
+            self.player.input_handler.grab_cursor(window);
+        }
+    }
+
+    fn close_map(&mut self) {
+        self.map_screen = None;
+        if let Some(window) = self.window_manager.get_window() {
+            self.player.input_handler.grab_cursor(window);
+        }
+    }
+
+    /// Creates a surface for the current window, e.g. after the OS destroyed the old one
+    fn recreate_surface(&mut self) {
+        let (Some(instance), Some(renderer), Some(window)) = (&self.instance, &mut self.renderer, self.window_manager.window.clone()) else {
+            return;
+        };
+        let size = window.inner_size();
+        match instance.create_surface(window) {
+            Ok(surface) => {
+                // Zero-sized windows are configured by the next real Resized event
+                renderer.resize(size, &surface);
+                self.surface = Some(surface);
+            }
+            Err(e) => error!("Failed to recreate surface: {:?}", e),
+        }
+    }
+
+    /// Sends the break and place actions that are due this frame. The server resolves what
+    /// the view ray hits and replies with block updates.
+    fn run_interactions(&mut self) {
+        let camera = self.player.get_camera();
+        let (origin, dir) = (camera.position, camera.forward());
+        for action in self.interaction.poll(self.clock.now()) {
+            let message = match action {
+                InteractionAction::Break => {
+                    // What the server will hit, so we can feel it break
+                    let entities = self.client.as_ref().map(|client| &client.entities);
+                    self.attacked = entities.and_then(|entities| match raycast::raycast(&self.chunk_manager, entities, origin, dir, PLAYER_REACH) {
+                        Some(RaycastHit::Block(hit)) => Some(hit.block),
+                        _ => None,
+                    });
+                    ClientMessage::Attack { origin, dir }
+                }
+                InteractionAction::Place => ClientMessage::Place { origin, dir, block_type: self.interaction.selected },
+            };
+            if let Some(client) = &mut self.client {
+                client.send(&message);
+            }
+        }
+    }
+
+    /// Tool and debug geometry for this frame
+    /// The first player's HUD, laid out for a viewport of `screen` pixels
+    fn build_overlay(&mut self, screen: (f32, f32), frustum: &Frustum) -> Overlay {
+        let mut overlay = Overlay::new();
+        let text_scale = 2.0 * self.game_state.effective_ui_scale(self.window_manager.scale_factor);
+        self.debug_overlays.draw(&mut overlay, &self.chunk_manager, self.player.get_position(), screen, text_scale, self.clock.now());
+        if let (true, Some(renderer), Some(texture)) = (self.debug_overlays.color, &self.renderer, &self.texture) {
+            debug::draw_color_audit(&mut overlay, &renderer.color_audit(texture), screen, text_scale);
+        }
+        // Tint the view while the camera is inside a fluid
+        let eye = ChunkManager::block_coords(self.player.get_position());
+        let tint = match self.chunk_manager.get_block(eye.0, eye.1, eye.2) {
+            Some(BlockType::Water(_)) => Some([0.1, 0.3, 0.8, 0.35]),
+            Some(BlockType::Lava(_)) => Some([1.0, 0.35, 0.0, 0.7]),
+            _ => None,
+        };
+        if let Some(color) = tint {
+            overlay.add_rect(0.0, 0.0, screen.0, screen.1, color);
+        }
+        if self.player.health < PLAYER_MAX_HEALTH {
+            let text = format!("Health {}", self.player.health.ceil());
+            overlay.add_label(8.0 * text_scale, screen.1 - 16.0 * text_scale, text_scale, [1.0, 0.4, 0.4, 1.0], &text);
+        }
+        if let Some(food) = self.player.food {
+            survival::draw_food(&mut overlay, food, screen, text_scale);
+        }
+        self.draw_world(&mut overlay, self.player.get_position(), frustum);
+        if screen.0 > 0.0 && screen.1 > 0.0 {
+            let camera = self.player.get_camera();
+            let view_proj = camera.view_proj_mat(screen.0 / screen.1);
+            self.waypoints.draw_markers(&mut overlay, view_proj, camera.position, screen, text_scale);
+        }
+        if let Some(map) = &self.map_screen {
+            let camera = self.player.get_camera();
+            let forward = camera.forward();
+            let mut markers: Vec<MapMarker> = self.waypoints.iter().map(|w| w.map_marker()).collect();
+            markers.push(MapMarker {
+                position: glam::Vec2::new(camera.position.x, camera.position.z),
+                label: "You".to_string(),
+                color: [1.0, 0.9, 0.2, 1.0],
+                heading: Some(glam::Vec2::new(forward.x, forward.z)),
             });
+            map.draw(&mut overlay, &self.world_map, &markers, screen, text_scale);
+        }
+        if let Some(inventory_screen) = &self.inventory_screen {
+            let cursor = self.cursor_position.map(|cursor| glam::Vec2::new(cursor.x as f32, cursor.y as f32));
+            inventory_screen.draw(&mut overlay, &self.inventory, &self.recipes, screen, text_scale, cursor);
+        }
+        if let Some(editor) = &self.sign_editor {
+            let caret = (self.clock.real_seconds() * 2.0).fract() < 0.5;
+            editor.draw(&mut overlay, screen, text_scale, caret, &self.player.input_handler.composition);
+        }
+        if let Some(chat) = &self.chat_box {
+            let caret = (self.clock.real_seconds() * 2.0).fract() < 0.5;
+            chat.draw(&mut overlay, screen, text_scale, caret, &self.player.input_handler.composition);
+        }
+        if let Some(death) = &self.death_screen {
+            let cursor = self.cursor_position.map(|cursor| glam::Vec2::new(cursor.x as f32, cursor.y as f32));
+            death.draw(&mut overlay, screen, text_scale, cursor);
+        }
+        if self.game_state.mode == GameMode::Editor {
+            self.editor.update(&self.chunk_manager);
+            self.editor.draw_overlay(&mut overlay, text_scale);
+        }
+        if self.game_state.show_fps {
+            let text = format!("FPS {}", self.game_state.last_fps);
+            let (width, _) = Overlay::text_size(&text, text_scale);
+            overlay.add_label(screen.0 - width - 8.0 * text_scale, 8.0 * text_scale, text_scale, [1.0, 1.0, 1.0, 1.0], &text);
+            let coordinates = debug::coordinates(self.player.get_position());
+            let (width, _) = Overlay::text_size(&coordinates, text_scale);
+            overlay.add_label(screen.0 - width - 8.0 * text_scale, 20.0 * text_scale, text_scale, [1.0, 1.0, 1.0, 1.0], &coordinates);
+        }
+        overlay
+    }
 
-        // Create atlas helper for UV coordinate calculations
-        let atlas_helper = crate::engine::graphics::texture::AtlasUVHelper::new(texture_paths.len());
+    /// What every view shows of the world through the overlay: the entities and particles
+    /// in `frustum` and the text of signs near `eye`
+    fn draw_world(&self, overlay: &mut Overlay, eye: glam::Vec3, frustum: &Frustum) {
+        if let Some(client) = &self.client {
+            // Primed TNT flashes white four times a second
+            let flash = (self.clock.simulation_seconds() * 4.0).fract() < 0.5;
+            for entity in frustum.cull(client.entities.iter(), |entity| entity.aabb()).visible {
+                let color = match entity.kind {
+                    EntityKind::ItemDrop => [0.9, 0.75, 0.3, 1.0],
+                    EntityKind::PrimedTnt if flash => [1.0, 1.0, 1.0, 0.8],
+                    EntityKind::PrimedTnt => [0.8, 0.15, 0.1, 1.0],
+                    EntityKind::Boat => [0.55, 0.35, 0.15, 1.0],
+                    _ => continue,
+                };
+                let bounds = entity.aabb();
+                overlay.add_box(bounds.min, bounds.max, color);
+            }
+        }
+        self.particles.draw(overlay, frustum);
+        sign::draw_nearby(overlay, &self.chunk_manager, eye);
+    }
 
-        // Create renderer with owned device and queue
-        let renderer = Renderer::new(device, queue, &surface, &adapter, size, &texture);
+    /// What the cursor points at, or the crosshair while the cursor is grabbed
+    fn pick_under_cursor(&self) -> PickTarget {
+        let (Some(renderer), Some(size)) = (&self.renderer, self.window_manager.get_size()) else {
+            return PickTarget::Nothing;
+        };
+        // The first player's view is the top left one, so its pixels line up with the window's
+        let viewport = Viewport::split((size.width, size.height), 1 + self.second_player.is_some() as usize, 0);
+        let pixel = match self.cursor_position {
+            Some(cursor) if !self.player.input_handler.is_cursor_grabbed() => (cursor.x as f32, cursor.y as f32),
+            _ => (viewport.width as f32 / 2.0, viewport.height as f32 / 2.0),
+        };
+        let chunks: Vec<&crate::game::world::chunk::Chunk> = self.chunk_manager.all_chunks().collect();
+        let boxes: Vec<_> = self.client.iter()
+            .flat_map(|client| client.entities.iter())
+            .map(|entity| (entity.id, entity.aabb()))
+            .collect();
+        renderer.pick(&self.view_camera(), viewport, pixel, &chunks, &boxes)
+    }
 
-        self.instance = Some(instance);
-        self.renderer = Some(renderer);
-        self.texture = Some(texture);
-        self.atlas_helper = Some(atlas_helper);
+    /// Opens the singleplayer world to other players on the LAN
+    pub fn open_to_lan(&self, port: u16) {
+        if let Some(server) = &self.server {
+            server.open_to_lan(port);
+        }
     }
 
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        // Record zero sizes too, so rendering pauses, but never configure a surface with them
+        self.window_manager.set_window_size(new_size);
         if new_size.width > 0 && new_size.height > 0 {
-            self.window_manager.set_window_size(new_size);
-            if let (Some(renderer), Some(window)) = (&mut self.renderer, self.window_manager.get_window()) {
-                let instance = self.instance.as_ref().unwrap_or_else(|| {
-                    error!("No wgpu instance available for resize");
-                    panic!("No wgpu instance available for resize");
-                });
-                let surface = instance.create_surface(window).unwrap_or_else(|e| {
-                    error!("Failed to create surface for resize: {:?}", e);
-                    panic!("Failed to create surface for resize: {:?}", e);
-                });
-                renderer.resize(new_size, &surface);
+            if let (Some(renderer), Some(surface)) = (&mut self.renderer, &self.surface) {
+                renderer.resize(new_size, surface);
             }
         }
     }
-} 
\ No newline at end of file
+} 
+
+/// Creates the device, surface, atlas and renderer for `window`
+/// Differs every run, so the playlist isn't shuffled the same way each time
+fn music_seed() -> u64 {
+    use std::hash::BuildHasher;
+    std::collections::hash_map::RandomState::new().hash_one(0u8)
+}
+
+async fn init_wgpu(window: Arc<Window>, mut timer: StageTimer) -> GpuContext {
+    let size = window.inner_size();
+    // A window created minimized reports zero size; build at 1x1 and let the first
+    // real Resized event configure the surface
+    let has_area = size.width > 0 && size.height > 0;
+    let size = winit::dpi::PhysicalSize::new(size.width.max(1), size.height.max(1));
+
+    // Decoding the atlas is CPU-only, so overlap it with adapter and device setup
+    #[cfg(not(all(target_arch = "wasm32", feature = "web")))]
+    let atlas_job = std::thread::spawn(|| -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+        let packs = ResourcePacks::from_settings();
+        let tiles = packs.read_all(&BLOCK_TEXTURE_PATHS)?;
+        let normal_tiles: Vec<_> = BLOCK_TEXTURE_PATHS.iter().map(|path| packs.read(&normal_map_path(path)).ok()).collect();
+        let atlas = Texture::decode_atlas_bytes(&tiles)?;
+        let normals = decode_normal_atlas(&normal_tiles, normal_map::tile_size(&atlas, tiles.len()));
+        Ok((atlas, normals))
+    });
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+
+    let surface = instance.create_surface(window.clone()).unwrap_or_else(|e| {
+        error!("Failed to create surface: {:?}", e);
+        std::process::exit(1);
+    });
+    let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        compatible_surface: Some(&surface),
+        force_fallback_adapter: false,
+    }).await.unwrap_or_else(|| {
+        error!("Failed to request adapter");
+        std::process::exit(1);
+    });
+    timer.stage("adapter");
+
+    let (device, queue) = adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::default(),
+        },
+        capture::trace_path().as_deref(),
+    ).await.unwrap_or_else(|e| {
+        error!("Failed to request device: {:?}", e);
+        std::process::exit(1);
+    });
+    timer.stage("device");
+
+    // Configure surface
+    let surface_caps = surface.get_capabilities(&adapter);
+    let surface_format = color::choose_surface_format(&surface_caps.formats).unwrap_or(surface_caps.formats[0]);
+
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: surface_format,
+        width: size.width,
+        height: size.height,
+        present_mode: surface_caps.present_modes[0],
+        alpha_mode: surface_caps.alpha_modes[0],
+        view_formats: vec![],
+        desired_maximum_frame_latency: 2,
+    };
+    if has_area {
+        surface.configure(&device, &config);
+        if let Err(e) = Renderer::present_splash(&device, &queue, &surface) {
+            warn!("Failed to present splash frame: {:?}", e);
+        }
+    }
+    timer.stage("surface and splash");
+
+    #[cfg(not(all(target_arch = "wasm32", feature = "web")))]
+    let atlas = atlas_job.join().unwrap_or_else(|_| Err("texture decoding thread panicked".into()));
+    // No spare threads in the browser, so fetch the tiles and decode them in place
+    #[cfg(all(target_arch = "wasm32", feature = "web"))]
+    let atlas = match crate::engine::assets::load_all(&BLOCK_TEXTURE_PATHS).await {
+        Ok(tiles) => {
+            let mut normal_tiles = Vec::with_capacity(tiles.len());
+            for path in BLOCK_TEXTURE_PATHS {
+                let path = normal_map_path(path);
+                let tile = crate::engine::assets::load_bytes(&path).await.ok()
+                    .or_else(|| crate::engine::assets::embedded::embedded(&path).map(<[u8]>::to_vec));
+                normal_tiles.push(tile);
+            }
+            Texture::decode_atlas_bytes(&tiles).map(|atlas| {
+                let normals = decode_normal_atlas(&normal_tiles, normal_map::tile_size(&atlas, tiles.len()));
+                (atlas, normals)
+            })
+        }
+        Err(e) => Err(e.to_string().into()),
+    };
+    timer.stage("waiting for textures");
+    let (texture, normal_atlas) = match atlas {
+        Ok((atlas, normals)) => (Texture::create_atlas(&device, &queue, &atlas), NormalAtlas::create(&device, &queue, &normals)),
+        Err(e) => {
+            warn!("Failed to load texture atlas: {:?}, using default", e);
+            (Texture::create_default(&device, &queue), NormalAtlas::flat(&device, &queue))
+        }
+    };
+    timer.stage("texture upload");
+
+    // Create atlas helper for UV coordinate calculations
+    let atlas_helper = crate::engine::graphics::texture::AtlasUVHelper::new(BLOCK_TEXTURE_PATHS.len());
+
+    // Create renderer with owned device and queue
+    let renderer = Renderer::new(device, queue, &surface, &adapter, size, &texture, normal_atlas);
+    timer.stage("renderer");
+
+    GpuContext { instance, surface, renderer, texture, atlas_helper, startup: timer }
+}
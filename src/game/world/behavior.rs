@@ -0,0 +1,232 @@
+//! What blocks do when used, placed, broken or randomly ticked, beyond changing a single cell.
+
+use glam::Vec3;
+
+use crate::engine::math::{Aabb, Rng};
+use crate::game::entity::EntityKind;
+use crate::game::item::{ItemStack, ItemType, ToolKind};
+use crate::game::world::chunk::{BlockType, DoorState, Hinge, FIRE_MAX_AGE, WHEAT_MAX_STAGE};
+use crate::game::world::chunk_manager::ChunkManager;
+use crate::game::world::light::{self, MAX_LIGHT};
+
+/// Darkest light crops still grow in
+pub const MIN_GROWTH_LIGHT: u8 = 9;
+/// Server ticks between fire ticks
+pub const FIRE_TICK_DELAY: u64 = 20;
+/// Fire with nothing left to burn goes out once it is this old
+const FIRE_BURNOUT_AGE: u8 = 3;
+/// Chance per fire tick of lighting a nearby cell
+const FIRE_SPREAD_CHANCE: f32 = 0.3;
+
+const NEIGHBORS: [(i32, i32, i32); 6] = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
+
+pub type BlockPos = (i32, i32, i32);
+
+/// Cells to change, and the sound, item drops and entities that go with it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockChange {
+    pub edits: Vec<(BlockPos, BlockType)>,
+    pub sound: Option<&'static str>,
+    pub drops: Vec<ItemStack>,
+    pub spawns: Vec<(EntityKind, Vec3)>,
+}
+
+impl BlockChange {
+    pub fn single(block: BlockPos, block_type: BlockType) -> Self {
+        Self { edits: vec![(block, block_type)], ..Self::default() }
+    }
+}
+
+/// Items a block leaves behind when broken with `tool`, or by hand for None: crops drop
+/// seeds to replant them and their harvest once ripe, and terrain drops itself. Blocks
+/// that need a tool drop nothing without it, except stone, which chips into flint.
+pub fn drops(block_type: BlockType, tool: Option<ToolKind>) -> Vec<ItemStack> {
+    let harvested = !block_type.needs_tool() || tool.is_some_and(|tool| block_type.preferred_tool() == Some(tool));
+    match block_type {
+        BlockType::Wheat(stage) if stage >= WHEAT_MAX_STAGE => {
+            vec![ItemStack::new(ItemType::Wheat, 1), ItemStack::new(ItemType::WheatSeeds, 2)]
+        }
+        BlockType::Wheat(_) => vec![ItemStack::new(ItemType::WheatSeeds, 1)],
+        BlockType::Grass | BlockType::Dirt => vec![ItemStack::new(ItemType::Dirt, 1)],
+        BlockType::Stone if harvested => vec![ItemStack::new(ItemType::Stone, 1)],
+        BlockType::Stone => vec![ItemStack::new(ItemType::Flint, 1)],
+        _ => Vec::new(),
+    }
+}
+
+/// Whether a crop can stand on this block
+fn supports_crop(block_type: Option<BlockType>) -> bool {
+    matches!(block_type, Some(BlockType::Grass | BlockType::Dirt))
+}
+
+fn offset(block: BlockPos, by: (i32, i32, i32)) -> BlockPos {
+    (block.0 + by.0, block.1 + by.1, block.2 + by.2)
+}
+
+/// The other half of the door at `block`, if it is still there
+fn other_half(chunks: &ChunkManager, block: BlockPos, door: DoorState) -> Option<(BlockPos, DoorState)> {
+    let pos = offset(block, (0, if door.upper { -1 } else { 1 }, 0));
+    match chunks.get_block(pos.0, pos.1, pos.2)? {
+        BlockType::Door(other) if other.upper != door.upper && other.facing == door.facing => Some((pos, other)),
+        _ => None,
+    }
+}
+
+/// What using (right-clicking) the block does, or None if it is not interactable. TNT
+/// turns into a primed TNT entity.
+pub fn use_block(chunks: &ChunkManager, block: BlockPos) -> Option<BlockChange> {
+    match chunks.get_block(block.0, block.1, block.2)? {
+        BlockType::Door(door) => {
+            let open = !door.open;
+            let mut edits = vec![(block, BlockType::Door(DoorState { open, ..door }))];
+            if let Some((pos, other)) = other_half(chunks, block, door) {
+                edits.push((pos, BlockType::Door(DoorState { open, ..other })));
+            }
+            Some(BlockChange { edits, sound: Some(if open { "door.open" } else { "door.close" }), ..BlockChange::default() })
+        }
+        BlockType::Tnt => Some(BlockChange {
+            sound: Some("tnt.fuse"),
+            spawns: vec![(EntityKind::PrimedTnt, Vec3::new(block.0 as f32, block.1 as f32, block.2 as f32))],
+            ..BlockChange::single(block, BlockType::Air)
+        }),
+        _ => None,
+    }
+}
+
+/// The cells placing `block_type` at `block` fills, or None if it does not fit. Doors take
+/// the cell above too, and hinge on the right when placed beside another door so the pair
+/// opens outwards. Crops need grass or dirt below.
+pub fn place(chunks: &ChunkManager, block: BlockPos, block_type: BlockType) -> Option<BlockChange> {
+    match block_type {
+        BlockType::Door(door) => {
+            let above = offset(block, (0, 1, 0));
+            if !chunks.get_block(above.0, above.1, above.2).is_some_and(|b| b.is_replaceable()) {
+                return None;
+            }
+            let left = offset(block, door.facing.counter_clockwise().offset());
+            let hinge = match chunks.get_block(left.0, left.1, left.2) {
+                Some(BlockType::Door(neighbor)) if neighbor.facing == door.facing && neighbor.hinge == Hinge::Left => Hinge::Right,
+                _ => Hinge::Left,
+            };
+            let lower = DoorState { hinge, upper: false, ..door };
+            Some(BlockChange {
+                edits: vec![(block, BlockType::Door(lower)), (above, BlockType::Door(DoorState { upper: true, ..lower }))],
+                ..BlockChange::default()
+            })
+        }
+        BlockType::Wheat(_) => {
+            let below = offset(block, (0, -1, 0));
+            supports_crop(chunks.get_block(below.0, below.1, below.2)).then(|| BlockChange::single(block, block_type))
+        }
+        block_type => Some(BlockChange::single(block, block_type)),
+    }
+}
+
+/// Breaking a door half removes the whole door, and breaking the block under a crop
+/// uproots the crop
+pub fn break_block(chunks: &ChunkManager, block: BlockPos, tool: Option<ToolKind>) -> BlockChange {
+    let mut change = BlockChange::single(block, BlockType::Air);
+    let Some(block_type) = chunks.get_block(block.0, block.1, block.2) else { return change };
+    change.drops = drops(block_type, tool);
+    if let BlockType::Door(door) = block_type {
+        if let Some((pos, _)) = other_half(chunks, block, door) {
+            change.edits.push((pos, BlockType::Air));
+        }
+    }
+    let above = offset(block, (0, 1, 0));
+    if let Some(crop @ BlockType::Wheat(_)) = chunks.get_block(above.0, above.1, above.2) {
+        change.edits.push((above, BlockType::Air));
+        change.drops.extend(drops(crop, None));
+    }
+    change
+}
+
+/// Whether a cell next to `block` holds something fire can burn
+fn near_flammable(chunks: &ChunkManager, block: BlockPos) -> bool {
+    NEIGHBORS.iter().any(|&d| {
+        let pos = offset(block, d);
+        chunks.get_block(pos.0, pos.1, pos.2).is_some_and(|b| b.flammability() > 0)
+    })
+}
+
+/// A random cell within one block of `block`, or just above it
+fn random_nearby(block: BlockPos, rng: &mut Rng) -> BlockPos {
+    offset(block, (rng.below(3) as i32 - 1, rng.below(2) as i32, rng.below(3) as i32 - 1))
+}
+
+/// Lights fire in a random nearby cell if it is open and next to something flammable
+fn try_ignite(chunks: &ChunkManager, block: BlockPos, rng: &mut Rng) -> Option<(BlockPos, BlockType)> {
+    let target = random_nearby(block, rng);
+    (chunks.get_block(target.0, target.1, target.2) == Some(BlockType::Air) && near_flammable(chunks, target))
+        .then_some((target, BlockType::Fire(0)))
+}
+
+/// What a random tick does to the block. Crops grow a stage at a time, more often in
+/// brighter light and not at all below MIN_GROWTH_LIGHT, and pop off if their support is gone.
+/// Lava sets fire to flammable blocks near it.
+pub fn random_tick(chunks: &ChunkManager, block: BlockPos, rng: &mut Rng) -> Option<BlockChange> {
+    match chunks.get_block(block.0, block.1, block.2)? {
+        BlockType::Lava(_) => try_ignite(chunks, block, rng).map(|(pos, fire)| BlockChange::single(pos, fire)),
+        BlockType::Wheat(stage) => {
+            let below = offset(block, (0, -1, 0));
+            if !supports_crop(chunks.get_block(below.0, below.1, below.2)) {
+                let mut change = BlockChange::single(block, BlockType::Air);
+                change.drops = drops(BlockType::Wheat(stage), None);
+                return Some(change);
+            }
+            if stage >= WHEAT_MAX_STAGE {
+                return None;
+            }
+            let light = light::light_at(chunks, block);
+            if light < MIN_GROWTH_LIGHT || !rng.chance(light as f32 / MAX_LIGHT as f32) {
+                return None;
+            }
+            Some(BlockChange::single(block, BlockType::Wheat(stage + 1)))
+        }
+        _ => None,
+    }
+}
+
+/// What a scheduled fire tick does. Fire ages every tick and goes out at FIRE_MAX_AGE, or
+/// sooner once nothing next to it can burn or it has nothing to stand on. Until then it may
+/// burn away each flammable neighbour and spread to nearby open cells.
+pub fn fire_tick(chunks: &ChunkManager, block: BlockPos, rng: &mut Rng) -> Option<BlockChange> {
+    let BlockType::Fire(age) = chunks.get_block(block.0, block.1, block.2)? else { return None };
+    let below = offset(block, (0, -1, 0));
+    let supported = chunks.get_block(below.0, below.1, below.2).is_some_and(|b| b.is_solid());
+    let fuel: Vec<(BlockPos, u8)> = NEIGHBORS
+        .iter()
+        .map(|&d| offset(block, d))
+        .filter_map(|pos| chunks.get_block(pos.0, pos.1, pos.2).map(|b| (pos, b.flammability())))
+        .filter(|&(_, flammability)| flammability > 0)
+        .collect();
+    if age >= FIRE_MAX_AGE || (fuel.is_empty() && (age >= FIRE_BURNOUT_AGE || !supported)) {
+        return Some(BlockChange::single(block, BlockType::Air));
+    }
+
+    let mut change = BlockChange::single(block, BlockType::Fire((age + 1 + rng.below(2) as u8).min(FIRE_MAX_AGE)));
+    for (pos, flammability) in fuel {
+        if rng.chance(flammability as f32 / 100.0) {
+            change.edits.push((pos, BlockType::Fire(0)));
+        }
+    }
+    if rng.chance(FIRE_SPREAD_CHANCE) {
+        change.edits.extend(try_ignite(chunks, block, rng));
+    }
+    Some(change)
+}
+
+/// The most damage any block overlapping `bounds` deals on contact
+pub fn contact_damage(chunks: &ChunkManager, bounds: &Aabb) -> f32 {
+    let min = ChunkManager::block_coords(bounds.min);
+    let max = ChunkManager::block_coords(bounds.max);
+    let mut damage: f32 = 0.0;
+    for x in min.0..=max.0 {
+        for y in min.1..=max.1 {
+            for z in min.2..=max.2 {
+                damage = damage.max(chunks.get_block(x, y, z).map_or(0.0, |b| b.contact_damage()));
+            }
+        }
+    }
+    damage
+}
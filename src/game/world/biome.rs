@@ -0,0 +1,179 @@
+//! Biomes, which for now only decide how grass and foliage are colored.
+//!
+//! Each column's biome comes from two climate values, temperature and humidity, that vary
+//! slowly across the world with its seed. A tinted texture is grayscale and the world shader
+//! multiplies it by a color looked up in the tint's colormap at the biome's climate. Every
+//! corner of a face averages the colors of the columns around it, so grass shades smoothly
+//! across biome borders instead of stepping.
+
+use crate::game::world::chunk::CHUNK_SIZE;
+use crate::game::world::material::Tint;
+
+/// Blocks between the lattice points of the climate noise; biomes are about this wide
+const CLIMATE_SCALE: f32 = 96.0;
+/// Columns on each side of a corner that its color is averaged over
+pub const BLEND_RADIUS: usize = 2;
+/// Packed corner colors of untinted faces: white, which leaves their texture as it is
+pub const NO_TINT: [u32; 2] = [u32::MAX; 2];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Plains,
+    Forest,
+    Swamp,
+    Taiga,
+    Savanna,
+    Jungle,
+}
+
+impl Biome {
+    /// The biome of a column with this temperature and humidity, each 0 to 1
+    pub fn from_climate(temperature: f32, humidity: f32) -> Self {
+        if temperature < 0.3 {
+            Biome::Taiga
+        } else if temperature > 0.7 {
+            if humidity < 0.4 { Biome::Savanna } else { Biome::Jungle }
+        } else if humidity > 0.7 {
+            Biome::Swamp
+        } else if humidity > 0.45 {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        }
+    }
+
+    /// Temperature and humidity its colors are looked up at
+    pub fn climate(&self) -> (f32, f32) {
+        match self {
+            Biome::Plains => (0.8, 0.4),
+            Biome::Forest => (0.7, 0.8),
+            Biome::Swamp => (0.6, 1.0),
+            Biome::Taiga => (0.25, 0.8),
+            Biome::Savanna => (1.0, 0.0),
+            Biome::Jungle => (0.95, 0.9),
+        }
+    }
+
+    /// The biome of a column of a world with this seed
+    pub fn at(seed: u32, x: i32, z: i32) -> Self {
+        let temperature = climate_noise(x, z, seed);
+        let humidity = climate_noise(x, z, seed ^ 0x9e37_79b9);
+        Self::from_climate(temperature, humidity)
+    }
+
+    /// Color of `tint` in this biome, 0 to 1
+    pub fn color(&self, tint: Tint) -> [f32; 3] {
+        let (temperature, humidity) = self.climate();
+        tint.colormap().lookup(temperature, humidity)
+    }
+}
+
+/// Colors at the four extremes of the climate, blended in between
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Colormap {
+    pub cold_dry: [u8; 3],
+    pub hot_dry: [u8; 3],
+    pub cold_wet: [u8; 3],
+    pub hot_wet: [u8; 3],
+}
+
+pub const GRASS_COLORMAP: Colormap = Colormap {
+    cold_dry: [0x80, 0xb4, 0x97],
+    hot_dry: [0xbf, 0xb7, 0x55],
+    cold_wet: [0x60, 0xa1, 0x7b],
+    hot_wet: [0x47, 0xcd, 0x33],
+};
+
+pub const FOLIAGE_COLORMAP: Colormap = Colormap {
+    cold_dry: [0x60, 0xa1, 0x7b],
+    hot_dry: [0xae, 0xa4, 0x2a],
+    cold_wet: [0x4a, 0x8f, 0x6a],
+    hot_wet: [0x30, 0xbb, 0x0b],
+};
+
+impl Colormap {
+    /// Color at a temperature and humidity, each 0 to 1
+    pub fn lookup(&self, temperature: f32, humidity: f32) -> [f32; 3] {
+        let (t, h) = (temperature.clamp(0.0, 1.0), humidity.clamp(0.0, 1.0));
+        std::array::from_fn(|i| {
+            let dry = self.cold_dry[i] as f32 * (1.0 - t) + self.hot_dry[i] as f32 * t;
+            let wet = self.cold_wet[i] as f32 * (1.0 - t) + self.hot_wet[i] as f32 * t;
+            (dry * (1.0 - h) + wet * h) / 255.0
+        })
+    }
+}
+
+/// A color packed as RGB565, the form face instances carry tints in
+pub fn pack_color(color: [f32; 3]) -> u32 {
+    let channel = |v: f32, max: f32| (v.clamp(0.0, 1.0) * max).round() as u32;
+    channel(color[0], 31.0) << 11 | channel(color[1], 63.0) << 5 | channel(color[2], 31.0)
+}
+
+fn lattice_noise(x: i32, z: i32, seed: u32) -> f32 {
+    let n = x.wrapping_mul(668265263).wrapping_add(z.wrapping_mul(374761393)) ^ (seed as i32).wrapping_mul(1274126177);
+    let n = (n ^ (n >> 15)).wrapping_mul(2246822519u32 as i32);
+    let n = n ^ (n >> 13);
+    ((n & 0x7fffffff) as f32) / 0x7fffffff as f32
+}
+
+/// Smoothly interpolated value noise, 0 to 1
+fn climate_noise(x: i32, z: i32, seed: u32) -> f32 {
+    let (fx, fz) = (x as f32 / CLIMATE_SCALE, z as f32 / CLIMATE_SCALE);
+    let (x0, z0) = (fx.floor(), fz.floor());
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, tz) = (smooth(fx - x0), smooth(fz - z0));
+    let (x0, z0) = (x0 as i32, z0 as i32);
+    let top = lattice_noise(x0, z0, seed) * (1.0 - tx) + lattice_noise(x0 + 1, z0, seed) * tx;
+    let bottom = lattice_noise(x0, z0 + 1, seed) * (1.0 - tx) + lattice_noise(x0 + 1, z0 + 1, seed) * tx;
+    top * (1.0 - tz) + bottom * tz
+}
+
+/// Blended tint colors at the corners of one chunk's columns
+pub struct TintGrid {
+    /// Corner (i, j) lies between columns i - 1 and i on x, and j - 1 and j on z
+    corners: Vec<[[f32; 3]; 2]>,
+}
+
+impl TintGrid {
+    const SIDE: usize = CHUNK_SIZE + 1;
+
+    /// Blends the biomes around the chunk whose first column is at (`x`, `z`). `biome` gives
+    /// the biome of any column.
+    pub fn new(x: i32, z: i32, biome: impl Fn(i32, i32) -> Biome) -> Self {
+        let reach = CHUNK_SIZE + 2 * BLEND_RADIUS;
+        let start = BLEND_RADIUS as i32;
+        let columns: Vec<[[f32; 3]; 2]> = (0..reach * reach).map(|i| {
+            let biome = biome(x - start + (i % reach) as i32, z - start + (i / reach) as i32);
+            [biome.color(Tint::Grass), biome.color(Tint::Foliage)]
+        }).collect();
+        let area = (2 * BLEND_RADIUS * 2 * BLEND_RADIUS) as f32;
+        let corners = (0..Self::SIDE * Self::SIDE).map(|i| {
+            let (ci, cj) = (i % Self::SIDE, i / Self::SIDE);
+            let mut sum = [[0.0; 3]; 2];
+            // Columns ci - R to ci + R - 1 sit around the corner, shifted by R into `columns`
+            for dz in 0..2 * BLEND_RADIUS {
+                for dx in 0..2 * BLEND_RADIUS {
+                    let column = columns[(cj + dz) * reach + ci + dx];
+                    for (total, color) in sum.iter_mut().zip(column) {
+                        for c in 0..3 {
+                            total[c] += color[c];
+                        }
+                    }
+                }
+            }
+            sum.map(|total| total.map(|c| c / area))
+        }).collect();
+        Self { corners }
+    }
+
+    /// The colors of `tint` at the four corners of the column at (`x`, `z`) within the chunk,
+    /// packed two to a word: -x-z and +x-z, then -x+z and +x+z
+    pub fn packed(&self, tint: Tint, x: usize, z: usize) -> [u32; 2] {
+        let slot = match tint {
+            Tint::Grass => 0,
+            Tint::Foliage => 1,
+        };
+        let corner = |dx: usize, dz: usize| pack_color(self.corners[(z + dz) * Self::SIDE + x + dx][slot]);
+        [corner(0, 0) | corner(1, 0) << 16, corner(0, 1) | corner(1, 1) << 16]
+    }
+}
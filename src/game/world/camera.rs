@@ -1,10 +1,48 @@
-use glam::{Mat4, Vec3};
+use glam::{IVec3, Mat4, Vec3};
 
+use crate::engine::math::Frustum;
+
+pub const DEFAULT_FOV: f32 = 45.0 * std::f32::consts::PI / 180.0;
+pub const MIN_FOV: f32 = 10.0 * std::f32::consts::PI / 180.0;
+pub const MAX_FOV: f32 = 110.0 * std::f32::consts::PI / 180.0;
+/// Far clip distance before it is fitted to the view distance, and the least it gets, so
+/// the clouds stay in view
+pub const DEFAULT_FAR: f32 = 100.0;
+/// Blocks an orthographic view shows from bottom to top unless another height is asked for
+pub const DEFAULT_ORTHO_HEIGHT: f32 = 32.0;
+pub const MIN_ORTHO_HEIGHT: f32 = 4.0;
+pub const MAX_ORTHO_HEIGHT: f32 = 256.0;
+/// Pitch of an isometric view: looking down the diagonal of a cube
+pub const ISOMETRIC_PITCH: f32 = -0.615_479_7;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective,
+    /// Parallel lines stay parallel, and the view is `height` blocks tall at any distance.
+    /// It reaches `far` behind the camera as well as in front, so a camera inside the world
+    /// still sees what is around it.
+    Orthographic { height: f32 },
+}
+
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub position: Vec3,
     pub yaw: f32,
     pub pitch: f32,
     pub distance: f32,
+    /// Vertical field of view in radians
+    pub fov: f32,
+    /// Nothing farther than this many blocks is drawn
+    pub far: f32,
+    /// Radians the view is tilted clockwise about the direction it looks in
+    pub roll: f32,
+    pub projection: Projection,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Camera {
@@ -14,6 +52,10 @@ impl Camera {
             yaw: 0.0,
             pitch: 0.0,
             distance: 3.0,
+            fov: DEFAULT_FOV,
+            far: DEFAULT_FAR,
+            roll: 0.0,
+            projection: Projection::Perspective,
         }
     }
 
@@ -22,6 +64,28 @@ impl Camera {
         self.pitch = (self.pitch + delta_pitch).clamp(-1.54, 1.54); // ~+-88 degrees
     }
 
+    /// Scales the field of view, or the height of an orthographic view; factors below 1
+    /// zoom in
+    pub fn zoom(&mut self, factor: f32) {
+        match &mut self.projection {
+            Projection::Perspective => self.fov = (self.fov * factor).clamp(MIN_FOV, MAX_FOV),
+            Projection::Orthographic { height } => *height = (*height * factor).clamp(MIN_ORTHO_HEIGHT, MAX_ORTHO_HEIGHT),
+        }
+    }
+
+    pub fn is_orthographic(&self) -> bool {
+        matches!(self.projection, Projection::Orthographic { .. })
+    }
+
+    /// This camera turned to look down at its position from an isometric angle, with an
+    /// orthographic projection `height` blocks tall. The yaw is snapped to the nearest
+    /// diagonal, so the view keeps one of the four isometric headings.
+    pub fn isometric(&self, height: f32) -> Camera {
+        let quarter = std::f32::consts::FRAC_PI_2;
+        let yaw = ((self.yaw - quarter * 0.5) / quarter).round() * quarter + quarter * 0.5;
+        Camera { yaw, pitch: ISOMETRIC_PITCH, roll: 0.0, projection: Projection::Orthographic { height }, ..self.clone() }
+    }
+
     pub fn move_forward(&mut self) {
         let right = Vec3::new(self.yaw.cos(), 0.0, self.yaw.sin());
         self.position += right * 0.1;
@@ -50,27 +114,61 @@ impl Camera {
         self.position.y -= 0.1;
     }
 
-    pub fn create_view_proj(&self, aspect: f32) -> [[f32; 4]; 4] {
+    /// Unit vector the camera is looking along
+    pub fn forward(&self) -> Vec3 {
         let (sy, cy) = self.yaw.sin_cos();
         let (sp, cp) = self.pitch.sin_cos();
-        let forward = Vec3::new(cy * cp, sp, sy * cp);
-        let eye = self.position;
-        let target = self.position + forward;
-        let up = Vec3::Y;
-        let view = Mat4::look_at_rh(eye, target, up);
-        let proj = Mat4::perspective_rh_gl(45.0_f32.to_radians(), aspect, 0.1, 100.0);
-        (proj * view).to_cols_array_2d()
+        Vec3::new(cy * cp, sp, sy * cp)
+    }
+
+    /// Up for the view looking along `forward`, tilted by the roll
+    fn up(&self, forward: Vec3) -> Vec3 {
+        if self.roll == 0.0 {
+            return Vec3::Y;
+        }
+        glam::Quat::from_axis_angle(forward, self.roll) * Vec3::Y
+    }
+
+    pub fn projection_mat(&self, aspect: f32) -> Mat4 {
+        match self.projection {
+            Projection::Perspective => Mat4::perspective_rh_gl(self.fov, aspect, 0.1, self.far),
+            Projection::Orthographic { height } => {
+                let (half_width, half_height) = (height * aspect * 0.5, height * 0.5);
+                // Depth from 0 to 1 as wgpu clips it; the GL convention would lose what is
+                // behind the camera
+                Mat4::orthographic_rh(-half_width, half_width, -half_height, half_height, -self.far, self.far)
+            }
+        }
+    }
+
+    pub fn create_view_proj(&self, aspect: f32) -> [[f32; 4]; 4] {
+        self.view_proj_mat(aspect).to_cols_array_2d()
     }
 
     pub fn view_proj_mat(&self, aspect: f32) -> Mat4 {
-        let (sy, cy) = self.yaw.sin_cos();
-        let (sp, cp) = self.pitch.sin_cos();
-        let forward = Vec3::new(cy * cp, sp, sy * cp);
-        let eye = self.position;
-        let target = self.position + forward;
-        let up = Vec3::Y;
-        let view = Mat4::look_at_rh(eye, target, up);
-        let proj = Mat4::perspective_rh_gl(45.0_f32.to_radians(), aspect, 0.1, 100.0);
-        proj * view
+        self.view_proj_from(self.position, aspect)
+    }
+
+    /// What this camera sees through a viewport of `aspect`
+    pub fn frustum(&self, aspect: f32) -> Frustum {
+        Frustum::from_view_proj(&self.view_proj_mat(aspect), self.is_orthographic())
+    }
+
+    /// The block the world is drawn relative to: the one the eye is in. Positions taken
+    /// relative to it stay small however far from the world origin the camera goes, so the
+    /// GPU works with floats that keep their precision.
+    pub fn render_origin(&self) -> IVec3 {
+        self.position.floor().as_ivec3()
+    }
+
+    /// The view-projection matrix for positions relative to `render_origin`
+    pub fn relative_view_proj_mat(&self, aspect: f32) -> Mat4 {
+        self.view_proj_from(self.position - self.render_origin().as_vec3(), aspect)
+    }
+
+    fn view_proj_from(&self, eye: Vec3, aspect: f32) -> Mat4 {
+        let forward = self.forward();
+        let view = Mat4::look_at_rh(eye, eye + forward, self.up(forward));
+        self.projection_mat(aspect) * view
     }
 } 
\ No newline at end of file
@@ -0,0 +1,92 @@
+//! Camera shake from hits, explosions and hard landings.
+//!
+//! Events add trauma, which decays over time; the shake is the square of the trauma, so
+//! small knocks barely move the view and big ones shake it hard. The shake only offsets the
+//! camera that is drawn, never the one the player moves and aims with.
+
+use glam::Vec3;
+
+use crate::game::world::camera::Camera;
+
+/// Trauma lost per second
+const TRAUMA_DECAY: f32 = 1.5;
+/// Largest yaw and pitch offsets, in radians, at full trauma
+const MAX_ANGLE: f32 = 0.05;
+/// Largest position offset, in blocks, at full trauma
+const MAX_OFFSET: f32 = 0.15;
+/// Falls slower than this, in blocks per second, land without a shake
+pub const LANDING_SHAKE_SPEED: f32 = 9.0;
+
+pub struct CameraShake {
+    /// Scales the shake, 0 to 1
+    pub intensity: f32,
+    /// Off switch for players who find it uncomfortable
+    pub enabled: bool,
+    trauma: f32,
+    /// Seconds of shaking so far, which drives the wobble
+    time: f32,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CameraShake {
+    pub fn new() -> Self {
+        Self { intensity: 1.0, enabled: true, trauma: 0.0, time: 0.0 }
+    }
+
+    /// Applies a console setting: `off`, `on`, or an intensity percentage
+    pub fn configure(&mut self, setting: &str) -> Result<(), String> {
+        match setting {
+            "off" => self.enabled = false,
+            "on" => self.enabled = true,
+            percent => {
+                let percent: f32 = percent.parse().map_err(|_| format!("expected off, on or a percentage, got {}", percent))?;
+                self.intensity = (percent / 100.0).clamp(0.0, 1.0);
+                self.enabled = true;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount.max(0.0)).min(1.0);
+    }
+
+    /// Losing a full heart is a light knock; losing half of the maximum health is the most
+    pub fn on_damage(&mut self, damage: f32, max_health: f32) {
+        self.add_trauma(damage / (max_health * 0.5));
+    }
+
+    /// Stronger for bigger explosions, fading with distance
+    pub fn on_explosion(&mut self, power: f32, distance: f32) {
+        self.add_trauma(power / (1.0 + distance));
+    }
+
+    pub fn on_landing(&mut self, speed: f32) {
+        self.add_trauma((speed - LANDING_SHAKE_SPEED) / LANDING_SHAKE_SPEED);
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.trauma = (self.trauma - TRAUMA_DECAY * delta_time).max(0.0);
+        self.time = if self.trauma > 0.0 { self.time + delta_time } else { 0.0 };
+    }
+
+    /// The camera to draw: `camera` shaken by the current trauma
+    pub fn apply(&self, camera: &Camera) -> Camera {
+        let mut shaken = camera.clone();
+        let shake = if self.enabled { self.trauma * self.trauma * self.intensity } else { 0.0 };
+        if shake <= 0.0 {
+            return shaken;
+        }
+        // Sums of sines at unrelated frequencies wobble without an obvious period
+        let wave = |a: f32, b: f32| ((self.time * a).sin() + (self.time * b).sin()) * 0.5;
+        shaken.yaw += MAX_ANGLE * shake * wave(37.0, 23.0);
+        shaken.pitch += MAX_ANGLE * shake * wave(31.0, 43.0);
+        shaken.position += Vec3::new(wave(29.0, 41.0), wave(47.0, 19.0), wave(17.0, 53.0)) * (MAX_OFFSET * shake);
+        shaken
+    }
+}
@@ -1,10 +1,140 @@
-use glam::Vec3;
+use std::collections::HashMap;
+use std::ops::Range;
+use glam::{IVec3, Vec3};
+use crate::game::world::biome::{TintGrid, NO_TINT};
+use crate::game::world::light::LightVolume;
+use crate::game::world::material::{Material, Tint};
+use crate::game::item::ToolKind;
+use crate::engine::graphics::pipeline_cache::RenderMaterial;
 use crate::engine::graphics::vertex::{BlockFaceInstance};
 use wgpu::util::DeviceExt;
 
 pub const CHUNK_SIZE: usize = 16;
 pub const CHUNK_SIZE_F: f32 = CHUNK_SIZE as f32;
 pub const OCCLUSION_DISTANCE_CHUNKS: f32 = 3.0;
+/// Blocks of height in each render section a chunk's mesh is split into for culling
+pub const SECTION_HEIGHT: usize = 16;
+/// Seed of the terrain every world currently uses
+pub const DEFAULT_SEED: u32 = 42;
+
+/// Horizontal side of a block cell, e.g. the wall a ladder is mounted on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Facing {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Facing {
+    pub const ALL: [Facing; 4] = [Facing::North, Facing::South, Facing::East, Facing::West];
+
+    pub fn index(&self) -> u8 {
+        match self {
+            Facing::North => 0,
+            Facing::South => 1,
+            Facing::East => 2,
+            Facing::West => 3,
+        }
+    }
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    /// Unit offset towards this side; north is -Z
+    pub fn offset(&self) -> (i32, i32, i32) {
+        match self {
+            Facing::North => (0, 0, -1),
+            Facing::South => (0, 0, 1),
+            Facing::East => (1, 0, 0),
+            Facing::West => (-1, 0, 0),
+        }
+    }
+
+    pub fn from_offset(offset: (i32, i32, i32)) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.offset() == offset)
+    }
+
+    /// The next side clockwise, seen from above
+    pub fn clockwise(&self) -> Self {
+        match self {
+            Facing::North => Facing::East,
+            Facing::East => Facing::South,
+            Facing::South => Facing::West,
+            Facing::West => Facing::North,
+        }
+    }
+
+    pub fn counter_clockwise(&self) -> Self {
+        self.clockwise().clockwise().clockwise()
+    }
+
+    /// The side a horizontal direction points towards most
+    pub fn from_direction(dir: Vec3) -> Self {
+        if dir.x.abs() > dir.z.abs() {
+            if dir.x > 0.0 { Facing::East } else { Facing::West }
+        } else if dir.z > 0.0 {
+            Facing::South
+        } else {
+            Facing::North
+        }
+    }
+
+    /// Index of the cube face on this side, in generate_mesh order
+    pub fn face(&self) -> u32 {
+        match self {
+            Facing::South => 0,
+            Facing::North => 1,
+            Facing::West => 2,
+            Facing::East => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hinge {
+    Left,
+    Right,
+}
+
+/// One half of a two block tall door
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DoorState {
+    /// Side of the cell the closed door covers
+    pub facing: Facing,
+    pub hinge: Hinge,
+    pub open: bool,
+    pub upper: bool,
+}
+
+impl DoorState {
+    pub fn meta(&self) -> u8 {
+        self.facing.index() | (self.hinge as u8) << 2 | (self.open as u8) << 3 | (self.upper as u8) << 4
+    }
+
+    pub fn from_meta(meta: u8) -> Option<Self> {
+        if meta >> 5 != 0 {
+            return None;
+        }
+        Some(Self {
+            facing: Facing::from_index(meta & 3)?,
+            hinge: if meta & 4 == 0 { Hinge::Left } else { Hinge::Right },
+            open: meta & 8 != 0,
+            upper: meta & 16 != 0,
+        })
+    }
+
+    /// Side of the cell the panel is on. An open door swings round to lie against the
+    /// side its hinge is on.
+    pub fn panel_side(&self) -> Facing {
+        match (self.open, self.hinge) {
+            (false, _) => self.facing,
+            (true, Hinge::Left) => self.facing.counter_clockwise(),
+            (true, Hinge::Right) => self.facing.clockwise(),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BlockType {
@@ -12,55 +142,413 @@ pub enum BlockType {
     Grass,
     Dirt,
     Stone,
+    /// Mounted on the given side of its cell
+    Ladder(Facing),
+    Door(DoorState),
+    /// A crop at growth stage 0 to WHEAT_MAX_STAGE
+    Wheat(u8),
+    /// 0 for a source, weaker flow up to fluid::MAX_FLOW_LEVEL, or fluid::FALLING
+    Water(u8),
+    /// Levels as for water
+    Lava(u8),
+    /// Burning for the given number of fire ticks, up to FIRE_MAX_AGE
+    Fire(u8),
+    /// Explosive, primed by using it
+    Tnt,
+    /// Mounted on the given side of its cell like a ladder; the chunk keeps its text
+    Sign(Facing),
+    /// Sets its user's respawn point, and lets them sleep through the night
+    Bed,
 }
 
+/// First face index of the flat models drawn inset against a cube face
+pub const FLAT_FACE_BASE: u32 = 6;
+/// First of the two diagonal quads crossing the cell, used for plants
+pub const CROSS_FACE_BASE: u32 = 10;
+/// Outward normal of each cube face, then the directions its quad's +x and +y run in, as
+/// the shaders' face_transform places them
+pub const FACE_AXES: [[(i32, i32, i32); 3]; 6] = [
+    [(0, 0, 1), (1, 0, 0), (0, 1, 0)],
+    [(0, 0, -1), (-1, 0, 0), (0, 1, 0)],
+    [(-1, 0, 0), (0, 0, -1), (0, 1, 0)],
+    [(1, 0, 0), (0, 0, 1), (0, 1, 0)],
+    [(0, 1, 0), (1, 0, 0), (0, 0, -1)],
+    [(0, -1, 0), (1, 0, 0), (0, 0, 1)],
+];
+/// Stage at which wheat is ripe and stops growing
+pub const WHEAT_MAX_STAGE: u8 = 7;
+/// texture_type of a water source; flowing water adds its level
+pub const WATER_TEXTURE_BASE: u32 = 14;
+/// texture_type of a lava source; flowing lava adds its level
+pub const LAVA_TEXTURE_BASE: u32 = 23;
+/// Age at which fire always burns out
+pub const FIRE_MAX_AGE: u8 = 15;
+
 impl BlockType {
+    /// Whether the block is a full cube that collides and hides its neighbours' faces
     pub fn is_solid(&self) -> bool {
-        !matches!(self, BlockType::Air)
+        !matches!(self, BlockType::Air | BlockType::Ladder(_) | BlockType::Door(_) | BlockType::Wheat(_) | BlockType::Water(_) | BlockType::Lava(_) | BlockType::Fire(_) | BlockType::Sign(_))
+    }
+
+    /// Whether the block stops movement. Closed doors block their whole cell.
+    pub fn collides(&self) -> bool {
+        match self {
+            BlockType::Door(door) => !door.open,
+            block => block.is_solid(),
+        }
+    }
+
+    /// Height of the collision box from the bottom of the cell, or 0 if the block does not
+    /// collide. Every block that collides is a full cube so far.
+    pub fn collision_height(&self) -> f32 {
+        if self.collides() { 1.0 } else { 0.0 }
+    }
+
+    /// Whether using the block does something, instead of building against it
+    pub fn is_interactable(&self) -> bool {
+        matches!(self, BlockType::Door(_) | BlockType::Tnt | BlockType::Sign(_) | BlockType::Bed)
+    }
+
+    /// Whether the block can be aimed at, broken or built against
+    pub fn is_targetable(&self) -> bool {
+        !matches!(self, BlockType::Air | BlockType::Water(_) | BlockType::Lava(_))
+    }
+
+    pub fn is_fluid(&self) -> bool {
+        matches!(self, BlockType::Water(_) | BlockType::Lava(_))
+    }
+
+    /// Whether placing a block may overwrite this one
+    pub fn is_replaceable(&self) -> bool {
+        matches!(self, BlockType::Air | BlockType::Water(_) | BlockType::Lava(_) | BlockType::Fire(_))
+    }
+
+    /// Light level the block gives off
+    pub fn light_emission(&self) -> u8 {
+        match self {
+            BlockType::Lava(_) | BlockType::Fire(_) => 15,
+            _ => 0,
+        }
+    }
+
+    /// Damage dealt to anything touching the block, per hit
+    pub fn contact_damage(&self) -> f32 {
+        match self {
+            BlockType::Lava(_) => 4.0,
+            BlockType::Fire(_) => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Percent chance per fire tick that an adjacent fire burns the block away
+    pub fn flammability(&self) -> u8 {
+        match self {
+            BlockType::Ladder(_) | BlockType::Door(_) | BlockType::Sign(_) | BlockType::Bed => 20,
+            BlockType::Wheat(_) => 60,
+            _ => 0,
+        }
+    }
+
+    /// Seconds it takes to break by hand; 0 for blocks that break at a touch
+    pub fn hardness(&self) -> f32 {
+        match self {
+            BlockType::Grass => 0.9,
+            BlockType::Dirt => 0.75,
+            BlockType::Stone => 7.5,
+            BlockType::Ladder(_) => 0.6,
+            BlockType::Door(_) => 4.5,
+            BlockType::Sign(_) => 1.5,
+            BlockType::Bed => 0.3,
+            _ => 0.0,
+        }
+    }
+
+    /// The tool that breaks this block faster
+    pub fn preferred_tool(&self) -> Option<ToolKind> {
+        match self {
+            BlockType::Grass | BlockType::Dirt => Some(ToolKind::Shovel),
+            BlockType::Stone => Some(ToolKind::Pickaxe),
+            BlockType::Ladder(_) | BlockType::Door(_) | BlockType::Sign(_) => Some(ToolKind::Axe),
+            _ => None,
+        }
+    }
+
+    /// Whether the block only drops itself when broken with its preferred tool
+    pub fn needs_tool(&self) -> bool {
+        matches!(self, BlockType::Stone)
+    }
+
+    /// What the block sounds like to walk on, or None for blocks such as fluids that make
+    /// no footsteps
+    pub fn material(&self) -> Option<Material> {
+        match self {
+            BlockType::Grass | BlockType::Tnt => Some(Material::Grass),
+            BlockType::Dirt => Some(Material::Dirt),
+            BlockType::Stone => Some(Material::Stone),
+            BlockType::Ladder(_) | BlockType::Door(_) | BlockType::Sign(_) => Some(Material::Wood),
+            BlockType::Wheat(_) => Some(Material::Plant),
+            BlockType::Bed => Some(Material::Cloth),
+            BlockType::Air | BlockType::Water(_) | BlockType::Lava(_) | BlockType::Fire(_) => None,
+        }
+    }
+
+    /// Whether this block's face towards `neighbor` is hidden. Fluids also hide the faces
+    /// between each other.
+    pub fn face_hidden_by(&self, neighbor: BlockType) -> bool {
+        neighbor.is_solid() || (self.is_fluid() && neighbor.is_fluid())
+    }
+
+    pub fn is_climbable(&self) -> bool {
+        matches!(self, BlockType::Ladder(_))
+    }
+
+    /// Whether the block changes on random ticks
+    pub fn ticks_randomly(&self) -> bool {
+        matches!(self, BlockType::Wheat(_) | BlockType::Lava(_))
+    }
+
+    /// Stable numeric id used by save files and the network protocol
+    pub fn id(&self) -> u8 {
+        match self {
+            BlockType::Air => 0,
+            BlockType::Grass => 1,
+            BlockType::Dirt => 2,
+            BlockType::Stone => 3,
+            BlockType::Ladder(_) => 4,
+            BlockType::Door(_) => 5,
+            BlockType::Wheat(_) => 6,
+            BlockType::Water(_) => 7,
+            BlockType::Lava(_) => 8,
+            BlockType::Fire(_) => 9,
+            BlockType::Tnt => 10,
+            BlockType::Sign(_) => 11,
+            BlockType::Bed => 12,
+        }
     }
+
+    /// Per-block state stored alongside the id, such as orientation. 0 for plain blocks.
+    pub fn meta(&self) -> u8 {
+        match self {
+            BlockType::Ladder(facing) | BlockType::Sign(facing) => facing.index(),
+            BlockType::Door(door) => door.meta(),
+            BlockType::Wheat(stage) => *stage,
+            BlockType::Water(level) | BlockType::Lava(level) => *level,
+            BlockType::Fire(age) => *age,
+            _ => 0,
+        }
+    }
+
+    /// The block with this id and default metadata
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::from_parts(id, 0)
+    }
+
+    pub fn from_parts(id: u8, meta: u8) -> Option<Self> {
+        match id {
+            0 => Some(BlockType::Air),
+            1 => Some(BlockType::Grass),
+            2 => Some(BlockType::Dirt),
+            3 => Some(BlockType::Stone),
+            4 => Facing::from_index(meta).map(BlockType::Ladder),
+            5 => DoorState::from_meta(meta).map(BlockType::Door),
+            6 if meta <= WHEAT_MAX_STAGE => Some(BlockType::Wheat(meta)),
+            7 if meta <= crate::game::world::fluid::FALLING => Some(BlockType::Water(meta)),
+            8 if meta <= crate::game::world::fluid::FALLING => Some(BlockType::Lava(meta)),
+            9 if meta <= FIRE_MAX_AGE => Some(BlockType::Fire(meta)),
+            10 => Some(BlockType::Tnt),
+            11 => Facing::from_index(meta).map(BlockType::Sign),
+            12 => Some(BlockType::Bed),
+            _ => None,
+        }
+    }
+
+    /// This block as placed against a face with the given outward normal by a player looking
+    /// along `look`, or None if it cannot attach there. Ladders hang on the wall they were
+    /// placed against, and so do signs; doors stand on the floor, closing on the side away from the player.
+    /// Crops are always planted as seeds, fluids as a source and fire freshly lit.
+    pub fn placed_against(&self, normal: (i32, i32, i32), look: Vec3) -> Option<Self> {
+        match self {
+            BlockType::Ladder(_) => Facing::from_offset((-normal.0, -normal.1, -normal.2)).map(BlockType::Ladder),
+            BlockType::Sign(_) => Facing::from_offset((-normal.0, -normal.1, -normal.2)).map(BlockType::Sign),
+            BlockType::Door(door) if normal == (0, 1, 0) => Some(BlockType::Door(DoorState {
+                facing: Facing::from_direction(look),
+                open: false,
+                upper: false,
+                ..*door
+            })),
+            BlockType::Door(_) => None,
+            BlockType::Wheat(_) if normal == (0, 1, 0) => Some(BlockType::Wheat(0)),
+            BlockType::Wheat(_) => None,
+            BlockType::Water(_) => Some(BlockType::Water(0)),
+            BlockType::Lava(_) => Some(BlockType::Lava(0)),
+            BlockType::Fire(_) => Some(BlockType::Fire(0)),
+            block => Some(*block),
+        }
+    }
+
+    /// Side of the cell a flat model is drawn against, for blocks meshed as a single quad
+    pub fn flat_side(&self) -> Option<Facing> {
+        match self {
+            BlockType::Ladder(facing) | BlockType::Sign(facing) => Some(*facing),
+            BlockType::Door(door) => Some(door.panel_side()),
+            _ => None,
+        }
+    }
+
+    /// Whether the block is meshed as two diagonal quads, like a plant
+    pub fn is_cross(&self) -> bool {
+        matches!(self, BlockType::Wheat(_) | BlockType::Fire(_))
+    }
+
+    /// How this block's faces are drawn
+    pub fn render_material(&self) -> RenderMaterial {
+        texture_render_material(self.texture_type())
+    }
+
+    /// Colormap the texture on `face` is multiplied by, for grayscale textures colored by
+    /// the biome
+    pub fn tint(&self, face: u32) -> Option<Tint> {
+        match (self, face) {
+            (BlockType::Grass, 4) => Some(Tint::Grass),
+            _ => None,
+        }
+    }
+
+    /// Texture slot read by the world shader
+    pub fn texture_type(&self) -> u32 {
+        match self {
+            BlockType::Grass => 0,
+            BlockType::Dirt => 1,
+            BlockType::Stone => 2,
+            BlockType::Ladder(_) => 3,
+            BlockType::Door(DoorState { upper: false, .. }) => 4,
+            BlockType::Door(DoorState { upper: true, .. }) => 5,
+            BlockType::Wheat(stage) => 6 + *stage as u32,
+            BlockType::Water(level) => WATER_TEXTURE_BASE + *level as u32,
+            BlockType::Lava(level) => LAVA_TEXTURE_BASE + *level as u32,
+            BlockType::Fire(_) => 32,
+            BlockType::Tnt => 33,
+            BlockType::Sign(_) => 34,
+            BlockType::Bed => 35,
+            BlockType::Air => 255,
+        }
+    }
+}
+
+/// How faces with this texture slot are drawn; faces carry only their slot, which is all
+/// the cached meshes keep
+pub fn texture_render_material(texture_type: u32) -> RenderMaterial {
+    match texture_type {
+        // Ladders, doors, wheat
+        3..=13 => RenderMaterial::Cutout,
+        WATER_TEXTURE_BASE..LAVA_TEXTURE_BASE => RenderMaterial::Translucent,
+        LAVA_TEXTURE_BASE..=31 => RenderMaterial::Fluid,
+        // Fire and signs
+        32 | 34 => RenderMaterial::Cutout,
+        _ => RenderMaterial::Solid,
+    }
+}
+
+/// A SECTION_HEIGHT tall slice of a chunk's mesh, culled and drawn on its own
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSection {
+    /// Its faces within the chunk's instances
+    pub instances: Range<u32>,
+    /// The part of `instances` drawn with each material present, in material order
+    pub materials: Vec<(RenderMaterial, Range<u32>)>,
+    /// Bounds of those faces
+    pub min: Vec3,
+    pub max: Vec3,
 }
 
+/// A chunk's blocks, by x, then y, then z
+pub type ChunkBlocks = [[[BlockType; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE];
+
 pub struct Chunk {
+    /// World position of the chunk's first block. Chunk positions are whole multiples of
+    /// CHUNK_SIZE, which f32 holds exactly out to 2^24 blocks; anything with a fraction works
+    /// from `origin` instead.
     pub position: Vec3,
-    pub blocks: [[[BlockType; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
+    pub blocks: ChunkBlocks,
+    /// Faces ordered by render section, then material
     pub block_face_instances: Vec<BlockFaceInstance>,
+    /// Sections holding faces, bottom first
+    pub sections: Vec<RenderSection>,
     pub instance_buffer: Option<wgpu::Buffer>,
+    /// Text of the signs in this chunk, by position within the chunk
+    pub sign_text: HashMap<(usize, usize, usize), String>,
 }
 
 impl Chunk {
     pub fn new(position: Vec3) -> Self {
-        let mut chunk = Self {
+        Self::with_seed(position, DEFAULT_SEED)
+    }
+
+    /// Generates the chunk at `position` of the world with the given seed
+    pub fn with_seed(position: Vec3, seed: u32) -> Self {
+        let mut chunk = Self::empty(position);
+        chunk.generate_terrain(seed);
+        chunk
+    }
+
+    /// An all-air chunk
+    pub fn empty(position: Vec3) -> Self {
+        Self {
             position,
             blocks: [[[BlockType::Air; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
             block_face_instances: Vec::new(),
+            sections: Vec::new(),
             instance_buffer: None,
-        };
-        chunk.generate_terrain();
-        chunk
+            sign_text: HashMap::new(),
+        }
+    }
+
+    pub fn is_all_air(&self) -> bool {
+        self.blocks.iter().flatten().flatten().all(|&b| b == BlockType::Air)
+    }
+
+    /// FNV-1a hash of the block ids and metadata, stable across builds and platforms
+    pub fn block_hash(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for block in self.blocks.iter().flatten().flatten() {
+            hash ^= block.id() as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+            // Only fed when present, so hashes of chunks without metadata stay as they were
+            if block.meta() != 0 {
+                hash ^= block.meta() as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        hash
     }
 
     fn value_noise(x: i32, z: i32, seed: u32) -> f32 {
         // Simple hash-based value noise
-        let n = x.wrapping_mul(374761393).wrapping_add(z.wrapping_mul(668265263)).wrapping_add(seed as i32 * 31);
+        let n = x.wrapping_mul(374761393).wrapping_add(z.wrapping_mul(668265263)).wrapping_add((seed as i32).wrapping_mul(31));
         let n = (n ^ (n >> 13)).wrapping_mul(1274126177);
         ((n & 0x7fffffff) as f32) / 0x7fffffff as f32
     }
 
-    pub fn generate_terrain(&mut self) {
+    /// Blocks of ground in the column at (`x`, `z`) of the default terrain; the top one is
+    /// grass
+    pub fn terrain_height(x: i32, z: i32, seed: u32) -> i32 {
+        let scale = 0.15;
+        let min_height = 1;
+        let max_height = CHUNK_SIZE as i32 / 4; // Lower hills
+        let noise = Self::value_noise((x as f32 * scale) as i32, (z as f32 * scale) as i32, seed);
+        min_height + ((noise * (max_height - min_height) as f32).round() as i32)
+    }
+
+    pub fn generate_terrain(&mut self, seed: u32) {
         // Only generate terrain for ground chunks (y == 0)
         if self.position.y != 0.0 {
             return;
         }
-        let seed = 42;
-        let scale = 0.15;
-        let min_height = 1;
-        let max_height = CHUNK_SIZE as i32 / 4; // Lower hills
         for x in 0..CHUNK_SIZE {
             for z in 0..CHUNK_SIZE {
                 let nx = self.position.x as i32 + x as i32;
                 let nz = self.position.z as i32 + z as i32;
-                let noise = Self::value_noise((nx as f32 * scale) as i32, (nz as f32 * scale) as i32, seed);
-                let height = min_height + ((noise * (max_height - min_height) as f32).round() as i32);
+                let height = Self::terrain_height(nx, nz, seed);
                 for y in 0..height.clamp(0, CHUNK_SIZE as i32 - 1) {
                     let block = if y == height - 1 {
                         BlockType::Grass
@@ -75,14 +563,45 @@ impl Chunk {
         }
     }
 
+    /// Block coordinates of the chunk's first block
+    pub fn origin(&self) -> (i32, i32, i32) {
+        (self.position.x as i32, self.position.y as i32, self.position.z as i32)
+    }
+
     pub fn generate_mesh(&mut self, chunk_manager: &crate::game::world::chunk_manager::ChunkManager) {
+        let light = self.light_volume(chunk_manager);
+        self.generate_mesh_lit(chunk_manager, &light);
+    }
+
+    /// Light around this chunk, for meshing. The chunk's own blocks are read from it, so it
+    /// needn't be in the manager.
+    pub fn light_volume(&self, chunk_manager: &crate::game::world::chunk_manager::ChunkManager) -> LightVolume {
+        let origin = self.origin();
+        let (min, max) = LightVolume::chunk_bounds(origin, CHUNK_SIZE as i32);
+        LightVolume::compute_with(min, max, |(x, y, z)| {
+            let (lx, ly, lz) = (x - origin.0, y - origin.1, z - origin.2);
+            let inside = [lx, ly, lz].iter().all(|&l| (0..CHUNK_SIZE as i32).contains(&l));
+            if inside {
+                Some(self.blocks[lx as usize][ly as usize][lz as usize])
+            } else {
+                chunk_manager.get_block(x, y, z)
+            }
+        })
+    }
+
+    /// `generate_mesh` with the light around the chunk already worked out
+    pub fn generate_mesh_lit(&mut self, chunk_manager: &crate::game::world::chunk_manager::ChunkManager, light: &LightVolume) {
         self.block_face_instances.clear();
+        let origin = self.origin();
+        // Only worked out once a tinted face turns up
+        let mut tints: Option<TintGrid> = None;
         
-        // Only add faces for solid blocks
+        // Solid blocks and fluids are meshed as cubes, with faces only where they show
         for x in 0..CHUNK_SIZE {
             for y in 0..CHUNK_SIZE {
                 for z in 0..CHUNK_SIZE {
-                    if self.blocks[x][y][z].is_solid() {
+                    let block = self.blocks[x][y][z];
+                    if block.is_solid() || block.is_fluid() {
                         for (face_idx, offset) in [
                             (0, 0, 1),   // Front
                             (0, 0, -1),  // Back
@@ -98,32 +617,152 @@ impl Chunk {
                             let neighbor_is_air = if nx >= 0 && ny >= 0 && nz >= 0 && 
                                                    nx < CHUNK_SIZE as isize && ny < CHUNK_SIZE as isize && nz < CHUNK_SIZE as isize {
                                 let (nx, ny, nz) = (nx as usize, ny as usize, nz as usize);
-                                !self.blocks[nx][ny][nz].is_solid()
+                                !block.face_hidden_by(self.blocks[nx][ny][nz])
                             } else {
                                 // At chunk boundary, check neighbor chunk
-                                let world_x = self.position.x as i32 + x as i32 + offset.0 as i32;
-                                let world_y = self.position.y as i32 + y as i32 + offset.1 as i32;
-                                let world_z = self.position.z as i32 + z as i32 + offset.2 as i32;
-                                chunk_manager.get_block(world_x, world_y, world_z).map_or(true, |b| !b.is_solid())
+                                let world_x = origin.0 + x as i32 + offset.0 as i32;
+                                let world_y = origin.1 + y as i32 + offset.1 as i32;
+                                let world_z = origin.2 + z as i32 + offset.2 as i32;
+                                chunk_manager.get_block(world_x, world_y, world_z).is_none_or(|b| !block.face_hidden_by(b))
                             };
                             
                             if neighbor_is_air {
+                                let block_pos = (origin.0 + x as i32, origin.1 + y as i32, origin.2 + z as i32);
+                                let [normal, u, v] = FACE_AXES[face_idx];
+                                let tint = match block.tint(face_idx as u32) {
+                                    Some(tint) => tints.get_or_insert_with(|| self.tint_grid(chunk_manager)).packed(tint, x, z),
+                                    None => NO_TINT,
+                                };
                                 self.block_face_instances.push(BlockFaceInstance {
-                                    position: [self.position.x + x as f32, self.position.y + y as f32, self.position.z + z as f32],
+                                    position: [origin.0 + x as i32, origin.1 + y as i32, origin.2 + z as i32],
                                     face: face_idx as u32,
-                                    block_type: match self.blocks[x][y][z] {
-                                        crate::game::world::chunk::BlockType::Grass => 0,
-                                        crate::game::world::chunk::BlockType::Dirt => 1,
-                                        crate::game::world::chunk::BlockType::Stone => 2,
-                                        crate::game::world::chunk::BlockType::Air => 255,
-                                    },
+                                    block_type: self.blocks[x][y][z].texture_type(),
+                                    tint,
+                                    light: light.face_corners(block_pos, normal, u, v),
                                 });
                             }
                         }
+                    } else if let Some(side) = self.blocks[x][y][z].flat_side() {
+                        // A single flat quad just off the side it lies against
+                        self.block_face_instances.push(BlockFaceInstance {
+                            position: [origin.0 + x as i32, origin.1 + y as i32, origin.2 + z as i32],
+                            face: FLAT_FACE_BASE + side.face(),
+                            block_type: self.blocks[x][y][z].texture_type(),
+                            tint: NO_TINT,
+                            light: light.uniform((origin.0 + x as i32, origin.1 + y as i32, origin.2 + z as i32)),
+                        });
+                    } else if self.blocks[x][y][z].is_cross() {
+                        for face in [CROSS_FACE_BASE, CROSS_FACE_BASE + 1] {
+                            self.block_face_instances.push(BlockFaceInstance {
+                                position: [origin.0 + x as i32, origin.1 + y as i32, origin.2 + z as i32],
+                                face,
+                                block_type: self.blocks[x][y][z].texture_type(),
+                                tint: NO_TINT,
+                                light: light.uniform((origin.0 + x as i32, origin.1 + y as i32, origin.2 + z as i32)),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        self.build_sections();
+    }
+
+    /// Orders the faces by render section and material, and works out each section's
+    /// ranges and bounds
+    pub fn build_sections(&mut self) {
+        let base = self.origin().1;
+        let section = |f: &BlockFaceInstance| ((f.position[1] - base) as usize / SECTION_HEIGHT) as u32;
+        self.block_face_instances.sort_by_key(|f| (section(f), texture_render_material(f.block_type)));
+        self.sections.clear();
+        for (i, face) in self.block_face_instances.iter().enumerate() {
+            let i = i as u32;
+            let center = IVec3::from(face.position).as_vec3();
+            let (low, high) = (center - 0.5, center + 0.5);
+            let material = texture_render_material(face.block_type);
+            match self.sections.last_mut() {
+                Some(last) if section(&self.block_face_instances[last.instances.start as usize]) == section(face) => {
+                    last.instances.end = i + 1;
+                    last.min = last.min.min(low);
+                    last.max = last.max.max(high);
+                    match last.materials.last_mut() {
+                        Some((m, range)) if *m == material => range.end = i + 1,
+                        _ => last.materials.push((material, i..i + 1)),
                     }
                 }
+                _ => self.sections.push(RenderSection {
+                    instances: i..i + 1,
+                    materials: vec![(material, i..i + 1)],
+                    min: low,
+                    max: high,
+                }),
+            }
+        }
+    }
+
+    /// Biome colors around this chunk's columns
+    fn tint_grid(&self, chunk_manager: &crate::game::world::chunk_manager::ChunkManager) -> TintGrid {
+        let generator = chunk_manager.generator();
+        TintGrid::new(self.position.x as i32, self.position.z as i32, |x, z| generator.biome(x, z))
+    }
+
+    /// Hash of everything generate_mesh reads: this chunk's blocks plus the layer of each
+    /// neighbouring chunk that touches it, or the fact that the neighbour isn't loaded, and
+    /// the light around it
+    pub fn mesh_hash(&self, chunk_manager: &crate::game::world::chunk_manager::ChunkManager, light: &LightVolume) -> u64 {
+        // FNV-1a, since the hash is stored on disk and must not change between builds
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut feed = |byte: u8| {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        };
+        for plane in &self.blocks {
+            for row in plane {
+                for block in row {
+                    feed(block.id());
+                    feed(block.meta());
+                }
+            }
+        }
+        for (level, solid) in light.cells() {
+            feed(level | (solid as u8) << 7);
+        }
+        // Tints come from the biomes
+        match chunk_manager.generator().biome_seed() {
+            Some(seed) => {
+                feed(1);
+                seed.to_le_bytes().into_iter().for_each(&mut feed);
+            }
+            None => feed(0),
+        }
+        let key = (
+            (self.position.x / CHUNK_SIZE_F).floor() as i32,
+            (self.position.y / CHUNK_SIZE_F).floor() as i32,
+            (self.position.z / CHUNK_SIZE_F).floor() as i32,
+        );
+        let last = CHUNK_SIZE - 1;
+        for (axis, side) in [(0, -1), (0, 1), (1, -1), (1, 1), (2, -1), (2, 1)] {
+            let mut neighbor_key = [key.0, key.1, key.2];
+            neighbor_key[axis] += side;
+            let Some(neighbor) = chunk_manager.loaded.get(&(neighbor_key[0], neighbor_key[1], neighbor_key[2])) else {
+                feed(0xff);
+                continue;
+            };
+            // The neighbour's layer facing us
+            let layer = if side < 0 { last } else { 0 };
+            for a in 0..CHUNK_SIZE {
+                for b in 0..CHUNK_SIZE {
+                    let block = match axis {
+                        0 => neighbor.blocks[layer][a][b],
+                        1 => neighbor.blocks[a][layer][b],
+                        _ => neighbor.blocks[a][b][layer],
+                    };
+                    feed(block.id());
+                    feed(block.meta());
+                }
             }
         }
+        hash
     }
 
     pub fn build_instance_buffer(&mut self, device: &wgpu::Device) {
@@ -0,0 +1,122 @@
+//! The fixed pool of threads chunks are generated or read from the save on.
+//!
+//! Requested chunks wait in a queue for one of at most MAX_LOADER_THREADS workers, however
+//! many are asked for at once, so flying fast queues work instead of starting a thread per
+//! chunk. The workers start with the first request, so chunk managers that never load
+//! anything, such as test fixtures, cost no threads. Platforms without threads load each
+//! chunk in place when it is requested.
+
+use std::sync::Arc;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use glam::Vec3;
+use log::error;
+
+use crate::engine::time::Instant;
+use crate::game::save::SavedChunks;
+use crate::game::world::chunk::Chunk;
+use crate::game::world::chunk_manager::ChunkSource;
+use crate::game::world::worldgen::WorldGen;
+
+type ChunkKey = (i32, i32, i32);
+/// Most loading threads one pool runs, which leaves cores for the game itself
+pub const MAX_LOADER_THREADS: usize = 4;
+
+pub struct LoadJob {
+    pub key: ChunkKey,
+    pub position: Vec3,
+    pub generator: Arc<WorldGen>,
+    pub saved: Option<Arc<SavedChunks>>,
+    /// Where the finished chunk goes
+    pub done: Sender<(ChunkKey, Chunk, ChunkSource)>,
+}
+
+impl LoadJob {
+    /// Reads the chunk from the save if it is there, and generates it otherwise
+    fn run(self) {
+        let start = Instant::now();
+        let delivered = match self.saved.and_then(|saved| saved.load(self.key)) {
+            Some(blocks) => {
+                let mut chunk = Chunk::empty(self.position);
+                chunk.blocks = *blocks;
+                (self.key, chunk, ChunkSource::Disk(start.elapsed()))
+            }
+            None => (self.key, self.generator.generate(self.position), ChunkSource::Generated(start.elapsed())),
+        };
+        self.done.send(delivered).ok();
+    }
+}
+
+#[derive(Default)]
+pub struct ChunkLoader {
+    /// None until the first request. The receiver is kept to throw queued jobs away.
+    queue: Option<(Sender<LoadJob>, Receiver<LoadJob>)>,
+}
+
+impl ChunkLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Threads a pool starts: one fewer than the cores, so the main thread keeps one
+    pub fn thread_count() -> usize {
+        std::thread::available_parallelism().map_or(2, |cores| cores.get()).saturating_sub(1).clamp(1, MAX_LOADER_THREADS)
+    }
+
+    pub fn request(&mut self, job: LoadJob) {
+        if cfg!(target_arch = "wasm32") {
+            job.run();
+            return;
+        }
+        let (jobs, _) = self.queue.get_or_insert_with(Self::start);
+        // With no worker left, e.g. none could be started, load it here instead
+        if let Err(unsent) = jobs.send(job) {
+            unsent.into_inner().run();
+        }
+    }
+
+    fn start() -> (Sender<LoadJob>, Receiver<LoadJob>) {
+        let (jobs, queue) = unbounded::<LoadJob>();
+        let mut started = 0;
+        for index in 0..Self::thread_count() {
+            let queue = queue.clone();
+            let spawned = std::thread::Builder::new()
+                .name(format!("chunk-loader-{}", index))
+                .spawn(move || {
+                    for job in queue {
+                        job.run();
+                    }
+                });
+            match spawned {
+                Ok(_) => started += 1,
+                Err(e) => error!("Failed to start a chunk loading thread: {}", e),
+            }
+        }
+        if started == 0 {
+            // Nothing would ever take the jobs, so refuse them
+            return (unbounded().0, crossbeam_channel::never());
+        }
+        (jobs, queue)
+    }
+
+    /// Drops the requests no worker has started, returning their chunks
+    pub fn cancel_queued(&mut self) -> Vec<ChunkKey> {
+        self.retain_queued(|_| false)
+    }
+
+    /// Drops the requests no worker has started for chunks `keep` turns down, e.g. ones
+    /// that went out of view while they waited, returning those chunks
+    pub fn retain_queued(&mut self, keep: impl Fn(&ChunkKey) -> bool) -> Vec<ChunkKey> {
+        let Some((jobs, queue)) = &self.queue else { return Vec::new() };
+        let mut dropped = Vec::new();
+        for job in queue.try_iter().collect::<Vec<_>>() {
+            if keep(&job.key) {
+                if let Err(unsent) = jobs.send(job) {
+                    unsent.into_inner().run();
+                }
+            } else {
+                dropped.push(job.key);
+            }
+        }
+        dropped
+    }
+}
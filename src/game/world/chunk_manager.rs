@@ -1,14 +1,90 @@
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
 use glam::Vec3;
-use crate::game::world::chunk::{Chunk, CHUNK_SIZE};
+use log::warn;
+use crate::game::save::{MeshCache, SavedChunks};
+use crate::game::world::chunk::{BlockType, Chunk, CHUNK_SIZE};
+use crate::game::world::chunk_loader::{ChunkLoader, LoadJob};
+use crate::game::world::light::MESH_LIGHT_HEADROOM;
+use crate::game::world::recent_chunks::{RecentChunks, DEFAULT_RECENT_CHUNKS};
+use crate::game::world::worldgen::WorldGen;
+use crate::engine::time::Instant;
 use crossbeam_channel::{Sender, Receiver, unbounded};
 
+type ChunkKey = (i32, i32, i32);
+
+/// Range of view distances, in chunks, that `set_view_distance` accepts
+pub const MIN_VIEW_DISTANCE: i32 = 2;
+pub const MAX_VIEW_DISTANCE: i32 = 12;
+/// Lowest layer of chunks. None are requested below it; that is the void, all air
+pub const MIN_CHUNK_Y: i32 = 0;
+/// Height of the underside of the lowest blocks
+pub const WORLD_FLOOR: f32 = (MIN_CHUNK_Y * CHUNK_SIZE as i32) as f32 - 0.5;
+/// Anything that falls this far below the floor is lost to the void
+pub const VOID_DEPTH: f32 = 64.0;
+/// Where a chunk delivered to the manager came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChunkSource {
+    /// Generated, taking this long
+    Generated(Duration),
+    /// Read from the save, taking this long
+    Disk(Duration),
+    /// Its blocks were still kept from when it was last unloaded
+    Recent,
+}
+
+/// A chunk ready to be loaded
+type Generated = (ChunkKey, Chunk, ChunkSource);
+/// A block edit at a position within its chunk
+type LocalEdit = ((usize, usize, usize), BlockType);
+
+/// Time spent building a chunk, for finding expensive areas of the world
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChunkTimings {
+    /// Generating the chunk, or reading it from the save
+    pub generate: Duration,
+    /// The most recent meshing; near zero when the mesh came from the cache
+    pub mesh: Duration,
+}
+
+impl ChunkTimings {
+    pub fn total(&self) -> Duration {
+        self.generate + self.mesh
+    }
+}
+
 pub struct ChunkManager {
     pub loaded: HashMap<(i32, i32, i32), Chunk>,
     pub pending: HashSet<(i32, i32, i32)>,
     pub view_distance: i32,
-    tx: Sender<(i32, i32, i32, Chunk)>,
-    rx: Receiver<(i32, i32, i32, Chunk)>,
+    /// Points chunks were last kept loaded around
+    centers: Vec<Vec3>,
+    tx: Sender<Generated>,
+    rx: Receiver<Generated>,
+    newly_loaded: Vec<(i32, i32, i32)>,
+    newly_unloaded: Vec<(i32, i32, i32)>,
+    /// Chunks whose blocks changed since they were last meshed
+    dirty: HashSet<(i32, i32, i32)>,
+    pub timings: HashMap<ChunkKey, ChunkTimings>,
+    /// Chunks generated since startup, and the time generating them took
+    pub chunks_generated: u64,
+    pub generate_time: Duration,
+    /// Chunks brought back from `recent` instead of being generated again
+    pub chunks_reused: u64,
+    /// Chunks read from the save instead of generated, and the time reading them took
+    pub chunks_from_disk: u64,
+    pub disk_time: Duration,
+    /// Blocks of chunks unloaded lately
+    pub recent: RecentChunks,
+    mesh_cache: Option<MeshCache>,
+    /// Shared with the generation threads
+    generator: Arc<WorldGen>,
+    loader: ChunkLoader,
+    /// Where the loading threads look for a chunk before generating it
+    saved: Option<Arc<SavedChunks>>,
+    /// Changes made elsewhere to chunks that hadn't arrived yet, by position in the chunk
+    deferred_edits: HashMap<ChunkKey, Vec<LocalEdit>>,
 }
 
 impl ChunkManager {
@@ -18,73 +94,392 @@ impl ChunkManager {
             loaded: HashMap::new(),
             pending: HashSet::new(),
             view_distance,
+            centers: Vec::new(),
             tx,
             rx,
+            newly_loaded: Vec::new(),
+            newly_unloaded: Vec::new(),
+            dirty: HashSet::new(),
+            timings: HashMap::new(),
+            chunks_generated: 0,
+            generate_time: Duration::ZERO,
+            chunks_reused: 0,
+            chunks_from_disk: 0,
+            disk_time: Duration::ZERO,
+            recent: RecentChunks::new(DEFAULT_RECENT_CHUNKS),
+            mesh_cache: None,
+            generator: Arc::new(WorldGen::default()),
+            loader: ChunkLoader::new(),
+            saved: None,
+            deferred_edits: HashMap::new(),
+        }
+    }
+
+    /// Reuses meshes from `cache` when a chunk and its neighbours are unchanged
+    pub fn set_mesh_cache(&mut self, cache: MeshCache) {
+        self.mesh_cache = Some(cache);
+    }
+
+    /// Loads chunks saved whole in `saved` rather than generating them. The lookup happens on
+    /// the loading threads, and a chunk that isn't there is generated as before.
+    pub fn set_saved_chunks(&mut self, saved: SavedChunks) {
+        self.saved = Some(Arc::new(saved));
+    }
+
+    pub fn generator(&self) -> &WorldGen {
+        &self.generator
+    }
+
+    /// Generates chunks with `generator` from now on. If it differs from the current one,
+    /// every chunk is unloaded and chunks still generating are thrown away, so the next
+    /// update brings the world back in as the new generator makes it.
+    pub fn set_generator(&mut self, generator: WorldGen) {
+        if *self.generator == generator {
+            return;
+        }
+        self.generator = Arc::new(generator);
+        // Threads still at work send into the old channel, which nobody reads any more
+        self.loader.cancel_queued();
+        (self.tx, self.rx) = unbounded();
+        self.pending.clear();
+        self.newly_unloaded.extend(self.loaded.keys().copied());
+        self.loaded.clear();
+        self.timings.clear();
+        self.dirty.clear();
+        self.recent.clear();
+        self.deferred_edits.clear();
+        let centers = std::mem::take(&mut self.centers);
+        self.update_chunks_around(&centers);
+    }
+
+    pub fn flush_mesh_cache(&mut self) {
+        if let Some(cache) = &mut self.mesh_cache {
+            if let Err(e) = cache.flush() {
+                warn!("Failed to write mesh cache: {}", e);
+            }
         }
     }
 
     pub fn update_chunks(&mut self, camera_pos: Vec3) {
-        let cam_chunk = (
-            (camera_pos.x / CHUNK_SIZE as f32).floor() as i32,
-            (camera_pos.y / CHUNK_SIZE as f32).floor() as i32,
-            (camera_pos.z / CHUNK_SIZE as f32).floor() as i32,
-        );
+        self.update_chunks_around(&[camera_pos]);
+    }
+
+    /// Keeps chunks loaded within view distance of any of the given points, e.g. every
+    /// connected player on a server
+    pub fn update_chunks_around(&mut self, centers: &[Vec3]) {
+        self.centers = centers.to_vec();
+        let center_chunks: Vec<(i32, i32, i32)> = centers.iter().map(|&p| Self::chunk_key_at(p)).collect();
         // Request new chunks in view distance
-        for dx in -self.view_distance..=self.view_distance {
-            for dy in -self.view_distance..=self.view_distance {
-                for dz in -self.view_distance..=self.view_distance {
-                    let pos = (cam_chunk.0 + dx, cam_chunk.1 + dy, cam_chunk.2 + dz);
-                    if !self.loaded.contains_key(&pos) && !self.pending.contains(&pos) {
-                        let chunk_pos = Vec3::new(
-                            pos.0 as f32 * CHUNK_SIZE as f32,
-                            pos.1 as f32 * CHUNK_SIZE as f32,
-                            pos.2 as f32 * CHUNK_SIZE as f32,
-                        );
-                        let tx = self.tx.clone();
-                        self.pending.insert(pos);
-                        std::thread::spawn(move || {
-                            let chunk = Chunk::new(chunk_pos);
-                            tx.send((pos.0, pos.1, pos.2, chunk)).ok();
-                        });
+        for cam_chunk in &center_chunks {
+            // Falling into the void must not ask for ever more empty chunks on the way down
+            let lowest = (MIN_CHUNK_Y - cam_chunk.1).max(-self.view_distance);
+            for dx in -self.view_distance..=self.view_distance {
+                for dy in lowest..=self.view_distance {
+                    for dz in -self.view_distance..=self.view_distance {
+                        let pos = (cam_chunk.0 + dx, cam_chunk.1 + dy, cam_chunk.2 + dz);
+                        if !self.loaded.contains_key(&pos) && !self.pending.contains(&pos) {
+                            let chunk_pos = Vec3::new(
+                                pos.0 as f32 * CHUNK_SIZE as f32,
+                                pos.1 as f32 * CHUNK_SIZE as f32,
+                                pos.2 as f32 * CHUNK_SIZE as f32,
+                            );
+                            self.pending.insert(pos);
+                            if let Some(blocks) = self.recent.take(pos) {
+                                let mut chunk = Chunk::empty(chunk_pos);
+                                chunk.blocks = *blocks;
+                                self.tx.send((pos, chunk, ChunkSource::Recent)).ok();
+                                continue;
+                            }
+                            self.loader.request(LoadJob {
+                                key: pos,
+                                position: chunk_pos,
+                                generator: Arc::clone(&self.generator),
+                                saved: self.saved.clone(),
+                                done: self.tx.clone(),
+                            });
+                        }
                     }
                 }
             }
         }
         // Unload distant chunks
-        self.loaded.retain(|&(x, y, z), _| {
-            (x - cam_chunk.0).abs() <= self.view_distance &&
-            (y - cam_chunk.1).abs() <= self.view_distance &&
-            (z - cam_chunk.2).abs() <= self.view_distance
+        let view_distance = self.view_distance;
+        let unloaded = &mut self.newly_unloaded;
+        let timings = &mut self.timings;
+        let dirty = &mut self.dirty;
+        let in_view = |key: &ChunkKey| center_chunks.iter().any(|c| {
+            (key.0 - c.0).abs() <= view_distance && (key.1 - c.1).abs() <= view_distance && (key.2 - c.2).abs() <= view_distance
         });
+        self.deferred_edits.retain(|key, _| in_view(key));
+        for key in self.loader.retain_queued(in_view) {
+            self.pending.remove(&key);
+        }
+        let recent = &mut self.recent;
+        self.loaded.retain(|&(x, y, z), chunk| {
+            let keep = in_view(&(x, y, z));
+            if !keep {
+                unloaded.push((x, y, z));
+                timings.remove(&(x, y, z));
+                dirty.remove(&(x, y, z));
+                recent.store((x, y, z), Box::new(chunk.blocks));
+            }
+            keep
+        });
+    }
+
+    /// Changes the view distance, clamped to MIN_VIEW_DISTANCE..=MAX_VIEW_DISTANCE, and
+    /// right away requests the chunks now in view and unloads those beyond it. Returns
+    /// the distance used.
+    pub fn set_view_distance(&mut self, view_distance: i32) -> i32 {
+        let view_distance = view_distance.clamp(MIN_VIEW_DISTANCE, MAX_VIEW_DISTANCE);
+        if view_distance != self.view_distance {
+            self.view_distance = view_distance;
+            let centers = std::mem::take(&mut self.centers);
+            self.update_chunks_around(&centers);
+        }
+        view_distance
+    }
+
+    /// Receives finished chunks without meshing them, for headless use on the server
+    pub fn poll_generated(&mut self) {
+        while let Ok((key, mut chunk, source)) = self.rx.try_recv() {
+            self.pending.remove(&key);
+            self.apply_deferred_edits(key, &mut chunk);
+            self.loaded.insert(key, chunk);
+            let generate = self.record_source(source);
+            self.timings.insert(key, ChunkTimings { generate, mesh: Duration::ZERO });
+            self.newly_loaded.push(key);
+        }
     }
 
     /// Call this every frame to receive finished chunks
     pub fn poll_new_chunks(&mut self, device: &wgpu::Device) {
         let mut to_remesh = Vec::new();
-        while let Ok((x, y, z, chunk)) = self.rx.try_recv() {
-            to_remesh.push(((x, y, z), chunk));
-            self.pending.remove(&(x, y, z));
+        while let Ok((key, mut chunk, source)) = self.rx.try_recv() {
+            self.apply_deferred_edits(key, &mut chunk);
+            to_remesh.push((key, chunk, source));
+            self.pending.remove(&key);
         }
-        for ((x, y, z), mut chunk) in to_remesh {
-            chunk.generate_mesh(self);
+        for ((x, y, z), mut chunk, source) in to_remesh {
+            let start = Instant::now();
+            let light = chunk.light_volume(self);
+            let hash = self.mesh_cache.is_some().then(|| chunk.mesh_hash(self, &light));
+            let cached = match (&mut self.mesh_cache, hash) {
+                (Some(cache), Some(hash)) => cache.get((x, y, z), chunk.origin(), hash),
+                _ => None,
+            };
+            match cached {
+                Some(faces) => {
+                    chunk.block_face_instances = faces;
+                    chunk.build_sections();
+                }
+                None => {
+                    chunk.generate_mesh_lit(self, &light);
+                    if let (Some(cache), Some(hash)) = (&mut self.mesh_cache, hash) {
+                        cache.store((x, y, z), chunk.origin(), hash, &chunk.block_face_instances);
+                    }
+                }
+            }
             chunk.build_instance_buffer(device);
+            let generate = self.record_source(source);
+            self.timings.insert((x, y, z), ChunkTimings { generate, mesh: start.elapsed() });
             self.loaded.insert((x, y, z), chunk);
+            self.newly_loaded.push((x, y, z));
+        }
+    }
+
+    fn apply_deferred_edits(&mut self, key: ChunkKey, chunk: &mut Chunk) {
+        for ((x, y, z), block_type) in self.deferred_edits.remove(&key).unwrap_or_default() {
+            chunk.blocks[x][y][z] = block_type;
+        }
+    }
+
+    /// `set_block` for changes made elsewhere, such as on the server. A chunk that hasn't
+    /// arrived yet takes the change when it does, unless it has gone out of view by then.
+    pub fn apply_edit(&mut self, block: (i32, i32, i32), block_type: BlockType) {
+        let key = Self::chunk_key(block);
+        if self.loaded.contains_key(&key) {
+            self.set_block(block, block_type);
+        } else {
+            self.deferred_edits.entry(key).or_default().push((Self::local(block), block_type));
         }
     }
 
+    /// Counts a delivered chunk, returning the time spent generating or reading it
+    fn record_source(&mut self, source: ChunkSource) -> Duration {
+        match source {
+            ChunkSource::Generated(generate) => {
+                self.chunks_generated += 1;
+                self.generate_time += generate;
+                generate
+            }
+            ChunkSource::Disk(read) => {
+                self.chunks_from_disk += 1;
+                self.disk_time += read;
+                read
+            }
+            ChunkSource::Recent => {
+                self.chunks_reused += 1;
+                Duration::ZERO
+            }
+        }
+    }
+
+    /// Waits up to `timeout` for chunks still being generated, throwing them away, so no
+    /// generation thread is cut off by the process exiting
+    pub fn finish_pending(&mut self, timeout: Duration) {
+        // Delivered chunks first: the browser generates in place, and its clock cannot
+        // back a blocking wait
+        while let Ok((key, ..)) = self.rx.try_recv() {
+            self.pending.remove(&key);
+        }
+        // Only chunks a thread has started are worth the wait
+        for key in self.loader.cancel_queued() {
+            self.pending.remove(&key);
+        }
+        let deadline = Instant::now() + timeout;
+        while !self.pending.is_empty() {
+            let received = deadline.checked_duration_since(Instant::now())
+                .and_then(|left| self.rx.recv_timeout(left).ok());
+            let Some((key, ..)) = received else {
+                warn!("Gave up waiting for {} chunks to generate", self.pending.len());
+                return;
+            };
+            self.pending.remove(&key);
+        }
+    }
+
+    /// Keys of chunks that finished loading since the last call
+    pub fn drain_loaded(&mut self) -> Vec<(i32, i32, i32)> {
+        std::mem::take(&mut self.newly_loaded)
+    }
+
+    /// Keys of chunks that were unloaded since the last call
+    pub fn drain_unloaded(&mut self) -> Vec<(i32, i32, i32)> {
+        std::mem::take(&mut self.newly_unloaded)
+    }
+
+    /// Generated chunks waiting in the channel for the next poll
+    pub fn queued_len(&self) -> usize {
+        self.rx.len()
+    }
+
+    /// Chunks whose blocks changed and still wait for a new mesh
+    pub fn dirty_len(&self) -> usize {
+        self.dirty.len()
+    }
+
+    pub fn is_loaded(&self, chunk_key: ChunkKey) -> bool {
+        self.loaded.contains_key(&chunk_key)
+    }
+
     pub fn all_chunks(&self) -> impl Iterator<Item = &Chunk> {
         self.loaded.values()
     }
 
-    pub fn get_block(&self, world_x: i32, world_y: i32, world_z: i32) -> Option<crate::game::world::chunk::BlockType> {
-        let chunk_x = (world_x as f32 / CHUNK_SIZE as f32).floor() as i32;
-        let chunk_y = (world_y as f32 / CHUNK_SIZE as f32).floor() as i32;
-        let chunk_z = (world_z as f32 / CHUNK_SIZE as f32).floor() as i32;
-        let local_x = ((world_x % CHUNK_SIZE as i32) + CHUNK_SIZE as i32) % CHUNK_SIZE as i32;
-        let local_y = ((world_y % CHUNK_SIZE as i32) + CHUNK_SIZE as i32) % CHUNK_SIZE as i32;
-        let local_z = ((world_z % CHUNK_SIZE as i32) + CHUNK_SIZE as i32) % CHUNK_SIZE as i32;
-        self.loaded.get(&(chunk_x, chunk_y, chunk_z)).map(|chunk| {
-            chunk.blocks[local_x as usize][local_y as usize][local_z as usize]
-        })
+    /// Block coordinates containing a world-space point. Blocks are rendered centered on
+    /// their integer coordinates, so each one spans [n - 0.5, n + 0.5) on every axis.
+    pub fn block_coords(pos: Vec3) -> (i32, i32, i32) {
+        let p = (pos + Vec3::splat(0.5)).floor();
+        (p.x as i32, p.y as i32, p.z as i32)
+    }
+
+    /// Key of the chunk containing the given block
+    pub fn chunk_key(block: (i32, i32, i32)) -> (i32, i32, i32) {
+        let cs = CHUNK_SIZE as i32;
+        (block.0.div_euclid(cs), block.1.div_euclid(cs), block.2.div_euclid(cs))
+    }
+
+    /// Key of the chunk containing a world-space point
+    pub fn chunk_key_at(pos: Vec3) -> (i32, i32, i32) {
+        Self::chunk_key(Self::block_coords(pos))
+    }
+
+    /// Position of a block within its chunk
+    fn local(block: (i32, i32, i32)) -> (usize, usize, usize) {
+        let cs = CHUNK_SIZE as i32;
+        (block.0.rem_euclid(cs) as usize, block.1.rem_euclid(cs) as usize, block.2.rem_euclid(cs) as usize)
+    }
+
+    /// Changes a block in a loaded chunk and returns what was there before. The chunk, and
+    /// any neighbour whose mesh lighting reaches the block, is remeshed by the next
+    /// `remesh_dirty`. Replacing a sign drops its text.
+    pub fn set_block(&mut self, block: (i32, i32, i32), block_type: BlockType) -> Option<BlockType> {
+        let key = Self::chunk_key(block);
+        let local = Self::local(block);
+        let chunk = self.loaded.get_mut(&key)?;
+        let previous = std::mem::replace(&mut chunk.blocks[local.0][local.1][local.2], block_type);
+        if previous != block_type {
+            chunk.sign_text.remove(&local);
+            // Chunks whose LightVolume::chunk_bounds hold the block, this one included
+            let near = |l: usize, below: usize| {
+                let mut offsets = vec![0];
+                if l < below {
+                    offsets.push(-1);
+                }
+                if l == CHUNK_SIZE - 1 {
+                    offsets.push(1);
+                }
+                offsets
+            };
+            let headroom = 1 + MESH_LIGHT_HEADROOM as usize;
+            for dx in near(local.0, 1) {
+                for dy in near(local.1, headroom) {
+                    for dz in near(local.2, 1) {
+                        self.dirty.insert((key.0 + dx, key.1 + dy, key.2 + dz));
+                    }
+                }
+            }
+        }
+        Some(previous)
+    }
+
+    /// Rebuilds the meshes of chunks changed by `set_block`
+    pub fn remesh_dirty(&mut self, device: &wgpu::Device) {
+        for key in std::mem::take(&mut self.dirty) {
+            // Meshing reads neighbours through the manager, so take the chunk out meanwhile
+            let Some(mut chunk) = self.loaded.remove(&key) else { continue };
+            let start = Instant::now();
+            chunk.generate_mesh(self);
+            chunk.build_instance_buffer(device);
+            self.timings.entry(key).or_default().mesh = start.elapsed();
+            self.loaded.insert(key, chunk);
+        }
+    }
+
+    /// Text of the sign at `block`, if it has any
+    pub fn sign_text(&self, block: (i32, i32, i32)) -> Option<&str> {
+        self.loaded.get(&Self::chunk_key(block))?.sign_text.get(&Self::local(block)).map(String::as_str)
+    }
+
+    /// Sets the text of the sign at `block`, returning false if there is no sign there
+    pub fn set_sign_text(&mut self, block: (i32, i32, i32), text: String) -> bool {
+        let local = Self::local(block);
+        let Some(chunk) = self.loaded.get_mut(&Self::chunk_key(block)) else { return false };
+        if !matches!(chunk.blocks[local.0][local.1][local.2], BlockType::Sign(_)) {
+            return false;
+        }
+        if text.is_empty() {
+            chunk.sign_text.remove(&local);
+        } else {
+            chunk.sign_text.insert(local, text);
+        }
+        true
+    }
+
+    pub fn get_block(&self, world_x: i32, world_y: i32, world_z: i32) -> Option<BlockType> {
+        let block = (world_x, world_y, world_z);
+        let local = Self::local(block);
+        self.loaded.get(&Self::chunk_key(block)).map(|chunk| chunk.blocks[local.0][local.1][local.2])
+    }
+
+    /// Like `get_block`, but below the lowest chunks, which are never loaded, there is air.
+    /// Collision uses this, so unloaded terrain stays solid without flooring the void.
+    pub fn get_block_or_void(&self, world_x: i32, world_y: i32, world_z: i32) -> Option<BlockType> {
+        let key = Self::chunk_key((world_x, world_y, world_z));
+        match self.get_block(world_x, world_y, world_z) {
+            None if key.1 < MIN_CHUNK_Y => Some(BlockType::Air),
+            block => block,
+        }
     }
 } 
\ No newline at end of file
@@ -0,0 +1,82 @@
+//! Time of day, kept by the server and mirrored by clients.
+//!
+//! A day is DAY_LENGTH server ticks long and starts at sunrise. The sun is up for the first
+//! half and down for the second, which is when players can sleep.
+
+use crate::game::server::scheduler::TICK_RATE;
+
+/// Server ticks in a full day and night, twenty minutes at TICK_RATE
+pub const DAY_LENGTH: u64 = 24_000;
+/// Ticks between telling clients the time, to correct their drift
+pub const TIME_SYNC_INTERVAL: u64 = 200;
+/// Sky color at noon
+const DAY_SKY: [f32; 3] = [0.1, 0.2, 0.3];
+/// Sky color at midnight
+const NIGHT_SKY: [f32; 3] = [0.005, 0.008, 0.02];
+/// How far the sun's path leans toward +z, so faces turned north and south are lit apart
+const SUN_TILT: f32 = 0.35;
+/// Days from one full moon to the next
+const MOON_CYCLE_DAYS: u64 = 8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeOfDay {
+    /// Ticks since the world was created
+    pub ticks: u64,
+}
+
+impl TimeOfDay {
+    pub fn new(ticks: u64) -> Self {
+        Self { ticks }
+    }
+
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    /// The time `seconds` of real time later, for clients between syncs
+    pub fn after(&self, seconds: f32) -> Self {
+        Self::new(self.ticks + (seconds.max(0.0) * TICK_RATE as f32) as u64)
+    }
+
+    /// How far through the current day it is, 0 at sunrise and 0.5 at sunset
+    pub fn phase(&self) -> f32 {
+        (self.ticks % DAY_LENGTH) as f32 / DAY_LENGTH as f32
+    }
+
+    /// Height of the sun, 1 at noon and -1 at midnight
+    pub fn sun_height(&self) -> f32 {
+        (self.phase() * std::f32::consts::TAU).sin()
+    }
+
+    /// Unit vector toward the sun, which rises in +x, passes overhead and sets in -x
+    pub fn sun_direction(&self) -> [f32; 3] {
+        let (sin, cos) = (self.phase() * std::f32::consts::TAU).sin_cos();
+        let length = (1.0 + SUN_TILT * SUN_TILT).sqrt();
+        [cos / length, sin / length, SUN_TILT / length]
+    }
+
+    /// How far through its cycle the moon is: 0 full, 0.5 new
+    pub fn moon_phase(&self) -> f32 {
+        let cycle = DAY_LENGTH * MOON_CYCLE_DAYS;
+        (self.ticks % cycle) as f32 / cycle as f32
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.sun_height() < 0.0
+    }
+
+    /// Brightness of daylight, 0 at night and 1 during the day, fading over dawn and dusk
+    pub fn daylight(&self) -> f32 {
+        (self.sun_height() * 4.0 + 0.5).clamp(0.0, 1.0)
+    }
+
+    pub fn sky_color(&self) -> [f32; 3] {
+        let t = self.daylight();
+        [0, 1, 2].map(|i| NIGHT_SKY[i] + (DAY_SKY[i] - NIGHT_SKY[i]) * t)
+    }
+
+    /// Jumps ahead to the next sunrise
+    pub fn skip_to_morning(&mut self) {
+        self.ticks = (self.ticks / DAY_LENGTH + 1) * DAY_LENGTH;
+    }
+}
@@ -0,0 +1,239 @@
+//! Decals on block faces: the cracks on a block being broken and the scorch marks an
+//! explosion leaves.
+//!
+//! Lasting decals are kept by the chunk their block is in, so they go when it unloads, and
+//! fade out at the end of their lifetime. A block that changes loses its decals. The
+//! textures are drawn here too, into a strip of DECAL_TILE square tiles, so there are no
+//! image files to ship for them.
+
+use std::collections::HashMap;
+use glam::Vec3;
+use image::{Rgba, RgbaImage};
+
+use crate::engine::graphics::DecalVertex;
+use crate::engine::math::Rng;
+use crate::game::world::chunk::FACE_AXES;
+use crate::game::world::chunk_manager::ChunkManager;
+
+type BlockPos = (i32, i32, i32);
+type ChunkKey = (i32, i32, i32);
+
+/// Pixels across each decal texture
+pub const DECAL_TILE: u32 = 16;
+/// Cracks deepen through this many textures as a block breaks
+pub const CRACK_STAGES: u32 = 8;
+const SCORCH_TILE: u32 = CRACK_STAGES;
+const TILE_COUNT: u32 = CRACK_STAGES + 1;
+/// How long scorch marks stay, and the last part of that over which they fade
+pub const SCORCH_SECONDS: f32 = 60.0;
+const FADE_SECONDS: f32 = 10.0;
+/// Blocks beyond the destroyed sphere that are still scorched
+const SCORCH_REACH: f32 = 1.5;
+/// Most lasting decals kept at once; more are not added
+pub const MAX_DECALS: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecalKind {
+    Scorch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decal {
+    pub kind: DecalKind,
+    pub block: BlockPos,
+    /// Index into FACE_AXES
+    pub face: usize,
+    /// Seconds left
+    pub life: f32,
+    /// Opacity before fading
+    pub alpha: f32,
+}
+
+impl Decal {
+    /// Opacity now, fading over the last FADE_SECONDS
+    pub fn opacity(&self) -> f32 {
+        self.alpha * (self.life / FADE_SECONDS).clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Decals {
+    chunks: HashMap<ChunkKey, Vec<Decal>>,
+    count: usize,
+}
+
+impl Decals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Decal> {
+        self.chunks.values().flatten()
+    }
+
+    /// Adds `decal`, replacing any of its kind on the same face. Returns false if there was
+    /// no room for it.
+    pub fn add(&mut self, decal: Decal) -> bool {
+        let decals = self.chunks.entry(ChunkManager::chunk_key(decal.block)).or_default();
+        if let Some(old) = decals.iter_mut().find(|d| d.kind == decal.kind && d.block == decal.block && d.face == decal.face) {
+            *old = decal;
+            return true;
+        }
+        if self.count >= MAX_DECALS {
+            return false;
+        }
+        decals.push(decal);
+        self.count += 1;
+        true
+    }
+
+    /// Ages every decal `delta_time` seconds, dropping those whose time is up
+    pub fn update(&mut self, delta_time: f32) {
+        let mut count = 0;
+        self.chunks.retain(|_, decals| {
+            for decal in decals.iter_mut() {
+                decal.life -= delta_time;
+            }
+            decals.retain(|decal| decal.life > 0.0);
+            count += decals.len();
+            !decals.is_empty()
+        });
+        self.count = count;
+    }
+
+    /// Drops the decals on `block`, which has changed
+    pub fn remove_block(&mut self, block: BlockPos) {
+        let key = ChunkManager::chunk_key(block);
+        let Some(decals) = self.chunks.get_mut(&key) else { return };
+        let before = decals.len();
+        decals.retain(|decal| decal.block != block);
+        self.count -= before - decals.len();
+        if decals.is_empty() {
+            self.chunks.remove(&key);
+        }
+    }
+
+    /// Drops the decals of a chunk that unloaded
+    pub fn remove_chunk(&mut self, key: ChunkKey) {
+        if let Some(decals) = self.chunks.remove(&key) {
+            self.count -= decals.len();
+        }
+    }
+
+    /// Scorches the open faces around an explosion that turn toward it, darker nearer the
+    /// blast. Call once its destroyed blocks are gone. Returns how many were scorched.
+    pub fn scorch(&mut self, chunks: &ChunkManager, center: Vec3, power: f32) -> usize {
+        let reach = power + SCORCH_REACH;
+        let around = ChunkManager::block_coords(center);
+        let r = reach.ceil() as i32;
+        let mut scorched = 0;
+        for x in -r..=r {
+            for y in -r..=r {
+                for z in -r..=r {
+                    let block = (around.0 + x, around.1 + y, around.2 + z);
+                    let position = Vec3::new(block.0 as f32, block.1 as f32, block.2 as f32);
+                    let distance = position.distance(center);
+                    if distance > reach || !chunks.get_block(block.0, block.1, block.2).is_some_and(|b| b.is_solid()) {
+                        continue;
+                    }
+                    for (face, [normal, _, _]) in FACE_AXES.iter().enumerate() {
+                        let normal = Vec3::new(normal.0 as f32, normal.1 as f32, normal.2 as f32);
+                        let outside = (block.0 + normal.x as i32, block.1 + normal.y as i32, block.2 + normal.z as i32);
+                        let open = chunks.get_block(outside.0, outside.1, outside.2).is_some_and(|b| !b.is_solid());
+                        if !open || normal.dot(center - position) <= 0.0 {
+                            continue;
+                        }
+                        let alpha = (1.0 - distance / reach).clamp(0.0, 1.0).sqrt();
+                        let decal = Decal { kind: DecalKind::Scorch, block, face, life: SCORCH_SECONDS, alpha };
+                        if self.add(decal) {
+                            scorched += 1;
+                        }
+                    }
+                }
+            }
+        }
+        scorched
+    }
+
+    /// Triangles for every decal, plus cracks on each face of `breaking` as far along as its
+    /// progress from 0 to 1
+    pub fn vertices(&self, breaking: Option<(BlockPos, f32)>) -> Vec<DecalVertex> {
+        let mut vertices = Vec::new();
+        for decal in self.iter() {
+            let tile = match decal.kind {
+                DecalKind::Scorch => SCORCH_TILE,
+            };
+            face_quad(&mut vertices, decal.block, decal.face, tile, decal.opacity());
+        }
+        if let Some((block, progress)) = breaking {
+            let stage = ((progress * CRACK_STAGES as f32) as u32).min(CRACK_STAGES - 1);
+            for face in 0..FACE_AXES.len() {
+                face_quad(&mut vertices, block, face, stage, 1.0);
+            }
+        }
+        vertices
+    }
+}
+
+/// Two triangles covering `face` of `block` with tile `tile` of the atlas
+fn face_quad(vertices: &mut Vec<DecalVertex>, block: BlockPos, face: usize, tile: u32, alpha: f32) {
+    let axis = |(x, y, z): (i32, i32, i32)| Vec3::new(x as f32, y as f32, z as f32);
+    let [normal, right, up] = FACE_AXES[face].map(axis);
+    // Blocks are centered on their coordinates
+    let center = axis(block) + normal * 0.5;
+    let (u0, u1) = (tile as f32 / TILE_COUNT as f32, (tile + 1) as f32 / TILE_COUNT as f32);
+    let corners = [(-0.5, -0.5, u0, 1.0), (0.5, -0.5, u1, 1.0), (0.5, 0.5, u1, 0.0), (-0.5, 0.5, u0, 0.0)];
+    for i in [0, 1, 2, 2, 3, 0] {
+        let (x, y, u, v) = corners[i];
+        let position = center + right * x + up * y;
+        vertices.push(DecalVertex { position: position.to_array(), uv: [u, v], alpha });
+    }
+}
+
+/// The decal textures: CRACK_STAGES crack tiles, each with more cracks than the last, then
+/// scorch
+pub fn atlas() -> RgbaImage {
+    let mut atlas = RgbaImage::new(DECAL_TILE * TILE_COUNT, DECAL_TILE);
+    // The same every run, so the cracks look alike between sessions
+    let mut rng = Rng::new(0xDECA1);
+    // Each stage keeps the cracks of the one before and grows them
+    let mut cracks = vec![false; (DECAL_TILE * DECAL_TILE) as usize];
+    let mut tips: Vec<(i32, i32)> = Vec::new();
+    let size = DECAL_TILE as i32;
+    for stage in 0..CRACK_STAGES {
+        if stage % 2 == 0 {
+            tips.push((size / 2 + rng.below(5) as i32 - 2, size / 2 + rng.below(5) as i32 - 2));
+        }
+        for tip in tips.iter_mut() {
+            for _ in 0..3 {
+                cracks[(tip.1 * size + tip.0) as usize] = true;
+                tip.0 = (tip.0 + rng.below(3) as i32 - 1).clamp(0, size - 1);
+                tip.1 = (tip.1 + rng.below(3) as i32 - 1).clamp(0, size - 1);
+            }
+        }
+        for (i, &cracked) in cracks.iter().enumerate() {
+            if cracked {
+                let (x, y) = (i as u32 % DECAL_TILE, i as u32 / DECAL_TILE);
+                atlas.put_pixel(stage * DECAL_TILE + x, y, Rgba([20, 20, 20, 200]));
+            }
+        }
+    }
+    let middle = (DECAL_TILE as f32 - 1.0) / 2.0;
+    for y in 0..DECAL_TILE {
+        for x in 0..DECAL_TILE {
+            // Darkest in the middle, frayed toward the edges
+            let distance = Vec3::new(x as f32 - middle, y as f32 - middle, 0.0).length() / (middle + 1.0);
+            let alpha = (1.0 - distance).max(0.0) * (0.7 + 0.3 * rng.next_f32());
+            atlas.put_pixel(SCORCH_TILE * DECAL_TILE + x, y, Rgba([15, 12, 10, (alpha * 230.0) as u8]));
+        }
+    }
+    atlas
+}
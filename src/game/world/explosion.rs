@@ -0,0 +1,88 @@
+//! Explosions: a sphere of destroyed blocks, plus damage and knockback that fall off with
+//! distance from the center.
+
+use glam::Vec3;
+
+use crate::game::world::behavior::{self, BlockChange, BlockPos};
+use crate::game::world::chunk::BlockType;
+use crate::game::world::chunk_manager::ChunkManager;
+
+/// Radius of a TNT explosion in blocks
+pub const TNT_POWER: f32 = 4.0;
+/// Damage at the center of an explosion of power 1; scales with power
+pub const EXPLOSION_DAMAGE: f32 = 2.0;
+/// Speed given to things at the center, in blocks per second
+pub const EXPLOSION_KNOCKBACK: f32 = 12.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Explosion {
+    pub center: Vec3,
+    /// Radius of the destroyed sphere; things are hurt out to twice this
+    pub power: f32,
+}
+
+/// What an explosion does to blocks
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlastResult {
+    /// Destroyed blocks and what they drop
+    pub change: BlockChange,
+    /// TNT caught in the blast, left in place for the caller to prime
+    pub tnt: Vec<BlockPos>,
+}
+
+impl Explosion {
+    pub fn new(center: Vec3, power: f32) -> Self {
+        Self { center, power }
+    }
+
+    /// Clears every block within `power` of the center except fluids, and collects the TNT
+    /// in range instead of destroying it
+    pub fn affect_blocks(&self, chunks: &ChunkManager) -> BlastResult {
+        let mut result = BlastResult::default();
+        result.change.sound = Some("explosion");
+        let center = ChunkManager::block_coords(self.center);
+        let reach = self.power.ceil() as i32;
+        for x in -reach..=reach {
+            for y in -reach..=reach {
+                for z in -reach..=reach {
+                    let block = (center.0 + x, center.1 + y, center.2 + z);
+                    let offset = Vec3::new(block.0 as f32, block.1 as f32, block.2 as f32) - self.center;
+                    if offset.length() > self.power {
+                        continue;
+                    }
+                    match chunks.get_block(block.0, block.1, block.2) {
+                        Some(BlockType::Tnt) => {
+                            result.tnt.push(block);
+                            result.change.edits.push((block, BlockType::Air));
+                        }
+                        Some(block_type) if block_type != BlockType::Air && !block_type.is_fluid() => {
+                            result.change.edits.push((block, BlockType::Air));
+                            // Blasted terrain is destroyed rather than dropped, which would
+                            // litter the crater with items
+                            if !block_type.is_solid() {
+                                result.change.drops.extend(behavior::drops(block_type, None));
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// How hard the blast hits a point, from 1 at the center to 0 at twice the power
+    pub fn impact(&self, point: Vec3) -> f32 {
+        (1.0 - point.distance(self.center) / (2.0 * self.power)).max(0.0)
+    }
+
+    pub fn damage_at(&self, point: Vec3) -> f32 {
+        self.impact(point) * EXPLOSION_DAMAGE * self.power
+    }
+
+    /// Velocity added to something at `point`, pushing it away from the center and a little up
+    pub fn knockback_at(&self, point: Vec3) -> Vec3 {
+        let away = (point - self.center).try_normalize().unwrap_or(Vec3::Y);
+        (away + Vec3::Y * 0.5).normalize() * EXPLOSION_KNOCKBACK * self.impact(point)
+    }
+}
@@ -0,0 +1,140 @@
+//! Far terrain imposters: the heightfield that stands in for the world past the loaded
+//! chunks.
+//!
+//! Square levels of cells are centered on the viewer, each level's cells twice the size of
+//! the one inside it, so detail falls off with distance. Cell corners sample the
+//! generator's surface directly, so nothing out there is generated or loaded. Cells wholly
+//! inside the loaded chunks are left out; the rest may overlap them, which the renderer's
+//! depth test hides.
+
+use glam::Vec3;
+
+use crate::engine::graphics::far_terrain::FarVertex;
+use crate::game::world::chunk::{BlockType, CHUNK_SIZE};
+use crate::game::world::chunk_manager::ChunkManager;
+use crate::game::world::material::Tint;
+use crate::game::world::world_map::MapCell;
+use crate::game::world::worldgen::WorldGen;
+
+/// Levels of detail, finest first
+pub const FAR_LEVELS: usize = 3;
+/// Blocks across a cell of the finest level
+pub const FINEST_CELL: i32 = 4;
+/// Cells across each level
+pub const LEVEL_CELLS: i32 = 48;
+/// How much the grass texture darkens its biome's color
+const GRASS_SHADE: f32 = 0.75;
+
+/// Blocks across a cell of `level`
+pub fn cell_size(level: usize) -> i32 {
+    FINEST_CELL << level
+}
+
+/// Blocks from the viewer to the edge of the far terrain
+pub fn reach() -> f32 {
+    (LEVEL_CELLS / 2 * cell_size(FAR_LEVELS - 1)) as f32
+}
+
+/// What a mesh was built for; a new one is only needed when this changes
+#[derive(Debug, Clone, PartialEq)]
+struct BuiltFor {
+    generator: WorldGen,
+    center: (i32, i32),
+    /// Column bounds of the loaded chunks, min inclusive and max exclusive
+    loaded: ((i32, i32), (i32, i32)),
+}
+
+pub struct FarTerrain {
+    pub enabled: bool,
+    built: Option<BuiltFor>,
+}
+
+impl Default for FarTerrain {
+    fn default() -> Self {
+        Self { enabled: true, built: None }
+    }
+}
+
+impl FarTerrain {
+    pub fn set_enabled(&mut self, on: bool) {
+        self.enabled = on;
+        self.built = None;
+    }
+
+    /// A new mesh for a viewer at `eye` with chunks loaded `view_distance` chunks around
+    /// them, or None if the last one still holds. Disabled, the new mesh is empty.
+    pub fn update(&mut self, generator: &WorldGen, eye: Vec3, view_distance: i32) -> Option<Vec<FarVertex>> {
+        // Every level is centered on the same point, a whole number of the coarsest cells,
+        // so each one's hole lines up with the level inside it
+        let snap = 2 * cell_size(FAR_LEVELS - 1);
+        let center = ((eye.x / snap as f32).round() as i32 * snap, (eye.z / snap as f32).round() as i32 * snap);
+        let chunk = ChunkManager::chunk_key_at(eye);
+        let cs = CHUNK_SIZE as i32;
+        let loaded = (
+            ((chunk.0 - view_distance) * cs, (chunk.2 - view_distance) * cs),
+            ((chunk.0 + view_distance + 1) * cs, (chunk.2 + view_distance + 1) * cs),
+        );
+        let built = BuiltFor { generator: generator.clone(), center, loaded };
+        if self.built.as_ref() == Some(&built) {
+            return None;
+        }
+        self.built = Some(built);
+        if !self.enabled {
+            return Some(Vec::new());
+        }
+        Some(build(generator, eye, center, loaded))
+    }
+}
+
+/// Color of the far terrain at a column
+fn surface_color(generator: &WorldGen, x: i32, z: i32, height: i32, block: BlockType) -> [f32; 3] {
+    match block {
+        BlockType::Grass => generator.biome(x, z).color(Tint::Grass).map(|c| c * GRASS_SHADE),
+        block => MapCell { height, block }.color(None),
+    }
+}
+
+/// The far terrain's triangles around `center`, farthest from `eye` first
+pub fn build(generator: &WorldGen, eye: Vec3, center: (i32, i32), loaded: ((i32, i32), (i32, i32))) -> Vec<FarVertex> {
+    let inside = |min: (i32, i32), max: (i32, i32), x: i32, z: i32, size: i32| {
+        x >= min.0 && z >= min.1 && x + size <= max.0 && z + size <= max.1
+    };
+    let mut vertices = Vec::new();
+    for level in (0..FAR_LEVELS).rev() {
+        let size = cell_size(level);
+        let half = LEVEL_CELLS / 2 * size;
+        let origin = (center.0 - half, center.1 - half);
+        let side = LEVEL_CELLS as usize + 1;
+        // Corners are shared between cells, so each is sampled once
+        let corners: Vec<Option<FarVertex>> = (0..side * side).map(|i| {
+            let (x, z) = (origin.0 + (i % side) as i32 * size, origin.1 + (i / side) as i32 * size);
+            generator.surface(x, z).map(|(height, block)| FarVertex {
+                // Blocks are centered on their coordinates, so the column's top face and
+                // its corner sit half a block off
+                position: [x as f32 - 0.5, height as f32 + 0.5, z as f32 - 0.5],
+                color: surface_color(generator, x, z, height, block),
+            })
+        }).collect();
+        let finer_half = half / 2;
+        let mut cells: Vec<(f32, [FarVertex; 4])> = Vec::new();
+        for j in 0..LEVEL_CELLS as usize {
+            for i in 0..LEVEL_CELLS as usize {
+                let (x, z) = (origin.0 + i as i32 * size, origin.1 + j as i32 * size);
+                let finer = (center.0 - finer_half, center.1 - finer_half);
+                let finer_max = (center.0 + finer_half, center.1 + finer_half);
+                if (level > 0 && inside(finer, finer_max, x, z, size)) || inside(loaded.0, loaded.1, x, z, size) {
+                    continue;
+                }
+                let corner = |di: usize, dj: usize| corners[(j + dj) * side + i + di];
+                let (Some(a), Some(b), Some(c), Some(d)) = (corner(0, 0), corner(1, 0), corner(0, 1), corner(1, 1)) else { continue };
+                let middle = Vec3::new(x as f32 + size as f32 * 0.5, eye.y, z as f32 + size as f32 * 0.5);
+                cells.push((middle.distance_squared(eye), [a, b, c, d]));
+            }
+        }
+        cells.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (_, [a, b, c, d]) in cells {
+            vertices.extend_from_slice(&[a, c, b, b, c, d]);
+        }
+    }
+    vertices
+}
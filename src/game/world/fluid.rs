@@ -0,0 +1,185 @@
+//! Flowing water and lava, advanced by scheduled block ticks.
+//!
+//! A source block (level 0) feeds flowing fluid around it, weaker by the fluid's level step
+//! per block out to MAX_FLOW_LEVEL. Fluid falls before it spreads sideways, and sideways flow
+//! heads for the nearest drop within FLOW_SEARCH_DISTANCE when there is one. Flow that loses
+//! what fed it drains away. The server ticks the cells around every change, so a fluid
+//! advances one block per its tick delay.
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::game::world::behavior::{BlockChange, BlockPos};
+use crate::game::world::chunk::BlockType;
+use crate::game::world::chunk_manager::ChunkManager;
+
+/// Weakest level flowing water reaches before it stops spreading
+pub const MAX_FLOW_LEVEL: u8 = 7;
+/// Level of fluid fed from above, which spreads like a source but drains like flow
+pub const FALLING: u8 = 8;
+/// How far sideways flow looks for a way down
+const FLOW_SEARCH_DISTANCE: usize = 4;
+
+const HORIZONTAL: [(i32, i32, i32); 4] = [(1, 0, 0), (-1, 0, 0), (0, 0, 1), (0, 0, -1)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fluid {
+    Water,
+    /// Slow and short-ranged
+    Lava,
+}
+
+impl Fluid {
+    /// The fluid in a block and its level
+    pub fn of(block_type: BlockType) -> Option<(Fluid, u8)> {
+        match block_type {
+            BlockType::Water(level) => Some((Fluid::Water, level)),
+            BlockType::Lava(level) => Some((Fluid::Lava, level)),
+            _ => None,
+        }
+    }
+
+    pub fn block(self, level: u8) -> BlockType {
+        match self {
+            Fluid::Water => BlockType::Water(level),
+            Fluid::Lava => BlockType::Lava(level),
+        }
+    }
+
+    /// Server ticks between a fluid block changing and its neighbours reacting
+    pub fn tick_delay(self) -> u64 {
+        match self {
+            Fluid::Water => 5,
+            Fluid::Lava => 30,
+        }
+    }
+
+    /// Levels lost per block of sideways flow
+    fn level_step(self) -> u8 {
+        match self {
+            Fluid::Water => 1,
+            Fluid::Lava => 2,
+        }
+    }
+
+    /// The level of this fluid in the block, if it holds this fluid
+    fn level_in(self, block_type: Option<BlockType>) -> Option<u8> {
+        block_type.and_then(Fluid::of).filter(|&(fluid, _)| fluid == self).map(|(_, level)| level)
+    }
+}
+
+/// Height of the fluid surface above the bottom of its block, matching how the renderer
+/// draws it: falling fluid fills the block and flow gets lower with each level
+pub fn surface_height(level: u8) -> f32 {
+    if level >= FALLING {
+        return 1.0;
+    }
+    (FALLING - level) as f32 / 8.0 * 0.875
+}
+
+fn offset(block: BlockPos, by: (i32, i32, i32)) -> BlockPos {
+    (block.0 + by.0, block.1 + by.1, block.2 + by.2)
+}
+
+fn get(chunks: &ChunkManager, block: BlockPos) -> Option<BlockType> {
+    chunks.get_block(block.0, block.1, block.2)
+}
+
+/// Level a fluid block passes on to its neighbours, before adding the level step
+fn feed_level(level: u8) -> u8 {
+    if level >= FALLING { 0 } else { level }
+}
+
+/// Whether the fluid can move into the cell: air, or its own flow that is not falling
+fn is_open(fluid: Fluid, block_type: Option<BlockType>) -> bool {
+    block_type == Some(BlockType::Air) || fluid.level_in(block_type).is_some_and(|level| level != 0 && level != FALLING)
+}
+
+/// Whether the fluid spreading sideways at `level` would change the cell
+fn accepts(fluid: Fluid, block_type: Option<BlockType>, level: u8) -> bool {
+    block_type == Some(BlockType::Air)
+        || fluid.level_in(block_type).is_some_and(|current| current != 0 && current != FALLING && current > level)
+}
+
+/// Whether the fluid at `block` would fall rather than spread
+fn can_fall(fluid: Fluid, chunks: &ChunkManager, block: BlockPos) -> bool {
+    let below = get(chunks, offset(block, (0, -1, 0)));
+    below == Some(BlockType::Air) || fluid.level_in(below).is_some_and(|level| level != 0)
+}
+
+/// Level the flow at `block` should have given its neighbours, or None if nothing feeds it
+/// any more
+fn fed_level(fluid: Fluid, chunks: &ChunkManager, block: BlockPos) -> Option<u8> {
+    if fluid.level_in(get(chunks, offset(block, (0, 1, 0)))).is_some() {
+        return Some(FALLING);
+    }
+    HORIZONTAL
+        .iter()
+        .filter_map(|&d| fluid.level_in(get(chunks, offset(block, d))))
+        .map(|level| feed_level(level) + fluid.level_step())
+        .min()
+        .filter(|&level| level <= MAX_FLOW_LEVEL)
+}
+
+/// Horizontal steps from `start` to the nearest open cell water could fall from, if one is
+/// within FLOW_SEARCH_DISTANCE
+fn drop_distance(fluid: Fluid, chunks: &ChunkManager, start: BlockPos) -> Option<usize> {
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([(start, 0)]);
+    while let Some((block, distance)) = queue.pop_front() {
+        if can_fall(fluid, chunks, block) {
+            return Some(distance);
+        }
+        if distance == FLOW_SEARCH_DISTANCE {
+            continue;
+        }
+        for d in HORIZONTAL {
+            let next = offset(block, d);
+            if is_open(fluid, get(chunks, next)) && seen.insert(next) {
+                queue.push_back((next, distance + 1));
+            }
+        }
+    }
+    None
+}
+
+/// What a scheduled tick does to the fluid at `block`, or None if it is settled
+pub fn tick(chunks: &ChunkManager, block: BlockPos) -> Option<BlockChange> {
+    let (fluid, mut level) = get(chunks, block).and_then(Fluid::of)?;
+    let mut edits = Vec::new();
+    if level != 0 {
+        match fed_level(fluid, chunks, block) {
+            None => return Some(BlockChange::single(block, BlockType::Air)),
+            Some(fed) if fed != level => {
+                level = fed;
+                edits.push((block, fluid.block(level)));
+            }
+            Some(_) => (),
+        }
+    }
+
+    let below = offset(block, (0, -1, 0));
+    if can_fall(fluid, chunks, block) {
+        if get(chunks, below) != Some(fluid.block(FALLING)) {
+            edits.push((below, fluid.block(FALLING)));
+        }
+    } else {
+        let spread = feed_level(level) + fluid.level_step();
+        if spread <= MAX_FLOW_LEVEL {
+            // Sides already holding the flow count too, so water keeps heading for the same
+            // drop once it has reached it
+            let sides: Vec<(BlockPos, Option<usize>)> = HORIZONTAL
+                .iter()
+                .map(|&d| offset(block, d))
+                .filter(|&side| is_open(fluid, get(chunks, side)))
+                .map(|side| (side, drop_distance(fluid, chunks, side)))
+                .collect();
+            let nearest = sides.iter().filter_map(|&(_, distance)| distance).min();
+            for (side, distance) in sides {
+                if (nearest.is_none() || distance == nearest) && accepts(fluid, get(chunks, side), spread) {
+                    edits.push((side, fluid.block(spread)));
+                }
+            }
+        }
+    }
+    (!edits.is_empty()).then(|| BlockChange { edits, ..BlockChange::default() })
+}
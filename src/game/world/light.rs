@@ -0,0 +1,198 @@
+//! Light propagation over a box of loaded blocks.
+//!
+//! Sky light enters from above at full strength and falls straight down through open
+//! columns without dimming, then spreads sideways and into caves, losing one level per
+//! block. Blocks that glow, such as lava, light their surroundings the same way. Solid blocks
+//! stop light.
+//!
+//! Meshing lights each corner of a face from the four cells in front of it that share the
+//! corner, darkening corners tucked against solid blocks, and the world shader blends the
+//! corners across the face.
+
+use std::collections::VecDeque;
+
+use crate::game::world::chunk::BlockType;
+use crate::game::world::chunk_manager::ChunkManager;
+
+pub const MAX_LIGHT: u8 = 15;
+/// How far above a chunk meshing looks for a roof; anything higher counts as open sky
+pub const MESH_LIGHT_HEADROOM: i32 = 8;
+/// Brightness lost per light level below full
+const LIGHT_FALLOFF: f32 = 0.8;
+/// Brightness of a corner in total darkness, so caves aren't pitch black
+const MIN_BRIGHTNESS: f32 = 0.08;
+/// Brightness of a corner by how many of its three neighbours around the face are solid
+const OCCLUSION_BRIGHTNESS: [f32; 4] = [1.0, 0.8, 0.65, 0.5];
+
+type BlockPos = (i32, i32, i32);
+
+pub struct LightVolume {
+    /// Inclusive block bounds
+    pub min: BlockPos,
+    pub max: BlockPos,
+    size: (usize, usize, usize),
+    levels: Vec<u8>,
+    solid: Vec<bool>,
+}
+
+impl LightVolume {
+    /// Computes light for every block between `min` and `max` inclusive. Blocks in unloaded
+    /// chunks count as open air, and everything above the box as open sky.
+    pub fn compute(chunks: &ChunkManager, min: BlockPos, max: BlockPos) -> Self {
+        Self::compute_with(min, max, |(x, y, z)| chunks.get_block(x, y, z))
+    }
+
+    /// Like `compute`, reading blocks through `block`, which gives None where nothing is
+    /// loaded
+    pub fn compute_with(min: BlockPos, max: BlockPos, block: impl Fn(BlockPos) -> Option<BlockType>) -> Self {
+        let size = ((max.0 - min.0 + 1).max(0) as usize, (max.1 - min.1 + 1).max(0) as usize, (max.2 - min.2 + 1).max(0) as usize);
+        let cells = size.0 * size.1 * size.2;
+        let mut volume = Self { min, max, size, levels: vec![0; cells], solid: Vec::new() };
+        let blocks: Vec<_> = (0..cells).map(|i| block(volume.position(i))).collect();
+        volume.solid = blocks.iter().map(|b| b.is_some_and(|b| b.is_solid())).collect();
+        let solid = &volume.solid;
+
+        let mut levels = vec![0; cells];
+        let mut queue = VecDeque::new();
+        for x in 0..size.0 {
+            for z in 0..size.2 {
+                for y in (0..size.1).rev() {
+                    let index = volume.index(x, y, z);
+                    if solid[index] {
+                        break;
+                    }
+                    levels[index] = MAX_LIGHT;
+                    queue.push_back(index);
+                }
+            }
+        }
+
+        for (index, block) in blocks.iter().enumerate() {
+            let emission = block.map_or(0, |b| b.light_emission());
+            if emission > levels[index] {
+                levels[index] = emission;
+                queue.push_back(index);
+            }
+        }
+
+        while let Some(index) = queue.pop_front() {
+            let level = levels[index];
+            if level <= 1 {
+                continue;
+            }
+            let (x, y, z) = volume.local(index);
+            for (dx, dy, dz) in [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)] {
+                let (nx, ny, nz) = (x as i32 + dx, y as i32 + dy, z as i32 + dz);
+                if nx < 0 || ny < 0 || nz < 0 || nx as usize >= size.0 || ny as usize >= size.1 || nz as usize >= size.2 {
+                    continue;
+                }
+                let neighbor = volume.index(nx as usize, ny as usize, nz as usize);
+                if !solid[neighbor] && levels[neighbor] < level - 1 {
+                    levels[neighbor] = level - 1;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        volume.levels = levels;
+        volume
+    }
+
+    /// The box meshing the chunk with this origin lights from: the chunk and one block
+    /// around it, plus MESH_LIGHT_HEADROOM more above
+    pub fn chunk_bounds(origin: BlockPos, size: i32) -> (BlockPos, BlockPos) {
+        (
+            (origin.0 - 1, origin.1 - 1, origin.2 - 1),
+            (origin.0 + size, origin.1 + size + MESH_LIGHT_HEADROOM, origin.2 + size),
+        )
+    }
+
+    fn contains(&self, block: BlockPos) -> Option<usize> {
+        let (x, y, z) = (block.0 - self.min.0, block.1 - self.min.1, block.2 - self.min.2);
+        if x < 0 || y < 0 || z < 0 || x as usize >= self.size.0 || y as usize >= self.size.1 || z as usize >= self.size.2 {
+            return None;
+        }
+        Some(self.index(x as usize, y as usize, z as usize))
+    }
+
+    /// Whether a block in the box is solid; outside it nothing is
+    pub fn is_solid(&self, block: BlockPos) -> bool {
+        self.contains(block).is_some_and(|i| self.solid[i])
+    }
+
+    /// Every level and whether each cell is solid, in a fixed order, for hashing
+    pub fn cells(&self) -> impl Iterator<Item = (u8, bool)> + '_ {
+        self.levels.iter().copied().zip(self.solid.iter().copied())
+    }
+
+    /// Brightness of the corners of the face of `block` looking along `normal`, 0 to 255,
+    /// one byte per corner. `u` and `v` are the face's own axes, and the corner 1 step along
+    /// u if `c & 1` and along v if `c & 2` is byte `c`, with -1 steps for the clear bits.
+    pub fn face_corners(&self, block: BlockPos, normal: BlockPos, u: BlockPos, v: BlockPos) -> u32 {
+        let front = (block.0 + normal.0, block.1 + normal.1, block.2 + normal.2);
+        let step = |p: BlockPos, d: BlockPos, s: i32| (p.0 + d.0 * s, p.1 + d.1 * s, p.2 + d.2 * s);
+        let mut packed = 0;
+        for corner in 0..4 {
+            let su = if corner & 1 != 0 { 1 } else { -1 };
+            let sv = if corner & 2 != 0 { 1 } else { -1 };
+            let side_u = step(front, u, su);
+            let side_v = step(front, v, sv);
+            let diagonal = step(side_u, v, sv);
+            let (open_u, open_v) = (!self.is_solid(side_u), !self.is_solid(side_v));
+            // With both sides solid the diagonal is hidden from the corner
+            let open_diagonal = (open_u || open_v) && !self.is_solid(diagonal);
+            let mut samples = vec![self.get(front).unwrap_or(MAX_LIGHT)];
+            for (open, cell) in [(open_u, side_u), (open_v, side_v), (open_diagonal, diagonal)] {
+                if open {
+                    samples.push(self.get(cell).unwrap_or(MAX_LIGHT));
+                }
+            }
+            let level = samples.iter().map(|&l| l as f32).sum::<f32>() / samples.len() as f32;
+            let occluders = 4 - samples.len();
+            let brightness = level_brightness(level) * OCCLUSION_BRIGHTNESS[occluders];
+            packed |= ((brightness * 255.0).round() as u32) << (8 * corner);
+        }
+        packed
+    }
+
+    /// `face_corners` for a model with no sides to shade, lit evenly by its own cell
+    pub fn uniform(&self, block: BlockPos) -> u32 {
+        let byte = (level_brightness(self.get(block).unwrap_or(MAX_LIGHT) as f32) * 255.0).round() as u32;
+        byte * 0x0101_0101
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (x * self.size.1 + y) * self.size.2 + z
+    }
+
+    fn local(&self, index: usize) -> (usize, usize, usize) {
+        (index / (self.size.1 * self.size.2), index / self.size.2 % self.size.1, index % self.size.2)
+    }
+
+    fn position(&self, index: usize) -> BlockPos {
+        let (x, y, z) = self.local(index);
+        (self.min.0 + x as i32, self.min.1 + y as i32, self.min.2 + z as i32)
+    }
+
+    /// Light level of a block in the box; solid blocks are always dark
+    pub fn get(&self, block: BlockPos) -> Option<u8> {
+        self.contains(block).map(|i| self.levels[i])
+    }
+}
+
+/// How bright a light level, possibly averaged between cells, looks
+fn level_brightness(level: f32) -> f32 {
+    LIGHT_FALLOFF.powf(MAX_LIGHT as f32 - level).max(MIN_BRIGHTNESS)
+}
+
+/// Horizontal reach of light_at; light spreading further than this is ignored
+const LIGHT_AT_RADIUS: i32 = 8;
+/// How far above the block light_at looks for a roof
+const LIGHT_AT_HEIGHT: i32 = 8;
+
+/// Light level at one block, computed over a small box around it. Anything more than
+/// LIGHT_AT_HEIGHT blocks above counts as open sky.
+pub fn light_at(chunks: &ChunkManager, block: BlockPos) -> u8 {
+    let min = (block.0 - LIGHT_AT_RADIUS, block.1 - 1, block.2 - LIGHT_AT_RADIUS);
+    let max = (block.0 + LIGHT_AT_RADIUS, block.1 + LIGHT_AT_HEIGHT, block.2 + LIGHT_AT_RADIUS);
+    LightVolume::compute(chunks, min, max).get(block).unwrap_or(0)
+}
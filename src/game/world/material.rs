@@ -0,0 +1,47 @@
+//! What blocks are made of: the sounds they make, and the colormap grayscale textures are
+//! tinted from.
+
+use crate::game::world::biome::{Colormap, FOLIAGE_COLORMAP, GRASS_COLORMAP};
+
+/// Sound set of a block. Sound names are looked up by the audio system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Material {
+    Grass,
+    Dirt,
+    Stone,
+    Wood,
+    /// Crops and other plants that are walked through rather than on
+    Plant,
+    Cloth,
+}
+
+impl Material {
+    /// Footstep variants, played in turn so consecutive steps differ
+    pub fn footstep_sounds(&self) -> &'static [&'static str] {
+        match self {
+            Material::Grass => &["step.grass1", "step.grass2", "step.grass3", "step.grass4"],
+            Material::Dirt => &["step.gravel1", "step.gravel2", "step.gravel3", "step.gravel4"],
+            Material::Stone => &["step.stone1", "step.stone2", "step.stone3", "step.stone4"],
+            Material::Wood => &["step.wood1", "step.wood2", "step.wood3", "step.wood4"],
+            Material::Plant => &["step.plant1", "step.plant2"],
+            Material::Cloth => &["step.cloth1", "step.cloth2", "step.cloth3"],
+        }
+    }
+}
+
+/// Colormap a grayscale texture is multiplied by, so it takes on its biome's color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tint {
+    Grass,
+    /// Leaves and other plants
+    Foliage,
+}
+
+impl Tint {
+    pub fn colormap(&self) -> &'static Colormap {
+        match self {
+            Tint::Grass => &GRASS_COLORMAP,
+            Tint::Foliage => &FOLIAGE_COLORMAP,
+        }
+    }
+}
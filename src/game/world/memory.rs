@@ -0,0 +1,77 @@
+//! Accounting of the memory held by world data.
+
+use std::fmt;
+
+use crate::engine::graphics::vertex::BlockFaceInstance;
+use crate::game::world::chunk::{Chunk, ChunkBlocks};
+use crate::game::world::chunk_manager::ChunkManager;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub chunks: usize,
+    /// Block arrays of loaded chunks
+    pub block_bytes: usize,
+    /// CPU copies of chunk face instances, by allocated capacity
+    pub mesh_bytes: usize,
+    /// Chunk instance buffers on the GPU
+    pub gpu_bytes: u64,
+    /// Chunks still being generated
+    pub generating: usize,
+    /// Generated chunks waiting to be picked up, and their size
+    pub queued: usize,
+    pub queued_bytes: usize,
+    /// Chunks unloaded lately whose blocks are kept, and their size
+    pub recent: usize,
+    pub recent_bytes: usize,
+}
+
+impl MemoryUsage {
+    pub fn measure(chunks: &ChunkManager) -> Self {
+        let mut usage = Self {
+            chunks: chunks.loaded.len(),
+            generating: chunks.pending.len().saturating_sub(chunks.queued_len()),
+            queued: chunks.queued_len(),
+            queued_bytes: chunks.queued_len() * std::mem::size_of::<Chunk>(),
+            recent: chunks.recent.len(),
+            recent_bytes: chunks.recent.len() * std::mem::size_of::<ChunkBlocks>(),
+            ..Self::default()
+        };
+        for chunk in chunks.all_chunks() {
+            usage.block_bytes += std::mem::size_of_val(&chunk.blocks);
+            usage.mesh_bytes += chunk.block_face_instances.capacity() * std::mem::size_of::<BlockFaceInstance>();
+            usage.gpu_bytes += chunk.instance_buffer.as_ref().map_or(0, |b| b.size());
+        }
+        usage
+    }
+
+    /// CPU-side bytes
+    pub fn cpu_bytes(&self) -> usize {
+        self.block_bytes + self.mesh_bytes + self.queued_bytes + self.recent_bytes
+    }
+}
+
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} chunks: blocks {}, meshes {}, GPU buffers {}",
+            self.chunks, format_bytes(self.block_bytes as u64), format_bytes(self.mesh_bytes as u64), format_bytes(self.gpu_bytes))?;
+        write!(f, "{} generating, {} queued ({}), {} recent ({}); CPU total {}",
+            self.generating, self.queued, format_bytes(self.queued_bytes as u64),
+            self.recent, format_bytes(self.recent_bytes as u64), format_bytes(self.cpu_bytes() as u64))
+    }
+}
+
+/// Bytes in the largest unit that keeps the number at least 1
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
@@ -1,9 +1,31 @@
+pub mod acoustics;
 pub mod camera;
+pub mod camera_shake;
 pub mod app;
+pub mod behavior;
+pub mod biome;
 pub mod chunk;
+pub mod chunk_loader;
 pub mod chunk_manager;
+pub mod day_cycle;
+pub mod decals;
+pub mod explosion;
+pub mod far_terrain;
+pub mod fluid;
+pub mod light;
+pub mod material;
+pub mod memory;
+pub mod raycast;
+pub mod recent_chunks;
+pub mod rules;
+pub mod sign;
+pub mod waypoint;
+pub mod weather;
+pub mod world_map;
+pub mod worldgen;
 
 pub use camera::Camera;
 pub use app::App;
 pub use chunk::Chunk;
-pub use chunk_manager::ChunkManager; 
\ No newline at end of file
+pub use chunk_manager::ChunkManager; 
+pub use worldgen::{WorldGen, WorldType};
\ No newline at end of file
@@ -0,0 +1,164 @@
+//! Ray casting against voxels and entities.
+
+use glam::Vec3;
+
+use crate::engine::math::Aabb;
+use crate::game::entity::{EntityId, EntityManager};
+use crate::game::world::chunk::BlockType;
+use crate::game::world::chunk_manager::ChunkManager;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockHit {
+    pub block: (i32, i32, i32),
+    /// Normal of the face that was entered, pointing back towards the ray origin
+    pub normal: (i32, i32, i32),
+    pub block_type: BlockType,
+    pub distance: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RaycastHit {
+    Block(BlockHit),
+    Entity { id: EntityId, distance: f32 },
+}
+
+impl RaycastHit {
+    pub fn distance(&self) -> f32 {
+        match self {
+            RaycastHit::Block(hit) => hit.distance,
+            RaycastHit::Entity { distance, .. } => *distance,
+        }
+    }
+}
+
+/// Walks the voxel grid along the ray (Amanatides & Woo) and returns the first block that
+/// stops movement
+pub fn raycast_blocks(chunk_manager: &ChunkManager, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<BlockHit> {
+    raycast_blocks_where(chunk_manager, origin, dir, max_distance, |block| block.collides())
+}
+
+/// Like raycast_blocks, but also stops at blocks that can be aimed at without being solid,
+/// such as ladders
+pub fn raycast_targets(chunk_manager: &ChunkManager, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<BlockHit> {
+    raycast_blocks_where(chunk_manager, origin, dir, max_distance, |block| block.is_targetable())
+}
+
+fn raycast_blocks_where(
+    chunk_manager: &ChunkManager,
+    origin: Vec3,
+    dir: Vec3,
+    max_distance: f32,
+    hits: impl Fn(BlockType) -> bool,
+) -> Option<BlockHit> {
+    VoxelWalk::new(origin, dir)?
+        .take_while(|step| step.distance <= max_distance)
+        .find_map(|step| {
+            let block_type = chunk_manager.get_block(step.block.0, step.block.1, step.block.2)?;
+            hits(block_type).then_some(BlockHit { block: step.block, normal: step.normal, block_type, distance: step.distance })
+        })
+}
+
+/// A block a ray passes through
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoxelStep {
+    pub block: (i32, i32, i32),
+    /// Normal of the face the ray entered by, zero for the block it starts in
+    pub normal: (i32, i32, i32),
+    /// Along the ray to where it entered the block
+    pub distance: f32,
+}
+
+/// The blocks along a ray in the order it passes through them, starting with the one it
+/// starts in (Amanatides & Woo). Never ends; bound it by distance.
+pub struct VoxelWalk {
+    cell: glam::IVec3,
+    step: glam::IVec3,
+    delta: Vec3,
+    /// Distance along the ray to the next boundary crossing on each axis
+    t_max: Vec3,
+    normal: (i32, i32, i32),
+    distance: f32,
+}
+
+impl VoxelWalk {
+    /// None for a zero direction
+    pub fn new(origin: Vec3, dir: Vec3) -> Option<Self> {
+        let dir = dir.normalize_or_zero();
+        if dir == Vec3::ZERO {
+            return None;
+        }
+        // Shift into a grid where block n spans [n, n + 1)
+        let start = origin + Vec3::splat(0.5);
+        let cell = start.floor().as_ivec3();
+        let axis_step = |d: f32| if d > 0.0 { 1 } else if d < 0.0 { -1 } else { 0 };
+        let step = glam::IVec3::new(axis_step(dir.x), axis_step(dir.y), axis_step(dir.z));
+        let delta = dir.abs().recip();
+        let first_crossing = |p: f32, c: i32, s: i32, d: f32| match s {
+            0 => f32::INFINITY,
+            1 => (c as f32 + 1.0 - p) * d,
+            _ => (p - c as f32) * d,
+        };
+        let t_max = Vec3::new(
+            first_crossing(start.x, cell.x, step.x, delta.x),
+            first_crossing(start.y, cell.y, step.y, delta.y),
+            first_crossing(start.z, cell.z, step.z, delta.z),
+        );
+        Some(Self { cell, step, delta, t_max, normal: (0, 0, 0), distance: 0.0 })
+    }
+}
+
+impl Iterator for VoxelWalk {
+    type Item = VoxelStep;
+
+    fn next(&mut self) -> Option<VoxelStep> {
+        let current = VoxelStep { block: (self.cell.x, self.cell.y, self.cell.z), normal: self.normal, distance: self.distance };
+        let (step, delta, t_max) = (self.step, self.delta, &mut self.t_max);
+        if t_max.x < t_max.y && t_max.x < t_max.z {
+            self.cell.x += step.x;
+            self.distance = t_max.x;
+            t_max.x += delta.x;
+            self.normal = (-step.x, 0, 0);
+        } else if t_max.y < t_max.z {
+            self.cell.y += step.y;
+            self.distance = t_max.y;
+            t_max.y += delta.y;
+            self.normal = (0, -step.y, 0);
+        } else {
+            self.cell.z += step.z;
+            self.distance = t_max.z;
+            t_max.z += delta.z;
+            self.normal = (0, 0, -step.z);
+        }
+        Some(current)
+    }
+}
+
+/// Returns the closest entity whose bounding box the ray passes through
+pub fn raycast_entities(entities: &EntityManager, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<(EntityId, f32)> {
+    let dir = dir.normalize_or_zero();
+    let end = origin + dir * max_distance;
+    let bounds = Aabb::new(origin.min(end), origin.max(end));
+    entities.query_aabb(&bounds)
+        .into_iter()
+        .filter_map(|id| entities.get(id))
+        .filter_map(|entity| entity.aabb().ray_intersect(origin, dir, max_distance).map(|t| (entity.id, t)))
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+/// Casts against both targetable blocks and entities, returning whichever is hit first
+pub fn raycast(
+    chunk_manager: &ChunkManager,
+    entities: &EntityManager,
+    origin: Vec3,
+    dir: Vec3,
+    max_distance: f32,
+) -> Option<RaycastHit> {
+    let block = raycast_targets(chunk_manager, origin, dir, max_distance);
+    let entity = raycast_entities(entities, origin, dir, max_distance);
+    match (block, entity) {
+        (Some(block), Some((id, distance))) if distance < block.distance => Some(RaycastHit::Entity { id, distance }),
+        (Some(block), _) => Some(RaycastHit::Block(block)),
+        (None, Some((id, distance))) => Some(RaycastHit::Entity { id, distance }),
+        (None, None) => None,
+    }
+}
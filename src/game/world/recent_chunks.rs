@@ -0,0 +1,77 @@
+//! Blocks of chunks unloaded a moment ago, kept in case they come back into view.
+//!
+//! Turning around or walking back and forth along the view distance edge would otherwise
+//! generate the same chunks again and again. Only the blocks are kept; meshes are rebuilt
+//! when a chunk comes back, since its neighbours may have changed since.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::game::world::chunk::ChunkBlocks;
+
+type ChunkKey = (i32, i32, i32);
+
+/// Chunks kept by default, a few MiB of blocks
+pub const DEFAULT_RECENT_CHUNKS: usize = 512;
+
+/// A bounded cache that forgets the chunk unloaded longest ago first
+pub struct RecentChunks {
+    capacity: usize,
+    /// Each entry with the stamp it was stored under
+    chunks: HashMap<ChunkKey, (Box<ChunkBlocks>, u64)>,
+    /// Keys oldest first. Entries taken or stored again are left behind with a stale stamp
+    /// and skipped when they come up.
+    order: VecDeque<(ChunkKey, u64)>,
+    next_stamp: u64,
+}
+
+impl RecentChunks {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, chunks: HashMap::new(), order: VecDeque::new(), next_stamp: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Keeps the blocks of a chunk that was just unloaded, forgetting the oldest chunk if
+    /// the cache is full
+    pub fn store(&mut self, key: ChunkKey, blocks: Box<ChunkBlocks>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let stamp = self.next_stamp;
+        self.next_stamp += 1;
+        self.chunks.insert(key, (blocks, stamp));
+        self.order.push_back((key, stamp));
+        while self.chunks.len() > self.capacity {
+            let Some((oldest, stamp)) = self.order.pop_front() else { break };
+            if self.chunks.get(&oldest).is_some_and(|&(_, s)| s == stamp) {
+                self.chunks.remove(&oldest);
+            }
+        }
+        // Stale entries build up when chunks are taken back; drop them once they outnumber
+        // the live ones
+        if self.order.len() > 2 * self.capacity.max(self.chunks.len()) {
+            let chunks = &self.chunks;
+            self.order.retain(|(key, stamp)| chunks.get(key).is_some_and(|&(_, s)| s == *stamp));
+        }
+    }
+
+    /// Removes and returns the blocks of a chunk, if they are still kept
+    pub fn take(&mut self, key: ChunkKey) -> Option<Box<ChunkBlocks>> {
+        self.chunks.remove(&key).map(|(blocks, _)| blocks)
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.order.clear();
+    }
+}
@@ -0,0 +1,55 @@
+//! Game rules: world-wide switches an admin changes with `/gamerule`, saved with the world.
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+
+/// Rule names, as typed after `/gamerule`
+pub const GAME_RULE_NAMES: [&str; 1] = ["keepInventory"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GameRules {
+    /// Players keep their items when they die, rather than dropping them where they fell
+    pub keep_inventory: bool,
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "on" => Ok(true),
+        "false" | "off" => Ok(false),
+        other => Err(format!("expected true or false, got {}", other)),
+    }
+}
+
+impl GameRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of the rule called `name`, as text
+    pub fn get(&self, name: &str) -> Option<String> {
+        match name {
+            "keepInventory" => Some(self.keep_inventory.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "keepInventory" => self.keep_inventory = parse_bool(value)?,
+            _ => return Err(format!("no game rule called {}; rules are {}", name, GAME_RULE_NAMES.join(", "))),
+        }
+        Ok(())
+    }
+
+    pub fn encode(&self, w: &mut ByteWriter) {
+        w.write_u8(self.keep_inventory as u8);
+    }
+
+    /// Rules added after a save was written keep their defaults
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        let mut rules = Self::new();
+        if !r.is_empty() {
+            rules.keep_inventory = r.read_u8()? != 0;
+        }
+        Ok(rules)
+    }
+}
@@ -0,0 +1,99 @@
+//! Sign text: limits, saving, and drawing it on the sign in the world.
+
+use glam::Vec3;
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::engine::graphics::font::LINE_HEIGHT;
+use crate::engine::graphics::overlay::{Color, Overlay};
+use crate::game::world::behavior::BlockPos;
+use crate::game::world::chunk::{BlockType, Facing, CHUNK_SIZE};
+use crate::game::world::chunk_manager::ChunkManager;
+
+pub const MAX_SIGN_LINES: usize = 4;
+pub const MAX_SIGN_LINE_CHARS: usize = 15;
+/// Size of one font pixel on a sign, in blocks
+const SIGN_TEXT_SCALE: f32 = 0.01;
+/// How far in front of the sign board the text floats, so it does not fight with it
+const SIGN_TEXT_LIFT: f32 = 0.01;
+const SIGN_TEXT_COLOR: Color = [0.1, 0.07, 0.03, 1.0];
+/// Chunks around the viewer whose signs get their text drawn; it is unreadable further out
+const SIGN_TEXT_DISTANCE: i32 = 1;
+
+/// Whether a character can be written on a sign. Anything printable can, so text composed
+/// with an input method is kept even where the font has no glyph for it yet.
+pub fn is_sign_char(c: char) -> bool {
+    !c.is_control()
+}
+
+/// Cuts text down to what fits on a sign, dropping control characters
+pub fn sanitize(text: &str) -> String {
+    text.lines()
+        .take(MAX_SIGN_LINES)
+        .map(|line| line.chars().filter(|c| is_sign_char(*c)).take(MAX_SIGN_LINE_CHARS).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A sign and its text, as kept in the save
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignData {
+    pub block: BlockPos,
+    pub facing: Facing,
+    pub text: String,
+}
+
+impl SignData {
+    pub fn encode(&self, w: &mut ByteWriter) {
+        w.write_i32(self.block.0);
+        w.write_i32(self.block.1);
+        w.write_i32(self.block.2);
+        w.write_u8(self.facing.index());
+        w.write_str(&self.text);
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        let block = (r.read_i32()?, r.read_i32()?, r.read_i32()?);
+        let facing_index = r.read_u8()?;
+        let facing = Facing::from_index(facing_index)
+            .ok_or_else(|| DecodeError::Invalid(format!("bad sign facing {}", facing_index)))?;
+        Ok(Self { block, facing, text: sanitize(&r.read_str()?) })
+    }
+}
+
+/// Draws the text on the board of a sign mounted on the `facing` side of `block`, each
+/// line centered
+pub fn draw_text(overlay: &mut Overlay, block: BlockPos, facing: Facing, text: &str) {
+    let (dx, _, dz) = facing.offset();
+    let toward_wall = Vec3::new(dx as f32, 0.0, dz as f32);
+    // Reading the sign means looking at the wall, so the text runs to the viewer's right
+    let right = toward_wall.cross(Vec3::Y);
+    // The board is the flat model, 1/16 of a block off the wall
+    let center = Vec3::new(block.0 as f32, block.1 as f32, block.2 as f32) + toward_wall * (0.4375 - SIGN_TEXT_LIFT);
+    let (_, height) = Overlay::text_size(text, SIGN_TEXT_SCALE);
+    let top = center + Vec3::Y * (height / 2.0);
+    for (row, line) in text.lines().enumerate() {
+        let (width, _) = Overlay::text_size(line, SIGN_TEXT_SCALE);
+        let origin = top - Vec3::Y * ((row as u32 * LINE_HEIGHT) as f32 * SIGN_TEXT_SCALE) - right * (width / 2.0);
+        overlay.add_world_text(origin, right, -Vec3::Y, SIGN_TEXT_SCALE, SIGN_TEXT_COLOR, line);
+    }
+}
+
+/// Draws the text of the signs in the chunks around `eye`
+pub fn draw_nearby(overlay: &mut Overlay, chunks: &ChunkManager, eye: Vec3) {
+    let center = ChunkManager::chunk_key_at(eye);
+    let cs = CHUNK_SIZE as i32;
+    for dx in -SIGN_TEXT_DISTANCE..=SIGN_TEXT_DISTANCE {
+        for dy in -SIGN_TEXT_DISTANCE..=SIGN_TEXT_DISTANCE {
+            for dz in -SIGN_TEXT_DISTANCE..=SIGN_TEXT_DISTANCE {
+                let key = (center.0 + dx, center.1 + dy, center.2 + dz);
+                let Some(chunk) = chunks.loaded.get(&key) else { continue };
+                for (&(x, y, z), text) in &chunk.sign_text {
+                    if let BlockType::Sign(facing) = chunk.blocks[x][y][z] {
+                        let block = (key.0 * cs + x as i32, key.1 * cs + y as i32, key.2 * cs + z as i32);
+                        draw_text(overlay, block, facing, text);
+                    }
+                }
+            }
+        }
+    }
+}
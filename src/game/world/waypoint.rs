@@ -0,0 +1,207 @@
+//! Named waypoints: places the player marked to find again.
+//!
+//! Waypoints belong to the client and are saved next to the world. While playing they are
+//! drawn as markers over the view with their distance; one that is off screen or behind
+//! the camera sticks to the edge of the screen in its direction.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use glam::{Mat4, Vec2, Vec3};
+use log::warn;
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::engine::graphics::overlay::{Color, Overlay};
+use crate::game::save::atomic;
+use crate::game::state::MapMarker;
+
+/// File in the world directory holding the waypoints
+pub const WAYPOINTS_FILE: &str = "waypoints.dat";
+pub const MAX_WAYPOINT_NAME_CHARS: usize = 24;
+const WAYPOINTS_MAGIC: &[u8; 4] = b"PSUW";
+const WAYPOINTS_VERSION: u32 = 1;
+/// Waypoint colors, handed out in turn
+const WAYPOINT_COLORS: [Color; 6] = [
+    [0.3, 0.8, 1.0, 1.0],
+    [1.0, 0.4, 0.4, 1.0],
+    [0.5, 1.0, 0.4, 1.0],
+    [1.0, 0.7, 0.2, 1.0],
+    [0.8, 0.5, 1.0, 1.0],
+    [1.0, 1.0, 1.0, 1.0],
+];
+/// Gap kept between a marker stuck to the edge and the edge itself, in text scale units
+const EDGE_MARGIN: f32 = 12.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waypoint {
+    pub name: String,
+    pub position: Vec3,
+    /// Index into the waypoint colors
+    pub color: u8,
+}
+
+impl Waypoint {
+    pub fn color(&self) -> Color {
+        WAYPOINT_COLORS[self.color as usize % WAYPOINT_COLORS.len()]
+    }
+
+    pub fn map_marker(&self) -> MapMarker {
+        MapMarker {
+            position: Vec2::new(self.position.x, self.position.z),
+            label: self.name.clone(),
+            color: self.color(),
+            heading: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Waypoints {
+    list: Vec<Waypoint>,
+    /// Changed since the last save
+    dirty: bool,
+}
+
+impl Waypoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Waypoint> {
+        self.list.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// A name no waypoint has yet, for waypoints made without typing one
+    pub fn next_name(&self) -> String {
+        (1..).map(|n| format!("Waypoint {}", n)).find(|name| self.get(name).is_none()).expect("names never run out")
+    }
+
+    /// Looks a waypoint up by name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&Waypoint> {
+        self.list.iter().find(|w| w.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Adds a waypoint, or moves the one with the same name, returning the name it was
+    /// saved under
+    pub fn set(&mut self, name: &str, position: Vec3) -> Result<String, String> {
+        let name: String = name.trim().chars().filter(|c| c.is_ascii_graphic() || *c == ' ').take(MAX_WAYPOINT_NAME_CHARS).collect();
+        if name.is_empty() {
+            return Err("waypoint names need at least one letter".into());
+        }
+        self.dirty = true;
+        match self.list.iter_mut().find(|w| w.name.eq_ignore_ascii_case(&name)) {
+            Some(existing) => existing.position = position,
+            None => {
+                let color = (self.list.len() % WAYPOINT_COLORS.len()) as u8;
+                self.list.push(Waypoint { name: name.clone(), position, color });
+            }
+        }
+        Ok(name)
+    }
+
+    /// Removes the waypoint with this name, returning whether there was one
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.list.len();
+        self.list.retain(|w| !w.name.eq_ignore_ascii_case(name));
+        self.dirty |= self.list.len() != before;
+        self.list.len() != before
+    }
+
+    pub fn encode(&self, w: &mut ByteWriter) {
+        w.write_bytes(WAYPOINTS_MAGIC);
+        w.write_u32(WAYPOINTS_VERSION);
+        w.write_u32(self.list.len() as u32);
+        for waypoint in &self.list {
+            w.write_str(&waypoint.name);
+            w.write_vec3(waypoint.position);
+            w.write_u8(waypoint.color);
+        }
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        if r.read_bytes(4)? != WAYPOINTS_MAGIC {
+            return Err(DecodeError::Invalid("bad waypoints magic".into()));
+        }
+        let version = r.read_u32()?;
+        if version != WAYPOINTS_VERSION {
+            return Err(DecodeError::Invalid(format!("unsupported waypoints version {}", version)));
+        }
+        let count = r.read_u32()?;
+        let mut list = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            list.push(Waypoint { name: r.read_str()?, position: r.read_vec3()?, color: r.read_u8()? });
+        }
+        Ok(Self { list, dirty: false })
+    }
+
+    /// Reads the waypoints saved in `world_dir`, starting with none if there are none or
+    /// they are unreadable
+    pub fn load(world_dir: &Path) -> Self {
+        let path = world_dir.join(WAYPOINTS_FILE);
+        atomic::discard_interrupted_write(&path);
+        [path.clone(), atomic::backup_path(&path)].iter().find_map(|path| {
+            let data = fs::read(path).ok()?;
+            Self::decode(&mut ByteReader::new(&data))
+                .map_err(|e| warn!("Failed to read waypoints {}: {}", path.display(), e))
+                .ok()
+        }).unwrap_or_default()
+    }
+
+    /// Writes the waypoints into `world_dir` if they changed since the last save
+    pub fn save(&mut self, world_dir: &Path) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let mut w = ByteWriter::new();
+        self.encode(&mut w);
+        atomic::write_atomic(&world_dir.join(WAYPOINTS_FILE), &w.into_inner())?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Draws a marker over the view for each waypoint, labelled with its distance from `eye`
+    pub fn draw_markers(&self, overlay: &mut Overlay, view_proj: Mat4, eye: Vec3, screen: (f32, f32), text_scale: f32) {
+        let margin = EDGE_MARGIN * text_scale;
+        for waypoint in &self.list {
+            let at = screen_position(view_proj, waypoint.position, screen, margin);
+            let dot = 2.0 * text_scale;
+            overlay.add_rect(at.x - dot, at.y - dot, 2.0 * dot, 2.0 * dot, waypoint.color());
+            let label = format!("{} {:.0}m", waypoint.name, eye.distance(waypoint.position));
+            let label_scale = text_scale * 0.5;
+            let (width, height) = Overlay::text_size(&label, label_scale);
+            // Keep the label on screen even when the marker is at the edge
+            let x = (at.x - width / 2.0).clamp(0.0, (screen.0 - width).max(0.0));
+            let y = if at.y + dot + height > screen.1 { at.y - dot - height } else { at.y + dot };
+            overlay.add_label(x, y, label_scale, [1.0, 1.0, 1.0, 1.0], &label);
+        }
+    }
+}
+
+/// Where `position` appears on screen, in pixels. Points off screen or behind the camera
+/// are pulled in to `margin` pixels from the edge, in the direction they lie.
+fn screen_position(view_proj: Mat4, position: Vec3, screen: (f32, f32), margin: f32) -> Vec2 {
+    let clip = view_proj * position.extend(1.0);
+    let mut ndc = Vec2::new(clip.x, clip.y) / clip.w.abs().max(1e-4);
+    let behind = clip.w <= 0.0;
+    if behind && ndc.length_squared() < 1e-8 {
+        // Straight behind: there is no side to prefer, so use the bottom edge
+        ndc = -Vec2::Y;
+    }
+    let size = Vec2::new(screen.0, screen.1);
+    let inset = (Vec2::ONE - 2.0 * margin / size).max(Vec2::ZERO);
+    let outside = ndc.x.abs() / inset.x.max(1e-4) > 1.0 || ndc.y.abs() / inset.y.max(1e-4) > 1.0;
+    if behind || outside {
+        // Scale along the direction until it touches the inset edge
+        let reach = (ndc.x.abs() / inset.x.max(1e-4)).max(ndc.y.abs() / inset.y.max(1e-4));
+        ndc /= reach.max(1e-4);
+    }
+    Vec2::new((ndc.x + 1.0) * 0.5 * size.x, (1.0 - ndc.y) * 0.5 * size.y)
+}
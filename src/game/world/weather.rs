@@ -0,0 +1,68 @@
+//! Rain, and the wetness it leaves behind.
+//!
+//! The server decides when it rains and tells clients whenever that changes. Each client
+//! then wets its surfaces over WETTING_SECONDS while it rains and dries them over
+//! DRYING_SECONDS once it stops, so the world keeps glistening for a while after a shower.
+
+use crate::engine::math::Rng;
+
+/// Shortest and longest dry spells, in server ticks
+const CLEAR_TICKS: (u64, u64) = (12_000, 36_000);
+/// Shortest and longest showers, in server ticks
+const RAIN_TICKS: (u64, u64) = (2_400, 9_600);
+/// Seconds of rain to soak surfaces completely
+const WETTING_SECONDS: f32 = 20.0;
+/// Seconds for soaked surfaces to dry once the rain stops
+const DRYING_SECONDS: f32 = 120.0;
+
+/// The server's weather: whether it rains, and for how much longer
+#[derive(Debug, Clone)]
+pub struct WeatherCycle {
+    pub raining: bool,
+    /// Ticks until the weather changes
+    remaining: u64,
+}
+
+impl Default for WeatherCycle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WeatherCycle {
+    /// Clear skies for the shortest dry spell
+    pub fn new() -> Self {
+        Self { raining: false, remaining: CLEAR_TICKS.0 }
+    }
+
+    /// Advances one tick, returning true if the weather changed
+    pub fn tick(&mut self, rng: &mut Rng) -> bool {
+        self.remaining = self.remaining.saturating_sub(1);
+        if self.remaining > 0 {
+            return false;
+        }
+        self.set(!self.raining, rng);
+        true
+    }
+
+    /// Starts or stops the rain now, for a random spell
+    pub fn set(&mut self, raining: bool, rng: &mut Rng) {
+        let (min, max) = if raining { RAIN_TICKS } else { CLEAR_TICKS };
+        self.raining = raining;
+        self.remaining = min + rng.below((max - min) as u32) as u64;
+    }
+}
+
+/// How wet surfaces are on a client, 0 dry to 1 soaked
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Wetness(pub f32);
+
+impl Wetness {
+    pub fn update(&mut self, raining: bool, delta_time: f32) {
+        self.0 = if raining {
+            (self.0 + delta_time / WETTING_SECONDS).min(1.0)
+        } else {
+            (self.0 - delta_time / DRYING_SECONDS).max(0.0)
+        };
+    }
+}
@@ -0,0 +1,186 @@
+//! The explored part of the world, seen from above, for the map screen.
+//!
+//! Every chunk the client loads records the topmost block of each of its columns; the
+//! record outlives the chunk, so the map keeps showing places after they unload. It is
+//! saved next to the world, so exploring carries over between sessions.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use log::warn;
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::game::save::atomic;
+use crate::game::world::chunk::{BlockType, Chunk, CHUNK_SIZE};
+
+/// File in the world directory holding the explored columns
+pub const WORLD_MAP_FILE: &str = "map.dat";
+const MAP_MAGIC: &[u8; 4] = b"PSUE";
+const MAP_VERSION: u32 = 1;
+/// Written in place of a block id for a column that has not been seen
+const UNEXPLORED: u8 = u8::MAX;
+
+/// The top of one column of blocks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapCell {
+    pub height: i32,
+    pub block: BlockType,
+}
+
+impl MapCell {
+    /// Color on the map, lighter where the ground rises from `north` and darker where it
+    /// falls, so hills read without contour lines
+    pub fn color(&self, north: Option<MapCell>) -> [f32; 3] {
+        let base = match self.block {
+            BlockType::Grass => [0.36, 0.6, 0.25],
+            BlockType::Dirt => [0.5, 0.36, 0.22],
+            BlockType::Stone => [0.5, 0.5, 0.52],
+            BlockType::Water(_) => [0.2, 0.35, 0.8],
+            BlockType::Lava(_) => [0.95, 0.4, 0.1],
+            BlockType::Fire(_) => [1.0, 0.6, 0.1],
+            BlockType::Wheat(_) => [0.8, 0.7, 0.3],
+            BlockType::Ladder(_) | BlockType::Door(_) | BlockType::Sign(_) => [0.6, 0.45, 0.25],
+            BlockType::Tnt => [0.8, 0.2, 0.15],
+            BlockType::Bed => [0.7, 0.12, 0.12],
+            BlockType::Air => [0.0, 0.0, 0.0],
+        };
+        let shade: f32 = match north.map(|north| self.height.cmp(&north.height)) {
+            Some(std::cmp::Ordering::Greater) => 1.15,
+            Some(std::cmp::Ordering::Less) => 0.85,
+            _ => 1.0,
+        };
+        base.map(|c| (c * shade).min(1.0))
+    }
+}
+
+/// The tops of the columns of one chunk column, indexed [x][z]
+type MapColumn = [[Option<MapCell>; CHUNK_SIZE]; CHUNK_SIZE];
+
+#[derive(Debug, Default)]
+pub struct WorldMap {
+    columns: HashMap<(i32, i32), Box<MapColumn>>,
+    /// Changed since the last save
+    dirty: bool,
+}
+
+impl WorldMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Chunk columns explored so far
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Records the tops of a loaded chunk's columns. A higher block seen in another chunk
+    /// stays, unless it was inside this chunk, where it may since have been dug out.
+    pub fn record(&mut self, key: (i32, i32, i32), chunk: &Chunk) {
+        let cs = CHUNK_SIZE as i32;
+        let (bottom, top) = (key.1 * cs, key.1 * cs + cs);
+        let column = self.columns.entry((key.0, key.2)).or_insert_with(|| Box::new([[None; CHUNK_SIZE]; CHUNK_SIZE]));
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let found = (0..CHUNK_SIZE).rev()
+                    .map(|y| (y, chunk.blocks[x][y][z]))
+                    .find(|(_, block)| !matches!(block, BlockType::Air))
+                    .map(|(y, block)| MapCell { height: bottom + y as i32, block });
+                let cell = &mut column[x][z];
+                let replace = match (*cell, found) {
+                    (_, Some(found)) if cell.is_none_or(|old| found.height >= old.height) => true,
+                    (Some(old), _) => (bottom..top).contains(&old.height),
+                    (None, _) => false,
+                };
+                if replace && *cell != found {
+                    *cell = found;
+                    self.dirty = true;
+                }
+            }
+        }
+    }
+
+    /// The top of the column at block coordinates (x, z), if it has been explored
+    pub fn cell(&self, x: i32, z: i32) -> Option<MapCell> {
+        let cs = CHUNK_SIZE as i32;
+        let column = self.columns.get(&(x.div_euclid(cs), z.div_euclid(cs)))?;
+        column[x.rem_euclid(cs) as usize][z.rem_euclid(cs) as usize]
+    }
+
+    pub fn encode(&self, w: &mut ByteWriter) {
+        w.write_bytes(MAP_MAGIC);
+        w.write_u32(MAP_VERSION);
+        w.write_u32(self.columns.len() as u32);
+        for (&(cx, cz), column) in &self.columns {
+            w.write_i32(cx);
+            w.write_i32(cz);
+            for cell in column.iter().flatten() {
+                match cell {
+                    Some(cell) => {
+                        w.write_u8(cell.block.id());
+                        w.write_u8(cell.block.meta());
+                        w.write_var_i32(cell.height);
+                    }
+                    None => w.write_u8(UNEXPLORED),
+                }
+            }
+        }
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        if r.read_bytes(4)? != MAP_MAGIC {
+            return Err(DecodeError::Invalid("bad world map magic".into()));
+        }
+        let version = r.read_u32()?;
+        if version != MAP_VERSION {
+            return Err(DecodeError::Invalid(format!("unsupported world map version {}", version)));
+        }
+        let count = r.read_u32()?;
+        let mut columns = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let key = (r.read_i32()?, r.read_i32()?);
+            let mut column: Box<MapColumn> = Box::new([[None; CHUNK_SIZE]; CHUNK_SIZE]);
+            for cell in column.iter_mut().flatten() {
+                let id = r.read_u8()?;
+                if id == UNEXPLORED {
+                    continue;
+                }
+                let meta = r.read_u8()?;
+                let block = BlockType::from_parts(id, meta)
+                    .ok_or_else(|| DecodeError::Invalid(format!("bad block {}:{} in world map", id, meta)))?;
+                *cell = Some(MapCell { height: r.read_var_i32()?, block });
+            }
+            columns.insert(key, column);
+        }
+        Ok(Self { columns, dirty: false })
+    }
+
+    /// Reads the map saved in `world_dir`, starting a blank one if there is none or it is
+    /// unreadable
+    pub fn load(world_dir: &Path) -> Self {
+        let path = world_dir.join(WORLD_MAP_FILE);
+        atomic::discard_interrupted_write(&path);
+        [path.clone(), atomic::backup_path(&path)].iter().find_map(|path| {
+            let data = fs::read(path).ok()?;
+            Self::decode(&mut ByteReader::new(&data))
+                .map_err(|e| warn!("Failed to read world map {}: {}", path.display(), e))
+                .ok()
+        }).unwrap_or_default()
+    }
+
+    /// Writes the map into `world_dir` if anything was explored since the last save
+    pub fn save(&mut self, world_dir: &Path) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let mut w = ByteWriter::new();
+        self.encode(&mut w);
+        atomic::write_atomic(&world_dir.join(WORLD_MAP_FILE), &w.into_inner())?;
+        self.dirty = false;
+        Ok(())
+    }
+}
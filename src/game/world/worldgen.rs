@@ -0,0 +1,258 @@
+//! World types, chosen when a world is created.
+//!
+//! Each type is its own way of filling a chunk: the default noise terrain, a superflat world
+//! of fixed layers, or a debug world holding one of every block state in a single chunk. The
+//! server and its clients must generate alike, so the generator is saved with the world and
+//! sent to every client that joins.
+
+use glam::Vec3;
+
+use crate::engine::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::game::world::biome::Biome;
+use crate::game::world::chunk::{BlockType, Chunk, CHUNK_SIZE, DEFAULT_SEED};
+
+/// Most layers a superflat world may have
+pub const MAX_FLAT_LAYERS: usize = 64;
+/// Names of the blocks superflat layers can be made of
+const LAYER_BLOCKS: [(&str, BlockType); 7] = [
+    ("air", BlockType::Air),
+    ("grass", BlockType::Grass),
+    ("dirt", BlockType::Dirt),
+    ("stone", BlockType::Stone),
+    ("water", BlockType::Water(0)),
+    ("lava", BlockType::Lava(0)),
+    ("tnt", BlockType::Tnt),
+];
+
+const TYPE_DEFAULT: u8 = 0;
+const TYPE_SUPERFLAT: u8 = 1;
+const TYPE_DEBUG: u8 = 2;
+
+/// One layer of a superflat world
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlatLayer {
+    pub block: BlockType,
+    /// Blocks the layer is thick
+    pub thickness: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum WorldType {
+    /// Rolling hills from the seed
+    #[default]
+    Default,
+    /// Layers stacked from y = 0 up, listed bottom first, with air above
+    Superflat { layers: Vec<FlatLayer> },
+    /// A stone floor in the chunk at the origin with every block state on a grid above it,
+    /// and nothing anywhere else
+    Debug,
+}
+
+impl WorldType {
+    /// Stone, two dirt and grass on top, level with the default terrain's lowest ground
+    pub fn default_superflat() -> Self {
+        WorldType::Superflat { layers: vec![
+            FlatLayer { block: BlockType::Stone, thickness: 1 },
+            FlatLayer { block: BlockType::Dirt, thickness: 2 },
+            FlatLayer { block: BlockType::Grass, thickness: 1 },
+        ] }
+    }
+
+    /// Parses `default`, `debug`, `superflat`, or `superflat:` followed by comma separated
+    /// layers from the bottom up, each a block name with an optional `*thickness`, e.g.
+    /// `superflat:stone*3,dirt*2,grass`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (name, layers) = match text.split_once(':') {
+            Some((name, layers)) => (name, Some(layers)),
+            None => (text, None),
+        };
+        match (name, layers) {
+            ("default", None) => Ok(WorldType::Default),
+            ("debug", None) => Ok(WorldType::Debug),
+            ("superflat", None) => Ok(Self::default_superflat()),
+            ("superflat", Some(layers)) => {
+                let layers = layers.split(',').map(parse_layer).collect::<Result<Vec<_>, _>>()?;
+                if layers.len() > MAX_FLAT_LAYERS {
+                    return Err(format!("a superflat world can have at most {} layers", MAX_FLAT_LAYERS));
+                }
+                Ok(WorldType::Superflat { layers })
+            }
+            _ => Err(format!("unknown world type '{}', expected default, superflat[:layers] or debug", text)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorldType::Default => "default",
+            WorldType::Superflat { .. } => "superflat",
+            WorldType::Debug => "debug",
+        }
+    }
+}
+
+fn parse_layer(text: &str) -> Result<FlatLayer, String> {
+    let (name, thickness) = match text.trim().split_once('*') {
+        Some((name, thickness)) => {
+            let thickness = thickness.trim().parse::<u8>().ok().filter(|&t| t > 0)
+                .ok_or_else(|| format!("bad layer thickness '{}'", thickness))?;
+            (name.trim(), thickness)
+        }
+        None => (text.trim(), 1),
+    };
+    let block = LAYER_BLOCKS.iter().find(|(n, _)| *n == name).map(|&(_, block)| block)
+        .ok_or_else(|| format!("unknown layer block '{}'", name))?;
+    Ok(FlatLayer { block, thickness })
+}
+
+/// How a world's chunks are generated
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldGen {
+    pub seed: u32,
+    pub world_type: WorldType,
+}
+
+impl Default for WorldGen {
+    fn default() -> Self {
+        Self { seed: DEFAULT_SEED, world_type: WorldType::Default }
+    }
+}
+
+impl WorldGen {
+    pub fn new(seed: u32, world_type: WorldType) -> Self {
+        Self { seed, world_type }
+    }
+
+    /// Generates the chunk at `position`, a multiple of CHUNK_SIZE on each axis
+    pub fn generate(&self, position: Vec3) -> Chunk {
+        match &self.world_type {
+            WorldType::Default => Chunk::with_seed(position, self.seed),
+            WorldType::Superflat { layers } => superflat(position, layers),
+            WorldType::Debug => debug(position),
+        }
+    }
+
+    /// Height and block of the topmost solid block in the column at (`x`, `z`), worked out
+    /// without generating its chunk, or None where the column is empty
+    pub fn surface(&self, x: i32, z: i32) -> Option<(i32, BlockType)> {
+        match &self.world_type {
+            WorldType::Default => {
+                let height = Chunk::terrain_height(x, z, self.seed).min(CHUNK_SIZE as i32 - 1);
+                (height > 0).then_some((height - 1, BlockType::Grass))
+            }
+            WorldType::Superflat { layers } => {
+                let mut top = None;
+                let mut bottom = 0;
+                for layer in layers {
+                    bottom += layer.thickness as i32;
+                    if layer.block.is_solid() || layer.block.is_fluid() {
+                        top = Some((bottom - 1, layer.block));
+                    }
+                }
+                top
+            }
+            WorldType::Debug => None,
+        }
+    }
+
+    /// Seed the biomes vary by, or None when the whole world is plains
+    pub fn biome_seed(&self) -> Option<u32> {
+        matches!(self.world_type, WorldType::Default).then_some(self.seed)
+    }
+
+    /// Biome of the column at (`x`, `z`)
+    pub fn biome(&self, x: i32, z: i32) -> Biome {
+        self.biome_seed().map_or(Biome::Plains, |seed| Biome::at(seed, x, z))
+    }
+
+    pub fn encode(&self, w: &mut ByteWriter) {
+        w.write_u32(self.seed);
+        match &self.world_type {
+            WorldType::Default => w.write_u8(TYPE_DEFAULT),
+            WorldType::Superflat { layers } => {
+                w.write_u8(TYPE_SUPERFLAT);
+                w.write_u8(layers.len() as u8);
+                for layer in layers {
+                    w.write_u8(layer.block.id());
+                    w.write_u8(layer.block.meta());
+                    w.write_u8(layer.thickness);
+                }
+            }
+            WorldType::Debug => w.write_u8(TYPE_DEBUG),
+        }
+    }
+
+    pub fn decode(r: &mut ByteReader) -> Result<Self, DecodeError> {
+        let seed = r.read_u32()?;
+        let world_type = match r.read_u8()? {
+            TYPE_DEFAULT => WorldType::Default,
+            TYPE_SUPERFLAT => {
+                let count = r.read_u8()? as usize;
+                if count > MAX_FLAT_LAYERS {
+                    return Err(DecodeError::Invalid(format!("{} superflat layers", count)));
+                }
+                let mut layers = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let (id, meta) = (r.read_u8()?, r.read_u8()?);
+                    let block = BlockType::from_parts(id, meta)
+                        .ok_or_else(|| DecodeError::Invalid(format!("unknown block {}:{}", id, meta)))?;
+                    layers.push(FlatLayer { block, thickness: r.read_u8()? });
+                }
+                WorldType::Superflat { layers }
+            }
+            TYPE_DEBUG => WorldType::Debug,
+            other => return Err(DecodeError::Invalid(format!("unknown world type {}", other))),
+        };
+        Ok(Self { seed, world_type })
+    }
+}
+
+fn superflat(position: Vec3, layers: &[FlatLayer]) -> Chunk {
+    let mut chunk = Chunk::empty(position);
+    let base = position.y as i32;
+    let mut bottom = 0;
+    for layer in layers {
+        let top = bottom + layer.thickness as i32;
+        for y in bottom.max(base)..top.min(base + CHUNK_SIZE as i32) {
+            let y = (y - base) as usize;
+            for column in chunk.blocks.iter_mut() {
+                column[y] = [layer.block; CHUNK_SIZE];
+            }
+        }
+        bottom = top;
+    }
+    chunk
+}
+
+/// Every valid combination of block id and metadata, air aside, in id order
+pub fn every_block_state() -> Vec<BlockType> {
+    (1..=u8::MAX)
+        // Plain blocks accept any metadata and ignore it, so only take states that keep theirs
+        .flat_map(|id| (0..=u8::MAX).filter_map(move |meta| BlockType::from_parts(id, meta).filter(|b| b.meta() == meta)))
+        .collect()
+}
+
+/// Grid cells of the debug world: every other block across the chunk, in layers two apart
+/// starting just above the floor
+pub fn debug_grid_cell(index: usize) -> Option<(usize, usize, usize)> {
+    let side = CHUNK_SIZE / 2;
+    let (x, z, layer) = (index % side, (index / side) % side, index / (side * side));
+    let y = 1 + layer * 2;
+    (y < CHUNK_SIZE).then_some((x * 2, y, z * 2))
+}
+
+fn debug(position: Vec3) -> Chunk {
+    let mut chunk = Chunk::empty(position);
+    if position != Vec3::ZERO {
+        return chunk;
+    }
+    for column in chunk.blocks.iter_mut() {
+        for block in column[0].iter_mut() {
+            *block = BlockType::Stone;
+        }
+    }
+    for (index, block) in every_block_state().into_iter().enumerate() {
+        let Some((x, y, z)) = debug_grid_cell(index) else { break };
+        chunk.blocks[x][y][z] = block;
+    }
+    chunk
+}
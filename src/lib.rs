@@ -1,7 +1,11 @@
 //! Library entry point for the game engine.
 
+#[cfg(target_os = "android")]
+mod android;
 pub mod engine;
 pub mod game;
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+pub mod web;
 
 // Re-export main types for convenience
-pub use game::App; 
\ No newline at end of file
+pub use game::App; 
@@ -1,15 +1,44 @@
 //! Application entry point.
+//!
+//! Usage: game [--world <dir>] [--world-type <type>] [--seed <number>]
+//!        game --connect <host[:port]|lan> [--name <name>]
+//! `--world-type` and `--seed` only apply when the world is created, as for the server.
+//! `--connect` joins a multiplayer server instead of playing singleplayer; `lan` joins the
+//! first one found on the local network.
 
 use winit::event_loop::{ControlFlow, EventLoop};
-use log::{info, error};
+use log::{info, error, warn};
+
+use game::game::world::app::{SINGLEPLAYER_NAME, WORLD_SAVE_DIR};
+use game::game::world::{WorldGen, WorldType};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
     info!("Logger initialized");
 
-    // Initialize logging, panic hooks, etc.
-    // TODO: Add proper error handling and logging setup
-    
+    // Put panics in the log too; unwinding then drops the app, which saves and shuts down
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic| {
+        error!("{}", panic);
+        default_hook(panic);
+    }));
+
+    let mut world_dir = WORLD_SAVE_DIR.to_string();
+    let mut generator = WorldGen::default();
+    let mut connect = None;
+    let mut name = SINGLEPLAYER_NAME.to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--world" => world_dir = args.next().ok_or("--world needs a directory")?,
+            "--world-type" => generator.world_type = WorldType::parse(&args.next().ok_or("--world-type needs a type")?)?,
+            "--seed" => generator.seed = args.next().ok_or("--seed needs a number")?.parse()?,
+            "--connect" => connect = Some(args.next().ok_or("--connect needs an address")?),
+            "--name" => name = args.next().ok_or("--name needs a name")?,
+            other => warn!("Ignoring unknown argument '{}'", other),
+        }
+    }
+
     let event_loop = EventLoop::new().map_err(|e| {
         error!("Failed to create event loop: {:?}", e);
         e
@@ -17,7 +46,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     event_loop.set_control_flow(ControlFlow::Poll);
 
     // Start the main app loop
-    let mut app = game::App::default();
+    let mut app = match connect {
+        Some(address) => game::App::join(&address, &name),
+        None => game::App::with_world(world_dir, generator),
+    };
     if let Err(e) = event_loop.run_app(&mut app) {
         error!("Application error: {:?}", e);
         return Err(Box::new(e));
@@ -0,0 +1,55 @@
+//! Browser entry point, run by wasm-bindgen when the module is instantiated.
+//!
+//! Build with `cargo rustc --lib --crate-type cdylib --release --target wasm32-unknown-unknown
+//! --features web`, run `wasm-bindgen --target web` on the output, and serve it next to
+//! `web/index.html` and the `assets/` directory.
+
+use log::{error, Level, LevelFilter, Log, Metadata, Record};
+use wasm_bindgen::prelude::*;
+use winit::event_loop::EventLoop;
+use winit::platform::web::EventLoopExtWebSys;
+
+use crate::game::App;
+
+/// Forwards log records to the browser console
+struct ConsoleLogger;
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = JsValue::from(format!("[{}] {}", record.target(), record.args()));
+        match record.level() {
+            Level::Error => web_sys::console::error_1(&message),
+            Level::Warn => web_sys::console::warn_1(&message),
+            _ => web_sys::console::log_1(&message),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: ConsoleLogger = ConsoleLogger;
+
+#[wasm_bindgen(start)]
+pub fn start() {
+    std::panic::set_hook(Box::new(|info| web_sys::console::error_1(&JsValue::from(info.to_string()))));
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+
+    let event_loop = match EventLoop::new() {
+        Ok(event_loop) => event_loop,
+        Err(e) => {
+            error!("Failed to create event loop: {:?}", e);
+            return;
+        }
+    };
+    // Returns immediately; the browser calls back into the app from its own event loop
+    event_loop.spawn_app(App::default());
+}
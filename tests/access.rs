@@ -0,0 +1,128 @@
+//! The whitelist and ops list come from files in the world directory, change through admin
+//! commands and take effect at once, and players keep their data under a stable id. Only
+//! plain names are let in, one session each.
+
+mod common;
+
+use glam::Vec3;
+use game::game::command::{CommandSender, PermissionLevel};
+use game::game::save::{PlayerData, PlayerId, WorldSave};
+use game::game::server::access::{AccessLists, OPS_FILE, WHITELIST_FILE};
+use game::game::server::network::{check_name, MAX_NAME_LENGTH};
+use game::game::server::Server;
+use game::game::world::chunk::DEFAULT_SEED;
+use game::game::world::{WorldGen, WorldType};
+
+/// An empty world directory, made up front for the list files
+fn scratch_dir(name: &str) -> std::path::PathBuf {
+    let dir = common::scratch_dir(&format!("access-{}", name));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn server(dir: &std::path::Path) -> Server {
+    Server::create(WorldSave::open(dir), WorldGen::new(DEFAULT_SEED, WorldType::Default))
+}
+
+#[test]
+fn lists_parse_and_round_trip() {
+    let lists = AccessLists::parse("# who\nenabled = true\nAlice\nbob\n", "Carol moderator\ndave\n").unwrap();
+    assert!(lists.is_allowed("alice") && lists.is_allowed("BOB") && !lists.is_allowed("eve"));
+    assert_eq!(lists.permission("carol"), PermissionLevel::Moderator);
+    assert_eq!(lists.permission("Dave"), PermissionLevel::Admin);
+    assert_eq!(lists.permission("alice"), PermissionLevel::Player);
+    let again = AccessLists::parse(&lists.whitelist_text(), &lists.ops_text()).unwrap();
+    assert_eq!(again, lists);
+
+    assert!(AccessLists::parse("", "").unwrap().is_allowed("anyone"));
+    assert_eq!(AccessLists::parse("enabled = maybe", "").unwrap_err(), "whitelist.txt line 1: enabled must be true or false");
+    assert!(AccessLists::parse("", "erin god").unwrap_err().starts_with("ops.txt line 1"));
+}
+
+#[test]
+fn whitelist_keeps_out_players_not_on_it() {
+    let dir = scratch_dir("whitelist");
+    let mut server = server(&dir);
+    let console = CommandSender::console();
+    server.execute(&console, "/whitelist add Alice").unwrap();
+    let bob = server.connect("bob", PermissionLevel::Player).unwrap();
+    let reply = server.execute(&console, "/whitelist on").unwrap();
+    // Turning it on sends away players not on it
+    assert!(reply.contains("disconnected 1"), "{}", reply);
+    assert!(server.session(bob).is_none());
+    assert!(server.connect("bob", PermissionLevel::Player).is_err());
+    assert!(server.connect("alice", PermissionLevel::Player).is_ok());
+    // The host is always let in
+    assert!(server.connect("host", PermissionLevel::Admin).is_ok());
+    assert!(std::fs::read_to_string(dir.join(WHITELIST_FILE)).unwrap().contains("enabled = true"));
+}
+
+#[test]
+fn ops_are_given_their_permission_and_reload_picks_up_edits() {
+    let dir = scratch_dir("ops");
+    let mut server = server(&dir);
+    let console = CommandSender::console();
+    let carol = server.connect("carol", PermissionLevel::Player).unwrap();
+    server.execute(&console, "/op carol moderator").unwrap();
+    assert_eq!(server.session(carol).unwrap().permission, PermissionLevel::Moderator);
+    assert!(std::fs::read_to_string(dir.join(OPS_FILE)).unwrap().contains("carol moderator"));
+
+    std::fs::write(dir.join(OPS_FILE), "carol admin\n").unwrap();
+    std::fs::write(dir.join(WHITELIST_FILE), "enabled = false\ncarol\n").unwrap();
+    server.execute(&console, "/reload").unwrap();
+    assert_eq!(server.session(carol).unwrap().permission, PermissionLevel::Admin);
+    // Ops are not let past the whitelist
+    std::fs::write(dir.join(WHITELIST_FILE), "enabled = true\n").unwrap();
+    let reply = server.execute(&console, "/reload").unwrap();
+    assert!(reply.contains("disconnected 1"), "{}", reply);
+    assert!(server.connect("carol", PermissionLevel::Player).is_err());
+
+    std::fs::write(dir.join(WHITELIST_FILE), "enabled = true\ncarol\n").unwrap();
+    server.execute(&console, "/reload").unwrap();
+    let carol = server.connect("Carol", PermissionLevel::Player).unwrap();
+    assert_eq!(server.session(carol).unwrap().permission, PermissionLevel::Admin);
+    server.execute(&console, "/deop carol").unwrap();
+    assert_eq!(server.session(carol).unwrap().permission, PermissionLevel::Player);
+    assert!(server.execute(&console, "/deop carol").is_err());
+}
+
+#[test]
+fn player_data_is_kept_under_a_stable_id() {
+    assert_eq!(PlayerId::from_name("Alice"), PlayerId::from_name("alice"));
+    assert_ne!(PlayerId::from_name("a.b"), PlayerId::from_name("a_b"));
+
+    let dir = scratch_dir("players");
+    let save = WorldSave::open(&dir);
+    let data = PlayerData::new(Vec3::new(4.0, 70.0, -9.0));
+    // A save from before player ids, under the player's name
+    std::fs::create_dir_all(dir.join("players")).unwrap();
+    let mut w = game::engine::codec::ByteWriter::new();
+    data.encode(&mut w);
+    std::fs::write(dir.join("players").join("Alice.dat"), w.into_inner()).unwrap();
+    let id = PlayerId::from_name("Alice");
+    assert_eq!(save.load_player(id, "Alice"), Some(data));
+
+    let moved = PlayerData::new(Vec3::new(1.0, 2.0, 3.0));
+    save.save_player(id, &moved);
+    save.wait().unwrap();
+    assert!(dir.join("players").join(format!("{}.dat", id)).exists());
+    assert_eq!(save.load_player(id, "Alice"), Some(moved));
+}
+
+#[test]
+fn only_plain_names_join_and_only_once() {
+    assert!(check_name("Alice_2-b").is_ok());
+    assert!(check_name("").is_err());
+    assert!(check_name(&"a".repeat(MAX_NAME_LENGTH + 1)).is_err());
+    for name in ["al ice", "alice\n", "\u{1b}[31malice", "alïce"] {
+        assert!(check_name(name).is_err(), "{:?}", name);
+    }
+
+    let dir = scratch_dir("names");
+    let mut server = server(&dir);
+    server.connect("alice", PermissionLevel::Player).unwrap();
+    assert!(server.connect("ALICE", PermissionLevel::Player).is_err());
+    // Would share a save with the first
+    server.connect("Ä", PermissionLevel::Player).unwrap();
+    assert!(server.connect("ä", PermissionLevel::Player).is_err());
+}
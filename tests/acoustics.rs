@@ -0,0 +1,70 @@
+//! Sounds are muffled by the blocks in their way, and enclosed air echoes more the more of
+//! it there is.
+
+mod common;
+
+use glam::Vec3;
+use game::engine::audio::{occlusion_gain, Reverb};
+use game::game::world::acoustics::{enclosed_air, occluding_blocks, reverb_at, SKY_HEIGHT};
+use game::game::world::chunk::BlockType;
+use game::game::world::chunk_manager::ChunkManager;
+
+/// Digs out the blocks from `min` to `max`, both included
+fn carve(chunks: &mut ChunkManager, min: (i32, i32, i32), max: (i32, i32, i32)) {
+    for x in min.0..=max.0 {
+        for y in min.1..=max.1 {
+            for z in min.2..=max.2 {
+                chunks.set_block((x, y, z), BlockType::Air);
+            }
+        }
+    }
+}
+
+fn at(block: (i32, i32, i32)) -> Vec3 {
+    Vec3::new(block.0 as f32, block.1 as f32, block.2 as f32)
+}
+
+#[test]
+fn walls_between_muffle_a_sound() {
+    let mut chunks = common::world(BlockType::Air);
+    assert_eq!(occluding_blocks(&chunks, at((0, 0, 0)), at((8, 0, 0))), 0);
+    for x in [3, 4] {
+        for y in -2..=2 {
+            for z in -2..=2 {
+                chunks.set_block((x, y, z), BlockType::Stone);
+            }
+        }
+    }
+    assert_eq!(occluding_blocks(&chunks, at((0, 0, 0)), at((8, 0, 0))), 2);
+    // A sound from inside a block, such as a door, isn't muffled by it
+    chunks.set_block((0, 0, 0), BlockType::Stone);
+    assert_eq!(occluding_blocks(&chunks, at((0, 0, 0)), at((8, 0, 0))), 2);
+    assert_eq!(occlusion_gain(0), 1.0);
+    assert_eq!(occlusion_gain(2), 0.25);
+}
+
+#[test]
+fn open_sky_does_not_echo() {
+    let chunks = common::world(BlockType::Air);
+    assert_eq!(enclosed_air(&chunks, (0, 0, 0)), None);
+    assert_eq!(reverb_at(&chunks, at((0, 0, 0))), Reverb::None);
+}
+
+#[test]
+fn bigger_spaces_echo_longer() {
+    let mut chunks = common::world(BlockType::Stone);
+    carve(&mut chunks, (0, 0, 0), (2, 2, 2));
+    assert_eq!(enclosed_air(&chunks, (1, 1, 1)), Some(27));
+    assert_eq!(reverb_at(&chunks, at((1, 1, 1))), Reverb::Room);
+
+    carve(&mut chunks, (-10, -4, -10), (0, 2, 0));
+    assert_eq!(reverb_at(&chunks, at((1, 1, 1))), Reverb::Cave);
+
+    carve(&mut chunks, (-20, -10, -20), (10, 2, 10));
+    assert_eq!(reverb_at(&chunks, at((1, 1, 1))), Reverb::LargeCave);
+    assert!(reverb_at(&chunks, at((1, 1, 1))).decay() > Reverb::Cave.decay());
+
+    // A shaft up to the surface lets the sound out
+    carve(&mut chunks, (1, 3, 1), (1, 3 + SKY_HEIGHT, 1));
+    assert_eq!(reverb_at(&chunks, at((1, 1, 1))), Reverb::None);
+}
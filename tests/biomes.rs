@@ -0,0 +1,39 @@
+//! Biome tints blend across borders and only color the faces that ask for them.
+
+use game::game::world::biome::{pack_color, Biome, TintGrid};
+use game::game::world::chunk::BlockType;
+use game::game::world::material::Tint;
+
+fn unpack(packed: u32) -> [f32; 3] {
+    [(packed >> 11) as f32 / 31.0, ((packed >> 5) & 63) as f32 / 63.0, (packed & 31) as f32 / 31.0]
+}
+
+#[test]
+fn one_biome_tints_every_corner_alike() {
+    let grid = TintGrid::new(0, 0, |_, _| Biome::Forest);
+    let color = pack_color(Biome::Forest.color(Tint::Grass));
+    assert_eq!(grid.packed(Tint::Grass, 7, 3), [color | color << 16; 2]);
+}
+
+#[test]
+fn corners_blend_across_a_border() {
+    // Swamp west of x = 8, savanna from there on
+    let grid = TintGrid::new(0, 0, |x, _| if x < 8 { Biome::Swamp } else { Biome::Savanna });
+    let red = |x: usize| unpack(grid.packed(Tint::Grass, x, 0)[0] & 0xffff)[0];
+    let (swamp, savanna) = (Biome::Swamp.color(Tint::Grass)[0], Biome::Savanna.color(Tint::Grass)[0]);
+    assert!((red(5) - swamp).abs() < 0.04);
+    assert!((red(11) - savanna).abs() < 0.04);
+    // The corners across the border step through the blend rather than jumping
+    let steps: Vec<f32> = (6..=10).map(red).collect();
+    assert!(steps.windows(2).all(|w| w[1] > w[0]), "{:?}", steps);
+    assert!(steps.windows(2).all(|w| w[1] - w[0] < (savanna - swamp) * 0.3), "{:?}", steps);
+}
+
+#[test]
+fn only_grass_tops_are_tinted() {
+    assert_eq!(BlockType::Grass.tint(4), Some(Tint::Grass));
+    for face in [0, 1, 2, 3, 5] {
+        assert_eq!(BlockType::Grass.tint(face), None);
+    }
+    assert_eq!(BlockType::Stone.tint(4), None);
+}
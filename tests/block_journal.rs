@@ -0,0 +1,199 @@
+//! Block edits reach clients that weren't there when they were made: players joining
+//! later get them in one message per chunk, unless they cached them last time, and chunks
+//! that arrive after an edit take it. Saving keeps the edits in the region files, so they
+//! outlast the server.
+
+mod common;
+
+use std::time::{Duration, Instant};
+use glam::Vec3;
+use game::engine::codec::{ByteReader, ByteWriter};
+use game::engine::net::loopback::LoopbackConnection;
+use game::engine::net::Connection;
+use game::game::command::PermissionLevel;
+use game::game::net::protocol::{chunk_edits_hash, ClientMessage, ServerMessage};
+use game::game::net::{ChunkEditCache, ClientEvent, ClientSession};
+use game::game::save::WorldSave;
+use game::game::server::{BlockJournal, Server};
+use game::game::world::chunk::{BlockType, DEFAULT_SEED};
+use game::game::world::chunk_manager::ChunkManager;
+use game::game::world::{WorldGen, WorldType};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[test]
+fn journal_keeps_the_latest_block_per_position() {
+    let mut journal = BlockJournal::new();
+    journal.record((1, 2, 3), BlockType::Stone);
+    journal.record((1, 2, 3), BlockType::Air);
+    journal.record((-1, 2, 3), BlockType::Dirt);
+    assert_eq!(journal.len(), 2);
+    assert_eq!(journal.edits((0, 0, 0)).collect::<Vec<_>>(), vec![((1, 2, 3), BlockType::Air)]);
+    assert_eq!(journal.edits((-1, 0, 0)).collect::<Vec<_>>(), vec![((-1, 2, 3), BlockType::Dirt)]);
+    assert_eq!(journal.chunks().count(), 2);
+}
+
+#[test]
+fn released_chunks_take_their_edits_back() {
+    let mut journal = BlockJournal::new();
+    journal.record((1, 2, 3), BlockType::Stone);
+    journal.record((-1, 2, 3), BlockType::Dirt);
+    let saved = journal.local_edits((0, 0, 0));
+    journal.release(|&key| key == (-1, 0, 0));
+    assert!(!journal.holds((0, 0, 0)));
+    assert_eq!(journal.len(), 1);
+    // Still edited, for clients coming within range
+    assert_eq!(journal.chunks().count(), 2);
+    assert_eq!(journal.released().collect::<Vec<_>>(), vec![&(0, 0, 0)]);
+
+    journal.restore((0, 0, 0), &saved);
+    assert!(journal.holds((0, 0, 0)));
+    assert_eq!(journal.edits((0, 0, 0)).collect::<Vec<_>>(), vec![((1, 2, 3), BlockType::Stone)]);
+    assert_eq!(journal.released().count(), 0);
+}
+
+#[test]
+fn chunk_edits_round_trip() {
+    let message = ServerMessage::ChunkEdits {
+        chunk: (-3, 0, 7),
+        edits: vec![((0, 15, 3), BlockType::Stone), ((2, 0, 9), BlockType::Air)],
+    };
+    let mut w = ByteWriter::new();
+    message.encode(&mut w);
+    let bytes = w.into_inner();
+    let decoded = ServerMessage::decode(&mut ByteReader::new(&bytes)).unwrap();
+    assert_eq!(decoded, message);
+}
+
+#[test]
+fn edit_cache_survives_reopening() {
+    let path = common::scratch_dir("journal-cache").join("edits.bin");
+    let edits = vec![((1, 2, 3), BlockType::Stone), ((4, 5, 6), BlockType::Air)];
+    let mut cache = ChunkEditCache::open(&path);
+    assert!(cache.is_empty());
+    cache.store((-2, 0, 5), edits.clone());
+    cache.save().unwrap();
+    let cache = ChunkEditCache::open(&path);
+    assert_eq!(cache.get((-2, 0, 5), chunk_edits_hash(&edits)), Some(edits.as_slice()));
+    assert_eq!(cache.get((-2, 0, 5), chunk_edits_hash(&edits[..1])), None);
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+/// Everything the client sent on `server` since the last call
+fn sent(server: &mut LoopbackConnection) -> Vec<ClientMessage> {
+    std::iter::from_fn(|| server.recv().unwrap()).map(|packet| ClientMessage::from_bytes(&packet).unwrap()).collect()
+}
+
+#[test]
+fn rejoining_takes_unchanged_edits_from_the_cache() {
+    let path = common::scratch_dir("journal-rejoin").join("edits.bin");
+    let chunk = (1, 0, -1);
+    let edits = vec![((0, 3, 15), BlockType::Stone)];
+    let hash = ServerMessage::ChunkHash { chunk, hash: chunk_edits_hash(&edits) };
+    let update = ClientEvent::BlockUpdate { block: (16, 3, -1), block_type: BlockType::Stone };
+
+    let (mut server, client) = LoopbackConnection::pair("first");
+    let mut session = ClientSession::connect(Box::new(client), "player");
+    session.set_edit_cache(ChunkEditCache::open(&path));
+    server.send(&hash.to_bytes()).unwrap();
+    assert!(session.poll().is_empty());
+    assert!(sent(&mut server).contains(&ClientMessage::RequestChunkEdits { chunk }));
+    server.send(&ServerMessage::ChunkEdits { chunk, edits: edits.clone() }.to_bytes()).unwrap();
+    assert_eq!(session.poll(), vec![update.clone()]);
+    session.close();
+
+    let (mut server, client) = LoopbackConnection::pair("second");
+    let mut session = ClientSession::connect(Box::new(client), "player");
+    session.set_edit_cache(ChunkEditCache::open(&path));
+    server.send(&hash.to_bytes()).unwrap();
+    assert_eq!(session.poll(), vec![update]);
+    assert!(!sent(&mut server).iter().any(|message| matches!(message, ClientMessage::RequestChunkEdits { .. })));
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+/// Ticks `server` until `block` is loaded
+fn load(server: &mut Server, block: (i32, i32, i32)) {
+    let start = Instant::now();
+    while server.chunks.get_block(block.0, block.1, block.2).is_none() {
+        assert!(start.elapsed() < TIMEOUT, "the spawn chunk never loaded");
+        server.tick();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn late_joiner_gets_earlier_edits() {
+    let dir = common::scratch_dir("journal-late");
+    let mut server = Server::create(WorldSave::open(&dir), WorldGen::new(DEFAULT_SEED, WorldType::Default));
+    let first = server.connect("first", PermissionLevel::Player).unwrap();
+    let block = (8, 4, 8);
+    load(&mut server, block);
+    let edited = if server.chunks.get_block(block.0, block.1, block.2) == Some(BlockType::Stone) { BlockType::Air } else { BlockType::Stone };
+    server.set_block(block, edited);
+    server.drain_outbox();
+
+    let late = server.connect("late", PermissionLevel::Player).unwrap();
+    server.tick();
+    let outbox = server.drain_outbox();
+    let hashes_for = |client| outbox.iter().filter_map(move |(to, message)| match message {
+        ServerMessage::ChunkHash { chunk, hash } if *to == client => Some((*chunk, *hash)),
+        _ => None,
+    }).collect::<Vec<_>>();
+    let edits = vec![((8, 4, 8), edited)];
+    assert_eq!(hashes_for(late), vec![((0, 0, 0), chunk_edits_hash(&edits))]);
+    // The first player saw the edit happen and isn't told again
+    assert!(hashes_for(first).is_empty());
+
+    server.handle_message(late, ClientMessage::RequestChunkEdits { chunk: (0, 0, 0) });
+    // Chunks out of range aren't given away
+    server.handle_message(late, ClientMessage::RequestChunkEdits { chunk: (100, 0, 0) });
+    let outbox = server.drain_outbox();
+    let sent: Vec<_> = outbox.iter().filter(|(to, _)| *to == late).map(|(_, message)| message.clone()).collect();
+    assert_eq!(sent, vec![ServerMessage::ChunkEdits { chunk: (0, 0, 0), edits }]);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn edits_outlast_the_server() {
+    let dir = common::scratch_dir("journal-restart");
+    let block = (8, 4, 8);
+    let mut server = Server::create(WorldSave::open(&dir), WorldGen::new(DEFAULT_SEED, WorldType::Default));
+    server.connect("player", PermissionLevel::Player).unwrap();
+    load(&mut server, block);
+    let edited = if server.chunks.get_block(block.0, block.1, block.2) == Some(BlockType::Stone) { BlockType::Air } else { BlockType::Stone };
+    server.set_block(block, edited);
+    server.save_all().unwrap();
+    drop(server);
+
+    let mut server = Server::create(WorldSave::open(&dir), WorldGen::new(DEFAULT_SEED, WorldType::Default));
+    // Known before anything loads, for clients coming within range
+    assert_eq!(server.journal.chunks().collect::<Vec<_>>(), vec![&(0, 0, 0)]);
+    let client = server.connect("player", PermissionLevel::Player).unwrap();
+    load(&mut server, block);
+    assert_eq!(server.chunks.get_block(block.0, block.1, block.2), Some(edited));
+    let outbox = server.drain_outbox();
+    let edits = vec![((8, 4, 8), edited)];
+    assert!(outbox.contains(&(client, ServerMessage::ChunkHash { chunk: (0, 0, 0), hash: chunk_edits_hash(&edits) })));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn edits_wait_for_their_chunk() {
+    let mut chunks = ChunkManager::new(1);
+    let position = Vec3::new(8.0, 8.0, 8.0);
+    let block = ChunkManager::block_coords(position);
+    let generated = WorldGen::default().generate(Vec3::ZERO).blocks[8][8][8];
+    let edited = if generated == BlockType::Stone { BlockType::Air } else { BlockType::Stone };
+    chunks.apply_edit(block, edited);
+    let start = Instant::now();
+    loop {
+        chunks.update_chunks(position);
+        chunks.poll_generated();
+        if chunks.loaded.contains_key(&ChunkManager::chunk_key(block)) {
+            break;
+        }
+        assert!(start.elapsed() < TIMEOUT, "the chunk never arrived");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(chunks.get_block(block.0, block.1, block.2), Some(edited));
+}
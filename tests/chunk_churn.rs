@@ -0,0 +1,188 @@
+//! Moves a virtual camera along long paths through a headless ChunkManager and checks that
+//! loading and unloading never leak or double-track chunks.
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use glam::Vec3;
+use game::game::world::chunk::{BlockType, Chunk};
+use game::game::world::chunk_manager::{ChunkManager, MIN_CHUNK_Y};
+use game::game::world::memory::MemoryUsage;
+
+const VIEW_DISTANCE: i32 = 2;
+const CUBE: usize = ((2 * VIEW_DISTANCE + 1) * (2 * VIEW_DISTANCE + 1) * (2 * VIEW_DISTANCE + 1)) as usize;
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What the manager reported through drain_loaded and drain_unloaded
+#[derive(Default)]
+struct Mirror {
+    keys: HashSet<(i32, i32, i32)>,
+}
+
+impl Mirror {
+    fn sync(&mut self, chunks: &mut ChunkManager) {
+        for key in chunks.drain_unloaded() {
+            assert!(self.keys.remove(&key), "unloaded {:?} which was never reported loaded", key);
+        }
+        for key in chunks.drain_loaded() {
+            assert!(self.keys.insert(key), "loaded {:?} twice", key);
+        }
+    }
+}
+
+/// Within view distance and not in the void below the world
+fn in_range(key: (i32, i32, i32), center: (i32, i32, i32)) -> bool {
+    key.1 >= MIN_CHUNK_Y
+        && (key.0 - center.0).abs() <= VIEW_DISTANCE
+        && (key.1 - center.1).abs() <= VIEW_DISTANCE
+        && (key.2 - center.2).abs() <= VIEW_DISTANCE
+}
+
+/// Chunks loaded around `center` once everything arrived: the cube, less what is below
+/// the world
+fn settled_len(center: (i32, i32, i32)) -> usize {
+    let side = (2 * VIEW_DISTANCE + 1) as usize;
+    let layers = (center.1 + VIEW_DISTANCE + 1 - MIN_CHUNK_Y.max(center.1 - VIEW_DISTANCE)).max(0) as usize;
+    side * side * layers
+}
+
+fn check_invariants(chunks: &ChunkManager, mirror: &Mirror, step: usize) {
+    for key in &chunks.pending {
+        assert!(!chunks.loaded.contains_key(key), "step {}: {:?} is both loaded and pending", step, key);
+        assert!(key.1 >= MIN_CHUNK_Y, "step {}: {:?} requested below the world", step, key);
+    }
+    // Everything the player could need at once, plus one cube still in flight from earlier
+    assert!(chunks.pending.len() <= 2 * CUBE, "step {}: {} chunks pending", step, chunks.pending.len());
+    let loaded: HashSet<_> = chunks.loaded.keys().copied().collect();
+    assert_eq!(loaded, mirror.keys, "step {}: drained load events disagree with the loaded set", step);
+    for key in chunks.timings.keys() {
+        assert!(loaded.contains(key), "step {}: timings kept for unloaded chunk {:?}", step, key);
+    }
+    let usage = MemoryUsage::measure(chunks);
+    assert_eq!(usage.block_bytes, loaded.len() * std::mem::size_of_val(&Chunk::empty(Vec3::ZERO).blocks), "step {}", step);
+    assert_eq!(usage.gpu_bytes, 0, "step {}: headless chunks should have no GPU buffers", step);
+}
+
+/// One frame: stream around `position`, then receive finished chunks
+fn frame(chunks: &mut ChunkManager, mirror: &mut Mirror, position: Vec3, step: usize) {
+    chunks.update_chunks(position);
+    mirror.sync(chunks);
+    let center = ChunkManager::chunk_key_at(position);
+    // Unloading happens in update_chunks, so right after it nothing is out of range
+    for key in chunks.loaded.keys() {
+        assert!(in_range(*key, center), "step {}: {:?} still loaded around {:?}", step, key, center);
+    }
+    assert!(chunks.loaded.len() <= CUBE, "step {}", step);
+    chunks.poll_generated();
+    mirror.sync(chunks);
+    check_invariants(chunks, mirror, step);
+}
+
+/// Waits for all generation work to come back, then checks the final state is exactly the
+/// cube around `position`
+fn settle(chunks: &mut ChunkManager, mirror: &mut Mirror, position: Vec3) {
+    let start = Instant::now();
+    loop {
+        frame(chunks, mirror, position, usize::MAX);
+        if chunks.pending.is_empty() {
+            break;
+        }
+        assert!(start.elapsed() < SETTLE_TIMEOUT, "{} chunks never finished generating", chunks.pending.len());
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    chunks.update_chunks(position);
+    mirror.sync(chunks);
+    check_invariants(chunks, mirror, usize::MAX);
+    assert_eq!(chunks.loaded.len(), settled_len(ChunkManager::chunk_key_at(position)));
+    assert_eq!(chunks.queued_len(), 0);
+}
+
+fn run(path: impl Iterator<Item = Vec3>) {
+    let mut chunks = ChunkManager::new(VIEW_DISTANCE);
+    let mut mirror = Mirror::default();
+    let mut last = Vec3::ZERO;
+    for (step, position) in path.enumerate() {
+        frame(&mut chunks, &mut mirror, position, step);
+        // Edits mark chunks for remeshing; moving away must not keep them tracked
+        if step % 7 == 0 {
+            chunks.set_block(ChunkManager::block_coords(position), BlockType::Stone);
+        }
+        last = position;
+    }
+    settle(&mut chunks, &mut mirror, last);
+}
+
+#[test]
+fn straight_flight() {
+    run((0..400).map(|i| Vec3::new(i as f32 * 3.0, 8.0, 0.0)));
+}
+
+#[test]
+fn diagonal_flight_through_negative_coordinates() {
+    run((0..400).map(|i| Vec3::new(200.0 - i as f32 * 2.5, 40.0 - i as f32 * 0.5, 100.0 - i as f32 * 2.0)));
+}
+
+#[test]
+fn back_and_forth_across_a_chunk_border() {
+    run((0..500).map(|i| Vec3::new(if i % 2 == 0 { -0.6 } else { 0.4 }, 8.0, 15.0 + (i % 3) as f32)));
+}
+
+#[test]
+fn circling() {
+    run((0..600).map(|i| {
+        let angle = i as f32 * 0.05;
+        Vec3::new(angle.cos() * 80.0, 8.0, angle.sin() * 80.0)
+    }));
+}
+
+#[test]
+fn falling_into_the_void() {
+    run((0..400).map(|i| Vec3::new(8.0, 8.0 - i as f32 * 2.0, 8.0)));
+}
+
+#[test]
+fn teleporting() {
+    let spots = [Vec3::ZERO, Vec3::new(5000.0, 0.0, -5000.0), Vec3::new(-320.0, 64.0, 48.0), Vec3::new(1.0, -100.0, 1.0)];
+    run((0..120).map(|i| spots[i % spots.len()]));
+}
+
+#[test]
+fn turning_back_reuses_recent_chunks() {
+    let mut chunks = ChunkManager::new(VIEW_DISTANCE);
+    let mut mirror = Mirror::default();
+    let (home, away) = (Vec3::new(8.0, 8.0, 8.0), Vec3::new(8.0 + 16.0 * 3.0, 8.0, 8.0));
+    settle(&mut chunks, &mut mirror, home);
+    let edited = (0, 2, 0);
+    chunks.set_block(edited, BlockType::Stone);
+    settle(&mut chunks, &mut mirror, away);
+    let generated = chunks.chunks_generated;
+    assert!(!chunks.recent.is_empty());
+    settle(&mut chunks, &mut mirror, home);
+    // Everything that came back was still kept, edits and all
+    assert_eq!(chunks.chunks_generated, generated);
+    assert!(chunks.chunks_reused > 0);
+    assert_eq!(chunks.get_block(edited.0, edited.1, edited.2), Some(BlockType::Stone));
+    assert!(MemoryUsage::measure(&chunks).recent_bytes > 0);
+}
+
+#[test]
+fn recent_chunks_stay_bounded() {
+    use game::game::world::recent_chunks::RecentChunks;
+    let mut recent = RecentChunks::new(4);
+    let blocks = Box::new(Chunk::empty(Vec3::ZERO).blocks);
+    for i in 0..6 {
+        recent.store((i, 0, 0), blocks.clone());
+    }
+    assert_eq!(recent.len(), 4);
+    // The two stored first went first
+    assert!(recent.take((0, 0, 0)).is_none() && recent.take((1, 0, 0)).is_none());
+    assert!(recent.take((2, 0, 0)).is_some());
+    // Storing a key again makes it the newest
+    recent.store((3, 0, 0), blocks.clone());
+    for i in 6..9 {
+        recent.store((i, 0, 0), blocks.clone());
+    }
+    assert!(recent.take((4, 0, 0)).is_none());
+    assert!(recent.take((3, 0, 0)).is_some());
+    assert!(recent.len() <= recent.capacity());
+}
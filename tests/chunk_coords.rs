@@ -0,0 +1,253 @@
+//! Randomized property tests for world/chunk/local coordinate math and chunk boundary meshing.
+//!
+//! Cases come from a fixed-seed generator so failures reproduce; each assertion reports the
+//! case number and inputs.
+
+use glam::{IVec3, Vec3};
+use game::game::world::chunk::{texture_render_material, BlockType, Chunk, CHUNK_SIZE, CHUNK_SIZE_F};
+use game::game::world::chunk_manager::ChunkManager;
+
+const CASES: u32 = 2000;
+/// Chunk positions are f32, which is exact for block coordinates up to 2^24
+const MESH_RANGE: i32 = 1 << 20;
+
+/// xorshift64*, so the tests need no extra dependencies
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in `lo..=hi`
+    fn range(&mut self, lo: i32, hi: i32) -> i32 {
+        let span = (hi as i64 - lo as i64 + 1) as u64;
+        (lo as i64 + (self.next_u64() % span) as i64) as i32
+    }
+
+    /// Mostly small coordinates around chunk borders, sometimes huge ones
+    fn coordinate(&mut self) -> i32 {
+        match self.next_u64() % 4 {
+            0 => self.range(-40, 40),
+            1 => self.range(-MESH_RANGE, MESH_RANGE),
+            2 => self.range(i32::MIN / 2, i32::MAX / 2),
+            _ => self.range(-3, 3) * CHUNK_SIZE as i32 + self.range(-1, 0),
+        }
+    }
+
+    fn block_pos(&mut self) -> (i32, i32, i32) {
+        (self.coordinate(), self.coordinate(), self.coordinate())
+    }
+
+    fn block_type(&mut self) -> BlockType {
+        BlockType::from_id((self.next_u64() % 4) as u8).unwrap()
+    }
+}
+
+fn chunk_origin(key: (i32, i32, i32)) -> Vec3 {
+    Vec3::new(key.0 as f32, key.1 as f32, key.2 as f32) * CHUNK_SIZE_F
+}
+
+fn local(block: (i32, i32, i32)) -> (usize, usize, usize) {
+    let cs = CHUNK_SIZE as i32;
+    (block.0.rem_euclid(cs) as usize, block.1.rem_euclid(cs) as usize, block.2.rem_euclid(cs) as usize)
+}
+
+#[test]
+fn chunk_key_and_local_round_trip() {
+    let mut rng = Rng::new(1);
+    let cs = CHUNK_SIZE as i64;
+    for case in 0..CASES {
+        let block = rng.block_pos();
+        let key = ChunkManager::chunk_key(block);
+        let (lx, ly, lz) = local(block);
+        let rebuilt = (
+            key.0 as i64 * cs + lx as i64,
+            key.1 as i64 * cs + ly as i64,
+            key.2 as i64 * cs + lz as i64,
+        );
+        assert_eq!(rebuilt, (block.0 as i64, block.1 as i64, block.2 as i64), "case {}: block {:?} key {:?}", case, block, key);
+    }
+}
+
+#[test]
+fn block_coords_finds_the_block_containing_a_point() {
+    let mut rng = Rng::new(2);
+    for case in 0..CASES {
+        // f32 only has room for a fraction below 2^22
+        let block = (rng.range(-1 << 20, 1 << 20), rng.range(-1 << 20, 1 << 20), rng.range(-1 << 20, 1 << 20));
+        // Blocks span [n - 0.5, n + 0.5)
+        let offset = Vec3::new(
+            rng.range(-8, 7) as f32 / 16.0,
+            rng.range(-8, 7) as f32 / 16.0,
+            rng.range(-8, 7) as f32 / 16.0,
+        );
+        let point = Vec3::new(block.0 as f32, block.1 as f32, block.2 as f32) + offset;
+        assert_eq!(ChunkManager::block_coords(point), block, "case {}: point {:?}", case, point);
+        assert_eq!(ChunkManager::chunk_key_at(point), ChunkManager::chunk_key(block), "case {}: point {:?}", case, point);
+    }
+}
+
+#[test]
+fn get_block_agrees_with_direct_chunk_indexing() {
+    let mut rng = Rng::new(3);
+    for case in 0..CASES {
+        let block = rng.block_pos();
+        let block_type = rng.block_type();
+        let key = ChunkManager::chunk_key(block);
+        let mut chunks = ChunkManager::new(0);
+        let mut chunk = Chunk::empty(chunk_origin(key));
+        let (lx, ly, lz) = local(block);
+        chunk.blocks[lx][ly][lz] = block_type;
+        chunks.loaded.insert(key, chunk);
+
+        assert_eq!(chunks.get_block(block.0, block.1, block.2), Some(block_type), "case {}: block {:?}", case, block);
+        // The neighbour across the nearest chunk border is in an unloaded chunk or the same one
+        let across = (block.0 + if lx == 0 { -1 } else { 1 }, block.1, block.2);
+        let expected = (ChunkManager::chunk_key(across) == key).then_some(BlockType::Air);
+        assert_eq!(chunks.get_block(across.0, across.1, across.2), expected, "case {}: neighbour {:?}", case, across);
+    }
+}
+
+#[test]
+fn set_block_round_trips_through_get_block() {
+    let mut rng = Rng::new(4);
+    for case in 0..CASES {
+        let block = rng.block_pos();
+        let block_type = rng.block_type();
+        let key = ChunkManager::chunk_key(block);
+        let mut chunks = ChunkManager::new(0);
+        assert_eq!(chunks.set_block(block, block_type), None, "case {}: unloaded chunk", case);
+        chunks.loaded.insert(key, Chunk::empty(chunk_origin(key)));
+
+        assert_eq!(chunks.set_block(block, block_type), Some(BlockType::Air), "case {}: block {:?}", case, block);
+        assert_eq!(chunks.get_block(block.0, block.1, block.2), Some(block_type), "case {}: block {:?}", case, block);
+        let (lx, ly, lz) = local(block);
+        assert_eq!(chunks.loaded[&key].blocks[lx][ly][lz], block_type, "case {}: block {:?}", case, block);
+    }
+}
+
+/// Face index and offset, in the order generate_mesh uses
+const FACES: [(u32, (i32, i32, i32)); 6] = [
+    (0, (0, 0, 1)),
+    (1, (0, 0, -1)),
+    (2, (-1, 0, 0)),
+    (3, (1, 0, 0)),
+    (4, (0, 1, 0)),
+    (5, (0, -1, 0)),
+];
+
+#[test]
+fn boundary_faces_follow_the_neighbouring_chunk() {
+    let mut rng = Rng::new(5);
+    for case in 0..200 {
+        let key = (
+            rng.range(-MESH_RANGE, MESH_RANGE) / CHUNK_SIZE as i32,
+            rng.range(-MESH_RANGE, MESH_RANGE) / CHUNK_SIZE as i32,
+            rng.range(-MESH_RANGE, MESH_RANGE) / CHUNK_SIZE as i32,
+        );
+        let (face, offset) = FACES[(rng.next_u64() % 6) as usize];
+        let neighbour_key = (key.0 + offset.0, key.1 + offset.1, key.2 + offset.2);
+        let mut chunks = ChunkManager::new(0);
+        for k in [key, neighbour_key] {
+            let mut chunk = Chunk::empty(chunk_origin(k));
+            for block in chunk.blocks.iter_mut().flatten().flatten() {
+                *block = if rng.next_u64().is_multiple_of(3) { BlockType::Air } else { BlockType::Stone };
+            }
+            chunks.loaded.insert(k, chunk);
+        }
+
+        let mut chunk = chunks.loaded.remove(&key).unwrap();
+        chunk.generate_mesh(&chunks);
+        let origin = (key.0 * CHUNK_SIZE as i32, key.1 * CHUNK_SIZE as i32, key.2 * CHUNK_SIZE as i32);
+        for (i, j) in (0..CHUNK_SIZE).flat_map(|i| (0..CHUNK_SIZE).map(move |j| (i, j))) {
+            // The layer of this chunk that touches the neighbour
+            let edge = |d: i32, v: usize| match d {
+                1 => CHUNK_SIZE - 1,
+                -1 => 0,
+                _ => v,
+            };
+            let (lx, ly, lz) = match offset {
+                (_, 0, 0) => (edge(offset.0, 0), i, j),
+                (0, _, 0) => (i, edge(offset.1, 0), j),
+                _ => (i, j, edge(offset.2, 0)),
+            };
+            let block = (origin.0 + lx as i32, origin.1 + ly as i32, origin.2 + lz as i32);
+            let beyond = (block.0 + offset.0, block.1 + offset.1, block.2 + offset.2);
+            let has_face = chunk.block_face_instances.iter().any(|f| {
+                f.face == face && f.position == [block.0, block.1, block.2]
+            });
+            let expected = chunk.blocks[lx][ly][lz].is_solid()
+                && !chunks.get_block(beyond.0, beyond.1, beyond.2).unwrap().is_solid();
+            assert_eq!(has_face, expected, "case {}: block {:?} face {} towards {:?}", case, block, face, beyond);
+        }
+    }
+}
+
+#[test]
+fn render_sections_cover_every_face_within_their_bounds_grouped_by_material() {
+    let mut rng = Rng::new(11);
+    let chunks = ChunkManager::new(0);
+    for case in 0..20 {
+        let key = (rng.range(-100, 100), rng.range(-100, 100), rng.range(-100, 100));
+        let mut chunk = Chunk::empty(chunk_origin(key));
+        for block in chunk.blocks.iter_mut().flatten().flatten() {
+            *block = match rng.next_u64() % 10 {
+                0 | 1 => BlockType::Stone,
+                2 => BlockType::Water(0),
+                3 => BlockType::Wheat(2),
+                _ => BlockType::Air,
+            };
+        }
+        chunk.generate_mesh(&chunks);
+        let mut next = 0;
+        for section in &chunk.sections {
+            assert_eq!(section.instances.start, next, "case {}", case);
+            next = section.instances.end;
+            let mut material_next = section.instances.start;
+            for pair in section.materials.windows(2) {
+                assert!(pair[0].0 < pair[1].0, "case {}: materials out of order", case);
+            }
+            for (material, range) in &section.materials {
+                assert_eq!(range.start, material_next, "case {}", case);
+                material_next = range.end;
+                for face in &chunk.block_face_instances[range.start as usize..range.end as usize] {
+                    assert_eq!(texture_render_material(face.block_type), *material, "case {}", case);
+                }
+            }
+            assert_eq!(material_next, section.instances.end, "case {}", case);
+            for face in &chunk.block_face_instances[section.instances.start as usize..section.instances.end as usize] {
+                let p = IVec3::from(face.position).as_vec3();
+                assert!(p.cmpge(section.min).all() && p.cmple(section.max).all(), "case {}: face at {:?}", case, p);
+            }
+        }
+        assert_eq!(next as usize, chunk.block_face_instances.len(), "case {}", case);
+    }
+}
+
+#[test]
+fn far_views_project_like_near_ones() {
+    use game::game::world::camera::Camera;
+    let mut rng = Rng::new(0xFA12);
+    for case in 0..CASES {
+        // Eyes far out, where an f32 block position only keeps a fraction of a block
+        let block = IVec3::new(rng.range(-MESH_RANGE, MESH_RANGE), rng.range(-64, 64), rng.range(-MESH_RANGE, MESH_RANGE));
+        let fraction = Vec3::new(rng.range(0, 99) as f32, rng.range(0, 99) as f32, rng.range(0, 99) as f32) / 100.0;
+        let mut far = Camera::new();
+        far.position = block.as_vec3() + fraction;
+        far.yaw = rng.range(-314, 314) as f32 / 100.0;
+        far.pitch = rng.range(-150, 150) as f32 / 100.0;
+        let origin = far.render_origin();
+        let near = Camera { position: far.position - origin.as_vec3(), ..far.clone() };
+        assert_eq!(near.render_origin(), IVec3::ZERO, "case {}: eye {:?}", case, far.position);
+        let (relative, plain) = (far.relative_view_proj_mat(1.5), near.view_proj_mat(1.5));
+        assert!(relative.abs_diff_eq(plain, 1e-5), "case {}: eye {:?}", case, far.position);
+    }
+}
@@ -0,0 +1,56 @@
+//! Copied text reaches the clipboard backend, falls back to what was copied in the game
+//! when the backend has nothing, and pastes into signs line by line.
+
+use std::sync::{Arc, Mutex};
+use glam::Vec3;
+use game::engine::input::{Clipboard, ClipboardBackend};
+use game::game::state::{debug, SignEditor};
+
+/// A clipboard shared with the test, or a broken one when `None`
+struct Shared(Option<Arc<Mutex<String>>>);
+
+impl ClipboardBackend for Shared {
+    fn get_text(&mut self) -> Option<String> {
+        self.0.as_ref().map(|text| text.lock().unwrap().clone())
+    }
+
+    fn set_text(&mut self, text: &str) -> bool {
+        let Some(shared) = &self.0 else { return false };
+        *shared.lock().unwrap() = text.to_string();
+        true
+    }
+}
+
+#[test]
+fn copies_reach_the_system_clipboard_and_pastes_come_from_it() {
+    let shared = Arc::new(Mutex::new(String::new()));
+    let mut clipboard = Clipboard::with_backend(Box::new(Shared(Some(shared.clone()))));
+    clipboard.copy("hello");
+    assert_eq!(*shared.lock().unwrap(), "hello");
+    // Something else copied since
+    *shared.lock().unwrap() = "from outside\r\n".to_string();
+    assert_eq!(clipboard.paste(), "from outside");
+}
+
+#[test]
+fn text_copied_in_the_game_pastes_without_a_system_clipboard() {
+    let mut clipboard = Clipboard::with_backend(Box::new(Shared(None)));
+    assert_eq!(clipboard.paste(), "");
+    clipboard.copy("kept");
+    assert_eq!(clipboard.paste(), "kept");
+}
+
+#[test]
+fn pasting_into_a_sign_fills_its_lines() {
+    let mut editor = SignEditor::open((0, 0, 0), "");
+    editor.paste("one\ntwo\nthree\nfour\nfive");
+    assert_eq!(editor.text(), "one\ntwo\nthree\nfour");
+    let mut editor = SignEditor::open((0, 0, 0), "Go ");
+    editor.paste("north to the big oak tree");
+    assert_eq!(editor.text(), "Go north to the");
+}
+
+#[test]
+fn coordinates_name_the_block_and_chunk() {
+    assert_eq!(debug::coordinates(Vec3::new(17.6, 64.0, -0.4)), "XYZ 17.60 64.00 -0.40\nBlock 18 64 0\nChunk 1 4 0");
+}
@@ -0,0 +1,76 @@
+//! The frame clock runs simulation time at the chosen speed, holds it while paused and keeps
+//! real time going throughout. Frames take their length from real time, up to a limit.
+
+use std::time::Duration;
+use game::engine::time::{Clock, MAX_FRAME_DELTA, MAX_TIME_SCALE, MIN_TIME_SCALE, SHADER_TIME_PERIOD};
+use game::game::server::TickClock;
+
+#[test]
+fn simulation_time_follows_the_speed() {
+    let mut clock = Clock::new();
+    assert_eq!(clock.advance(0.5), 0.5);
+    assert_eq!(clock.set_scale(0.25), 0.25);
+    assert_eq!(clock.advance(0.5), 0.125);
+    assert_eq!(clock.delta(), 0.125);
+    assert_eq!(clock.real_delta(), 0.5);
+    assert_eq!(clock.simulation_seconds(), 0.625);
+    assert_eq!((clock.frames(), clock.ticks()), (2, 2));
+
+    assert_eq!(clock.set_scale(100.0), MAX_TIME_SCALE);
+    assert_eq!(clock.set_scale(0.0), MIN_TIME_SCALE);
+    assert_eq!(clock.set_scale(f32::NAN), 1.0);
+}
+
+#[test]
+fn pausing_stops_the_simulation_but_not_real_time() {
+    let mut clock = Clock::new();
+    clock.advance(1.0);
+    clock.set_paused(true);
+    let before = clock.now();
+    std::thread::sleep(Duration::from_millis(5));
+    assert_eq!(clock.advance(1.0), 0.0);
+    assert_eq!(clock.simulation_seconds(), 1.0);
+    assert_eq!((clock.frames(), clock.ticks()), (2, 1));
+    assert!(clock.since(before) >= Duration::from_millis(5));
+    assert!(clock.real_seconds() > 0.0);
+
+    clock.set_paused(false);
+    assert_eq!(clock.advance(1.0), 1.0);
+    assert_eq!(clock.ticks(), 2);
+}
+
+#[test]
+fn frames_last_as_long_as_they_really_take_up_to_a_limit() {
+    let mut clock = Clock::new();
+    std::thread::sleep(Duration::from_millis(20));
+    clock.set_scale(0.5);
+    let delta = clock.begin_frame();
+    assert!(clock.real_delta() >= 0.02 && clock.real_delta() <= MAX_FRAME_DELTA, "{}", clock.real_delta());
+    assert_eq!(delta, clock.real_delta() * 0.5);
+
+    std::thread::sleep(Duration::from_secs_f32(MAX_FRAME_DELTA * 1.5));
+    clock.begin_frame();
+    assert_eq!(clock.real_delta(), MAX_FRAME_DELTA);
+}
+
+#[test]
+fn shader_time_wraps() {
+    let mut clock = Clock::new();
+    clock.set_scale(MAX_TIME_SCALE);
+    let steps = (SHADER_TIME_PERIOD / MAX_TIME_SCALE as f64) as usize + 1;
+    for _ in 0..steps {
+        clock.advance(1.0);
+    }
+    assert!(clock.simulation_seconds() > SHADER_TIME_PERIOD);
+    assert!((clock.frame_time().simulation as f64) < SHADER_TIME_PERIOD);
+}
+
+#[test]
+fn server_ticks_slow_down_with_the_clock() {
+    let mut ticks = TickClock::default();
+    let normal = ticks.interval();
+    ticks.set_scale(0.5);
+    assert_eq!(ticks.interval(), normal * 2);
+    ticks.set_scale(1.0);
+    assert_eq!(ticks.interval(), normal);
+}
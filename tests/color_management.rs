@@ -0,0 +1,54 @@
+//! sRGB is decoded once on the way in and encoded once on the way out, by the target's
+//! format where it can and by the present pass where the surface is linear.
+
+use wgpu::TextureFormat;
+use game::engine::graphics::color::{choose_surface_format, linear_to_srgb, needs_manual_gamma, render_format, srgb_to_linear};
+use game::engine::graphics::{ColorAudit, ColorRole};
+
+#[test]
+fn srgb_round_trips_every_byte() {
+    for byte in 0..=255u8 {
+        let encoded = byte as f32 / 255.0;
+        let back = (linear_to_srgb(srgb_to_linear(encoded)) * 255.0).round() as u8;
+        assert_eq!(back, byte);
+    }
+    // Half gray in sRGB is about a fifth of the light
+    assert!((srgb_to_linear(0.5) - 0.214).abs() < 0.001);
+    assert!((linear_to_srgb(0.5) - 0.735).abs() < 0.001);
+}
+
+#[test]
+fn srgb_surfaces_are_preferred() {
+    let formats = [TextureFormat::Bgra8Unorm, TextureFormat::Bgra8UnormSrgb];
+    assert_eq!(choose_surface_format(&formats), Some(TextureFormat::Bgra8UnormSrgb));
+    assert_eq!(choose_surface_format(&[TextureFormat::Rgba8Unorm]), Some(TextureFormat::Rgba8Unorm));
+    assert_eq!(choose_surface_format(&[]), None);
+}
+
+#[test]
+fn linear_surfaces_are_drawn_through_an_srgb_target() {
+    assert!(!needs_manual_gamma(TextureFormat::Bgra8UnormSrgb));
+    assert_eq!(render_format(TextureFormat::Bgra8UnormSrgb), TextureFormat::Bgra8UnormSrgb);
+    assert!(needs_manual_gamma(TextureFormat::Bgra8Unorm));
+    assert_eq!(render_format(TextureFormat::Bgra8Unorm), TextureFormat::Bgra8UnormSrgb);
+    assert_eq!(render_format(TextureFormat::Rgba8Unorm), TextureFormat::Rgba8UnormSrgb);
+    // No sRGB variant of its own
+    assert_eq!(render_format(TextureFormat::Rgb10a2Unorm), TextureFormat::Rgba8UnormSrgb);
+    // Floating point surfaces take linear values
+    assert!(!needs_manual_gamma(TextureFormat::Rgba16Float));
+}
+
+#[test]
+fn the_audit_finds_colors_encoded_twice_or_not_at_all() {
+    let textures = [("atlas", TextureFormat::Rgba8UnormSrgb, ColorRole::Color), ("normals", TextureFormat::Rgba8Unorm, ColorRole::Data)];
+    let good = ColorAudit::new(TextureFormat::Bgra8UnormSrgb, TextureFormat::Bgra8UnormSrgb, false, &textures);
+    assert!(good.is_ok(), "{}", good);
+    let presented = ColorAudit::new(TextureFormat::Bgra8Unorm, render_format(TextureFormat::Bgra8Unorm), true, &textures);
+    assert!(presented.is_ok() && presented.manual_gamma, "{}", presented);
+
+    assert!(!ColorAudit::new(TextureFormat::Bgra8Unorm, TextureFormat::Bgra8Unorm, false, &textures).is_ok());
+    assert!(!ColorAudit::new(TextureFormat::Bgra8Unorm, TextureFormat::Bgra8Unorm, true, &textures).is_ok());
+    assert!(!ColorAudit::new(TextureFormat::Bgra8UnormSrgb, TextureFormat::Rgba8UnormSrgb, false, &textures).is_ok());
+    let swapped = [("atlas", TextureFormat::Rgba8Unorm, ColorRole::Color), ("normals", TextureFormat::Rgba8UnormSrgb, ColorRole::Data)];
+    assert_eq!(ColorAudit::new(TextureFormat::Bgra8UnormSrgb, TextureFormat::Bgra8UnormSrgb, false, &swapped).problems.len(), 2);
+}
@@ -0,0 +1,29 @@
+//! Helpers shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use glam::Vec3;
+use game::game::world::chunk::{BlockType, Chunk, CHUNK_SIZE, CHUNK_SIZE_F};
+use game::game::world::chunk_manager::ChunkManager;
+
+/// An empty directory under the system temp dir, unique to `name` and this test run
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("psu-{}-{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    dir
+}
+
+/// Loads the 3x3x3 chunks around the origin, every block `fill`
+pub fn world(fill: BlockType) -> ChunkManager {
+    let mut chunks = ChunkManager::new(1);
+    for x in -1..=1 {
+        for y in -1..=1 {
+            for z in -1..=1 {
+                let mut chunk = Chunk::empty(Vec3::new(x as f32, y as f32, z as f32) * CHUNK_SIZE_F);
+                chunk.blocks = [[[fill; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE];
+                chunks.loaded.insert((x, y, z), chunk);
+            }
+        }
+    }
+    chunks
+}
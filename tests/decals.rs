@@ -0,0 +1,85 @@
+//! Decals are kept per chunk, fade out and expire, and an explosion scorches the faces that
+//! turn toward it.
+
+mod common;
+
+use glam::Vec3;
+use game::game::world::chunk::{BlockType, CHUNK_SIZE};
+use game::game::world::chunk_manager::ChunkManager;
+use game::game::world::decals::{atlas, Decal, DecalKind, Decals, CRACK_STAGES, DECAL_TILE, SCORCH_SECONDS};
+
+/// Face index of the +y face in FACE_AXES
+const TOP: usize = 4;
+
+fn scorch(block: (i32, i32, i32), face: usize) -> Decal {
+    Decal { kind: DecalKind::Scorch, block, face, life: SCORCH_SECONDS, alpha: 1.0 }
+}
+
+/// The 3x3x3 chunks around the origin, empty but for a stone floor at y = -1
+fn floor() -> ChunkManager {
+    let mut chunks = common::world(BlockType::Air);
+    let cs = CHUNK_SIZE as i32;
+    for x in -cs..2 * cs {
+        for z in -cs..2 * cs {
+            chunks.set_block((x, -1, z), BlockType::Stone);
+        }
+    }
+    chunks
+}
+
+#[test]
+fn decals_fade_and_expire() {
+    let mut decals = Decals::new();
+    assert!(decals.add(scorch((0, 0, 0), TOP)));
+    // The same face again replaces the first
+    assert!(decals.add(scorch((0, 0, 0), TOP)));
+    assert!(decals.add(scorch((0, 0, 0), 0)));
+    assert_eq!(decals.len(), 2);
+
+    decals.update(SCORCH_SECONDS / 2.0);
+    assert!(decals.iter().all(|decal| decal.opacity() == 1.0));
+    decals.update(SCORCH_SECONDS / 2.0 - 5.0);
+    assert!(decals.iter().all(|decal| decal.opacity() > 0.0 && decal.opacity() < 1.0));
+    decals.update(5.0);
+    assert!(decals.is_empty());
+}
+
+#[test]
+fn decals_go_with_their_block_or_chunk() {
+    let mut decals = Decals::new();
+    decals.add(scorch((1, 2, 3), TOP));
+    decals.add(scorch((4, 2, 3), TOP));
+    decals.add(scorch((-1, 2, 3), TOP));
+    decals.remove_block((1, 2, 3));
+    assert_eq!(decals.len(), 2);
+    // (-1, 2, 3) is in the chunk west of the others
+    decals.remove_chunk((-1, 0, 0));
+    assert_eq!(decals.len(), 1);
+    assert_eq!(decals.iter().next().unwrap().block, (4, 2, 3));
+}
+
+#[test]
+fn explosions_scorch_the_faces_toward_them() {
+    let chunks = floor();
+    let mut decals = Decals::new();
+    let scorched = decals.scorch(&chunks, Vec3::new(0.0, 1.0, 0.0), 2.0);
+    assert!(scorched > 0);
+    assert_eq!(decals.len(), scorched);
+    assert!(decals.iter().all(|decal| decal.face == TOP && decal.block.1 == -1));
+    // Darker under the blast than at its edge
+    let under = decals.iter().find(|decal| decal.block == (0, -1, 0)).unwrap();
+    assert!(decals.iter().all(|decal| decal.alpha <= under.alpha));
+}
+
+#[test]
+fn broken_blocks_crack_on_every_face() {
+    let mut decals = Decals::new();
+    assert!(decals.vertices(None).is_empty());
+    decals.add(scorch((0, -1, 0), TOP));
+    let vertices = decals.vertices(Some(((5, 5, 5), 0.99)));
+    assert_eq!(vertices.len(), 6 * 7);
+    // The scorch lies on the floor's top face
+    assert!(vertices[..6].iter().all(|vertex| vertex.position[1] == -0.5));
+    let atlas = atlas();
+    assert_eq!((atlas.width(), atlas.height()), (DECAL_TILE * (CRACK_STAGES + 1), DECAL_TILE));
+}
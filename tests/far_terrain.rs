@@ -0,0 +1,54 @@
+//! Far terrain covers the distance around the loaded chunks, coarser farther out, without
+//! generating any chunks.
+
+use glam::Vec3;
+use game::game::world::chunk::{Chunk, DEFAULT_SEED};
+use game::game::world::far_terrain::{build, reach, FarTerrain};
+use game::game::world::{WorldGen, WorldType};
+
+#[test]
+fn heights_follow_the_terrain_and_stay_out_of_the_loaded_area() {
+    let generator = WorldGen::default();
+    let loaded = ((-64, -64), (64, 64));
+    let vertices = build(&generator, Vec3::ZERO, (0, 0), loaded);
+    assert!(!vertices.is_empty());
+    for triangle in vertices.chunks_exact(3) {
+        let middle = triangle.iter().fold(Vec3::ZERO, |sum, v| sum + Vec3::from(v.position)) / 3.0;
+        assert!(middle.x.abs() > 63.0 || middle.z.abs() > 63.0, "triangle inside the loaded area at {:?}", middle);
+        assert!(middle.x.abs() < reach() && middle.z.abs() < reach());
+    }
+    for v in &vertices {
+        let (x, z) = (v.position[0] + 0.5, v.position[2] + 0.5);
+        let height = Chunk::terrain_height(x as i32, z as i32, DEFAULT_SEED);
+        assert_eq!(v.position[1], height as f32 - 0.5);
+    }
+}
+
+#[test]
+fn triangles_come_farthest_first() {
+    let vertices = build(&WorldGen::default(), Vec3::ZERO, (0, 0), ((-64, -64), (64, 64)));
+    let distances: Vec<f32> = vertices.chunks_exact(6).map(|quad| {
+        let middle = quad.iter().fold(Vec3::ZERO, |sum, v| sum + Vec3::from(v.position)) / 6.0;
+        middle.x.abs().max(middle.z.abs())
+    }).collect();
+    // Levels go coarse to fine, and within a level the cells close in on the viewer
+    assert!(distances.first() > distances.last());
+}
+
+#[test]
+fn rebuilds_only_when_something_changed() {
+    let mut far = FarTerrain::default();
+    let generator = WorldGen::default();
+    assert!(far.update(&generator, Vec3::ZERO, 4).is_some());
+    assert!(far.update(&generator, Vec3::new(3.0, 10.0, 2.0), 4).is_none());
+    assert!(far.update(&generator, Vec3::new(40.0, 0.0, 0.0), 4).is_some());
+    assert!(far.update(&WorldGen::new(7, WorldType::Default), Vec3::new(40.0, 0.0, 0.0), 4).is_some());
+    far.set_enabled(false);
+    assert_eq!(far.update(&generator, Vec3::new(40.0, 0.0, 0.0), 4), Some(Vec::new()));
+}
+
+#[test]
+fn the_debug_world_has_no_far_terrain() {
+    let generator = WorldGen::new(0, WorldType::Debug);
+    assert!(build(&generator, Vec3::ZERO, (0, 0), ((-16, -16), (16, 16))).is_empty());
+}
@@ -0,0 +1,52 @@
+//! Entities and particles out of the camera's view are culled before they reach the
+//! overlay, the same way chunks are.
+
+use glam::Vec3;
+use game::engine::graphics::particles::{Particle, ParticleSystem};
+use game::engine::graphics::Overlay;
+use game::engine::math::Aabb;
+use game::game::world::camera::{Camera, Projection};
+
+/// At the origin, looking along +x
+fn camera() -> Camera {
+    Camera { position: Vec3::ZERO, yaw: 0.0, pitch: 0.0, ..Camera::new() }
+}
+
+fn unit_box(center: Vec3) -> Aabb {
+    Aabb::from_center(center, Vec3::splat(0.5))
+}
+
+#[test]
+fn boxes_are_tested_against_every_plane() {
+    let frustum = camera().frustum(1.5);
+    assert!(frustum.intersects(&unit_box(Vec3::new(10.0, 0.0, 0.0))));
+    // Behind, beyond the far plane, and off to the side
+    assert!(!frustum.intersects(&unit_box(Vec3::new(-10.0, 0.0, 0.0))));
+    assert!(!frustum.intersects(&unit_box(Vec3::new(500.0, 0.0, 0.0))));
+    assert!(!frustum.intersects(&unit_box(Vec3::new(10.0, 0.0, 40.0))));
+    // Straddling the edge of the view
+    assert!(frustum.intersects(&Aabb::new(Vec3::new(5.0, -1.0, -1.0), Vec3::new(6.0, 1.0, 40.0))));
+
+    // An orthographic view also sees what is behind its position
+    let ortho = Camera { projection: Projection::Orthographic { height: 32.0 }, ..camera() };
+    assert!(ortho.frustum(1.5).intersects(&unit_box(Vec3::new(-10.0, 0.0, 0.0))));
+}
+
+#[test]
+fn culling_keeps_the_visible_in_order_and_counts_the_rest() {
+    let frustum = camera().frustum(1.0);
+    let centers = [Vec3::new(4.0, 0.0, 0.0), Vec3::new(-4.0, 0.0, 0.0), Vec3::new(8.0, 1.0, 0.0), Vec3::new(0.0, 50.0, 0.0)];
+    let culled = frustum.cull(centers.iter(), |center| unit_box(**center));
+    assert_eq!(culled.visible, vec![&centers[0], &centers[2]]);
+    assert_eq!(culled.hidden, 2);
+}
+
+#[test]
+fn only_particles_in_view_are_drawn() {
+    let mut particles = ParticleSystem::new();
+    for x in [-6.0, -3.0, 3.0, 6.0, 9.0] {
+        particles.spawn(Particle { position: Vec3::new(x, 0.0, 0.0), velocity: Vec3::ZERO, life: 1.0, size: 0.2, color: [1.0; 4] });
+    }
+    let mut overlay = Overlay::new();
+    assert_eq!(particles.draw(&mut overlay, &camera().frustum(1.5)), 3);
+}
@@ -0,0 +1,47 @@
+//! Particles the GPU simulates are handed over as they spawn and fill the pool's slots in
+//! turn, overwriting the oldest.
+
+use glam::Vec3;
+use game::engine::graphics::gpu_particles::{spawn_ranges, GpuParticle};
+use game::engine::graphics::particles::{Particle, ParticleSystem};
+use game::engine::graphics::Overlay;
+use game::game::world::camera::Camera;
+
+#[test]
+fn spawned_particles_wrap_around_the_pool() {
+    assert_eq!(spawn_ranges(0, 3, 8), vec![(0, 0..3)]);
+    assert_eq!(spawn_ranges(6, 2, 8), vec![(6, 0..2)]);
+    assert_eq!(spawn_ranges(6, 5, 8), vec![(6, 0..2), (0, 2..5)]);
+    // More than fit keep only the newest
+    assert_eq!(spawn_ranges(5, 20, 8), vec![(5, 12..15), (0, 15..20)]);
+    assert_eq!(spawn_ranges(0, 0, 8), vec![(0, 0..0)]);
+}
+
+#[test]
+fn offloaded_particles_wait_for_the_gpu() {
+    let mut particles = ParticleSystem::new();
+    particles.set_offloaded(true);
+    particles.burst(Vec3::new(5.0, 0.0, 0.0), 10, 2.0, [1.0; 4]);
+    particles.update(0.1);
+    assert!(particles.is_empty());
+    let camera = Camera { position: Vec3::ZERO, ..Camera::new() };
+    assert_eq!(particles.draw(&mut Overlay::new(), &camera.frustum(1.0)), 0);
+    let spawned = particles.take_spawned();
+    assert_eq!(spawned.len(), 10);
+    assert!(particles.take_spawned().is_empty());
+
+    let gpu = GpuParticle::from(&spawned[0]);
+    assert_eq!(gpu.position, [5.0, 0.0, 0.0]);
+    assert_eq!(std::mem::size_of::<GpuParticle>(), 48);
+}
+
+#[test]
+fn particles_simulate_on_the_cpu_otherwise() {
+    let mut particles = ParticleSystem::new();
+    particles.spawn(Particle { position: Vec3::ZERO, velocity: Vec3::X, life: 1.0, size: 0.1, color: [1.0; 4] });
+    assert!(particles.take_spawned().is_empty());
+    particles.update(0.5);
+    assert_eq!(particles.len(), 1);
+    particles.update(0.6);
+    assert!(particles.is_empty());
+}
@@ -0,0 +1,87 @@
+//! Rumble patterns play out over time, mix by the strongest motor, scale with the intensity
+//! setting and reach the backend only when the motors change.
+
+use std::sync::{Arc, Mutex};
+use game::engine::input::{HapticEvent, Haptics, RumbleBackend, RumblePattern};
+
+const DT: f32 = 0.01;
+
+/// Motor strengths in the order the backend was sent them
+type Sent = Arc<Mutex<Vec<(f32, f32)>>>;
+
+/// Records every change it is sent
+struct Recorder(Sent);
+
+impl RumbleBackend for Recorder {
+    fn is_supported(&self) -> bool {
+        true
+    }
+
+    fn set_motors(&mut self, low: f32, high: f32) {
+        self.0.lock().unwrap().push((low, high));
+    }
+}
+
+fn recorded() -> (Haptics, Sent) {
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    (Haptics::with_backend(Box::new(Recorder(sent.clone()))), sent)
+}
+
+#[test]
+fn patterns_play_their_pulses_in_order() {
+    let pattern = RumblePattern::pulse(1.0, 0.0, 0.1).then(0.0, 0.0, 0.05).then(0.0, 0.5, 0.1);
+    assert_eq!(pattern.duration(), 0.25);
+    assert_eq!(pattern.at(0.05), Some((1.0, 0.0)));
+    assert_eq!(pattern.at(0.12), Some((0.0, 0.0)));
+    assert_eq!(pattern.at(0.2), Some((0.0, 0.5)));
+    assert_eq!(pattern.at(0.25), None);
+}
+
+#[test]
+fn custom_patterns_reach_the_backend_once_per_change() {
+    let (mut haptics, sent) = recorded();
+    assert!(haptics.is_supported());
+    haptics.play_pattern(RumblePattern::pulse(0.8, 0.2, 0.1).then(0.0, 0.6, 0.1));
+    for _ in 0..30 {
+        haptics.update(DT);
+    }
+    assert_eq!(*sent.lock().unwrap(), vec![(0.8, 0.2), (0.0, 0.6), (0.0, 0.0)]);
+}
+
+#[test]
+fn overlapping_patterns_take_the_strongest_motor() {
+    let (mut haptics, _) = recorded();
+    haptics.play_pattern(RumblePattern::pulse(0.3, 0.9, 0.2));
+    haptics.play_pattern(RumblePattern::pulse(0.7, 0.1, 0.2));
+    haptics.update(DT);
+    assert_eq!(haptics.motors(), (0.7, 0.9));
+}
+
+#[test]
+fn intensity_scales_and_off_silences() {
+    let (mut haptics, _) = recorded();
+    haptics.configure("50").unwrap();
+    haptics.play_pattern(RumblePattern::pulse(0.8, 0.4, 0.2));
+    haptics.update(DT);
+    assert_eq!(haptics.motors(), (0.4, 0.2));
+    haptics.configure("off").unwrap();
+    haptics.update(DT);
+    assert_eq!(haptics.motors(), (0.0, 0.0));
+    haptics.play(HapticEvent::BlockBreak);
+    haptics.configure("on").unwrap();
+    haptics.update(DT);
+    // Only what was still playing comes back; nothing was started while off
+    assert_eq!(haptics.motors(), (0.4, 0.2));
+    assert!(haptics.configure("loud").is_err());
+}
+
+#[test]
+fn harder_events_rumble_harder() {
+    let strongest = |event: HapticEvent| {
+        let pattern = event.pattern();
+        pattern.pulses.iter().map(|pulse| pulse.low.max(pulse.high)).fold(0.0, f32::max)
+    };
+    assert!(strongest(HapticEvent::Damage(0.5)) > strongest(HapticEvent::Damage(0.05)));
+    assert!(strongest(HapticEvent::Landing(1.0)) > strongest(HapticEvent::Landing(0.1)));
+    assert!(HapticEvent::BlockBreak.pattern().duration() > 0.0);
+}
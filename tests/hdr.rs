@@ -0,0 +1,78 @@
+//! HDR frames reach HDR displays scaled to the chosen brightness, and every other display
+//! tonemapped.
+
+use wgpu::TextureFormat;
+use game::engine::graphics::hdr::{f16_to_f32, hdr_surface_format, shoulder, PresentParams, DEFAULT_PAPER_WHITE, HDR_FORMAT, MAX_PAPER_WHITE, SCRGB_WHITE_NITS};
+use game::engine::graphics::HdrSettings;
+
+fn args(words: &str) -> Vec<String> {
+    words.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn settings_parse_and_keep_paper_white_below_the_peak() {
+    let mut hdr = HdrSettings::default();
+    assert!(!hdr.enabled);
+    hdr.configure(&args("on")).unwrap();
+    assert!(hdr.enabled && hdr.paper_white == DEFAULT_PAPER_WHITE);
+    hdr.configure(&args("off")).unwrap();
+    assert!(!hdr.enabled);
+
+    // Setting a brightness turns HDR on
+    hdr.configure(&args("paper 300")).unwrap();
+    assert!(hdr.enabled && hdr.paper_white == 300.0);
+    hdr.configure(&args("paper 9000")).unwrap();
+    assert_eq!(hdr.paper_white, MAX_PAPER_WHITE);
+    hdr.configure(&args("peak 400")).unwrap();
+    assert_eq!((hdr.peak, hdr.paper_white), (400.0, 400.0));
+
+    assert!(hdr.configure(&args("paper")).is_err());
+    assert!(hdr.configure(&args("paper bright")).is_err());
+    assert!(hdr.configure(&args("dim")).is_err());
+}
+
+#[test]
+fn the_shoulder_only_bends_highlights() {
+    for c in [0.0, 0.25, 0.5, 0.8] {
+        assert_eq!(shoulder(c), c);
+    }
+    let mut last = shoulder(0.8);
+    for step in 1..100 {
+        let rolled = shoulder(0.8 + step as f32 * 0.5);
+        assert!(rolled > last && rolled < 1.0, "{}", rolled);
+        last = rolled;
+    }
+}
+
+#[test]
+fn frames_are_scaled_tonemapped_or_passed_through_by_surface() {
+    let hdr = HdrSettings { enabled: true, paper_white: 240.0, peak: 800.0 };
+    let output = PresentParams::new(&hdr, HDR_FORMAT, HDR_FORMAT);
+    assert_eq!(output.scale, 240.0 / SCRGB_WHITE_NITS);
+    assert_eq!(output.encode_srgb, 0.0);
+    // White paper shows at paper white, and nothing past the peak
+    assert!((output.apply(1.0) * SCRGB_WHITE_NITS - 240.0).abs() < 0.01);
+    assert!(output.apply(100.0) * SCRGB_WHITE_NITS < 800.0);
+
+    let tonemapped = PresentParams::new(&hdr, HDR_FORMAT, TextureFormat::Bgra8UnormSrgb);
+    assert_eq!((tonemapped.scale, tonemapped.encode_srgb), (1.0, 0.0));
+    assert!(tonemapped.apply(50.0) < 1.0);
+    assert_eq!(PresentParams::new(&hdr, HDR_FORMAT, TextureFormat::Bgra8Unorm).encode_srgb, 1.0);
+
+    // SDR frames are left alone but for encoding
+    let sdr = PresentParams::new(&HdrSettings::default(), TextureFormat::Bgra8UnormSrgb, TextureFormat::Bgra8Unorm);
+    assert_eq!((sdr.tonemap, sdr.encode_srgb), (0.0, 1.0));
+    assert!((sdr.apply(0.5) - 0.735).abs() < 0.001);
+}
+
+#[test]
+fn hdr_surfaces_are_found_and_halves_decode() {
+    assert_eq!(hdr_surface_format(&[TextureFormat::Bgra8UnormSrgb, TextureFormat::Rgba16Float]), Some(HDR_FORMAT));
+    assert_eq!(hdr_surface_format(&[TextureFormat::Bgra8UnormSrgb]), None);
+
+    assert_eq!(f16_to_f32(0x3c00), 1.0);
+    assert_eq!(f16_to_f32(0xc000), -2.0);
+    assert_eq!(f16_to_f32(0x3800), 0.5);
+    assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+    assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+}
@@ -0,0 +1,134 @@
+//! The music manager shuffles its playlist with silence between tracks, fades between them,
+//! ducks under menus and switches to the tension track while hostile mobs are near.
+
+use game::engine::audio::music::TENSION_LINGER;
+use game::engine::audio::{MusicConfig, MusicManager, Track};
+
+const DT: f32 = 0.1;
+
+fn config() -> MusicConfig {
+    MusicConfig::parse("
+        track a 20
+        track b 20
+        track c 20
+        tension fight 15
+        gap 5 10
+        fade 2
+        duck 0.25
+    ").unwrap()
+}
+
+fn playing(music: &MusicManager) -> Vec<(String, f32)> {
+    music.playing().map(|(name, gain)| (name.to_string(), gain)).collect()
+}
+
+#[test]
+fn built_in_settings_parse() {
+    let config = MusicConfig::parse(include_str!("../assets/music.txt")).unwrap();
+    assert!(!config.tracks.is_empty());
+    assert!(config.tension.is_some());
+}
+
+#[test]
+fn bad_settings_name_their_line() {
+    assert_eq!(MusicConfig::parse("fade 2\ngap 10 5").unwrap_err(), "line 2: the longest gap is shorter than the shortest");
+    assert!(MusicConfig::parse("track a").unwrap_err().starts_with("line 1"));
+    assert!(MusicConfig::parse("duck 2").is_err());
+    assert_eq!(MusicConfig::parse("track a 20").unwrap().tracks, vec![Track { name: "a".into(), length: 20.0 }]);
+}
+
+#[test]
+fn playlist_is_shuffled_with_gaps_between_tracks() {
+    let mut music = MusicManager::new(config(), 7);
+    let mut started = Vec::new();
+    // Seconds nothing has played; the gaps are longer than the fades, so every track
+    // starts out of silence
+    let mut silence = 0.0;
+    let mut silences = Vec::new();
+    for _ in 0..(600.0 / DT) as usize {
+        music.update(DT);
+        let now = playing(&music);
+        if now.is_empty() {
+            silence += DT;
+        } else if silence > 0.0 {
+            started.push(now[0].0.clone());
+            silences.push(silence);
+            silence = 0.0;
+        }
+    }
+    assert!(started.len() >= 9, "only {:?} started", started);
+    // Every track plays once before any plays again, and never twice in a row
+    for round in started.chunks(3).filter(|round| round.len() == 3) {
+        let mut names = round.to_vec();
+        names.sort();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+    assert!(started.windows(2).all(|pair| pair[0] != pair[1]));
+    // The gap starts as a track begins fading out, so the silence is the gap less the fade
+    for silence in &silences[1..] {
+        assert!((3.0 - DT..=8.0 + DT).contains(silence), "{} seconds of silence", silence);
+    }
+}
+
+#[test]
+fn tracks_fade_in_and_out() {
+    let mut music = MusicManager::new(config(), 1);
+    while playing(&music).is_empty() {
+        music.update(DT);
+    }
+    let gains: Vec<f32> = (0..25).map(|_| {
+        music.update(DT);
+        playing(&music)[0].1
+    }).collect();
+    assert!(gains.windows(2).all(|pair| pair[1] >= pair[0]));
+    assert!(gains[0] < 0.2 && *gains.last().unwrap() == 1.0);
+}
+
+#[test]
+fn menus_duck_the_music() {
+    let mut music = MusicManager::new(config(), 1);
+    for _ in 0..100 {
+        music.update(DT);
+    }
+    assert_eq!(playing(&music)[0].1, 1.0);
+    music.set_ducked(true);
+    for _ in 0..10 {
+        music.update(DT);
+    }
+    assert_eq!(playing(&music)[0].1, 0.25);
+    music.set_ducked(false);
+    for _ in 0..10 {
+        music.update(DT);
+    }
+    assert_eq!(playing(&music)[0].1, 1.0);
+}
+
+#[test]
+fn hostiles_bring_on_the_tension_track_until_they_leave() {
+    let mut music = MusicManager::new(config(), 3);
+    for _ in 0..100 {
+        music.update(DT);
+    }
+    let calm = playing(&music)[0].0.clone();
+    music.set_hostiles_near(true);
+    music.update(DT);
+    assert!(music.is_tense());
+    for _ in 0..30 {
+        music.update(DT);
+    }
+    // The calm track faded out as the tension track faded in
+    assert_eq!(playing(&music), vec![("fight".to_string(), 1.0)]);
+    assert!(!playing(&music).iter().any(|(name, _)| *name == calm));
+
+    // It loops past its length while they stay
+    for _ in 0..(30.0 / DT) as usize {
+        music.set_hostiles_near(true);
+        music.update(DT);
+    }
+    assert!(music.is_tense());
+    for _ in 0..((TENSION_LINGER + 3.0) / DT) as usize {
+        music.update(DT);
+    }
+    assert!(!music.is_tense());
+    assert!(playing(&music).is_empty());
+}
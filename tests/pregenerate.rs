@@ -0,0 +1,87 @@
+//! Pregenerated chunks are saved whole, read back exactly as the generator made them, and
+//! loaded from the save instead of generated.
+
+mod common;
+
+use std::time::{Duration, Instant};
+use glam::Vec3;
+use game::game::save::{SavedChunks, WorldSave};
+use game::game::server::pregenerate::{area_chunks, pregenerate, PREGENERATE_LAYERS};
+use game::game::world::chunk::{BlockType, Chunk, CHUNK_SIZE, CHUNK_SIZE_F, DEFAULT_SEED};
+use game::game::world::chunk_manager::ChunkManager;
+use game::game::world::{WorldGen, WorldType};
+
+#[test]
+fn area_is_a_disc_nearest_first() {
+    let chunks = area_chunks((3, -2), 2);
+    // 13 columns lie within a radius of 2
+    assert_eq!(chunks.len(), 13 * PREGENERATE_LAYERS as usize);
+    assert_eq!((chunks[0].0, chunks[0].2), (3, -2));
+    let distance = |k: &(i32, i32, i32)| (k.0 - 3).pow(2) + (k.2 + 2).pow(2);
+    assert!(chunks.windows(2).all(|pair| distance(&pair[0]) <= distance(&pair[1])));
+    assert!(!chunks.contains(&(5, 0, 0)));
+}
+
+#[test]
+fn pregenerated_chunks_load_back_as_generated() {
+    let dir = common::scratch_dir("pregenerate-load");
+    let generator = WorldGen::new(DEFAULT_SEED, WorldType::Default);
+    let chunks = area_chunks((0, 0), 1);
+    let mut updates = 0;
+    let report = {
+        let mut save = WorldSave::open(&dir);
+        pregenerate(&mut save, &generator, &chunks, 3, |progress| {
+            updates += 1;
+            assert_eq!(progress.total, chunks.len());
+            assert_eq!(progress.done, updates);
+        }).unwrap()
+    };
+    assert_eq!(report.generated, chunks.len());
+    assert_eq!(updates, chunks.len());
+
+    let mut save = WorldSave::open(&dir);
+    let mut saved = 0;
+    for &key in &chunks {
+        let generated = generator.generate(Vec3::new(key.0 as f32, key.1 as f32, key.2 as f32) * CHUNK_SIZE_F);
+        match save.chunk_blocks(key) {
+            Some(blocks) => {
+                saved += 1;
+                assert!(*blocks == generated.blocks, "chunk {:?} changed on the way through the save", key);
+            }
+            None => assert!(generated.is_all_air(), "chunk {:?} was not saved", key),
+        }
+    }
+    assert_eq!(saved, report.saved);
+    // The default terrain only fills the lowest layer
+    assert_eq!(report.saved, chunks.len() / PREGENERATE_LAYERS as usize);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn saved_chunks_load_instead_of_generating() {
+    let dir = common::scratch_dir("pregenerate-stream");
+    let stone = Box::new([[[BlockType::Stone; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]);
+    {
+        let mut save = WorldSave::open(&dir);
+        save.store_chunk_blocks((0, 0, 0), &stone);
+        // A region of its own, so both the cached and the uncached path are taken
+        save.store_chunk_blocks((-9, 0, 0), &stone);
+        save.flush().unwrap();
+    }
+    let mut chunks = ChunkManager::new(2);
+    chunks.set_saved_chunks(SavedChunks::open(&dir));
+    for (key, center) in [((0, 0, 0), Vec3::new(8.0, 8.0, 8.0)), ((-9, 0, 0), Vec3::new(-9.0 * 16.0 + 8.0, 8.0, 8.0))] {
+        chunks.update_chunks(center);
+        let start = Instant::now();
+        while !chunks.pending.is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(30), "chunks never loaded");
+            chunks.poll_generated();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let chunk: &Chunk = &chunks.loaded[&key];
+        assert!(chunk.blocks == *stone, "chunk {:?} was generated rather than loaded", key);
+    }
+    assert_eq!(chunks.chunks_from_disk, 2);
+    assert!(chunks.chunks_generated > 0);
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,80 @@
+//! A quicksave brings back the edited blocks, the time and the players online as they were,
+//! undoing edits made since down to the generated terrain.
+
+mod common;
+
+use std::time::{Duration, Instant};
+use glam::Vec3;
+use game::engine::codec::{ByteReader, ByteWriter};
+use game::game::command::{CommandSender, PermissionLevel};
+use game::game::net::protocol::{ClientMessage, ServerMessage};
+use game::game::save::{PlayerData, WorldSave};
+use game::game::server::quicksave::{QuickPlayer, QuickSave};
+use game::game::server::Server;
+use game::game::world::chunk::{BlockType, DEFAULT_SEED};
+use game::game::world::{WorldGen, WorldType};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Anything but `block`
+fn other(block: Option<BlockType>) -> BlockType {
+    if block == Some(BlockType::Stone) { BlockType::Dirt } else { BlockType::Stone }
+}
+
+#[test]
+fn quicksaves_round_trip() {
+    let save = QuickSave {
+        ticks: 1234,
+        raining: true,
+        players: vec![QuickPlayer { name: "dev".into(), data: PlayerData::new(Vec3::new(1.0, 2.0, 3.0)), health: 7.5 }],
+        chunks: vec![((0, -1, 2), vec![((1, 2, 3), BlockType::Stone)])],
+    };
+    let mut w = ByteWriter::new();
+    save.encode(&mut w);
+    let bytes = w.into_inner();
+    assert_eq!(QuickSave::decode(&mut ByteReader::new(&bytes)).unwrap(), save);
+    assert!(QuickSave::decode(&mut ByteReader::new(b"nope")).is_err());
+}
+
+#[test]
+fn quickload_puts_the_world_back() {
+    let dir = common::scratch_dir("quicksave-load");
+    let mut server = Server::create(WorldSave::open(&dir), WorldGen::new(DEFAULT_SEED, WorldType::Default));
+    let client = server.connect("dev", PermissionLevel::Player).unwrap();
+    let (kept, undone) = ((8, 4, 8), (9, 4, 8));
+    let start = Instant::now();
+    while server.chunks.get_block(kept.0, kept.1, kept.2).is_none() {
+        assert!(start.elapsed() < TIMEOUT, "the spawn chunk never loaded");
+        server.tick();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    let generated = server.chunks.get_block(undone.0, undone.1, undone.2);
+    let edited = other(server.chunks.get_block(kept.0, kept.1, kept.2));
+    server.set_block(kept, edited);
+    let position = server.session(client).unwrap().position;
+    let ticks = server.time.ticks;
+    let console = CommandSender::console();
+    server.execute(&console, "/quicksave 2").unwrap();
+
+    server.set_block(kept, other(Some(edited)));
+    server.set_block(undone, other(generated));
+    server.handle_message(client, ClientMessage::Move { position: position + Vec3::new(0.2, 0.0, 0.0), yaw: 1.0, pitch: 0.0 });
+    for _ in 0..20 {
+        server.tick();
+    }
+    assert_ne!(server.time.ticks, ticks);
+    server.drain_outbox();
+
+    server.execute(&console, "/quickload 2").unwrap();
+    assert_eq!(server.chunks.get_block(kept.0, kept.1, kept.2), Some(edited));
+    assert_eq!(server.chunks.get_block(undone.0, undone.1, undone.2), generated);
+    assert_eq!(server.time.ticks, ticks);
+    assert_eq!(server.session(client).unwrap().position, position);
+    let outbox = server.drain_outbox();
+    assert!(outbox.iter().any(|(to, message)| *to == client && *message == ServerMessage::CorrectPosition { position }));
+    assert!(outbox.iter().any(|(_, message)| *message == ServerMessage::BlockUpdate { block: undone, block_type: generated.unwrap() }));
+
+    assert!(server.execute(&console, "/quickload 3").is_err());
+    assert!(server.execute(&console, "/quicksave 10").is_err());
+    std::fs::remove_dir_all(&dir).ok();
+}
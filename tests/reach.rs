@@ -0,0 +1,78 @@
+//! The server casts attack and place rays from where the player is, not from wherever a
+//! client says they start.
+
+mod common;
+
+use std::time::{Duration, Instant};
+use glam::Vec3;
+use game::game::command::PermissionLevel;
+use game::game::net::protocol::ClientMessage;
+use game::game::save::WorldSave;
+use game::game::server::Server;
+use game::game::world::chunk::{BlockType, DEFAULT_SEED};
+use game::game::world::chunk_manager::ChunkManager;
+use game::game::world::{WorldGen, WorldType};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Ticks `server` until every one of `blocks` is loaded
+fn load(server: &mut Server, blocks: impl Iterator<Item = (i32, i32, i32)>) {
+    let blocks: Vec<_> = blocks.collect();
+    let start = Instant::now();
+    while blocks.iter().any(|&(x, y, z)| server.chunks.get_block(x, y, z).is_none()) {
+        assert!(start.elapsed() < TIMEOUT, "the blocks near the player never loaded");
+        server.tick();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn places_from_far_away_origins_are_ignored() {
+    let dir = common::scratch_dir("reach");
+    let mut server = Server::create(WorldSave::open(&dir), WorldGen::new(DEFAULT_SEED, WorldType::Default));
+    let client = server.connect("player", PermissionLevel::Player).unwrap();
+    let eye = server.session(client).unwrap().position;
+    let (x, y, z) = ChunkManager::block_coords(eye);
+    load(&mut server, (x..=x + 9).flat_map(|x| (y - 1..=y + 1).map(move |y| (x, y, z))));
+    // A wall in reach and another past it, with air between
+    for y in y - 1..=y + 1 {
+        for x in x + 1..=x + 8 {
+            server.set_block((x, y, z), BlockType::Air);
+        }
+        server.set_block((x + 3, y, z), BlockType::Stone);
+        server.set_block((x + 9, y, z), BlockType::Stone);
+    }
+    let placed = |server: &Server, x: i32| (y - 1..=y + 1).any(|y| server.chunks.get_block(x, y, z) == Some(BlockType::Stone));
+
+    // Starting the ray past the near wall would reach the far one
+    let origin = eye + Vec3::new(5.2, 0.0, 0.0);
+    server.handle_message(client, ClientMessage::Place { origin, dir: Vec3::X, block_type: BlockType::Stone });
+    assert!(!placed(&server, x + 8));
+
+    server.handle_message(client, ClientMessage::Place { origin: eye, dir: Vec3::X, block_type: BlockType::Stone });
+    assert!(placed(&server, x + 2));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn actions_cast_from_nan_are_ignored() {
+    let dir = common::scratch_dir("reach-nan");
+    let mut server = Server::create(WorldSave::open(&dir), WorldGen::new(DEFAULT_SEED, WorldType::Default));
+    let client = server.connect("player", PermissionLevel::Player).unwrap();
+    let eye = server.session(client).unwrap().position;
+    // A NaN ray would start in the block at the world origin. Below it is the void.
+    let neighbours = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, 0, 1), (0, 0, -1)];
+    load(&mut server, neighbours.into_iter().chain([(0, 0, 0)]));
+    server.set_block((0, 0, 0), BlockType::Stone);
+    for block in neighbours {
+        server.set_block(block, BlockType::Air);
+    }
+
+    let nan = Vec3::splat(f32::NAN);
+    server.handle_message(client, ClientMessage::Place { origin: nan, dir: Vec3::X, block_type: BlockType::Stone });
+    server.handle_message(client, ClientMessage::Place { origin: eye, dir: nan, block_type: BlockType::Stone });
+    assert!(neighbours.iter().all(|&(x, y, z)| server.chunks.get_block(x, y, z) == Some(BlockType::Air)));
+    server.handle_message(client, ClientMessage::Attack { origin: nan, dir: Vec3::X });
+    assert_eq!(server.chunks.get_block(0, 0, 0), Some(BlockType::Stone));
+    std::fs::remove_dir_all(&dir).ok();
+}
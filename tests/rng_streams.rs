@@ -0,0 +1,31 @@
+//! Named random streams depend only on the seed and their own draws.
+
+use game::engine::math::RngStreams;
+use game::game::server::random;
+
+fn draws(streams: &mut RngStreams, name: &str) -> Vec<u64> {
+    (0..8).map(|_| streams.stream(name).next_u64()).collect()
+}
+
+#[test]
+fn the_same_seed_gives_the_same_streams() {
+    let mut a = RngStreams::new(42);
+    let mut b = RngStreams::new(42);
+    assert_eq!(draws(&mut a, random::TICKS), draws(&mut b, random::TICKS));
+    assert_ne!(draws(&mut a, random::TICKS), draws(&mut RngStreams::new(43), random::TICKS));
+}
+
+#[test]
+fn streams_do_not_disturb_each_other() {
+    let mut quiet = RngStreams::new(7);
+    let mut busy = RngStreams::new(7);
+    for _ in 0..100 {
+        busy.stream(random::LOOT).next_u64();
+    }
+    assert_eq!(draws(&mut quiet, random::WEATHER), draws(&mut busy, random::WEATHER));
+    // Streams differ from each other, and start as `derive` says
+    assert_ne!(draws(&mut RngStreams::new(7), random::AI), draws(&mut RngStreams::new(7), random::WORLDGEN));
+    let mut derived = RngStreams::derive(7, random::AI);
+    let first: Vec<u64> = (0..8).map(|_| derived.next_u64()).collect();
+    assert_eq!(first, draws(&mut RngStreams::new(7), random::AI));
+}
@@ -0,0 +1,60 @@
+//! Meshing lights face corners from the cells around them.
+
+use glam::Vec3;
+use game::game::world::chunk::{BlockType, Chunk, CHUNK_SIZE_F};
+use game::game::world::chunk_manager::ChunkManager;
+
+/// A stone floor at y = 0 with a wall block on it at (8, 1, 8)
+fn floor_with_wall() -> Chunk {
+    let mut chunk = Chunk::empty(Vec3::ZERO);
+    for column in chunk.blocks.iter_mut() {
+        column[0] = [BlockType::Stone; 16];
+    }
+    chunk.blocks[8][1][8] = BlockType::Stone;
+    chunk
+}
+
+/// Corner brightnesses of the top face of the block at `at`
+fn top_corners(chunk: &Chunk, at: (i32, i32, i32)) -> [u8; 4] {
+    let face = chunk.block_face_instances.iter()
+        .find(|f| f.face == 4 && f.position == [at.0, at.1, at.2])
+        .expect("top face");
+    face.light.to_le_bytes()
+}
+
+#[test]
+fn open_ground_is_evenly_lit_and_corners_by_walls_are_darker() {
+    let chunks = ChunkManager::new(0);
+    let mut chunk = floor_with_wall();
+    chunk.generate_mesh(&chunks);
+    assert_eq!(top_corners(&chunk, (2, 0, 2)), [255; 4]);
+    // West of the wall: the top face's +x corners touch it, quad +y runs toward -z
+    let beside = top_corners(&chunk, (7, 0, 8));
+    assert!(beside[1] < beside[0] && beside[3] < beside[2], "{:?}", beside);
+    // Diagonal to the wall only one corner is shaded
+    let diagonal = top_corners(&chunk, (7, 0, 9));
+    assert_eq!(diagonal.iter().filter(|&&b| b < 255).count(), 1, "{:?}", diagonal);
+}
+
+#[test]
+fn roofed_ground_is_darker_than_open_ground() {
+    let mut chunks = ChunkManager::new(0);
+    let mut above = Chunk::empty(Vec3::new(0.0, CHUNK_SIZE_F, 0.0));
+    for column in above.blocks.iter_mut() {
+        column[2] = [BlockType::Stone; 16];
+    }
+    chunks.loaded.insert((0, 1, 0), above);
+    let mut chunk = floor_with_wall();
+    chunk.generate_mesh(&chunks);
+    // Light still spreads in from the unloaded chunks around, which count as open
+    assert!(top_corners(&chunk, (5, 0, 5)).iter().all(|&b| b < 128));
+}
+
+#[test]
+fn changes_under_a_chunk_remesh_the_chunk_below() {
+    let mut chunks = ChunkManager::new(0);
+    chunks.loaded.insert((0, 0, 0), Chunk::empty(Vec3::ZERO));
+    chunks.loaded.insert((0, 1, 0), Chunk::empty(Vec3::new(0.0, CHUNK_SIZE_F, 0.0)));
+    chunks.set_block((5, 20, 5), BlockType::Stone);
+    assert_eq!(chunks.dirty_len(), 2);
+}
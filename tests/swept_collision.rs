@@ -0,0 +1,150 @@
+//! Fast falls and long time steps against 1-block-thick floors and walls: bodies and
+//! entities must stop on the near side, however far they would move in one step. Also
+//! covers stepping up onto ledges while walking.
+
+mod common;
+
+use glam::Vec3;
+use game::game::entity::{EntityKind, EntityManager};
+use game::game::player::physics::{self, MoveInput, PlayerBody, DEFAULT_MAX_SUBSTEP, EYE_HEIGHT, MAX_SUBSTEPS};
+use game::game::world::chunk::{BlockType, CHUNK_SIZE};
+use game::game::world::chunk_manager::ChunkManager;
+
+/// Top face of the floor, which is the single layer of blocks at y = 0
+const FLOOR_TOP: f32 = 0.5;
+
+/// Air in the 3x3x3 chunks around the origin, with a stone floor one block thick at
+/// y = 0, and a stone wall one block thick and three tall at x = `wall` if given
+fn world(wall: Option<i32>) -> ChunkManager {
+    let mut chunks = common::world(BlockType::Air);
+    let cs = CHUNK_SIZE as i32;
+    for x in -cs..cs {
+        for z in -cs..cs {
+            chunks.set_block((x, 0, z), BlockType::Stone);
+            if let Some(wall) = wall {
+                for y in 1..4 {
+                    chunks.set_block((wall, y, z), BlockType::Stone);
+                }
+            }
+        }
+    }
+    chunks
+}
+
+fn feet(eye: Vec3) -> f32 {
+    eye.y - EYE_HEIGHT
+}
+
+#[test]
+fn substeps_cover_the_motion_and_are_capped() {
+    assert_eq!(physics::substeps(Vec3::ZERO, DEFAULT_MAX_SUBSTEP), 1);
+    assert_eq!(physics::substeps(Vec3::new(0.0, -10.0, 0.0), 0.25), 40);
+    assert_eq!(physics::substeps(Vec3::new(0.3, 0.0, -0.1), 0.25), 2);
+    assert_eq!(physics::substeps(Vec3::new(0.0, -1000.0, 0.0), 0.25), MAX_SUBSTEPS);
+    // Substepping can be turned off
+    assert_eq!(physics::substeps(Vec3::new(0.0, -10.0, 0.0), f32::INFINITY), 1);
+}
+
+#[test]
+fn long_falls_land_on_a_thin_floor() {
+    let chunks = world(None);
+    for max_substep in [DEFAULT_MAX_SUBSTEP, 0.05, 1.0, f32::INFINITY] {
+        for delta_time in [0.016, 0.1, 0.5, 2.0] {
+            let mut body = PlayerBody::new();
+            body.velocity.y = -physics::MAX_FALL_SPEED;
+            let mut eye = Vec3::new(0.3, 6.0 + EYE_HEIGHT, -0.7);
+            for _ in 0..200 {
+                body.step(&mut eye, MoveInput::default(), &chunks, delta_time, max_substep);
+                assert!(feet(eye) >= FLOOR_TOP - 1e-3, "fell through with dt {} substep {}: feet at {}", delta_time, max_substep, feet(eye));
+            }
+            assert!(body.on_ground, "dt {} substep {}: not on the ground", delta_time, max_substep);
+            assert!((feet(eye) - FLOOR_TOP).abs() < 0.01, "dt {} substep {}: feet at {}", delta_time, max_substep, feet(eye));
+        }
+    }
+}
+
+#[test]
+fn walking_into_a_thin_wall_stops_in_front_of_it() {
+    let chunks = world(Some(3));
+    let input = MoveInput { wish: Vec3::X, forward: true, ..MoveInput::default() };
+    for max_substep in [DEFAULT_MAX_SUBSTEP, f32::INFINITY] {
+        for delta_time in [0.016, 0.5, 3.0] {
+            let mut body = PlayerBody::new();
+            let mut eye = Vec3::new(0.0, FLOOR_TOP + EYE_HEIGHT + 0.01, 0.0);
+            for _ in 0..50 {
+                body.step(&mut eye, input, &chunks, delta_time, max_substep);
+            }
+            let front = eye.x + physics::PLAYER_HALF_WIDTH;
+            assert!(front <= 2.5, "dt {} substep {}: walked into the wall, front at {}", delta_time, max_substep, front);
+            assert!(front > 2.49, "dt {} substep {}: stopped short at {}", delta_time, max_substep, front);
+        }
+    }
+}
+
+#[test]
+fn fast_falling_entities_land_on_a_thin_floor() {
+    let chunks = world(None);
+    for speed in [5.0, 20.0, 60.0, 200.0] {
+        let mut entities = EntityManager::new();
+        let id = entities.spawn(EntityKind::ItemDrop, Vec3::new(0.0, 20.0, 0.0));
+        entities.get_mut(id).unwrap().velocity = Vec3::new(0.0, -speed, 0.0);
+        // One 20 Hz server tick at 200 blocks per second covers ten blocks
+        for _ in 0..40 {
+            entities.update(0.05, &chunks);
+        }
+        let entity = entities.get(id).unwrap();
+        let bottom = entity.position.y - entity.kind.half_extents().y;
+        assert!((bottom - FLOOR_TOP).abs() < 1e-3, "speed {}: came to rest at {}", speed, bottom);
+        assert!(entity.on_ground, "speed {}: not on the ground", speed);
+    }
+}
+
+/// Walks a body along +x from the origin for a second and returns where its eye ended up
+fn walk(chunks: &ChunkManager) -> Vec3 {
+    let input = MoveInput { wish: Vec3::X, forward: true, ..MoveInput::default() };
+    let mut body = PlayerBody::new();
+    let mut eye = Vec3::new(0.0, FLOOR_TOP + EYE_HEIGHT + 0.01, 0.0);
+    for _ in 0..60 {
+        body.step(&mut eye, input, chunks, 1.0 / 60.0, DEFAULT_MAX_SUBSTEP);
+    }
+    eye
+}
+
+#[test]
+fn walking_steps_up_onto_a_one_block_ledge() {
+    let mut chunks = world(None);
+    for z in -2..=2 {
+        for x in 2..10 {
+            chunks.set_block((x, 1, z), BlockType::Stone);
+        }
+    }
+    let eye = walk(&chunks);
+    assert!(eye.x > 2.5, "stopped at the ledge at x {}", eye.x);
+    assert!((feet(eye) - (FLOOR_TOP + 1.0)).abs() < 0.01, "feet at {} instead of on the ledge", feet(eye));
+}
+
+#[test]
+fn walking_does_not_step_up_two_blocks() {
+    let mut chunks = world(None);
+    for z in -2..=2 {
+        chunks.set_block((2, 1, z), BlockType::Stone);
+        chunks.set_block((2, 2, z), BlockType::Stone);
+    }
+    let eye = walk(&chunks);
+    assert!(eye.x + physics::PLAYER_HALF_WIDTH <= 1.5, "walked into the wall at x {}", eye.x);
+    assert!((feet(eye) - FLOOR_TOP).abs() < 0.01, "feet at {}", feet(eye));
+}
+
+#[test]
+fn walking_does_not_step_up_without_headroom() {
+    let mut chunks = world(None);
+    for z in -2..=2 {
+        chunks.set_block((2, 1, z), BlockType::Stone);
+        // Ceiling over the ledge leaves only one block of room above it
+        for x in 1..4 {
+            chunks.set_block((x, 3, z), BlockType::Stone);
+        }
+    }
+    let eye = walk(&chunks);
+    assert!(eye.x + physics::PLAYER_HALF_WIDTH <= 1.5, "squeezed onto the ledge at x {}", eye.x);
+}
@@ -0,0 +1,72 @@
+//! TAA nudges each frame by a different fraction of a pixel and finds where each pixel was
+//! last frame from the camera's motion.
+
+use glam::{Vec2, Vec3, Vec3Swizzles};
+use game::engine::graphics::taa::{halton, jitter, jitter_matrix, reproject_uv, reprojection, AntiAliasing, JITTER_PHASES};
+use game::game::world::camera::Camera;
+
+fn camera(position: Vec3) -> Camera {
+    Camera { position, yaw: 0.0, pitch: 0.0, ..Camera::new() }
+}
+
+#[test]
+fn the_jitter_covers_the_pixel_and_repeats() {
+    assert_eq!(halton(1, 2), 0.5);
+    assert_eq!(halton(3, 2), 0.75);
+    assert!((halton(2, 3) - 2.0 / 3.0).abs() < 1e-6);
+
+    let phases: Vec<Vec2> = (0..JITTER_PHASES).map(jitter).collect();
+    for (i, offset) in phases.iter().enumerate() {
+        assert!(offset.abs().max_element() <= 0.5, "{}", offset);
+        // No phase repeats within the pattern
+        assert!(phases[..i].iter().all(|earlier| earlier != offset));
+    }
+    let mean = phases.iter().sum::<Vec2>() / JITTER_PHASES as f32;
+    assert!(mean.abs().max_element() < 0.1, "{}", mean);
+    assert_eq!(jitter(JITTER_PHASES), jitter(0));
+}
+
+#[test]
+fn jittering_moves_the_picture_by_whole_pixels_at_any_depth() {
+    let size = (800, 600);
+    let view_proj = camera(Vec3::ZERO).view_proj_mat(800.0 / 600.0);
+    let nudged = jitter_matrix(Vec2::new(0.5, -0.25), size) * view_proj;
+    for point in [Vec3::new(1.0, 0.5, 4.0), Vec3::new(-3.0, 2.0, 60.0)] {
+        let before = view_proj.project_point3(point).xy();
+        let after = nudged.project_point3(point).xy();
+        let pixels = (after - before) * Vec2::new(size.0 as f32, size.1 as f32) / 2.0;
+        assert!((pixels - Vec2::new(0.5, -0.25)).length() < 1e-3, "{}", pixels);
+    }
+}
+
+#[test]
+fn pixels_reproject_with_the_camera() {
+    let aspect = 1.5;
+    let still = camera(Vec3::new(100.5, 70.2, -40.7));
+    let view_proj = still.relative_view_proj_mat(aspect);
+    let origin = still.render_origin();
+    let unmoved = reprojection(view_proj, origin, view_proj, origin);
+    let uv = reproject_uv(unmoved, Vec2::new(0.3, 0.6), 0.9);
+    assert!((uv - Vec2::new(0.3, 0.6)).length() < 1e-4, "{}", uv);
+
+    // Stepping into the next block, a point ahead and to the side was nearer the middle of
+    // the screen last frame
+    let moved = camera(still.position + Vec3::new(0.0, 0.0, -0.6));
+    assert_ne!(moved.render_origin(), origin);
+    let point = moved.position + Vec3::new(10.0, 0.0, 3.0);
+    let current = moved.relative_view_proj_mat(aspect);
+    let ndc = current.project_point3(point - moved.render_origin().as_vec3());
+    let uv = Vec2::new(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+    let previous_uv = reproject_uv(reprojection(view_proj, origin, current, moved.render_origin()), uv, ndc.z);
+    let expected = view_proj.project_point3(point - origin.as_vec3());
+    assert!((previous_uv - Vec2::new(expected.x * 0.5 + 0.5, 0.5 - expected.y * 0.5)).length() < 1e-3);
+    assert!((previous_uv.x - 0.5).abs() < (uv.x - 0.5).abs());
+}
+
+#[test]
+fn modes_parse_by_name() {
+    for mode in [AntiAliasing::Off, AntiAliasing::Taa] {
+        assert_eq!(AntiAliasing::from_name(mode.name()), Some(mode));
+    }
+    assert_eq!(AntiAliasing::from_name("msaa"), None);
+}
@@ -0,0 +1,94 @@
+//! Text composed with an input method shows in the sign editor and the chat box while it
+//! is being built and is typed once committed.
+
+use game::engine::input::TextComposition;
+use game::game::state::chat_box::MAX_CHAT_CHARS;
+use game::game::state::{ChatBox, SignEditor};
+use game::game::world::sign;
+use winit::event::Ime;
+
+fn allowed() -> TextComposition {
+    let mut composition = TextComposition::new();
+    assert!(composition.set_allowed(true));
+    assert!(!composition.set_allowed(true));
+    composition.handle(&Ime::Enabled);
+    composition
+}
+
+#[test]
+fn preedit_is_kept_until_committed() {
+    let mut composition = allowed();
+    assert_eq!(composition.handle(&Ime::Preedit("にほ".into(), Some((6, 6)))), None);
+    assert!(composition.is_composing());
+    assert_eq!(composition.preedit(), "にほ");
+    // The byte offset is turned into characters
+    assert_eq!(composition.preedit_cursor(), Some(2));
+    assert_eq!(composition.handle(&Ime::Commit("日本".into())), Some("日本".to_string()));
+    assert!(!composition.is_composing());
+    assert_eq!(composition.preedit_cursor(), None);
+}
+
+#[test]
+fn nothing_is_composed_without_a_text_field() {
+    let mut composition = TextComposition::new();
+    composition.handle(&Ime::Preedit("ha".into(), None));
+    assert!(!composition.is_composing());
+    assert_eq!(composition.handle(&Ime::Commit("は".into())), None);
+
+    let mut composition = allowed();
+    composition.handle(&Ime::Preedit("ha".into(), None));
+    // Closing the field drops what was being composed
+    assert!(composition.set_allowed(false));
+    assert!(!composition.is_composing());
+    let mut composition = allowed();
+    composition.handle(&Ime::Preedit("ha".into(), None));
+    composition.handle(&Ime::Disabled);
+    assert_eq!(composition.preedit(), "");
+}
+
+#[test]
+fn committed_text_goes_on_the_sign() {
+    let mut composition = allowed();
+    let mut editor = SignEditor::open((0, 0, 0), "");
+    editor.type_text("Hi ");
+    composition.handle(&Ime::Preedit("ni".into(), Some((2, 2))));
+    let before = editor.caret_area((800.0, 600.0), 2.0, &TextComposition::new());
+    let composing = editor.caret_area((800.0, 600.0), 2.0, &composition);
+    // The caret sits after the composed text, two characters on
+    assert_eq!(composing.0 - before.0, 2.0 * before.2);
+    assert_eq!(editor.text(), "Hi ");
+    if let Some(text) = composition.handle(&Ime::Commit("你".into())) {
+        editor.type_text(&text);
+    }
+    assert_eq!(editor.text(), "Hi 你");
+    assert_eq!(sign::sanitize("你好\u{7}\nline"), "你好\nline");
+}
+
+#[test]
+fn committed_text_goes_into_chat() {
+    let mut composition = allowed();
+    let mut chat = ChatBox::open("/");
+    chat.type_text("say ");
+    composition.handle(&Ime::Preedit("ko".into(), Some((2, 2))));
+    let before = chat.caret_area((800.0, 600.0), 2.0, &TextComposition::new());
+    let composing = chat.caret_area((800.0, 600.0), 2.0, &composition);
+    assert_eq!(composing.0 - before.0, 2.0 * before.2);
+    if let Some(text) = composition.handle(&Ime::Commit("こ".into())) {
+        chat.type_text(&text);
+    }
+    assert_eq!(chat.message().as_deref(), Some("/say こ"));
+
+    // One line, of limited length, and a blank one is not sent
+    let mut chat = ChatBox::open("");
+    chat.paste("two\nlines\u{7}");
+    assert_eq!(chat.text(), "two lines");
+    chat.type_text(&"x".repeat(MAX_CHAT_CHARS));
+    assert_eq!(chat.text().chars().count(), MAX_CHAT_CHARS);
+    assert_eq!(ChatBox::open("  ").message(), None);
+
+    // A long line shows its end, so the caret stays on screen
+    let mut chat = ChatBox::open("");
+    chat.type_text(&"x".repeat(MAX_CHAT_CHARS));
+    let caret = chat.caret_area((400.0, 300.0), 2.0, &TextComposition::new());
+    assert!(caret.0 + caret.2 <= 400.0, "{:?}", caret);
+}
@@ -0,0 +1,44 @@
+//! Water is drawn after everything opaque, farthest first: sections by their distance, and
+//! the faces around the eye one by one.
+
+use glam::Vec3;
+use game::engine::graphics::render_queue::{face_center, sort_faces_back_to_front, RenderQueue};
+use game::engine::graphics::vertex::BlockFaceInstance;
+use game::engine::graphics::RenderMaterial;
+use game::game::world::chunk::{texture_render_material, LAVA_TEXTURE_BASE, WATER_TEXTURE_BASE};
+
+fn face(position: [i32; 3], face: u32) -> BlockFaceInstance {
+    BlockFaceInstance { position, face, block_type: WATER_TEXTURE_BASE, tint: [0; 2], light: 0 }
+}
+
+#[test]
+fn water_is_translucent_and_lava_is_not() {
+    assert_eq!(texture_render_material(WATER_TEXTURE_BASE), RenderMaterial::Translucent);
+    assert_eq!(texture_render_material(LAVA_TEXTURE_BASE - 1), RenderMaterial::Translucent);
+    assert_eq!(texture_render_material(LAVA_TEXTURE_BASE), RenderMaterial::Fluid);
+    assert!(RenderMaterial::Translucent.is_translucent());
+    assert!(RenderMaterial::ALL.iter().filter(|m| m.is_translucent()).eq([&RenderMaterial::Translucent]));
+    // Drawn last of all
+    assert_eq!(RenderMaterial::ALL.last(), Some(&RenderMaterial::Translucent));
+}
+
+#[test]
+fn the_queue_hands_back_the_farthest_first() {
+    let mut queue = RenderQueue::new();
+    for (distance, name) in [(4.0, "middle"), (9.0, "far"), (1.0, "near"), (4.0, "middle again")] {
+        queue.push(distance, name);
+    }
+    assert_eq!(queue.len(), 4);
+    assert_eq!(queue.back_to_front().collect::<Vec<_>>(), vec!["far", "middle", "middle again", "near"]);
+}
+
+#[test]
+fn faces_around_the_eye_sort_by_their_own_centers() {
+    // The top face of the block below the eye is nearer than that block's side faces
+    let top = face([0, -1, 0], 4);
+    assert_eq!(face_center(&top), Vec3::new(0.0, -0.5, 0.0));
+    let mut faces = vec![top, face([0, -1, 0], 3), face([5, -1, 0], 4), face([-2, -1, 0], 4)];
+    sort_faces_back_to_front(&mut faces, Vec3::new(0.0, 0.5, 0.0));
+    let order: Vec<_> = faces.iter().map(|f| (f.position, f.face)).collect();
+    assert_eq!(order, vec![([5, -1, 0], 4), ([-2, -1, 0], 4), ([0, -1, 0], 3), ([0, -1, 0], 4)]);
+}
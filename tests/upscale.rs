@@ -0,0 +1,58 @@
+//! The world can be drawn below the window's resolution and stretched back up by the
+//! present pass, hard, blended or sharpened.
+
+use wgpu::TextureFormat;
+use game::engine::graphics::hdr::PresentParams;
+use game::engine::graphics::upscale::{scaled_size, MIN_RENDER_SCALE};
+use game::engine::graphics::{HdrSettings, UpscaleMode, UpscaleSettings, Viewport};
+
+fn args(words: &str) -> Vec<String> {
+    words.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn settings_take_a_percentage_and_a_mode_in_any_order() {
+    let mut upscale = UpscaleSettings::default();
+    assert!(!upscale.is_scaled());
+    upscale.configure(&args("sharpen 50")).unwrap();
+    assert_eq!(upscale, UpscaleSettings { scale: 0.5, mode: UpscaleMode::Sharpen });
+    upscale.configure(&args("75%")).unwrap();
+    assert_eq!(upscale, UpscaleSettings { scale: 0.75, mode: UpscaleMode::Sharpen });
+    upscale.configure(&args("5")).unwrap();
+    assert_eq!(upscale.scale, MIN_RENDER_SCALE);
+    upscale.configure(&args("400 nearest")).unwrap();
+    assert!(!upscale.is_scaled() && upscale.mode == UpscaleMode::Nearest);
+
+    // A bad word changes nothing, even after a good one
+    let before = upscale;
+    assert!(upscale.configure(&args("50 blurry")).is_err());
+    assert_eq!(upscale, before);
+}
+
+#[test]
+fn smaller_frames_keep_the_window_in_proportion() {
+    assert_eq!(scaled_size((1920, 1080), 1.0), (1920, 1080));
+    assert_eq!(scaled_size((1920, 1080), 0.5), (960, 540));
+    assert_eq!(scaled_size((3, 1), 0.25), (1, 1));
+
+    // Split views scale without gaps or overlaps between them
+    let (window, frame) = ((1281, 721), scaled_size((1281, 721), 0.67));
+    let halves = [Viewport::split(window, 2, 0), Viewport::split(window, 2, 1)].map(|v| v.scaled(window, frame));
+    assert_eq!(halves[0].x, 0);
+    assert_eq!(halves[0].x + halves[0].width, halves[1].x);
+    assert_eq!(halves[1].x + halves[1].width, frame.0);
+    assert!(halves.iter().all(|v| v.height == frame.1));
+}
+
+#[test]
+fn the_present_pass_only_filters_scaled_frames() {
+    let params = PresentParams::new(&HdrSettings::default(), TextureFormat::Bgra8UnormSrgb, TextureFormat::Bgra8UnormSrgb);
+    let native = UpscaleSettings { mode: UpscaleMode::Sharpen, ..UpscaleSettings::default() };
+    assert_eq!(params.upscaled(&native), params);
+
+    let upscale = |mode| params.upscaled(&UpscaleSettings { scale: 0.5, mode }).upscale;
+    assert_eq!(upscale(UpscaleMode::Nearest), 0.0);
+    assert_eq!(upscale(UpscaleMode::Bilinear), 1.0);
+    assert_eq!(upscale(UpscaleMode::Sharpen), 2.0);
+    assert!(params.upscaled(&UpscaleSettings { scale: 0.5, mode: UpscaleMode::Sharpen }).sharpness > 0.0);
+}
@@ -0,0 +1,71 @@
+//! World types generate what they promise, and survive the trip through saves and packets.
+
+use glam::Vec3;
+use game::engine::codec::{ByteReader, ByteWriter};
+use game::game::world::chunk::{BlockType, CHUNK_SIZE, CHUNK_SIZE_F, DEFAULT_SEED};
+use game::game::world::worldgen::{debug_grid_cell, every_block_state, FlatLayer};
+use game::game::world::{Chunk, WorldGen, WorldType};
+
+fn chunk_at(generator: &WorldGen, key: (i32, i32, i32)) -> Chunk {
+    generator.generate(Vec3::new(key.0 as f32, key.1 as f32, key.2 as f32) * CHUNK_SIZE_F)
+}
+
+#[test]
+fn default_type_matches_plain_terrain() {
+    let generator = WorldGen::new(DEFAULT_SEED, WorldType::Default);
+    for key in [(0, 0, 0), (-3, 0, 5)] {
+        let position = Vec3::new(key.0 as f32, key.1 as f32, key.2 as f32) * CHUNK_SIZE_F;
+        assert_eq!(chunk_at(&generator, key).block_hash(), Chunk::with_seed(position, DEFAULT_SEED).block_hash());
+    }
+}
+
+#[test]
+fn superflat_stacks_layers_across_chunks() {
+    let world_type = WorldType::parse("superflat:stone*10,dirt*7,grass").unwrap();
+    assert_eq!(world_type, WorldType::Superflat { layers: vec![
+        FlatLayer { block: BlockType::Stone, thickness: 10 },
+        FlatLayer { block: BlockType::Dirt, thickness: 7 },
+        FlatLayer { block: BlockType::Grass, thickness: 1 },
+    ] });
+    let generator = WorldGen::new(0, world_type);
+    let ground = chunk_at(&generator, (4, 0, -2));
+    let above = chunk_at(&generator, (4, 1, -2));
+    assert_eq!(ground.blocks[3][9][5], BlockType::Stone);
+    assert_eq!(ground.blocks[3][10][5], BlockType::Dirt);
+    assert_eq!(above.blocks[3][0][5], BlockType::Dirt);
+    assert_eq!(above.blocks[3][1][5], BlockType::Grass);
+    assert_eq!(above.blocks[3][2][5], BlockType::Air);
+    assert!(chunk_at(&generator, (4, -1, -2)).blocks.iter().flatten().flatten().all(|&b| b == BlockType::Air));
+}
+
+#[test]
+fn bad_world_types_are_refused() {
+    for text in ["hilly", "superflat:", "superflat:bedrock", "superflat:stone*0", "debug:stone"] {
+        assert!(WorldType::parse(text).is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn debug_world_holds_every_block_state_once() {
+    let generator = WorldGen::new(DEFAULT_SEED, WorldType::Debug);
+    let states = every_block_state();
+    assert!(debug_grid_cell(states.len() - 1).is_some(), "debug grid is too small");
+    let chunk = chunk_at(&generator, (0, 0, 0));
+    for (index, state) in states.iter().enumerate() {
+        let (x, y, z) = debug_grid_cell(index).unwrap();
+        assert_eq!(chunk.blocks[x][y][z], *state);
+    }
+    assert!(chunk.blocks.iter().all(|column| column[0] == [BlockType::Stone; CHUNK_SIZE]));
+    assert!(chunk_at(&generator, (1, 0, 0)).blocks.iter().flatten().flatten().all(|&b| b == BlockType::Air));
+}
+
+#[test]
+fn generators_round_trip() {
+    for world_type in [WorldType::Default, WorldType::default_superflat(), WorldType::Debug] {
+        let generator = WorldGen::new(0xDEAD_BEEF, world_type);
+        let mut w = ByteWriter::new();
+        generator.encode(&mut w);
+        let bytes = w.into_inner();
+        assert_eq!(WorldGen::decode(&mut ByteReader::new(&bytes)).unwrap(), generator);
+    }
+}
@@ -0,0 +1,81 @@
+//! Golden hashes of generated terrain.
+//!
+//! Any change to the noise or terrain code that alters generated blocks fails here. If the
+//! change is intended, run `UPDATE_GOLDEN=1 cargo test --test worldgen_golden -- --nocapture`
+//! and paste the printed table over GOLDEN.
+
+use glam::Vec3;
+use game::game::world::chunk::{Chunk, CHUNK_SIZE_F, DEFAULT_SEED};
+
+const SEEDS: [u32; 3] = [DEFAULT_SEED, 0, 0xDEAD_BEEF];
+
+/// Chunk keys covering the origin, negative coordinates, far-away terrain and empty sky
+const CHUNKS: [(i32, i32, i32); 6] = [
+    (0, 0, 0),
+    (-1, 0, -1),
+    (3, 0, -7),
+    (-20, 0, 13),
+    (1000, 0, -1000),
+    (0, 1, 0),
+];
+
+/// (seed, chunk key, block hash)
+const GOLDEN: &[(u32, (i32, i32, i32), u64)] = &[
+    (0x0000002a, (0, 0, 0), 0x16d8a9feba49d7e7),
+    (0x0000002a, (-1, 0, -1), 0x085c3b5d0ce2ca87),
+    (0x0000002a, (3, 0, -7), 0xff6803aa66624bb5),
+    (0x0000002a, (-20, 0, 13), 0xaa693058141ce215),
+    (0x0000002a, (1000, 0, -1000), 0xe839c898a4c2713d),
+    (0x0000002a, (0, 1, 0), 0xb93a0c83ce3b6325),
+    (0x00000000, (0, 0, 0), 0x09d3dd3459652357),
+    (0x00000000, (-1, 0, -1), 0x2c1ae0307cb38e9d),
+    (0x00000000, (3, 0, -7), 0x87bd3e65fd985c85),
+    (0x00000000, (-20, 0, 13), 0xd0c7546d1135a0d5),
+    (0x00000000, (1000, 0, -1000), 0xff50c6a7f66edccf),
+    (0x00000000, (0, 1, 0), 0xb93a0c83ce3b6325),
+    (0xdeadbeef, (0, 0, 0), 0xecbc20fdf0b42f7d),
+    (0xdeadbeef, (-1, 0, -1), 0xf394c4357ae1161f),
+    (0xdeadbeef, (3, 0, -7), 0x149675c29f2529fd),
+    (0xdeadbeef, (-20, 0, 13), 0xa1be035f11c29c15),
+    (0xdeadbeef, (1000, 0, -1000), 0x8e6c05a03b92292f),
+    (0xdeadbeef, (0, 1, 0), 0xb93a0c83ce3b6325),
+];
+
+fn generate(seed: u32, key: (i32, i32, i32)) -> u64 {
+    let position = Vec3::new(key.0 as f32, key.1 as f32, key.2 as f32) * CHUNK_SIZE_F;
+    Chunk::with_seed(position, seed).block_hash()
+}
+
+#[test]
+fn generated_chunks_match_golden_hashes() {
+    let actual: Vec<(u32, (i32, i32, i32), u64)> = SEEDS.iter()
+        .flat_map(|&seed| CHUNKS.iter().map(move |&key| (seed, key, generate(seed, key))))
+        .collect();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        for (seed, key, hash) in &actual {
+            println!("    ({:#010x}, {:?}, {:#018x}),", seed, key, hash);
+        }
+        return;
+    }
+    assert_eq!(actual.len(), GOLDEN.len(), "golden table is out of date with SEEDS and CHUNKS");
+    let mismatches: Vec<String> = actual.iter().zip(GOLDEN)
+        .filter(|(a, g)| a != g)
+        .map(|((seed, key, hash), (_, _, expected))| format!("seed {:#x} chunk {:?}: got {:#018x}, expected {:#018x}", seed, key, hash, expected))
+        .collect();
+    assert!(mismatches.is_empty(), "terrain generation changed:\n{}", mismatches.join("\n"));
+}
+
+#[test]
+fn generation_is_deterministic() {
+    for &seed in &SEEDS {
+        for &key in &CHUNKS {
+            assert_eq!(generate(seed, key), generate(seed, key), "seed {} chunk {:?}", seed, key);
+        }
+    }
+}
+
+#[test]
+fn seeds_produce_different_terrain() {
+    let hashes = |seed| CHUNKS.iter().map(|&key| generate(seed, key)).collect::<Vec<_>>();
+    assert_ne!(hashes(SEEDS[0]), hashes(SEEDS[1]));
+}